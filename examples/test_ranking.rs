@@ -2,7 +2,7 @@
 //!
 //! Run with: cargo run --example test_ranking
 
-use yocore::ai::ranking::{get_ranking_stats, rank_project_memories};
+use yocore::ai::ranking::{get_ranking_stats, rank_project_memories, RankingConfig};
 use yocore::db::Database;
 
 fn main() {
@@ -17,6 +17,7 @@ fn main() {
 
     // Get project ID
     let project_id: String = {
+        #[allow(deprecated)]
         let conn = db.conn();
         conn.query_row("SELECT id FROM projects LIMIT 1", [], |row| row.get(0))
             .expect("No projects found")
@@ -32,7 +33,8 @@ fn main() {
     // Run ranking (use larger batch to process all memories)
     let batch_size = 2000;
     println!("=== Running Ranking (batch={}) ===", batch_size);
-    let result = rank_project_memories(&db, &project_id, batch_size).unwrap();
+    let result =
+        rank_project_memories(&db, &project_id, batch_size, &RankingConfig::default()).unwrap();
     println!("Evaluated: {}", result.memories_evaluated);
     println!("Promoted:  {}", result.promoted);
     println!("Demoted:   {}", result.demoted);
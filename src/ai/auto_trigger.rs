@@ -3,12 +3,13 @@
 //! Automatically triggers AI tasks (title, memory, skills) after session parsing.
 //! Replaces the Desktop-side background-sync.ts logic — yocore now owns the full pipeline.
 
-use crate::ai::cli::CliProvider;
+use crate::ai::cli::{provider_name, with_fallback, CliProvider};
 use crate::ai::title::{generate_title, store_title};
 use crate::ai::types::AiEvent;
-use crate::ai::AiTaskQueue;
-use crate::config::Config;
+use crate::ai::{AiTaskKind, AiTaskQueue};
+use crate::config::{AiConfig, AiFeature, Config};
 use crate::db::Database;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::PathBuf;
 use std::sync::Arc;
@@ -20,8 +21,130 @@ const EXTRACTION_THRESHOLD: usize = 10;
 /// Messages between periodic extraction triggers
 const EXTRACTION_INTERVAL: usize = 50;
 
-/// Minimum messages for title generation
-const MIN_MESSAGES_FOR_TITLE: usize = 25;
+/// Per-project overrides of the global AI feature toggles (`None` = use global)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProjectAiSettings {
+    pub title_generation: Option<bool>,
+    pub memory_extraction: Option<bool>,
+    pub skills_discovery: Option<bool>,
+}
+
+impl ProjectAiSettings {
+    /// Whether `feature` should run for this project under `config`
+    pub fn is_feature_active(&self, config: &Config, feature: AiFeature) -> bool {
+        let toggle = match feature {
+            AiFeature::TitleGeneration => self.title_generation,
+            AiFeature::MemoryExtraction => self.memory_extraction,
+            AiFeature::SkillsDiscovery => self.skills_discovery,
+            AiFeature::MarkerDetection | AiFeature::IntentExtraction => None,
+        };
+        match toggle {
+            Some(enabled) => enabled && config.is_feature_available(feature),
+            None => config.is_feature_active(feature),
+        }
+    }
+
+    /// Whether any overridable feature is active for this project
+    pub fn is_any_active(&self, config: &Config) -> bool {
+        [
+            AiFeature::TitleGeneration,
+            AiFeature::MemoryExtraction,
+            AiFeature::SkillsDiscovery,
+        ]
+        .into_iter()
+        .any(|feature| self.is_feature_active(config, feature))
+    }
+}
+
+/// The AI overrides of the project a session belongs to (all unset if none)
+pub fn project_ai_settings_for_session(
+    conn: &rusqlite::Connection,
+    session_id: &str,
+) -> rusqlite::Result<ProjectAiSettings> {
+    use rusqlite::OptionalExtension;
+    conn.query_row(
+        "SELECT pas.title_generation, pas.memory_extraction, pas.skills_discovery
+         FROM sessions s
+         JOIN project_ai_settings pas ON pas.project_id = s.project_id
+         WHERE s.id = ?",
+        [session_id],
+        |row| {
+            Ok(ProjectAiSettings {
+                title_generation: row.get(0)?,
+                memory_extraction: row.get(1)?,
+                skills_discovery: row.get(2)?,
+            })
+        },
+    )
+    .optional()
+    .map(Option::unwrap_or_default)
+}
+
+/// A session with AI work outstanding, found at startup
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PendingAiSession {
+    pub session_id: String,
+    pub message_count: usize,
+    pub needs_title: bool,
+    pub needs_memory: bool,
+    pub needs_skills: bool,
+    /// Overrides for the session's project
+    pub settings: ProjectAiSettings,
+}
+
+/// Sessions of at least `min_messages` messages still needing a title,
+/// memories, or skills (newest first, at most 50).
+/// Hidden sessions are skipped unless `include_hidden` is set.
+pub fn pending_ai_sessions(
+    conn: &rusqlite::Connection,
+    include_hidden: bool,
+    min_messages: usize,
+) -> rusqlite::Result<Vec<PendingAiSession>> {
+    let mut stmt = conn.prepare(
+        "SELECT s.id, s.message_count,
+            (COALESCE(s.title_ai_generated, 0) = 0 AND COALESCE(s.title_edited, 0) = 0) as needs_title,
+            (s.memories_extracted_at IS NULL) as needs_memory,
+            (s.skills_extracted_at IS NULL) as needs_skills,
+            pas.title_generation, pas.memory_extraction, pas.skills_discovery
+        FROM sessions s
+        INNER JOIN projects p ON s.project_id = p.id
+        LEFT JOIN project_ai_settings pas ON pas.project_id = s.project_id
+        WHERE COALESCE(s.import_status, 'success') = 'success'
+          AND s.message_count >= ?2
+          AND (?1 OR s.is_hidden = 0)
+          AND s.deleted_at IS NULL
+          AND (
+            (COALESCE(s.title_ai_generated, 0) = 0 AND COALESCE(s.title_edited, 0) = 0)
+            OR s.memories_extracted_at IS NULL
+            OR s.skills_extracted_at IS NULL
+          )
+        ORDER BY s.created_at DESC
+        LIMIT 50",
+    )?;
+
+    let results = stmt
+        .query_map(
+            rusqlite::params![include_hidden, min_messages as i64],
+            |row| {
+                Ok(PendingAiSession {
+                    session_id: row.get(0)?,
+                    message_count: row.get(1)?,
+                    needs_title: row.get::<_, i32>(2)? != 0,
+                    needs_memory: row.get::<_, i32>(3)? != 0,
+                    needs_skills: row.get::<_, i32>(4)? != 0,
+                    settings: ProjectAiSettings {
+                        title_generation: row.get(5)?,
+                        memory_extraction: row.get(6)?,
+                        skills_discovery: row.get(7)?,
+                    },
+                })
+            },
+        )?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    Ok(results)
+}
 
 /// Handles automatic AI task triggering after session parsing
 pub struct AiAutoTrigger {
@@ -31,8 +154,8 @@ pub struct AiAutoTrigger {
     ai_task_queue: AiTaskQueue,
     /// Track message count at last extraction per session
     extraction_tracker: HashMap<String, usize>,
-    /// Configured AI CLI provider
-    provider: CliProvider,
+    /// Configured AI providers, in fallback order
+    providers: Vec<CliProvider>,
 }
 
 impl AiAutoTrigger {
@@ -41,7 +164,7 @@ impl AiAutoTrigger {
         db: Arc<Database>,
         ai_event_tx: broadcast::Sender<AiEvent>,
         ai_task_queue: AiTaskQueue,
-        provider: CliProvider,
+        providers: Vec<CliProvider>,
     ) -> Self {
         Self {
             config_path,
@@ -49,7 +172,7 @@ impl AiAutoTrigger {
             ai_event_tx,
             ai_task_queue,
             extraction_tracker: HashMap::new(),
-            provider,
+            providers,
         }
     }
 
@@ -64,30 +187,60 @@ impl AiAutoTrigger {
             }
         };
 
-        if !config.is_ai_active() {
+        if config.ai.provider.is_none() {
+            return;
+        }
+
+        let sid = session_id.to_string();
+        let settings = self
+            .db
+            .with_read_conn(move |conn| project_ai_settings_for_session(conn, &sid))
+            .await
+            .unwrap_or_default();
+        if !settings.is_any_active(&config) {
+            return;
+        }
+
+        let (hidden, deleted) = self.visibility(session_id).await;
+        if deleted || (hidden && !config.ai.process_hidden_sessions) {
             return;
         }
 
         // Title generation: check if session needs one
-        if config.is_feature_active(crate::config::AiFeature::TitleGeneration)
-            && message_count >= MIN_MESSAGES_FOR_TITLE
+        if settings.is_feature_active(&config, AiFeature::TitleGeneration)
+            && message_count >= config.ai.min_messages_for_processing
         {
-            self.maybe_trigger_title(session_id).await;
+            self.maybe_trigger_title(session_id, &config.ai).await;
         }
 
         // Memory & Skills extraction: threshold-based
         if self.should_trigger_extraction(session_id, message_count) {
             self.record_extraction(session_id, message_count);
 
-            if config.is_feature_active(crate::config::AiFeature::MemoryExtraction) {
-                self.trigger_memory_extraction(session_id).await;
+            if settings.is_feature_active(&config, AiFeature::MemoryExtraction) {
+                self.trigger_memory_extraction(session_id, &config.ai).await;
             }
-            if config.is_feature_active(crate::config::AiFeature::SkillsDiscovery) {
-                self.trigger_skill_extraction(session_id).await;
+            if settings.is_feature_active(&config, AiFeature::SkillsDiscovery) {
+                self.trigger_skill_extraction(session_id, &config.ai).await;
             }
         }
     }
 
+    /// Whether the user has hidden this session, and whether it is soft-deleted
+    async fn visibility(&self, session_id: &str) -> (bool, bool) {
+        let sid = session_id.to_string();
+        self.db
+            .with_conn(move |conn| {
+                conn.query_row(
+                    "SELECT is_hidden, deleted_at IS NOT NULL FROM sessions WHERE id = ?",
+                    [&sid],
+                    |row| Ok((row.get::<_, bool>(0)?, row.get::<_, bool>(1)?)),
+                )
+            })
+            .await
+            .unwrap_or((false, false))
+    }
+
     /// Check if we should trigger extraction based on message count thresholds
     fn should_trigger_extraction(&self, session_id: &str, message_count: usize) -> bool {
         let last_count = self
@@ -117,7 +270,7 @@ impl AiAutoTrigger {
     }
 
     /// Trigger title generation if session doesn't have an AI-generated or user-edited title
-    async fn maybe_trigger_title(&self, session_id: &str) {
+    async fn maybe_trigger_title(&self, session_id: &str, ai: &AiConfig) {
         let db = self.db.clone();
         let sid = session_id.to_string();
 
@@ -153,7 +306,9 @@ impl AiAutoTrigger {
         let db = self.db.clone();
         let ai_event_tx = self.ai_event_tx.clone();
         let session_id = session_id.to_string();
-        let provider = self.provider;
+        let providers = self.providers.clone();
+        let ai = ai.clone();
+        let task = self.ai_task_queue.track(&session_id, AiTaskKind::Title);
 
         tokio::spawn(async move {
             let _permit = permit;
@@ -163,7 +318,15 @@ impl AiAutoTrigger {
                 session_id: sid.clone(),
             });
 
-            let result = generate_title(&db, &sid, None, provider).await;
+            let Some(result) = task
+                .run(with_fallback("Title generation", &providers, |provider| {
+                    generate_title(&db, &sid, None, provider, &ai)
+                }))
+                .await
+            else {
+                let _ = ai_event_tx.send(AiEvent::TitleCancelled { session_id: sid });
+                return;
+            };
 
             if let Some(ref title) = result.title {
                 if let Err(e) = store_title(&db, &sid, title).await {
@@ -182,6 +345,7 @@ impl AiAutoTrigger {
                 let _ = ai_event_tx.send(AiEvent::TitleComplete {
                     session_id: sid,
                     title: title.clone(),
+                    provider: provider_name(result.provider),
                 });
             } else if let Some(error) = result.error {
                 tracing::warn!(
@@ -197,7 +361,7 @@ impl AiAutoTrigger {
         });
     }
 
-    async fn trigger_memory_extraction(&self, session_id: &str) {
+    async fn trigger_memory_extraction(&self, session_id: &str, ai: &AiConfig) {
         let permit = match self.ai_task_queue.acquire().await {
             Ok(p) => p,
             Err(_) => return,
@@ -206,7 +370,9 @@ impl AiAutoTrigger {
         let db = self.db.clone();
         let ai_event_tx = self.ai_event_tx.clone();
         let session_id = session_id.to_string();
-        let provider = self.provider;
+        let providers = self.providers.clone();
+        let ai = ai.clone();
+        let task = self.ai_task_queue.track(&session_id, AiTaskKind::Memory);
 
         tokio::spawn(async move {
             let _permit = permit;
@@ -216,7 +382,23 @@ impl AiAutoTrigger {
                 session_id: sid.clone(),
             });
 
-            let result = crate::ai::extract_memories(&db, &sid, None, false, provider).await;
+            let Some(result) = task
+                .run(with_fallback("Memory extraction", &providers, |provider| {
+                    crate::ai::extract_memories(
+                        &db,
+                        &sid,
+                        None,
+                        false,
+                        provider,
+                        &ai,
+                        ai.lenient_json,
+                    )
+                }))
+                .await
+            else {
+                let _ = ai_event_tx.send(AiEvent::MemoryCancelled { session_id: sid });
+                return;
+            };
 
             if let Some(error) = result.error {
                 tracing::warn!(
@@ -237,12 +419,13 @@ impl AiAutoTrigger {
                 let _ = ai_event_tx.send(AiEvent::MemoryComplete {
                     session_id: sid,
                     count: result.memories_extracted,
+                    provider: provider_name(result.provider),
                 });
             }
         });
     }
 
-    async fn trigger_skill_extraction(&self, session_id: &str) {
+    async fn trigger_skill_extraction(&self, session_id: &str, ai: &AiConfig) {
         let permit = match self.ai_task_queue.acquire().await {
             Ok(p) => p,
             Err(_) => return,
@@ -251,7 +434,9 @@ impl AiAutoTrigger {
         let db = self.db.clone();
         let ai_event_tx = self.ai_event_tx.clone();
         let session_id = session_id.to_string();
-        let provider = self.provider;
+        let providers = self.providers.clone();
+        let ai = ai.clone();
+        let task = self.ai_task_queue.track(&session_id, AiTaskKind::Skill);
 
         tokio::spawn(async move {
             let _permit = permit;
@@ -261,7 +446,23 @@ impl AiAutoTrigger {
                 session_id: sid.clone(),
             });
 
-            let result = crate::ai::extract_skills(&db, &sid, None, false, provider).await;
+            let Some(result) = task
+                .run(with_fallback("Skill extraction", &providers, |provider| {
+                    crate::ai::extract_skills(
+                        &db,
+                        &sid,
+                        None,
+                        false,
+                        provider,
+                        &ai,
+                        ai.lenient_json,
+                    )
+                }))
+                .await
+            else {
+                let _ = ai_event_tx.send(AiEvent::SkillCancelled { session_id: sid });
+                return;
+            };
 
             if let Some(error) = result.error {
                 tracing::warn!(
@@ -282,8 +483,187 @@ impl AiAutoTrigger {
                 let _ = ai_event_tx.send(AiEvent::SkillComplete {
                     session_id: sid,
                     count: result.skills_extracted,
+                    provider: provider_name(result.provider),
                 });
             }
         });
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support;
+
+    fn seed(conn: &rusqlite::Connection) {
+        test_support::seed_project(conn);
+        conn.execute_batch(
+            "INSERT INTO sessions (id, project_id, file_path, ai_tool, message_count, is_hidden, created_at, indexed_at) VALUES
+                ('visible', 'p1', '/tmp/proj/a.jsonl', 'Claude Code', 40, 0, '2024-01-01T00:00:00Z', '2024-01-01T00:00:00Z'),
+                ('hidden', 'p1', '/tmp/proj/b.jsonl', 'Claude Code', 40, 1, '2024-01-02T00:00:00Z', '2024-01-02T00:00:00Z');",
+        )
+        .unwrap();
+    }
+
+    fn ids(sessions: Vec<PendingAiSession>) -> Vec<String> {
+        sessions.into_iter().map(|s| s.session_id).collect()
+    }
+
+    #[tokio::test]
+    async fn test_hidden_sessions_excluded_from_recovery_by_default() {
+        let tmp = tempfile::tempdir().unwrap();
+        let db = Database::new(tmp.path().join("test.db")).unwrap();
+        db.with_conn(seed).await;
+
+        let default_pending = db
+            .with_conn(|conn| {
+                let ai = Config::default().ai;
+                pending_ai_sessions(
+                    conn,
+                    ai.process_hidden_sessions,
+                    ai.min_messages_for_processing,
+                )
+            })
+            .await
+            .unwrap();
+        assert_eq!(ids(default_pending), vec!["visible"]);
+
+        let all_pending = db
+            .with_conn(|conn| pending_ai_sessions(conn, true, 25))
+            .await
+            .unwrap();
+        assert_eq!(ids(all_pending), vec!["hidden", "visible"]);
+    }
+
+    #[tokio::test]
+    async fn test_min_messages_for_processing() {
+        let tmp = tempfile::tempdir().unwrap();
+        let db = Arc::new(Database::new(tmp.path().join("test.db")).unwrap());
+        db.with_conn(|conn| {
+            seed(conn);
+            conn.execute(
+                "INSERT INTO sessions (id, project_id, file_path, ai_tool, message_count, created_at, indexed_at)
+                 VALUES ('short', 'p1', '/tmp/proj/c.jsonl', 'Claude Code', 6, '2024-01-03T00:00:00Z', '2024-01-03T00:00:00Z')",
+                [],
+            )
+            .unwrap();
+        })
+        .await;
+
+        // Too short for the default threshold
+        let pending = db
+            .with_conn(|conn| pending_ai_sessions(conn, false, 25))
+            .await
+            .unwrap();
+        assert_eq!(ids(pending), vec!["visible"]);
+
+        let config_path = tmp.path().join("config.toml");
+        let mut config = Config::default();
+        config.ai.provider = Some("claude_code".into());
+        config.ai.min_messages_for_processing = 5;
+        config.ai.memory_extraction = false;
+        config.ai.skills_discovery = false;
+        config.save_to_file(&config_path).unwrap();
+
+        let min_messages = config.ai.min_messages_for_processing;
+        let pending = db
+            .with_conn(move |conn| pending_ai_sessions(conn, false, min_messages))
+            .await
+            .unwrap();
+        assert_eq!(ids(pending), vec!["short", "visible"]);
+
+        let (ai_event_tx, _) = broadcast::channel(16);
+        let queue = AiTaskQueue::new(2);
+        let mut trigger = AiAutoTrigger::new(
+            config_path,
+            db,
+            ai_event_tx,
+            queue.clone(),
+            vec![CliProvider::ClaudeCode],
+        );
+        trigger.on_session_parsed("short", 6).await;
+        assert_eq!(queue.cancel_session("short"), vec![AiTaskKind::Title]);
+    }
+
+    #[tokio::test]
+    async fn test_deleted_session_is_not_processed() {
+        let tmp = tempfile::tempdir().unwrap();
+        let db = Arc::new(Database::new(tmp.path().join("test.db")).unwrap());
+        db.with_conn(|conn| {
+            seed(conn);
+            conn.execute(
+                "UPDATE sessions SET deleted_at = '2024-02-01T00:00:00Z' WHERE id = 'visible'",
+                [],
+            )
+            .unwrap();
+        })
+        .await;
+
+        let pending = db
+            .with_conn(|conn| pending_ai_sessions(conn, true, 25))
+            .await
+            .unwrap();
+        assert_eq!(ids(pending), vec!["hidden"]);
+
+        let config_path = tmp.path().join("config.toml");
+        let mut config = Config::default();
+        config.ai.provider = Some("claude_code".into());
+        config.save_to_file(&config_path).unwrap();
+
+        let (ai_event_tx, _) = broadcast::channel(16);
+        let queue = AiTaskQueue::new(2);
+        let mut trigger = AiAutoTrigger::new(
+            config_path,
+            db,
+            ai_event_tx,
+            queue.clone(),
+            vec![CliProvider::ClaudeCode],
+        );
+        trigger.on_session_parsed("visible", 40).await;
+        assert!(queue.cancel_session("visible").is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_project_override_disables_memory_extraction() {
+        let tmp = tempfile::tempdir().unwrap();
+        let db = Arc::new(Database::new(tmp.path().join("test.db")).unwrap());
+        db.with_conn(|conn| {
+            seed(conn);
+            conn.execute_batch(
+                "INSERT INTO projects (id, name, folder_path, created_at, updated_at)
+                 VALUES ('scratch', 'scratch', '/tmp/scratch', '2024-01-01T00:00:00Z', '2024-01-01T00:00:00Z');
+                 INSERT INTO sessions (id, project_id, file_path, ai_tool, message_count, created_at, indexed_at)
+                 VALUES ('scratch-session', 'scratch', '/tmp/scratch/a.jsonl', 'Claude Code', 12, '2024-01-01T00:00:00Z', '2024-01-01T00:00:00Z');
+                 INSERT INTO project_ai_settings (project_id, memory_extraction, updated_at)
+                 VALUES ('scratch', 0, '2024-01-01T00:00:00Z');",
+            )
+            .unwrap();
+        })
+        .await;
+
+        let config_path = tmp.path().join("config.toml");
+        let mut config = Config::default();
+        config.ai.provider = Some("claude_code".into());
+        config.ai.title_generation = false;
+        config.ai.skills_discovery = false;
+        config.save_to_file(&config_path).unwrap();
+
+        let (ai_event_tx, _) = broadcast::channel(16);
+        let queue = AiTaskQueue::new(2);
+        let mut trigger = AiAutoTrigger::new(
+            config_path,
+            db,
+            ai_event_tx,
+            queue.clone(),
+            vec![CliProvider::ClaudeCode],
+        );
+
+        // The overridden project is skipped...
+        trigger.on_session_parsed("scratch-session", 12).await;
+        assert!(queue.cancel_session("scratch-session").is_empty());
+
+        // ...while other projects still follow the global setting
+        trigger.on_session_parsed("visible", 12).await;
+        assert_eq!(queue.cancel_session("visible"), vec![AiTaskKind::Memory]);
+    }
+}
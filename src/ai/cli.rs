@@ -3,6 +3,9 @@
 //! Detects installed AI CLI tools and invokes them for AI operations.
 //! Provider-specific logic is encapsulated in `CliProvider` methods.
 //! Adding a new provider requires only adding an enum variant and match arms here.
+//! `Ollama` and `OpenAi` are the exceptions: they talk HTTP (see `ollama` and
+//! `openai`), and `run_cli`/`call_cli_with_prompt` route to them instead of
+//! spawning a process.
 
 use std::path::PathBuf;
 use std::process::Stdio;
@@ -10,6 +13,11 @@ use std::time::Duration;
 use tokio::process::Command;
 use tokio::time::timeout;
 
+use super::ollama::OllamaProvider;
+use super::openai::OpenAiProvider;
+use crate::config::AiConfig;
+use crate::error::CoreError;
+
 /// Supported AI CLI providers
 #[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "snake_case")]
@@ -17,6 +25,9 @@ pub enum CliProvider {
     ClaudeCode,
     #[serde(rename = "openclaw")]
     OpenClaw,
+    Ollama,
+    #[serde(rename = "openai")]
+    OpenAi,
 }
 
 impl CliProvider {
@@ -25,15 +36,55 @@ impl CliProvider {
         match s {
             "claude_code" => Some(CliProvider::ClaudeCode),
             "openclaw" => Some(CliProvider::OpenClaw),
+            "ollama" => Some(CliProvider::Ollama),
+            "openai" => Some(CliProvider::OpenAi),
             _ => None,
         }
     }
 
+    /// Resolve the provider from `[ai]` config (the first of a fallback chain),
+    /// defaulting to Claude Code.
+    pub fn from_ai_config(ai: &AiConfig) -> Self {
+        Self::chain_from_ai_config(ai)[0]
+    }
+
+    /// Resolve the fallback chain from `[ai]` config, in the order providers are
+    /// tried. Never empty: defaults to Claude Code.
+    pub fn chain_from_ai_config(ai: &AiConfig) -> Vec<Self> {
+        let chain: Vec<Self> = ai
+            .provider
+            .iter()
+            .flat_map(|chain| chain.names())
+            .filter_map(|name| Self::from_config_str(name))
+            .collect();
+        if chain.is_empty() {
+            vec![CliProvider::ClaudeCode]
+        } else {
+            chain
+        }
+    }
+
     /// Display name for the provider
     pub fn display_name(&self) -> &'static str {
         match self {
             CliProvider::ClaudeCode => "Claude Code",
             CliProvider::OpenClaw => "OpenClaw",
+            CliProvider::Ollama => "Ollama",
+            CliProvider::OpenAi => "OpenAI",
+        }
+    }
+
+    /// Error reported when the provider can't be used
+    pub fn unavailable_message(&self) -> String {
+        match self {
+            CliProvider::ClaudeCode | CliProvider::OpenClaw => {
+                format!("{} CLI not installed", self.display_name())
+            }
+            CliProvider::Ollama => "Ollama server not reachable".to_string(),
+            CliProvider::OpenAi => {
+                "OpenAI API key not configured (set ai.openai.api_key or OPENAI_API_KEY)"
+                    .to_string()
+            }
         }
     }
 
@@ -42,6 +93,9 @@ impl CliProvider {
         match self {
             CliProvider::ClaudeCode => "claude",
             CliProvider::OpenClaw => "openclaw",
+            CliProvider::Ollama => "ollama",
+            // HTTP provider — no binary
+            CliProvider::OpenAi => "openai",
         }
     }
 
@@ -50,6 +104,9 @@ impl CliProvider {
         match self {
             CliProvider::ClaudeCode => Duration::from_secs(60),
             CliProvider::OpenClaw => Duration::from_secs(90),
+            // Local models are slower, especially on a cold load
+            CliProvider::Ollama => Duration::from_secs(120),
+            CliProvider::OpenAi => Duration::from_secs(60),
         }
     }
 
@@ -58,6 +115,9 @@ impl CliProvider {
         match self {
             CliProvider::ClaudeCode => Duration::from_secs(120),
             CliProvider::OpenClaw => Duration::from_secs(180),
+            CliProvider::Ollama => Duration::from_secs(300),
+            // Leaves room for 429 backoff within the budget
+            CliProvider::OpenAi => Duration::from_secs(180),
         }
     }
 
@@ -84,6 +144,8 @@ impl CliProvider {
                 "--thinking".to_string(),
                 "high".to_string(),
             ],
+            // Never spawned; AI calls go over HTTP
+            CliProvider::Ollama | CliProvider::OpenAi => vec![],
         }
     }
 
@@ -106,7 +168,9 @@ impl CliProvider {
                 "--print".to_string(),
             ],
             // OpenClaw has no JSON output mode; prompt asks for JSON directly
-            CliProvider::OpenClaw => self.build_args(prompt),
+            CliProvider::OpenClaw | CliProvider::Ollama | CliProvider::OpenAi => {
+                self.build_args(prompt)
+            }
         }
     }
 
@@ -115,7 +179,7 @@ impl CliProvider {
     pub fn has_json_wrapper(&self) -> bool {
         match self {
             CliProvider::ClaudeCode => true,
-            CliProvider::OpenClaw => false,
+            CliProvider::OpenClaw | CliProvider::Ollama | CliProvider::OpenAi => false,
         }
     }
 
@@ -124,6 +188,8 @@ impl CliProvider {
         match self {
             CliProvider::ClaudeCode => get_claude_common_paths(),
             CliProvider::OpenClaw => get_openclaw_common_paths(),
+            // HTTP providers — nothing to look for on disk
+            CliProvider::Ollama | CliProvider::OpenAi => vec![],
         }
     }
 }
@@ -135,10 +201,42 @@ pub struct DetectedCli {
     pub installed: bool,
     pub path: Option<PathBuf>,
     pub version: Option<String>,
+    /// Connection settings captured at detection (HTTP providers only)
+    #[serde(skip)]
+    pub http: Option<HttpProvider>,
+}
+
+/// An AI provider reached over HTTP rather than a spawned CLI
+#[derive(Debug, Clone)]
+pub enum HttpProvider {
+    Ollama(OllamaProvider),
+    OpenAi(OpenAiProvider),
 }
 
-/// Detect if a CLI provider is installed
-pub async fn detect_provider(provider: CliProvider) -> DetectedCli {
+impl HttpProvider {
+    /// Send a single-message chat request and return the reply text
+    pub async fn chat(
+        &self,
+        prompt: &str,
+        timeout_duration: Duration,
+        json: bool,
+    ) -> crate::error::Result<String> {
+        match self {
+            HttpProvider::Ollama(p) => p.chat(prompt, timeout_duration, json).await,
+            HttpProvider::OpenAi(p) => p.chat(prompt, timeout_duration, json).await,
+        }
+    }
+}
+
+/// Detect if a CLI provider is installed. HTTP providers are reached with
+/// their settings from `ai`.
+pub async fn detect_provider(provider: CliProvider, ai: &AiConfig) -> DetectedCli {
+    match provider {
+        CliProvider::Ollama => return OllamaProvider::from_config(&ai.ollama).detect().await,
+        CliProvider::OpenAi => return OpenAiProvider::from_config(&ai.openai).detect(),
+        CliProvider::ClaudeCode | CliProvider::OpenClaw => {}
+    }
+
     let common_paths = provider.common_paths();
     let command_name = provider.command_name();
 
@@ -152,6 +250,7 @@ pub async fn detect_provider(provider: CliProvider) -> DetectedCli {
                     installed: true,
                     path: Some(path.clone()),
                     version: Some(version),
+                    http: None,
                 };
             }
         }
@@ -165,6 +264,7 @@ pub async fn detect_provider(provider: CliProvider) -> DetectedCli {
                 installed: true,
                 path: Some(path),
                 version: Some(version),
+                http: None,
             };
         }
     }
@@ -174,12 +274,13 @@ pub async fn detect_provider(provider: CliProvider) -> DetectedCli {
         installed: false,
         path: None,
         version: None,
+        http: None,
     }
 }
 
-/// Legacy wrapper — prefer detect_provider(CliProvider::ClaudeCode)
+/// Legacy wrapper — prefer detect_provider(CliProvider::ClaudeCode, ai)
 pub async fn detect_claude_code() -> DetectedCli {
-    detect_provider(CliProvider::ClaudeCode).await
+    detect_provider(CliProvider::ClaudeCode, &AiConfig::default()).await
 }
 
 /// Common installation paths for Claude Code CLI
@@ -277,13 +378,13 @@ async fn check_cli_version(path: &PathBuf) -> Option<String> {
 
 /// Synchronously detect available CLI for a given provider.
 /// Returns None if the CLI is not installed.
-pub fn detect_cli_sync(provider: CliProvider) -> Option<DetectedCli> {
+pub fn detect_cli_sync(provider: CliProvider, ai: &AiConfig) -> Option<DetectedCli> {
     let rt = tokio::runtime::Handle::try_current()
         .map(|h| {
             std::thread::scope(|s| {
                 s.spawn(|| {
                     h.block_on(async {
-                        let detected = detect_provider(provider).await;
+                        let detected = detect_provider(provider, ai).await;
                         if detected.installed {
                             Some(detected)
                         } else {
@@ -298,7 +399,7 @@ pub fn detect_cli_sync(provider: CliProvider) -> Option<DetectedCli> {
         .unwrap_or_else(|_| {
             let rt = tokio::runtime::Runtime::new().ok()?;
             rt.block_on(async {
-                let detected = detect_provider(provider).await;
+                let detected = detect_provider(provider, ai).await;
                 if detected.installed {
                     Some(detected)
                 } else {
@@ -310,9 +411,52 @@ pub fn detect_cli_sync(provider: CliProvider) -> Option<DetectedCli> {
     rt
 }
 
-/// Legacy wrapper — prefer detect_cli_sync(provider)
+/// Legacy wrapper — prefer detect_cli_sync(provider, ai)
 pub fn detect_cli() -> Option<DetectedCli> {
-    detect_cli_sync(CliProvider::ClaudeCode)
+    detect_cli_sync(CliProvider::ClaudeCode, &AiConfig::default())
+}
+
+/// A result from one provider that can fall back to the next in the chain
+pub trait FallbackResult {
+    /// Why the provider failed, if it did
+    fn error(&self) -> Option<&str>;
+}
+
+/// Display name of the provider behind a result, empty if none ran
+pub fn provider_name(provider: Option<CliProvider>) -> String {
+    provider
+        .map(|p| p.display_name().to_string())
+        .unwrap_or_default()
+}
+
+/// Run `attempt` with each provider in `chain` until one succeeds.
+/// Each failure but the last is logged; the last is returned for the caller to report.
+pub async fn with_fallback<T, F, Fut>(task: &str, chain: &[CliProvider], mut attempt: F) -> T
+where
+    T: FallbackResult,
+    F: FnMut(CliProvider) -> Fut,
+    Fut: std::future::Future<Output = T>,
+{
+    let chain = if chain.is_empty() {
+        &[CliProvider::ClaudeCode][..]
+    } else {
+        chain
+    };
+    let mut result = attempt(chain[0]).await;
+    for (failed, next) in chain.iter().zip(&chain[1..]) {
+        let Some(error) = result.error() else {
+            break;
+        };
+        tracing::warn!(
+            "{} with {} failed, falling back to {}: {}",
+            task,
+            failed.display_name(),
+            next.display_name(),
+            error
+        );
+        result = attempt(*next).await;
+    }
+    result
 }
 
 /// Run CLI with a prompt and return the output
@@ -320,8 +464,15 @@ pub async fn run_cli(
     cli: &DetectedCli,
     prompt: &str,
     timeout_duration: Duration,
-) -> Result<String, String> {
-    let path = cli.path.as_ref().ok_or("CLI path not available")?;
+) -> crate::error::Result<String> {
+    if let Some(http) = &cli.http {
+        return http.chat(prompt, timeout_duration, false).await;
+    }
+
+    let path = cli
+        .path
+        .as_ref()
+        .ok_or_else(|| CoreError::ProviderUnavailable("CLI path not available".to_string()))?;
 
     let args = cli.provider.build_args(prompt);
 
@@ -342,6 +493,8 @@ pub async fn run_cli(
             .stdin(Stdio::null())
             .stdout(Stdio::piped())
             .stderr(Stdio::piped())
+            // Kill the process if this future is dropped (timeout or cancellation)
+            .kill_on_drop(true)
             .output()
             .await
     })
@@ -354,13 +507,16 @@ pub async fn run_cli(
                 Ok(stdout.trim().to_string())
             } else {
                 let stderr = String::from_utf8_lossy(&output.stderr);
-                Err(format!("CLI failed: {}", stderr.trim()))
+                Err(CoreError::Api(format!("CLI failed: {}", stderr.trim())))
             }
         }
-        Ok(Err(e)) => Err(format!("Failed to execute CLI: {}", e)),
-        Err(_) => Err(format!(
-            "CLI timed out after {} seconds",
-            timeout_duration.as_secs()
+        Ok(Err(e)) => Err(CoreError::ProviderUnavailable(format!(
+            "failed to execute CLI: {}",
+            e
+        ))),
+        Err(_) => Err(CoreError::Timeout(
+            "CLI".to_string(),
+            timeout_duration.as_secs(),
         )),
     }
 }
@@ -371,9 +527,16 @@ pub async fn call_cli_with_prompt(
     prompt: &str,
     cli: &DetectedCli,
     timeout_secs: u64,
-) -> Result<String, String> {
-    let path = cli.path.as_ref().ok_or("CLI path not available")?;
+) -> crate::error::Result<String> {
     let timeout_duration = Duration::from_secs(timeout_secs);
+    if let Some(http) = &cli.http {
+        return http.chat(prompt, timeout_duration, true).await;
+    }
+
+    let path = cli
+        .path
+        .as_ref()
+        .ok_or_else(|| CoreError::ProviderUnavailable("CLI path not available".to_string()))?;
 
     let args = cli.provider.build_json_args(prompt);
 
@@ -387,6 +550,8 @@ pub async fn call_cli_with_prompt(
             .stdin(Stdio::null())
             .stdout(Stdio::piped())
             .stderr(Stdio::piped())
+            // Kill the process if this future is dropped (timeout or cancellation)
+            .kill_on_drop(true)
             .output()
             .await
     })
@@ -398,11 +563,11 @@ pub async fn call_cli_with_prompt(
             let response = stdout.trim();
 
             if !output.status.success() {
-                return Err(format!("CLI failed: {}", response));
+                return Err(CoreError::Api(format!("CLI failed: {}", response)));
             }
 
             if response.is_empty() {
-                return Err("CLI returned empty response".to_string());
+                return Err(CoreError::Api("CLI returned empty response".to_string()));
             }
 
             // Unwrap provider-specific JSON wrapper if present
@@ -418,42 +583,175 @@ pub async fn call_cli_with_prompt(
 
             Ok(response.to_string())
         }
-        Ok(Err(e)) => Err(format!("Failed to execute CLI: {}", e)),
-        Err(_) => Err(format!("CLI timed out after {} seconds", timeout_secs)),
+        Ok(Err(e)) => Err(CoreError::ProviderUnavailable(format!(
+            "failed to execute CLI: {}",
+            e
+        ))),
+        Err(_) => Err(CoreError::Timeout("CLI".to_string(), timeout_secs)),
     }
 }
 
-/// Parse JSON response from CLI (handles markdown code blocks)
-pub fn parse_json_response<T: serde::de::DeserializeOwned>(response: &str) -> Result<T, String> {
-    // Try direct parse first
-    if let Ok(result) = serde_json::from_str(response) {
-        return Ok(result);
+/// JSON value located in free-form AI output
+#[derive(Debug)]
+pub struct ExtractedJson {
+    pub value: serde_json::Value,
+    /// True when the text only parsed after lenient repair (trailing commas, raw control chars)
+    pub repaired: bool,
+}
+
+/// Maximum number of candidate JSON start positions tried in prose-wrapped output
+const MAX_JSON_CANDIDATES: usize = 16;
+
+/// Locate and parse the JSON object/array in an AI response.
+///
+/// Tries, in order: the whole response, the contents of the first markdown code
+/// fence, and balanced `{...}`/`[...]` segments found in surrounding prose.
+/// When `lenient` is set and no candidate parses strictly, each candidate is
+/// retried after `repair_json`.
+pub fn extract_json(response: &str, lenient: bool) -> Result<ExtractedJson, String> {
+    let trimmed = response.trim();
+    let mut candidates: Vec<&str> = vec![trimmed];
+
+    let body = strip_code_fence(trimmed).unwrap_or(trimmed);
+    if body != trimmed {
+        candidates.push(body);
     }
 
-    // Extract from markdown code block
-    let json_str = if response.contains("```") {
-        let lines: Vec<&str> = response.lines().collect();
-        let mut in_block = false;
-        let mut json_lines = Vec::new();
+    // Prefer the longest balanced segment so incidental brackets in prose
+    // (e.g. "[2] items") don't win over the actual payload
+    let mut segments: Vec<&str> = body
+        .char_indices()
+        .filter(|(_, c)| *c == '{' || *c == '[')
+        .take(MAX_JSON_CANDIDATES)
+        .filter_map(|(start, _)| balanced_segment(&body[start..]))
+        .collect();
+    segments.sort_by_key(|seg| std::cmp::Reverse(seg.len()));
+    candidates.extend(segments);
+
+    let mut last_error = None;
+    for candidate in &candidates {
+        match serde_json::from_str(candidate) {
+            Ok(value) => {
+                return Ok(ExtractedJson {
+                    value,
+                    repaired: false,
+                })
+            }
+            Err(e) => last_error = Some(e),
+        }
+    }
 
-        for line in lines {
-            if line.starts_with("```json") || (line.starts_with("```") && !in_block) {
-                in_block = true;
-                continue;
+    if lenient {
+        for candidate in &candidates {
+            if let Ok(value) = serde_json::from_str(&repair_json(candidate)) {
+                return Ok(ExtractedJson {
+                    value,
+                    repaired: true,
+                });
             }
-            if line.starts_with("```") && in_block {
-                break;
+        }
+    }
+
+    Err(match last_error {
+        Some(e) => format!("Failed to parse JSON: {}", e),
+        None => "No JSON found in response".to_string(),
+    })
+}
+
+/// Return the contents of the first markdown code fence (```` ``` ```` or ```` ```json ````)
+fn strip_code_fence(text: &str) -> Option<&str> {
+    let open = text.find("```")?;
+    let after_open = &text[open + 3..];
+    // Skip the language tag on the opening fence line
+    let body_start = after_open.find('\n').map(|i| i + 1).unwrap_or(0);
+    let body = &after_open[body_start..];
+    let close = body.find("```").unwrap_or(body.len());
+    Some(body[..close].trim())
+}
+
+/// Return the prefix of `text` (which starts with `{` or `[`) up to its matching
+/// closing bracket, ignoring brackets inside string literals.
+fn balanced_segment(text: &str) -> Option<&str> {
+    let mut depth = 0usize;
+    let mut in_string = false;
+    let mut escaped = false;
+
+    for (i, c) in text.char_indices() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
             }
-            if in_block {
-                json_lines.push(line);
+            continue;
+        }
+        match c {
+            '"' => in_string = true,
+            '{' | '[' => depth += 1,
+            '}' | ']' => {
+                depth = depth.checked_sub(1)?;
+                if depth == 0 {
+                    return Some(&text[..=i]);
+                }
             }
+            _ => {}
         }
-        json_lines.join("\n")
-    } else {
-        response.to_string()
-    };
+    }
+    None
+}
 
-    serde_json::from_str(&json_str).map_err(|e| format!("Failed to parse JSON: {}", e))
+/// Repair common LLM JSON mistakes: trailing commas before `}`/`]` and raw
+/// newlines/tabs inside string literals.
+fn repair_json(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut in_string = false;
+    let mut escaped = false;
+    let chars: Vec<char> = text.chars().collect();
+
+    for (i, &c) in chars.iter().enumerate() {
+        if in_string {
+            match c {
+                _ if escaped => {
+                    escaped = false;
+                    out.push(c);
+                }
+                '\\' => {
+                    escaped = true;
+                    out.push(c);
+                }
+                '"' => {
+                    in_string = false;
+                    out.push(c);
+                }
+                '\n' => out.push_str("\\n"),
+                '\r' => out.push_str("\\r"),
+                '\t' => out.push_str("\\t"),
+                _ => out.push(c),
+            }
+            continue;
+        }
+        match c {
+            '"' => {
+                in_string = true;
+                out.push(c);
+            }
+            ',' => {
+                let next = chars[i + 1..].iter().find(|c| !c.is_whitespace());
+                if !matches!(next, Some('}') | Some(']')) {
+                    out.push(c);
+                }
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Parse JSON response from CLI (handles markdown code blocks and surrounding prose)
+pub fn parse_json_response<T: serde::de::DeserializeOwned>(response: &str) -> Result<T, String> {
+    let extracted = extract_json(response, true)?;
+    serde_json::from_value(extracted.value).map_err(|e| format!("Failed to parse JSON: {}", e))
 }
 
 #[cfg(test)]
@@ -470,6 +768,14 @@ mod tests {
             CliProvider::from_config_str("openclaw"),
             Some(CliProvider::OpenClaw)
         );
+        assert_eq!(
+            CliProvider::from_config_str("ollama"),
+            Some(CliProvider::Ollama)
+        );
+        assert_eq!(
+            CliProvider::from_config_str("openai"),
+            Some(CliProvider::OpenAi)
+        );
         assert_eq!(CliProvider::from_config_str("unknown"), None);
         assert_eq!(CliProvider::from_config_str(""), None);
     }
@@ -527,15 +833,156 @@ mod tests {
 
     #[tokio::test]
     async fn test_detect_provider_claude_code() {
-        let detected = detect_provider(CliProvider::ClaudeCode).await;
+        let detected = detect_provider(CliProvider::ClaudeCode, &AiConfig::default()).await;
         assert_eq!(detected.provider, CliProvider::ClaudeCode);
         println!("Claude Code detected: {:?}", detected);
     }
 
     #[tokio::test]
     async fn test_detect_provider_openclaw() {
-        let detected = detect_provider(CliProvider::OpenClaw).await;
+        let detected = detect_provider(CliProvider::OpenClaw, &AiConfig::default()).await;
         assert_eq!(detected.provider, CliProvider::OpenClaw);
         println!("OpenClaw detected: {:?}", detected);
     }
+
+    #[test]
+    fn test_extract_json_fenced() {
+        let response = "Sure! Here you go:\n```json\n{\"title\": \"Fix bug\"}\n```\nLet me know.";
+        let extracted = extract_json(response, false).unwrap();
+        assert_eq!(extracted.value["title"], "Fix bug");
+        assert!(!extracted.repaired);
+    }
+
+    #[test]
+    fn test_extract_json_prose_wrapped() {
+        let response = "I found [2] items: [{\"a\": \"x]\"}, {\"a\": 2}] — hope that helps!";
+        let extracted = extract_json(response, false).unwrap();
+        assert_eq!(extracted.value.as_array().unwrap().len(), 2);
+        assert_eq!(extracted.value[0]["a"], "x]");
+        assert!(!extracted.repaired);
+    }
+
+    #[test]
+    fn test_extract_json_trailing_commas_repaired() {
+        let response = "```\n[{\"a\": 1, \"b\": [1, 2,],},]\n```";
+        assert!(extract_json(response, false).is_err());
+        let extracted = extract_json(response, true).unwrap();
+        assert!(extracted.repaired);
+        assert_eq!(extracted.value[0]["b"][1], 2);
+    }
+
+    #[test]
+    fn test_extract_json_raw_newline_in_string_repaired() {
+        let response = "{\"content\": \"line one\nline two\"}";
+        let extracted = extract_json(response, true).unwrap();
+        assert!(extracted.repaired);
+        assert_eq!(extracted.value["content"], "line one\nline two");
+    }
+
+    #[test]
+    fn test_extract_json_no_json() {
+        assert!(extract_json("Nothing to report.", true).is_err());
+    }
+
+    #[test]
+    fn test_parse_json_response_prose_wrapped() {
+        #[derive(serde::Deserialize)]
+        struct Out {
+            ok: bool,
+        }
+        let out: Out = parse_json_response("Result: {\"ok\": true,} done").unwrap();
+        assert!(out.ok);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_cancelled_task_kills_cli_process() {
+        use std::os::unix::fs::PermissionsExt;
+
+        // Stand-in CLI that records its PID and hangs
+        let tmp = tempfile::tempdir().unwrap();
+        let pid_file = tmp.path().join("pid");
+        let script = tmp.path().join("fake-cli");
+        std::fs::write(
+            &script,
+            format!(
+                "#!/bin/sh\necho $$ > {}\nexec sleep 60\n",
+                pid_file.display()
+            ),
+        )
+        .unwrap();
+        std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let cli = DetectedCli {
+            provider: CliProvider::ClaudeCode,
+            installed: true,
+            path: Some(script),
+            version: None,
+            http: None,
+        };
+
+        let queue = crate::ai::AiTaskQueue::new(1);
+        let task = queue.track("s1", crate::ai::AiTaskKind::Title);
+        let handle = tokio::spawn(async move {
+            task.run(run_cli(&cli, "prompt", Duration::from_secs(60)))
+                .await
+        });
+
+        let mut pid = None;
+        for _ in 0..100 {
+            if let Ok(s) = std::fs::read_to_string(&pid_file) {
+                if let Ok(p) = s.trim().parse::<u32>() {
+                    pid = Some(p);
+                    break;
+                }
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        let pid = pid.expect("fake CLI should have started");
+
+        queue.cancel_session("s1");
+        assert!(handle.await.unwrap().is_none());
+
+        // Gone, or a zombie awaiting reaping — either way no longer running
+        let mut stopped = false;
+        for _ in 0..100 {
+            match std::fs::read_to_string(format!("/proc/{}/stat", pid)) {
+                Err(_) => stopped = true,
+                Ok(stat) => stopped = stat.contains(") Z "),
+            }
+            if stopped {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert!(stopped, "CLI process {} still running after cancel", pid);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_cli_timeout_is_a_gateway_timeout() {
+        use axum::response::IntoResponse;
+        use std::os::unix::fs::PermissionsExt;
+
+        // Stand-in CLI that never answers in time
+        let tmp = tempfile::tempdir().unwrap();
+        let script = tmp.path().join("slow-cli");
+        std::fs::write(&script, "#!/bin/sh\nexec sleep 5\n").unwrap();
+        std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let cli = DetectedCli {
+            provider: CliProvider::ClaudeCode,
+            installed: true,
+            path: Some(script),
+            version: None,
+            http: None,
+        };
+
+        let err = run_cli(&cli, "prompt", Duration::from_millis(200))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, CoreError::Timeout(..)), "{}", err);
+        assert_eq!(
+            err.into_response().status(),
+            axum::http::StatusCode::GATEWAY_TIMEOUT
+        );
+    }
 }
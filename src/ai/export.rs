@@ -1,6 +1,6 @@
 //! AI Export Generation
 //!
-//! Processes session content through Claude Code CLI to produce
+//! Processes session content through the configured AI provider to produce
 //! structured exports (Dev Notes, Blog Posts). Supports single-request
 //! and chunked map-reduce flows for large sessions.
 
@@ -9,6 +9,8 @@ use std::time::{Duration, Instant};
 use serde::{Deserialize, Serialize};
 
 use super::cli::{detect_provider, run_cli, CliProvider, DetectedCli};
+use crate::config::AiConfig;
+use crate::error::{CoreError, Result};
 
 /// Maximum input length to send to CLI
 pub const MAX_INPUT_LENGTH: usize = 100_000;
@@ -85,6 +87,9 @@ pub struct ChunkResult {
 pub struct MergeRequest {
     pub format: String,
     pub partial_results: Vec<String>,
+    /// Overlap the chunks were split with; raw merges drop the repeated text
+    #[serde(default)]
+    pub overlap_chars: usize,
 }
 
 /// Provider capabilities for export decisions
@@ -93,6 +98,8 @@ pub struct ProviderCapabilities {
     pub max_content_size: usize,
     pub timeout_secs: u64,
     pub supports_chunking: bool,
+    /// Characters consecutive chunks should share (`export.chunk_overlap_chars`)
+    pub chunk_overlap_chars: usize,
 }
 
 // ============================================================================
@@ -210,7 +217,9 @@ Segment content:\n{}",
 }
 
 fn get_merge_prompt(format: ExportFormat, partial_results: &[String]) -> String {
-    let combined = partial_results.join("\n\n---\n\n");
+    let combined = dedupe_partial_results(partial_results).join("\n\n---\n\n");
+    let overlap_note = "Consecutive segments overlap slightly, so an event near a segment \
+boundary may appear in two of them - include it only once.";
 
     match format {
         ExportFormat::Raw => combined,
@@ -223,10 +232,12 @@ Create these sections:\n\
 3. **Important Changes**: File edits, new code (include best snippets)\n\
 4. **Problems & Solutions**: Issues and resolutions\n\
 5. **Final State**: What was accomplished\n\n\
+{}\n\n\
 Write in professional technical documentation style.\n\
 Never include literal triple backticks as examples within code blocks.\n\n\
 Partial analyses:\n{}",
             partial_results.len(),
+            overlap_note,
             combined
         ),
 
@@ -244,6 +255,7 @@ Required structure:\n\
    - Include specific numbers/metrics\n\
 5. **Key Takeaway**: The main lesson learned (1-2 paragraphs)\n\
 6. **Impact**: Tech stack, what was accomplished\n\n\
+{}\n\n\
 Style:\n\
 - First person, conversational but professional\n\
 - Concrete details over vague descriptions\n\
@@ -252,55 +264,132 @@ Style:\n\
 - Never include literal triple backticks as examples within code blocks\n\n\
 Extracted highlights:\n{}",
             partial_results.len(),
+            overlap_note,
             combined
         ),
     }
 }
 
-/// Truncate content to fit within CLI limits (UTF-8 safe)
-fn truncate_content(content: &str, max_length: usize) -> String {
-    if content.len() <= max_length {
-        return content.to_string();
+/// Largest char boundary at or before `index`
+fn floor_char_boundary(s: &str, mut index: usize) -> usize {
+    index = index.min(s.len());
+    while !s.is_char_boundary(index) {
+        index -= 1;
     }
+    index
+}
 
-    // Find a valid UTF-8 boundary at or before max_length
-    let safe_end = content
-        .char_indices()
-        .take_while(|(i, _)| *i <= max_length)
-        .last()
-        .map(|(i, _)| i)
-        .unwrap_or(0);
-    let truncated = &content[..safe_end];
-
-    // Try to truncate at a message boundary (### User / ### Assistant)
-    if let Some(pos) = truncated.rfind("\n### ") {
-        return format!(
-            "{}\n\n[... content truncated for length ...]",
-            &content[..pos]
-        );
+/// Where the overlap repeated at the head of the next chunk begins in `text`
+/// (the content up to a chunk boundary): `overlap_chars` back from the end,
+/// moved forward to the next line start when one falls inside the overlap.
+/// Depends only on `text`, so `merge_overlapping` can recompute it exactly.
+fn overlap_start(text: &str, overlap_chars: usize) -> usize {
+    let start = floor_char_boundary(text, text.len().saturating_sub(overlap_chars));
+    match text[start..].find('\n') {
+        Some(pos) if start + pos + 1 < text.len() => start + pos + 1,
+        _ => start,
     }
+}
 
-    // Fallback: truncate at last newline
-    if let Some(pos) = truncated.rfind('\n') {
-        return format!(
-            "{}\n\n[... content truncated for length ...]",
-            &content[..pos]
-        );
+/// Split content into chunks of at most `max_chunk_chars` bytes.
+///
+/// Chunks end at a message boundary (`\n### `) or newline when possible, and each
+/// chunk after the first repeats (up to) the last `overlap_chars` of the previous
+/// one. Overlap is capped at half a chunk so splitting always makes progress.
+/// `merge_overlapping` with the same overlap reverses the split.
+pub fn split_into_chunks(
+    content: &str,
+    max_chunk_chars: usize,
+    overlap_chars: usize,
+) -> Vec<String> {
+    let overlap_chars = overlap_chars.min(max_chunk_chars / 2);
+    let mut chunks = Vec::new();
+    let mut start = 0;
+
+    loop {
+        if content.len() - start <= max_chunk_chars {
+            chunks.push(content[start..].to_string());
+            return chunks;
+        }
+
+        let mut limit = floor_char_boundary(content, start + max_chunk_chars);
+        if limit <= start {
+            // Chunk smaller than one character: take the character whole
+            limit = start + content[start..].chars().next().map_or(1, char::len_utf8);
+        }
+        // Don't cut so early that the next chunk would start at or before this one
+        let min_end = start + overlap_chars + 1;
+        let window = &content[start..limit];
+        let end = window
+            .rfind("\n### ")
+            .or_else(|| window.rfind('\n'))
+            .map(|pos| start + pos + 1)
+            .filter(|&end| end >= min_end)
+            .unwrap_or(limit);
+        chunks.push(content[start..end].to_string());
+
+        let next = overlap_start(&content[..end], overlap_chars);
+        start = if next > start { next } else { end };
+    }
+}
+
+/// Join chunk contents produced with `overlap_chars` of overlap, keeping the text
+/// repeated across each boundary only once. Parts that don't start with the
+/// expected overlap (or any parts when `overlap_chars` is 0) are separated by a
+/// blank line.
+pub fn merge_overlapping(parts: &[String], overlap_chars: usize) -> String {
+    let mut merged = String::new();
+    for part in parts {
+        if merged.is_empty() {
+            merged.push_str(part);
+            continue;
+        }
+        let shared_len = merged.len() - overlap_start(&merged, overlap_chars);
+        if overlap_chars > 0 && part.starts_with(&merged[merged.len() - shared_len..]) {
+            merged.push_str(&part[shared_len..]);
+        } else {
+            merged.push_str("\n\n");
+            merged.push_str(part);
+        }
     }
+    merged
+}
 
-    format!("{}...", truncated)
+/// Drop lines a partial result repeats from the one before it. Consecutive
+/// chunks overlap, so an event near a boundary can be reported by both; only
+/// its first report is kept. Blank lines and headings are left alone.
+fn dedupe_partial_results(parts: &[String]) -> Vec<String> {
+    let mut deduped = Vec::with_capacity(parts.len());
+    let mut previous = std::collections::HashSet::new();
+    for part in parts {
+        let kept: Vec<&str> = part
+            .lines()
+            .filter(|line| {
+                let line = line.trim();
+                line.is_empty() || line.starts_with('#') || !previous.contains(line)
+            })
+            .collect();
+        previous = part.lines().map(str::trim).collect();
+        deduped.push(kept.join("\n"));
+    }
+    deduped
 }
 
 // ============================================================================
 // Generation functions
 // ============================================================================
 
-/// Generate export content using AI CLI
+/// Generate export content using AI CLI.
+///
+/// Content longer than `MAX_INPUT_LENGTH` is split into chunks that share
+/// `overlap_chars`, each chunk is analyzed on its own, and the partial results
+/// are merged.
 pub async fn generate_export(
     content: &str,
     format: ExportFormat,
     cli: &DetectedCli,
-) -> Result<ExportResult, String> {
+    overlap_chars: usize,
+) -> Result<ExportResult> {
     if format == ExportFormat::Raw {
         return Ok(ExportResult {
             content: content.to_string(),
@@ -310,23 +399,47 @@ pub async fn generate_export(
         });
     }
 
-    let truncated = truncate_content(content, MAX_INPUT_LENGTH);
-    let prompt = get_format_prompt(format, &truncated);
-    let timeout = format.timeout();
+    let format_str = serde_json::to_value(format)
+        .ok()
+        .and_then(|v| v.as_str().map(|s| s.to_string()))
+        .unwrap_or_else(|| "unknown".to_string());
 
     let start = Instant::now();
-    let result = run_cli(cli, &prompt, timeout).await?;
+    let result = if content.len() <= MAX_INPUT_LENGTH {
+        run_cli(cli, &get_format_prompt(format, content), format.timeout()).await?
+    } else {
+        let chunks = split_into_chunks(content, MAX_INPUT_LENGTH, overlap_chars);
+        let total_chunks = chunks.len();
+        let mut partial_results = Vec::with_capacity(total_chunks);
+        for (chunk_index, chunk_content) in chunks.into_iter().enumerate() {
+            let request = ChunkRequest {
+                format: format_str.clone(),
+                chunk_content,
+                chunk_index,
+                total_chunks,
+                is_first: chunk_index == 0,
+                is_last: chunk_index + 1 == total_chunks,
+                // Leave room for every partial result in the merge prompt
+                target_output_chars: MAX_INPUT_LENGTH / total_chunks,
+            };
+            partial_results.push(process_chunk(&request, cli).await?.content);
+        }
+        tracing::info!("Merging {} export chunks", total_chunks);
+        run_cli(
+            cli,
+            &get_merge_prompt(format, &partial_results),
+            format.timeout(),
+        )
+        .await?
+    };
     let generation_time_ms = start.elapsed().as_millis() as u64;
 
     if result.is_empty() {
-        return Err("AI returned empty response. Please try again.".to_string());
+        return Err(CoreError::Api(
+            "AI returned empty response. Please try again.".to_string(),
+        ));
     }
 
-    let format_str = serde_json::to_value(format)
-        .ok()
-        .and_then(|v| v.as_str().map(|s| s.to_string()))
-        .unwrap_or_else(|| "unknown".to_string());
-
     Ok(ExportResult {
         content: result,
         format: format_str,
@@ -336,12 +449,9 @@ pub async fn generate_export(
 }
 
 /// Process a single chunk of content
-pub async fn process_chunk(
-    request: &ChunkRequest,
-    cli: &DetectedCli,
-) -> Result<ChunkResult, String> {
+pub async fn process_chunk(request: &ChunkRequest, cli: &DetectedCli) -> Result<ChunkResult> {
     let format = ExportFormat::parse_format(&request.format)
-        .ok_or_else(|| format!("Unknown format: {}", request.format))?;
+        .ok_or_else(|| CoreError::Validation(format!("Unknown format: {}", request.format)))?;
 
     if format == ExportFormat::Raw {
         return Ok(ChunkResult {
@@ -364,8 +474,8 @@ pub async fn process_chunk(
 
     let timeout = Duration::from_secs(180);
     let start = Instant::now();
-    let result = run_cli(cli, &prompt, timeout).await.map_err(|e| {
-        format!(
+    let result = run_cli(cli, &prompt, timeout).await.inspect_err(|e| {
+        tracing::warn!(
             "Chunk {}/{} failed: {}",
             request.chunk_index + 1,
             request.total_chunks,
@@ -375,11 +485,11 @@ pub async fn process_chunk(
     let generation_time_ms = start.elapsed().as_millis() as u64;
 
     if result.is_empty() {
-        return Err(format!(
+        return Err(CoreError::Api(format!(
             "Chunk {}/{} returned empty",
             request.chunk_index + 1,
             request.total_chunks
-        ));
+        )));
     }
 
     Ok(ChunkResult {
@@ -391,16 +501,13 @@ pub async fn process_chunk(
 }
 
 /// Merge partial results from chunks
-pub async fn merge_chunks(
-    request: &MergeRequest,
-    cli: &DetectedCli,
-) -> Result<ExportResult, String> {
+pub async fn merge_chunks(request: &MergeRequest, cli: &DetectedCli) -> Result<ExportResult> {
     let format = ExportFormat::parse_format(&request.format)
-        .ok_or_else(|| format!("Unknown format: {}", request.format))?;
+        .ok_or_else(|| CoreError::Validation(format!("Unknown format: {}", request.format)))?;
 
     if format == ExportFormat::Raw {
         return Ok(ExportResult {
-            content: request.partial_results.join("\n\n"),
+            content: merge_overlapping(&request.partial_results, request.overlap_chars),
             format: request.format.clone(),
             provider: "none".to_string(),
             generation_time_ms: 0,
@@ -415,7 +522,7 @@ pub async fn merge_chunks(
     let generation_time_ms = start.elapsed().as_millis() as u64;
 
     if result.is_empty() {
-        return Err("Merge returned empty response".to_string());
+        return Err(CoreError::Api("Merge returned empty response".to_string()));
     }
 
     Ok(ExportResult {
@@ -427,22 +534,220 @@ pub async fn merge_chunks(
 }
 
 /// Detect CLI and return capabilities
-pub async fn get_capabilities() -> ProviderCapabilities {
+pub async fn get_capabilities(chunk_overlap_chars: usize) -> ProviderCapabilities {
     ProviderCapabilities {
         max_content_size: MAX_INPUT_LENGTH,
         timeout_secs: 180,
         supports_chunking: true,
+        chunk_overlap_chars,
     }
 }
 
 /// Detect CLI availability (convenience wrapper)
-pub async fn ensure_cli(provider: CliProvider) -> Result<DetectedCli, String> {
-    let cli = detect_provider(provider).await;
+pub async fn ensure_cli(provider: CliProvider, ai: &AiConfig) -> Result<DetectedCli> {
+    let cli = detect_provider(provider, ai).await;
     if !cli.installed {
-        return Err(format!(
-            "{} CLI not installed. Please install it first.",
-            cli.provider.display_name()
+        return Err(CoreError::ProviderUnavailable(
+            cli.provider.unavailable_message(),
         ));
     }
     Ok(cli)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Session-like content: numbered `### User` messages of a few lines each
+    fn session_content(messages: usize) -> String {
+        (0..messages)
+            .map(|i| format!("### User\nmessage {} asks about the watcher config\n\n", i))
+            .collect()
+    }
+
+    #[test]
+    fn test_split_into_chunks_overlaps_consecutive_chunks() {
+        let content = session_content(200);
+        let chunks = split_into_chunks(&content, 1000, 200);
+
+        assert!(chunks.len() > 1);
+        for pair in chunks.windows(2) {
+            assert!(pair[0].len() <= 1000);
+            let shared = &pair[0][overlap_start(&pair[0], 200)..];
+            assert!(!shared.is_empty() && shared.len() <= 200);
+            assert!(pair[1].starts_with(shared));
+        }
+        assert_eq!(merge_overlapping(&chunks, 200), content);
+    }
+
+    #[test]
+    fn test_split_without_overlap_concatenates_to_original() {
+        let content = session_content(50);
+        let chunks = split_into_chunks(&content, 700, 0);
+        assert_eq!(chunks.concat(), content);
+    }
+
+    #[test]
+    fn test_boundary_content_appears_once_after_merge() {
+        let mut content = session_content(30);
+        let sentinel = "### Assistant\nSwitched the watcher to notify-debouncer-mini\n\n";
+        // Place the sentinel so it straddles the first chunk boundary
+        content.insert_str(content.len() / 2 - 20, sentinel);
+        content.push_str(&session_content(30));
+
+        let chunks = split_into_chunks(&content, content.len() / 2, 300);
+        assert!(chunks.len() >= 2);
+        assert!(chunks[0].contains(sentinel) || chunks[1].contains(sentinel));
+
+        let merged = merge_overlapping(&chunks, 300);
+        assert_eq!(merged, content);
+        assert_eq!(merged.matches(sentinel).count(), 1);
+    }
+
+    #[test]
+    fn test_split_is_utf8_safe() {
+        let content = "日本語のテキスト\n".repeat(100);
+        let chunks = split_into_chunks(&content, 101, 40);
+        assert_eq!(merge_overlapping(&chunks, 40), content);
+    }
+
+    #[test]
+    fn test_merge_without_overlap_separates_parts() {
+        let parts = vec!["first part".to_string(), "second part".to_string()];
+        assert_eq!(merge_overlapping(&parts, 0), "first part\n\nsecond part");
+        assert_eq!(merge_overlapping(&parts, 4), "first part\n\nsecond part");
+    }
+
+    #[test]
+    fn test_dedupe_partial_results_drops_lines_repeated_from_previous_part() {
+        let parts = vec![
+            "## Decisions\n- Use SQLite\n- Debounce watcher events".to_string(),
+            "## Decisions\n- Debounce watcher events\n- Add FTS".to_string(),
+            "## Decisions\n- Use SQLite".to_string(),
+        ];
+        assert_eq!(
+            dedupe_partial_results(&parts),
+            vec![
+                "## Decisions\n- Use SQLite\n- Debounce watcher events",
+                "## Decisions\n- Add FTS",
+                // Only the part right before is compared
+                "## Decisions\n- Use SQLite",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_generated_export_reports_boundary_content_once() {
+        use crate::ai::openai::OpenAiProvider;
+        use axum::{routing::post, Json, Router};
+
+        // Each chunk is "analyzed" by echoing its content, overlap included, and
+        // the merge by echoing the partial results it was given
+        let app = Router::new().route(
+            "/chat/completions",
+            post(|Json(req): Json<serde_json::Value>| async move {
+                let prompt = req["messages"][0]["content"].as_str().unwrap();
+                let reply = ["Segment content:\n", "Partial analyses:\n"]
+                    .iter()
+                    .find_map(|marker| prompt.split_once(marker).map(|(_, rest)| rest))
+                    .unwrap_or("")
+                    .to_string();
+                Json(serde_json::json!({
+                    "choices": [{ "message": { "role": "assistant", "content": reply } }]
+                }))
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        let cli = OpenAiProvider::new(
+            format!("http://{}", addr),
+            "gpt-4o-mini",
+            Some("test-key".to_string()),
+        )
+        .detect();
+
+        let content = session_content(3000);
+        assert!(content.len() > MAX_INPUT_LENGTH);
+        let result = generate_export(&content, ExportFormat::TechnicalSummary, &cli, 2000)
+            .await
+            .unwrap();
+
+        for i in 0..3000 {
+            let line = format!("message {} asks about the watcher config", i);
+            assert_eq!(result.content.matches(&line).count(), 1, "{}", line);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_chunked_export_preserves_chunk_order() {
+        use crate::ai::openai::OpenAiProvider;
+        use axum::{routing::post, Json, Router};
+
+        // Chunk prompts are answered with their chunk number; the merge prompt
+        // echoes the partial results in the order they appear in it.
+        let app = Router::new().route(
+            "/chat/completions",
+            post(|Json(req): Json<serde_json::Value>| async move {
+                let prompt = req["messages"][0]["content"].as_str().unwrap().to_string();
+                let chunk = regex::Regex::new(r"PARTIAL segment \(chunk (\d+)/\d+\)").unwrap();
+                let part = regex::Regex::new(r"analysis of chunk \d+").unwrap();
+                let reply = match chunk.captures(&prompt) {
+                    Some(c) => format!("analysis of chunk {}", &c[1]),
+                    None => part
+                        .find_iter(&prompt)
+                        .map(|m| m.as_str())
+                        .collect::<Vec<_>>()
+                        .join("\n"),
+                };
+                Json(serde_json::json!({
+                    "choices": [{ "message": { "role": "assistant", "content": reply } }]
+                }))
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        let cli = OpenAiProvider::new(
+            format!("http://{}", addr),
+            "gpt-4o-mini",
+            Some("test-key".to_string()),
+        )
+        .detect();
+
+        // Chunks are split and sent by the client; the server merges in order
+        let chunks = split_into_chunks(&session_content(6000), MAX_INPUT_LENGTH, 2000);
+        assert!(chunks.len() > 2);
+        let total_chunks = chunks.len();
+        let mut partial_results = Vec::new();
+        for (chunk_index, chunk_content) in chunks.into_iter().enumerate() {
+            let request = ChunkRequest {
+                format: "technical-summary".to_string(),
+                chunk_content,
+                chunk_index,
+                total_chunks,
+                is_first: chunk_index == 0,
+                is_last: chunk_index + 1 == total_chunks,
+                target_output_chars: 1000,
+            };
+            partial_results.push(process_chunk(&request, &cli).await.unwrap().content);
+        }
+
+        let result = merge_chunks(
+            &MergeRequest {
+                format: "technical-summary".to_string(),
+                partial_results,
+                overlap_chars: 2000,
+            },
+            &cli,
+        )
+        .await
+        .unwrap();
+        let expected: Vec<String> = (1..=total_chunks)
+            .map(|i| format!("analysis of chunk {}", i))
+            .collect();
+        assert_eq!(result.content, expected.join("\n"));
+        assert_eq!(result.format, "technical-summary");
+        assert_eq!(result.provider, "OpenAI");
+    }
+}
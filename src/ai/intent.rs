@@ -0,0 +1,531 @@
+//! Session Intents
+//!
+//! Extracts what the user asked for ("add OAuth login", "fix the flaky test")
+//! from a session's early user messages. Unlike the single title, a long session
+//! keeps one intent per distinct request.
+//!
+//! A regex heuristic runs whenever the watcher stores messages. With
+//! `ai.intent_extraction` enabled, `POST /api/sessions/:id/intents/extract` asks
+//! the configured provider instead, falling back to the heuristic on failure.
+//! AI-extracted intents are kept until the next explicit extraction.
+
+use crate::config::AiConfig;
+use crate::db::Database;
+use regex::Regex;
+use std::collections::HashSet;
+use std::sync::{Arc, LazyLock};
+
+use super::cli::{detect_provider, extract_json, run_cli, CliProvider, DetectedCli};
+use super::types::{IntentExtractionResult, SessionIntent};
+
+/// Early user messages considered for intents
+const MAX_USER_MESSAGES: usize = 20;
+
+/// Maximum intents stored per session
+const MAX_INTENTS: usize = 5;
+
+/// Maximum characters per intent
+const MAX_INTENT_LENGTH: usize = 120;
+
+/// Maximum characters of each message sent to AI
+const MAX_MESSAGE_CHARS: usize = 500;
+
+/// Source recorded for heuristic intents
+pub const SOURCE_HEURISTIC: &str = "heuristic";
+
+/// Source recorded for AI-extracted intents
+pub const SOURCE_AI: &str = "ai";
+
+/// A request: optional filler and politeness, then an imperative verb (captured)
+static REQUEST_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?ix)^
+        (?:(?:hey|hi|ok|okay|so|now|also|and|then|next|great|thanks)[,!.]?\s+)*
+        (?:please\s+|pls\s+
+          |(?:can|could|would|will)\s+you\s+(?:please\s+)?
+          |i(?:'d|\s+would)\s+like\s+(?:you\s+)?to\s+
+          |i\s+(?:want|need)\s+(?:you\s+)?to\s+
+          |let'?s\s+
+          |help\s+me\s+
+          |we\s+(?:need\s+to|should)\s+
+        )?
+        (add|fix|implement|create|make|build|write|update|change|remove|delete|refactor
+          |rename|move|support|set\s+up|setup|investigate|debug|optimi[sz]e|improve|convert
+          |migrate|replace|extract|split|test|document|review|upgrade|clean\s+up|port
+          |expose|introduce|handle|enable|disable|allow|prevent|speed\s+up)\b",
+    )
+    .unwrap()
+});
+
+/// Build the intent extraction prompt from `[sequence] text` lines
+fn build_intent_prompt(messages: &str) -> String {
+    format!(
+        r#"List the distinct requests the user makes in these opening messages of an AI coding session.
+
+**Guidelines:**
+- One entry per separate ask; merge follow-ups that refine the same request
+- Phrase each as a short imperative (e.g., "Add OAuth login to the settings page")
+- Ignore greetings, acknowledgements, and answers to the assistant's questions
+- At most {} entries, each under {} characters
+
+Respond with ONLY a JSON array:
+[{{"sequence_num": <number in brackets of the message making the request>, "intent": "..."}}]
+
+Messages:
+{}"#,
+        MAX_INTENTS, MAX_INTENT_LENGTH, messages
+    )
+}
+
+/// Early user-typed messages (not tool results) as `(sequence_num, text)`
+pub fn early_user_messages(
+    conn: &rusqlite::Connection,
+    session_id: &str,
+) -> rusqlite::Result<Vec<(i64, String)>> {
+    let mut stmt = conn.prepare(
+        "SELECT sequence_num, COALESCE(search_content, content_preview, '')
+         FROM session_messages
+         WHERE session_id = ? AND role = 'user' AND tool_type IS NULL
+         ORDER BY sequence_num ASC
+         LIMIT ?",
+    )?;
+    let rows = stmt.query_map(
+        rusqlite::params![session_id, MAX_USER_MESSAGES as i64],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+    rows.collect()
+}
+
+/// Pick out requests from user messages.
+///
+/// Each sentence starting with an imperative (optionally after "please",
+/// "can you", "let's", ...) becomes an intent. If nothing matches, the first
+/// sentence of the first message stands in as the session's request.
+pub fn heuristic_intents(messages: &[(i64, String)]) -> Vec<SessionIntent> {
+    let mut seen = HashSet::new();
+    let mut intents = Vec::new();
+
+    for (seq, text) in messages {
+        for sentence in sentences(text) {
+            let Some(verb) = REQUEST_RE.captures(sentence).and_then(|c| c.get(1)) else {
+                continue;
+            };
+            let intent = clean_intent(&sentence[verb.start()..]);
+            if seen.insert(intent.to_lowercase()) {
+                intents.push(SessionIntent {
+                    sequence_num: Some(*seq),
+                    intent,
+                });
+            }
+            if intents.len() == MAX_INTENTS {
+                return intents;
+            }
+        }
+    }
+
+    if intents.is_empty() {
+        if let Some((seq, sentence)) = messages
+            .iter()
+            .find_map(|(seq, text)| sentences(text).next().map(|s| (*seq, s)))
+        {
+            intents.push(SessionIntent {
+                sequence_num: Some(seq),
+                intent: clean_intent(sentence),
+            });
+        }
+    }
+    intents
+}
+
+/// Non-empty sentences and lines of a message
+fn sentences(text: &str) -> impl Iterator<Item = &str> {
+    text.lines()
+        .flat_map(|line| line.split(". "))
+        .flat_map(|s| s.split("? "))
+        .flat_map(|s| s.split("! "))
+        .map(|s| s.trim())
+        .filter(|s| !s.is_empty() && !s.starts_with('<'))
+}
+
+/// Capitalize, strip trailing punctuation, and truncate at a word boundary
+fn clean_intent(raw: &str) -> String {
+    let text = raw.trim().trim_end_matches(['.', '?', '!', ':', ',']);
+    let mut chars = text.chars();
+    let text = match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect::<String>(),
+        None => String::new(),
+    };
+
+    if text.len() <= MAX_INTENT_LENGTH {
+        return text;
+    }
+    let mut end = MAX_INTENT_LENGTH - 3;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    let truncated = &text[..end];
+    match truncated.rfind(' ') {
+        Some(space) => format!("{}...", &truncated[..space]),
+        None => format!("{}...", truncated),
+    }
+}
+
+/// Replace a session's intents
+pub fn replace_intents(
+    conn: &rusqlite::Connection,
+    session_id: &str,
+    intents: &[SessionIntent],
+    source: &str,
+) -> rusqlite::Result<()> {
+    let now = chrono::Utc::now().to_rfc3339();
+    conn.execute(
+        "DELETE FROM session_intents WHERE session_id = ?",
+        [session_id],
+    )?;
+    for intent in intents {
+        conn.execute(
+            "INSERT INTO session_intents (session_id, sequence_num, intent, source, created_at)
+             VALUES (?, ?, ?, ?, ?)",
+            rusqlite::params![session_id, intent.sequence_num, intent.intent, source, now],
+        )?;
+    }
+    Ok(())
+}
+
+/// Recompute heuristic intents after new messages are stored.
+/// Sessions with AI-extracted intents are left alone.
+pub fn refresh_heuristic_intents(
+    conn: &rusqlite::Connection,
+    session_id: &str,
+) -> rusqlite::Result<()> {
+    let has_ai: bool = conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM session_intents WHERE session_id = ? AND source = ?)",
+        [session_id, SOURCE_AI],
+        |row| row.get(0),
+    )?;
+    if has_ai {
+        return Ok(());
+    }
+    let intents = heuristic_intents(&early_user_messages(conn, session_id)?);
+    replace_intents(conn, session_id, &intents, SOURCE_HEURISTIC)
+}
+
+/// Recompute heuristic intents after messages from `first_new_seq` on were
+/// appended, if any of them is among the early user messages the heuristic
+/// reads. Later appends can't change the intents, so they are skipped.
+pub fn refresh_heuristic_intents_after_append(
+    conn: &rusqlite::Connection,
+    session_id: &str,
+    first_new_seq: i64,
+) -> rusqlite::Result<()> {
+    let appended_early = early_user_messages(conn, session_id)?
+        .iter()
+        .any(|(seq, _)| *seq >= first_new_seq);
+    if appended_early {
+        refresh_heuristic_intents(conn, session_id)?;
+    }
+    Ok(())
+}
+
+/// Stored intents for a session, in message order
+pub fn get_intents(
+    conn: &rusqlite::Connection,
+    session_id: &str,
+) -> rusqlite::Result<Vec<SessionIntent>> {
+    let mut stmt = conn.prepare(
+        "SELECT sequence_num, intent FROM session_intents
+         WHERE session_id = ?
+         ORDER BY sequence_num IS NULL, sequence_num, id",
+    )?;
+    let rows = stmt.query_map([session_id], |row| {
+        Ok(SessionIntent {
+            sequence_num: row.get(0)?,
+            intent: row.get(1)?,
+        })
+    })?;
+    rows.collect()
+}
+
+/// Extract intents with the AI provider, falling back to the heuristic.
+///
+/// The fallback's intents are returned with `source = "heuristic"` and the AI
+/// failure in `error`. Nothing is stored; see `store_intents`.
+pub async fn extract_intents(
+    db: &Arc<Database>,
+    session_id: &str,
+    cli: Option<DetectedCli>,
+    provider: CliProvider,
+    ai: &AiConfig,
+    lenient_json: bool,
+) -> IntentExtractionResult {
+    let messages = match load_messages(db, session_id).await {
+        Ok(m) => m,
+        Err(e) => return intent_error(session_id, &e),
+    };
+
+    let fallback = |error: String| IntentExtractionResult {
+        session_id: session_id.to_string(),
+        intents: heuristic_intents(&messages),
+        source: SOURCE_HEURISTIC.to_string(),
+        json_repaired: false,
+        error: Some(error),
+    };
+
+    let cli = match cli {
+        Some(c) => c,
+        None => detect_provider(provider, ai).await,
+    };
+    if !cli.installed {
+        return fallback(cli.provider.unavailable_message());
+    }
+
+    let numbered = messages
+        .iter()
+        .map(|(seq, text)| {
+            let end = text
+                .char_indices()
+                .map(|(i, _)| i)
+                .nth(MAX_MESSAGE_CHARS)
+                .unwrap_or(text.len());
+            format!("[{}] {}", seq, &text[..end])
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    let prompt = build_intent_prompt(&numbered);
+
+    let output = match run_cli(&cli, &prompt, cli.provider.title_timeout()).await {
+        Ok(o) => o,
+        Err(e) => return fallback(e.to_string()),
+    };
+    let extracted = match extract_json(&output, lenient_json) {
+        Ok(j) => j,
+        Err(e) => return fallback(e),
+    };
+
+    let sequences: HashSet<i64> = messages.iter().map(|(seq, _)| *seq).collect();
+    let mut seen = HashSet::new();
+    let intents: Vec<SessionIntent> = extracted
+        .value
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|item| {
+            let intent = clean_intent(item.get("intent")?.as_str()?);
+            let sequence_num = item
+                .get("sequence_num")
+                .and_then(|v| v.as_i64())
+                .filter(|seq| sequences.contains(seq));
+            (!intent.is_empty() && seen.insert(intent.to_lowercase())).then_some(SessionIntent {
+                sequence_num,
+                intent,
+            })
+        })
+        .take(MAX_INTENTS)
+        .collect();
+
+    if intents.is_empty() {
+        return fallback("AI returned no intents".to_string());
+    }
+    IntentExtractionResult {
+        session_id: session_id.to_string(),
+        intents,
+        source: SOURCE_AI.to_string(),
+        json_repaired: extracted.repaired,
+        error: None,
+    }
+}
+
+/// Extract intents with the heuristic only
+pub async fn extract_heuristic_intents(
+    db: &Arc<Database>,
+    session_id: &str,
+) -> IntentExtractionResult {
+    match load_messages(db, session_id).await {
+        Ok(messages) => IntentExtractionResult {
+            session_id: session_id.to_string(),
+            intents: heuristic_intents(&messages),
+            source: SOURCE_HEURISTIC.to_string(),
+            json_repaired: false,
+            error: None,
+        },
+        Err(e) => intent_error(session_id, &e),
+    }
+}
+
+async fn load_messages(db: &Arc<Database>, session_id: &str) -> Result<Vec<(i64, String)>, String> {
+    let sid = session_id.to_string();
+    match db
+        .with_conn(move |conn| early_user_messages(conn, &sid))
+        .await
+    {
+        Ok(m) if !m.is_empty() => Ok(m),
+        Ok(_) => Err("No user messages found in session".to_string()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+fn intent_error(session_id: &str, error: &str) -> IntentExtractionResult {
+    IntentExtractionResult {
+        session_id: session_id.to_string(),
+        intents: Vec::new(),
+        source: SOURCE_HEURISTIC.to_string(),
+        json_repaired: false,
+        error: Some(error.to_string()),
+    }
+}
+
+/// Store an extraction result, replacing the session's intents
+pub async fn store_intents(
+    db: &Arc<Database>,
+    result: &IntentExtractionResult,
+) -> Result<(), String> {
+    let session_id = result.session_id.clone();
+    let intents = result.intents.clone();
+    let source = result.source.clone();
+    db.with_conn(move |conn| replace_intents(conn, &session_id, &intents, &source))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support;
+
+    fn texts(intents: &[SessionIntent]) -> Vec<&str> {
+        intents.iter().map(|i| i.intent.as_str()).collect()
+    }
+
+    #[test]
+    fn test_two_requests_record_both_intents() {
+        let tmp = tempfile::tempdir().unwrap();
+        let db = Database::new(tmp.path().join("test.db")).unwrap();
+        #[allow(deprecated)]
+        let conn = db.conn();
+        test_support::seed_session(&conn, "s1");
+        conn.execute_batch(
+            "INSERT INTO session_messages (session_id, sequence_num, role, search_content, tool_type, timestamp) VALUES
+                ('s1', 0, 'user', 'Hi! Can you add OAuth login to the settings page?', NULL, '2024-01-01T00:00:00Z'),
+                ('s1', 1, 'assistant', 'Sure, let me add that.', NULL, '2024-01-01T00:00:01Z'),
+                ('s1', 2, 'user', 'Looks good, thanks', NULL, '2024-01-01T00:00:02Z'),
+                ('s1', 3, 'user', 'fix: the test broke', 'result', '2024-01-01T00:00:03Z'),
+                ('s1', 4, 'user', 'Now please fix the flaky login test in CI.', NULL, '2024-01-01T00:00:04Z');",
+        )
+        .unwrap();
+
+        refresh_heuristic_intents(&conn, "s1").unwrap();
+        let intents = get_intents(&conn, "s1").unwrap();
+        assert_eq!(
+            texts(&intents),
+            [
+                "Add OAuth login to the settings page",
+                "Fix the flaky login test in CI"
+            ]
+        );
+        assert_eq!(intents[0].sequence_num, Some(0));
+        assert_eq!(intents[1].sequence_num, Some(4));
+
+        // AI-extracted intents survive later heuristic refreshes
+        let ai = [SessionIntent {
+            sequence_num: Some(0),
+            intent: "Add OAuth login".to_string(),
+        }];
+        replace_intents(&conn, "s1", &ai, SOURCE_AI).unwrap();
+        refresh_heuristic_intents(&conn, "s1").unwrap();
+        assert_eq!(
+            texts(&get_intents(&conn, "s1").unwrap()),
+            ["Add OAuth login"]
+        );
+    }
+
+    #[test]
+    fn test_appends_past_the_early_messages_keep_intents() {
+        let tmp = tempfile::tempdir().unwrap();
+        let db = Database::new(tmp.path().join("test.db")).unwrap();
+        #[allow(deprecated)]
+        let conn = db.conn();
+        test_support::seed_session(&conn, "s1");
+        let insert = |seq: i64, text: &str| {
+            conn.execute(
+                "INSERT INTO session_messages (session_id, sequence_num, role, search_content, timestamp)
+                 VALUES ('s1', ?1, 'user', ?2, '2024-01-01T00:00:00Z')",
+                rusqlite::params![seq, text],
+            )
+            .unwrap();
+        };
+
+        insert(0, "Add OAuth login to the settings page.");
+        refresh_heuristic_intents(&conn, "s1").unwrap();
+        insert(1, "Now please fix the flaky login test.");
+        refresh_heuristic_intents_after_append(&conn, "s1", 1).unwrap();
+        assert_eq!(get_intents(&conn, "s1").unwrap().len(), 2);
+
+        // Message 20 is past the early user messages the heuristic reads, so
+        // the heuristic doesn't run again (it would restore the cleared intents)
+        for seq in 2..20 {
+            insert(seq, "ok");
+        }
+        insert(20, "Please delete the build cache.");
+        conn.execute("DELETE FROM session_intents", []).unwrap();
+        refresh_heuristic_intents_after_append(&conn, "s1", 20).unwrap();
+        assert!(get_intents(&conn, "s1").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_heuristic_falls_back_to_first_sentence() {
+        let messages = vec![
+            (
+                0,
+                "The OAuth callback 500s on Safari. No idea why".to_string(),
+            ),
+            (2, "ok".to_string()),
+        ];
+        let intents = heuristic_intents(&messages);
+        assert_eq!(texts(&intents), ["The OAuth callback 500s on Safari"]);
+    }
+
+    #[test]
+    fn test_duplicate_requests_collapse() {
+        let messages = vec![
+            (0, "Add dark mode".to_string()),
+            (1, "please add dark mode.".to_string()),
+        ];
+        assert_eq!(texts(&heuristic_intents(&messages)), ["Add dark mode"]);
+    }
+
+    #[tokio::test]
+    async fn test_extract_intents_with_openai() {
+        use crate::ai::openai::{tests::mock_openai, OpenAiProvider};
+
+        let tmp = tempfile::tempdir().unwrap();
+        let db = Arc::new(Database::new(tmp.path().join("test.db")).unwrap());
+        db.with_conn(|conn| {
+            test_support::seed_session(conn, "s1");
+            conn.execute_batch(
+                "INSERT INTO session_messages (session_id, sequence_num, role, search_content, timestamp)
+                 VALUES ('s1', 0, 'user', 'OAuth is broken, and the docs are stale', '2024-01-01T00:00:00Z');",
+            )
+        })
+        .await
+        .unwrap();
+
+        let reply = r#"[{"sequence_num": 0, "intent": "Fix OAuth"}, {"sequence_num": 9, "intent": "update the docs."}]"#;
+        let (url, _) = mock_openai(reply, 0).await;
+        let cli = OpenAiProvider::new(url, "gpt-4o-mini", Some("test-key".to_string())).detect();
+
+        let result = extract_intents(
+            &db,
+            "s1",
+            Some(cli),
+            CliProvider::OpenAi,
+            &AiConfig::default(),
+            true,
+        )
+        .await;
+        assert_eq!(result.error, None);
+        assert_eq!(result.source, SOURCE_AI);
+        assert_eq!(texts(&result.intents), ["Fix OAuth", "Update the docs"]);
+        // Sequence numbers outside the prompt are dropped
+        assert_eq!(result.intents[1].sequence_num, None);
+    }
+}
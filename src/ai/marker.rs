@@ -4,17 +4,21 @@
 //! Uses two-phase detection:
 //! - Phase 1: Find important moment indices (fast, no labels)
 //! - Phase 2: Generate labels for detected moments (accurate, small input)
+//!
+//! Without an AI provider, `detect_markers_heuristic` marks messages matching
+//! the `[[ai.marker_patterns]]` regexes instead.
 
 use crate::ai::cli::{
     call_cli_with_prompt, detect_provider, parse_json_response, CliProvider, DetectedCli,
 };
+use crate::config::{AiConfig, MarkerPattern};
 use crate::db::Database;
 use chrono::Utc;
+use regex::Regex;
 use rusqlite::params;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::fs::File;
-use std::io::{Read as IoRead, Seek, SeekFrom};
+use std::path::Path;
 use std::sync::Arc;
 use tokio::sync::Semaphore;
 
@@ -43,6 +47,18 @@ impl MarkerType {
             MarkerType::Stuck => "stuck",
         }
     }
+
+    /// Parse a marker type name as stored in the database
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "breakthrough" => Some(MarkerType::Breakthrough),
+            "ship" => Some(MarkerType::Ship),
+            "decision" => Some(MarkerType::Decision),
+            "bug" => Some(MarkerType::Bug),
+            "stuck" => Some(MarkerType::Stuck),
+            _ => None,
+        }
+    }
 }
 
 /// Session marker stored in database
@@ -214,14 +230,12 @@ fn read_full_content(
     byte_offset: i64,
     byte_length: i64,
 ) -> Result<String, String> {
-    let mut file = File::open(file_path).map_err(|e| format!("Failed to open file: {}", e))?;
-
-    file.seek(SeekFrom::Start(byte_offset as u64))
-        .map_err(|e| format!("Failed to seek: {}", e))?;
-
-    let mut buffer = vec![0u8; byte_length as usize];
-    file.read_exact(&mut buffer)
-        .map_err(|e| format!("Failed to read: {}", e))?;
+    let buffer = crate::parser::file::read_range(
+        Path::new(file_path),
+        byte_offset as u64,
+        byte_length as usize,
+    )
+    .map_err(|e| format!("Failed to read: {}", e))?;
 
     String::from_utf8(buffer).map_err(|e| format!("Failed to decode UTF-8: {}", e))
 }
@@ -500,7 +514,9 @@ async fn detect_phase1(
     let events_json = events_to_compact_json(sampled_events, file_path)?;
     let prompt = build_phase1_prompt(&events_json, target_markers);
 
-    let response = call_cli_with_prompt(&prompt, cli, 90).await?;
+    let response = call_cli_with_prompt(&prompt, cli, 90)
+        .await
+        .map_err(|e| e.to_string())?;
     let result: Phase1Result = parse_json_response(&response)?;
 
     Ok(result)
@@ -539,7 +555,9 @@ async fn detect_phase2(
         .map_err(|e| format!("Failed to serialize: {}", e))?;
 
     let prompt = build_phase2_prompt(&messages_json);
-    let response = call_cli_with_prompt(&prompt, cli, 60).await?;
+    let response = call_cli_with_prompt(&prompt, cli, 60)
+        .await
+        .map_err(|e| e.to_string())?;
     let result: Phase2Result = parse_json_response(&response)?;
 
     Ok(result)
@@ -590,11 +608,12 @@ pub async fn detect_markers(
     session_id: &str,
     cli: Option<DetectedCli>,
     provider: CliProvider,
+    ai: &AiConfig,
 ) -> MarkerDetectionResult {
     let cli = match cli {
         Some(c) => c,
         None => {
-            let detected = detect_provider(provider).await;
+            let detected = detect_provider(provider, ai).await;
             if !detected.installed {
                 return MarkerDetectionResult {
                     session_id: session_id.to_string(),
@@ -699,6 +718,157 @@ pub async fn detect_markers(
     }
 }
 
+// ============================================================================
+// Heuristic Detection
+// ============================================================================
+
+/// Maximum characters of the matched line kept as a heuristic marker's description
+const MAX_HEURISTIC_DESCRIPTION: usize = 80;
+
+/// Load each message's searchable text (search content and tool summary)
+fn load_message_texts(
+    conn: &rusqlite::Connection,
+    session_id: &str,
+) -> Result<Vec<(i32, String)>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT sequence_num, COALESCE(search_content, ''), COALESCE(tool_summary, '')
+             FROM session_messages
+             WHERE session_id = ?1
+             ORDER BY sequence_num ASC",
+        )
+        .map_err(|e| format!("Failed to prepare query: {}", e))?;
+
+    let messages = stmt
+        .query_map([session_id], |row| {
+            let content: String = row.get(1)?;
+            let tool_summary: String = row.get(2)?;
+            Ok((row.get(0)?, format!("{}\n{}", content, tool_summary)))
+        })
+        .map_err(|e| format!("Failed to query messages: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to collect messages: {}", e))?;
+
+    Ok(messages)
+}
+
+/// Compile marker patterns, skipping any with an unknown type or invalid regex
+fn compile_patterns(patterns: &[MarkerPattern]) -> Vec<(MarkerType, &str, Regex)> {
+    patterns
+        .iter()
+        .filter_map(|p| {
+            let Some(marker_type) = MarkerType::parse(&p.marker_type) else {
+                eprintln!(
+                    "[markers] Unknown marker type '{}', skipping",
+                    p.marker_type
+                );
+                return None;
+            };
+            match Regex::new(&p.pattern) {
+                Ok(regex) => Some((marker_type, p.label.as_str(), regex)),
+                Err(e) => {
+                    eprintln!("[markers] Invalid pattern for '{}': {}", p.label, e);
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+/// Mark each message by the first pattern it matches. A repeat of the previous
+/// marker's pattern (e.g. tests passing again) is skipped.
+fn heuristic_markers(
+    messages: &[(i32, String)],
+    patterns: &[(MarkerType, &str, Regex)],
+) -> Vec<(MarkerType, MarkerData)> {
+    let mut markers = Vec::new();
+    let mut previous: Option<usize> = None;
+
+    for (sequence_num, text) in messages {
+        let Some((index, m)) = patterns
+            .iter()
+            .enumerate()
+            .find_map(|(i, (_, _, regex))| regex.find(text).map(|m| (i, m)))
+        else {
+            continue;
+        };
+        if previous == Some(index) {
+            continue;
+        }
+        previous = Some(index);
+
+        // The line the match is on, as the description
+        let start = text[..m.start()].rfind('\n').map_or(0, |i| i + 1);
+        let end = text[m.end()..]
+            .find('\n')
+            .map_or(text.len(), |i| m.end() + i);
+        let description: String = text[start..end]
+            .trim()
+            .chars()
+            .take(MAX_HEURISTIC_DESCRIPTION)
+            .collect();
+
+        let (marker_type, label, _) = &patterns[index];
+        markers.push((
+            *marker_type,
+            MarkerData {
+                event_index: *sequence_num,
+                label: label.to_string(),
+                description,
+            },
+        ));
+    }
+
+    markers
+}
+
+/// Detect and store markers for a session by pattern, without an AI provider.
+/// Replaces the session's existing markers.
+pub async fn detect_markers_heuristic(
+    db: &Arc<Database>,
+    session_id: &str,
+    patterns: &[MarkerPattern],
+) -> MarkerDetectionResult {
+    let session_id_for_load = session_id.to_string();
+    let messages = match db
+        .with_conn(move |conn| load_message_texts(conn, &session_id_for_load))
+        .await
+    {
+        Ok(m) => m,
+        Err(e) => {
+            eprintln!("[markers] Failed to load session: {}", e);
+            return MarkerDetectionResult {
+                session_id: session_id.to_string(),
+                markers_detected: 0,
+            };
+        }
+    };
+
+    let markers = heuristic_markers(&messages, &compile_patterns(patterns));
+
+    let session_id_for_store = session_id.to_string();
+    let count = db
+        .with_conn(move |conn| {
+            delete_markers(conn, &session_id_for_store)?;
+            store_markers(conn, &session_id_for_store, &markers)
+        })
+        .await
+        .unwrap_or_else(|e| {
+            eprintln!("[markers] Failed to store markers: {}", e);
+            0
+        });
+
+    println!(
+        "[markers] Detected {} markers by pattern for session {}",
+        count, session_id
+    );
+
+    MarkerDetectionResult {
+        session_id: session_id.to_string(),
+        markers_detected: count,
+    }
+}
+
 async fn process_single_chunk(
     sampled: &[SessionMessage],
     all_messages: &[SessionMessage],
@@ -801,3 +971,68 @@ async fn process_multiple_chunks(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AiConfig;
+    use crate::test_support;
+
+    #[tokio::test]
+    async fn test_detect_markers_heuristic() {
+        let tmp = tempfile::tempdir().unwrap();
+        let db = Arc::new(Database::new(tmp.path().join("test.db")).unwrap());
+        db.with_conn(|conn| {
+            test_support::seed_project(conn);
+            conn.execute_batch(
+                "INSERT INTO sessions (id, project_id, file_path, ai_tool, message_count, created_at, indexed_at)
+                 VALUES ('s1', 'p1', '/tmp/proj/s1.jsonl', 'Claude Code', 7, '2024-01-01T00:00:00Z', '2024-01-01T00:00:00Z');
+                 INSERT INTO session_markers (session_id, event_index, marker_type, label, created_at)
+                 VALUES ('s1', 0, 'stuck', 'Stale marker', '2024-01-01T00:00:00Z');",
+            )
+            .unwrap();
+            let messages: [(&str, Option<&str>); 7] = [
+                ("Let's wire up the release pipeline", None),
+                ("Running cargo build", Some("error: could not compile `yocore`\nBuild failed")),
+                ("Fixed the missing import", None),
+                ("Running the suite", Some("test result: ok. 42 passed; 0 failed")),
+                ("And once more after the cleanup", Some("test result: ok. 42 passed; 0 failed")),
+                ("That commit broke the docs, running git revert HEAD", None),
+                ("Deployed to production, all green", None),
+            ];
+            for (i, (content, tool_summary)) in messages.iter().enumerate() {
+                conn.execute(
+                    "INSERT INTO session_messages (session_id, sequence_num, role, search_content, tool_summary, timestamp)
+                     VALUES ('s1', ?1, 'assistant', ?2, ?3, '2024-01-01T00:00:00Z')",
+                    params![i as i64, content, tool_summary],
+                )
+                .unwrap();
+            }
+        })
+        .await;
+
+        let patterns = AiConfig::default().marker_patterns;
+        let result = detect_markers_heuristic(&db, "s1", &patterns).await;
+        assert_eq!(result.markers_detected, 4);
+
+        let markers = db.with_conn(|conn| get_markers(conn, "s1")).await.unwrap();
+        let found: Vec<(i32, &str, &str)> = markers
+            .iter()
+            .map(|m| (m.event_index, m.marker_type.as_str(), m.label.as_str()))
+            .collect();
+        // The stale marker is replaced and the repeated test pass collapsed
+        assert_eq!(
+            found,
+            vec![
+                (1, "bug", "Build failed"),
+                (3, "breakthrough", "Tests passed"),
+                (5, "decision", "Git revert"),
+                (6, "ship", "Deploy"),
+            ]
+        );
+        assert_eq!(
+            markers[0].description.as_deref(),
+            Some("error: could not compile `yocore`")
+        );
+    }
+}
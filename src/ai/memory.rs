@@ -3,10 +3,11 @@
 //! Extracts memories from sessions using AI.
 //! Memories are structured knowledge items (decisions, facts, preferences, etc.)
 
+use crate::config::AiConfig;
 use crate::db::Database;
 use std::sync::Arc;
 
-use super::cli::{detect_provider, run_cli, CliProvider, DetectedCli};
+use super::cli::{detect_provider, extract_json, run_cli, CliProvider, DetectedCli};
 use super::types::MemoryExtractionResult;
 
 /// Maximum characters of input to send to AI
@@ -276,6 +277,8 @@ pub async fn extract_memories(
     cli: Option<DetectedCli>,
     force: bool,
     provider: CliProvider,
+    ai: &AiConfig,
+    lenient_json: bool,
 ) -> MemoryExtractionResult {
     // Check if already extracted and no significant new content (unless force)
     if !force {
@@ -311,6 +314,8 @@ pub async fn extract_memories(
                 session_id: session_id.to_string(),
                 memories_extracted: 0,
                 memories_skipped: 0,
+                json_repaired: false,
+                provider: None,
                 error: None,
             };
         }
@@ -319,7 +324,7 @@ pub async fn extract_memories(
     // Detect CLI if not provided
     let cli = match cli {
         Some(c) => c,
-        None => detect_provider(provider).await,
+        None => detect_provider(provider, ai).await,
     };
 
     if !cli.installed {
@@ -327,7 +332,9 @@ pub async fn extract_memories(
             session_id: session_id.to_string(),
             memories_extracted: 0,
             memories_skipped: 0,
-            error: Some(format!("{} CLI not installed", cli.provider.display_name())),
+            json_repaired: false,
+            provider: Some(cli.provider),
+            error: Some(cli.provider.unavailable_message()),
         };
     }
 
@@ -339,6 +346,8 @@ pub async fn extract_memories(
                 session_id: session_id.to_string(),
                 memories_extracted: 0,
                 memories_skipped: 0,
+                json_repaired: false,
+                provider: Some(cli.provider),
                 error: Some(e),
             }
         }
@@ -356,24 +365,35 @@ pub async fn extract_memories(
                 session_id: session_id.to_string(),
                 memories_extracted: 0,
                 memories_skipped: 0,
-                error: Some(e),
+                json_repaired: false,
+                provider: Some(cli.provider),
+                error: Some(e.to_string()),
             }
         }
     };
 
     // Parse memories from response
-    let memories = match parse_memories(&output) {
+    let (memories, json_repaired) = match parse_memories(&output, lenient_json) {
         Ok(m) => m,
         Err(e) => {
             return MemoryExtractionResult {
                 session_id: session_id.to_string(),
                 memories_extracted: 0,
                 memories_skipped: 0,
+                json_repaired: false,
+                provider: Some(cli.provider),
                 error: Some(format!("Failed to parse memories: {}", e)),
             }
         }
     };
 
+    if json_repaired {
+        tracing::info!(
+            "Memory extraction output for {} needed JSON repair",
+            session_id
+        );
+    }
+
     // Store memories
     let mut extracted = 0;
     let mut skipped = 0;
@@ -412,7 +432,7 @@ pub async fn extract_memories(
                     {
                         Ok(Ok(embedding)) => {
                             let bytes = crate::embeddings::embedding_to_bytes(&embedding);
-                            let _ = db_embed
+                            let stored = db_embed
                                 .with_conn(move |conn| {
                                     conn.execute(
                                         "INSERT OR REPLACE INTO memory_embeddings (memory_id, embedding) VALUES (?, ?)",
@@ -420,6 +440,9 @@ pub async fn extract_memories(
                                     )
                                 })
                                 .await;
+                            if stored.is_ok() {
+                                db_embed.index_memory_embedding(memory_id, &embedding);
+                            }
                         }
                         Ok(Err(e)) => {
                             tracing::debug!(
@@ -469,62 +492,40 @@ pub async fn extract_memories(
         session_id: session_id.to_string(),
         memories_extracted: extracted,
         memories_skipped: skipped,
+        json_repaired,
+        provider: Some(cli.provider),
         error: None,
     }
 }
 
-/// Parse memories from AI response
-fn parse_memories(response: &str) -> Result<Vec<RawMemory>, String> {
-    // Extract JSON from markdown code block if present
-    let json_str = if response.contains("```") {
-        let lines: Vec<&str> = response.lines().collect();
-        let mut in_block = false;
-        let mut json_lines = Vec::new();
-
-        for line in lines {
-            if line.starts_with("```json") || (line.starts_with("```") && !in_block) {
-                in_block = true;
-                continue;
-            }
-            if line.starts_with("```") && in_block {
-                break;
-            }
-            if in_block {
-                json_lines.push(line);
-            }
+/// Parse memories from AI response.
+/// Returns the memories and whether the JSON needed lenient repair.
+fn parse_memories(response: &str, lenient: bool) -> Result<(Vec<RawMemory>, bool), String> {
+    let extracted = extract_json(response, lenient)?;
+
+    // Accept either a bare array or an object with a "memories" field
+    let value = match extracted.value {
+        serde_json::Value::Object(mut map) if map.contains_key("memories") => {
+            map.remove("memories").unwrap_or_default()
         }
-        json_lines.join("\n")
-    } else {
-        response.to_string()
+        value => value,
     };
 
-    // Try parsing as array
-    if let Ok(memories) = serde_json::from_str::<Vec<RawMemory>>(&json_str) {
-        return Ok(memories);
-    }
-
-    // Try parsing as object with "memories" field
-    #[derive(serde::Deserialize)]
-    struct Wrapper {
-        memories: Vec<RawMemory>,
-    }
-
-    if let Ok(wrapper) = serde_json::from_str::<Wrapper>(&json_str) {
-        return Ok(wrapper.memories);
-    }
-
-    Err("Failed to parse memories JSON".to_string())
+    serde_json::from_value::<Vec<RawMemory>>(value)
+        .map(|memories| (memories, extracted.repaired))
+        .map_err(|e| format!("Failed to parse memories JSON: {}", e))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_support;
 
     #[test]
     fn test_parse_memories_array() {
         let response =
             r#"[{"type": "decision", "title": "Use React", "content": "Decided to use React"}]"#;
-        let memories = parse_memories(response).unwrap();
+        let memories = parse_memories(response, true).unwrap().0;
         assert_eq!(memories.len(), 1);
         assert_eq!(memories[0].title, "Use React");
     }
@@ -532,7 +533,7 @@ mod tests {
     #[test]
     fn test_parse_memories_object() {
         let response = r#"{"memories": [{"type": "fact", "title": "API endpoint", "content": "Found the API"}]}"#;
-        let memories = parse_memories(response).unwrap();
+        let memories = parse_memories(response, true).unwrap().0;
         assert_eq!(memories.len(), 1);
         assert_eq!(memories[0].title, "API endpoint");
     }
@@ -543,7 +544,97 @@ mod tests {
 ```json
 [{"type": "preference", "title": "Tab size", "content": "Use 2 spaces"}]
 ```"#;
-        let memories = parse_memories(response).unwrap();
+        let memories = parse_memories(response, true).unwrap().0;
+        assert_eq!(memories.len(), 1);
+    }
+
+    #[test]
+    fn test_parse_memories_prose_wrapped() {
+        let response = r#"I reviewed the session. {"memories": [{"type": "fact", "title": "Port", "content": "API runs on 19420"}]} Those are the key points."#;
+        let (memories, repaired) = parse_memories(response, true).unwrap();
         assert_eq!(memories.len(), 1);
+        assert_eq!(memories[0].title, "Port");
+        assert!(!repaired);
+    }
+
+    #[test]
+    fn test_parse_memories_trailing_comma_repaired() {
+        let response = r#"```json
+[{"type": "decision", "title": "Use SQLite", "content": "Chosen for portability",},]
+```"#;
+        assert!(parse_memories(response, false).is_err());
+        let (memories, repaired) = parse_memories(response, true).unwrap();
+        assert_eq!(memories.len(), 1);
+        assert!(repaired);
+    }
+
+    #[tokio::test]
+    async fn test_extract_memories_with_openai() {
+        use crate::ai::openai::{tests::mock_openai, OpenAiProvider};
+
+        let tmp = tempfile::tempdir().unwrap();
+        let db = Arc::new(Database::new(tmp.path().join("test.db")).unwrap());
+        db.with_conn(|conn| {
+            test_support::seed_project(conn);
+            conn.execute_batch(
+                "INSERT INTO sessions (id, project_id, file_path, ai_tool, message_count, created_at, indexed_at)
+                 VALUES ('s1', 'p1', '/tmp/proj/s1.jsonl', 'Claude Code', 30, '2024-01-01T00:00:00Z', '2024-01-01T00:00:00Z');",
+            )
+            .unwrap();
+            for i in 0..30 {
+                conn.execute(
+                    "INSERT INTO session_messages (session_id, sequence_num, role, content_preview, timestamp)
+                     VALUES ('s1', ?1, ?2, ?3, '2024-01-01T00:00:00Z')",
+                    rusqlite::params![
+                        i,
+                        if i % 2 == 0 { "user" } else { "assistant" },
+                        format!("step {} of wiring the provider", i)
+                    ],
+                )
+                .unwrap();
+            }
+        })
+        .await;
+
+        let reply = r#"[
+            {"type": "decision", "title": "Use reqwest for HTTP providers", "content": "Already in the dependency tree via hf-hub", "confidence": 0.9},
+            {"type": "fact", "title": "Key falls back to OPENAI_API_KEY", "content": "Config value wins when both are set", "confidence": 0.85}
+        ]"#;
+        let (url, calls) = mock_openai(reply, 1).await;
+        let cli = OpenAiProvider::new(url, "gpt-4o-mini", Some("test-key".to_string())).detect();
+
+        let result = extract_memories(
+            &db,
+            "s1",
+            Some(cli),
+            true,
+            CliProvider::OpenAi,
+            &AiConfig::default(),
+            true,
+        )
+        .await;
+        assert_eq!(result.error, None);
+        assert_eq!(result.memories_extracted, 2);
+        // One rate-limited attempt, then the successful retry
+        assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), 2);
+
+        let titles: Vec<String> = db
+            .with_conn(|conn| {
+                let mut stmt = conn
+                    .prepare("SELECT title FROM memories WHERE session_id = 's1' ORDER BY id")
+                    .unwrap();
+                stmt.query_map([], |row| row.get(0))
+                    .unwrap()
+                    .filter_map(|r| r.ok())
+                    .collect()
+            })
+            .await;
+        assert_eq!(
+            titles,
+            vec![
+                "Use reqwest for HTTP providers",
+                "Key falls back to OPENAI_API_KEY"
+            ]
+        );
     }
 }
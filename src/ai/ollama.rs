@@ -0,0 +1,266 @@
+//! Ollama Provider
+//!
+//! Runs AI operations against a local Ollama server over HTTP instead of
+//! spawning a CLI subprocess. Selected with `provider = "ollama"`; the server
+//! URL and model come from `[ai.ollama]`.
+
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use super::cli::{CliProvider, DetectedCli, HttpProvider};
+use crate::config::OllamaConfig;
+use crate::error::CoreError;
+
+/// Connection settings for an Ollama server
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OllamaProvider {
+    pub base_url: String,
+    pub model: String,
+}
+
+#[derive(Serialize)]
+struct ChatRequest<'a> {
+    model: &'a str,
+    messages: [ChatMessage<'a>; 1],
+    stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    format: Option<&'a str>,
+}
+
+#[derive(Serialize)]
+struct ChatMessage<'a> {
+    role: &'a str,
+    content: &'a str,
+}
+
+#[derive(Deserialize)]
+struct ChatResponse {
+    message: ChatResponseMessage,
+}
+
+#[derive(Deserialize)]
+struct ChatResponseMessage {
+    content: String,
+}
+
+#[derive(Deserialize)]
+struct VersionResponse {
+    version: String,
+}
+
+impl OllamaProvider {
+    pub fn new(base_url: impl Into<String>, model: impl Into<String>) -> Self {
+        OllamaProvider {
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            model: model.into(),
+        }
+    }
+
+    pub fn from_config(config: &OllamaConfig) -> Self {
+        Self::new(&config.base_url, &config.model)
+    }
+
+    /// Check the server is reachable. `installed` is true when `/api/version` answers.
+    pub async fn detect(&self) -> DetectedCli {
+        let version = async {
+            let resp = reqwest::Client::new()
+                .get(format!("{}/api/version", self.base_url))
+                .timeout(Duration::from_secs(5))
+                .send()
+                .await
+                .ok()?
+                .error_for_status()
+                .ok()?;
+            resp.json::<VersionResponse>().await.ok()
+        }
+        .await;
+
+        DetectedCli {
+            provider: CliProvider::Ollama,
+            installed: version.is_some(),
+            path: None,
+            version: version.map(|v| v.version),
+            http: Some(HttpProvider::Ollama(self.clone())),
+        }
+    }
+
+    /// Send `prompt` as a single user message to `/api/chat` and return the reply.
+    /// With `json` set, Ollama constrains the model output to valid JSON.
+    pub async fn chat(
+        &self,
+        prompt: &str,
+        timeout_duration: Duration,
+        json: bool,
+    ) -> crate::error::Result<String> {
+        let request = ChatRequest {
+            model: &self.model,
+            messages: [ChatMessage {
+                role: "user",
+                content: prompt,
+            }],
+            stream: false,
+            format: json.then_some("json"),
+        };
+
+        tracing::debug!("Calling Ollama {} at {}", self.model, self.base_url);
+
+        let resp = reqwest::Client::new()
+            .post(format!("{}/api/chat", self.base_url))
+            .timeout(timeout_duration)
+            .json(&request)
+            .send()
+            .await
+            .map_err(|e| {
+                if e.is_timeout() {
+                    CoreError::Timeout("Ollama".to_string(), timeout_duration.as_secs())
+                } else {
+                    CoreError::ProviderUnavailable(format!(
+                        "failed to reach Ollama at {}: {}",
+                        self.base_url, e
+                    ))
+                }
+            })?;
+
+        let status = resp.status();
+        let body = resp
+            .text()
+            .await
+            .map_err(|e| CoreError::Api(format!("Failed to read Ollama response: {}", e)))?;
+
+        if !status.is_success() {
+            // Ollama reports failures as {"error": "..."}
+            let message = serde_json::from_str::<serde_json::Value>(&body)
+                .ok()
+                .and_then(|v| v.get("error")?.as_str().map(str::to_string))
+                .unwrap_or(body);
+            return Err(CoreError::Api(format!(
+                "Ollama request failed ({}): {}",
+                status,
+                message.trim()
+            )));
+        }
+
+        let parsed: ChatResponse = serde_json::from_str(&body)
+            .map_err(|e| CoreError::ParseFailed(format!("Ollama response: {}", e)))?;
+        let content = parsed.message.content.trim();
+        if content.is_empty() {
+            return Err(CoreError::Api("Ollama returned empty response".to_string()));
+        }
+        Ok(content.to_string())
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use axum::{
+        http::StatusCode,
+        routing::{get, post},
+        Json, Router,
+    };
+
+    /// Serve `/api/version` and `/api/chat` on a random local port, answering every
+    /// chat request with `status` and `body`. Returns the server's base URL.
+    pub(crate) async fn mock_ollama(status: StatusCode, body: serde_json::Value) -> String {
+        let app = Router::new()
+            .route(
+                "/api/version",
+                get(|| async { Json(serde_json::json!({ "version": "0.5.7" })) }),
+            )
+            .route(
+                "/api/chat",
+                post(move |Json(req): Json<serde_json::Value>| async move {
+                    assert_eq!(req["stream"], false);
+                    assert_eq!(req["messages"][0]["role"], "user");
+                    (status, Json(body))
+                }),
+            );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        format!("http://{}", addr)
+    }
+
+    #[test]
+    fn test_new_trims_trailing_slash() {
+        let provider = OllamaProvider::new("http://localhost:11434/", "llama3.1");
+        assert_eq!(provider.base_url, "http://localhost:11434");
+    }
+
+    #[tokio::test]
+    async fn test_chat_returns_message_content() {
+        let url = mock_ollama(
+            StatusCode::OK,
+            serde_json::json!({
+                "model": "llama3.1",
+                "message": { "role": "assistant", "content": "  hello  " },
+                "done": true
+            }),
+        )
+        .await;
+
+        let reply = OllamaProvider::new(url, "llama3.1")
+            .chat("hi", Duration::from_secs(5), false)
+            .await;
+        assert_eq!(reply.unwrap(), "hello");
+    }
+
+    #[tokio::test]
+    async fn test_chat_maps_error_body() {
+        let url = mock_ollama(
+            StatusCode::NOT_FOUND,
+            serde_json::json!({ "error": "model 'nope' not found" }),
+        )
+        .await;
+
+        let err = OllamaProvider::new(url, "nope")
+            .chat("hi", Duration::from_secs(5), true)
+            .await
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("404"));
+        assert!(err.contains("model 'nope' not found"));
+    }
+
+    #[tokio::test]
+    async fn test_detect_reads_server_version() {
+        let url = mock_ollama(StatusCode::OK, serde_json::json!({})).await;
+        let detected = OllamaProvider::new(url, "llama3.1").detect().await;
+        assert!(detected.installed);
+        assert_eq!(detected.version.as_deref(), Some("0.5.7"));
+    }
+
+    #[tokio::test]
+    async fn test_detect_provider_uses_config_settings() {
+        let url = mock_ollama(StatusCode::OK, serde_json::json!({})).await;
+        let mut ai = crate::config::AiConfig::default();
+        ai.ollama = OllamaConfig {
+            base_url: url.clone(),
+            model: "qwen2.5".to_string(),
+        };
+
+        let detected = crate::ai::cli::detect_provider(CliProvider::Ollama, &ai).await;
+        assert!(detected.installed);
+        match detected.http {
+            Some(HttpProvider::Ollama(provider)) => {
+                assert_eq!(provider, OllamaProvider::new(url, "qwen2.5"))
+            }
+            other => panic!("expected Ollama settings, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_server_not_installed() {
+        // Bind then drop to get a port with nothing listening
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        drop(listener);
+
+        let detected = OllamaProvider::new(format!("http://{}", addr), "llama3.1")
+            .detect()
+            .await;
+        assert_eq!(detected.provider, CliProvider::Ollama);
+        assert!(!detected.installed);
+    }
+}
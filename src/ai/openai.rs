@@ -0,0 +1,366 @@
+//! OpenAI-Compatible Provider
+//!
+//! Runs AI operations against an OpenAI-compatible `/chat/completions` API
+//! (OpenAI, Azure OpenAI v1, vLLM, LiteLLM, ...). Selected with
+//! `provider = "openai"`; URL, model, and API key come from `[ai.openai]`,
+//! with the key falling back to `OPENAI_API_KEY`.
+
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use super::cli::{CliProvider, DetectedCli, HttpProvider};
+use crate::config::OpenAiConfig;
+use crate::error::CoreError;
+
+/// Retries after a 429 before giving up
+const MAX_RATE_LIMIT_RETRIES: u32 = 3;
+
+/// First backoff delay when a 429 carries no `Retry-After`; doubles per retry
+const BASE_BACKOFF: Duration = Duration::from_secs(2);
+
+/// Longest `Retry-After` honoured, so one response can't stall the task queue
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// Connection settings for an OpenAI-compatible API
+#[derive(Clone, PartialEq, Eq)]
+pub struct OpenAiProvider {
+    pub base_url: String,
+    pub model: String,
+    pub api_key: Option<String>,
+}
+
+// Keep the API key out of logs and debug output
+impl std::fmt::Debug for OpenAiProvider {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OpenAiProvider")
+            .field("base_url", &self.base_url)
+            .field("model", &self.model)
+            .field("api_key", &self.api_key.as_ref().map(|_| "<redacted>"))
+            .finish()
+    }
+}
+
+#[derive(Serialize)]
+struct ChatRequest<'a> {
+    model: &'a str,
+    messages: [ChatMessage<'a>; 1],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_format: Option<ResponseFormat>,
+}
+
+#[derive(Serialize)]
+struct ChatMessage<'a> {
+    role: &'a str,
+    content: &'a str,
+}
+
+#[derive(Serialize)]
+struct ResponseFormat {
+    #[serde(rename = "type")]
+    kind: &'static str,
+}
+
+#[derive(Deserialize)]
+struct ChatResponse {
+    choices: Vec<Choice>,
+}
+
+#[derive(Deserialize)]
+struct Choice {
+    message: ChoiceMessage,
+}
+
+#[derive(Deserialize)]
+struct ChoiceMessage {
+    content: Option<String>,
+}
+
+impl OpenAiProvider {
+    pub fn new(
+        base_url: impl Into<String>,
+        model: impl Into<String>,
+        api_key: Option<String>,
+    ) -> Self {
+        OpenAiProvider {
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            model: model.into(),
+            api_key: api_key.filter(|k| !k.is_empty()),
+        }
+    }
+
+    /// Settings from `[ai.openai]`, taking the key from `OPENAI_API_KEY` if unset
+    pub fn from_config(config: &OpenAiConfig) -> Self {
+        let api_key = config
+            .api_key
+            .clone()
+            .filter(|k| !k.is_empty())
+            .or_else(|| std::env::var("OPENAI_API_KEY").ok());
+        Self::new(&config.base_url, &config.model, api_key)
+    }
+
+    /// Available when an API key is configured. No request is made, so a bad
+    /// key or URL surfaces as an error on the first call instead.
+    pub fn detect(&self) -> DetectedCli {
+        DetectedCli {
+            provider: CliProvider::OpenAi,
+            installed: self.api_key.is_some(),
+            path: None,
+            version: None,
+            http: Some(HttpProvider::OpenAi(self.clone())),
+        }
+    }
+
+    /// Send `prompt` as a single user message to `/chat/completions` and return the reply.
+    ///
+    /// With `json` set, requests `response_format: json_object`. A 429 is retried
+    /// up to `MAX_RATE_LIMIT_RETRIES` times, waiting for `Retry-After` or an
+    /// exponential backoff.
+    pub async fn chat(
+        &self,
+        prompt: &str,
+        timeout_duration: Duration,
+        json: bool,
+    ) -> crate::error::Result<String> {
+        let request = ChatRequest {
+            model: &self.model,
+            messages: [ChatMessage {
+                role: "user",
+                content: prompt,
+            }],
+            response_format: json.then_some(ResponseFormat {
+                kind: "json_object",
+            }),
+        };
+        let client = reqwest::Client::new();
+
+        let mut attempt = 0;
+        loop {
+            tracing::debug!("Calling OpenAI {} at {}", self.model, self.base_url);
+
+            let mut builder = client
+                .post(format!("{}/chat/completions", self.base_url))
+                .timeout(timeout_duration)
+                .json(&request);
+            if let Some(key) = &self.api_key {
+                builder = builder.bearer_auth(key);
+            }
+
+            let resp = builder.send().await.map_err(|e| {
+                if e.is_timeout() {
+                    CoreError::Timeout("OpenAI request".to_string(), timeout_duration.as_secs())
+                } else {
+                    CoreError::ProviderUnavailable(format!(
+                        "failed to reach OpenAI API at {}: {}",
+                        self.base_url, e
+                    ))
+                }
+            })?;
+
+            let status = resp.status();
+            if status == reqwest::StatusCode::TOO_MANY_REQUESTS && attempt < MAX_RATE_LIMIT_RETRIES
+            {
+                let delay = retry_after(&resp).unwrap_or(BASE_BACKOFF * 2u32.pow(attempt));
+                attempt += 1;
+                tracing::warn!(
+                    "OpenAI rate limited, retry {}/{} in {:?}",
+                    attempt,
+                    MAX_RATE_LIMIT_RETRIES,
+                    delay
+                );
+                tokio::time::sleep(delay).await;
+                continue;
+            }
+
+            let body = resp.text().await.map_err(|e| {
+                if e.is_timeout() {
+                    CoreError::Timeout("OpenAI request".to_string(), timeout_duration.as_secs())
+                } else {
+                    CoreError::Api(format!("Failed to read OpenAI response: {}", e))
+                }
+            })?;
+
+            if !status.is_success() {
+                // Errors come back as {"error": {"message": "..."}}
+                let message = serde_json::from_str::<serde_json::Value>(&body)
+                    .ok()
+                    .and_then(|v| v["error"]["message"].as_str().map(str::to_string))
+                    .unwrap_or(body);
+                return Err(CoreError::Api(format!(
+                    "OpenAI request failed ({}): {}",
+                    status,
+                    message.trim()
+                )));
+            }
+
+            let parsed: ChatResponse = serde_json::from_str(&body)
+                .map_err(|e| CoreError::ParseFailed(format!("OpenAI response: {}", e)))?;
+            let content = parsed
+                .choices
+                .into_iter()
+                .next()
+                .and_then(|c| c.message.content)
+                .unwrap_or_default();
+            let content = content.trim();
+            if content.is_empty() {
+                return Err(CoreError::Api("OpenAI returned empty response".to_string()));
+            }
+            return Ok(content.to_string());
+        }
+    }
+}
+
+/// Delay requested by a `Retry-After: <seconds>` header, capped at `MAX_BACKOFF`
+fn retry_after(resp: &reqwest::Response) -> Option<Duration> {
+    let secs: u64 = resp
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse()
+        .ok()?;
+    Some(Duration::from_secs(secs).min(MAX_BACKOFF))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use axum::{
+        http::{HeaderMap, StatusCode},
+        response::IntoResponse,
+        routing::post,
+        Json, Router,
+    };
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    /// Serve `/chat/completions` on a random local port. The first `rate_limited`
+    /// requests get a 429 with `Retry-After: 0`; later ones get a completion whose
+    /// message content is `reply`. Returns the base URL and a request counter.
+    pub(crate) async fn mock_openai(
+        reply: &str,
+        rate_limited: usize,
+    ) -> (String, Arc<AtomicUsize>) {
+        let reply = reply.to_string();
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        let app = Router::new().route(
+            "/chat/completions",
+            post(
+                move |headers: HeaderMap, Json(req): Json<serde_json::Value>| async move {
+                    assert_eq!(headers.get("authorization").unwrap(), "Bearer test-key");
+                    assert_eq!(req["messages"][0]["role"], "user");
+                    if counter.fetch_add(1, Ordering::SeqCst) < rate_limited {
+                        return (
+                            StatusCode::TOO_MANY_REQUESTS,
+                            [("retry-after", "0")],
+                            Json(serde_json::json!({ "error": { "message": "slow down" } })),
+                        )
+                            .into_response();
+                    }
+                    Json(serde_json::json!({
+                        "choices": [{ "message": { "role": "assistant", "content": reply } }]
+                    }))
+                    .into_response()
+                },
+            ),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        (format!("http://{}", addr), calls)
+    }
+
+    fn provider(url: &str) -> OpenAiProvider {
+        OpenAiProvider::new(url, "gpt-4o-mini", Some("test-key".to_string()))
+    }
+
+    #[test]
+    fn test_detect_requires_api_key() {
+        let keyless = OpenAiProvider::new("http://localhost:1", "m", Some(String::new()));
+        assert!(!keyless.detect().installed);
+        assert!(provider("http://localhost:1").detect().installed);
+        assert!(!format!("{:?}", provider("http://x")).contains("test-key"));
+    }
+
+    #[tokio::test]
+    async fn test_detect_provider_uses_each_configs_settings() {
+        let config = |base_url: &str, model: &str| {
+            let mut ai = crate::config::AiConfig::default();
+            ai.openai = OpenAiConfig {
+                base_url: base_url.to_string(),
+                model: model.to_string(),
+                api_key: Some("test-key".to_string()),
+            };
+            ai
+        };
+        let first = config("http://first", "gpt-4o-mini");
+        let second = config("http://second", "gpt-4o");
+
+        // Detecting with one config leaves the other's settings alone
+        for (ai, expected) in [
+            (&first, "http://first"),
+            (&second, "http://second"),
+            (&first, "http://first"),
+        ] {
+            let detected = crate::ai::cli::detect_provider(CliProvider::OpenAi, ai).await;
+            match detected.http {
+                Some(HttpProvider::OpenAi(p)) => {
+                    assert_eq!(p.base_url, expected);
+                    assert_eq!(p.model, ai.openai.model);
+                }
+                other => panic!("expected OpenAI settings, got {:?}", other),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_chat_retries_after_rate_limit() {
+        let (url, calls) = mock_openai("done", 2).await;
+        let reply = provider(&url)
+            .chat("hi", Duration::from_secs(5), false)
+            .await;
+        assert_eq!(reply.unwrap(), "done");
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_chat_gives_up_after_max_retries() {
+        let (url, calls) = mock_openai("never", usize::MAX).await;
+        let err = provider(&url)
+            .chat("hi", Duration::from_secs(5), false)
+            .await
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("429"));
+        assert!(err.contains("slow down"));
+        assert_eq!(
+            calls.load(Ordering::SeqCst),
+            MAX_RATE_LIMIT_RETRIES as usize + 1
+        );
+    }
+
+    #[tokio::test]
+    async fn test_chat_timeout_is_reported_distinctly() {
+        let app = Router::new().route(
+            "/chat/completions",
+            post(|| async {
+                tokio::time::sleep(Duration::from_secs(5)).await;
+                "late"
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let err = provider(&format!("http://{}", addr))
+            .chat("hi", Duration::from_millis(200), false)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, CoreError::Timeout(..)), "{}", err);
+        assert!(err.to_string().starts_with("OpenAI request timed out"));
+        // The API reports it as a gateway timeout rather than a generic failure
+        assert_eq!(err.into_response().status(), StatusCode::GATEWAY_TIMEOUT);
+    }
+}
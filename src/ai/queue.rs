@@ -2,15 +2,72 @@
 //!
 //! Limits concurrent AI operations to prevent resource exhaustion.
 //! Uses a semaphore-based queue with configurable concurrency limit.
+//! Running tasks are registered per `(session_id, AiTaskKind)` so they can be cancelled.
 
-use std::sync::Arc;
+use crate::maintenance::MaintenanceMode;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tokio_util::sync::CancellationToken;
+
+/// Kind of AI task running against a session
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AiTaskKind {
+    Title,
+    Memory,
+    Skill,
+    Marker,
+}
+
+type TaskRegistry = Mutex<HashMap<(String, AiTaskKind), Vec<(u64, CancellationToken)>>>;
 
 /// Task queue for limiting concurrent AI operations
 #[derive(Clone)]
 pub struct AiTaskQueue {
     semaphore: Arc<Semaphore>,
     max_concurrent: usize,
+    maintenance: MaintenanceMode,
+    running: Arc<TaskRegistry>,
+    next_task_id: Arc<AtomicU64>,
+}
+
+/// Registration of a running task; unregisters itself when dropped
+pub struct TrackedTask {
+    key: (String, AiTaskKind),
+    id: u64,
+    token: CancellationToken,
+    running: Arc<TaskRegistry>,
+}
+
+impl TrackedTask {
+    /// Run `work` until it finishes or the task is cancelled.
+    ///
+    /// Returns `None` on cancellation. `work` is dropped at that point, which
+    /// aborts in-flight HTTP requests and kills provider subprocesses.
+    pub async fn run<F: Future>(&self, work: F) -> Option<F::Output> {
+        tokio::select! {
+            biased;
+            _ = self.token.cancelled() => None,
+            output = work => Some(output),
+        }
+    }
+}
+
+impl Drop for TrackedTask {
+    fn drop(&mut self) {
+        if let Ok(mut running) = self.running.lock() {
+            if let Some(tasks) = running.get_mut(&self.key) {
+                tasks.retain(|(id, _)| *id != self.id);
+                if tasks.is_empty() {
+                    running.remove(&self.key);
+                }
+            }
+        }
+    }
 }
 
 impl AiTaskQueue {
@@ -19,14 +76,25 @@ impl AiTaskQueue {
         Self {
             semaphore: Arc::new(Semaphore::new(max_concurrent)),
             max_concurrent,
+            maintenance: MaintenanceMode::default(),
+            running: Arc::new(Mutex::new(HashMap::new())),
+            next_task_id: Arc::new(AtomicU64::new(0)),
         }
     }
 
+    /// Defer new tasks while `maintenance` is enabled
+    pub fn with_maintenance(mut self, maintenance: MaintenanceMode) -> Self {
+        self.maintenance = maintenance;
+        self
+    }
+
     /// Acquire a permit to run an AI task
     ///
-    /// This will block if the maximum number of concurrent tasks is reached.
+    /// This will block if the maximum number of concurrent tasks is reached,
+    /// or while maintenance mode is enabled.
     /// The permit is automatically released when dropped.
     pub async fn acquire(&self) -> Result<OwnedSemaphorePermit, String> {
+        self.maintenance.wait_for(false).await;
         self.semaphore
             .clone()
             .acquire_owned()
@@ -43,6 +111,69 @@ impl AiTaskQueue {
     pub fn max_concurrent(&self) -> usize {
         self.max_concurrent
     }
+
+    /// Register a running task so `cancel_session` can stop it
+    pub fn track(&self, session_id: &str, kind: AiTaskKind) -> TrackedTask {
+        let key = (session_id.to_string(), kind);
+        let id = self.next_task_id.fetch_add(1, Ordering::Relaxed);
+        let token = CancellationToken::new();
+        if let Ok(mut running) = self.running.lock() {
+            running
+                .entry(key.clone())
+                .or_default()
+                .push((id, token.clone()));
+        }
+        TrackedTask {
+            key,
+            id,
+            token,
+            running: self.running.clone(),
+        }
+    }
+
+    /// Cancel every running task for a session, returning the kinds cancelled
+    pub fn cancel_session(&self, session_id: &str) -> Vec<AiTaskKind> {
+        let Ok(running) = self.running.lock() else {
+            return Vec::new();
+        };
+        let mut cancelled: Vec<AiTaskKind> = running
+            .iter()
+            .filter(|((sid, _), _)| sid == session_id)
+            .map(|((_, kind), tasks)| {
+                for (_, token) in tasks {
+                    token.cancel();
+                }
+                *kind
+            })
+            .collect();
+        cancelled.sort_by_key(|kind| *kind as u8);
+        cancelled
+    }
+
+    /// Wait up to `grace` for running tasks to release their permits, then
+    /// cancel any still running. Returns whether every task finished in time.
+    ///
+    /// Used on shutdown: once drained, the queue keeps every permit so no new
+    /// task starts while the process exits.
+    pub async fn drain(&self, grace: Duration) -> bool {
+        let all = self.max_concurrent as u32;
+        match tokio::time::timeout(grace, self.semaphore.clone().acquire_many_owned(all)).await {
+            Ok(Ok(permits)) => {
+                permits.forget();
+                true
+            }
+            Ok(Err(_)) => true,
+            Err(_) => {
+                let Ok(running) = self.running.lock() else {
+                    return false;
+                };
+                for (_, token) in running.values().flatten() {
+                    token.cancel();
+                }
+                false
+            }
+        }
+    }
 }
 
 impl Default for AiTaskQueue {
@@ -73,4 +204,84 @@ mod tests {
         // Should have one available permit
         assert_eq!(queue.available_permits(), 1);
     }
+
+    #[tokio::test]
+    async fn test_second_acquire_waits_at_limit_one() {
+        let queue = AiTaskQueue::new(1);
+        let permit = queue.acquire().await.unwrap();
+
+        let waiting = tokio::spawn({
+            let queue = queue.clone();
+            async move { queue.acquire().await.map(|_| ()) }
+        });
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        assert!(!waiting.is_finished());
+
+        drop(permit);
+        tokio::time::timeout(std::time::Duration::from_secs(1), waiting)
+            .await
+            .expect("second acquire should proceed once the permit is released")
+            .unwrap()
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_cancel_releases_permit() {
+        let queue = AiTaskQueue::new(1);
+        let permit = queue.acquire().await.unwrap();
+        let task = queue.track("s1", AiTaskKind::Title);
+
+        let handle = tokio::spawn(async move {
+            let _permit = permit;
+            task.run(tokio::time::sleep(std::time::Duration::from_secs(60)))
+                .await
+        });
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        assert_eq!(queue.available_permits(), 0);
+
+        assert!(queue.cancel_session("other").is_empty());
+        assert_eq!(queue.cancel_session("s1"), vec![AiTaskKind::Title]);
+
+        let outcome = tokio::time::timeout(std::time::Duration::from_secs(1), handle)
+            .await
+            .expect("cancelled task should stop promptly")
+            .unwrap();
+        assert_eq!(outcome, None);
+        assert_eq!(queue.available_permits(), 1);
+        // Finished tasks unregister themselves
+        assert!(queue.cancel_session("s1").is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_drain_waits_for_running_task() {
+        let queue = AiTaskQueue::new(2);
+        let permit = queue.acquire().await.unwrap();
+        let handle = tokio::spawn(async move {
+            let _permit = permit;
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        });
+
+        assert!(queue.drain(Duration::from_secs(5)).await);
+        assert!(handle.is_finished());
+        // No new task starts once drained
+        assert_eq!(queue.available_permits(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_drain_cancels_after_grace() {
+        let queue = AiTaskQueue::new(1);
+        let permit = queue.acquire().await.unwrap();
+        let task = queue.track("s1", AiTaskKind::Memory);
+        let handle = tokio::spawn(async move {
+            let _permit = permit;
+            task.run(tokio::time::sleep(Duration::from_secs(60))).await
+        });
+
+        assert!(!queue.drain(Duration::from_millis(50)).await);
+        let outcome = tokio::time::timeout(Duration::from_secs(1), handle)
+            .await
+            .expect("cancelled task should stop promptly")
+            .unwrap();
+        assert_eq!(outcome, None);
+    }
 }
@@ -30,14 +30,20 @@ pub struct RankingConfig {
 
 impl Default for RankingConfig {
     fn default() -> Self {
+        Self::from(&crate::config::RankingConfig::default())
+    }
+}
+
+impl From<&crate::config::RankingConfig> for RankingConfig {
+    fn from(c: &crate::config::RankingConfig) -> Self {
         RankingConfig {
-            high_threshold: 0.7,
-            min_access_for_high: 3,
-            demotion_threshold: 0.4, // Moderate: demote below 0.4
-            removal_threshold: 0.3,  // Moderate: remove below 0.3
-            stale_days: 90,
-            demotion_age_days: 14, // Moderate: demote after 14 days
-            removal_age_days: 30,  // Moderate: remove after 30 days
+            high_threshold: c.high_threshold,
+            min_access_for_high: c.min_access_for_high,
+            demotion_threshold: c.demotion_threshold,
+            removal_threshold: c.removal_threshold,
+            stale_days: c.stale_days,
+            demotion_age_days: c.demotion_age_days,
+            removal_age_days: c.removal_age_days,
         }
     }
 }
@@ -179,38 +185,35 @@ fn determine_transition(
                 });
             }
         }
-        "low" => {
-            // Promote to high if score improved significantly
-            if score >= 0.6 && memory.access_count >= 5 {
-                return Some(StateTransition {
-                    memory_id: memory.id,
-                    from_state: "low".to_string(),
-                    to_state: "high".to_string(),
-                    score,
-                    reason: format!(
-                        "Score improved to {:.2} with {} accesses",
-                        score, memory.access_count
-                    ),
-                });
-            }
+        // Promote to high if score improved significantly
+        "low" if score >= 0.6 && memory.access_count >= 5 => {
+            return Some(StateTransition {
+                memory_id: memory.id,
+                from_state: "low".to_string(),
+                to_state: "high".to_string(),
+                score,
+                reason: format!(
+                    "Score improved to {:.2} with {} accesses",
+                    score, memory.access_count
+                ),
+            });
         }
-        "high" => {
-            // Demote if stale and not validated
+        // Demote if stale and not validated
+        "high"
             if score < config.demotion_threshold
                 && stale_days > config.stale_days
-                && !memory.is_validated
-            {
-                return Some(StateTransition {
-                    memory_id: memory.id,
-                    from_state: "high".to_string(),
-                    to_state: "low".to_string(),
-                    score,
-                    reason: format!(
-                        "Score dropped to {:.2}, stale for {} days",
-                        score, stale_days
-                    ),
-                });
-            }
+                && !memory.is_validated =>
+        {
+            return Some(StateTransition {
+                memory_id: memory.id,
+                from_state: "high".to_string(),
+                to_state: "low".to_string(),
+                score,
+                reason: format!(
+                    "Score dropped to {:.2}, stale for {} days",
+                    score, stale_days
+                ),
+            });
         }
         _ => {}
     }
@@ -275,6 +278,9 @@ fn apply_transitions(db: &Database, transitions: &[StateTransition]) -> Result<(
             params![transition.to_state, transition.memory_id],
         )
         .map_err(|e| format!("Failed to update memory {}: {}", transition.memory_id, e))?;
+        if transition.to_state == "removed" {
+            db.unindex_memories(&[transition.memory_id]);
+        }
     }
 
     Ok(())
@@ -285,8 +291,8 @@ pub fn rank_project_memories(
     db: &Database,
     project_id: &str,
     batch_size: usize,
+    config: &RankingConfig,
 ) -> Result<RankingResult, String> {
-    let config = RankingConfig::default();
     let weights = ScoreWeights::default();
     let now = Utc::now();
 
@@ -298,7 +304,7 @@ pub fn rank_project_memories(
     let mut transitions = Vec::new();
     for memory in &memories {
         let score = calculate_memory_score(memory, &weights, now);
-        if let Some(transition) = determine_transition(memory, score, &config, now) {
+        if let Some(transition) = determine_transition(memory, score, config, now) {
             transitions.push(transition);
         }
     }
@@ -327,7 +333,11 @@ pub fn rank_project_memories(
 }
 
 /// Rank memories for all projects
-pub fn rank_all_projects(db: &Database, batch_size: usize) -> Vec<RankingResult> {
+pub fn rank_all_projects(
+    db: &Database,
+    batch_size: usize,
+    config: &RankingConfig,
+) -> Vec<RankingResult> {
     let conn = db.conn();
 
     // Get all project IDs
@@ -341,7 +351,7 @@ pub fn rank_all_projects(db: &Database, batch_size: usize) -> Vec<RankingResult>
 
     let mut results = Vec::new();
     for project_id in project_ids {
-        match rank_project_memories(db, &project_id, batch_size) {
+        match rank_project_memories(db, &project_id, batch_size, config) {
             Ok(result) => {
                 if result.memories_evaluated > 0 {
                     tracing::info!(
@@ -494,4 +504,60 @@ mod tests {
             "Validated memories should not be demoted"
         );
     }
+
+    #[test]
+    fn test_configured_thresholds_reach_ranking() {
+        let config: crate::Config = toml::from_str(
+            r#"
+[scheduler.ranking]
+min_access_for_high = 10
+demotion_age_days = 60
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.scheduler.ranking.high_threshold, 0.7);
+        let custom = RankingConfig::from(&config.scheduler.ranking);
+        assert_eq!(custom.min_access_for_high, 10);
+        assert_eq!(custom.demotion_age_days, 60);
+
+        let tmp = tempfile::tempdir().unwrap();
+        let db = Database::new(tmp.path().join("test.db")).unwrap();
+        let now = Utc::now();
+        let recent = (now - Duration::days(1)).to_rfc3339();
+        let month_old = (now - Duration::days(20)).to_rfc3339();
+        let seed = |db: &Database| {
+            db.conn()
+                .execute_batch(&format!(
+                    "DELETE FROM memories;
+                     INSERT OR IGNORE INTO projects (id, name, folder_path, created_at, updated_at)
+                     VALUES ('p1', 'proj', '/tmp/proj', '{recent}', '{recent}');
+                     INSERT OR IGNORE INTO sessions (id, project_id, file_path, ai_tool, created_at, indexed_at)
+                     VALUES ('s1', 'p1', '/tmp/proj/s1.jsonl', 'Claude Code', '{recent}', '{recent}');
+                     INSERT INTO memories (id, project_id, session_id, memory_type, title, content,
+                                           confidence, is_validated, access_count, extracted_at, last_accessed_at)
+                     VALUES (1, 'p1', 's1', 'fact', 'useful', 'x', 0.9, 1, 5, '{month_old}', '{recent}'),
+                            (2, 'p1', 's1', 'fact', 'weak', 'y', 0.1, 0, 1, '{month_old}', '{month_old}');"
+                ))
+                .unwrap();
+        };
+        let to_state = |result: &RankingResult, id: i64| {
+            result
+                .transitions
+                .iter()
+                .find(|t| t.memory_id == id)
+                .map(|t| t.to_state.clone())
+        };
+
+        // Defaults promote the well-used memory and demote the weak one
+        seed(&db);
+        let result = rank_project_memories(&db, "p1", 500, &RankingConfig::default()).unwrap();
+        assert_eq!(to_state(&result, 1).as_deref(), Some("high"));
+        assert_eq!(to_state(&result, 2).as_deref(), Some("low"));
+
+        // Stricter configured thresholds leave both alone
+        seed(&db);
+        let result = rank_project_memories(&db, "p1", 500, &custom).unwrap();
+        assert_eq!(result.memories_evaluated, 2);
+        assert!(result.transitions.is_empty(), "{:?}", result.transitions);
+    }
 }
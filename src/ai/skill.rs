@@ -3,10 +3,11 @@
 //! Extracts workflow patterns (skills) from sessions using AI.
 //! Skills are reusable procedures that can be applied in future sessions.
 
+use crate::config::AiConfig;
 use crate::db::Database;
 use std::sync::Arc;
 
-use super::cli::{detect_provider, run_cli, CliProvider, DetectedCli};
+use super::cli::{detect_provider, extract_json, run_cli, CliProvider, DetectedCli};
 use super::types::SkillExtractionResult;
 
 /// Maximum characters of input to send to AI
@@ -315,6 +316,8 @@ pub async fn extract_skills(
     cli: Option<DetectedCli>,
     force: bool,
     provider: CliProvider,
+    ai: &AiConfig,
+    lenient_json: bool,
 ) -> SkillExtractionResult {
     // Check if already extracted and no significant new content (unless force)
     if !force {
@@ -350,6 +353,8 @@ pub async fn extract_skills(
                 session_id: session_id.to_string(),
                 skills_extracted: 0,
                 duplicates_found: 0,
+                json_repaired: false,
+                provider: None,
                 error: None,
             };
         }
@@ -358,7 +363,7 @@ pub async fn extract_skills(
     // Detect CLI if not provided
     let cli = match cli {
         Some(c) => c,
-        None => detect_provider(provider).await,
+        None => detect_provider(provider, ai).await,
     };
 
     if !cli.installed {
@@ -366,7 +371,9 @@ pub async fn extract_skills(
             session_id: session_id.to_string(),
             skills_extracted: 0,
             duplicates_found: 0,
-            error: Some(format!("{} CLI not installed", cli.provider.display_name())),
+            json_repaired: false,
+            provider: Some(cli.provider),
+            error: Some(cli.provider.unavailable_message()),
         };
     }
 
@@ -378,6 +385,8 @@ pub async fn extract_skills(
                 session_id: session_id.to_string(),
                 skills_extracted: 0,
                 duplicates_found: 0,
+                json_repaired: false,
+                provider: Some(cli.provider),
                 error: Some(e),
             }
         }
@@ -395,24 +404,35 @@ pub async fn extract_skills(
                 session_id: session_id.to_string(),
                 skills_extracted: 0,
                 duplicates_found: 0,
-                error: Some(e),
+                json_repaired: false,
+                provider: Some(cli.provider),
+                error: Some(e.to_string()),
             }
         }
     };
 
     // Parse skills from response
-    let skills = match parse_skills(&output) {
+    let (skills, json_repaired) = match parse_skills(&output, lenient_json) {
         Ok(s) => s,
         Err(e) => {
             return SkillExtractionResult {
                 session_id: session_id.to_string(),
                 skills_extracted: 0,
                 duplicates_found: 0,
+                json_repaired: false,
+                provider: Some(cli.provider),
                 error: Some(format!("Failed to parse skills: {}", e)),
             }
         }
     };
 
+    if json_repaired {
+        tracing::info!(
+            "Skill extraction output for {} needed JSON repair",
+            session_id
+        );
+    }
+
     // Store skills
     let mut extracted = 0;
     let mut duplicates = 0;
@@ -473,34 +493,33 @@ pub async fn extract_skills(
         session_id: session_id.to_string(),
         skills_extracted: extracted,
         duplicates_found: duplicates,
+        json_repaired,
+        provider: Some(cli.provider),
         error: None,
     }
 }
 
-/// Parse skills from AI response
-fn parse_skills(response: &str) -> Result<Vec<RawSkill>, String> {
-    let trimmed = response.trim();
-
-    // If response doesn't contain a JSON array, return empty
-    if !trimmed.contains('[') {
-        return Ok(vec![]);
+/// Parse skills from AI response.
+/// Returns the skills and whether the JSON needed lenient repair.
+fn parse_skills(response: &str, lenient: bool) -> Result<(Vec<RawSkill>, bool), String> {
+    // If response doesn't contain a JSON array, there are no skills
+    if !response.contains('[') {
+        return Ok((vec![], false));
     }
 
-    // Extract JSON array from response
-    let start = trimmed.find('[').unwrap();
-    let end = match trimmed.rfind(']') {
-        Some(e) => e,
-        None => return Ok(vec![]),
-    };
-
-    let json_str = &trimmed[start..=end];
+    let extracted = extract_json(response, lenient)?;
 
-    // Handle empty array
-    if json_str.trim() == "[]" {
-        return Ok(vec![]);
-    }
+    // Accept either a bare array or an object with a "skills" field
+    let value = match extracted.value {
+        serde_json::Value::Object(mut map) if map.contains_key("skills") => {
+            map.remove("skills").unwrap_or_default()
+        }
+        value => value,
+    };
 
-    serde_json::from_str(json_str).map_err(|e| format!("Failed to parse skills JSON: {}", e))
+    serde_json::from_value::<Vec<RawSkill>>(value)
+        .map(|skills| (skills, extracted.repaired))
+        .map_err(|e| format!("Failed to parse skills JSON: {}", e))
 }
 
 #[cfg(test)]
@@ -510,7 +529,7 @@ mod tests {
     #[test]
     fn test_parse_skills_array() {
         let response = r#"[{"name": "reviewing-prs", "description": "Reviews PRs", "steps": ["Fetch diff", "Analyze"], "confidence": 0.95}]"#;
-        let skills = parse_skills(response).unwrap();
+        let skills = parse_skills(response, true).unwrap().0;
         assert_eq!(skills.len(), 1);
         assert_eq!(skills[0].name, "reviewing-prs");
     }
@@ -518,14 +537,14 @@ mod tests {
     #[test]
     fn test_parse_skills_empty() {
         let response = "[]";
-        let skills = parse_skills(response).unwrap();
+        let skills = parse_skills(response, true).unwrap().0;
         assert!(skills.is_empty());
     }
 
     #[test]
     fn test_parse_skills_no_json() {
         let response = "No skills found in this session.";
-        let skills = parse_skills(response).unwrap();
+        let skills = parse_skills(response, true).unwrap().0;
         assert!(skills.is_empty());
     }
 
@@ -534,8 +553,21 @@ mod tests {
         let response = r#"Here are the skills:
 [{"name": "deploying-apps", "description": "Deploys apps", "steps": ["Build", "Push"], "confidence": 0.9}]
 That's all!"#;
-        let skills = parse_skills(response).unwrap();
+        let skills = parse_skills(response, true).unwrap().0;
         assert_eq!(skills.len(), 1);
         assert_eq!(skills[0].name, "deploying-apps");
     }
+
+    #[test]
+    fn test_parse_skills_fenced_trailing_comma_repaired() {
+        let response = r#"Sure:
+```json
+[{"name": "running-tests", "description": "Runs tests", "steps": ["cargo test",], "confidence": 0.9},]
+```"#;
+        assert!(parse_skills(response, false).is_err());
+        let (skills, repaired) = parse_skills(response, true).unwrap();
+        assert_eq!(skills.len(), 1);
+        assert_eq!(skills[0].steps, vec!["cargo test"]);
+        assert!(repaired);
+    }
 }
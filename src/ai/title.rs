@@ -2,6 +2,7 @@
 //!
 //! Generates concise titles for sessions using AI.
 
+use crate::config::AiConfig;
 use crate::db::Database;
 use std::sync::Arc;
 
@@ -96,18 +97,20 @@ pub async fn generate_title(
     session_id: &str,
     cli: Option<DetectedCli>,
     provider: CliProvider,
+    ai: &AiConfig,
 ) -> TitleGenerationResult {
     // Detect CLI if not provided
     let cli = match cli {
         Some(c) => c,
-        None => detect_provider(provider).await,
+        None => detect_provider(provider, ai).await,
     };
 
     if !cli.installed {
         return TitleGenerationResult {
             session_id: session_id.to_string(),
             title: None,
-            error: Some(format!("{} CLI not installed", cli.provider.display_name())),
+            provider: Some(cli.provider),
+            error: Some(cli.provider.unavailable_message()),
         };
     }
 
@@ -118,6 +121,7 @@ pub async fn generate_title(
             return TitleGenerationResult {
                 session_id: session_id.to_string(),
                 title: None,
+                provider: Some(cli.provider),
                 error: Some(e),
             }
         }
@@ -135,13 +139,15 @@ pub async fn generate_title(
             TitleGenerationResult {
                 session_id: session_id.to_string(),
                 title: Some(title),
+                provider: Some(cli.provider),
                 error: None,
             }
         }
         Err(e) => TitleGenerationResult {
             session_id: session_id.to_string(),
             title: None,
-            error: Some(e),
+            provider: Some(cli.provider),
+            error: Some(e.to_string()),
         },
     }
 }
@@ -192,17 +198,19 @@ pub async fn generate_title_from_text(
     first_messages: &str,
     cli: Option<DetectedCli>,
     provider: CliProvider,
+    ai: &AiConfig,
 ) -> TitleGenerationResult {
     let cli = match cli {
         Some(c) => c,
-        None => detect_provider(provider).await,
+        None => detect_provider(provider, ai).await,
     };
 
     if !cli.installed {
         return TitleGenerationResult {
             session_id: session_id.to_string(),
             title: None,
-            error: Some(format!("{} CLI not installed", cli.provider.display_name())),
+            provider: Some(cli.provider),
+            error: Some(cli.provider.unavailable_message()),
         };
     }
 
@@ -212,12 +220,14 @@ pub async fn generate_title_from_text(
         Ok(output) => TitleGenerationResult {
             session_id: session_id.to_string(),
             title: Some(clean_title(&output)),
+            provider: Some(cli.provider),
             error: None,
         },
         Err(e) => TitleGenerationResult {
             session_id: session_id.to_string(),
             title: None,
-            error: Some(e),
+            provider: Some(cli.provider),
+            error: Some(e.to_string()),
         },
     }
 }
@@ -239,4 +249,96 @@ mod tests {
         assert!(cleaned.len() <= MAX_TITLE_LENGTH);
         assert!(cleaned.ends_with("..."));
     }
+
+    #[tokio::test]
+    async fn test_generate_title_with_ollama() {
+        use crate::ai::ollama::{tests::mock_ollama, OllamaProvider};
+
+        let url = mock_ollama(
+            axum::http::StatusCode::OK,
+            serde_json::json!({
+                "model": "llama3.1",
+                "message": {
+                    "role": "assistant",
+                    "content": "\"Add Ollama provider for AI titles\"\n"
+                },
+                "done": true
+            }),
+        )
+        .await;
+        let cli = OllamaProvider::new(url, "llama3.1").detect().await;
+
+        let result = generate_title_from_text(
+            "s1",
+            "user: let's call a local model for titles",
+            Some(cli),
+            CliProvider::Ollama,
+            &AiConfig::default(),
+        )
+        .await;
+        assert_eq!(result.error, None);
+        assert_eq!(
+            result.title.as_deref(),
+            Some("Add Ollama provider for AI titles")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_title_falls_back_to_next_provider() {
+        use crate::ai::cli::with_fallback;
+        use crate::ai::ollama::{tests::mock_ollama, OllamaProvider};
+        use crate::ai::openai::{tests::mock_openai, OpenAiProvider};
+        use std::sync::Mutex;
+
+        #[derive(Clone, Default)]
+        struct Logs(Arc<Mutex<Vec<u8>>>);
+        impl std::io::Write for Logs {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                self.0.lock().unwrap().extend_from_slice(buf);
+                Ok(buf.len())
+            }
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+        let logs = Logs::default();
+        let writer = logs.clone();
+        let _subscriber = tracing::subscriber::set_default(
+            tracing_subscriber::fmt()
+                .with_writer(move || writer.clone())
+                .with_ansi(false)
+                .finish(),
+        );
+
+        // Ollama is up but failing; OpenAI answers
+        let ollama_url = mock_ollama(
+            axum::http::StatusCode::SERVICE_UNAVAILABLE,
+            serde_json::json!({ "error": "model is loading" }),
+        )
+        .await;
+        let ollama = OllamaProvider::new(ollama_url, "llama3.1").detect().await;
+        let (openai_url, _) = mock_openai("Fall back to OpenAI for titles", 0).await;
+        let openai =
+            OpenAiProvider::new(openai_url, "gpt-4o-mini", Some("test-key".to_string())).detect();
+
+        let ai = AiConfig::default();
+        let chain = [CliProvider::Ollama, CliProvider::OpenAi];
+        let result = with_fallback("Title generation", &chain, |provider| {
+            let cli = match provider {
+                CliProvider::Ollama => ollama.clone(),
+                _ => openai.clone(),
+            };
+            generate_title_from_text("s1", "user: keep titles working", Some(cli), provider, &ai)
+        })
+        .await;
+
+        assert_eq!(result.error, None);
+        assert_eq!(
+            result.title.as_deref(),
+            Some("Fall back to OpenAI for titles")
+        );
+        assert_eq!(result.provider, Some(CliProvider::OpenAi));
+        let logs = String::from_utf8(logs.0.lock().unwrap().clone()).unwrap();
+        assert!(logs.contains("Title generation with Ollama failed, falling back to OpenAI"));
+    }
 }
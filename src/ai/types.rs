@@ -4,6 +4,8 @@
 
 use serde::{Deserialize, Serialize};
 
+use super::cli::{CliProvider, FallbackResult};
+
 /// AI-related events for SSE broadcast
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
@@ -11,27 +13,47 @@ pub enum AiEvent {
     /// Title generation started
     TitleStart { session_id: String },
     /// Title generation completed successfully
-    TitleComplete { session_id: String, title: String },
+    TitleComplete {
+        session_id: String,
+        title: String,
+        provider: String,
+    },
     /// Title generation failed
     TitleError { session_id: String, error: String },
+    /// Title generation cancelled
+    TitleCancelled { session_id: String },
     /// Memory extraction started
     MemoryStart { session_id: String },
     /// Memory extraction completed
-    MemoryComplete { session_id: String, count: usize },
+    MemoryComplete {
+        session_id: String,
+        count: usize,
+        provider: String,
+    },
     /// Memory extraction failed
     MemoryError { session_id: String, error: String },
+    /// Memory extraction cancelled
+    MemoryCancelled { session_id: String },
     /// Skill extraction started
     SkillStart { session_id: String },
     /// Skill extraction completed
-    SkillComplete { session_id: String, count: usize },
+    SkillComplete {
+        session_id: String,
+        count: usize,
+        provider: String,
+    },
     /// Skill extraction failed
     SkillError { session_id: String, error: String },
+    /// Skill extraction cancelled
+    SkillCancelled { session_id: String },
     /// Marker detection started
     MarkerStart { session_id: String },
     /// Marker detection completed
     MarkerComplete { session_id: String, count: usize },
     /// Marker detection failed
     MarkerError { session_id: String, error: String },
+    /// Marker detection cancelled
+    MarkerCancelled { session_id: String },
     /// Export generation started
     ExportStart { session_id: String, format: String },
     /// Export generation completed
@@ -48,6 +70,14 @@ pub enum AiEvent {
         format: String,
         error: String,
     },
+    /// Embedding backfill finished a batch
+    EmbeddingProgress { done: usize, total: usize },
+    /// Embedding backfill finished
+    EmbeddingComplete {
+        embedded: usize,
+        failed: usize,
+        total: usize,
+    },
 }
 
 impl AiEvent {
@@ -57,18 +87,24 @@ impl AiEvent {
             AiEvent::TitleStart { .. } => "ai:title:start",
             AiEvent::TitleComplete { .. } => "ai:title:complete",
             AiEvent::TitleError { .. } => "ai:title:error",
+            AiEvent::TitleCancelled { .. } => "ai:title:cancelled",
             AiEvent::MemoryStart { .. } => "ai:memory:start",
             AiEvent::MemoryComplete { .. } => "ai:memory:complete",
             AiEvent::MemoryError { .. } => "ai:memory:error",
+            AiEvent::MemoryCancelled { .. } => "ai:memory:cancelled",
             AiEvent::SkillStart { .. } => "ai:skill:start",
             AiEvent::SkillComplete { .. } => "ai:skill:complete",
             AiEvent::SkillError { .. } => "ai:skill:error",
+            AiEvent::SkillCancelled { .. } => "ai:skill:cancelled",
             AiEvent::MarkerStart { .. } => "ai:markers:start",
             AiEvent::MarkerComplete { .. } => "ai:markers:complete",
             AiEvent::MarkerError { .. } => "ai:markers:error",
+            AiEvent::MarkerCancelled { .. } => "ai:markers:cancelled",
             AiEvent::ExportStart { .. } => "ai:export:start",
             AiEvent::ExportComplete { .. } => "ai:export:complete",
             AiEvent::ExportError { .. } => "ai:export:error",
+            AiEvent::EmbeddingProgress { .. } => "ai:embedding:progress",
+            AiEvent::EmbeddingComplete { .. } => "ai:embedding:complete",
         }
     }
 }
@@ -78,24 +114,78 @@ impl AiEvent {
 pub struct TitleGenerationResult {
     pub session_id: String,
     pub title: Option<String>,
+    /// Provider that produced the result
+    #[serde(default)]
+    pub provider: Option<CliProvider>,
     pub error: Option<String>,
 }
 
+impl FallbackResult for TitleGenerationResult {
+    fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+}
+
 /// Result of memory extraction
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MemoryExtractionResult {
     pub session_id: String,
     pub memories_extracted: usize,
     pub memories_skipped: usize,
+    /// AI output only parsed after lenient JSON repair
+    #[serde(default)]
+    pub json_repaired: bool,
+    /// Provider that produced the result
+    #[serde(default)]
+    pub provider: Option<CliProvider>,
     pub error: Option<String>,
 }
 
+impl FallbackResult for MemoryExtractionResult {
+    fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+}
+
 /// Result of skill extraction
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SkillExtractionResult {
     pub session_id: String,
     pub skills_extracted: usize,
     pub duplicates_found: usize,
+    /// AI output only parsed after lenient JSON repair
+    #[serde(default)]
+    pub json_repaired: bool,
+    /// Provider that produced the result
+    #[serde(default)]
+    pub provider: Option<CliProvider>,
+    pub error: Option<String>,
+}
+
+impl FallbackResult for SkillExtractionResult {
+    fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+}
+
+/// A request the user made in a session
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionIntent {
+    /// Message the request was made in, if known
+    pub sequence_num: Option<i64>,
+    pub intent: String,
+}
+
+/// Result of intent extraction
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IntentExtractionResult {
+    pub session_id: String,
+    pub intents: Vec<SessionIntent>,
+    /// "ai", or "heuristic" when AI is off or failed
+    pub source: String,
+    /// AI output only parsed after lenient JSON repair
+    #[serde(default)]
+    pub json_repaired: bool,
     pub error: Option<String>,
 }
 
@@ -0,0 +1,1020 @@
+//! Admin route handlers
+//!
+//! Operational endpoints for self-hosters: maintenance mode, config reload and
+//! (DB mode only) backups, full-text reindexing, on-demand scheduler runs, and
+//! session imports.
+
+use super::AppState;
+use crate::config::Config;
+use crate::scheduler::{RunTaskError, SchedulerRun};
+use axum::{
+    extract::{Path as UrlPath, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use std::path::{Component, Path, PathBuf};
+
+#[derive(Debug, Deserialize)]
+pub struct MaintenanceRequest {
+    pub enabled: bool,
+}
+
+/// GET /api/admin/maintenance
+pub async fn get_maintenance(State(state): State<AppState>) -> impl IntoResponse {
+    Json(serde_json::json!({ "enabled": state.maintenance.is_enabled() }))
+}
+
+/// POST /api/admin/maintenance
+/// Pause (or resume) the watcher, scheduler, and AI task queue.
+/// File changes seen while paused are processed on resume.
+pub async fn set_maintenance(
+    State(state): State<AppState>,
+    Json(req): Json<MaintenanceRequest>,
+) -> impl IntoResponse {
+    let previous = state.maintenance.set(req.enabled);
+    Json(serde_json::json!({
+        "enabled": req.enabled,
+        "previous": previous,
+    }))
+}
+
+/// POST /api/admin/reload-config
+/// Re-read the config file and apply watch path, AI feature, and scheduler changes.
+pub async fn reload_config(State(state): State<AppState>) -> impl IntoResponse {
+    let Some(reloader) = &state.config_reload else {
+        return (
+            StatusCode::NOT_IMPLEMENTED,
+            Json(serde_json::json!({ "error": "Config reload is not available" })),
+        )
+            .into_response();
+    };
+    match reloader.reload().await {
+        Ok(summary) => Json(summary).into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({ "error": format!("Config reload failed: {}", e) })),
+        )
+            .into_response(),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BackupRequest {
+    /// Destination file, absolute or relative to `database.backup_dir`
+    pub path: String,
+}
+
+/// Resolve a requested backup path, rejecting anything outside `backup_dir`
+fn resolve_backup_path(backup_dir: &Path, requested: &str) -> Result<PathBuf, String> {
+    let requested = Path::new(requested);
+    if requested
+        .components()
+        .any(|c| matches!(c, Component::ParentDir))
+    {
+        return Err("Backup path must not contain '..'".to_string());
+    }
+
+    let path = backup_dir.join(requested);
+    if !path.starts_with(backup_dir) || path == backup_dir {
+        return Err(format!(
+            "Backup path must be a file inside {}",
+            backup_dir.display()
+        ));
+    }
+    Ok(path)
+}
+
+/// POST /api/admin/backup
+/// Write a consistent copy of the live database using SQLite's online backup API.
+pub async fn backup_database(
+    State(state): State<AppState>,
+    Json(req): Json<BackupRequest>,
+) -> impl IntoResponse {
+    let db = match &state.db {
+        Some(db) => db.clone(),
+        None => {
+            return (
+                StatusCode::NOT_IMPLEMENTED,
+                Json(serde_json::json!({ "error": "Not available in ephemeral mode" })),
+            )
+                .into_response()
+        }
+    };
+
+    let backup_dir = Config::from_file(&state.config_path)
+        .unwrap_or_default()
+        .backup_dir();
+    let path = match resolve_backup_path(&backup_dir, &req.path) {
+        Ok(p) => p,
+        Err(e) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({ "error": e })),
+            )
+                .into_response()
+        }
+    };
+    if path.exists() {
+        return (
+            StatusCode::CONFLICT,
+            Json(serde_json::json!({ "error": format!("{} already exists", path.display()) })),
+        )
+            .into_response();
+    }
+
+    let dest = path.clone();
+    let result = db
+        .with_read_conn(move |conn| {
+            let started = std::time::Instant::now();
+            if let Some(parent) = dest.parent() {
+                std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+            }
+            conn.backup(rusqlite::DatabaseName::Main, &dest, None)
+                .map_err(|e| e.to_string())?;
+            let size = std::fs::metadata(&dest).map_err(|e| e.to_string())?.len();
+            Ok::<_, String>((size, started.elapsed().as_millis() as u64))
+        })
+        .await;
+
+    match result {
+        Ok((size_bytes, duration_ms)) => {
+            tracing::info!(
+                "Database backed up to {} ({} bytes, {}ms)",
+                path.display(),
+                size_bytes,
+                duration_ms
+            );
+            Json(serde_json::json!({
+                "path": path.to_string_lossy(),
+                "size_bytes": size_bytes,
+                "duration_ms": duration_ms,
+            }))
+            .into_response()
+        }
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({ "error": format!("Backup failed: {}", e) })),
+        )
+            .into_response(),
+    }
+}
+
+/// POST /api/admin/reindex-fts
+/// Rebuild the full-text search indexes from the message and memory tables.
+pub async fn reindex_fts(State(state): State<AppState>) -> impl IntoResponse {
+    let db = match &state.db {
+        Some(db) => db.clone(),
+        None => {
+            return (
+                StatusCode::NOT_IMPLEMENTED,
+                Json(serde_json::json!({ "error": "Not available in ephemeral mode" })),
+            )
+                .into_response()
+        }
+    };
+
+    let started = std::time::Instant::now();
+    match db.with_conn(crate::db::schema::rebuild_fts).await {
+        Ok(indexes) => {
+            let duration_ms = started.elapsed().as_millis() as u64;
+            tracing::info!("Full-text indexes rebuilt in {}ms", duration_ms);
+            Json(serde_json::json!({
+                "indexes": indexes,
+                "duration_ms": duration_ms,
+            }))
+            .into_response()
+        }
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({ "error": format!("Reindex failed: {}", e) })),
+        )
+            .into_response(),
+    }
+}
+
+/// POST /api/admin/scheduler/run/:task
+/// Run one scheduler task now and return its `TaskResult`.
+pub async fn run_scheduler_task(
+    State(state): State<AppState>,
+    UrlPath(task): UrlPath<String>,
+) -> impl IntoResponse {
+    let db = match &state.db {
+        Some(db) => db.clone(),
+        None => {
+            return (
+                StatusCode::NOT_IMPLEMENTED,
+                Json(serde_json::json!({ "error": "Not available in ephemeral mode" })),
+            )
+                .into_response()
+        }
+    };
+
+    let config = Config::from_file(&state.config_path).unwrap_or_default();
+    match crate::scheduler::run_task(
+        &task,
+        &config,
+        db,
+        state.event_tx.clone(),
+        &state.running_tasks,
+    )
+    .await
+    {
+        Ok(result) => Json(result).into_response(),
+        Err(e) => {
+            let status = match e {
+                RunTaskError::UnknownTask(_) => StatusCode::NOT_FOUND,
+                RunTaskError::Inactive(_) | RunTaskError::AlreadyRunning(_) => StatusCode::CONFLICT,
+            };
+            (status, Json(serde_json::json!({ "error": e.to_string() }))).into_response()
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SchedulerHistoryQuery {
+    /// Only runs of this task
+    pub task: Option<String>,
+    pub limit: Option<usize>,
+}
+
+/// GET /api/admin/scheduler/history
+/// Recorded scheduler runs, newest first.
+pub async fn get_scheduler_history(
+    State(state): State<AppState>,
+    Query(query): Query<SchedulerHistoryQuery>,
+) -> impl IntoResponse {
+    let db = match &state.db {
+        Some(db) => db.clone(),
+        None => {
+            return (
+                StatusCode::NOT_IMPLEMENTED,
+                Json(serde_json::json!({ "error": "Not available in ephemeral mode" })),
+            )
+                .into_response()
+        }
+    };
+
+    let limit = query.limit.unwrap_or(50).clamp(1, 500) as i64;
+    let task = query.task;
+    let result = db
+        .with_read_conn(move |conn| {
+            let mut stmt = conn.prepare(
+                "SELECT id, task_name, started_at, finished_at, items_processed, items_affected, errors, detail, skipped
+                 FROM scheduler_runs
+                 WHERE ?1 IS NULL OR task_name = ?1
+                 ORDER BY id DESC
+                 LIMIT ?2",
+            )?;
+            let rows = stmt.query_map(rusqlite::params![task, limit], |row| {
+                Ok(SchedulerRun {
+                    id: row.get(0)?,
+                    task_name: row.get(1)?,
+                    started_at: row.get(2)?,
+                    finished_at: row.get(3)?,
+                    items_processed: row.get::<_, i64>(4)? as usize,
+                    items_affected: row.get::<_, i64>(5)? as usize,
+                    errors: row.get::<_, i64>(6)? as usize,
+                    detail: row.get(7)?,
+                    skipped: row.get(8)?,
+                })
+            })?;
+            rows.collect::<rusqlite::Result<Vec<_>>>()
+        })
+        .await;
+
+    match result {
+        Ok(runs) => Json(serde_json::json!({ "runs": runs })).into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({ "error": format!("Failed to load history: {}", e) })),
+        )
+            .into_response(),
+    }
+}
+
+/// How `POST /api/admin/import` treats rows that already exist
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportMode {
+    /// Keep the existing row
+    #[default]
+    Skip,
+    /// Replace the existing row with the imported one
+    Overwrite,
+}
+
+/// A session bundle from `GET /api/sessions/:id/export?format=json`, plus the
+/// project to import it into
+#[derive(Debug, Deserialize)]
+pub struct ImportRequest {
+    /// Target project (ID or folder-path suffix). Imported rows are moved into it.
+    pub project_id: String,
+    #[serde(default)]
+    pub mode: ImportMode,
+    pub session: ImportSession,
+    #[serde(default)]
+    pub messages: Vec<ImportMessage>,
+    #[serde(default)]
+    pub memories: Vec<ImportMemory>,
+    #[serde(default)]
+    pub skills: Vec<ImportSkill>,
+    #[serde(default)]
+    pub markers: Vec<crate::ai::marker::SessionMarker>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ImportSession {
+    pub id: String,
+    pub file_path: String,
+    pub title: Option<String>,
+    pub ai_tool: String,
+    pub message_count: i64,
+    pub duration_ms: Option<i64>,
+    pub has_code: bool,
+    pub has_errors: bool,
+    #[serde(default)]
+    pub is_hidden: bool,
+    pub created_at: String,
+    pub indexed_at: String,
+    pub parent_session_id: Option<String>,
+    #[serde(default)]
+    pub parse_errors: i64,
+    #[serde(default)]
+    pub parse_error_lines: Vec<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ImportMessage {
+    pub sequence_num: i64,
+    pub role: String,
+    pub content_preview: Option<String>,
+    pub search_content: Option<String>,
+    pub has_code: bool,
+    pub has_error: bool,
+    pub has_file_changes: bool,
+    pub tool_name: Option<String>,
+    pub tool_type: Option<String>,
+    pub tool_summary: Option<String>,
+    pub byte_offset: i64,
+    pub byte_length: i64,
+    pub input_tokens: Option<i64>,
+    pub output_tokens: Option<i64>,
+    pub cache_read_tokens: Option<i64>,
+    pub cache_creation_tokens: Option<i64>,
+    pub model: Option<String>,
+    pub timestamp: String,
+    pub parent_sequence_num: Option<i64>,
+    pub code_language: Option<String>,
+    /// Absent in bundles exported before token estimation existed
+    #[serde(default)]
+    pub tokens_estimated: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ImportMemory {
+    pub memory_type: String,
+    pub title: String,
+    pub content: String,
+    pub context: Option<String>,
+    /// JSON array encoded as a string, as exported
+    pub tags: String,
+    pub confidence: f64,
+    pub is_validated: bool,
+    pub state: String,
+    pub extracted_at: String,
+    pub file_reference: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ImportSkill {
+    /// Session the skill was extracted from, which may not be the exported one
+    pub session_id: String,
+    pub name: String,
+    pub description: String,
+    pub steps: Vec<String>,
+    pub confidence: f64,
+    pub extracted_at: String,
+}
+
+/// Rows per kind
+#[derive(Debug, Default, Serialize)]
+pub struct ImportCounts {
+    pub session: usize,
+    pub messages: usize,
+    pub memories: usize,
+    pub skills: usize,
+    pub markers: usize,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct ImportSummary {
+    /// Rows inserted or overwritten
+    pub imported: ImportCounts,
+    /// Rows left alone because they already existed (`skip` mode)
+    pub skipped: ImportCounts,
+}
+
+/// Write one imported row into `columns` of `table`. A row already matching on
+/// the first `key_len` columns (the row's natural key) is left alone in `skip`
+/// mode and updated in place in `overwrite` mode; otherwise a new row is
+/// inserted, getting a fresh local id. Returns whether the row was written and
+/// its local rowid.
+fn import_row(
+    conn: &rusqlite::Connection,
+    table: &str,
+    columns: &[&str],
+    key_len: usize,
+    values: &[&dyn rusqlite::ToSql],
+    mode: ImportMode,
+) -> rusqlite::Result<(bool, i64)> {
+    use rusqlite::OptionalExtension;
+
+    let key: Vec<String> = columns[..key_len]
+        .iter()
+        .enumerate()
+        .map(|(i, c)| format!("{} IS ?{}", c, i + 1))
+        .collect();
+    let existing: Option<i64> = conn
+        .prepare_cached(&format!(
+            "SELECT rowid FROM {} WHERE {}",
+            table,
+            key.join(" AND ")
+        ))?
+        .query_row(&values[..key_len], |row| row.get(0))
+        .optional()?;
+
+    match (existing, mode) {
+        (Some(rowid), ImportMode::Skip) => Ok((false, rowid)),
+        (Some(rowid), ImportMode::Overwrite) => {
+            let set: Vec<String> = columns
+                .iter()
+                .enumerate()
+                .skip(key_len)
+                .map(|(i, c)| format!("{} = ?{}", c, i + 1))
+                .collect();
+            let mut params = values.to_vec();
+            params.push(&rowid);
+            conn.prepare_cached(&format!(
+                "UPDATE {} SET {} WHERE rowid = ?{}",
+                table,
+                set.join(", "),
+                params.len()
+            ))?
+            .execute(params.as_slice())?;
+            Ok((true, rowid))
+        }
+        (None, _) => {
+            let placeholders: Vec<String> =
+                (1..=columns.len()).map(|i| format!("?{}", i)).collect();
+            conn.prepare_cached(&format!(
+                "INSERT INTO {} ({}) VALUES ({})",
+                table,
+                columns.join(", "),
+                placeholders.join(", ")
+            ))?
+            .execute(values)?;
+            Ok((true, conn.last_insert_rowid()))
+        }
+    }
+}
+
+/// Write a session bundle into `project_id`. The session keeps its id; messages,
+/// memories, skills and markers get fresh local ids and are matched against
+/// existing rows by natural key: `(session_id, sequence_num)` for messages,
+/// `(session_id, memory_type, title)` for memories, `(project_id, name)` for
+/// skills and `(session_id, event_index, marker_type, label)` for markers.
+/// Overwriting replaces the session's messages and markers wholesale; memories
+/// and skills are only replaced by key, so ones extracted locally are kept.
+fn import_bundle(
+    conn: &rusqlite::Connection,
+    project_id: &str,
+    req: &ImportRequest,
+) -> rusqlite::Result<ImportSummary> {
+    use rusqlite::params;
+
+    let mode = req.mode;
+    let session = &req.session;
+    let mut summary = ImportSummary::default();
+    let mut tally = |written: bool, count: fn(&mut ImportCounts) -> &mut usize| {
+        let counts = if written {
+            &mut summary.imported
+        } else {
+            &mut summary.skipped
+        };
+        *count(counts) += 1;
+    };
+
+    let parse_error_lines = (!session.parse_error_lines.is_empty())
+        .then(|| serde_json::to_string(&session.parse_error_lines).unwrap_or_default());
+    let (written, _) = import_row(
+        conn,
+        "sessions",
+        &[
+            "id",
+            "project_id",
+            "file_path",
+            "title",
+            "ai_tool",
+            "message_count",
+            "duration_ms",
+            "has_code",
+            "has_errors",
+            "is_hidden",
+            "created_at",
+            "indexed_at",
+            "parent_session_id",
+            "parse_errors",
+            "parse_error_lines",
+        ],
+        1,
+        params![
+            session.id,
+            project_id,
+            session.file_path,
+            session.title,
+            session.ai_tool,
+            session.message_count,
+            session.duration_ms,
+            session.has_code,
+            session.has_errors,
+            session.is_hidden,
+            session.created_at,
+            session.indexed_at,
+            session.parent_session_id,
+            session.parse_errors,
+            parse_error_lines,
+        ],
+        mode,
+    )?;
+    tally(written, |c| &mut c.session);
+
+    if mode == ImportMode::Overwrite {
+        conn.execute(
+            "DELETE FROM session_messages WHERE session_id = ?",
+            [&session.id],
+        )?;
+        conn.execute(
+            "DELETE FROM session_markers WHERE session_id = ?",
+            [&session.id],
+        )?;
+    }
+
+    for m in &req.messages {
+        let (written, _) = import_row(
+            conn,
+            "session_messages",
+            &[
+                "session_id",
+                "sequence_num",
+                "role",
+                "content_preview",
+                "search_content",
+                "has_code",
+                "has_error",
+                "has_file_changes",
+                "tool_name",
+                "tool_type",
+                "tool_summary",
+                "byte_offset",
+                "byte_length",
+                "input_tokens",
+                "output_tokens",
+                "cache_read_tokens",
+                "cache_creation_tokens",
+                "model",
+                "timestamp",
+                "parent_sequence_num",
+                "code_language",
+                "tokens_estimated",
+            ],
+            2,
+            params![
+                session.id,
+                m.sequence_num,
+                m.role,
+                m.content_preview,
+                m.search_content,
+                m.has_code,
+                m.has_error,
+                m.has_file_changes,
+                m.tool_name,
+                m.tool_type,
+                m.tool_summary,
+                m.byte_offset,
+                m.byte_length,
+                m.input_tokens,
+                m.output_tokens,
+                m.cache_read_tokens,
+                m.cache_creation_tokens,
+                m.model,
+                m.timestamp,
+                m.parent_sequence_num,
+                m.code_language,
+                m.tokens_estimated,
+            ],
+            mode,
+        )?;
+        tally(written, |c| &mut c.messages);
+    }
+
+    for m in &req.memories {
+        let (written, _) = import_row(
+            conn,
+            "memories",
+            &[
+                "session_id",
+                "memory_type",
+                "title",
+                "project_id",
+                "content",
+                "context",
+                "tags",
+                "confidence",
+                "is_validated",
+                "state",
+                "extracted_at",
+                "file_reference",
+            ],
+            3,
+            params![
+                session.id,
+                m.memory_type,
+                m.title,
+                project_id,
+                m.content,
+                m.context,
+                m.tags,
+                m.confidence,
+                m.is_validated,
+                m.state,
+                m.extracted_at,
+                m.file_reference,
+            ],
+            mode,
+        )?;
+        tally(written, |c| &mut c.memories);
+    }
+
+    for skill in &req.skills {
+        // A skill linked from another session keeps that origin only if it
+        // exists here; otherwise it is attributed to the imported session
+        let origin_exists: bool = skill.session_id == session.id
+            || conn.query_row(
+                "SELECT EXISTS(SELECT 1 FROM sessions WHERE id = ?)",
+                [&skill.session_id],
+                |row| row.get(0),
+            )?;
+        let origin = if origin_exists {
+            &skill.session_id
+        } else {
+            &session.id
+        };
+        let steps = serde_json::to_string(&skill.steps).unwrap_or_else(|_| "[]".to_string());
+        let (written, skill_id) = import_row(
+            conn,
+            "skills",
+            &[
+                "project_id",
+                "name",
+                "session_id",
+                "description",
+                "steps",
+                "confidence",
+                "extracted_at",
+            ],
+            2,
+            params![
+                project_id,
+                skill.name,
+                origin,
+                skill.description,
+                steps,
+                skill.confidence,
+                skill.extracted_at,
+            ],
+            mode,
+        )?;
+        tally(written, |c| &mut c.skills);
+        if origin != &session.id {
+            conn.execute(
+                "INSERT OR IGNORE INTO skill_sessions (skill_id, session_id) VALUES (?1, ?2)",
+                params![skill_id, session.id],
+            )?;
+        }
+    }
+
+    for m in &req.markers {
+        let (written, _) = import_row(
+            conn,
+            "session_markers",
+            &[
+                "session_id",
+                "event_index",
+                "marker_type",
+                "label",
+                "description",
+                "created_at",
+            ],
+            4,
+            params![
+                session.id,
+                m.event_index,
+                m.marker_type,
+                m.label,
+                m.description,
+                m.created_at,
+            ],
+            mode,
+        )?;
+        tally(written, |c| &mut c.markers);
+    }
+
+    Ok(summary)
+}
+
+/// POST /api/admin/import
+/// Import a session bundle exported by another instance into a local project.
+/// The session id is kept and child rows get fresh ids; `mode` decides whether
+/// rows that already exist are skipped (default) or overwritten.
+pub async fn import_session(
+    State(state): State<AppState>,
+    Json(req): Json<ImportRequest>,
+) -> impl IntoResponse {
+    let db = match &state.db {
+        Some(db) => db.clone(),
+        None => {
+            return (
+                StatusCode::NOT_IMPLEMENTED,
+                Json(serde_json::json!({ "error": "Not available in ephemeral mode" })),
+            )
+                .into_response()
+        }
+    };
+
+    let session_id = req.session.id.clone();
+    let mode = req.mode;
+    let result = db
+        .with_conn(move |conn| {
+            let Some(project_id) = super::routes::resolve_project_id(conn, &req.project_id) else {
+                return Ok(None);
+            };
+            let tx = conn.unchecked_transaction()?;
+            let summary = import_bundle(&tx, &project_id, &req)?;
+            tx.commit()?;
+            Ok::<_, rusqlite::Error>(Some((project_id, summary)))
+        })
+        .await;
+
+    match result {
+        Ok(Some((project_id, summary))) => {
+            tracing::info!(
+                "Imported session {} into project {} ({} messages, {} memories, {} skills, {} markers)",
+                session_id,
+                project_id,
+                summary.imported.messages,
+                summary.imported.memories,
+                summary.imported.skills,
+                summary.imported.markers
+            );
+            Json(serde_json::json!({
+                "session_id": session_id,
+                "project_id": project_id,
+                "mode": mode,
+                "imported": summary.imported,
+                "skipped": summary.skipped,
+            }))
+            .into_response()
+        }
+        Ok(None) => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": "Project not found" })),
+        )
+            .into_response(),
+        // e.g. another session already uses the file path
+        Err(rusqlite::Error::SqliteFailure(e, msg))
+            if e.code == rusqlite::ErrorCode::ConstraintViolation =>
+        {
+            (
+                StatusCode::CONFLICT,
+                Json(serde_json::json!({
+                    "error": format!(
+                        "Import conflicts with existing data: {}",
+                        msg.unwrap_or_else(|| e.to_string())
+                    )
+                })),
+            )
+                .into_response()
+        }
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({ "error": format!("Import failed: {}", e) })),
+        )
+            .into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support;
+
+    fn test_state(dir: &Path) -> AppState {
+        let mut config = Config::default();
+        config.database.backup_dir = Some(dir.join("backups"));
+        std::fs::write(dir.join("config.toml"), toml::to_string(&config).unwrap()).unwrap();
+        test_support::app_state(dir)
+    }
+
+    async fn backup(state: &AppState, path: &str) -> axum::response::Response {
+        backup_database(
+            State(state.clone()),
+            Json(BackupRequest {
+                path: path.to_string(),
+            }),
+        )
+        .await
+        .into_response()
+    }
+
+    #[tokio::test]
+    async fn test_backup_copies_rows() {
+        let tmp = tempfile::tempdir().unwrap();
+        let state = test_state(tmp.path());
+        state
+            .db
+            .as_ref()
+            .unwrap()
+            .with_conn(|conn| {
+                for i in 0..3 {
+                    conn.execute(
+                        "INSERT INTO projects (id, name, folder_path, created_at, updated_at)
+                         VALUES (?1, ?1, ?2, 'now', 'now')",
+                        rusqlite::params![format!("p{}", i), format!("/tmp/p{}", i)],
+                    )
+                    .unwrap();
+                }
+            })
+            .await;
+
+        let resp = backup(&state, "nightly/yolog.db").await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert!(body["size_bytes"].as_u64().unwrap() > 0);
+
+        let copy = rusqlite::Connection::open(tmp.path().join("backups/nightly/yolog.db")).unwrap();
+        let count: i64 = copy
+            .query_row("SELECT COUNT(*) FROM projects", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 3);
+
+        // Never overwrites an existing backup
+        let resp = backup(&state, "nightly/yolog.db").await;
+        assert_eq!(resp.status(), StatusCode::CONFLICT);
+    }
+
+    #[tokio::test]
+    async fn test_reindex_fts_restores_search() {
+        let tmp = tempfile::tempdir().unwrap();
+        let state = test_state(tmp.path());
+        let db = state.db.clone().unwrap();
+        let matches = || {
+            db.with_read_conn(|conn| {
+                conn.query_row(
+                    "SELECT COUNT(*) FROM session_messages_fts WHERE session_messages_fts MATCH 'deadlock'",
+                    [],
+                    |row| row.get::<_, i64>(0),
+                )
+                .unwrap()
+            })
+        };
+        db.with_conn(|conn| {
+            test_support::seed_session(conn, "s1");
+            conn.execute_batch(
+                "INSERT INTO session_messages (session_id, sequence_num, role, content_preview, search_content, timestamp)
+                    VALUES ('s1', 0, 'user', '', 'Why does the watcher deadlock?', 'now');
+                 INSERT INTO session_messages_fts(session_messages_fts) VALUES ('delete-all');",
+            )
+            .unwrap();
+        })
+        .await;
+        // The emptied index no longer finds the message
+        assert_eq!(matches().await, 0);
+
+        let resp = reindex_fts(State(state.clone())).await.into_response();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(body["indexes"][0]["table"], "session_messages_fts");
+        assert_eq!(body["indexes"][0]["rows"], 1);
+        assert_eq!(body["indexes"][1]["table"], "memories_fts");
+        assert_eq!(body["indexes"][1]["rows"], 0);
+        assert!(body["duration_ms"].is_u64());
+
+        assert_eq!(matches().await, 1);
+    }
+
+    async fn run_task(state: &AppState, task: &str) -> (StatusCode, serde_json::Value) {
+        let resp = run_scheduler_task(State(state.clone()), UrlPath(task.to_string()))
+            .await
+            .into_response();
+        let status = resp.status();
+        let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, serde_json::from_slice(&bytes).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_run_scheduler_task() {
+        let tmp = tempfile::tempdir().unwrap();
+        let state = test_state(tmp.path());
+
+        // memory_extraction needs a provider before its tasks can run
+        let (status, _) = run_task(&state, "duplicate_cleanup").await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        let (status, _) = run_task(&state, "nope").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        let mut config = Config::from_file(&state.config_path).unwrap();
+        config.ai.provider = Some("claude_code".into());
+        config.ai.memory_extraction = true;
+        std::fs::write(&state.config_path, toml::to_string(&config).unwrap()).unwrap();
+
+        let (status, body) = run_task(&state, "duplicate_cleanup").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["task_name"], "duplicate_cleanup");
+        assert_eq!(body["errors"], 0);
+        assert!(body["detail"].is_string());
+    }
+
+    #[tokio::test]
+    async fn test_scheduler_history_records_runs() {
+        let tmp = tempfile::tempdir().unwrap();
+        let state = test_state(tmp.path());
+        let mut config = Config::from_file(&state.config_path).unwrap();
+        config.ai.provider = Some("claude_code".into());
+        config.ai.memory_extraction = true;
+        config.scheduler.history_retention = 2;
+        std::fs::write(&state.config_path, toml::to_string(&config).unwrap()).unwrap();
+
+        for _ in 0..3 {
+            let (status, _) = run_task(&state, "duplicate_cleanup").await;
+            assert_eq!(status, StatusCode::OK);
+        }
+        let (status, _) = run_task(&state, "maintenance").await;
+        assert_eq!(status, StatusCode::OK);
+
+        let history = |task: Option<&str>| {
+            let state = state.clone();
+            let task = task.map(str::to_string);
+            async move {
+                let resp = get_scheduler_history(
+                    State(state),
+                    Query(SchedulerHistoryQuery { task, limit: None }),
+                )
+                .await
+                .into_response();
+                assert_eq!(resp.status(), StatusCode::OK);
+                let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX)
+                    .await
+                    .unwrap();
+                let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+                body["runs"].as_array().unwrap().clone()
+            }
+        };
+
+        // Trimmed to history_retention per task
+        let runs = history(Some("duplicate_cleanup")).await;
+        assert_eq!(runs.len(), 2);
+        assert_eq!(runs[0]["task_name"], "duplicate_cleanup");
+        assert_eq!(runs[0]["errors"], 0);
+        assert_eq!(runs[0]["skipped"], false);
+        assert!(
+            runs[0]["started_at"].as_str().unwrap() <= runs[0]["finished_at"].as_str().unwrap()
+        );
+        assert!(runs[0]["id"].as_i64() > runs[1]["id"].as_i64());
+
+        let runs = history(None).await;
+        assert_eq!(runs.len(), 3);
+        assert_eq!(runs[0]["task_name"], "maintenance");
+    }
+
+    #[tokio::test]
+    async fn test_backup_rejects_paths_outside_backup_dir() {
+        let tmp = tempfile::tempdir().unwrap();
+        let state = test_state(tmp.path());
+
+        for path in ["../escape.db", "/etc/yolog.db", ""] {
+            let resp = backup(&state, path).await;
+            assert_eq!(resp.status(), StatusCode::BAD_REQUEST, "{}", path);
+        }
+        assert!(!tmp.path().join("escape.db").exists());
+    }
+}
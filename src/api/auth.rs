@@ -1,30 +1,40 @@
 //! Authentication middleware for API key validation
 
 use super::AppState;
+use crate::config::ApiKeyScope;
 use axum::{
     body::Body,
     extract::State,
-    http::{Request, StatusCode},
+    http::{Method, Request, StatusCode},
     middleware::Next,
     response::{IntoResponse, Response},
     Json,
 };
 
+/// An API key the request was authenticated with, added to the request's
+/// extensions by [`auth_middleware`] for later layers
+#[derive(Clone)]
+pub struct AuthenticatedKey(pub String);
+
 /// Authentication middleware
 ///
-/// If `api_key` is configured in AppState, validates the Authorization header.
+/// If any API keys are configured in AppState, validates the Authorization header.
 /// Expected format: `Authorization: Bearer <api_key>`
 ///
-/// If no `api_key` is configured, all requests are allowed (local mode).
+/// Read-scoped keys may only make requests that read data (GET/HEAD/OPTIONS
+/// and the query POSTs read-only mode allows); anything else is rejected with
+/// 403.
+///
+/// If no keys are configured, all requests are allowed (local mode).
 pub async fn auth_middleware(
     State(state): State<AppState>,
-    request: Request<Body>,
+    mut request: Request<Body>,
     next: Next,
 ) -> Response {
     // If no API key is configured, allow all requests
-    let Some(expected_key) = &state.api_key else {
+    if state.api_keys.is_empty() {
         return next.run(request).await;
-    };
+    }
 
     // Check Authorization header
     let auth_header = request
@@ -36,18 +46,31 @@ pub async fn auth_middleware(
         Some(header) if header.starts_with("Bearer ") => {
             let provided_key = &header[7..]; // Skip "Bearer "
 
-            if provided_key == expected_key {
-                // Valid API key, proceed with request
-                next.run(request).await
-            } else {
-                // Invalid API key
-                (
-                    StatusCode::UNAUTHORIZED,
+            match state.api_keys.iter().find(|k| k.key == provided_key) {
+                Some(key) if scope_allows(key.scope, request.method(), request.uri().path()) => {
+                    // Valid API key, proceed with request
+                    request
+                        .extensions_mut()
+                        .insert(AuthenticatedKey(key.key.clone()));
+                    next.run(request).await
+                }
+                Some(_) => (
+                    StatusCode::FORBIDDEN,
                     Json(serde_json::json!({
-                        "error": "Invalid API key"
+                        "error": "API key is read-only"
                     })),
                 )
-                    .into_response()
+                    .into_response(),
+                None => {
+                    // Invalid API key
+                    (
+                        StatusCode::UNAUTHORIZED,
+                        Json(serde_json::json!({
+                            "error": "Invalid API key"
+                        })),
+                    )
+                        .into_response()
+                }
             }
         }
         Some(_) => {
@@ -73,10 +96,37 @@ pub async fn auth_middleware(
     }
 }
 
+/// Whether a key with `scope` may make a `method` request to `path` (relative
+/// to `/api`)
+fn scope_allows(scope: ApiKeyScope, method: &Method, path: &str) -> bool {
+    match scope {
+        ApiKeyScope::Admin => true,
+        ApiKeyScope::Read => super::read_only::is_read(method, path),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_read_scope_allows_only_reads() {
+        assert!(scope_allows(ApiKeyScope::Read, &Method::GET, "/sessions"));
+        assert!(scope_allows(ApiKeyScope::Read, &Method::HEAD, "/sessions"));
+        assert!(scope_allows(ApiKeyScope::Read, &Method::POST, "/search"));
+        assert!(!scope_allows(ApiKeyScope::Read, &Method::POST, "/memories"));
+        assert!(!scope_allows(
+            ApiKeyScope::Read,
+            &Method::DELETE,
+            "/sessions/s1"
+        ));
+        assert!(scope_allows(
+            ApiKeyScope::Admin,
+            &Method::DELETE,
+            "/sessions/s1"
+        ));
+    }
+
     #[test]
     fn test_bearer_token_extraction() {
         let header = "Bearer my-secret-key";
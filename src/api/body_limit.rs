@@ -0,0 +1,75 @@
+//! JSON responses for request bodies over `server.max_body_bytes`
+
+use axum::{
+    body::Body,
+    extract::State,
+    http::{Request, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+
+/// Payload-too-large middleware
+///
+/// The body limit itself is enforced by `RequestBodyLimitLayer`, which answers
+/// oversized `Content-Length` requests directly and makes body extractors fail
+/// for oversized streamed bodies. Both produce a plain-text 413; this rewrites
+/// it as the JSON error shape every other endpoint uses.
+pub async fn payload_too_large_json(
+    State(limit): State<usize>,
+    request: Request<Body>,
+    next: Next,
+) -> Response {
+    let response = next.run(request).await;
+    if response.status() != StatusCode::PAYLOAD_TOO_LARGE {
+        return response;
+    }
+
+    (
+        StatusCode::PAYLOAD_TOO_LARGE,
+        Json(serde_json::json!({
+            "error": format!("Request body too large (limit is {} bytes)", limit)
+        })),
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{extract::DefaultBodyLimit, middleware, routing::post, Router};
+    use tower::ServiceExt;
+    use tower_http::limit::RequestBodyLimitLayer;
+
+    fn app(limit: usize) -> Router {
+        Router::new()
+            .route(
+                "/echo",
+                post(|Json(body): Json<serde_json::Value>| async move { Json(body) }),
+            )
+            .layer(RequestBodyLimitLayer::new(limit))
+            .layer(DefaultBodyLimit::disable())
+            .layer(middleware::from_fn_with_state(
+                limit,
+                payload_too_large_json,
+            ))
+    }
+
+    fn post_json(body: String) -> Request<Body> {
+        Request::post("/echo")
+            .header("content-type", "application/json")
+            .body(Body::from(body))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_body_above_axum_default_is_accepted_under_configured_limit() {
+        // Larger than axum's built-in 2MB extractor limit
+        let body = format!("{{\"raw_content\": \"{}\"}}", "x".repeat(3 * 1024 * 1024));
+        let resp = app(16 * 1024 * 1024)
+            .oneshot(post_json(body))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+}
@@ -0,0 +1,89 @@
+//! Gzip/deflate response compression
+
+use axum::{
+    http::{header, HeaderValue},
+    response::Response,
+};
+use tower_http::compression::predicate::{NotForContentType, Predicate, SizeAbove};
+use tower_http::compression::CompressionLayer;
+
+/// Responses known to be smaller than this are sent uncompressed
+pub const MIN_COMPRESS_BYTES: u16 = 1024;
+
+/// Compress responses with the best coding the client accepts.
+///
+/// SSE, images, ranges and responses under `MIN_COMPRESS_BYTES` are left
+/// alone; other streamed bodies are compressed as they stream.
+pub fn layer() -> CompressionLayer<impl Predicate> {
+    CompressionLayer::new().compress_when(
+        SizeAbove::new(MIN_COMPRESS_BYTES)
+            .and(NotForContentType::GRPC)
+            .and(NotForContentType::IMAGES)
+            .and(NotForContentType::SSE),
+    )
+}
+
+/// Add `Vary: Accept-Encoding` to every response, compressed or not, so
+/// caches don't hand one client's encoding to another
+pub async fn vary_accept_encoding(mut response: Response) -> Response {
+    let varies = response
+        .headers()
+        .get_all(header::VARY)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .any(|v| v.to_ascii_lowercase().contains("accept-encoding"));
+    if !varies {
+        response
+            .headers_mut()
+            .append(header::VARY, HeaderValue::from_static("accept-encoding"));
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, http::Request, middleware, routing::get, Router};
+    use tower::ServiceExt;
+
+    fn app() -> Router {
+        Router::new()
+            .route("/tiny", get(|| async { "ok" }))
+            .route("/large", get(|| async { "x".repeat(4096) }))
+            .layer(layer())
+            .layer(middleware::map_response(vary_accept_encoding))
+    }
+
+    async fn get_with(path: &str, accept: &str) -> Response {
+        app()
+            .oneshot(
+                Request::get(path)
+                    .header(header::ACCEPT_ENCODING, accept)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_small_responses_are_not_compressed() {
+        let resp = get_with("/tiny", "gzip").await;
+        assert!(resp.headers().get(header::CONTENT_ENCODING).is_none());
+        assert_eq!(resp.headers()[header::VARY], "accept-encoding");
+    }
+
+    #[tokio::test]
+    async fn test_negotiates_encoding_and_always_varies() {
+        let resp = get_with("/large", "gzip").await;
+        assert_eq!(resp.headers()[header::CONTENT_ENCODING], "gzip");
+        assert_eq!(resp.headers().get_all(header::VARY).iter().count(), 1);
+
+        let resp = get_with("/large", "gzip;q=0, deflate;q=0.5").await;
+        assert_eq!(resp.headers()[header::CONTENT_ENCODING], "deflate");
+
+        let resp = get_with("/large", "br, identity").await;
+        assert!(resp.headers().get(header::CONTENT_ENCODING).is_none());
+        assert_eq!(resp.headers()[header::VARY], "accept-encoding");
+    }
+}
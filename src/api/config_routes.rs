@@ -4,7 +4,7 @@
 //! Changes are persisted to config.toml.
 
 use super::AppState;
-use crate::config::{AiConfig, Config, WatchConfig};
+use crate::config::{AiConfig, Config, ConfigProblem, ProviderChain, WatchConfig};
 use axum::{
     extract::{Path, State},
     http::StatusCode,
@@ -46,15 +46,22 @@ pub struct WatchConfigResponse {
     pub path: String,
     pub parser: String,
     pub enabled: bool,
+    pub delete_on_remove: bool,
+    pub namespace_session_ids: bool,
+    pub parse_agents: bool,
+    pub dedupe_by_hash: bool,
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
 }
 
 #[derive(Serialize)]
 pub struct AiConfigResponse {
-    pub provider: Option<String>,
+    pub provider: Option<ProviderChain>,
     pub title_generation: bool,
     pub marker_detection: bool,
     pub memory_extraction: bool,
     pub skills_discovery: bool,
+    pub lenient_json: bool,
 }
 
 #[derive(Serialize)]
@@ -73,11 +80,12 @@ pub struct ConfigMeta {
 
 #[derive(Deserialize)]
 pub struct UpdateAiConfigRequest {
-    pub provider: Option<String>,
+    pub provider: Option<ProviderChain>,
     pub title_generation: Option<bool>,
     pub marker_detection: Option<bool>,
     pub memory_extraction: Option<bool>,
     pub skills_discovery: Option<bool>,
+    pub lenient_json: Option<bool>,
 }
 
 #[derive(Deserialize)]
@@ -85,6 +93,12 @@ pub struct AddWatchPathRequest {
     pub path: String,
     pub parser: Option<String>,
     pub enabled: Option<bool>,
+    pub delete_on_remove: Option<bool>,
+    pub namespace_session_ids: Option<bool>,
+    pub parse_agents: Option<bool>,
+    pub dedupe_by_hash: Option<bool>,
+    pub include: Option<Vec<String>>,
+    pub exclude: Option<Vec<String>>,
 }
 
 #[derive(Deserialize)]
@@ -109,7 +123,7 @@ pub async fn get_config(State(state): State<AppState>) -> impl IntoResponse {
                 server: ServerConfigResponse {
                     host: config.server.host.clone(),
                     port: config.server.port,
-                    has_api_key: config.server.api_key.is_some(),
+                    has_api_key: !config.server.effective_api_keys().is_empty(),
                 },
                 watch: config
                     .watch
@@ -120,6 +134,12 @@ pub async fn get_config(State(state): State<AppState>) -> impl IntoResponse {
                         path: w.path.to_string_lossy().to_string(),
                         parser: w.parser.clone(),
                         enabled: w.enabled,
+                        delete_on_remove: w.delete_on_remove,
+                        namespace_session_ids: w.namespace_session_ids,
+                        parse_agents: w.parse_agents,
+                        dedupe_by_hash: w.dedupe_by_hash,
+                        include: w.include.clone(),
+                        exclude: w.exclude.clone(),
                     })
                     .collect(),
                 ai: AiConfigResponse {
@@ -128,6 +148,7 @@ pub async fn get_config(State(state): State<AppState>) -> impl IntoResponse {
                     marker_detection: config.ai.marker_detection,
                     memory_extraction: config.ai.memory_extraction,
                     skills_discovery: config.ai.skills_discovery,
+                    lenient_json: config.ai.lenient_json,
                 },
                 data_dir: config.data_dir().to_string_lossy().to_string(),
                 meta: ConfigMeta {
@@ -147,6 +168,30 @@ pub async fn get_config(State(state): State<AppState>) -> impl IntoResponse {
     }
 }
 
+/// GET /api/config/validate - Check the config file for errors and warnings
+pub async fn validate_config(State(state): State<AppState>) -> impl IntoResponse {
+    let (warnings, errors) = match Config::parse_file(&state.config_path) {
+        Ok(config) => match config.validate() {
+            Ok(warnings) => (warnings, Vec::new()),
+            Err(errors) => (Vec::new(), errors),
+        },
+        Err(e) => (
+            Vec::new(),
+            vec![ConfigProblem {
+                key: String::new(),
+                message: e.to_string(),
+            }],
+        ),
+    };
+
+    Json(serde_json::json!({
+        "valid": errors.is_empty(),
+        "errors": errors,
+        "warnings": warnings,
+    }))
+    .into_response()
+}
+
 /// PUT /api/config - Update configuration (currently only AI settings)
 pub async fn update_config(
     State(state): State<AppState>,
@@ -201,6 +246,7 @@ pub async fn get_ai_config(State(state): State<AppState>) -> impl IntoResponse {
                 marker_detection: config.ai.marker_detection,
                 memory_extraction: config.ai.memory_extraction,
                 skills_discovery: config.ai.skills_discovery,
+                lenient_json: config.ai.lenient_json,
             };
             Json(response).into_response()
         }
@@ -252,6 +298,7 @@ pub async fn update_ai_config(
                 marker_detection: config.ai.marker_detection,
                 memory_extraction: config.ai.memory_extraction,
                 skills_discovery: config.ai.skills_discovery,
+                lenient_json: config.ai.lenient_json,
             };
             Json(response).into_response()
         }
@@ -276,6 +323,12 @@ pub async fn list_watch_paths(State(state): State<AppState>) -> impl IntoRespons
                     path: w.path.to_string_lossy().to_string(),
                     parser: w.parser.clone(),
                     enabled: w.enabled,
+                    delete_on_remove: w.delete_on_remove,
+                    namespace_session_ids: w.namespace_session_ids,
+                    parse_agents: w.parse_agents,
+                    dedupe_by_hash: w.dedupe_by_hash,
+                    include: w.include.clone(),
+                    exclude: w.exclude.clone(),
                 })
                 .collect();
 
@@ -328,11 +381,27 @@ pub async fn add_watch_path(
             .into_response();
     }
 
+    let include = req.include.unwrap_or_default();
+    let exclude = req.exclude.unwrap_or_default();
+    if let Err(e) = crate::watcher::PathFilter::new(&include, &exclude) {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": e })),
+        )
+            .into_response();
+    }
+
     // Add new watch path
     config.watch.push(WatchConfig {
         path: new_path,
         parser: req.parser.unwrap_or_else(|| "claude_code".to_string()),
         enabled: req.enabled.unwrap_or(true),
+        delete_on_remove: req.delete_on_remove.unwrap_or(false),
+        namespace_session_ids: req.namespace_session_ids.unwrap_or(false),
+        parse_agents: req.parse_agents.unwrap_or(false),
+        dedupe_by_hash: req.dedupe_by_hash.unwrap_or(false),
+        include,
+        exclude,
     });
 
     // Save config
@@ -347,6 +416,12 @@ pub async fn add_watch_path(
                     path: w.path.to_string_lossy().to_string(),
                     parser: w.parser.clone(),
                     enabled: w.enabled,
+                    delete_on_remove: w.delete_on_remove,
+                    namespace_session_ids: w.namespace_session_ids,
+                    parse_agents: w.parse_agents,
+                    dedupe_by_hash: w.dedupe_by_hash,
+                    include: w.include.clone(),
+                    exclude: w.exclude.clone(),
                 })
                 .collect();
 
@@ -421,7 +496,7 @@ pub async fn remove_watch_path(
 
 fn apply_ai_update(ai: &mut AiConfig, update: UpdateAiConfigRequest) {
     if let Some(provider) = update.provider {
-        ai.provider = if provider.is_empty() {
+        ai.provider = if provider.names().iter().all(String::is_empty) {
             None
         } else {
             Some(provider)
@@ -439,4 +514,7 @@ fn apply_ai_update(ai: &mut AiConfig, update: UpdateAiConfigRequest) {
     if let Some(skills_discovery) = update.skills_discovery {
         ai.skills_discovery = skills_discovery;
     }
+    if let Some(lenient_json) = update.lenient_json {
+        ai.lenient_json = lenient_json;
+    }
 }
@@ -6,7 +6,7 @@
 
 use super::AppState;
 use crate::mcp::db::McpDb;
-use crate::mcp::types::{Memory, MemoryType, SessionContextResult};
+use crate::mcp::types::{Memory, MemoryType, SessionContext, SessionContextResult, Skill};
 use axum::{
     extract::{Query, State},
     http::StatusCode,
@@ -61,12 +61,29 @@ pub struct SearchContextRequest {
     pub limit: usize,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct ContextPackRequest {
+    pub project_path: String,
+    /// Budget for `formatted_text`, estimated at ~4 characters per token
+    #[serde(default = "default_pack_max_tokens")]
+    pub max_tokens: usize,
+}
+
 fn default_sessions() -> usize {
     3
 }
 fn default_limit() -> usize {
     10
 }
+fn default_pack_max_tokens() -> usize {
+    2000
+}
+
+/// Decisions and preferences below this confidence are left out of the context pack
+const PACK_MIN_CONFIDENCE: f32 = 0.7;
+
+/// Candidates fetched per context pack section before trimming to the budget
+const PACK_SECTION_LIMIT: usize = 20;
 
 // ============================================================================
 // Formatting helpers (produce same markdown as MCP handlers)
@@ -240,6 +257,101 @@ fn format_search_results(project_name: &str, query: Option<&str>, memories: &[Me
     output
 }
 
+// ============================================================================
+// Context pack assembly
+// ============================================================================
+
+/// Rough token estimate used for budgeting (~4 characters per token)
+fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4)
+}
+
+/// Markdown built section by section in priority order, keeping only the
+/// items that fit in the token budget
+struct PackBuilder {
+    text: String,
+    chars: usize,
+    max_tokens: usize,
+    omitted: usize,
+}
+
+impl PackBuilder {
+    fn new(header: &str, max_tokens: usize) -> Self {
+        let mut builder = PackBuilder {
+            text: String::new(),
+            chars: 0,
+            max_tokens,
+            omitted: 0,
+        };
+        builder.push_if_fits(header);
+        builder
+    }
+
+    fn push_if_fits(&mut self, addition: &str) -> bool {
+        let chars = addition.chars().count();
+        if (self.chars + chars).div_ceil(4) > self.max_tokens {
+            return false;
+        }
+        self.text.push_str(addition);
+        self.chars += chars;
+        true
+    }
+
+    /// Append a section with as many `items` (in order) as fit. The heading is
+    /// only written with the first item that fits. Returns the kept indices.
+    fn section(&mut self, heading: &str, items: &[String]) -> Vec<usize> {
+        let mut kept = Vec::new();
+        for (i, item) in items.iter().enumerate() {
+            let addition = if kept.is_empty() {
+                format!("\n## {}\n{}", heading, item)
+            } else {
+                item.clone()
+            };
+            if self.push_if_fits(&addition) {
+                kept.push(i);
+            } else {
+                self.omitted += 1;
+            }
+        }
+        kept
+    }
+}
+
+fn format_lifeboat(ctx: &SessionContext) -> String {
+    let mut output = String::new();
+    if let Some(task) = &ctx.active_task {
+        output.push_str(&format!("**Active Task:** {}\n", task));
+    }
+    if let Some(resume) = &ctx.resume_context {
+        output.push_str(&format!("**Resume Context:** {}\n", resume));
+    }
+    if !ctx.open_questions.is_empty() {
+        output.push_str(&format!(
+            "**Open Questions:** {}\n",
+            ctx.open_questions.join("; ")
+        ));
+    }
+    output
+}
+
+fn format_skill_line(skill: &Skill) -> String {
+    if skill.steps.is_empty() {
+        format!("- **{}**: {}\n", skill.name, skill.description)
+    } else {
+        format!(
+            "- **{}**: {} (steps: {})\n",
+            skill.name,
+            skill.description,
+            skill.steps.join(" → ")
+        )
+    }
+}
+
+/// Keep the items at `kept` indices, in order
+fn select<T: Clone>(items: &[T], kept: &[usize]) -> Vec<T> {
+    kept.iter().map(|&i| items[i].clone()).collect()
+}
+
 // ============================================================================
 // Helper: resolve project by path with error response
 // ============================================================================
@@ -691,3 +803,240 @@ pub async fn search_context(
             .into_response(),
     }
 }
+
+/// POST /api/context/pack
+/// Token-bounded project knowledge for prompt injection: latest lifeboat,
+/// high-confidence decisions and preferences, top skills, then recent memories
+pub async fn get_context_pack(
+    State(state): State<AppState>,
+    Json(req): Json<ContextPackRequest>,
+) -> impl IntoResponse {
+    if state.db.is_none() {
+        return Json(json!({
+            "project_name": "",
+            "max_tokens": req.max_tokens,
+            "estimated_tokens": 0,
+            "lifeboat": null,
+            "decisions": [],
+            "preferences": [],
+            "skills": [],
+            "recent_memories": [],
+            "omitted": 0,
+            "formatted_text": "No project knowledge available in ephemeral mode.",
+        }))
+        .into_response();
+    }
+    let db = state.db.clone().unwrap();
+
+    let result = tokio::task::spawn_blocking(move || {
+        let mcp_db = McpDb::new(db);
+        let project = resolve_project(&mcp_db, &req.project_path)?;
+
+        let lifeboat: Vec<SessionContext> = mcp_db
+            .get_latest_lifeboat(&project.id)
+            .unwrap_or_default()
+            .into_iter()
+            .collect();
+        let high_confidence = |memory_type| {
+            mcp_db
+                .get_memories_by_type(&project.id, memory_type, PACK_SECTION_LIMIT)
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|m| m.confidence >= PACK_MIN_CONFIDENCE)
+                .collect::<Vec<_>>()
+        };
+        let decisions = high_confidence(MemoryType::Decision);
+        let preferences = high_confidence(MemoryType::Preference);
+        let skills = mcp_db
+            .get_top_skills(&project.id, PACK_SECTION_LIMIT)
+            .unwrap_or_default();
+
+        // Recent memories not already covered above, most confident first
+        let recent_sessions = mcp_db
+            .get_recent_sessions(&project.id, default_sessions())
+            .unwrap_or_default();
+        let mut recent_memories: Vec<Memory> = mcp_db
+            .get_memories_by_sessions(&recent_sessions, PACK_SECTION_LIMIT)
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|m| !decisions.iter().chain(&preferences).any(|d| d.id == m.id))
+            .collect();
+        recent_memories.sort_by(|a, b| {
+            b.confidence
+                .partial_cmp(&a.confidence)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+
+        let mut pack = PackBuilder::new(
+            &format!("# Project Knowledge: {}\n", project.name),
+            req.max_tokens,
+        );
+        let lifeboat_items: Vec<String> = lifeboat.iter().map(format_lifeboat).collect();
+        let lifeboat = select(
+            &lifeboat,
+            &pack.section("Where We Left Off", &lifeboat_items),
+        )
+        .pop();
+        let memory_lines = |memories: &[Memory]| -> Vec<String> {
+            memories.iter().map(format_memory_line).collect()
+        };
+        let decisions = select(
+            &decisions,
+            &pack.section("Key Decisions", &memory_lines(&decisions)),
+        );
+        let preferences = select(
+            &preferences,
+            &pack.section("Preferences", &memory_lines(&preferences)),
+        );
+        let skill_lines: Vec<String> = skills.iter().map(format_skill_line).collect();
+        let skills = select(&skills, &pack.section("Skills", &skill_lines));
+        let recent_memories = select(
+            &recent_memories,
+            &pack.section("Recent Memories", &memory_lines(&recent_memories)),
+        );
+
+        // Track access for included memories (feeds into ranking)
+        let memory_ids: Vec<i64> = decisions
+            .iter()
+            .chain(&preferences)
+            .chain(&recent_memories)
+            .map(|m| m.id)
+            .collect();
+        let _ = mcp_db.track_memory_access(&memory_ids);
+
+        Ok::<_, (StatusCode, serde_json::Value)>(json!({
+            "project_name": project.name,
+            "max_tokens": req.max_tokens,
+            "estimated_tokens": estimate_tokens(&pack.text),
+            "lifeboat": lifeboat,
+            "decisions": decisions,
+            "preferences": preferences,
+            "skills": skills,
+            "recent_memories": recent_memories,
+            "omitted": pack.omitted,
+            "formatted_text": pack.text,
+        }))
+    })
+    .await;
+
+    match result {
+        Ok(Ok(data)) => Json(data).into_response(),
+        Ok(Err((status, err))) => (status, Json(err)).into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({ "error": e.to_string() })),
+        )
+            .into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support;
+
+    async fn seed(state: &AppState) {
+        let long_content = "details ".repeat(60);
+        state
+            .db
+            .as_ref()
+            .unwrap()
+            .with_conn(move |conn| {
+                test_support::seed_session(conn, "s1");
+                conn.execute_batch(
+                    "INSERT INTO memories (id, project_id, session_id, memory_type, title, content, confidence, extracted_at) VALUES
+                        (1, 'p1', 's1', 'decision', 'Use SQLite WAL', 'WAL mode for concurrent readers', 0.95, '2024-01-01T00:00:00Z'),
+                        (2, 'p1', 's1', 'decision', 'Maybe try Postgres', 'Floated once, never adopted', 0.4, '2024-01-01T00:00:00Z'),
+                        (3, 'p1', 's1', 'preference', 'Tabs', 'Indent with tabs', 0.9, '2024-01-01T00:00:00Z');
+                     INSERT INTO skills (id, project_id, session_id, name, description, steps, confidence, extracted_at)
+                     VALUES (1, 'p1', 's1', 'Release', 'Cut a release', '[\"bump\", \"tag\"]', 0.8, '2024-01-01T00:00:00Z');
+                     INSERT INTO session_context (session_id, project_id, resume_context, created_at, updated_at)
+                     VALUES ('s1', 'p1', 'Halfway through the migration', '2024-01-01T00:00:00Z', '2024-01-01T00:00:00Z');",
+                )
+                .unwrap();
+                for i in 0..5 {
+                    conn.execute(
+                        "INSERT INTO memories (project_id, session_id, memory_type, title, content, confidence, extracted_at)
+                         VALUES ('p1', 's1', 'fact', ?1, ?2, 0.5, '2024-01-01T00:00:00Z')",
+                        rusqlite::params![format!("fact {}", i), long_content],
+                    )
+                    .unwrap();
+                }
+            })
+            .await;
+    }
+
+    async fn pack(state: &AppState, max_tokens: usize) -> serde_json::Value {
+        let resp = get_context_pack(
+            State(state.clone()),
+            Json(ContextPackRequest {
+                project_path: "/tmp/proj".to_string(),
+                max_tokens,
+            }),
+        )
+        .await
+        .into_response();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    fn titles(body: &serde_json::Value, key: &str) -> Vec<String> {
+        body[key]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|m| m["title"].as_str().unwrap().to_string())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_context_pack_includes_all_sections() {
+        let tmp = tempfile::tempdir().unwrap();
+        let state = test_support::app_state(tmp.path());
+        seed(&state).await;
+
+        let body = pack(&state, 10_000).await;
+        let text = body["formatted_text"].as_str().unwrap();
+        assert!(text.starts_with("# Project Knowledge: proj"));
+        assert!(text.contains("Halfway through the migration"));
+        assert!(text.contains("Release**: Cut a release (steps: bump → tag)"));
+        assert_eq!(titles(&body, "decisions"), vec!["Use SQLite WAL"]);
+        assert_eq!(titles(&body, "preferences"), vec!["Tabs"]);
+        assert_eq!(body["recent_memories"].as_array().unwrap().len(), 6);
+        assert_eq!(body["omitted"], 0);
+
+        // Priority order: lifeboat, decisions, preferences, skills, recent memories
+        let positions: Vec<usize> = [
+            "## Where We Left Off",
+            "## Key Decisions",
+            "## Preferences",
+            "## Skills",
+            "## Recent Memories",
+        ]
+        .iter()
+        .map(|h| text.find(h).unwrap())
+        .collect();
+        assert!(positions.windows(2).all(|w| w[0] < w[1]));
+    }
+
+    #[tokio::test]
+    async fn test_context_pack_respects_budget_and_keeps_high_confidence() {
+        let tmp = tempfile::tempdir().unwrap();
+        let state = test_support::app_state(tmp.path());
+        seed(&state).await;
+
+        let body = pack(&state, 80).await;
+        let text = body["formatted_text"].as_str().unwrap();
+        assert!(estimate_tokens(text) <= 80);
+        assert!(body["estimated_tokens"].as_u64().unwrap() <= 80);
+
+        // High-confidence knowledge survives; the long low-confidence facts don't
+        assert_eq!(titles(&body, "decisions"), vec!["Use SQLite WAL"]);
+        assert_eq!(titles(&body, "preferences"), vec!["Tabs"]);
+        assert!(!text.contains("fact 0"));
+        assert!(body["omitted"].as_u64().unwrap() >= 5);
+    }
+}
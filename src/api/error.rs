@@ -0,0 +1,69 @@
+//! HTTP responses for `CoreError`
+
+use crate::error::CoreError;
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+
+impl CoreError {
+    /// Status code a handler should answer with for this error
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            CoreError::ProviderUnavailable(_) | CoreError::DbBusy(_) => {
+                StatusCode::SERVICE_UNAVAILABLE
+            }
+            CoreError::Timeout(..) => StatusCode::GATEWAY_TIMEOUT,
+            CoreError::NotFound(..) => StatusCode::NOT_FOUND,
+            CoreError::Validation(_) => StatusCode::BAD_REQUEST,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+/// `{"error": "..."}` with the error's status code
+impl IntoResponse for CoreError {
+    fn into_response(self) -> Response {
+        (
+            self.status_code(),
+            Json(serde_json::json!({ "error": self.to_string() })),
+        )
+            .into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_codes() {
+        let busy = rusqlite::Error::SqliteFailure(
+            rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_BUSY),
+            None,
+        );
+        let cases = [
+            (
+                CoreError::ProviderUnavailable("Claude Code CLI not installed".into()),
+                StatusCode::SERVICE_UNAVAILABLE,
+            ),
+            (
+                CoreError::Timeout("CLI".into(), 60),
+                StatusCode::GATEWAY_TIMEOUT,
+            ),
+            (CoreError::from(busy), StatusCode::SERVICE_UNAVAILABLE),
+            (
+                CoreError::ParseFailed("AI response".into()),
+                StatusCode::INTERNAL_SERVER_ERROR,
+            ),
+            (
+                CoreError::from(rusqlite::Error::QueryReturnedNoRows),
+                StatusCode::INTERNAL_SERVER_ERROR,
+            ),
+        ];
+        for (error, status) in cases {
+            assert_eq!(error.into_response().status(), status);
+        }
+    }
+}
@@ -4,27 +4,29 @@
 //! Uses fire-and-forget pattern: returns 202 immediately, delivers result via SSE.
 
 use super::AppState;
-use crate::ai::cli::CliProvider;
+use crate::ai::cli::{CliProvider, DetectedCli};
 use crate::ai::export::{self, ExportFormat};
 use crate::ai::types::AiEvent;
 use crate::config::Config;
 use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
 
-/// Resolve the configured CLI provider from config
-fn resolve_provider(state: &AppState) -> CliProvider {
+/// Detect the configured provider, reaching it with the settings from config
+async fn resolve_cli(state: &AppState) -> crate::error::Result<DetectedCli> {
+    let config = Config::from_file(&state.config_path).unwrap_or_default();
+    export::ensure_cli(CliProvider::from_ai_config(&config.ai), &config.ai).await
+}
+
+/// Read `export.chunk_overlap_chars` from config.toml
+fn resolve_chunk_overlap(state: &AppState) -> usize {
     Config::from_file(&state.config_path)
-        .ok()
-        .and_then(|c| {
-            c.ai.provider
-                .as_deref()
-                .and_then(CliProvider::from_config_str)
-        })
-        .unwrap_or(CliProvider::ClaudeCode)
+        .unwrap_or_default()
+        .export
+        .chunk_overlap_chars
 }
 
 /// Get AI export capabilities
-pub async fn get_ai_export_capabilities() -> impl IntoResponse {
-    Json(export::get_capabilities().await)
+pub async fn get_ai_export_capabilities(State(state): State<AppState>) -> impl IntoResponse {
+    Json(export::get_capabilities(resolve_chunk_overlap(&state)).await)
 }
 
 /// Generate AI export content (async — returns 202, result delivered via SSE)
@@ -55,16 +57,9 @@ pub async fn generate_ai_export(
     }
 
     // Detect CLI
-    let provider = resolve_provider(&state);
-    let cli = match export::ensure_cli(provider).await {
+    let cli = match resolve_cli(&state).await {
         Ok(cli) => cli,
-        Err(e) => {
-            return (
-                StatusCode::SERVICE_UNAVAILABLE,
-                Json(serde_json::json!({ "error": e })),
-            )
-                .into_response()
-        }
+        Err(e) => return e.into_response(),
     };
 
     // Acquire task queue permit
@@ -82,6 +77,7 @@ pub async fn generate_ai_export(
     let session_id = req.session_id.clone();
     let format_str = req.format.clone();
     let raw_content = req.raw_content;
+    let chunk_overlap = resolve_chunk_overlap(&state);
     let ai_event_tx = state.ai_event_tx.clone();
 
     tracing::info!("Starting AI export generation ({})", format_str);
@@ -93,7 +89,7 @@ pub async fn generate_ai_export(
             format: format_str.clone(),
         });
 
-        match export::generate_export(&raw_content, format, &cli).await {
+        match export::generate_export(&raw_content, format, &cli, chunk_overlap).await {
             Ok(result) => {
                 let _ = ai_event_tx.send(AiEvent::ExportComplete {
                     session_id,
@@ -108,7 +104,7 @@ pub async fn generate_ai_export(
                 let _ = ai_event_tx.send(AiEvent::ExportError {
                     session_id,
                     format: format_str,
-                    error: e,
+                    error: e.to_string(),
                 });
             }
         }
@@ -130,16 +126,9 @@ pub async fn process_ai_export_chunk(
     State(state): State<AppState>,
     Json(req): Json<export::ChunkRequest>,
 ) -> impl IntoResponse {
-    let provider = resolve_provider(&state);
-    let cli = match export::ensure_cli(provider).await {
+    let cli = match resolve_cli(&state).await {
         Ok(cli) => cli,
-        Err(e) => {
-            return (
-                StatusCode::SERVICE_UNAVAILABLE,
-                Json(serde_json::json!({ "error": e })),
-            )
-                .into_response()
-        }
+        Err(e) => return e.into_response(),
     };
 
     let _permit = match state.ai_task_queue.acquire().await {
@@ -163,11 +152,7 @@ pub async fn process_ai_export_chunk(
         Ok(result) => Json(result).into_response(),
         Err(e) => {
             tracing::error!("Chunk processing failed: {}", e);
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(serde_json::json!({ "error": e })),
-            )
-                .into_response()
+            e.into_response()
         }
     }
 }
@@ -177,16 +162,9 @@ pub async fn merge_ai_export_chunks(
     State(state): State<AppState>,
     Json(req): Json<export::MergeRequest>,
 ) -> impl IntoResponse {
-    let provider = resolve_provider(&state);
-    let cli = match export::ensure_cli(provider).await {
+    let cli = match resolve_cli(&state).await {
         Ok(cli) => cli,
-        Err(e) => {
-            return (
-                StatusCode::SERVICE_UNAVAILABLE,
-                Json(serde_json::json!({ "error": e })),
-            )
-                .into_response()
-        }
+        Err(e) => return e.into_response(),
     };
 
     let _permit = match state.ai_task_queue.acquire().await {
@@ -206,11 +184,7 @@ pub async fn merge_ai_export_chunks(
         Ok(result) => Json(result).into_response(),
         Err(e) => {
             tracing::error!("Chunk merge failed: {}", e);
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(serde_json::json!({ "error": e })),
-            )
-                .into_response()
+            e.into_response()
         }
     }
 }
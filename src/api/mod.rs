@@ -2,30 +2,47 @@
 //!
 //! Provides REST API endpoints for sessions, projects, memories, and search.
 
+mod admin_routes;
 mod auth;
+mod body_limit;
+mod compression;
 mod config_routes;
 mod context_routes;
+mod error;
 mod export_routes;
+mod pagination;
+mod rate_limit;
+mod read_only;
 pub mod routes;
-mod sse;
+pub(crate) mod sse;
+mod sync_routes;
+mod timeout;
 
 use crate::ai::queue::AiTaskQueue;
 use crate::ai::types::AiEvent;
-use crate::config::{Config, Storage};
+use crate::config::{ApiKeyConfig, Config, ServerConfig, Storage};
 use crate::db::Database;
 use crate::ephemeral::EphemeralIndex;
 use crate::error::Result;
+use crate::maintenance::MaintenanceMode;
+use crate::reload::ConfigReloader;
+use crate::scheduler::RunningTasks;
 use crate::watcher::WatcherEvent;
 
 use axum::{
+    extract::DefaultBodyLimit,
     middleware,
     routing::{delete, get, patch, post, put},
     Router,
 };
+use std::future::{Future, IntoFuture};
 use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::broadcast;
+use tokio_util::sync::CancellationToken;
 use tower_http::cors::{Any, CorsLayer};
+use tower_http::limit::RequestBodyLimitLayer;
 use tower_http::trace::TraceLayer;
 
 /// Application state shared across all handlers
@@ -37,7 +54,8 @@ pub struct AppState {
     pub ephemeral: Option<Arc<EphemeralIndex>>,
     /// Active storage mode
     pub storage: Storage,
-    pub api_key: Option<String>,
+    /// Accepted API keys with their scopes (empty = no auth)
+    pub api_keys: Vec<ApiKeyConfig>,
     /// Broadcast channel for SSE events from watcher
     pub event_tx: broadcast::Sender<WatcherEvent>,
     /// Broadcast channel for AI-related SSE events
@@ -46,6 +64,16 @@ pub struct AppState {
     pub ai_task_queue: AiTaskQueue,
     /// Path to the config file (for config API)
     pub config_path: std::path::PathBuf,
+    /// Pauses background work while enabled (admin API)
+    pub maintenance: MaintenanceMode,
+    /// Scheduler tasks with a run in progress (admin API)
+    pub running_tasks: RunningTasks,
+    /// Cancelled when the server starts shutting down; ends open SSE streams
+    pub shutdown: CancellationToken,
+    /// Numbered SSE events kept for `Last-Event-ID` replay
+    pub event_log: sse::EventLog,
+    /// Applies config file changes at runtime (None when embedded without a `Core`)
+    pub config_reload: Option<ConfigReloader>,
 }
 
 /// Start the HTTP API server
@@ -59,19 +87,31 @@ pub async fn serve(
     event_tx: broadcast::Sender<WatcherEvent>,
     ai_event_tx: broadcast::Sender<AiEvent>,
     ai_task_queue: AiTaskQueue,
+    maintenance: MaintenanceMode,
+    running_tasks: RunningTasks,
+    config_reload: Option<ConfigReloader>,
 ) -> Result<()> {
     let state = AppState {
         db: db.clone(),
         ephemeral,
         storage: config.storage.clone(),
-        api_key: config.server.api_key.clone(),
+        api_keys: config.server.effective_api_keys(),
         event_tx,
         ai_event_tx,
-        ai_task_queue,
+        ai_task_queue: ai_task_queue.clone(),
         config_path,
+        maintenance,
+        running_tasks,
+        shutdown: CancellationToken::new(),
+        event_log: sse::EventLog::new(config.server.sse_replay_buffer),
+        config_reload,
     };
+    let shutdown = state.shutdown.clone();
+    state
+        .event_log
+        .spawn_forwarder(&state.event_tx, &state.ai_event_tx);
 
-    let app = create_router(state);
+    let app = create_router(state, &config.server);
 
     // DB-specific initialization (instance UUID, instance name)
     if let Some(db) = &db {
@@ -132,18 +172,76 @@ pub async fn serve(
     tracing::info!("Listening on http://{}", addr);
 
     let listener = tokio::net::TcpListener::bind(addr).await?;
-    axum::serve(listener, app)
-        .with_graceful_shutdown(shutdown_signal())
+    let timeout = Duration::from_secs(config.server.shutdown_timeout_secs);
+    run_server(listener, app, shutdown_signal(), shutdown, timeout)
         .await
         .map_err(|e| crate::error::CoreError::Api(e.to_string()))?;
 
+    // Let running AI tasks finish writing their results before the process exits
+    let grace = Duration::from_secs(config.server.shutdown_grace_secs);
+    if ai_task_queue.available_permits() < ai_task_queue.max_concurrent() {
+        tracing::info!("Waiting up to {}s for running AI tasks", grace.as_secs());
+    }
+    if !ai_task_queue.drain(grace).await {
+        tracing::warn!(
+            "AI tasks still running after {}s, cancelled",
+            grace.as_secs()
+        );
+    }
+
     // _mdns_service is dropped here, which calls unregister() via Drop
 
     Ok(())
 }
 
+/// Serve `app` until `signal` fires, then shut down gracefully.
+///
+/// Cancels `shutdown` (closing SSE streams) and waits up to `timeout` for
+/// in-flight requests. Connections still open after that are abandoned and
+/// closed when the runtime exits.
+async fn run_server(
+    listener: tokio::net::TcpListener,
+    app: Router,
+    signal: impl Future<Output = ()> + Send + 'static,
+    shutdown: CancellationToken,
+    timeout: Duration,
+) -> std::io::Result<()> {
+    let graceful = {
+        let shutdown = shutdown.clone();
+        async move {
+            signal.await;
+            shutdown.cancel();
+        }
+    };
+    // Client addresses key the rate limiter for requests without an API key
+    let app = app.into_make_service_with_connect_info::<SocketAddr>();
+    let server = axum::serve(listener, app).with_graceful_shutdown(graceful);
+
+    tokio::select! {
+        result = server.into_future() => result,
+        _ = async {
+            shutdown.cancelled().await;
+            tokio::time::sleep(timeout).await;
+        } => {
+            tracing::warn!(
+                "Graceful shutdown timed out after {}s, closing remaining connections",
+                timeout.as_secs()
+            );
+            Ok(())
+        }
+    }
+}
+
 /// Create the API router with all routes
-fn create_router(state: AppState) -> Router {
+///
+/// `server` supplies the request timeout, the rate limit and the request body
+/// size limit.
+pub(crate) fn create_router(state: AppState, server: &ServerConfig) -> Router {
+    let request_timeout = match server.request_timeout_secs {
+        0 => None,
+        secs => Some(Duration::from_secs(secs)),
+    };
+
     // CORS configuration - allow all origins for development
     let cors = CorsLayer::new()
         .allow_origin(Any)
@@ -153,6 +251,8 @@ fn create_router(state: AppState) -> Router {
     // All API routes — each handler handles ephemeral mode internally
     // (returning empty results or serving from EphemeralIndex as appropriate)
     let api_routes = Router::new()
+        // LAN discovery
+        .route("/discover", get(routes::discover_instances))
         // Projects
         .route("/projects", get(routes::list_projects))
         .route("/projects", post(routes::create_project))
@@ -160,6 +260,10 @@ fn create_router(state: AppState) -> Router {
         .route("/projects/:id", get(routes::get_project))
         .route("/projects/:id", patch(routes::update_project))
         .route("/projects/:id", delete(routes::delete_project))
+        .route(
+            "/projects/:id/ai-settings",
+            patch(routes::update_project_ai_settings),
+        )
         .route(
             "/projects/:id/analytics",
             get(routes::get_project_analytics),
@@ -167,9 +271,11 @@ fn create_router(state: AppState) -> Router {
         // Sessions
         .route("/sessions", get(routes::list_sessions))
         .route("/sessions/limit", get(routes::get_session_limit_info))
+        .route("/sessions/bulk-delete", post(routes::bulk_delete_sessions))
         .route("/sessions/:id", get(routes::get_session))
         .route("/sessions/:id", patch(routes::update_session))
         .route("/sessions/:id", delete(routes::delete_session))
+        .route("/sessions/:id/restore", post(routes::restore_session))
         .route("/sessions/:id/messages", get(routes::get_session_messages))
         .route(
             "/sessions/:id/messages/:seq/content",
@@ -183,20 +289,49 @@ fn create_router(state: AppState) -> Router {
             "/sessions/:id/agent-summary",
             post(routes::update_agent_summary),
         )
+        .route("/sessions/:id/agents", get(routes::get_session_agents))
+        .route(
+            "/sessions/:id/composition",
+            get(routes::get_session_composition),
+        )
+        .route("/sessions/:id/summary", get(routes::get_session_summary))
         .route("/sessions/:id/markers", get(routes::get_session_markers))
+        .route("/sessions/:id/tags", post(routes::add_session_tags))
+        .route(
+            "/sessions/:id/tags/:tag",
+            delete(routes::remove_session_tag),
+        )
+        .route("/sessions/:id/intents", get(routes::get_session_intents))
+        .route("/intents/search", get(routes::search_intents))
         .route("/sessions/:id/search", get(routes::search_session))
+        .route(
+            "/sessions/:id/semantic-search",
+            post(routes::semantic_search_session),
+        )
         .route("/sessions/:id/bytes", get(routes::read_session_bytes))
+        .route("/sessions/:id/export", get(routes::export_session))
         // Search
         .route("/search", post(routes::search))
+        .route("/search/facets", get(routes::search_facets))
         // Memories
         .route("/memories", get(routes::list_memories))
         .route("/memories/search", post(routes::search_memories))
+        .route("/memories/merge", post(routes::merge_memories))
+        .route(
+            "/memories/semantic-search",
+            post(routes::semantic_search_memories),
+        )
         .route("/memories/:id", get(routes::get_memory))
         .route("/memories/:id", patch(routes::update_memory))
         .route("/memories/:id", delete(routes::delete_memory))
+        .route("/memories/:id/reembed", post(routes::reembed_memory))
         // Memory Stats & Tags
         .route("/projects/:id/memory-stats", get(routes::get_memory_stats))
         .route("/projects/:id/memory-tags", get(routes::get_memory_tags))
+        .route(
+            "/projects/:id/memories/export",
+            get(routes::export_project_memories),
+        )
         // Markers
         .route("/markers/:id", delete(routes::delete_marker))
         // AI Features
@@ -216,6 +351,10 @@ fn create_router(state: AppState) -> Router {
             "/ai/sessions/:id/markers",
             post(routes::trigger_marker_detection),
         )
+        .route(
+            "/ai/sessions/:id/tasks",
+            delete(routes::cancel_session_ai_tasks),
+        )
         .route("/ai/cli/status", get(routes::get_ai_cli_status))
         .route("/ai/pending-sessions", get(routes::get_pending_ai_sessions))
         // AI Export
@@ -223,18 +362,6 @@ fn create_router(state: AppState) -> Router {
             "/ai/export/capabilities",
             get(export_routes::get_ai_export_capabilities),
         )
-        .route(
-            "/ai/export/generate",
-            post(export_routes::generate_ai_export),
-        )
-        .route(
-            "/ai/export/chunk",
-            post(export_routes::process_ai_export_chunk),
-        )
-        .route(
-            "/ai/export/merge",
-            post(export_routes::merge_ai_export_chunks),
-        )
         // Memory Ranking
         .route(
             "/projects/:id/rank-memories",
@@ -248,8 +375,6 @@ fn create_router(state: AppState) -> Router {
         .route("/projects/:id/skills", get(routes::list_project_skills))
         .route("/projects/:id/skills/stats", get(routes::get_skill_stats))
         .route("/skills/:id", delete(routes::delete_skill_by_id))
-        // Embeddings
-        .route("/embeddings/backfill", post(routes::backfill_embeddings))
         // Context API
         .route("/context/project", get(context_routes::get_project_context))
         .route(
@@ -262,9 +387,11 @@ fn create_router(state: AppState) -> Router {
         )
         .route("/context/lifeboat", post(context_routes::save_lifeboat))
         .route("/context/search", post(context_routes::search_context))
+        .route("/context/pack", post(context_routes::get_context_pack))
         // Config API
         .route("/config", get(config_routes::get_config))
         .route("/config", put(config_routes::update_config))
+        .route("/config/validate", get(config_routes::validate_config))
         .route("/config/ai", get(config_routes::get_ai_config))
         .route("/config/ai", put(config_routes::update_ai_config))
         .route("/config/watch", get(config_routes::list_watch_paths))
@@ -273,12 +400,86 @@ fn create_router(state: AppState) -> Router {
             "/config/watch/:index",
             delete(config_routes::remove_watch_path),
         )
+        // Admin
+        .route("/admin/maintenance", get(admin_routes::get_maintenance))
+        .route("/admin/maintenance", post(admin_routes::set_maintenance))
+        .route("/admin/reload-config", post(admin_routes::reload_config))
+        .route(
+            "/admin/scheduler/history",
+            get(admin_routes::get_scheduler_history),
+        )
+        .route("/sync/changes", get(sync_routes::get_changes))
+        // Abort handlers that run past the request timeout. Routes added
+        // below this layer are long-running by design and exempt.
+        .layer(middleware::from_fn_with_state(
+            request_timeout,
+            timeout::request_timeout,
+        ))
+        // Long-running AI and admin work
+        .route(
+            "/sessions/:id/intents/extract",
+            post(routes::extract_session_intents),
+        )
+        .route(
+            "/ai/export/generate",
+            post(export_routes::generate_ai_export),
+        )
+        .route(
+            "/ai/export/chunk",
+            post(export_routes::process_ai_export_chunk),
+        )
+        .route(
+            "/ai/export/merge",
+            post(export_routes::merge_ai_export_chunks),
+        )
+        .route("/embeddings/backfill", post(routes::backfill_embeddings))
+        .route(
+            "/sessions/:id/embeddings/backfill",
+            post(routes::backfill_session_embeddings),
+        )
+        .route("/admin/backup", post(admin_routes::backup_database))
+        .route("/admin/reindex-fts", post(admin_routes::reindex_fts))
+        .route(
+            "/admin/scheduler/run/:task",
+            post(admin_routes::run_scheduler_task),
+        )
+        // Reject oversized request bodies with a JSON 413. Only request
+        // bodies are bounded; streamed responses (/bytes, /export, SSE)
+        // are unaffected.
+        .layer(RequestBodyLimitLayer::new(server.max_body_bytes))
+        .layer(DefaultBodyLimit::disable())
+        .layer(middleware::from_fn_with_state(
+            server.max_body_bytes,
+            body_limit::payload_too_large_json,
+        ))
+        // Session bundles carry whole transcripts, so imports get their own
+        // body limit and no request timeout
+        .merge(
+            Router::new()
+                .route("/admin/import", post(admin_routes::import_session))
+                .layer(RequestBodyLimitLayer::new(server.max_import_bytes))
+                .layer(DefaultBodyLimit::disable())
+                .layer(middleware::from_fn_with_state(
+                    server.max_import_bytes,
+                    body_limit::payload_too_large_json,
+                )),
+        )
+        // Throttle clients past `server.rate_limit`. SSE, added below, is exempt.
+        .layer(middleware::from_fn_with_state(
+            rate_limit::RateLimiter::new(&server.rate_limit),
+            rate_limit::rate_limit,
+        ))
         // Server-Sent Events
         .route("/events", get(sse::events_handler))
         // Apply auth middleware to all API routes
         .layer(middleware::from_fn_with_state(
             state.clone(),
             auth::auth_middleware,
+        ))
+        // Viewer mode rejects writes before any API key is considered
+        .layer(middleware::from_fn_with_state(
+            server.read_only,
+            read_only::reject_writes,
         ));
 
     Router::new()
@@ -286,6 +487,9 @@ fn create_router(state: AppState) -> Router {
         .route("/health", get(routes::health))
         // Nest protected routes under /api
         .nest("/api", api_routes)
+        // Compress responses the client accepts; SSE and ranges pass through
+        .layer(compression::layer())
+        .layer(middleware::map_response(compression::vary_accept_encoding))
         // Global middleware
         .layer(TraceLayer::new_for_http())
         .layer(cors)
@@ -329,7 +533,7 @@ async fn start_mdns_service(
         version: env!("CARGO_PKG_VERSION").to_string(),
         uuid,
         hostname,
-        api_key_required: config.server.api_key.is_some(),
+        api_key_required: !config.server.effective_api_keys().is_empty(),
         project_count,
         name: config.server.instance_name.clone(),
     };
@@ -338,7 +542,7 @@ async fn start_mdns_service(
 }
 
 /// Graceful shutdown signal handler
-async fn shutdown_signal() {
+pub(crate) async fn shutdown_signal() {
     let ctrl_c = async {
         tokio::signal::ctrl_c()
             .await
@@ -363,3 +567,129 @@ async fn shutdown_signal() {
 
     tracing::info!("Shutdown signal received, starting graceful shutdown");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support;
+
+    /// Run `app` on a random port; sending on the returned channel triggers shutdown
+    async fn start(
+        app: Router,
+        shutdown: CancellationToken,
+        timeout: Duration,
+    ) -> (
+        SocketAddr,
+        tokio::sync::oneshot::Sender<()>,
+        tokio::task::JoinHandle<std::io::Result<()>>,
+    ) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (tx, rx) = tokio::sync::oneshot::channel::<()>();
+        let signal = async move {
+            let _ = rx.await;
+        };
+        let server = tokio::spawn(run_server(listener, app, signal, shutdown, timeout));
+        (addr, tx, server)
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_closes_open_sse_streams() {
+        let tmp = tempfile::tempdir().unwrap();
+        let shutdown = CancellationToken::new();
+        let state = AppState {
+            shutdown: shutdown.clone(),
+            ..test_support::app_state(tmp.path())
+        };
+        // Long timeout: finishing promptly means the SSE stream was closed, not abandoned
+        let (addr, tx, server) = start(
+            create_router(state, &ServerConfig::default()),
+            shutdown,
+            Duration::from_secs(30),
+        )
+        .await;
+
+        let mut sse = reqwest::get(format!("http://{}/api/events", addr))
+            .await
+            .unwrap();
+        assert!(sse.status().is_success());
+
+        tx.send(()).unwrap();
+        while sse.chunk().await.unwrap().is_some() {}
+        tokio::time::timeout(Duration::from_secs(5), server)
+            .await
+            .expect("server should stop once SSE streams close")
+            .unwrap()
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_sse_reconnect_replays_missed_events() {
+        let tmp = tempfile::tempdir().unwrap();
+        let state = AppState {
+            event_log: sse::EventLog::with_first_id(16, 1),
+            ..test_support::app_state(tmp.path())
+        };
+        let event_tx = state.event_tx.clone();
+        state
+            .event_log
+            .spawn_forwarder(&state.event_tx, &state.ai_event_tx);
+        let (addr, _tx, _server) = start(
+            create_router(state, &ServerConfig::default()),
+            CancellationToken::new(),
+            Duration::from_secs(1),
+        )
+        .await;
+
+        // Events 1-3 happen while the client is disconnected
+        for session_id in ["s1", "s2", "s3"] {
+            event_tx
+                .send(WatcherEvent::SessionRemoved {
+                    session_id: session_id.to_string(),
+                })
+                .unwrap();
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let mut sse = reqwest::Client::new()
+            .get(format!("http://{}/api/events", addr))
+            .header("Last-Event-ID", "1")
+            .send()
+            .await
+            .unwrap();
+
+        let mut body = String::new();
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while !body.contains("id: 3") {
+                let chunk = sse.chunk().await.unwrap().unwrap();
+                body.push_str(&String::from_utf8_lossy(&chunk));
+            }
+        })
+        .await
+        .expect("missed events should be replayed");
+
+        assert!(!body.contains("\"s1\""), "already-seen event replayed");
+        let s2 = body.find("id: 2").unwrap();
+        let s3 = body.find("id: 3").unwrap();
+        assert!(s2 < s3);
+        assert!(body.contains("event: session:removed"));
+        assert!(body.contains("\"session_id\":\"s3\""));
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_gives_up_after_timeout() {
+        let app = Router::new().route("/hang", get(std::future::pending::<()>));
+        let (addr, tx, server) =
+            start(app, CancellationToken::new(), Duration::from_millis(200)).await;
+
+        tokio::spawn(reqwest::get(format!("http://{}/hang", addr)));
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        tx.send(()).unwrap();
+        tokio::time::timeout(Duration::from_secs(5), server)
+            .await
+            .expect("shutdown should not wait past the timeout")
+            .unwrap()
+            .unwrap();
+    }
+}
@@ -0,0 +1,49 @@
+//! Keyset pagination cursors
+//!
+//! A cursor is the `(timestamp, id)` of the last row on a page, encoded as an
+//! opaque URL-safe base64 token. The next page continues strictly after it, so
+//! rows inserted between requests can't shift the window.
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+
+/// Position after which the next page starts
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cursor {
+    pub timestamp: String,
+    pub id: String,
+}
+
+impl Cursor {
+    pub fn new(timestamp: impl Into<String>, id: impl Into<String>) -> Self {
+        Cursor {
+            timestamp: timestamp.into(),
+            id: id.into(),
+        }
+    }
+
+    /// Encode as an opaque token for the `cursor` query parameter
+    pub fn encode(&self) -> String {
+        let json = serde_json::json!([self.timestamp, self.id]).to_string();
+        URL_SAFE_NO_PAD.encode(json)
+    }
+
+    /// Decode a token produced by `encode`. Returns `None` for anything malformed.
+    pub fn decode(token: &str) -> Option<Self> {
+        let bytes = URL_SAFE_NO_PAD.decode(token).ok()?;
+        let (timestamp, id): (String, String) = serde_json::from_slice(&bytes).ok()?;
+        Some(Cursor { timestamp, id })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cursor_round_trip() {
+        let cursor = Cursor::new("2024-01-01T00:00:00Z", "abc:def");
+        assert_eq!(Cursor::decode(&cursor.encode()), Some(cursor));
+        assert_eq!(Cursor::decode("not a cursor"), None);
+    }
+}
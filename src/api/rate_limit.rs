@@ -0,0 +1,274 @@
+//! Per-client rate limiting middleware for API handlers
+
+use super::auth::AuthenticatedKey;
+use crate::config::RateLimitConfig;
+use axum::{
+    body::Body,
+    extract::{ConnectInfo, State},
+    http::{header, Request, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use std::collections::{BTreeMap, HashMap};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Clients tracked before the least recently seen one is forgotten
+const MAX_TRACKED_CLIENTS: usize = 10_000;
+
+/// Token buckets per client, shared by every request
+pub struct RateLimiter {
+    /// Tokens added per second
+    rate: f64,
+    /// Bucket capacity
+    burst: f64,
+    buckets: Mutex<Buckets>,
+}
+
+/// Buckets by client, with their clients ordered by last use
+#[derive(Default)]
+struct Buckets {
+    by_client: HashMap<String, Bucket>,
+    /// Client of each bucket by its `last_use`, oldest first
+    by_use: BTreeMap<u64, String>,
+    /// Incremented on every request
+    clock: u64,
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+    last_use: u64,
+}
+
+impl RateLimiter {
+    /// A limiter for `config`, or None when rate limiting is off
+    pub fn new(config: &RateLimitConfig) -> Option<Arc<Self>> {
+        if config.requests_per_min == 0 {
+            return None;
+        }
+        Some(Arc::new(RateLimiter {
+            rate: config.requests_per_min as f64 / 60.0,
+            burst: config.burst.unwrap_or(config.requests_per_min).max(1) as f64,
+            buckets: Mutex::new(Buckets::default()),
+        }))
+    }
+
+    /// Take a token from `client`'s bucket, or return how long until one is available
+    fn acquire(&self, client: &str, now: Instant) -> Result<(), Duration> {
+        let mut buckets = self.buckets.lock().unwrap();
+        let Buckets {
+            by_client,
+            by_use,
+            clock,
+        } = &mut *buckets;
+        *clock += 1;
+
+        match by_client.get(client) {
+            Some(bucket) => {
+                by_use.remove(&bucket.last_use);
+            }
+            None if by_client.len() >= MAX_TRACKED_CLIENTS => {
+                if let Some((_, oldest)) = by_use.pop_first() {
+                    by_client.remove(&oldest);
+                }
+            }
+            None => {}
+        }
+        let bucket = by_client.entry(client.to_string()).or_insert(Bucket {
+            tokens: self.burst,
+            updated: now,
+            last_use: 0,
+        });
+        bucket.last_use = *clock;
+        by_use.insert(*clock, client.to_string());
+
+        if self.refill(bucket, now) >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) / self.rate))
+        }
+    }
+
+    /// Add the tokens earned since the bucket was last updated; returns the new level
+    fn refill(&self, bucket: &mut Bucket, now: Instant) -> f64 {
+        let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.rate).min(self.burst);
+        bucket.updated = now;
+        bucket.tokens
+    }
+}
+
+/// Rate limiting middleware
+///
+/// Runs after authentication: requests made with an API key that
+/// `auth_middleware` accepted are counted against that key; others against the
+/// client's IP address, so made-up keys can't be used to get fresh buckets. A
+/// client past its limit gets 429 with `Retry-After` (whole seconds) until its
+/// bucket refills.
+///
+/// If no limiter is configured, all requests are allowed.
+pub async fn rate_limit(
+    State(limiter): State<Option<Arc<RateLimiter>>>,
+    request: Request<Body>,
+    next: Next,
+) -> Response {
+    let Some(limiter) = limiter else {
+        return next.run(request).await;
+    };
+
+    let client = match request.extensions().get::<AuthenticatedKey>() {
+        Some(AuthenticatedKey(key)) => format!("key:{}", key),
+        None => match request.extensions().get::<ConnectInfo<SocketAddr>>() {
+            Some(ConnectInfo(addr)) => format!("ip:{}", addr.ip()),
+            None => "ip:unknown".to_string(),
+        },
+    };
+
+    match limiter.acquire(&client, Instant::now()) {
+        Ok(()) => next.run(request).await,
+        Err(wait) => {
+            let retry_after = wait.as_secs_f64().ceil().max(1.0) as u64;
+            (
+                StatusCode::TOO_MANY_REQUESTS,
+                [(header::RETRY_AFTER, retry_after.to_string())],
+                Json(serde_json::json!({ "error": "Too many requests" })),
+            )
+                .into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{ApiKeyConfig, ApiKeyScope};
+    use axum::{middleware, routing::get, Router};
+    use tower::ServiceExt;
+
+    /// A limited `/search` and an exempt `/events` behind auth accepting `api_keys`
+    fn app(
+        requests_per_min: u32,
+        burst: Option<u32>,
+        api_keys: &[&str],
+    ) -> (tempfile::TempDir, Router) {
+        let limiter = RateLimiter::new(&RateLimitConfig {
+            requests_per_min,
+            burst,
+        });
+        let tmp = tempfile::tempdir().unwrap();
+        let state = crate::api::AppState {
+            api_keys: api_keys
+                .iter()
+                .map(|key| ApiKeyConfig {
+                    key: key.to_string(),
+                    scope: ApiKeyScope::default(),
+                })
+                .collect(),
+            ..crate::test_support::app_state(tmp.path())
+        };
+        let app = Router::new()
+            .route("/search", get(|| async { "results" }))
+            .layer(middleware::from_fn_with_state(limiter, rate_limit))
+            .route("/events", get(|| async { "stream" }))
+            .layer(middleware::from_fn_with_state(
+                state,
+                crate::api::auth::auth_middleware,
+            ));
+        (tmp, app)
+    }
+
+    async fn get_as(app: Router, uri: &str, key: Option<&str>) -> Response {
+        let mut request = Request::get(uri);
+        if let Some(key) = key {
+            request = request.header("Authorization", format!("Bearer {}", key));
+        }
+        app.oneshot(request.body(Body::empty()).unwrap())
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_requests_past_limit_get_429() {
+        let (_tmp, app) = app(60, Some(2), &["a", "b"]);
+        for _ in 0..2 {
+            let resp = get_as(app.clone(), "/search", Some("a")).await;
+            assert_eq!(resp.status(), StatusCode::OK);
+        }
+        let resp = get_as(app.clone(), "/search", Some("a")).await;
+        assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(resp.headers()[header::RETRY_AFTER], "1");
+
+        // Other keys have their own bucket, and exempt routes aren't counted
+        let resp = get_as(app.clone(), "/search", Some("b")).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let resp = get_as(app, "/events", Some("a")).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_unvalidated_keys_share_the_ip_bucket() {
+        // Without configured keys nothing is validated, so made-up keys
+        // don't get buckets of their own
+        let (_tmp, app) = app(60, Some(2), &[]);
+        for key in ["x", "y"] {
+            let resp = get_as(app.clone(), "/search", Some(key)).await;
+            assert_eq!(resp.status(), StatusCode::OK);
+        }
+        let resp = get_as(app, "/search", Some("z")).await;
+        assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+    }
+
+    #[tokio::test]
+    async fn test_no_limit_when_disabled() {
+        let (_tmp, app) = app(0, None, &[]);
+        for _ in 0..10 {
+            let resp = get_as(app.clone(), "/search", None).await;
+            assert_eq!(resp.status(), StatusCode::OK);
+        }
+    }
+
+    #[test]
+    fn test_bucket_refills_over_time() {
+        let limiter = RateLimiter::new(&RateLimitConfig {
+            requests_per_min: 120,
+            burst: Some(1),
+        })
+        .unwrap();
+        let start = Instant::now();
+        assert!(limiter.acquire("ip:127.0.0.1", start).is_ok());
+        let wait = limiter.acquire("ip:127.0.0.1", start).unwrap_err();
+        assert_eq!(wait, Duration::from_millis(500));
+        assert!(limiter
+            .acquire("ip:127.0.0.1", start + Duration::from_millis(500))
+            .is_ok());
+    }
+
+    #[test]
+    fn test_least_recently_seen_client_is_forgotten() {
+        let limiter = RateLimiter::new(&RateLimitConfig {
+            requests_per_min: 1,
+            burst: Some(1),
+        })
+        .unwrap();
+        let now = Instant::now();
+        assert!(limiter.acquire("ip:a", now).is_ok());
+        assert!(limiter.acquire("ip:b", now).is_ok());
+        for i in 2..MAX_TRACKED_CLIENTS {
+            assert!(limiter.acquire(&format!("ip:{}", i), now).is_ok());
+        }
+        // `a` is seen again, so the next new client evicts `b` instead
+        assert!(limiter.acquire("ip:a", now).is_err());
+        assert!(limiter.acquire("ip:new", now).is_ok());
+
+        assert!(limiter.acquire("ip:a", now).is_err());
+        assert!(limiter.acquire("ip:b", now).is_ok());
+        assert_eq!(
+            limiter.buckets.lock().unwrap().by_client.len(),
+            MAX_TRACKED_CLIENTS
+        );
+    }
+}
@@ -0,0 +1,118 @@
+//! Read-only ("viewer") mode middleware for API handlers
+
+use axum::{
+    body::Body,
+    extract::State,
+    http::{Method, Request, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+
+/// POST endpoints that only query data, relative to `/api`
+const QUERY_POSTS: &[&str] = &[
+    "/search",
+    "/memories/search",
+    "/memories/semantic-search",
+    "/context/session",
+    "/context/search",
+    "/context/pack",
+];
+
+/// Read-only mode middleware
+///
+/// When `server.read_only` is set, every request that could change data
+/// (create, update, delete, AI triggers, imports, backfills, admin actions)
+/// is rejected with 403, whatever API key it carries. Reads, searches and
+/// SSE are unaffected.
+pub async fn reject_writes(
+    State(read_only): State<bool>,
+    request: Request<Body>,
+    next: Next,
+) -> Response {
+    if !read_only || is_read(request.method(), request.uri().path()) {
+        return next.run(request).await;
+    }
+
+    (
+        StatusCode::FORBIDDEN,
+        Json(serde_json::json!({ "error": "Server is in read-only mode" })),
+    )
+        .into_response()
+}
+
+/// Whether a request only reads data. `path` is relative to `/api`.
+pub(super) fn is_read(method: &Method, path: &str) -> bool {
+    match *method {
+        Method::GET | Method::HEAD | Method::OPTIONS => true,
+        Method::POST => {
+            QUERY_POSTS.contains(&path)
+                || (path.starts_with("/sessions/") && path.ends_with("/semantic-search"))
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{
+        middleware,
+        routing::{delete, get, post},
+        Router,
+    };
+    use tower::ServiceExt;
+
+    fn app(read_only: bool) -> Router {
+        let api = Router::new()
+            .route("/sessions", get(|| async { "sessions" }))
+            .route("/sessions/:id", delete(|| async { StatusCode::NO_CONTENT }))
+            .route("/search", post(|| async { "results" }))
+            .route("/memories/merge", post(|| async { "merged" }))
+            .layer(middleware::from_fn_with_state(read_only, reject_writes));
+        Router::new().nest("/api", api)
+    }
+
+    async fn status(app: Router, method: Method, uri: &str) -> StatusCode {
+        app.oneshot(
+            Request::builder()
+                .method(method)
+                .uri(uri)
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap()
+        .status()
+    }
+
+    #[tokio::test]
+    async fn test_read_only_forbids_writes() {
+        let app = app(true);
+        assert_eq!(
+            status(app.clone(), Method::DELETE, "/api/sessions/s1").await,
+            StatusCode::FORBIDDEN
+        );
+        assert_eq!(
+            status(app.clone(), Method::POST, "/api/memories/merge").await,
+            StatusCode::FORBIDDEN
+        );
+        assert_eq!(
+            status(app.clone(), Method::GET, "/api/sessions").await,
+            StatusCode::OK
+        );
+        // Search is a POST but doesn't write
+        assert_eq!(
+            status(app, Method::POST, "/api/search").await,
+            StatusCode::OK
+        );
+    }
+
+    #[tokio::test]
+    async fn test_writes_allowed_when_not_read_only() {
+        assert_eq!(
+            status(app(false), Method::DELETE, "/api/sessions/s1").await,
+            StatusCode::NO_CONTENT
+        );
+    }
+}
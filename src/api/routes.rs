@@ -1,6 +1,14 @@
 //! HTTP route handlers for the API
 
+use super::pagination::Cursor;
 use super::AppState;
+pub use crate::api_types::{
+    ListProjectsQuery, ListSessionsQuery, SearchRequest, TitleGenerationRequest,
+};
+use crate::db::search::{
+    fts_match_query, fulltext_count, fulltext_match_sql, fulltext_search, is_fts_syntax_error,
+    search_result_json, CreatedRange, SearchFilters, SNIPPET_SQL,
+};
 use axum::{
     extract::{Path, Query, State},
     http::StatusCode,
@@ -17,7 +25,8 @@ pub async fn health(State(state): State<AppState>) -> impl IntoResponse {
     let mut resp = serde_json::json!({
         "status": "ok",
         "version": if cfg!(debug_assertions) { "dev" } else { env!("CARGO_PKG_VERSION") },
-        "storage": if state.storage.is_db() { "db" } else { "ephemeral" }
+        "storage": if state.storage.is_db() { "db" } else { "ephemeral" },
+        "maintenance": state.maintenance.is_enabled(),
     });
 
     // In DB mode, include instance UUID and name from database
@@ -45,15 +54,38 @@ pub async fn health(State(state): State<AppState>) -> impl IntoResponse {
 }
 
 // ============================================================================
-// Projects
+// Discovery
 // ============================================================================
 
 #[derive(Debug, Deserialize)]
-pub struct ListProjectsQuery {
-    pub limit: Option<i64>,
-    pub offset: Option<i64>,
+pub struct DiscoverQuery {
+    /// How long to listen for announcements (default 2000, max 10000)
+    pub timeout_ms: Option<u64>,
+}
+
+/// GET /api/discover
+/// Other yocore instances announcing themselves via mDNS on the local network.
+pub async fn discover_instances(Query(query): Query<DiscoverQuery>) -> impl IntoResponse {
+    let timeout =
+        std::time::Duration::from_millis(query.timeout_ms.unwrap_or(2000).clamp(100, 10_000));
+    let result = tokio::task::spawn_blocking(move || crate::mdns::browse(timeout))
+        .await
+        .unwrap_or_else(|e| Err(format!("Discovery task failed: {}", e)));
+
+    match result {
+        Ok(instances) => Json(serde_json::json!({ "instances": instances })).into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({ "error": e })),
+        )
+            .into_response(),
+    }
 }
 
+// ============================================================================
+// Projects
+// ============================================================================
+
 pub async fn list_projects(
     State(state): State<AppState>,
     Query(query): Query<ListProjectsQuery>,
@@ -88,7 +120,7 @@ pub async fn list_projects(
         .with_read_conn(move |conn| {
             let mut stmt = conn.prepare(
                 "SELECT id, name, folder_path, description, repo_url, language, framework,
-                        auto_sync, longest_streak, created_at, updated_at
+                        auto_sync, created_at, updated_at
                  FROM projects
                  ORDER BY updated_at DESC
                  LIMIT ? OFFSET ?",
@@ -105,9 +137,9 @@ pub async fn list_projects(
                         "language": row.get::<_, Option<String>>(5)?,
                         "framework": row.get::<_, Option<String>>(6)?,
                         "auto_sync": row.get::<_, bool>(7)?,
-                        "longest_streak": row.get::<_, i64>(8)?,
-                        "created_at": row.get::<_, String>(9)?,
-                        "updated_at": row.get::<_, String>(10)?,
+                        "longest_streak": project_longest_streak(conn, &row.get::<_, String>(0)?)?,
+                        "created_at": row.get::<_, String>(8)?,
+                        "updated_at": row.get::<_, String>(9)?,
                     }))
                 })?
                 .filter_map(|r| r.ok())
@@ -162,7 +194,10 @@ pub async fn create_project(
         .into_response();
     }
 
-    let id = uuid::Uuid::new_v4().to_string();
+    let upsert = Config::from_file(&state.config_path)
+        .map(|c| c.database.duplicate_projects == crate::config::DuplicateProjectPolicy::Upsert)
+        .unwrap_or(false);
+    let id = state.db.as_ref().unwrap().new_id();
     let now = chrono::Utc::now().to_rfc3339();
 
     let id_clone = id.clone();
@@ -175,32 +210,87 @@ pub async fn create_project(
         .as_ref()
         .unwrap()
         .with_conn(move |conn| {
-            conn.execute(
-                "INSERT INTO projects (id, name, folder_path, description, repo_url, language, framework, created_at, updated_at)
-                 VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
-                rusqlite::params![
-                    id_clone,
-                    req.name,
-                    req.folder_path,
-                    req.description,
-                    req.repo_url,
-                    req.language,
-                    req.framework,
-                    now_clone,
-                    now_clone
-                ],
-            )
+            let existing: Option<(String, String)> = conn
+                .query_row(
+                    "SELECT id, created_at FROM projects WHERE folder_path = ?",
+                    [&req.folder_path],
+                    |row| Ok((row.get(0)?, row.get(1)?)),
+                )
+                .ok();
+
+            match existing {
+                Some((existing_id, _)) if !upsert => Ok(Err(existing_id)),
+                Some((existing_id, created_at)) => {
+                    conn.execute(
+                        "UPDATE projects SET name = ?1,
+                            description = COALESCE(?2, description),
+                            repo_url = COALESCE(?3, repo_url),
+                            language = COALESCE(?4, language),
+                            framework = COALESCE(?5, framework),
+                            updated_at = ?6
+                         WHERE id = ?7",
+                        rusqlite::params![
+                            req.name,
+                            req.description,
+                            req.repo_url,
+                            req.language,
+                            req.framework,
+                            now_clone,
+                            existing_id
+                        ],
+                    )?;
+                    Ok(Ok((existing_id, created_at)))
+                }
+                None => {
+                    conn.execute(
+                        "INSERT INTO projects (id, name, folder_path, description, repo_url, language, framework, created_at, updated_at)
+                         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+                        rusqlite::params![
+                            id_clone,
+                            req.name,
+                            req.folder_path,
+                            req.description,
+                            req.repo_url,
+                            req.language,
+                            req.framework,
+                            now_clone,
+                            now_clone
+                        ],
+                    )?;
+                    Ok(Ok((id_clone.clone(), now_clone.clone())))
+                }
+            }
         })
         .await;
 
     match result {
-        Ok(_) => Json(serde_json::json!({
+        Ok(Ok((id, created_at))) => Json(serde_json::json!({
             "id": id,
             "name": name,
             "folder_path": folder_path,
-            "created_at": now
+            "created_at": created_at
         }))
         .into_response(),
+        Ok(Err(existing_id)) => (
+            StatusCode::CONFLICT,
+            Json(serde_json::json!({
+                "error": format!("A project already exists for folder: {}", folder_path),
+                "project_id": existing_id,
+            })),
+        )
+            .into_response(),
+        Err(e @ rusqlite::Error::SqliteFailure(..))
+            if e.sqlite_error_code() == Some(rusqlite::ErrorCode::ConstraintViolation) =>
+        {
+            // Lost a race with another insert for the same folder
+            (
+                StatusCode::CONFLICT,
+                Json(serde_json::json!({
+                    "error": format!("A project already exists for folder: {}", folder_path),
+                })),
+            )
+                .into_response()
+        }
         Err(e) => (
             StatusCode::INTERNAL_SERVER_ERROR,
             Json(serde_json::json!({ "error": e.to_string() })),
@@ -235,7 +325,7 @@ pub async fn get_project(
         .with_read_conn(move |conn| {
             conn.query_row(
                 "SELECT id, name, folder_path, description, repo_url, language, framework,
-                        auto_sync, longest_streak, created_at, updated_at
+                        auto_sync, created_at, updated_at
                  FROM projects WHERE id = ?",
                 [&id],
                 |row| {
@@ -248,9 +338,9 @@ pub async fn get_project(
                         "language": row.get::<_, Option<String>>(5)?,
                         "framework": row.get::<_, Option<String>>(6)?,
                         "auto_sync": row.get::<_, bool>(7)?,
-                        "longest_streak": row.get::<_, i64>(8)?,
-                        "created_at": row.get::<_, String>(9)?,
-                        "updated_at": row.get::<_, String>(10)?,
+                        "longest_streak": project_longest_streak(conn, &row.get::<_, String>(0)?)?,
+                        "created_at": row.get::<_, String>(8)?,
+                        "updated_at": row.get::<_, String>(9)?,
                     }))
                 },
             )
@@ -279,6 +369,8 @@ pub struct UpdateProjectRequest {
     pub repo_url: Option<String>,
     pub language: Option<String>,
     pub framework: Option<String>,
+    /// Whether the watcher stores new sessions from this project's folder
+    pub auto_sync: Option<bool>,
 }
 
 pub async fn update_project(
@@ -313,29 +405,33 @@ pub async fn update_project(
         .with_conn(move |conn| {
             // Build dynamic update query
             let mut updates = vec!["updated_at = ?"];
-            let mut params: Vec<Box<dyn rusqlite::ToSql>> = vec![Box::new(now_clone)];
+            let mut params: Vec<Box<dyn rusqlite::ToSql>> = vec![Box::new(now_clone.clone())];
 
-            if let Some(name) = req.name {
+            if let Some(name) = req.name.clone() {
                 updates.push("name = ?");
                 params.push(Box::new(name));
             }
-            if let Some(desc) = req.description {
+            if let Some(desc) = req.description.clone() {
                 updates.push("description = ?");
                 params.push(Box::new(desc));
             }
-            if let Some(repo) = req.repo_url {
+            if let Some(repo) = req.repo_url.clone() {
                 updates.push("repo_url = ?");
                 params.push(Box::new(repo));
             }
-            if let Some(lang) = req.language {
+            if let Some(lang) = req.language.clone() {
                 updates.push("language = ?");
                 params.push(Box::new(lang));
             }
-            if let Some(fw) = req.framework {
+            if let Some(fw) = req.framework.clone() {
                 updates.push("framework = ?");
                 params.push(Box::new(fw));
             }
-            params.push(Box::new(id_clone));
+            if let Some(auto_sync) = req.auto_sync {
+                updates.push("auto_sync = ?");
+                params.push(Box::new(auto_sync));
+            }
+            params.push(Box::new(id_clone.clone()));
 
             let query = format!("UPDATE projects SET {} WHERE id = ?", updates.join(", "));
             let params_refs: Vec<&dyn rusqlite::ToSql> =
@@ -364,6 +460,124 @@ pub async fn update_project(
     }
 }
 
+/// Deserialize a field that distinguishes absent (`None`) from `null` (`Some(None)`)
+fn double_option<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+/// Body of `PATCH /api/projects/:id/ai-settings`. An omitted field is left
+/// unchanged; `null` clears the override so the global setting applies.
+#[derive(Debug, Deserialize)]
+pub struct UpdateProjectAiSettingsRequest {
+    #[serde(default, deserialize_with = "double_option")]
+    pub title_generation: Option<Option<bool>>,
+    #[serde(default, deserialize_with = "double_option")]
+    pub memory_extraction: Option<Option<bool>>,
+    #[serde(default, deserialize_with = "double_option")]
+    pub skills_discovery: Option<Option<bool>>,
+}
+
+/// Override the global AI feature toggles for one project
+pub async fn update_project_ai_settings(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(req): Json<UpdateProjectAiSettingsRequest>,
+) -> impl IntoResponse {
+    let Some(db) = &state.db else {
+        return (
+            StatusCode::NOT_IMPLEMENTED,
+            Json(serde_json::json!({ "error": "Not available in ephemeral mode" })),
+        )
+            .into_response();
+    };
+
+    let now = chrono::Utc::now().to_rfc3339();
+    let project_id = id.clone();
+    let now_clone = now.clone();
+    let result = db
+        .with_conn(move |conn| {
+            use rusqlite::OptionalExtension;
+            let exists: bool = conn.query_row(
+                "SELECT EXISTS(SELECT 1 FROM projects WHERE id = ?)",
+                [&project_id],
+                |row| row.get(0),
+            )?;
+            if !exists {
+                return Ok(None);
+            }
+
+            let mut settings = conn
+                .query_row(
+                    "SELECT title_generation, memory_extraction, skills_discovery
+                     FROM project_ai_settings WHERE project_id = ?",
+                    [&project_id],
+                    |row| {
+                        Ok(ProjectAiSettings {
+                            title_generation: row.get(0)?,
+                            memory_extraction: row.get(1)?,
+                            skills_discovery: row.get(2)?,
+                        })
+                    },
+                )
+                .optional()?
+                .unwrap_or_default();
+            if let Some(v) = req.title_generation {
+                settings.title_generation = v;
+            }
+            if let Some(v) = req.memory_extraction {
+                settings.memory_extraction = v;
+            }
+            if let Some(v) = req.skills_discovery {
+                settings.skills_discovery = v;
+            }
+
+            conn.execute(
+                "INSERT INTO project_ai_settings
+                    (project_id, title_generation, memory_extraction, skills_discovery, updated_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5)
+                 ON CONFLICT(project_id) DO UPDATE SET
+                    title_generation = excluded.title_generation,
+                    memory_extraction = excluded.memory_extraction,
+                    skills_discovery = excluded.skills_discovery,
+                    updated_at = excluded.updated_at",
+                rusqlite::params![
+                    project_id,
+                    settings.title_generation,
+                    settings.memory_extraction,
+                    settings.skills_discovery,
+                    now_clone
+                ],
+            )?;
+            Ok::<_, rusqlite::Error>(Some(settings))
+        })
+        .await;
+
+    match result {
+        Ok(Some(settings)) => Json(serde_json::json!({
+            "project_id": id,
+            "title_generation": settings.title_generation,
+            "memory_extraction": settings.memory_extraction,
+            "skills_discovery": settings.skills_discovery,
+            "updated_at": now
+        }))
+        .into_response(),
+        Ok(None) => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": "Project not found" })),
+        )
+            .into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({ "error": e.to_string() })),
+        )
+            .into_response(),
+    }
+}
+
 pub async fn delete_project(
     State(state): State<AppState>,
     Path(id): Path<String>,
@@ -380,20 +594,26 @@ pub async fn delete_project(
             .into_response();
     }
 
-    let result = state
-        .db
-        .as_ref()
-        .unwrap()
-        .with_conn(move |conn| conn.execute("DELETE FROM projects WHERE id = ?", [&id]))
+    let db = state.db.clone().unwrap();
+    let result = db
+        .with_conn(move |conn| {
+            // Memories go with the project (cascade); collect them for the ANN index
+            let memory_ids = crate::db::memory_ids_where(conn, "project_id = ?", &id)?;
+            let deleted = conn.execute("DELETE FROM projects WHERE id = ?", [&id])?;
+            Ok::<_, rusqlite::Error>((deleted, memory_ids))
+        })
         .await;
 
     match result {
-        Ok(0) => (
+        Ok((0, _)) => (
             StatusCode::NOT_FOUND,
             Json(serde_json::json!({ "error": "Project not found" })),
         )
             .into_response(),
-        Ok(_) => StatusCode::NO_CONTENT.into_response(),
+        Ok((_, memory_ids)) => {
+            db.unindex_memories(&memory_ids);
+            StatusCode::NO_CONTENT.into_response()
+        }
         Err(e) => (
             StatusCode::INTERNAL_SERVER_ERROR,
             Json(serde_json::json!({ "error": e.to_string() })),
@@ -409,17 +629,36 @@ pub async fn delete_project(
 #[derive(Debug, Deserialize)]
 pub struct ResolveProjectQuery {
     pub path: String,
+    /// Resolve to the project owning the nearest ancestor of `path`
+    #[serde(default)]
+    pub fuzzy: bool,
 }
 
-/// GET /api/projects/resolve?path=<CWD>
+/// GET /api/projects/resolve?path=<CWD>[&fuzzy=true]
 /// Resolves a filesystem path to a project (exact match, Claude path conversion, prefix match).
+/// With `fuzzy=true`, any path inside a project tree resolves to its deepest owning project;
+/// 409 is returned when more than one project matches at that level.
 pub async fn resolve_project(
     State(state): State<AppState>,
     Query(query): Query<ResolveProjectQuery>,
 ) -> impl IntoResponse {
     if let Some(idx) = &state.ephemeral {
         let path = query.path;
-        return match idx.resolve_project_by_folder(&path) {
+        let project = if query.fuzzy {
+            std::path::Path::new(path.trim_end_matches('/'))
+                .ancestors()
+                .map(|a| a.to_string_lossy().to_string())
+                .take_while(|a| !a.is_empty() && a != "/")
+                .find_map(|a| {
+                    idx.resolve_project_by_folder(&a).or_else(|| {
+                        crate::mcp::db::convert_to_claude_project_path(&a)
+                            .and_then(|c| idx.resolve_project_by_folder(&c))
+                    })
+                })
+        } else {
+            idx.resolve_project_by_folder(&path)
+        };
+        return match project {
             Some(p) => Json(serde_json::json!({
                 "id": p.id,
                 "name": p.name,
@@ -440,6 +679,10 @@ pub async fn resolve_project(
     let path = query.path;
     let path_for_error = path.clone();
 
+    if query.fuzzy {
+        return resolve_project_fuzzy(db, path).await;
+    }
+
     let result = tokio::task::spawn_blocking(move || {
         let mcp_db = crate::mcp::db::McpDb::new(db);
         mcp_db.get_project_by_path_prefix(&path)
@@ -471,6 +714,59 @@ pub async fn resolve_project(
     }
 }
 
+async fn resolve_project_fuzzy(
+    db: std::sync::Arc<crate::db::Database>,
+    path: String,
+) -> axum::response::Response {
+    let path_for_error = path.clone();
+    let result = tokio::task::spawn_blocking(move || {
+        let mcp_db = crate::mcp::db::McpDb::new(db);
+        mcp_db.find_projects_for_path(&path)
+    })
+    .await;
+
+    match result {
+        Ok(Ok(projects)) => match projects.as_slice() {
+            [] => (
+                StatusCode::NOT_FOUND,
+                Json(serde_json::json!({ "error": format!("No project found for path: {}", path_for_error) })),
+            )
+                .into_response(),
+            [project] => Json(serde_json::json!({
+                "id": project.id,
+                "name": project.name,
+                "folder_path": project.folder_path,
+            }))
+            .into_response(),
+            candidates => (
+                StatusCode::CONFLICT,
+                Json(serde_json::json!({
+                    "error": format!("Multiple projects match path: {}", path_for_error),
+                    "candidates": candidates
+                        .iter()
+                        .map(|p| serde_json::json!({
+                            "id": p.id,
+                            "name": p.name,
+                            "folder_path": p.folder_path,
+                        }))
+                        .collect::<Vec<_>>(),
+                })),
+            )
+                .into_response(),
+        },
+        Ok(Err(e)) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({ "error": e })),
+        )
+            .into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({ "error": e.to_string() })),
+        )
+            .into_response(),
+    }
+}
+
 // ============================================================================
 // Project Analytics
 // ============================================================================
@@ -485,11 +781,105 @@ pub struct ProjectStats {
     pub total_output_tokens: i64,
     pub total_cache_read_tokens: i64,
     pub total_cache_creation_tokens: i64,
+    /// Part of `total_output_tokens` estimated from message text (`parser.estimate_tokens`)
+    pub estimated_output_tokens: i64,
     pub models_used: std::collections::HashMap<String, i64>,
     pub user_messages: i64,
     pub assistant_messages: i64,
     pub tool_uses: i64,
     pub tool_results: i64,
+    /// Token totals and estimated cost for each model, priced by `ai.model_pricing`
+    pub model_costs: Vec<ModelCost>,
+    /// Sum of `model_costs`
+    pub estimated_cost_usd: f64,
+    /// Models with no `ai.model_pricing` entry; they contribute zero cost
+    pub unpriced_models: Vec<String>,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct ModelCost {
+    pub model: String,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    pub cache_read_tokens: i64,
+    pub cache_creation_tokens: i64,
+    pub estimated_cost_usd: f64,
+}
+
+/// Price per-model token totals `(model, input, output, cache_read, cache_creation)`.
+///
+/// Returns the per-model costs, their total, and the models without pricing.
+fn estimate_model_costs(
+    usage: Vec<(String, i64, i64, i64, i64)>,
+    pricing: &std::collections::HashMap<String, crate::config::ModelPricing>,
+) -> (Vec<ModelCost>, f64, Vec<String>) {
+    let mut unpriced_models = Vec::new();
+    let model_costs: Vec<ModelCost> = usage
+        .into_iter()
+        .map(|(model, input, output, cache_read, cache_creation)| {
+            let estimated_cost_usd = match pricing.get(&model) {
+                Some(price) => price.cost(input, output, cache_read, cache_creation),
+                None => {
+                    unpriced_models.push(model.clone());
+                    0.0
+                }
+            };
+            ModelCost {
+                model,
+                input_tokens: input,
+                output_tokens: output,
+                cache_read_tokens: cache_read,
+                cache_creation_tokens: cache_creation,
+                estimated_cost_usd,
+            }
+        })
+        .collect();
+    let total = model_costs.iter().map(|c| c.estimated_cost_usd).sum();
+    (model_costs, total, unpriced_models)
+}
+
+/// Distinct UTC days with visible sessions in a project, newest first
+fn project_active_dates(
+    conn: &rusqlite::Connection,
+    project_id: &str,
+) -> rusqlite::Result<Vec<String>> {
+    let mut stmt = conn.prepare(
+        "SELECT DISTINCT DATE(created_at) FROM sessions
+         WHERE project_id = ? AND is_hidden = 0 AND deleted_at IS NULL
+         ORDER BY DATE(created_at) DESC",
+    )?;
+    let dates = stmt.query_map([project_id], |row| row.get::<_, String>(0))?;
+    dates.collect()
+}
+
+/// A project's `longest_streak`, computed from its sessions on every read so
+/// GETs never write
+fn project_longest_streak(conn: &rusqlite::Connection, project_id: &str) -> rusqlite::Result<i64> {
+    Ok(longest_streak(&project_active_dates(conn, project_id)?))
+}
+
+/// Longest run of consecutive calendar days among `YYYY-MM-DD` dates (UTC, any
+/// order, duplicates ignored). Unparseable dates are skipped.
+fn longest_streak(active_dates: &[String]) -> i64 {
+    let mut dates: Vec<chrono::NaiveDate> = active_dates
+        .iter()
+        .filter_map(|d| chrono::NaiveDate::parse_from_str(d, "%Y-%m-%d").ok())
+        .collect();
+    dates.sort_unstable();
+    dates.dedup();
+
+    let mut longest = 0;
+    let mut current = 0;
+    let mut previous: Option<chrono::NaiveDate> = None;
+    for date in dates {
+        current = match previous {
+            Some(p) if p.succ_opt() == Some(date) => current + 1,
+            _ => 1,
+        };
+        longest = longest.max(current);
+        previous = Some(date);
+    }
+    longest
 }
 
 #[derive(Debug, serde::Serialize)]
@@ -560,11 +950,15 @@ pub async fn get_project_analytics(
                 total_output_tokens: 0,
                 total_cache_read_tokens: 0,
                 total_cache_creation_tokens: 0,
+                estimated_output_tokens: 0,
                 models_used: std::collections::HashMap::new(),
                 user_messages: 0,
                 assistant_messages: 0,
                 tool_uses: 0,
                 tool_results: 0,
+                model_costs: vec![],
+                estimated_cost_usd: 0.0,
+                unpriced_models: vec![],
             },
             session_metrics: vec![],
             active_dates: vec![],
@@ -575,6 +969,10 @@ pub async fn get_project_analytics(
         .into_response();
     }
 
+    let pricing = Config::from_file(&state.config_path)
+        .map(|c| c.ai.model_pricing)
+        .unwrap_or_default();
+
     let result = state
         .db
         .as_ref()
@@ -583,7 +981,7 @@ pub async fn get_project_analytics(
             // 1. Project Stats
             let total_sessions: i64 = conn
                 .query_row(
-                    "SELECT COUNT(*) FROM sessions WHERE project_id = ? AND is_hidden = 0",
+                    "SELECT COUNT(*) FROM sessions WHERE project_id = ? AND is_hidden = 0 AND deleted_at IS NULL",
                     [&project_id],
                     |row| row.get(0),
                 )
@@ -591,7 +989,7 @@ pub async fn get_project_analytics(
 
             let total_duration_ms: i64 = conn
                 .query_row(
-                    "SELECT COALESCE(SUM(duration_ms), 0) FROM sessions WHERE project_id = ? AND is_hidden = 0",
+                    "SELECT COALESCE(SUM(duration_ms), 0) FROM sessions WHERE project_id = ? AND is_hidden = 0 AND deleted_at IS NULL",
                     [&project_id],
                     |row| row.get(0),
                 )
@@ -609,34 +1007,35 @@ pub async fn get_project_analytics(
                         SUM(CASE WHEN role = 'user' AND tool_name IS NOT NULL THEN 1 ELSE 0 END)
                      FROM session_messages sm
                      JOIN sessions s ON sm.session_id = s.id
-                     WHERE s.project_id = ? AND s.is_hidden = 0",
+                     WHERE s.project_id = ? AND s.is_hidden = 0 AND s.deleted_at IS NULL",
                     [&project_id],
                     |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?, row.get(5)?)),
                 )
                 .unwrap_or((0, 0, 0, 0, 0, 0));
 
             // Token totals
-            let (total_input_tokens, total_output_tokens, total_cache_read_tokens, total_cache_creation_tokens): (i64, i64, i64, i64) = conn
+            let (total_input_tokens, total_output_tokens, total_cache_read_tokens, total_cache_creation_tokens, estimated_output_tokens): (i64, i64, i64, i64, i64) = conn
                 .query_row(
                     "SELECT
                         COALESCE(SUM(input_tokens), 0),
                         COALESCE(SUM(output_tokens), 0),
                         COALESCE(SUM(cache_read_tokens), 0),
-                        COALESCE(SUM(cache_creation_tokens), 0)
+                        COALESCE(SUM(cache_creation_tokens), 0),
+                        COALESCE(SUM(CASE WHEN tokens_estimated = 1 THEN output_tokens END), 0)
                      FROM session_messages sm
                      JOIN sessions s ON sm.session_id = s.id
-                     WHERE s.project_id = ? AND s.is_hidden = 0",
+                     WHERE s.project_id = ? AND s.is_hidden = 0 AND s.deleted_at IS NULL",
                     [&project_id],
-                    |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+                    |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?)),
                 )
-                .unwrap_or((0, 0, 0, 0));
+                .unwrap_or((0, 0, 0, 0, 0));
 
             // Models used
             let mut models_used: std::collections::HashMap<String, i64> = std::collections::HashMap::new();
             if let Ok(mut stmt) = conn.prepare(
                 "SELECT model, COUNT(*) FROM session_messages sm
                  JOIN sessions s ON sm.session_id = s.id
-                 WHERE s.project_id = ? AND s.is_hidden = 0 AND model IS NOT NULL
+                 WHERE s.project_id = ? AND s.is_hidden = 0 AND s.deleted_at IS NULL AND model IS NOT NULL
                  GROUP BY model"
             ) {
                 if let Ok(rows) = stmt.query_map([&project_id], |row| {
@@ -648,6 +1047,29 @@ pub async fn get_project_analytics(
                 }
             }
 
+            // Token usage per model, for cost estimates
+            let mut model_usage: Vec<(String, i64, i64, i64, i64)> = Vec::new();
+            if let Ok(mut stmt) = conn.prepare(
+                "SELECT model,
+                        COALESCE(SUM(input_tokens), 0),
+                        COALESCE(SUM(output_tokens), 0),
+                        COALESCE(SUM(cache_read_tokens), 0),
+                        COALESCE(SUM(cache_creation_tokens), 0)
+                 FROM session_messages sm
+                 JOIN sessions s ON sm.session_id = s.id
+                 WHERE s.project_id = ? AND s.is_hidden = 0 AND s.deleted_at IS NULL AND model IS NOT NULL
+                 GROUP BY model
+                 ORDER BY model"
+            ) {
+                if let Ok(rows) = stmt.query_map([&project_id], |row| {
+                    Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?))
+                }) {
+                    model_usage = rows.filter_map(|r| r.ok()).collect();
+                }
+            }
+            let (model_costs, estimated_cost_usd, unpriced_models) =
+                estimate_model_costs(model_usage, &pricing);
+
             let stats = ProjectStats {
                 total_sessions,
                 total_messages,
@@ -657,11 +1079,15 @@ pub async fn get_project_analytics(
                 total_output_tokens,
                 total_cache_read_tokens,
                 total_cache_creation_tokens,
+                estimated_output_tokens,
                 models_used,
                 user_messages,
                 assistant_messages,
                 tool_uses,
                 tool_results,
+                model_costs,
+                estimated_cost_usd,
+                unpriced_models,
             };
 
             // 2. Session Metrics
@@ -676,7 +1102,7 @@ pub async fn get_project_analytics(
                         SUM(CASE WHEN sm.role = 'assistant' AND sm.tool_name IS NOT NULL THEN 1 ELSE 0 END)
                  FROM sessions s
                  LEFT JOIN session_messages sm ON s.id = sm.session_id
-                 WHERE s.project_id = ? AND s.is_hidden = 0
+                 WHERE s.project_id = ? AND s.is_hidden = 0 AND s.deleted_at IS NULL
                  GROUP BY s.id
                  ORDER BY s.created_at DESC"
             ) {
@@ -697,17 +1123,8 @@ pub async fn get_project_analytics(
                 }
             }
 
-            // 3. Active Dates
-            let mut active_dates: Vec<String> = Vec::new();
-            if let Ok(mut stmt) = conn.prepare(
-                "SELECT DISTINCT DATE(created_at) FROM sessions
-                 WHERE project_id = ? AND is_hidden = 0
-                 ORDER BY DATE(created_at) DESC"
-            ) {
-                if let Ok(rows) = stmt.query_map([&project_id], |row| row.get::<_, String>(0)) {
-                    active_dates = rows.filter_map(|r| r.ok()).collect();
-                }
-            }
+            // 3. Active Dates (DATE() normalizes timestamps to UTC)
+            let active_dates = project_active_dates(conn, &project_id).unwrap_or_default();
 
             // 4. Daily Tokens
             let mut daily_tokens: Vec<DailyTokens> = Vec::new();
@@ -720,7 +1137,7 @@ pub async fn get_project_analytics(
                         COALESCE(SUM(sm.cache_creation_tokens), 0)
                  FROM sessions s
                  LEFT JOIN session_messages sm ON s.id = sm.session_id
-                 WHERE s.project_id = ? AND s.is_hidden = 0
+                 WHERE s.project_id = ? AND s.is_hidden = 0 AND s.deleted_at IS NULL
                  GROUP BY DATE(s.created_at)
                  ORDER BY date DESC"
             ) {
@@ -744,7 +1161,7 @@ pub async fn get_project_analytics(
                 "SELECT DATE(s.created_at) as date, SUM(CASE WHEN sm.has_error = 1 THEN 1 ELSE 0 END)
                  FROM sessions s
                  LEFT JOIN session_messages sm ON s.id = sm.session_id
-                 WHERE s.project_id = ? AND s.is_hidden = 0
+                 WHERE s.project_id = ? AND s.is_hidden = 0 AND s.deleted_at IS NULL
                  GROUP BY DATE(s.created_at)
                  HAVING SUM(CASE WHEN sm.has_error = 1 THEN 1 ELSE 0 END) > 0
                  ORDER BY date DESC"
@@ -772,7 +1189,7 @@ pub async fn get_project_analytics(
                         COALESCE(SUM(sm.cache_creation_tokens), 0)
                  FROM sessions s
                  LEFT JOIN session_messages sm ON s.id = sm.session_id
-                 WHERE s.project_id = ? AND s.is_hidden = 0
+                 WHERE s.project_id = ? AND s.is_hidden = 0 AND s.deleted_at IS NULL
                  GROUP BY DATE(s.created_at)
                  ORDER BY date DESC"
             ) {
@@ -817,29 +1234,131 @@ pub async fn get_project_analytics(
 // Sessions
 // ============================================================================
 
-#[derive(Debug, Deserialize)]
-pub struct ListSessionsQuery {
-    pub project_id: Option<String>,
-    pub limit: Option<i64>,
-    pub offset: Option<i64>,
-    pub include_hidden: Option<bool>,
+/// ORDER BY column and direction for `list_sessions`, whitelisted to prevent SQL injection
+fn session_sort(query: &ListSessionsQuery) -> (&'static str, &'static str) {
+    let sort_column = match query.sort_by.as_deref() {
+        Some("message_count") => "message_count",
+        Some("duration_ms") => "duration_ms",
+        Some("indexed_at") => "indexed_at",
+        Some("created_at") | None => "created_at",
+        _ => "created_at", // Default for unknown values
+    };
+    let sort_direction = match query.sort_order.as_deref() {
+        Some("asc") => "ASC",
+        Some("desc") | None => "DESC",
+        _ => "DESC", // Default for unknown values
+    };
+    (sort_column, sort_direction)
+}
+
+fn bad_request(message: String) -> axum::response::Response {
+    (
+        StatusCode::BAD_REQUEST,
+        Json(serde_json::json!({ "error": message })),
+    )
+        .into_response()
+}
+
+fn invalid_cursor() -> axum::response::Response {
+    (
+        StatusCode::BAD_REQUEST,
+        Json(serde_json::json!({ "error": "Invalid cursor" })),
+    )
+        .into_response()
+}
+
+/// First extracted intent of the outer `sessions` row, shown under the title
+const SUBTITLE_SQL: &str = "SELECT i.intent FROM session_intents i WHERE i.session_id = sessions.id
+     ORDER BY i.sequence_num IS NULL, i.sequence_num, i.id LIMIT 1";
+
+/// Tags of the outer `sessions` row as a JSON array, alphabetical
+const TAGS_SQL: &str = "SELECT json_group_array(tag) FROM
+     (SELECT t.tag FROM session_tags t WHERE t.session_id = sessions.id ORDER BY t.tag)";
+
+/// Decode a `TAGS_SQL` column
+fn tags_from_json(json: Option<String>) -> Vec<String> {
+    json.and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+/// Trimmed, non-empty, de-duplicated tags in input order
+fn normalize_tags<'a>(tags: impl IntoIterator<Item = &'a str>) -> Vec<String> {
+    let mut normalized: Vec<String> = Vec::new();
+    for tag in tags.into_iter().map(str::trim) {
+        if !tag.is_empty() && !normalized.iter().any(|t| t == tag) {
+            normalized.push(tag.to_string());
+        }
+    }
+    normalized
 }
 
 pub async fn list_sessions(
     State(state): State<AppState>,
     Query(query): Query<ListSessionsQuery>,
 ) -> impl IntoResponse {
+    let cursor = match query.cursor.as_deref().map(Cursor::decode) {
+        Some(None) => return invalid_cursor(),
+        Some(cursor) => cursor,
+        None => None,
+    };
+    let range = match CreatedRange::parse(
+        query.created_after.as_deref(),
+        query.created_before.as_deref(),
+    ) {
+        Ok(range) => range,
+        Err(e) => return bad_request(e),
+    };
+    let (sort_column, sort_direction) = session_sort(&query);
+    // Keyset pagination only follows the newest-first created_at order
+    let keyset = (sort_column, sort_direction) == ("created_at", "DESC");
+    if cursor.is_some() && !keyset {
+        return bad_request("cursor can't be combined with sort_by or sort_order".to_string());
+    }
+    let tags = normalize_tags(query.tags.as_deref().unwrap_or_default().split(','));
+    let limit = query.limit.unwrap_or(50);
+    // A cursor already marks the position; offset only applies without one
+    let offset = if cursor.is_some() {
+        0
+    } else {
+        query.offset.unwrap_or(0)
+    };
+
     // Ephemeral mode
     if let Some(idx) = &state.ephemeral {
         let include_hidden = query.include_hidden.unwrap_or(false);
-        let all = idx.list_sessions_filtered(query.project_id.as_deref(), include_hidden);
-        let offset = query.offset.unwrap_or(0) as usize;
-        let limit = query.limit.unwrap_or(50) as usize;
+        let mut all: Vec<_> = idx
+            .list_sessions_filtered(query.project_id.as_deref(), include_hidden)
+            .into_iter()
+            .filter(|s| range.contains(&s.created_at))
+            // Tags, soft deletes and git branches are only stored in the database
+            .filter(|_| tags.is_empty() && !query.deleted && query.branch.is_none())
+            .collect();
+        // Only message_count is tracked in memory besides created_at
+        if sort_column == "message_count" {
+            all.sort_by_key(|s| s.message_count);
+            if sort_direction == "DESC" {
+                all.reverse();
+            }
+        } else if sort_direction == "ASC" {
+            all.reverse();
+        }
         let total = all.len();
-        let sessions: Vec<serde_json::Value> = all
+        let page: Vec<_> = all
+            .into_iter()
+            .filter(|s| {
+                cursor.as_ref().is_none_or(|c| {
+                    (s.created_at.as_str(), s.id.as_str()) < (c.timestamp.as_str(), c.id.as_str())
+                })
+            })
+            .skip(offset as usize)
+            .take(limit as usize)
+            .collect();
+        let next_cursor = (keyset && page.len() as i64 == limit)
+            .then(|| page.last())
+            .flatten()
+            .map(|s| Cursor::new(&s.created_at, &s.id).encode());
+        let sessions: Vec<serde_json::Value> = page
             .into_iter()
-            .skip(offset)
-            .take(limit)
             .map(|s| {
                 serde_json::json!({
                     "id": s.id,
@@ -851,16 +1370,22 @@ pub async fn list_sessions(
                     "has_code": s.has_code,
                     "has_errors": s.has_errors,
                     "created_at": s.created_at,
+                    "tags": [],
                 })
             })
             .collect();
-        return Json(serde_json::json!({ "sessions": sessions, "total": total })).into_response();
+        return Json(serde_json::json!({
+            "sessions": sessions,
+            "total": total,
+            "next_cursor": next_cursor,
+        }))
+        .into_response();
     }
 
     let db = state.db.as_ref().unwrap();
-    let limit = query.limit.unwrap_or(50);
-    let offset = query.offset.unwrap_or(0);
     let include_hidden = query.include_hidden.unwrap_or(false);
+    let deleted = query.deleted;
+    let branch = query.branch.clone();
     let project_id_input = query.project_id.clone();
 
     let result = db
@@ -870,44 +1395,78 @@ pub async fn list_sessions(
                 .as_ref()
                 .and_then(|pid| resolve_project_id(conn, pid));
 
-            let (sql, params): (String, Vec<Box<dyn rusqlite::ToSql>>) =
-                if let Some(ref pid) = project_id {
-                    let hidden_filter = if include_hidden {
-                        ""
-                    } else {
-                        " AND is_hidden = 0"
-                    };
-                    (
-                        format!(
-                            "SELECT id, project_id, file_path, title, ai_tool, message_count,
-                                duration_ms, has_code, has_errors, is_hidden, created_at, indexed_at
-                             FROM sessions
-                             WHERE project_id = ?{hidden_filter}
-                             ORDER BY created_at DESC
-                             LIMIT ? OFFSET ?"
-                        ),
-                        vec![Box::new(pid.clone()), Box::new(limit), Box::new(offset)],
-                    )
-                } else {
-                    let hidden_filter = if include_hidden {
-                        ""
-                    } else {
-                        " WHERE is_hidden = 0"
-                    };
-                    (
-                        format!(
-                            "SELECT id, project_id, file_path, title, ai_tool, message_count,
-                                duration_ms, has_code, has_errors, is_hidden, created_at, indexed_at
-                             FROM sessions{hidden_filter}
-                             ORDER BY created_at DESC
-                             LIMIT ? OFFSET ?"
-                        ),
-                        vec![Box::new(limit), Box::new(offset)],
-                    )
-                };
-
-            let params_refs: Vec<&dyn rusqlite::ToSql> =
-                params.iter().map(|p| p.as_ref()).collect();
+            let mut conditions: Vec<&str> = Vec::new();
+            let mut params: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
+            if let Some(ref pid) = project_id {
+                conditions.push("project_id = ?");
+                params.push(Box::new(pid.clone()));
+            }
+            if !include_hidden {
+                conditions.push("is_hidden = 0");
+            }
+            conditions.push(if deleted {
+                "deleted_at IS NOT NULL"
+            } else {
+                "deleted_at IS NULL"
+            });
+            let mut range_params = Vec::new();
+            let range_sql = range.sql("created_at", &mut range_params);
+            if !range_sql.is_empty() {
+                conditions.push(range_sql.trim_start_matches(" AND "));
+                params.extend(
+                    range_params
+                        .into_iter()
+                        .map(|p| Box::new(p) as Box<dyn rusqlite::ToSql>),
+                );
+            }
+            for tag in &tags {
+                conditions.push("id IN (SELECT session_id FROM session_tags WHERE tag = ?)");
+                params.push(Box::new(tag.clone()));
+            }
+            if let Some(branch) = branch.clone() {
+                conditions.push("git_branch = ?");
+                params.push(Box::new(branch));
+            }
+
+            // Total count ignores the cursor: it describes the whole filtered set
+            let count_where = if conditions.is_empty() {
+                String::new()
+            } else {
+                format!(" WHERE {}", conditions.join(" AND "))
+            };
+            let count_refs: Vec<&dyn rusqlite::ToSql> = params.iter().map(|p| p.as_ref()).collect();
+            let total: i64 = conn
+                .query_row(
+                    &format!("SELECT COUNT(*) FROM sessions{count_where}"),
+                    count_refs.as_slice(),
+                    |row| row.get(0),
+                )
+                .unwrap_or(0);
+
+            if let Some(ref c) = cursor {
+                conditions.push("(created_at, id) < (?, ?)");
+                params.push(Box::new(c.timestamp.clone()));
+                params.push(Box::new(c.id.clone()));
+            }
+            let where_clause = if conditions.is_empty() {
+                String::new()
+            } else {
+                format!(" WHERE {}", conditions.join(" AND "))
+            };
+            params.push(Box::new(limit));
+            params.push(Box::new(offset));
+
+            let sql = format!(
+                "SELECT id, project_id, file_path, title, ai_tool, message_count,
+                    duration_ms, has_code, has_errors, is_hidden, created_at, indexed_at,
+                    ({SUBTITLE_SQL}), ({TAGS_SQL}), deleted_at, git_branch, git_commit
+                 FROM sessions{where_clause}
+                 ORDER BY {sort_column} {sort_direction}, id {sort_direction}
+                 LIMIT ? OFFSET ?"
+            );
+
+            let params_refs: Vec<&dyn rusqlite::ToSql> =
+                params.iter().map(|p| p.as_ref()).collect();
 
             let mut stmt = conn.prepare(&sql)?;
             let sessions: Vec<serde_json::Value> = stmt
@@ -925,46 +1484,39 @@ pub async fn list_sessions(
                         "is_hidden": row.get::<_, bool>(9)?,
                         "created_at": row.get::<_, String>(10)?,
                         "indexed_at": row.get::<_, String>(11)?,
+                        "subtitle": row.get::<_, Option<String>>(12)?,
+                        "tags": tags_from_json(row.get(13)?),
+                        "deleted_at": row.get::<_, Option<String>>(14)?,
+                        "git_branch": row.get::<_, Option<String>>(15)?,
+                        "git_commit": row.get::<_, Option<String>>(16)?,
                     }))
                 })?
                 .filter_map(|r| r.ok())
                 .collect();
 
-            // Get total count
-            let count_sql = if project_id.is_some() {
-                let hidden_filter = if include_hidden {
-                    ""
-                } else {
-                    " AND is_hidden = 0"
-                };
-                format!("SELECT COUNT(*) FROM sessions WHERE project_id = ?{hidden_filter}")
-            } else {
-                let hidden_filter = if include_hidden {
-                    ""
-                } else {
-                    " WHERE is_hidden = 0"
-                };
-                format!("SELECT COUNT(*) FROM sessions{hidden_filter}")
-            };
-
-            let total: i64 = if let Some(ref pid) = project_id {
-                conn.query_row(&count_sql, [pid], |row| row.get(0))
-                    .unwrap_or(0)
-            } else {
-                conn.query_row(&count_sql, [], |row| row.get(0))
-                    .unwrap_or(0)
-            };
-
             Ok::<_, rusqlite::Error>((sessions, total))
         })
         .await;
 
     match result {
-        Ok((sessions, total)) => Json(serde_json::json!({
-            "sessions": sessions,
-            "total": total
-        }))
-        .into_response(),
+        Ok((sessions, total)) => {
+            let next_cursor = (keyset && sessions.len() as i64 == limit)
+                .then(|| sessions.last())
+                .flatten()
+                .map(|s| {
+                    Cursor::new(
+                        s["created_at"].as_str().unwrap_or_default(),
+                        s["id"].as_str().unwrap_or_default(),
+                    )
+                    .encode()
+                });
+            Json(serde_json::json!({
+                "sessions": sessions,
+                "total": total,
+                "next_cursor": next_cursor,
+            }))
+            .into_response()
+        }
         Err(e) => (
             StatusCode::INTERNAL_SERVER_ERROR,
             Json(serde_json::json!({ "error": e.to_string() })),
@@ -973,24 +1525,70 @@ pub async fn list_sessions(
     }
 }
 
+/// An ephemeral session as returned by `GET /api/sessions/:id`
+fn ephemeral_session_json(s: crate::ephemeral::SessionMeta) -> serde_json::Value {
+    serde_json::json!({
+        "id": s.id,
+        "project_id": s.project_id,
+        "file_path": s.file_path,
+        "title": s.title,
+        "ai_tool": s.ai_tool,
+        "message_count": s.message_count,
+        "has_code": s.has_code,
+        "has_errors": s.has_errors,
+        "created_at": s.created_at,
+        "tags": [],
+    })
+}
+
+/// A session row as returned by `GET /api/sessions/:id`
+fn session_row_json(conn: &rusqlite::Connection, id: &str) -> rusqlite::Result<serde_json::Value> {
+    conn.query_row(
+        &format!(
+            "SELECT id, project_id, file_path, title, ai_tool, message_count,
+                duration_ms, has_code, has_errors, is_hidden, created_at, indexed_at,
+                parent_session_id, ({SUBTITLE_SQL}), parse_errors, parse_error_lines,
+                ({TAGS_SQL}), git_branch, git_commit
+             FROM sessions WHERE id = ? AND deleted_at IS NULL"
+        ),
+        [id],
+        |row| {
+            let parse_error_lines: Vec<i64> = row
+                .get::<_, Option<String>>(15)?
+                .and_then(|s| serde_json::from_str(&s).ok())
+                .unwrap_or_default();
+            Ok(serde_json::json!({
+                "id": row.get::<_, String>(0)?,
+                "project_id": row.get::<_, String>(1)?,
+                "file_path": row.get::<_, String>(2)?,
+                "title": row.get::<_, Option<String>>(3)?,
+                "ai_tool": row.get::<_, String>(4)?,
+                "message_count": row.get::<_, i64>(5)?,
+                "duration_ms": row.get::<_, Option<i64>>(6)?,
+                "has_code": row.get::<_, bool>(7)?,
+                "has_errors": row.get::<_, bool>(8)?,
+                "is_hidden": row.get::<_, bool>(9)?,
+                "created_at": row.get::<_, String>(10)?,
+                "indexed_at": row.get::<_, String>(11)?,
+                "parent_session_id": row.get::<_, Option<String>>(12)?,
+                "subtitle": row.get::<_, Option<String>>(13)?,
+                "parse_errors": row.get::<_, i64>(14)?,
+                "parse_error_lines": parse_error_lines,
+                "tags": tags_from_json(row.get(16)?),
+                "git_branch": row.get::<_, Option<String>>(17)?,
+                "git_commit": row.get::<_, Option<String>>(18)?,
+            }))
+        },
+    )
+}
+
 pub async fn get_session(
     State(state): State<AppState>,
     Path(id): Path<String>,
 ) -> impl IntoResponse {
     if let Some(idx) = &state.ephemeral {
         return match idx.get_session(&id) {
-            Some(s) => Json(serde_json::json!({
-                "id": s.id,
-                "project_id": s.project_id,
-                "file_path": s.file_path,
-                "title": s.title,
-                "ai_tool": s.ai_tool,
-                "message_count": s.message_count,
-                "has_code": s.has_code,
-                "has_errors": s.has_errors,
-                "created_at": s.created_at,
-            }))
-            .into_response(),
+            Some(s) => Json(ephemeral_session_json(s)).into_response(),
             None => (
                 StatusCode::NOT_FOUND,
                 Json(serde_json::json!({ "error": "Session not found" })),
@@ -999,37 +1597,303 @@ pub async fn get_session(
         };
     }
 
+    let db = state.db.as_ref().unwrap();
+    let result = db
+        .with_read_conn(move |conn| session_row_json(conn, &id))
+        .await;
+
+    match result {
+        Ok(session) => Json(session).into_response(),
+        Err(rusqlite::Error::QueryReturnedNoRows) => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": "Session not found" })),
+        )
+            .into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({ "error": e.to_string() })),
+        )
+            .into_response(),
+    }
+}
+
+/// List sub-agent sessions linked to a parent session
+pub async fn get_session_agents(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    if state.db.is_none() {
+        return Json(serde_json::json!({ "agents": [] })).into_response();
+    }
+
+    let result = state
+        .db
+        .as_ref()
+        .unwrap()
+        .with_read_conn(move |conn| {
+            let mut stmt = conn.prepare(
+                "SELECT id, file_path, title, message_count, duration_ms, has_code, has_errors, created_at
+                 FROM sessions WHERE parent_session_id = ? AND deleted_at IS NULL
+                 ORDER BY created_at ASC",
+            )?;
+            let rows = stmt.query_map([&id], |row| {
+                Ok(serde_json::json!({
+                    "id": row.get::<_, String>(0)?,
+                    "file_path": row.get::<_, String>(1)?,
+                    "title": row.get::<_, Option<String>>(2)?,
+                    "message_count": row.get::<_, i64>(3)?,
+                    "duration_ms": row.get::<_, Option<i64>>(4)?,
+                    "has_code": row.get::<_, bool>(5)?,
+                    "has_errors": row.get::<_, bool>(6)?,
+                    "created_at": row.get::<_, String>(7)?,
+                }))
+            })?;
+            rows.collect::<rusqlite::Result<Vec<_>>>()
+        })
+        .await;
+
+    match result {
+        Ok(agents) => Json(serde_json::json!({ "agents": agents })).into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({ "error": e.to_string() })),
+        )
+            .into_response(),
+    }
+}
+
+/// Message counts for a session, by role, tool type, and tool name
+#[derive(Debug, Default, Serialize)]
+pub struct SessionComposition {
+    pub session_id: String,
+    pub total_messages: i64,
+    pub roles: std::collections::BTreeMap<String, i64>,
+    pub tool_types: std::collections::BTreeMap<String, i64>,
+    /// Tool uses by tool name
+    pub tools: std::collections::BTreeMap<String, i64>,
+    pub errors: i64,
+}
+
+impl SessionComposition {
+    fn add(
+        &mut self,
+        role: &str,
+        tool_type: Option<&str>,
+        tool_name: Option<&str>,
+        count: i64,
+        errors: i64,
+    ) {
+        self.total_messages += count;
+        self.errors += errors;
+        *self.roles.entry(role.to_string()).or_default() += count;
+        if let Some(tool_type) = tool_type {
+            *self.tool_types.entry(tool_type.to_string()).or_default() += count;
+            if let (Some(name), "use") = (tool_name, tool_type) {
+                *self.tools.entry(name.to_string()).or_default() += count;
+            }
+        }
+    }
+}
+
+/// GET /api/sessions/:id/composition
+/// Histogram of a session's messages without fetching them.
+pub async fn get_session_composition(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    let not_found = || {
+        (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": "Session not found" })),
+        )
+            .into_response()
+    };
+
+    if let Some(idx) = &state.ephemeral {
+        if idx.get_session(&id).is_none() {
+            return not_found();
+        }
+        let mut composition = SessionComposition {
+            session_id: id.clone(),
+            ..Default::default()
+        };
+        for m in idx.get_messages(&id) {
+            composition.add(
+                &m.role,
+                m.tool_type.as_deref(),
+                m.tool_name.as_deref(),
+                1,
+                m.has_error as i64,
+            );
+        }
+        return Json(composition).into_response();
+    }
+
     let db = state.db.as_ref().unwrap();
     let result = db
         .with_read_conn(move |conn| {
-            conn.query_row(
-                "SELECT id, project_id, file_path, title, ai_tool, message_count,
-                        duration_ms, has_code, has_errors, is_hidden, created_at, indexed_at
-                 FROM sessions WHERE id = ?",
+            let exists = conn
+                .query_row("SELECT 1 FROM sessions WHERE id = ?", [&id], |_| Ok(()))
+                .is_ok();
+            if !exists {
+                return Ok(None);
+            }
+
+            let mut composition = SessionComposition {
+                session_id: id.clone(),
+                ..Default::default()
+            };
+            let mut stmt = conn.prepare(
+                "SELECT role, tool_type, tool_name, COUNT(*), SUM(has_error)
+                 FROM session_messages WHERE session_id = ?
+                 GROUP BY role, tool_type, tool_name",
+            )?;
+            let mut rows = stmt.query([&id])?;
+            while let Some(row) = rows.next()? {
+                composition.add(
+                    &row.get::<_, String>(0)?,
+                    row.get::<_, Option<String>>(1)?.as_deref(),
+                    row.get::<_, Option<String>>(2)?.as_deref(),
+                    row.get(3)?,
+                    row.get(4)?,
+                );
+            }
+            Ok::<_, rusqlite::Error>(Some(composition))
+        })
+        .await;
+
+    match result {
+        Ok(Some(composition)) => Json(composition).into_response(),
+        Ok(None) => not_found(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({ "error": e.to_string() })),
+        )
+            .into_response(),
+    }
+}
+
+/// Token totals, message counts and estimated cost for one session
+#[derive(Debug, Serialize)]
+pub struct SessionSummary {
+    #[serde(flatten)]
+    pub composition: SessionComposition,
+    pub duration_ms: Option<i64>,
+    pub total_input_tokens: i64,
+    pub total_output_tokens: i64,
+    pub total_cache_read_tokens: i64,
+    pub total_cache_creation_tokens: i64,
+    /// Part of `total_output_tokens` estimated from message text (`parser.estimate_tokens`)
+    pub estimated_output_tokens: i64,
+    /// Token totals and estimated cost for each model, priced by `ai.model_pricing`
+    pub model_costs: Vec<ModelCost>,
+    /// Sum of `model_costs`
+    pub estimated_cost_usd: f64,
+    /// Models with no `ai.model_pricing` entry; they contribute zero cost
+    pub unpriced_models: Vec<String>,
+}
+
+/// GET /api/sessions/:id/summary
+/// Token and cost totals for a session, with its composition, from one
+/// aggregate query over its messages.
+pub async fn get_session_summary(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    let Some(db) = &state.db else {
+        return (
+            StatusCode::NOT_IMPLEMENTED,
+            Json(serde_json::json!({ "error": "Not available in ephemeral mode" })),
+        )
+            .into_response();
+    };
+
+    let pricing = Config::from_file(&state.config_path)
+        .map(|c| c.ai.model_pricing)
+        .unwrap_or_default();
+    let result = db
+        .with_read_conn(move |conn| {
+            let duration_ms: Option<i64> = match conn.query_row(
+                "SELECT duration_ms FROM sessions WHERE id = ? AND deleted_at IS NULL",
                 [&id],
-                |row| {
-                    Ok(serde_json::json!({
-                        "id": row.get::<_, String>(0)?,
-                        "project_id": row.get::<_, String>(1)?,
-                        "file_path": row.get::<_, String>(2)?,
-                        "title": row.get::<_, Option<String>>(3)?,
-                        "ai_tool": row.get::<_, String>(4)?,
-                        "message_count": row.get::<_, i64>(5)?,
-                        "duration_ms": row.get::<_, Option<i64>>(6)?,
-                        "has_code": row.get::<_, bool>(7)?,
-                        "has_errors": row.get::<_, bool>(8)?,
-                        "is_hidden": row.get::<_, bool>(9)?,
-                        "created_at": row.get::<_, String>(10)?,
-                        "indexed_at": row.get::<_, String>(11)?,
-                    }))
-                },
-            )
+                |row| row.get(0),
+            ) {
+                Ok(duration_ms) => duration_ms,
+                Err(rusqlite::Error::QueryReturnedNoRows) => return Ok(None),
+                Err(e) => return Err(e),
+            };
+
+            let mut composition = SessionComposition {
+                session_id: id.clone(),
+                ..Default::default()
+            };
+            // input, output, cache read, cache creation, estimated output
+            let mut tokens = [0i64; 5];
+            let mut model_usage: std::collections::BTreeMap<String, [i64; 4]> =
+                std::collections::BTreeMap::new();
+            let mut stmt = conn.prepare(
+                "SELECT role, tool_type, tool_name, model, COUNT(*), SUM(has_error),
+                        COALESCE(SUM(input_tokens), 0),
+                        COALESCE(SUM(output_tokens), 0),
+                        COALESCE(SUM(cache_read_tokens), 0),
+                        COALESCE(SUM(cache_creation_tokens), 0),
+                        COALESCE(SUM(CASE WHEN tokens_estimated = 1 THEN output_tokens END), 0)
+                 FROM session_messages WHERE session_id = ?
+                 GROUP BY role, tool_type, tool_name, model",
+            )?;
+            let mut rows = stmt.query([&id])?;
+            while let Some(row) = rows.next()? {
+                composition.add(
+                    &row.get::<_, String>(0)?,
+                    row.get::<_, Option<String>>(1)?.as_deref(),
+                    row.get::<_, Option<String>>(2)?.as_deref(),
+                    row.get(4)?,
+                    row.get(5)?,
+                );
+                let group: [i64; 5] = [
+                    row.get(6)?,
+                    row.get(7)?,
+                    row.get(8)?,
+                    row.get(9)?,
+                    row.get(10)?,
+                ];
+                for (total, n) in tokens.iter_mut().zip(group) {
+                    *total += n;
+                }
+                if let Some(model) = row.get::<_, Option<String>>(3)? {
+                    let usage = model_usage.entry(model).or_default();
+                    for (total, n) in usage.iter_mut().zip(group) {
+                        *total += n;
+                    }
+                }
+            }
+
+            let usage = model_usage
+                .into_iter()
+                .map(|(model, [input, output, cache_read, cache_creation])| {
+                    (model, input, output, cache_read, cache_creation)
+                })
+                .collect();
+            let (model_costs, estimated_cost_usd, unpriced_models) =
+                estimate_model_costs(usage, &pricing);
+            Ok::<_, rusqlite::Error>(Some(SessionSummary {
+                composition,
+                duration_ms,
+                total_input_tokens: tokens[0],
+                total_output_tokens: tokens[1],
+                total_cache_read_tokens: tokens[2],
+                total_cache_creation_tokens: tokens[3],
+                estimated_output_tokens: tokens[4],
+                model_costs,
+                estimated_cost_usd,
+                unpriced_models,
+            }))
         })
         .await;
 
     match result {
-        Ok(session) => Json(session).into_response(),
-        Err(rusqlite::Error::QueryReturnedNoRows) => (
+        Ok(Some(summary)) => Json(summary).into_response(),
+        Ok(None) => (
             StatusCode::NOT_FOUND,
             Json(serde_json::json!({ "error": "Session not found" })),
         )
@@ -1079,9 +1943,9 @@ pub async fn update_session(
         .unwrap()
         .with_conn(move |conn| {
             let mut updates = vec!["indexed_at = ?"];
-            let mut params: Vec<Box<dyn rusqlite::ToSql>> = vec![Box::new(now_clone)];
+            let mut params: Vec<Box<dyn rusqlite::ToSql>> = vec![Box::new(now_clone.clone())];
 
-            if let Some(title) = req.title {
+            if let Some(title) = req.title.clone() {
                 updates.push("title = ?");
                 updates.push("title_edited = 1");
                 params.push(Box::new(title));
@@ -1091,7 +1955,7 @@ pub async fn update_session(
                 params.push(Box::new(hidden));
             }
 
-            params.push(Box::new(id_clone));
+            params.push(Box::new(id_clone.clone()));
             let query = format!("UPDATE sessions SET {} WHERE id = ?", updates.join(", "));
             let params_refs: Vec<&dyn rusqlite::ToSql> =
                 params.iter().map(|p| p.as_ref()).collect();
@@ -1115,9 +1979,20 @@ pub async fn update_session(
     }
 }
 
+#[derive(Debug, Deserialize)]
+pub struct DeleteSessionQuery {
+    /// Delete the row now instead of marking it deleted
+    #[serde(default)]
+    pub permanent: bool,
+}
+
+/// Soft-delete a session: it disappears from lists and search but can be
+/// restored until `scheduler.session_purge` removes it. `?permanent=true`
+/// deletes it outright, soft-deleted or not.
 pub async fn delete_session(
     State(state): State<AppState>,
     Path(id): Path<String>,
+    Query(query): Query<DeleteSessionQuery>,
 ) -> impl IntoResponse {
     // Ephemeral mode: delete from in-memory index
     if let Some(idx) = &state.ephemeral {
@@ -1131,20 +2006,77 @@ pub async fn delete_session(
             .into_response();
     }
 
-    let result = state
-        .db
-        .as_ref()
-        .unwrap()
-        .with_conn(move |conn| conn.execute("DELETE FROM sessions WHERE id = ?", [&id]))
+    let now = chrono::Utc::now().to_rfc3339();
+    let db = state.db.clone().unwrap();
+    let result = db
+        .with_conn(move |conn| {
+            if query.permanent {
+                let memory_ids = crate::db::memory_ids_where(conn, "session_id = ?", &id)?;
+                let deleted = conn.execute("DELETE FROM sessions WHERE id = ?", [&id])?;
+                Ok((deleted, memory_ids))
+            } else {
+                conn.execute(
+                    "UPDATE sessions SET deleted_at = ?1 WHERE id = ?2 AND deleted_at IS NULL",
+                    [&now, &id],
+                )
+                .map(|updated| (updated, Vec::new()))
+            }
+        })
         .await;
 
     match result {
-        Ok(0) => (
+        Ok((0, _)) => (
             StatusCode::NOT_FOUND,
             Json(serde_json::json!({ "error": "Session not found" })),
         )
             .into_response(),
-        Ok(_) => StatusCode::NO_CONTENT.into_response(),
+        Ok((_, memory_ids)) => {
+            db.unindex_memories(&memory_ids);
+            StatusCode::NO_CONTENT.into_response()
+        }
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({ "error": e.to_string() })),
+        )
+            .into_response(),
+    }
+}
+
+/// Undo a soft delete, answering with the restored session
+pub async fn restore_session(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    let Some(db) = &state.db else {
+        return (
+            StatusCode::NOT_IMPLEMENTED,
+            Json(serde_json::json!({ "error": "Not available in ephemeral mode" })),
+        )
+            .into_response();
+    };
+
+    let result = db
+        .with_conn(move |conn| {
+            // Bumping indexed_at makes delta sync send the session again
+            let restored = conn.execute(
+                "UPDATE sessions SET deleted_at = NULL, indexed_at = ?1
+                 WHERE id = ?2 AND deleted_at IS NOT NULL",
+                [&chrono::Utc::now().to_rfc3339(), &id],
+            )?;
+            if restored == 0 {
+                return Ok(None);
+            }
+            session_row_json(conn, &id).map(Some)
+        })
+        .await;
+
+    match result {
+        Ok(Some(session)) => Json(session).into_response(),
+        Ok(None) => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": "Deleted session not found" })),
+        )
+            .into_response(),
         Err(e) => (
             StatusCode::INTERNAL_SERVER_ERROR,
             Json(serde_json::json!({ "error": e.to_string() })),
@@ -1154,16 +2086,439 @@ pub async fn delete_session(
 }
 
 #[derive(Debug, Deserialize)]
-pub struct GetMessagesQuery {
-    pub offset: Option<i64>,
-    pub limit: Option<i64>,
+pub struct AddSessionTagsRequest {
+    pub tags: Vec<String>,
 }
 
-pub async fn get_session_messages(
+/// Tag a session. Tags it already has are left as they are.
+pub async fn add_session_tags(
     State(state): State<AppState>,
-    Path(session_id): Path<String>,
-    Query(query): Query<GetMessagesQuery>,
+    Path(id): Path<String>,
+    Json(req): Json<AddSessionTagsRequest>,
 ) -> impl IntoResponse {
+    let Some(db) = &state.db else {
+        return (
+            StatusCode::NOT_IMPLEMENTED,
+            Json(serde_json::json!({ "error": "Not available in ephemeral mode" })),
+        )
+            .into_response();
+    };
+
+    let tags = normalize_tags(req.tags.iter().map(String::as_str));
+    if tags.is_empty() {
+        return bad_request("At least one non-empty tag is required".to_string());
+    }
+
+    let now = chrono::Utc::now().to_rfc3339();
+    let session_id = id.clone();
+    let result = db
+        .with_conn(move |conn| {
+            let tx = conn.unchecked_transaction()?;
+            tx.query_row("SELECT 1 FROM sessions WHERE id = ?", [&session_id], |_| {
+                Ok(())
+            })?;
+            for tag in &tags {
+                tx.execute(
+                    "INSERT OR IGNORE INTO session_tags (session_id, tag, created_at)
+                     VALUES (?1, ?2, ?3)",
+                    rusqlite::params![session_id, tag, now],
+                )?;
+            }
+            let all: Vec<String> = {
+                let mut stmt =
+                    tx.prepare("SELECT tag FROM session_tags WHERE session_id = ? ORDER BY tag")?;
+                let rows = stmt.query_map([&session_id], |row| row.get(0))?;
+                rows.collect::<rusqlite::Result<_>>()?
+            };
+            tx.commit()?;
+            Ok::<_, rusqlite::Error>(all)
+        })
+        .await;
+
+    match result {
+        Ok(tags) => Json(serde_json::json!({ "session_id": id, "tags": tags })).into_response(),
+        Err(rusqlite::Error::QueryReturnedNoRows) => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": "Session not found" })),
+        )
+            .into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({ "error": e.to_string() })),
+        )
+            .into_response(),
+    }
+}
+
+/// Remove one tag from a session
+pub async fn remove_session_tag(
+    State(state): State<AppState>,
+    Path((id, tag)): Path<(String, String)>,
+) -> impl IntoResponse {
+    let Some(db) = &state.db else {
+        return (
+            StatusCode::NOT_IMPLEMENTED,
+            Json(serde_json::json!({ "error": "Not available in ephemeral mode" })),
+        )
+            .into_response();
+    };
+
+    let result = db
+        .with_conn(move |conn| {
+            conn.execute(
+                "DELETE FROM session_tags WHERE session_id = ? AND tag = ?",
+                rusqlite::params![id, tag.trim()],
+            )
+        })
+        .await;
+
+    match result {
+        Ok(0) => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": "Tag not found on session" })),
+        )
+            .into_response(),
+        Ok(_) => StatusCode::NO_CONTENT.into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({ "error": e.to_string() })),
+        )
+            .into_response(),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BulkDeleteSessionsRequest {
+    /// Delete these sessions
+    pub session_ids: Option<Vec<String>>,
+    /// Delete sessions created before this RFC 3339 timestamp
+    pub before: Option<String>,
+    /// With `before`, only delete this project's sessions
+    pub project_id: Option<String>,
+}
+
+/// Soft-delete sessions by id or by creation date, in one transaction, as
+/// `DELETE /api/sessions/:id` does. Sessions already deleted aren't counted.
+pub async fn bulk_delete_sessions(
+    State(state): State<AppState>,
+    Json(req): Json<BulkDeleteSessionsRequest>,
+) -> impl IntoResponse {
+    let before = match (&req.session_ids, req.before.as_deref()) {
+        (Some(_), None) => None,
+        (None, Some(before)) => match chrono::DateTime::parse_from_rfc3339(before) {
+            Ok(t) => Some(t.with_timezone(&chrono::Utc)),
+            Err(_) => return bad_request("before must be an RFC 3339 timestamp".to_string()),
+        },
+        _ => return bad_request("Provide either session_ids or before".to_string()),
+    };
+
+    if let Some(idx) = &state.ephemeral {
+        let ids = match req.session_ids {
+            Some(ids) => ids,
+            None => idx
+                .list_sessions_filtered(req.project_id.as_deref(), true)
+                .into_iter()
+                .filter(|s| {
+                    chrono::DateTime::parse_from_rfc3339(&s.created_at)
+                        .is_ok_and(|t| before.is_some_and(|before| t < before))
+                })
+                .map(|s| s.id)
+                .collect(),
+        };
+        let deleted = ids.iter().filter(|id| idx.delete_session(id)).count();
+        return Json(serde_json::json!({ "deleted": deleted })).into_response();
+    }
+
+    let result = state
+        .db
+        .as_ref()
+        .unwrap()
+        .with_conn(move |conn| {
+            let now = chrono::Utc::now().to_rfc3339();
+            let tx = conn.unchecked_transaction()?;
+            // Accept the folder-name form of a project id, as list endpoints do
+            let project_id = req
+                .project_id
+                .clone()
+                .map(|id| resolve_project_id(&tx, &id).unwrap_or(id));
+            let deleted = match (req.session_ids.clone(), before) {
+                (Some(ids), _) => {
+                    let mut stmt = tx.prepare(
+                        "UPDATE sessions SET deleted_at = ?1 WHERE id = ?2 AND deleted_at IS NULL",
+                    )?;
+                    let mut deleted = 0;
+                    for id in &ids {
+                        deleted += stmt.execute([&now, id])?;
+                    }
+                    deleted
+                }
+                (None, Some(before)) => tx.execute(
+                    "UPDATE sessions SET deleted_at = ?1
+                     WHERE julianday(created_at) < julianday(?2)
+                       AND (?3 IS NULL OR project_id = ?3)
+                       AND deleted_at IS NULL",
+                    rusqlite::params![now, CreatedRange::bound(&before), project_id],
+                )?,
+                (None, None) => 0,
+            };
+            tx.commit()?;
+            Ok::<_, rusqlite::Error>(deleted)
+        })
+        .await;
+
+    match result {
+        Ok(deleted) => Json(serde_json::json!({ "deleted": deleted })).into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({ "error": e.to_string() })),
+        )
+            .into_response(),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GetMessagesQuery {
+    pub offset: Option<i64>,
+    pub limit: Option<i64>,
+}
+
+/// An ephemeral message as returned by `GET /api/sessions/:id/messages`
+fn ephemeral_message_json(session_id: &str, m: crate::ephemeral::MessageMeta) -> serde_json::Value {
+    serde_json::json!({
+        "session_id": session_id,
+        "sequence_num": m.sequence_num,
+        "role": m.role,
+        "content_preview": m.content_preview,
+        "has_code": m.has_code,
+        "code_language": m.code_language,
+        "has_error": m.has_error,
+        "has_file_changes": m.has_file_changes,
+        "tool_name": m.tool_name,
+        "tool_type": m.tool_type,
+        "tool_summary": m.tool_summary,
+        "parent_sequence_num": m.parent_sequence_num,
+        "byte_offset": m.byte_offset,
+        "byte_length": m.byte_length,
+        "input_tokens": m.input_tokens,
+        "output_tokens": m.output_tokens,
+        "cache_read_tokens": m.cache_read_tokens,
+        "cache_creation_tokens": m.cache_creation_tokens,
+        "tokens_estimated": m.tokens_estimated,
+        "model": m.model,
+        "timestamp": m.timestamp,
+    })
+}
+
+/// Message rows of one session (`?1`) in `sequence_num` order, paged by `?2`/`?3`;
+/// columns as read by [`message_row_json`]
+const SESSION_MESSAGES_SQL: &str =
+    "SELECT id, sequence_num, role, content_preview, search_content, has_code, has_error,
+            has_file_changes, tool_name, tool_type, tool_summary,
+            byte_offset, byte_length, input_tokens, output_tokens,
+            cache_read_tokens, cache_creation_tokens, model, timestamp,
+            parent_sequence_num, code_language, tokens_estimated
+     FROM session_messages
+     WHERE session_id = ?1 AND sequence_num > ?2
+     ORDER BY sequence_num
+     LIMIT ?3 OFFSET ?4";
+
+/// A message row as returned by `GET /api/sessions/:id/messages`
+fn message_row_json(session_id: &str, row: &rusqlite::Row) -> rusqlite::Result<serde_json::Value> {
+    Ok(serde_json::json!({
+        "id": row.get::<_, i64>(0)?,
+        "session_id": session_id,
+        "sequence_num": row.get::<_, i64>(1)?,
+        "role": row.get::<_, String>(2)?,
+        "content_preview": row.get::<_, Option<String>>(3)?,
+        "search_content": row.get::<_, Option<String>>(4)?,
+        "has_code": row.get::<_, bool>(5)?,
+        "has_error": row.get::<_, bool>(6)?,
+        "has_file_changes": row.get::<_, bool>(7)?,
+        "tool_name": row.get::<_, Option<String>>(8)?,
+        "tool_type": row.get::<_, Option<String>>(9)?,
+        "tool_summary": row.get::<_, Option<String>>(10)?,
+        "byte_offset": row.get::<_, i64>(11)?,
+        "byte_length": row.get::<_, i64>(12)?,
+        "input_tokens": row.get::<_, Option<i64>>(13)?,
+        "output_tokens": row.get::<_, Option<i64>>(14)?,
+        "cache_read_tokens": row.get::<_, Option<i64>>(15)?,
+        "cache_creation_tokens": row.get::<_, Option<i64>>(16)?,
+        "model": row.get::<_, Option<String>>(17)?,
+        "timestamp": row.get::<_, String>(18)?,
+        "parent_sequence_num": row.get::<_, Option<i64>>(19)?,
+        "code_language": row.get::<_, Option<String>>(20)?,
+        "tokens_estimated": row.get::<_, bool>(21)?,
+    }))
+}
+
+/// A page of message rows as returned by `GET /api/sessions/:id/messages`,
+/// in `sequence_num` order
+fn session_messages_json(
+    conn: &rusqlite::Connection,
+    session_id: &str,
+    limit: i64,
+    offset: i64,
+) -> rusqlite::Result<Vec<serde_json::Value>> {
+    let mut stmt = conn.prepare(SESSION_MESSAGES_SQL)?;
+    let messages = stmt
+        .query_map(
+            rusqlite::params![session_id, i64::MIN, limit, offset],
+            |row| message_row_json(session_id, row),
+        )?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(messages)
+}
+
+/// Media type of newline-delimited JSON responses
+const NDJSON: &str = "application/x-ndjson";
+
+/// Whether the request's `Accept` header asks for NDJSON
+fn accepts_ndjson(headers: &axum::http::HeaderMap) -> bool {
+    headers
+        .get(axum::http::header::ACCEPT)
+        .and_then(|h| h.to_str().ok())
+        .is_some_and(|accept| accept.contains(NDJSON))
+}
+
+/// Rows read per database round trip when streaming messages
+const MESSAGE_PAGE_ROWS: i64 = 256;
+
+/// Sends the chunks of a streamed response body
+type BodySender = tokio::sync::mpsc::Sender<std::io::Result<axum::body::Bytes>>;
+
+/// A response body that streams whatever is sent through the returned sender
+fn channel_body() -> (BodySender, axum::body::Body) {
+    let (tx, rx) = tokio::sync::mpsc::channel(64);
+    (
+        tx,
+        axum::body::Body::from_stream(tokio_stream::wrappers::ReceiverStream::new(rx)),
+    )
+}
+
+/// Which messages `send_session_messages` reads and how it renders them
+struct MessagePages {
+    limit: i64,
+    offset: i64,
+    /// Rows per read
+    page_rows: i64,
+    /// Text sent for a `SESSION_MESSAGES_SQL` row; empty text is skipped
+    render: fn(&str, &rusqlite::Row) -> rusqlite::Result<String>,
+    /// Sent between rendered messages
+    separator: &'static str,
+}
+
+/// Send a session's messages through `tx` in `sequence_num` order.
+///
+/// Rows are read `page_rows` at a time, each read continuing after the last
+/// `sequence_num` sent. The read connection is released between reads, so a
+/// slow client holds neither a pooled connection nor a read transaction.
+/// Returns false when the response should end here: the client went away, or
+/// a read failed and the error was sent, which aborts the body.
+async fn send_session_messages(
+    db: &crate::db::Database,
+    session_id: &str,
+    pages: MessagePages,
+    tx: &BodySender,
+) -> bool {
+    let MessagePages {
+        limit,
+        offset,
+        page_rows,
+        render,
+        separator,
+    } = pages;
+    // The first read skips `offset` rows; later ones start after `after`
+    let (mut after, mut offset, mut remaining) = (i64::MIN, offset, limit);
+    let mut first = true;
+    while remaining > 0 {
+        let page_size = remaining.min(page_rows);
+        let sid = session_id.to_string();
+        let page = db
+            .with_read_conn(move |conn| {
+                let mut stmt = conn.prepare(SESSION_MESSAGES_SQL)?;
+                let rows = stmt
+                    .query_map(rusqlite::params![&sid, after, page_size, offset], |row| {
+                        Ok((row.get::<_, i64>(1)?, render(&sid, row)?))
+                    })?;
+                rows.collect::<rusqlite::Result<Vec<_>>>()
+            })
+            .await;
+        let page = match page {
+            Ok(page) => page,
+            Err(e) => {
+                tracing::warn!("Streaming messages of {} failed: {}", session_id, e);
+                let _ = tx.send(Err(std::io::Error::other(e))).await;
+                return false;
+            }
+        };
+        let read = page.len() as i64;
+        for (sequence_num, text) in page {
+            after = sequence_num;
+            if text.is_empty() {
+                continue;
+            }
+            let chunk = if first {
+                text
+            } else {
+                format!("{separator}{text}")
+            };
+            first = false;
+            // Client went away
+            if tx.send(Ok(chunk.into())).await.is_err() {
+                return false;
+            }
+        }
+        if read < page_size {
+            break;
+        }
+        remaining -= read;
+        offset = 0;
+    }
+    true
+}
+
+/// A message row as one NDJSON line
+fn ndjson_message_line(session_id: &str, row: &rusqlite::Row) -> rusqlite::Result<String> {
+    let mut line = message_row_json(session_id, row)?.to_string();
+    line.push('\n');
+    Ok(line)
+}
+
+/// Stream a page of messages as NDJSON, one message object per line.
+/// A database error ends the stream early.
+fn stream_session_messages(
+    db: std::sync::Arc<crate::db::Database>,
+    session_id: String,
+    limit: i64,
+    offset: i64,
+    page_rows: i64,
+) -> axum::response::Response {
+    let (tx, body) = channel_body();
+    tokio::spawn(async move {
+        let pages = MessagePages {
+            limit,
+            offset,
+            page_rows,
+            render: ndjson_message_line,
+            separator: "",
+        };
+        send_session_messages(&db, &session_id, pages, &tx).await;
+    });
+
+    ([(axum::http::header::CONTENT_TYPE, NDJSON)], body).into_response()
+}
+
+/// GET /api/sessions/:id/messages
+/// With `Accept: application/x-ndjson` the page is streamed one message per
+/// line instead of returned as a JSON object.
+pub async fn get_session_messages(
+    State(state): State<AppState>,
+    Path(session_id): Path<String>,
+    Query(query): Query<GetMessagesQuery>,
+    headers: axum::http::HeaderMap,
+) -> impl IntoResponse {
+    let ndjson = accepts_ndjson(&headers);
+
     // Ephemeral mode
     if let Some(idx) = &state.ephemeral {
         let all = idx.get_messages(&session_id);
@@ -1174,29 +2529,13 @@ pub async fn get_session_messages(
             .into_iter()
             .skip(offset)
             .take(limit)
-            .map(|m| {
-                serde_json::json!({
-                    "session_id": session_id,
-                    "sequence_num": m.sequence_num,
-                    "role": m.role,
-                    "content_preview": m.content_preview,
-                    "has_code": m.has_code,
-                    "has_error": m.has_error,
-                    "has_file_changes": m.has_file_changes,
-                    "tool_name": m.tool_name,
-                    "tool_type": m.tool_type,
-                    "tool_summary": m.tool_summary,
-                    "byte_offset": m.byte_offset,
-                    "byte_length": m.byte_length,
-                    "input_tokens": m.input_tokens,
-                    "output_tokens": m.output_tokens,
-                    "cache_read_tokens": m.cache_read_tokens,
-                    "cache_creation_tokens": m.cache_creation_tokens,
-                    "model": m.model,
-                    "timestamp": m.timestamp,
-                })
-            })
+            .map(|m| ephemeral_message_json(&session_id, m))
             .collect();
+        if ndjson {
+            // Already in memory, nothing to stream from
+            let body: String = messages.iter().map(|m| format!("{}\n", m)).collect();
+            return ([(axum::http::header::CONTENT_TYPE, NDJSON)], body).into_response();
+        }
         return Json(serde_json::json!({ "messages": messages, "total": total })).into_response();
     }
 
@@ -1206,47 +2545,13 @@ pub async fn get_session_messages(
     let limit = query.limit.unwrap_or(i64::MAX);
     let offset = query.offset.unwrap_or(0);
 
+    if ndjson {
+        return stream_session_messages(db.clone(), session_id, limit, offset, MESSAGE_PAGE_ROWS);
+    }
+
     let result = db
         .with_read_conn(move |conn| {
-            let session_id_clone = session_id.clone();
-            let mut stmt = conn.prepare(
-                "SELECT id, sequence_num, role, content_preview, search_content, has_code, has_error,
-                        has_file_changes, tool_name, tool_type, tool_summary,
-                        byte_offset, byte_length, input_tokens, output_tokens,
-                        cache_read_tokens, cache_creation_tokens, model, timestamp
-                 FROM session_messages
-                 WHERE session_id = ?
-                 ORDER BY sequence_num
-                 LIMIT ? OFFSET ?",
-            )?;
-
-            let messages: Vec<serde_json::Value> = stmt
-                .query_map(rusqlite::params![session_id, limit, offset], |row| {
-                    Ok(serde_json::json!({
-                        "id": row.get::<_, i64>(0)?,
-                        "session_id": session_id_clone,
-                        "sequence_num": row.get::<_, i64>(1)?,
-                        "role": row.get::<_, String>(2)?,
-                        "content_preview": row.get::<_, Option<String>>(3)?,
-                        "search_content": row.get::<_, Option<String>>(4)?,
-                        "has_code": row.get::<_, bool>(5)?,
-                        "has_error": row.get::<_, bool>(6)?,
-                        "has_file_changes": row.get::<_, bool>(7)?,
-                        "tool_name": row.get::<_, Option<String>>(8)?,
-                        "tool_type": row.get::<_, Option<String>>(9)?,
-                        "tool_summary": row.get::<_, Option<String>>(10)?,
-                        "byte_offset": row.get::<_, i64>(11)?,
-                        "byte_length": row.get::<_, i64>(12)?,
-                        "input_tokens": row.get::<_, Option<i64>>(13)?,
-                        "output_tokens": row.get::<_, Option<i64>>(14)?,
-                        "cache_read_tokens": row.get::<_, Option<i64>>(15)?,
-                        "cache_creation_tokens": row.get::<_, Option<i64>>(16)?,
-                        "model": row.get::<_, Option<String>>(17)?,
-                        "timestamp": row.get::<_, String>(18)?,
-                    }))
-                })?
-                .filter_map(|r| r.ok())
-                .collect();
+            let messages = session_messages_json(conn, &session_id, limit, offset)?;
 
             let total: i64 = conn
                 .query_row(
@@ -1332,13 +2637,11 @@ pub async fn get_message_content(
 
     // Read file content in spawn_blocking to avoid blocking async runtime
     let file_result = tokio::task::spawn_blocking(move || {
-        use std::io::{Read, Seek, SeekFrom};
-
-        let mut file = std::fs::File::open(&file_path)?;
-        file.seek(SeekFrom::Start(byte_offset as u64))?;
-
-        let mut buffer = vec![0u8; byte_length as usize];
-        file.read_exact(&mut buffer)?;
+        let buffer = crate::parser::file::read_range(
+            std::path::Path::new(&file_path),
+            byte_offset as u64,
+            byte_length as usize,
+        )?;
 
         String::from_utf8(buffer)
             .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
@@ -1556,133 +2859,93 @@ fn normalize_raw_event(event: serde_json::Value) -> serde_json::Value {
 // Search
 // ============================================================================
 
-#[derive(Debug, Deserialize)]
-pub struct SearchRequest {
-    pub query: String,
-    pub project_id: Option<String>,
-    #[serde(rename = "type", default = "default_search_type")]
-    pub search_type: String,
-    pub limit: Option<i64>,
-    /// Filter by role: "all", "user", "assistant", "tool"
-    pub role: Option<String>,
-    /// Only return messages with code
-    pub has_code: Option<bool>,
+impl From<&SearchRequest> for SearchFilters {
+    fn from(req: &SearchRequest) -> Self {
+        SearchFilters {
+            role: req.role.clone(),
+            has_code: req.has_code,
+            language: req.language.clone(),
+            tool_name: req.tool_name.clone(),
+            tool_type: req.tool_type.clone(),
+        }
+    }
 }
 
-fn default_search_type() -> String {
-    "fulltext".to_string()
+impl SearchFilters {
+    /// Whether an in-memory message passes the filters (ephemeral mode).
+    /// Like `sql`, but without the system and Write/Edit exclusions.
+    fn matches(&self, m: &crate::ephemeral::MessageMeta) -> bool {
+        let role_ok = match self.role.as_deref() {
+            Some("tool") => m.tool_type.is_some(),
+            Some(role @ ("user" | "assistant")) => m.role == role && m.tool_type.is_none(),
+            _ => true,
+        };
+        role_ok
+            && (self.has_code != Some(true) || m.has_code)
+            && self
+                .language_value()
+                .is_none_or(|language| m.code_language.as_deref() == Some(language.as_str()))
+            && self
+                .tool_name
+                .as_deref()
+                .is_none_or(|name| m.tool_name.as_deref() == Some(name))
+            && self
+                .tool_type
+                .as_deref()
+                .is_none_or(|tool_type| m.tool_type.as_deref() == Some(tool_type))
+    }
 }
 
 pub async fn search(
     State(state): State<AppState>,
     Json(req): Json<SearchRequest>,
 ) -> impl IntoResponse {
+    let created =
+        match CreatedRange::parse(req.created_after.as_deref(), req.created_before.as_deref()) {
+            Ok(created) => created,
+            Err(e) => return bad_request(e),
+        };
+
+    if let Some(idx) = &state.ephemeral {
+        return search_ephemeral(idx, &req, &created).into_response();
+    }
     if state.db.is_none() {
         return Json(serde_json::json!({ "results": [], "total": 0 })).into_response();
     }
 
+    if req.search_type == "hybrid" {
+        return search_hybrid(state, req, created).await;
+    }
+
     let limit = req.limit.unwrap_or(100);
-    let query_str = req.query.clone();
+    let offset = req.offset.unwrap_or(0).max(0);
+    let query_str = if req.raw {
+        req.query.clone()
+    } else {
+        fts_match_query(&req.query)
+    };
+    if query_str.trim().is_empty() {
+        return Json(serde_json::json!({ "results": [], "total_count": 0 })).into_response();
+    }
     let project_id = req.project_id.clone();
-    let role_filter = req.role.clone();
-    let has_code_filter = req.has_code;
+    let filters = SearchFilters::from(&req);
 
     let result = state
         .db
         .as_ref()
         .unwrap()
         .with_read_conn(move |conn| {
-            // Build filter clauses
-            let mut filter_clauses = String::new();
-
-            // Exclude system messages
-            filter_clauses.push_str(" AND m.role != 'system'");
-
-            // Exclude Write/Edit tool_type='use' - redundant with tool_type='result'
-            filter_clauses.push_str(
-                " AND (m.tool_type IS NULL OR m.tool_type != 'use' OR m.tool_name NOT IN ('Write', 'Edit'))",
-            );
-
-            // Apply role filter
-            if let Some(ref role) = role_filter {
-                match role.as_str() {
-                    "all" => {}
-                    "tool" => filter_clauses.push_str(" AND m.tool_type IS NOT NULL"),
-                    "user" => filter_clauses.push_str(" AND m.role = 'user' AND m.tool_type IS NULL"),
-                    "assistant" => {
-                        filter_clauses.push_str(" AND m.role = 'assistant' AND m.tool_type IS NULL")
-                    }
-                    _ => {}
-                }
-            }
-
-            // Apply has_code filter
-            if has_code_filter == Some(true) {
-                filter_clauses.push_str(" AND m.has_code = 1");
-            }
-
-            // Build SQL with all fields needed by Desktop
-            let sql = if project_id.is_some() {
-                format!(
-                    "SELECT m.session_id, s.title, s.file_path, m.sequence_num, m.content_preview,
-                            m.role, m.timestamp, m.tool_name, m.tool_type, m.has_code,
-                            m.byte_offset, m.byte_length, bm25(session_messages_fts) as score
-                     FROM session_messages_fts fts
-                     JOIN session_messages m ON m.id = fts.rowid
-                     JOIN sessions s ON s.id = m.session_id
-                     WHERE session_messages_fts MATCH ? AND s.project_id = ?{filter_clauses}
-                     ORDER BY score
-                     LIMIT {limit}"
-                )
-            } else {
-                format!(
-                    "SELECT m.session_id, s.title, s.file_path, m.sequence_num, m.content_preview,
-                            m.role, m.timestamp, m.tool_name, m.tool_type, m.has_code,
-                            m.byte_offset, m.byte_length, bm25(session_messages_fts) as score
-                     FROM session_messages_fts fts
-                     JOIN session_messages m ON m.id = fts.rowid
-                     JOIN sessions s ON s.id = m.session_id
-                     WHERE session_messages_fts MATCH ?{filter_clauses}
-                     ORDER BY score
-                     LIMIT {limit}"
-                )
-            };
-
-            let mut stmt = conn.prepare(&sql)?;
-
-            let map_row = |row: &rusqlite::Row| -> rusqlite::Result<serde_json::Value> {
-                let score: f64 = row.get(12)?;
-                // Normalize BM25 score (negative, lower is better) to 0-1 scale
-                let normalized_score = 1.0 / (1.0 + (-score).abs());
-
-                Ok(serde_json::json!({
-                    "session_id": row.get::<_, String>(0)?,
-                    "session_title": row.get::<_, Option<String>>(1)?,
-                    "session_file_path": row.get::<_, String>(2)?,
-                    "line_number": row.get::<_, i64>(3)?,
-                    "preview": row.get::<_, Option<String>>(4)?.unwrap_or_default(),
-                    "role": row.get::<_, String>(5)?,
-                    "timestamp": row.get::<_, String>(6)?,
-                    "tool_name": row.get::<_, Option<String>>(7)?,
-                    "tool_type": row.get::<_, Option<String>>(8)?,
-                    "has_code": row.get::<_, bool>(9)?,
-                    "byte_offset": row.get::<_, i64>(10)?,
-                    "byte_length": row.get::<_, i64>(11)?,
-                    "relevance_score": normalized_score,
-                }))
-            };
-
-            let results: Vec<serde_json::Value> = if let Some(ref pid) = project_id {
-                stmt.query_map([&query_str, pid], map_row)?
-                    .filter_map(|r| r.ok())
-                    .collect()
-            } else {
-                stmt.query_map([&query_str], map_row)?
-                    .filter_map(|r| r.ok())
-                    .collect()
-            };
-
-            let total_count = results.len();
+            let results = fulltext_search(
+                conn,
+                &query_str,
+                project_id.as_deref(),
+                &filters,
+                &created,
+                limit,
+                offset,
+            )?;
+            let total_count =
+                fulltext_count(conn, &query_str, project_id.as_deref(), &filters, &created)?;
             Ok::<_, rusqlite::Error>((results, total_count))
         })
         .await;
@@ -1693,6 +2956,11 @@ pub async fn search(
             "total_count": total_count,
         }))
         .into_response(),
+        // Only raw queries can be malformed
+        Err(e) if is_fts_syntax_error(&e) => bad_request(format!(
+            "Invalid search syntax ({}). Quote terms containing punctuation, or omit \"raw\" to search for the words as typed",
+            e
+        )),
         Err(e) => (
             StatusCode::INTERNAL_SERVER_ERROR,
             Json(serde_json::json!({ "error": e.to_string() })),
@@ -1701,47 +2969,345 @@ pub async fn search(
     }
 }
 
+/// Ephemeral mode search over the in-memory term index, in the fulltext result shape.
+/// Search type and date range are ignored; there are no BM25 scores or snippets.
+fn search_ephemeral(
+    idx: &crate::ephemeral::EphemeralIndex,
+    req: &SearchRequest,
+    created: &CreatedRange,
+) -> Json<serde_json::Value> {
+    let limit = req.limit.unwrap_or(100).max(0) as usize;
+    let offset = req.offset.unwrap_or(0).max(0) as usize;
+    let filters = SearchFilters::from(req);
+    let (hits, total_count) = idx.search(
+        &req.query,
+        req.project_id.as_deref(),
+        |session, message| filters.matches(message) && created.contains(&session.created_at),
+        offset,
+        limit,
+    );
+    let results: Vec<serde_json::Value> = hits
+        .into_iter()
+        .map(|hit| {
+            serde_json::json!({
+                "session_id": hit.session.id,
+                "session_title": hit.session.title,
+                "session_file_path": hit.session.file_path,
+                "line_number": hit.message.sequence_num,
+                "preview": hit.message.content_preview.unwrap_or_default(),
+                "role": hit.message.role,
+                "timestamp": hit.message.timestamp,
+                "tool_name": hit.message.tool_name,
+                "tool_type": hit.message.tool_type,
+                "has_code": hit.message.has_code,
+                "byte_offset": hit.message.byte_offset,
+                "byte_length": hit.message.byte_length,
+                "relevance_score": hit.score,
+                "bm25_score": null,
+                "highlighted_snippet": null,
+            })
+        })
+        .collect();
+    Json(serde_json::json!({
+        "results": results,
+        "total_count": total_count,
+    }))
+}
+
 #[derive(Debug, Deserialize)]
-pub struct SearchSessionQuery {
-    pub q: String,
-    pub limit: Option<i64>,
+pub struct SearchFacetsQuery {
+    pub project_id: Option<String>,
 }
 
-pub async fn search_session(
+/// Message counts per tool name, for building search filter UIs. Counts the
+/// messages a `tool_name` search filter would match.
+pub async fn search_facets(
     State(state): State<AppState>,
-    Path(session_id): Path<String>,
-    Query(query): Query<SearchSessionQuery>,
+    Query(query): Query<SearchFacetsQuery>,
 ) -> impl IntoResponse {
+    if let Some(idx) = &state.ephemeral {
+        let mut counts: std::collections::HashMap<String, i64> = std::collections::HashMap::new();
+        for session in idx.list_sessions(query.project_id.as_deref()) {
+            for m in idx.get_messages(&session.id) {
+                if m.role == "system" {
+                    continue;
+                }
+                if let Some(tool_name) = m.tool_name {
+                    *counts.entry(tool_name).or_default() += 1;
+                }
+            }
+        }
+        let mut counts: Vec<(String, i64)> = counts.into_iter().collect();
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        return Json(tool_name_facets_json(counts)).into_response();
+    }
     if state.db.is_none() {
-        return Json(serde_json::json!({ "results": [], "total": 0 })).into_response();
+        return Json(tool_name_facets_json(Vec::new())).into_response();
     }
 
-    let limit = query.limit.unwrap_or(50);
-    let search_query = query.q.clone();
-
     let result = state
         .db
         .as_ref()
         .unwrap()
         .with_read_conn(move |conn| {
-            let sql = format!(
-                "SELECT m.sequence_num, m.content_preview, m.timestamp,
-                        bm25(session_messages_fts) as score
-                 FROM session_messages_fts fts
-                 JOIN session_messages m ON m.id = fts.rowid
-                 WHERE session_messages_fts MATCH ? AND m.session_id = ?
-                 ORDER BY score
-                 LIMIT {limit}"
-            );
-
-            let mut stmt = conn.prepare(&sql)?;
-            let results: Vec<serde_json::Value> = stmt
+            let mut params = Vec::new();
+            let project_clause = if let Some(pid) = query.project_id.clone() {
+                params.push(pid);
+                " AND s.project_id = ?"
+            } else {
+                ""
+            };
+            let filter_clauses = SearchFilters::default().sql(&mut params);
+            let mut stmt = conn.prepare(&format!(
+                "SELECT m.tool_name, COUNT(*) AS count
+                 FROM session_messages m
+                 JOIN sessions s ON s.id = m.session_id
+                 WHERE m.tool_name IS NOT NULL{project_clause}{filter_clauses}
+                 GROUP BY m.tool_name
+                 ORDER BY count DESC, m.tool_name"
+            ))?;
+            let rows = stmt.query_map(rusqlite::params_from_iter(params), |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?))
+            })?;
+            rows.collect::<rusqlite::Result<Vec<_>>>()
+        })
+        .await;
+
+    match result {
+        Ok(counts) => Json(tool_name_facets_json(counts)).into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({ "error": e.to_string() })),
+        )
+            .into_response(),
+    }
+}
+
+fn tool_name_facets_json(counts: Vec<(String, i64)>) -> serde_json::Value {
+    let tool_names: Vec<serde_json::Value> = counts
+        .into_iter()
+        .map(|(tool_name, count)| serde_json::json!({ "tool_name": tool_name, "count": count }))
+        .collect();
+    serde_json::json!({ "tool_names": tool_names })
+}
+
+/// Reciprocal-rank fusion constant (dampens the weight of top ranks)
+const RRF_K: f64 = 60.0;
+
+/// FTS5 query matching any of the query's words, so paraphrases that share only
+/// some words still become hybrid candidates
+fn fts_any_word_query(query: &str) -> String {
+    query
+        .split_whitespace()
+        .map(|word| format!("\"{}\"", word.replace('"', "")))
+        .filter(|word| word != "\"\"")
+        .collect::<Vec<_>>()
+        .join(" OR ")
+}
+
+/// Fuse a BM25-ordered candidate list with vector similarity using weighted
+/// reciprocal-rank fusion.
+///
+/// `similarities[i]` is the cosine similarity of the i-th BM25 candidate to the
+/// query. Returns `(candidate index, fused score)` best first; scores are scaled so
+/// a candidate ranked first by both lists scores 1.0.
+fn hybrid_rank(similarities: &[f32], vector_weight: f64) -> Vec<(usize, f64)> {
+    let mut by_similarity: Vec<usize> = (0..similarities.len()).collect();
+    by_similarity.sort_by(|&a, &b| {
+        similarities[b]
+            .partial_cmp(&similarities[a])
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    let mut vector_rank = vec![0; similarities.len()];
+    for (rank, &idx) in by_similarity.iter().enumerate() {
+        vector_rank[idx] = rank;
+    }
+
+    let mut fused: Vec<(usize, f64)> = (0..similarities.len())
+        .map(|idx| {
+            let score = (1.0 - vector_weight) / (RRF_K + idx as f64 + 1.0)
+                + vector_weight / (RRF_K + vector_rank[idx] as f64 + 1.0);
+            (idx, score * (RRF_K + 1.0))
+        })
+        .collect();
+    fused.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+    fused
+}
+
+/// BM25 candidates re-ranked by `"type": "hybrid"` search. Fixed, so every page
+/// is cut from the same ranking.
+const HYBRID_CANDIDATES: usize = 500;
+
+/// `"type": "hybrid"` search: BM25 candidates re-ranked with embedding similarity.
+/// Falls back to BM25 order when the embedding model is unavailable.
+async fn search_hybrid(
+    state: AppState,
+    req: SearchRequest,
+    created: CreatedRange,
+) -> axum::response::Response {
+    let vector_weight = req.vector_weight.unwrap_or(0.5);
+    if !(0.0..=1.0).contains(&vector_weight) {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": "vector_weight must be between 0.0 and 1.0" })),
+        )
+            .into_response();
+    }
+
+    let limit = req.limit.unwrap_or(100).max(0) as usize;
+    let offset = req.offset.unwrap_or(0).max(0) as usize;
+    let fts_query = fts_any_word_query(&req.query);
+    let project_id = req.project_id.clone();
+    let filters = SearchFilters::from(&req);
+
+    let candidates = state
+        .db
+        .as_ref()
+        .unwrap()
+        .with_read_conn(move |conn| {
+            if fts_query.is_empty() {
+                return Ok((vec![], 0));
+            }
+            let (match_sql, params) =
+                fulltext_match_sql(&fts_query, project_id.as_deref(), &filters, &created);
+            let sql = format!(
+                "SELECT m.session_id, s.title, s.file_path, m.sequence_num, m.content_preview,
+                        m.role, m.timestamp, m.tool_name, m.tool_type, m.has_code,
+                        m.byte_offset, m.byte_length, bm25(session_messages_fts) as score,
+                        {SNIPPET_SQL}, m.search_content
+                 {match_sql}
+                 ORDER BY score
+                 LIMIT {HYBRID_CANDIDATES}"
+            );
+            let mut stmt = conn.prepare(&sql)?;
+            let rows: Vec<(serde_json::Value, String)> = stmt
+                .query_map(rusqlite::params_from_iter(params), |row| {
+                    Ok((
+                        search_result_json(row)?,
+                        row.get::<_, Option<String>>(14)?.unwrap_or_default(),
+                    ))
+                })?
+                .filter_map(|r| r.ok())
+                .collect();
+            // Only the candidates can be paged through
+            let total_count =
+                fulltext_count(conn, &fts_query, project_id.as_deref(), &filters, &created)?
+                    .min(HYBRID_CANDIDATES as i64);
+            Ok::<_, rusqlite::Error>((rows, total_count))
+        })
+        .await;
+
+    let (candidates, total_count) = match candidates {
+        Ok(c) => c,
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": e.to_string() })),
+            )
+                .into_response();
+        }
+    };
+
+    let query = req.query.clone();
+    let results = tokio::task::spawn_blocking(move || {
+        let (results, contents): (Vec<serde_json::Value>, Vec<String>) =
+            candidates.into_iter().unzip();
+        let texts: Vec<&str> = contents.iter().map(|c| c.as_str()).collect();
+        let similarities = crate::embeddings::embed_text(&query).and_then(|q| {
+            crate::embeddings::embed_texts(&texts).map(|embeddings| {
+                embeddings
+                    .iter()
+                    .map(|e| crate::embeddings::cosine_similarity(&q, e))
+                    .collect::<Vec<f32>>()
+            })
+        });
+
+        match similarities {
+            Ok(similarities) => hybrid_rank(&similarities, vector_weight)
+                .into_iter()
+                .skip(offset)
+                .take(limit)
+                .map(|(idx, score)| {
+                    let mut result = results[idx].clone();
+                    result["relevance_score"] = serde_json::json!(score);
+                    result["vector_score"] = serde_json::json!(similarities[idx]);
+                    result
+                })
+                .collect(),
+            Err(e) => {
+                tracing::debug!("Hybrid search embedding failed, using BM25 order: {}", e);
+                results
+                    .into_iter()
+                    .skip(offset)
+                    .take(limit)
+                    .collect::<Vec<_>>()
+            }
+        }
+    })
+    .await;
+    let results: Vec<serde_json::Value> = match results {
+        Ok(results) => results,
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": format!("Hybrid ranking failed: {}", e) })),
+            )
+                .into_response();
+        }
+    };
+
+    Json(serde_json::json!({
+        "results": results,
+        "total_count": total_count,
+    }))
+    .into_response()
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SearchSessionQuery {
+    pub q: String,
+    pub limit: Option<i64>,
+}
+
+pub async fn search_session(
+    State(state): State<AppState>,
+    Path(session_id): Path<String>,
+    Query(query): Query<SearchSessionQuery>,
+) -> impl IntoResponse {
+    if state.db.is_none() {
+        return Json(serde_json::json!({ "results": [], "total": 0 })).into_response();
+    }
+
+    let limit = query.limit.unwrap_or(50);
+    let search_query = fts_match_query(&query.q);
+    if search_query.is_empty() {
+        return Json(serde_json::json!({ "results": [] })).into_response();
+    }
+
+    let result = state
+        .db
+        .as_ref()
+        .unwrap()
+        .with_read_conn(move |conn| {
+            let sql = format!(
+                "SELECT m.sequence_num, m.content_preview, m.timestamp,
+                        bm25(session_messages_fts) as score, {SNIPPET_SQL}
+                 FROM session_messages_fts fts
+                 JOIN session_messages m ON m.id = fts.rowid
+                 WHERE session_messages_fts MATCH ? AND m.session_id = ?
+                 ORDER BY score
+                 LIMIT {limit}"
+            );
+
+            let mut stmt = conn.prepare(&sql)?;
+            let results: Vec<serde_json::Value> = stmt
                 .query_map([&search_query, &session_id], |row| {
                     Ok(serde_json::json!({
                         "message_seq": row.get::<_, i64>(0)?,
                         "snippet": row.get::<_, Option<String>>(1)?,
                         "timestamp": row.get::<_, String>(2)?,
                         "score": row.get::<_, f64>(3)?,
+                        "highlighted_snippet": row.get::<_, Option<String>>(4)?,
                     }))
                 })?
                 .filter_map(|r| r.ok())
@@ -1761,6 +3327,149 @@ pub async fn search_session(
     }
 }
 
+#[derive(Debug, Deserialize)]
+pub struct SessionSemanticSearchRequest {
+    pub query: String,
+    pub limit: Option<usize>,
+}
+
+/// Rank a session's messages by embedding similarity to the query.
+///
+/// Finds messages that discuss the query without sharing its words. Needs
+/// message embeddings from `POST /sessions/:id/embeddings/backfill`.
+pub async fn semantic_search_session(
+    State(state): State<AppState>,
+    Path(session_id): Path<String>,
+    Json(req): Json<SessionSemanticSearchRequest>,
+) -> impl IntoResponse {
+    let Some(db) = state.db.clone() else {
+        return (
+            StatusCode::NOT_IMPLEMENTED,
+            Json(serde_json::json!({ "error": "Not available in ephemeral mode" })),
+        )
+            .into_response();
+    };
+    let limit = req.limit.unwrap_or(10);
+
+    let sid = session_id.clone();
+    let embedded = db
+        .with_read_conn(move |conn| {
+            conn.query_row("SELECT 1 FROM sessions WHERE id = ?", [&sid], |_| Ok(()))?;
+            conn.query_row(
+                "SELECT COUNT(*) FROM message_embeddings e
+                 JOIN session_messages m ON m.id = e.message_id
+                 WHERE m.session_id = ?",
+                [&sid],
+                |row| row.get::<_, i64>(0),
+            )
+        })
+        .await;
+    match embedded {
+        Ok(0) => {
+            return (
+                StatusCode::CONFLICT,
+                Json(serde_json::json!({
+                    "error": "No message embeddings for this session yet. \
+                              Run POST /api/sessions/:id/embeddings/backfill first."
+                })),
+            )
+                .into_response()
+        }
+        Ok(_) => {}
+        Err(rusqlite::Error::QueryReturnedNoRows) => {
+            return (
+                StatusCode::NOT_FOUND,
+                Json(serde_json::json!({ "error": "Session not found" })),
+            )
+                .into_response()
+        }
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": e.to_string() })),
+            )
+                .into_response()
+        }
+    }
+
+    let query_embedding = match tokio::task::spawn_blocking(move || {
+        crate::embeddings::embed_text(&req.query)
+    })
+    .await
+    {
+        Ok(Ok(embedding)) => embedding,
+        Ok(Err(e)) => {
+            return (
+                StatusCode::SERVICE_UNAVAILABLE,
+                Json(serde_json::json!({
+                    "error": format!("Embedding model unavailable: {}", e)
+                })),
+            )
+                .into_response()
+        }
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": e.to_string() })),
+            )
+                .into_response()
+        }
+    };
+
+    let result = db
+        .with_read_conn(move |conn| {
+            rank_session_messages(conn, &session_id, &query_embedding, limit)
+        })
+        .await;
+
+    match result {
+        Ok(results) => Json(serde_json::json!({ "results": results })).into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({ "error": e.to_string() })),
+        )
+            .into_response(),
+    }
+}
+
+/// The `limit` embedded messages of a session most similar to `query`, best
+/// first, each with its cosine `score`
+fn rank_session_messages(
+    conn: &rusqlite::Connection,
+    session_id: &str,
+    query: &[f32],
+    limit: usize,
+) -> rusqlite::Result<Vec<serde_json::Value>> {
+    let mut stmt = conn.prepare(
+        "SELECT m.sequence_num, m.content_preview, m.timestamp, e.embedding
+         FROM message_embeddings e
+         JOIN session_messages m ON m.id = e.message_id
+         WHERE m.session_id = ?",
+    )?;
+    let mut scored: Vec<(f32, serde_json::Value)> = stmt
+        .query_map([session_id], |row| {
+            let embedding = crate::embeddings::bytes_to_embedding(&row.get::<_, Vec<u8>>(3)?);
+            Ok((
+                crate::embeddings::cosine_similarity(query, &embedding),
+                serde_json::json!({
+                    "message_seq": row.get::<_, i64>(0)?,
+                    "snippet": row.get::<_, Option<String>>(1)?,
+                    "timestamp": row.get::<_, String>(2)?,
+                }),
+            ))
+        })?
+        .collect::<rusqlite::Result<_>>()?;
+    scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+    Ok(scored
+        .into_iter()
+        .take(limit)
+        .map(|(score, mut result)| {
+            result["score"] = serde_json::json!(score);
+            result
+        })
+        .collect())
+}
+
 // ============================================================================
 // Session Streaming (Phase 3.2)
 // ============================================================================
@@ -1778,11 +3487,146 @@ pub struct SessionByteLine {
     pub line_number: i64,
 }
 
-/// Read session bytes from file starting at offset
+/// Parse a single `Range: bytes=...` spec against a file of `file_size` bytes.
+///
+/// Returns the inclusive `(start, end)` clamped to the file, `Some(Err(()))`
+/// when the range can't be satisfied, and `None` for headers we don't handle
+/// (other units, multiple ranges, malformed) so the caller ignores them.
+fn parse_byte_range(header: &str, file_size: u64) -> Option<Result<(u64, u64), ()>> {
+    let spec = header.trim().strip_prefix("bytes=")?;
+    if spec.contains(',') {
+        return None;
+    }
+    let (start, end) = spec.split_once('-')?;
+    let (start, end) = (start.trim(), end.trim());
+
+    let range = if start.is_empty() {
+        // Suffix range: the last N bytes
+        let len: u64 = end.parse().ok()?;
+        if len == 0 || file_size == 0 {
+            return Some(Err(()));
+        }
+        (file_size.saturating_sub(len), file_size - 1)
+    } else {
+        let start: u64 = start.parse().ok()?;
+        let end = if end.is_empty() {
+            u64::MAX
+        } else {
+            end.parse().ok()?
+        };
+        if end < start {
+            return None;
+        }
+        if start >= file_size {
+            return Some(Err(()));
+        }
+        (start, end.min(file_size - 1))
+    };
+    Some(Ok(range))
+}
+
+/// Chunk size for streaming raw session bytes
+const BYTE_RANGE_CHUNK: usize = 64 * 1024;
+
+/// Serve a `Range: bytes=...` request for a session file as raw bytes.
+///
+/// The range is streamed in chunks rather than read into memory. Returns None
+/// when the header isn't a range we support, so the caller answers as if it
+/// were absent.
+async fn read_session_byte_range(
+    path: std::path::PathBuf,
+    range: String,
+) -> Option<axum::response::Response> {
+    use axum::http::header;
+    use std::io::Read;
+
+    // Offsets and sizes address the decompressed content of `.jsonl.gz` files
+    let opened = tokio::task::spawn_blocking(move || {
+        let file_size = crate::parser::file::content_len(&path)?;
+        let Some(range) = parse_byte_range(&range, file_size) else {
+            return Ok(None);
+        };
+        let start = range.map_or(0, |(start, _)| start);
+        let file = crate::parser::file::open_at(&path, start)?;
+        Ok::<_, std::io::Error>(Some((file, file_size, range)))
+    })
+    .await;
+
+    // parse_byte_range only yields non-empty ranges (start <= end < file_size);
+    // an empty or past-the-end range is unsatisfiable
+    let (file, file_size, (start, end)) = match opened {
+        Ok(Ok(None)) => return None,
+        Ok(Ok(Some((file, file_size, Ok(range))))) => (file, file_size, range),
+        Ok(Ok(Some((_, file_size, Err(()))))) => {
+            return Some(
+                (
+                    StatusCode::RANGE_NOT_SATISFIABLE,
+                    [
+                        (header::ACCEPT_RANGES, "bytes".to_string()),
+                        (header::CONTENT_RANGE, format!("bytes */{}", file_size)),
+                    ],
+                )
+                    .into_response(),
+            )
+        }
+        _ => {
+            return Some(
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(serde_json::json!({ "error": "Failed to read session file" })),
+                )
+                    .into_response(),
+            )
+        }
+    };
+    let len = end - start + 1;
+
+    // A read error (or the file shrinking) ends the body early
+    let (tx, rx) = tokio::sync::mpsc::channel::<std::io::Result<axum::body::Bytes>>(4);
+    tokio::task::spawn_blocking(move || {
+        let mut reader = file.take(len);
+        let mut buf = vec![0; BYTE_RANGE_CHUNK];
+        loop {
+            let chunk = match reader.read(&mut buf) {
+                Ok(0) => break,
+                Ok(n) => Ok(axum::body::Bytes::copy_from_slice(&buf[..n])),
+                Err(e) => Err(e),
+            };
+            let failed = chunk.is_err();
+            // Client went away
+            if tx.blocking_send(chunk).is_err() || failed {
+                break;
+            }
+        }
+    });
+
+    Some(
+        (
+            StatusCode::PARTIAL_CONTENT,
+            [
+                (header::CONTENT_TYPE, "application/octet-stream".to_string()),
+                (header::ACCEPT_RANGES, "bytes".to_string()),
+                (
+                    header::CONTENT_RANGE,
+                    format!("bytes {}-{}/{}", start, end, file_size),
+                ),
+                (header::CONTENT_LENGTH, len.to_string()),
+            ],
+            axum::body::Body::from_stream(tokio_stream::wrappers::ReceiverStream::new(rx)),
+        )
+            .into_response(),
+    )
+}
+
+/// Read session bytes from file starting at offset.
+///
+/// With a `Range: bytes=start-end` header the raw bytes are returned as
+/// `206 Partial Content` instead of parsed lines.
 pub async fn read_session_bytes(
     State(state): State<AppState>,
     Path(session_id): Path<String>,
     Query(query): Query<ReadBytesQuery>,
+    headers: axum::http::HeaderMap,
 ) -> impl IntoResponse {
     let offset = query.offset.unwrap_or(0);
 
@@ -1821,23 +3665,28 @@ pub async fn read_session_bytes(
         }
     };
 
+    let range = headers
+        .get(axum::http::header::RANGE)
+        .and_then(|h| h.to_str().ok());
+    if let Some(range) = range {
+        let path = std::path::PathBuf::from(&file_path);
+        if let Some(resp) = read_session_byte_range(path, range.to_string()).await {
+            return resp;
+        }
+    }
+
     // Read file and get lines from offset
     let path = std::path::PathBuf::from(&file_path);
     let read_result = tokio::task::spawn_blocking(move || {
-        use std::io::{BufRead, Seek, SeekFrom};
-        let file = std::fs::File::open(&path)?;
-        let file_size = file.metadata()?.len() as i64;
-        let mut reader = std::io::BufReader::new(file);
-
-        if offset > 0 {
-            reader.seek(SeekFrom::Start(offset as u64))?;
-        }
+        use std::io::BufRead;
+        let file_size = crate::parser::file::content_len(&path)? as i64;
+        let reader =
+            std::io::BufReader::new(crate::parser::file::open_at(&path, offset.max(0) as u64)?);
 
         let mut lines = Vec::new();
         let mut current_offset = offset;
-        let mut line_number = 1i64;
 
-        for line_result in reader.lines() {
+        for (line_number, line_result) in (1i64..).zip(reader.lines()) {
             let line = line_result?;
             let byte_length = line.len() as i64 + 1; // +1 for newline
             lines.push(SessionByteLine {
@@ -1847,7 +3696,6 @@ pub async fn read_session_bytes(
                 line_number,
             });
             current_offset += byte_length;
-            line_number += 1;
         }
 
         Ok::<_, std::io::Error>((lines, file_size))
@@ -1868,20 +3716,409 @@ pub async fn read_session_bytes(
     }
 }
 
+// ============================================================================
+// Session Export
+// ============================================================================
+
 #[derive(Debug, Deserialize)]
-pub struct ParsedMessageInput {
-    pub role: String,
-    pub content_preview: Option<String>,
-    pub search_content: Option<String>,
-    pub has_code: bool,
-    pub has_error: bool,
-    pub has_file_changes: bool,
-    pub tool_name: Option<String>,
-    pub tool_type: Option<String>,
-    pub tool_summary: Option<String>,
-    pub byte_offset: i64,
-    pub byte_length: i64,
-    pub input_tokens: Option<i64>,
+pub struct ExportSessionQuery {
+    /// "markdown" (default) or "json"
+    pub format: Option<String>,
+}
+
+/// A message as rendered by the Markdown export
+struct ExportMessage {
+    role: String,
+    content: String,
+    has_code: bool,
+    tool_name: Option<String>,
+    tool_type: Option<String>,
+    tool_summary: Option<String>,
+}
+
+/// Render a session transcript as Markdown: one heading per user/assistant
+/// turn, code fenced when the parser flagged it, tool calls as one-liners
+fn render_session_markdown(
+    title: Option<&str>,
+    ai_tool: &str,
+    created_at: &str,
+    messages: &[ExportMessage],
+) -> String {
+    let mut out = markdown_header(title, ai_tool, created_at);
+    for m in messages {
+        out.push_str(&markdown_message(m));
+    }
+    out
+}
+
+/// Title line and metadata that open a Markdown transcript
+fn markdown_header(title: Option<&str>, ai_tool: &str, created_at: &str) -> String {
+    format!(
+        "# {}\n\n*{} · {}*\n",
+        title.unwrap_or("Untitled session"),
+        ai_tool,
+        created_at
+    )
+}
+
+/// One message of a Markdown transcript; empty for system messages
+fn markdown_message(m: &ExportMessage) -> String {
+    if m.role == "system" {
+        return String::new();
+    }
+
+    if let Some(tool_type) = &m.tool_type {
+        let label = if tool_type == "result" {
+            "Tool result"
+        } else {
+            "Tool call"
+        };
+        let summary = m
+            .tool_summary
+            .as_deref()
+            .or_else(|| m.content.lines().find(|l| !l.trim().is_empty()))
+            .unwrap_or("");
+        return format!(
+            "\n> **{}** `{}`: {}\n",
+            label,
+            m.tool_name.as_deref().unwrap_or("tool"),
+            summary.trim()
+        );
+    }
+
+    let heading = match m.role.as_str() {
+        "user" => "User",
+        "assistant" => "Assistant",
+        other => other,
+    };
+    let content = m.content.trim();
+    if m.has_code && !content.contains("```") {
+        format!("\n## {}\n\n```\n{}\n```\n", heading, content)
+    } else {
+        format!("\n## {}\n\n{}\n", heading, content)
+    }
+}
+
+/// A `SESSION_MESSAGES_SQL` row as Markdown, preferring full content to the preview
+fn markdown_message_row(_session_id: &str, row: &rusqlite::Row) -> rusqlite::Result<String> {
+    let content = row
+        .get::<_, Option<String>>(4)?
+        .filter(|c| !c.is_empty())
+        .or(row.get::<_, Option<String>>(3)?)
+        .unwrap_or_default();
+    Ok(markdown_message(&ExportMessage {
+        role: row.get(2)?,
+        content,
+        has_code: row.get(5)?,
+        tool_name: row.get(8)?,
+        tool_type: row.get(9)?,
+        tool_summary: row.get(10)?,
+    }))
+}
+
+/// A `SESSION_MESSAGES_SQL` row as a JSON object
+fn json_message_row(session_id: &str, row: &rusqlite::Row) -> rusqlite::Result<String> {
+    Ok(message_row_json(session_id, row)?.to_string())
+}
+
+/// Attachment filename from the session title (`session-<id>.md` when untitled)
+fn export_filename(title: Option<&str>, session_id: &str) -> String {
+    let slug = title
+        .unwrap_or("")
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '-'
+            }
+        })
+        .collect::<String>()
+        .split('-')
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-");
+    let slug: String = slug.chars().take(80).collect();
+    let slug = slug.trim_end_matches('-');
+
+    if slug.is_empty() {
+        let id: String = session_id
+            .chars()
+            .filter(|c| c.is_ascii_alphanumeric() || *c == '-')
+            .take(36)
+            .collect();
+        format!("session-{}.md", id)
+    } else {
+        format!("{}.md", slug)
+    }
+}
+
+/// Export a session as a Markdown transcript or a self-contained JSON bundle
+pub async fn export_session(
+    State(state): State<AppState>,
+    Path(session_id): Path<String>,
+    Query(query): Query<ExportSessionQuery>,
+) -> axum::response::Response {
+    match query.format.as_deref().unwrap_or("markdown") {
+        "markdown" => export_session_markdown(&state, session_id).await,
+        "json" => export_session_json(&state, session_id).await,
+        format => (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": format!("Unknown export format: {}", format) })),
+        )
+            .into_response(),
+    }
+}
+
+/// Bundle a session with its messages, memories, skills, and markers.
+/// Related lists are empty (never missing) when nothing was extracted.
+async fn export_session_json(state: &AppState, session_id: String) -> axum::response::Response {
+    if let Some(idx) = &state.ephemeral {
+        let Some(session) = idx.get_session(&session_id) else {
+            return (
+                StatusCode::NOT_FOUND,
+                Json(serde_json::json!({ "error": "Session not found" })),
+            )
+                .into_response();
+        };
+        let messages: Vec<serde_json::Value> = idx
+            .get_messages(&session_id)
+            .into_iter()
+            .map(|m| ephemeral_message_json(&session_id, m))
+            .collect();
+        return Json(serde_json::json!({
+            "session": ephemeral_session_json(session),
+            "messages": messages,
+            "memories": [],
+            "skills": [],
+            "markers": [],
+        }))
+        .into_response();
+    }
+
+    let db = state.db.as_ref().unwrap().clone();
+    let sid = session_id.clone();
+    let result = db
+        .with_read_conn(move |conn| {
+            let session_id = &sid;
+            let session = match session_row_json(conn, session_id) {
+                Ok(session) => session,
+                Err(rusqlite::Error::QueryReturnedNoRows) => return Ok(None),
+                Err(e) => return Err(e.to_string()),
+            };
+            let memories: Vec<serde_json::Value> = conn
+                .prepare(
+                    "SELECT id, project_id, session_id, memory_type, title, content,
+                            context, tags, confidence, is_validated, extracted_at,
+                            file_reference, state
+                     FROM memories
+                     WHERE session_id = ?
+                     ORDER BY extracted_at, id",
+                )
+                .and_then(|mut stmt| {
+                    stmt.query_map([&session_id], crate::db::memories::row_to_memory)?
+                        .map(|r| r.map(memory_to_api_json))
+                        .collect()
+                })
+                .map_err(|e| e.to_string())?;
+
+            // Skills extracted from this session or linked to it as a duplicate
+            let skills: Vec<serde_json::Value> = conn
+                .prepare(
+                    "SELECT id, project_id, session_id, name, description, steps, confidence,
+                            extracted_at
+                     FROM skills
+                     WHERE session_id = ?1
+                        OR id IN (SELECT skill_id FROM skill_sessions WHERE session_id = ?1)
+                     ORDER BY extracted_at, id",
+                )
+                .and_then(|mut stmt| {
+                    stmt.query_map([&session_id], |row| {
+                        let steps: String = row.get(5)?;
+                        Ok(serde_json::json!({
+                            "id": row.get::<_, i64>(0)?,
+                            "project_id": row.get::<_, String>(1)?,
+                            "session_id": row.get::<_, String>(2)?,
+                            "name": row.get::<_, String>(3)?,
+                            "description": row.get::<_, String>(4)?,
+                            "steps": serde_json::from_str::<Vec<String>>(&steps)
+                                .unwrap_or_default(),
+                            "confidence": row.get::<_, f64>(6)?,
+                            "extracted_at": row.get::<_, String>(7)?,
+                        }))
+                    })?
+                    .collect()
+                })
+                .map_err(|e| e.to_string())?;
+
+            let markers = crate::ai::marker::get_markers(conn, session_id)?;
+
+            // Messages go last, streamed after everything else
+            Ok(Some(format!(
+                "{{\"session\":{},\"memories\":{},\"skills\":{},\"markers\":{},\"messages\":[",
+                session,
+                serde_json::Value::from(memories),
+                serde_json::Value::from(skills),
+                serde_json::json!(markers),
+            )))
+        })
+        .await;
+
+    match result {
+        Ok(Some(head)) => {
+            let (tx, body) = channel_body();
+            tokio::spawn(async move {
+                if tx.send(Ok(head.into())).await.is_err() {
+                    return;
+                }
+                let pages = MessagePages {
+                    limit: i64::MAX,
+                    offset: 0,
+                    page_rows: MESSAGE_PAGE_ROWS,
+                    render: json_message_row,
+                    separator: ",",
+                };
+                if send_session_messages(&db, &session_id, pages, &tx).await {
+                    let _ = tx.send(Ok("]}".into())).await;
+                }
+            });
+            (
+                [(axum::http::header::CONTENT_TYPE, "application/json")],
+                body,
+            )
+                .into_response()
+        }
+        Ok(None) => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": "Session not found" })),
+        )
+            .into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({ "error": e })),
+        )
+            .into_response(),
+    }
+}
+
+/// Render a session as a Markdown download. In db mode messages are streamed
+/// as they are read; a database error part way through aborts the download.
+async fn export_session_markdown(state: &AppState, session_id: String) -> axum::response::Response {
+    let headers = |title: Option<&str>| {
+        [
+            (
+                axum::http::header::CONTENT_TYPE,
+                "text/markdown; charset=utf-8".to_string(),
+            ),
+            (
+                axum::http::header::CONTENT_DISPOSITION,
+                format!(
+                    "attachment; filename=\"{}\"",
+                    export_filename(title, &session_id)
+                ),
+            ),
+        ]
+    };
+    let not_found = || {
+        (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": "Session not found" })),
+        )
+            .into_response()
+    };
+
+    // Ephemeral mode only keeps previews, so those stand in for full content
+    if let Some(idx) = &state.ephemeral {
+        let Some(session) = idx.get_session(&session_id) else {
+            return not_found();
+        };
+        let messages: Vec<ExportMessage> = idx
+            .get_messages(&session_id)
+            .into_iter()
+            .map(|m| ExportMessage {
+                role: m.role,
+                content: m.content_preview.unwrap_or_default(),
+                has_code: m.has_code,
+                tool_name: m.tool_name,
+                tool_type: m.tool_type,
+                tool_summary: m.tool_summary,
+            })
+            .collect();
+        let markdown = render_session_markdown(
+            session.title.as_deref(),
+            &session.ai_tool,
+            &session.created_at,
+            &messages,
+        );
+        return (headers(session.title.as_deref()), markdown).into_response();
+    }
+
+    let db = state.db.as_ref().unwrap().clone();
+    let sid = session_id.clone();
+    let session = db
+        .with_read_conn(move |conn| {
+            use rusqlite::OptionalExtension;
+            conn.query_row(
+                "SELECT title, ai_tool, created_at FROM sessions WHERE id = ? AND deleted_at IS NULL",
+                [&sid],
+                |row| {
+                    Ok((
+                        row.get::<_, Option<String>>(0)?,
+                        row.get::<_, String>(1)?,
+                        row.get::<_, String>(2)?,
+                    ))
+                },
+            )
+            .optional()
+        })
+        .await;
+    let (title, ai_tool, created_at) = match session {
+        Ok(Some(session)) => session,
+        Ok(None) => return not_found(),
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": e.to_string() })),
+            )
+                .into_response();
+        }
+    };
+
+    let headers = headers(title.as_deref());
+    let header = markdown_header(title.as_deref(), &ai_tool, &created_at);
+    let (tx, body) = channel_body();
+    tokio::spawn(async move {
+        if tx.send(Ok(header.into())).await.is_err() {
+            return;
+        }
+        let pages = MessagePages {
+            limit: i64::MAX,
+            offset: 0,
+            page_rows: MESSAGE_PAGE_ROWS,
+            render: markdown_message_row,
+            separator: "",
+        };
+        send_session_messages(&db, &session_id, pages, &tx).await;
+    });
+    (headers, body).into_response()
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ParsedMessageInput {
+    pub role: String,
+    pub content_preview: Option<String>,
+    pub search_content: Option<String>,
+    pub has_code: bool,
+    #[serde(default)]
+    pub code_language: Option<String>,
+    pub has_error: bool,
+    pub has_file_changes: bool,
+    pub tool_name: Option<String>,
+    pub tool_type: Option<String>,
+    pub tool_summary: Option<String>,
+    pub byte_offset: i64,
+    pub byte_length: i64,
+    pub input_tokens: Option<i64>,
     pub output_tokens: Option<i64>,
     pub cache_read_tokens: Option<i64>,
     pub cache_creation_tokens: Option<i64>,
@@ -1933,14 +4170,15 @@ pub async fn append_session_messages(
                         session_id, sequence_num, role, content_preview, search_content,
                         has_code, has_error, has_file_changes, tool_name, tool_type, tool_summary,
                         byte_offset, byte_length, input_tokens, output_tokens,
-                        cache_read_tokens, cache_creation_tokens, model, timestamp
-                    ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+                        cache_read_tokens, cache_creation_tokens, model, timestamp, code_language
+                    ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
                     rusqlite::params![
                         session_id, seq, msg.role, msg.content_preview, msg.search_content,
                         msg.has_code, msg.has_error, msg.has_file_changes,
                         msg.tool_name, msg.tool_type, msg.tool_summary,
                         msg.byte_offset, msg.byte_length, msg.input_tokens, msg.output_tokens,
-                        msg.cache_read_tokens, msg.cache_creation_tokens, msg.model, msg.timestamp
+                        msg.cache_read_tokens, msg.cache_creation_tokens, msg.model, msg.timestamp,
+                        msg.code_language
                     ],
                 )?;
                 inserted.push(serde_json::json!({
@@ -1965,7 +4203,7 @@ pub async fn append_session_messages(
                 )
                 .unwrap_or(0);
 
-            Ok::<_, rusqlite::Error>((inserted, total, session_id))
+            Ok::<_, rusqlite::Error>((inserted, total, session_id.clone()))
         })
         .await;
 
@@ -2035,7 +4273,7 @@ pub async fn update_agent_summary(
                         req.cache_read_tokens, req.cache_creation_tokens, req.model, id
                     ],
                 )?;
-                Ok::<_, rusqlite::Error>(("updated", id, session_id, None::<i64>))
+                Ok::<_, rusqlite::Error>(("updated", id, session_id.clone(), None::<i64>))
             } else {
                 // Insert new
                 let max_seq: i64 = conn
@@ -2061,7 +4299,7 @@ pub async fn update_agent_summary(
                     ],
                 )?;
                 let id = conn.last_insert_rowid();
-                Ok(("created", id, session_id, Some(seq)))
+                Ok(("created", id, session_id.clone(), Some(seq)))
             }
         })
         .await;
@@ -2109,29 +4347,24 @@ pub struct ListMemoriesQuery {
     pub tag: Option<String>,
     /// Comma-separated list of tags - memories must contain ALL tags (AND logic)
     pub tags: Option<String>,
+    /// Opaque keyset cursor from a previous page's `next_cursor`.
+    /// Implies newest-first `extracted_at` ordering and takes precedence over offset.
+    pub cursor: Option<String>,
 }
 
-/// Resolve a project identifier to a UUID.
-/// If the input looks like a folder-path-based ID (starts with '-' or is not a valid UUID),
-/// look it up by folder_path. Otherwise, use it directly as a UUID.
-fn resolve_project_id(conn: &rusqlite::Connection, project_id: &str) -> Option<String> {
-    // Check if it looks like a UUID (36 chars with hyphens in right places)
-    let is_uuid = project_id.len() == 36
-        && project_id.chars().nth(8) == Some('-')
-        && project_id.chars().nth(13) == Some('-');
-
-    if is_uuid {
-        // Verify it exists
-        let exists: bool = conn
-            .query_row(
-                "SELECT EXISTS(SELECT 1 FROM projects WHERE id = ?)",
-                [project_id],
-                |row| row.get(0),
-            )
-            .unwrap_or(false);
-        if exists {
-            return Some(project_id.to_string());
-        }
+/// Resolve a project identifier to a project ID.
+/// An existing project ID (UUID or ULID) is used directly; anything else is
+/// treated as a folder-path-based ID and looked up by folder_path.
+pub(super) fn resolve_project_id(conn: &rusqlite::Connection, project_id: &str) -> Option<String> {
+    let exists: bool = conn
+        .query_row(
+            "SELECT EXISTS(SELECT 1 FROM projects WHERE id = ?)",
+            [project_id],
+            |row| row.get(0),
+        )
+        .unwrap_or(false);
+    if exists {
+        return Some(project_id.to_string());
     }
 
     // Try to find by folder_path (ending with this segment)
@@ -2145,13 +4378,39 @@ fn resolve_project_id(conn: &rusqlite::Connection, project_id: &str) -> Option<S
     .ok()
 }
 
+/// Keyset cursor for a memory row as returned by `list_memories`
+fn memory_cursor(memory: &serde_json::Value) -> String {
+    Cursor::new(
+        memory["extracted_at"].as_str().unwrap_or_default(),
+        memory["id"].as_i64().unwrap_or_default().to_string(),
+    )
+    .encode()
+}
+
 pub async fn list_memories(
     State(state): State<AppState>,
     Query(query): Query<ListMemoriesQuery>,
 ) -> impl IntoResponse {
     if state.db.is_none() {
-        return Json(serde_json::json!({ "memories": [], "total": 0 })).into_response();
+        return Json(serde_json::json!({ "memories": [], "total": 0, "next_cursor": null }))
+            .into_response();
+    }
+
+    // Cursor ids are memory row ids
+    let cursor = match query.cursor.as_deref().map(|token| {
+        Cursor::decode(token).and_then(|c| c.id.parse::<i64>().ok().map(|id| (c.timestamp, id)))
+    }) {
+        Some(None) => return invalid_cursor(),
+        Some(cursor) => cursor,
+        None => None,
+    };
+    // Keyset pagination only follows the newest-first extracted_at order
+    let keyset_order = query.sort_by.as_deref().unwrap_or("extracted_at") == "extracted_at"
+        && query.sort_order.as_deref() != Some("asc");
+    if cursor.is_some() && !keyset_order {
+        return bad_request("cursor can't be combined with sort_by or sort_order".to_string());
     }
+    let keyset = cursor.is_some() || (query.sort_by.is_some() && keyset_order);
 
     let result = state
         .db
@@ -2159,25 +4418,29 @@ pub async fn list_memories(
         .unwrap()
         .with_read_conn(move |conn| {
             let limit = query.limit.unwrap_or(100);
-            let offset = query.offset.unwrap_or(0);
+            let offset = if cursor.is_some() {
+                0
+            } else {
+                query.offset.unwrap_or(0)
+            };
 
             let mut conditions = vec!["1=1".to_string()];
             let mut params: Vec<Box<dyn rusqlite::ToSql>> = vec![];
 
-            if let Some(project_id_input) = query.project_id {
+            if let Some(project_id_input) = query.project_id.clone() {
                 // Resolve folder-path-based ID to actual UUID
                 let resolved_id =
                     resolve_project_id(conn, &project_id_input).unwrap_or(project_id_input);
                 conditions.push("project_id = ?".to_string());
                 params.push(Box::new(resolved_id));
             }
-            if let Some(session_id) = query.session_id {
+            if let Some(session_id) = query.session_id.clone() {
                 conditions.push("session_id = ?".to_string());
                 params.push(Box::new(session_id));
             }
 
             // Handle multiple memory types (OR logic)
-            if let Some(memory_types) = query.memory_types {
+            if let Some(memory_types) = &query.memory_types {
                 let types: Vec<&str> = memory_types
                     .split(',')
                     .map(|s| s.trim())
@@ -2190,13 +4453,13 @@ pub async fn list_memories(
                         params.push(Box::new(t.to_string()));
                     }
                 }
-            } else if let Some(memory_type) = query.memory_type {
+            } else if let Some(memory_type) = query.memory_type.clone() {
                 // Fallback to single type (deprecated)
                 conditions.push("memory_type = ?".to_string());
                 params.push(Box::new(memory_type));
             }
 
-            if let Some(memory_state) = query.state {
+            if let Some(memory_state) = query.state.clone() {
                 conditions.push("state = ?".to_string());
                 params.push(Box::new(memory_state));
             }
@@ -2212,27 +4475,37 @@ pub async fn list_memories(
 
             // Handle multiple tags (AND logic - memory must contain ALL specified tags)
             let tags_to_filter: Option<Vec<String>> =
-                query.tags.or(query.tag.map(|t| t.to_string())).map(|t| {
+                query.tags.clone().or(query.tag.clone()).map(|t| {
                     t.split(',')
                         .map(|s| s.trim().to_lowercase())
                         .filter(|s| !s.is_empty())
                         .collect()
                 });
 
+            if let Some((timestamp, id)) = cursor.clone() {
+                conditions.push("(extracted_at, id) < (?, ?)".to_string());
+                params.push(Box::new(timestamp));
+                params.push(Box::new(id));
+            }
+
             params.push(Box::new(limit));
             params.push(Box::new(offset));
 
             // Build ORDER BY clause - whitelist allowed columns to prevent SQL injection
             let sort_column = match query.sort_by.as_deref() {
+                _ if keyset => "extracted_at",
                 Some("extracted_at") => "extracted_at",
                 Some("confidence") | None => "confidence",
                 _ => "confidence", // Default for unknown values
             };
             let sort_direction = match query.sort_order.as_deref() {
+                _ if keyset => "DESC",
                 Some("asc") => "ASC",
                 Some("desc") | None => "DESC",
                 _ => "DESC", // Default for unknown values
             };
+            // id breaks ties so keyset pages never skip or repeat rows
+            let tie_break = if keyset { ", id DESC" } else { "" };
 
             // For tag filtering, we need a larger initial fetch if filtering by tags
             // since tag matching is done in Rust (tags are stored as JSON arrays)
@@ -2253,11 +4526,12 @@ pub async fn list_memories(
                         context, tags, confidence, is_validated, state, extracted_at
                  FROM memories
                  WHERE {} AND state != 'removed'
-                 ORDER BY {} {}
+                 ORDER BY {} {}{}
                  LIMIT ? OFFSET ?",
                 conditions.join(" AND "),
                 sort_column,
-                sort_direction
+                sort_direction,
+                tie_break
             );
 
             let params_refs: Vec<&dyn rusqlite::ToSql> =
@@ -2284,6 +4558,10 @@ pub async fn list_memories(
                 .filter_map(|r| r.ok())
                 .collect();
 
+            // Last row scanned, so a tag-filtered keyset page can resume after it
+            let scanned = memories.len() as i64;
+            let last_scanned = memories.last().map(memory_cursor);
+
             // Apply tag filtering in Rust (tags are stored as JSON arrays)
             if let Some(ref filter_tags) = tags_to_filter {
                 memories.retain(|m| {
@@ -2297,6 +4575,18 @@ pub async fn list_memories(
                     filter_tags.iter().all(|ft| memory_tags_lower.contains(ft))
                 });
                 // Apply pagination after filtering
+                if keyset {
+                    let full_page = memories.len() as i64 >= limit;
+                    memories.truncate(limit as usize);
+                    let next_cursor = if full_page {
+                        memories.last().map(memory_cursor)
+                    } else if scanned == effective_limit {
+                        last_scanned
+                    } else {
+                        None
+                    };
+                    return Ok((memories, next_cursor));
+                }
                 let start = offset as usize;
                 let end = (offset + limit) as usize;
                 if start < memories.len() {
@@ -2306,12 +4596,21 @@ pub async fn list_memories(
                 }
             }
 
-            Ok::<_, rusqlite::Error>(memories)
+            let next_cursor = if keyset && tags_to_filter.is_none() && scanned == limit {
+                last_scanned
+            } else {
+                None
+            };
+            Ok::<_, rusqlite::Error>((memories, next_cursor))
         })
         .await;
 
     match result {
-        Ok(memories) => Json(serde_json::json!({ "memories": memories })).into_response(),
+        Ok((memories, next_cursor)) => Json(serde_json::json!({
+            "memories": memories,
+            "next_cursor": next_cursor,
+        }))
+        .into_response(),
         Err(e) => (
             StatusCode::INTERNAL_SERVER_ERROR,
             Json(serde_json::json!({ "error": e.to_string() })),
@@ -2378,15 +4677,16 @@ pub async fn search_memories(
                         m.file_reference, m.state
                  FROM memories_fts fts
                  JOIN memories m ON m.id = fts.rowid
-                 WHERE memories_fts MATCH ? AND m.state != 'removed'
+                 WHERE memories_fts MATCH ? AND m.state != 'removed' AND {}
                  ORDER BY bm25(memories_fts)
                  LIMIT {}",
+                crate::db::memories::LIVE_SESSION_MEMORY,
                 limit
             );
 
             let mut stmt = conn.prepare(&sql).map_err(|e| e.to_string())?;
             let memories: Vec<crate::mcp::types::Memory> = stmt
-                .query_map([&fts_query], crate::mcp::db::row_to_memory_pub)
+                .query_map([&fts_query], crate::db::memories::row_to_memory)
                 .map_err(|e| e.to_string())?
                 .filter_map(|r| r.ok())
                 .collect();
@@ -2424,6 +4724,82 @@ pub async fn search_memories(
     }
 }
 
+#[derive(Debug, Deserialize)]
+pub struct SemanticSearchRequest {
+    pub query: String,
+    pub project_id: String,
+    pub limit: Option<usize>,
+}
+
+/// Rank a project's memories by embedding similarity to the query.
+///
+/// Unlike `search_memories` there is no keyword component, so memories that
+/// match conceptually but share no words with the query are still found.
+pub async fn semantic_search_memories(
+    State(state): State<AppState>,
+    Json(req): Json<SemanticSearchRequest>,
+) -> impl IntoResponse {
+    let Some(db) = state.db.clone() else {
+        return Json(serde_json::json!({ "memories": [] })).into_response();
+    };
+    let limit = req.limit.unwrap_or(10);
+
+    let result = tokio::task::spawn_blocking(move || {
+        let mcp_db = crate::mcp::db::McpDb::new(db);
+        let project_id = {
+            #[allow(deprecated)]
+            let conn = mcp_db.db().conn();
+            resolve_project_id(&conn, &req.project_id)
+        };
+        let Some(project_id) = project_id else {
+            return Err((StatusCode::NOT_FOUND, "Project not found".to_string()));
+        };
+
+        let embedded = mcp_db
+            .count_memory_embeddings(&project_id)
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+        if embedded == 0 {
+            return Err((
+                StatusCode::CONFLICT,
+                "No memory embeddings for this project yet. They are backfilled by the \
+                 embedding_refresh scheduler task (requires ai.memory_extraction)."
+                    .to_string(),
+            ));
+        }
+
+        let query_embedding = crate::embeddings::embed_text(&req.query).map_err(|e| {
+            (
+                StatusCode::SERVICE_UNAVAILABLE,
+                format!("Embedding model unavailable: {}", e),
+            )
+        })?;
+        mcp_db
+            .rank_memories_by_embedding(&query_embedding, &project_id, None, limit)
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))
+    })
+    .await;
+
+    match result {
+        Ok(Ok(ranked)) => {
+            let memories: Vec<serde_json::Value> = ranked
+                .into_iter()
+                .map(|(memory, score)| {
+                    let mut json = memory_to_api_json(memory);
+                    json["score"] = serde_json::json!(score);
+                    json
+                })
+                .collect();
+            Json(serde_json::json!({ "memories": memories })).into_response()
+        }
+        Ok(Err((status, e))) => (status, Json(serde_json::json!({ "error": e }))).into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({ "error": e.to_string() })),
+        )
+            .into_response(),
+    }
+}
+
 /// Convert MCP Memory to API JSON format
 /// The API returns tags as a JSON string (not parsed array) for backwards compatibility
 fn memory_to_api_json(memory: crate::mcp::types::Memory) -> serde_json::Value {
@@ -2500,6 +4876,8 @@ pub async fn get_memory(State(state): State<AppState>, Path(id): Path<i64>) -> i
 
 #[derive(Debug, Deserialize)]
 pub struct UpdateMemoryRequest {
+    pub title: Option<String>,
+    pub content: Option<String>,
     pub state: Option<String>,
     pub confidence: Option<f64>,
     pub is_validated: Option<bool>,
@@ -2519,19 +4897,39 @@ pub async fn update_memory(
     }
 
     // Early return if no updates
-    if req.state.is_none() && req.confidence.is_none() && req.is_validated.is_none() {
+    if req.title.is_none()
+        && req.content.is_none()
+        && req.state.is_none()
+        && req.confidence.is_none()
+        && req.is_validated.is_none()
+    {
         return Json(serde_json::json!({ "id": id })).into_response();
     }
 
+    // Embeddings cover title + content, so either change makes the stored vector stale
+    let text_changed = req.title.is_some() || req.content.is_some();
+    let removed = req.state.as_deref() == Some("removed");
+    let restored = req.state.is_some() && !removed;
+
     let result = state
         .db
         .as_ref()
         .unwrap()
         .with_conn(move |conn| {
+            use rusqlite::OptionalExtension;
+
             let mut updates = vec![];
             let mut params: Vec<Box<dyn rusqlite::ToSql>> = vec![];
 
-            if let Some(memory_state) = req.state {
+            if let Some(title) = req.title.clone() {
+                updates.push("title = ?");
+                params.push(Box::new(title));
+            }
+            if let Some(content) = req.content.clone() {
+                updates.push("content = ?");
+                params.push(Box::new(content));
+            }
+            if let Some(memory_state) = req.state.clone() {
                 updates.push("state = ?");
                 params.push(Box::new(memory_state));
             }
@@ -2550,17 +4948,53 @@ pub async fn update_memory(
             let params_refs: Vec<&dyn rusqlite::ToSql> =
                 params.iter().map(|p| p.as_ref()).collect();
 
-            conn.execute(&query, params_refs.as_slice())
+            let updated = conn.execute(&query, params_refs.as_slice())?;
+            if updated > 0 && text_changed {
+                // Drop the stale vector now so search never ranks by old content
+                conn.execute("DELETE FROM memory_embeddings WHERE memory_id = ?", [id])?;
+            }
+            // A memory moved out of 'removed' goes back into the ANN index
+            let embedding = if updated > 0 && restored && !text_changed {
+                conn.query_row(
+                    "SELECT embedding FROM memory_embeddings WHERE memory_id = ?",
+                    [id],
+                    |row| row.get::<_, Vec<u8>>(0),
+                )
+                .optional()?
+            } else {
+                None
+            };
+            Ok::<_, rusqlite::Error>((updated, embedding))
         })
         .await;
 
     match result {
-        Ok(0) => (
+        Ok((0, _)) => (
             StatusCode::NOT_FOUND,
             Json(serde_json::json!({ "error": "Memory not found" })),
         )
             .into_response(),
-        Ok(_) => Json(serde_json::json!({ "id": id })).into_response(),
+        Ok((_, embedding)) => {
+            let db = state.db.as_ref().unwrap();
+            if removed || text_changed {
+                db.unindex_memories(&[id]);
+            }
+            if let Some(bytes) = embedding {
+                db.index_memory_embedding(id, &crate::embeddings::bytes_to_embedding(&bytes));
+            }
+            if text_changed && !removed {
+                // Re-embed in the background (non-fatal; backfill picks up any miss)
+                let db = state.db.clone().unwrap();
+                tokio::spawn(async move {
+                    if let Err(e) =
+                        reembed_memory_with(&db, id, crate::embeddings::embed_text).await
+                    {
+                        tracing::debug!("Re-embedding memory {} failed: {}", id, e);
+                    }
+                });
+            }
+            Json(serde_json::json!({ "id": id })).into_response()
+        }
         Err(e) => (
             StatusCode::INTERNAL_SERVER_ERROR,
             Json(serde_json::json!({ "error": e.to_string() })),
@@ -2569,25 +5003,111 @@ pub async fn update_memory(
     }
 }
 
-pub async fn delete_memory(
-    State(state): State<AppState>,
-    Path(id): Path<i64>,
-) -> impl IntoResponse {
-    if state.db.is_none() {
-        return (
-            StatusCode::NOT_FOUND,
-            Json(serde_json::json!({ "error": "Not found" })),
-        )
-            .into_response();
-    }
-
-    // Soft delete by setting state to 'removed'
-    let result = state
-        .db
-        .as_ref()
-        .unwrap()
+/// Embed a memory's current title and content with `embed` and store the vector.
+/// Returns the vector's dimension, or `None` if the memory doesn't exist.
+async fn reembed_memory_with<F>(
+    db: &std::sync::Arc<crate::db::Database>,
+    id: i64,
+    embed: F,
+) -> Result<Option<usize>, String>
+where
+    F: FnOnce(&str) -> Result<Vec<f32>, String> + Send + 'static,
+{
+    let text = match db
         .with_conn(move |conn| {
-            conn.execute("UPDATE memories SET state = 'removed' WHERE id = ?", [id])
+            conn.query_row(
+                "SELECT title, content FROM memories WHERE id = ?",
+                [id],
+                |row| {
+                    Ok(format!(
+                        "{}\n{}",
+                        row.get::<_, String>(0)?,
+                        row.get::<_, String>(1)?
+                    ))
+                },
+            )
+        })
+        .await
+    {
+        Ok(text) => text,
+        Err(rusqlite::Error::QueryReturnedNoRows) => return Ok(None),
+        Err(e) => return Err(e.to_string()),
+    };
+
+    let embedding = tokio::task::spawn_blocking(move || embed(&text))
+        .await
+        .map_err(|e| format!("Embedding task failed: {}", e))??;
+    let dimension = embedding.len();
+    let bytes = crate::embeddings::embedding_to_bytes(&embedding);
+
+    let live = db
+        .with_conn(move |conn| {
+            conn.execute(
+                "INSERT OR REPLACE INTO memory_embeddings (memory_id, embedding) VALUES (?, ?)",
+                rusqlite::params![id, bytes],
+            )?;
+            conn.query_row(
+                "SELECT state != 'removed' FROM memories WHERE id = ?",
+                [id],
+                |row| row.get::<_, bool>(0),
+            )
+        })
+        .await
+        .map_err(|e| e.to_string())?;
+    if live {
+        db.index_memory_embedding(id, &embedding);
+    }
+
+    Ok(Some(dimension))
+}
+
+/// Regenerate a memory's embedding from its current title and content
+pub async fn reembed_memory(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> impl IntoResponse {
+    let Some(db) = state.db.clone() else {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": "Not found" })),
+        )
+            .into_response();
+    };
+
+    match reembed_memory_with(&db, id, crate::embeddings::embed_text).await {
+        Ok(Some(dimension)) => {
+            Json(serde_json::json!({ "id": id, "dimension": dimension })).into_response()
+        }
+        Ok(None) => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": "Memory not found" })),
+        )
+            .into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({ "error": format!("Failed to embed memory: {}", e) })),
+        )
+            .into_response(),
+    }
+}
+
+pub async fn delete_memory(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> impl IntoResponse {
+    if state.db.is_none() {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": "Not found" })),
+        )
+            .into_response();
+    }
+
+    // Soft delete by setting state to 'removed'
+    let db = state.db.clone().unwrap();
+    let result = db
+        .with_conn(move |conn| {
+            conn.execute("UPDATE memories SET state = 'removed' WHERE id = ?", [id])
         })
         .await;
 
@@ -2597,7 +5117,10 @@ pub async fn delete_memory(
             Json(serde_json::json!({ "error": "Memory not found" })),
         )
             .into_response(),
-        Ok(_) => StatusCode::NO_CONTENT.into_response(),
+        Ok(_) => {
+            db.unindex_memories(&[id]);
+            StatusCode::NO_CONTENT.into_response()
+        }
         Err(e) => (
             StatusCode::INTERNAL_SERVER_ERROR,
             Json(serde_json::json!({ "error": e.to_string() })),
@@ -2606,6 +5129,118 @@ pub async fn delete_memory(
     }
 }
 
+#[derive(Debug, Deserialize)]
+pub struct MergeMemoriesRequest {
+    pub primary_id: i64,
+    pub duplicate_ids: Vec<i64>,
+}
+
+/// Merge duplicate memories into a primary one, in one transaction.
+///
+/// The primary gains the union of the duplicates' tags, the highest confidence,
+/// their access counts, and is validated if any of them was. Duplicates are
+/// soft-removed. Embeddings are the only rows that reference memories, and the
+/// primary keeps its own.
+pub async fn merge_memories(
+    State(state): State<AppState>,
+    Json(req): Json<MergeMemoriesRequest>,
+) -> impl IntoResponse {
+    let Some(db) = state.db.clone() else {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": "Not found" })),
+        )
+            .into_response();
+    };
+    let primary_id = req.primary_id;
+    let mut duplicate_ids = req.duplicate_ids;
+    duplicate_ids.sort_unstable();
+    duplicate_ids.dedup();
+    if duplicate_ids.is_empty() || duplicate_ids.contains(&primary_id) {
+        return bad_request(
+            "duplicate_ids must be non-empty and must not contain primary_id".to_string(),
+        );
+    }
+
+    let result = db
+        .with_conn(move |conn| {
+            let not_found = |id: i64| (StatusCode::NOT_FOUND, format!("Memory {} not found", id));
+            let internal = |e: rusqlite::Error| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string());
+            let tx = conn.unchecked_transaction().map_err(internal)?;
+            let load = |id: i64| {
+                tx.query_row(
+                    "SELECT project_id, tags, confidence, is_validated, access_count
+                     FROM memories WHERE id = ? AND state != 'removed'",
+                    [id],
+                    |row| {
+                        Ok((
+                            row.get::<_, String>(0)?,
+                            row.get::<_, String>(1)?,
+                            row.get::<_, f64>(2)?,
+                            row.get::<_, bool>(3)?,
+                            row.get::<_, i64>(4)?,
+                        ))
+                    },
+                )
+                .map_err(|e| match e {
+                    rusqlite::Error::QueryReturnedNoRows => not_found(id),
+                    e => internal(e),
+                })
+            };
+
+            let (project_id, tags, mut confidence, mut is_validated, mut access_count) =
+                load(primary_id)?;
+            let mut tags: Vec<String> = serde_json::from_str(&tags).unwrap_or_default();
+            for &id in &duplicate_ids {
+                let (dup_project, dup_tags, dup_confidence, dup_validated, dup_access) = load(id)?;
+                if dup_project != project_id {
+                    return Err((
+                        StatusCode::BAD_REQUEST,
+                        format!("Memory {} belongs to a different project", id),
+                    ));
+                }
+                for tag in serde_json::from_str::<Vec<String>>(&dup_tags).unwrap_or_default() {
+                    if !tags.contains(&tag) {
+                        tags.push(tag);
+                    }
+                }
+                confidence = confidence.max(dup_confidence);
+                is_validated |= dup_validated;
+                access_count += dup_access;
+                tx.execute("UPDATE memories SET state = 'removed' WHERE id = ?", [id])
+                    .map_err(internal)?;
+            }
+
+            let tags = serde_json::to_string(&tags).unwrap_or_else(|_| "[]".to_string());
+            tx.execute(
+                "UPDATE memories SET tags = ?1, confidence = ?2, is_validated = ?3, access_count = ?4
+                 WHERE id = ?5",
+                rusqlite::params![tags, confidence, is_validated, access_count, primary_id],
+            )
+            .map_err(internal)?;
+            tx.commit().map_err(internal)?;
+
+            Ok((
+                serde_json::json!({
+                    "id": primary_id,
+                    "merged": duplicate_ids.len(),
+                    "tags": tags,
+                    "confidence": confidence,
+                }),
+                duplicate_ids.clone(),
+            ))
+        })
+        .await;
+
+    match result {
+        Ok((merged, duplicate_ids)) => {
+            db.unindex_memories(&duplicate_ids);
+            Json(merged).into_response()
+        }
+        Err((status, e)) => (status, Json(serde_json::json!({ "error": e }))).into_response(),
+    }
+}
+
 /// Memory type count for statistics
 #[derive(Debug, serde::Serialize)]
 pub struct MemoryTypeCount {
@@ -2770,7 +5405,7 @@ pub async fn get_memory_tags(
 
             // Sort by count descending, take top 25
             let mut sorted: Vec<(String, usize)> = tag_counts.into_iter().collect();
-            sorted.sort_by(|a, b| b.1.cmp(&a.1));
+            sorted.sort_by_key(|b| std::cmp::Reverse(b.1));
             let tags: Vec<String> = sorted.into_iter().take(25).map(|(tag, _)| tag).collect();
 
             Ok::<_, rusqlite::Error>(tags)
@@ -2787,27 +5422,168 @@ pub async fn get_memory_tags(
     }
 }
 
+#[derive(Debug, Deserialize)]
+pub struct ExportMemoriesQuery {
+    /// "claude_md" (default)
+    pub format: Option<String>,
+}
+
+/// A memory as rendered into an exported context file
+struct ExportMemory {
+    memory_type: String,
+    title: String,
+    content: String,
+    tags: Vec<String>,
+}
+
+/// Section headings for each memory type, in render order
+const MEMORY_EXPORT_SECTIONS: &[(&str, &str)] = &[
+    ("decision", "Decisions"),
+    ("fact", "Facts"),
+    ("preference", "Preferences"),
+    ("context", "Context"),
+    ("task", "Tasks"),
+];
+
+/// Render memories as a CLAUDE.md document: one section per memory type,
+/// memories kept in the order given, tags appended inline
+fn render_memories_claude_md(project_name: &str, memories: &[ExportMemory]) -> String {
+    let mut out = format!(
+        "# {}\n\nProject knowledge exported from validated, high-confidence memories.\n",
+        project_name
+    );
+
+    for (memory_type, heading) in MEMORY_EXPORT_SECTIONS {
+        let mut section = memories
+            .iter()
+            .filter(|m| m.memory_type == *memory_type)
+            .peekable();
+        if section.peek().is_none() {
+            continue;
+        }
+        out.push_str(&format!("\n## {}\n\n", heading));
+        for m in section {
+            // Keep each memory a single list item
+            let content = m.content.split_whitespace().collect::<Vec<_>>().join(" ");
+            out.push_str(&format!("- **{}**: {}", m.title.trim(), content));
+            if !m.tags.is_empty() {
+                let tags: Vec<String> = m.tags.iter().map(|t| format!("`{}`", t)).collect();
+                out.push_str(&format!(" (tags: {})", tags.join(", ")));
+            }
+            out.push('\n');
+        }
+    }
+
+    out
+}
+
+/// Export a project's high-state, validated memories as a context file
+pub async fn export_project_memories(
+    State(state): State<AppState>,
+    Path(project_id): Path<String>,
+    Query(query): Query<ExportMemoriesQuery>,
+) -> axum::response::Response {
+    let format = query.format.as_deref().unwrap_or("claude_md");
+    if format != "claude_md" {
+        return bad_request(format!("Unknown export format: {}", format));
+    }
+    let Some(db) = state.db.clone() else {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": "Not found" })),
+        )
+            .into_response();
+    };
+
+    let result = db
+        .with_read_conn(move |conn| {
+            let Some(project_id) = resolve_project_id(conn, &project_id) else {
+                return Ok(None);
+            };
+            let name: String = conn.query_row(
+                "SELECT name FROM projects WHERE id = ?",
+                [&project_id],
+                |row| row.get(0),
+            )?;
+
+            let mut stmt = conn.prepare(
+                "SELECT memory_type, title, content, tags FROM memories
+                 WHERE project_id = ? AND state = 'high' AND is_validated = 1
+                 ORDER BY confidence DESC, extracted_at DESC",
+            )?;
+            let memories = stmt
+                .query_map([&project_id], |row| {
+                    let tags: Option<String> = row.get(3)?;
+                    Ok(ExportMemory {
+                        memory_type: row.get(0)?,
+                        title: row.get(1)?,
+                        content: row.get(2)?,
+                        tags: tags
+                            .and_then(|t| serde_json::from_str(&t).ok())
+                            .unwrap_or_default(),
+                    })
+                })?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            Ok::<_, rusqlite::Error>(Some(render_memories_claude_md(&name, &memories)))
+        })
+        .await;
+
+    match result {
+        Ok(Some(markdown)) => (
+            [
+                (
+                    axum::http::header::CONTENT_TYPE,
+                    "text/markdown; charset=utf-8".to_string(),
+                ),
+                (
+                    axum::http::header::CONTENT_DISPOSITION,
+                    "attachment; filename=\"CLAUDE.md\"".to_string(),
+                ),
+            ],
+            markdown,
+        )
+            .into_response(),
+        Ok(None) => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": "Project not found" })),
+        )
+            .into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({ "error": e.to_string() })),
+        )
+            .into_response(),
+    }
+}
+
 // ============================================================================
 // AI Features
 // ============================================================================
 
-use crate::ai::cli::CliProvider;
+use crate::ai::auto_trigger::ProjectAiSettings;
+use crate::ai::cli::{provider_name, with_fallback, CliProvider};
 use crate::ai::title::{generate_title, generate_title_from_text, store_title};
 use crate::ai::types::AiEvent;
+use crate::ai::AiTaskKind;
 use crate::config::Config;
 
-use crate::config::AiFeature;
+use crate::config::{AiConfig, AiFeature};
 
 /// Resolve the configured CLI provider from config
 fn resolve_provider(state: &AppState) -> CliProvider {
-    Config::from_file(&state.config_path)
-        .ok()
-        .and_then(|c| {
-            c.ai.provider
-                .as_deref()
-                .and_then(CliProvider::from_config_str)
-        })
-        .unwrap_or(CliProvider::ClaudeCode)
+    let config = Config::from_file(&state.config_path).unwrap_or_default();
+    CliProvider::from_ai_config(&config.ai)
+}
+
+/// Resolve the configured provider fallback chain from config
+fn resolve_providers(state: &AppState) -> Vec<CliProvider> {
+    let config = Config::from_file(&state.config_path).unwrap_or_default();
+    CliProvider::chain_from_ai_config(&config.ai)
+}
+
+/// Read the `[ai]` section (provider settings included) from config.toml
+fn resolve_ai_config(state: &AppState) -> AiConfig {
+    Config::from_file(&state.config_path).unwrap_or_default().ai
 }
 
 /// Check if a specific AI feature is active in config.toml
@@ -2837,7 +5613,7 @@ fn check_ai_feature(
 /// Get AI CLI detection status
 pub async fn get_ai_cli_status(State(state): State<AppState>) -> impl IntoResponse {
     let provider = resolve_provider(&state);
-    let detected = crate::ai::cli::detect_provider(provider).await;
+    let detected = crate::ai::cli::detect_provider(provider, &resolve_ai_config(&state)).await;
     Json(serde_json::json!({
         "provider": detected.provider.display_name(),
         "provider_id": detected.provider.command_name(),
@@ -2865,11 +5641,15 @@ pub async fn get_pending_ai_sessions(State(state): State<AppState>) -> impl Into
         return Json(serde_json::json!({ "sessions": [] })).into_response();
     }
 
+    let min_messages = Config::from_file(&state.config_path)
+        .unwrap_or_default()
+        .ai
+        .min_messages_for_processing as i64;
     let result = state
         .db
         .as_ref()
         .unwrap()
-        .with_read_conn(|conn| {
+        .with_read_conn(move |conn| {
             let mut stmt = conn.prepare(
                 "SELECT
                     s.id as session_id,
@@ -2881,7 +5661,8 @@ pub async fn get_pending_ai_sessions(State(state): State<AppState>) -> impl Into
                 FROM sessions s
                 INNER JOIN projects p ON s.project_id = p.id
                 WHERE COALESCE(s.import_status, 'success') = 'success'
-                  AND s.message_count >= 25
+                  AND s.message_count >= ?
+                  AND s.deleted_at IS NULL
                   AND (
                     (COALESCE(s.title_ai_generated, 0) = 0 AND COALESCE(s.title_edited, 0) = 0)
                     OR s.memories_extracted_at IS NULL
@@ -2892,7 +5673,7 @@ pub async fn get_pending_ai_sessions(State(state): State<AppState>) -> impl Into
             )?;
 
             let sessions: Vec<PendingAiSession> = stmt
-                .query_map([], |row| {
+                .query_map([min_messages], |row| {
                     Ok(PendingAiSession {
                         session_id: row.get(0)?,
                         project_id: row.get(1)?,
@@ -2970,12 +5751,6 @@ pub async fn get_session_limit_info(State(state): State<AppState>) -> impl IntoR
     .into_response()
 }
 
-#[derive(Debug, Deserialize)]
-pub struct TitleGenerationRequest {
-    #[serde(default)]
-    pub force: bool,
-}
-
 /// Trigger title generation for a session (async, returns immediately)
 pub async fn trigger_title_generation(
     State(state): State<AppState>,
@@ -3026,7 +5801,9 @@ pub async fn trigger_title_generation(
         let idx = idx.clone();
         let ai_event_tx = state.ai_event_tx.clone();
         let sid = session_id.clone();
-        let provider = resolve_provider(&state);
+        let providers = resolve_providers(&state);
+        let ai = resolve_ai_config(&state);
+        let task = state.ai_task_queue.track(&session_id, AiTaskKind::Title);
 
         tokio::spawn(async move {
             let _permit = permit;
@@ -3034,7 +5811,15 @@ pub async fn trigger_title_generation(
                 session_id: sid.clone(),
             });
 
-            let result = generate_title_from_text(&sid, &first_messages, None, provider).await;
+            let Some(result) = task
+                .run(with_fallback("Title generation", &providers, |provider| {
+                    generate_title_from_text(&sid, &first_messages, None, provider, &ai)
+                }))
+                .await
+            else {
+                let _ = ai_event_tx.send(AiEvent::TitleCancelled { session_id: sid });
+                return;
+            };
 
             if let Some(ref title) = result.title {
                 idx.update_session(&sid, Some(title.clone()), None);
@@ -3042,6 +5827,7 @@ pub async fn trigger_title_generation(
                 let _ = ai_event_tx.send(AiEvent::TitleComplete {
                     session_id: sid,
                     title: title.clone(),
+                    provider: provider_name(result.provider),
                 });
             } else if let Some(error) = result.error {
                 let _ = ai_event_tx.send(AiEvent::TitleError {
@@ -3133,7 +5919,9 @@ pub async fn trigger_title_generation(
     let db = state.db.clone().unwrap();
     let ai_event_tx = state.ai_event_tx.clone();
     let session_id_for_task = session_id.clone();
-    let provider = resolve_provider(&state);
+    let providers = resolve_providers(&state);
+    let ai = resolve_ai_config(&state);
+    let task = state.ai_task_queue.track(&session_id, AiTaskKind::Title);
 
     // Spawn background task for title generation
     tokio::spawn(async move {
@@ -3145,8 +5933,18 @@ pub async fn trigger_title_generation(
             session_id: session_id_for_task.clone(),
         });
 
-        // Generate title
-        let result = generate_title(&db, &session_id_for_task, None, provider).await;
+        // Generate title (stops early if cancelled)
+        let Some(result) = task
+            .run(with_fallback("Title generation", &providers, |provider| {
+                generate_title(&db, &session_id_for_task, None, provider, &ai)
+            }))
+            .await
+        else {
+            let _ = ai_event_tx.send(AiEvent::TitleCancelled {
+                session_id: session_id_for_task,
+            });
+            return;
+        };
 
         // Store result and emit event
         if let Some(ref title) = result.title {
@@ -3161,6 +5959,7 @@ pub async fn trigger_title_generation(
             let _ = ai_event_tx.send(AiEvent::TitleComplete {
                 session_id: session_id_for_task,
                 title: title.clone(),
+                provider: provider_name(result.provider),
             });
         } else if let Some(error) = result.error {
             let _ = ai_event_tx.send(AiEvent::TitleError {
@@ -3249,7 +6048,9 @@ pub async fn trigger_memory_extraction(
     let db = state.db.clone().unwrap();
     let ai_event_tx = state.ai_event_tx.clone();
     let session_id_for_task = session_id.clone();
-    let provider = resolve_provider(&state);
+    let providers = resolve_providers(&state);
+    let ai = resolve_ai_config(&state);
+    let task = state.ai_task_queue.track(&session_id, AiTaskKind::Memory);
 
     // Spawn background task for memory extraction
     tokio::spawn(async move {
@@ -3262,8 +6063,25 @@ pub async fn trigger_memory_extraction(
         });
 
         // Extract memories (skip if already extracted unless force=true)
-        let result =
-            crate::ai::extract_memories(&db, &session_id_for_task, None, force, provider).await;
+        let Some(result) = task
+            .run(with_fallback("Memory extraction", &providers, |provider| {
+                crate::ai::extract_memories(
+                    &db,
+                    &session_id_for_task,
+                    None,
+                    force,
+                    provider,
+                    &ai,
+                    ai.lenient_json,
+                )
+            }))
+            .await
+        else {
+            let _ = ai_event_tx.send(AiEvent::MemoryCancelled {
+                session_id: session_id_for_task,
+            });
+            return;
+        };
 
         // Emit completion or error event
         if let Some(error) = result.error {
@@ -3275,6 +6093,7 @@ pub async fn trigger_memory_extraction(
             let _ = ai_event_tx.send(AiEvent::MemoryComplete {
                 session_id: session_id_for_task,
                 count: result.memories_extracted,
+                provider: provider_name(result.provider),
             });
         }
     });
@@ -3358,7 +6177,9 @@ pub async fn trigger_skill_extraction(
     let db = state.db.clone().unwrap();
     let ai_event_tx = state.ai_event_tx.clone();
     let session_id_for_task = session_id.clone();
-    let provider = resolve_provider(&state);
+    let providers = resolve_providers(&state);
+    let ai = resolve_ai_config(&state);
+    let task = state.ai_task_queue.track(&session_id, AiTaskKind::Skill);
 
     // Spawn background task for skill extraction
     tokio::spawn(async move {
@@ -3371,8 +6192,25 @@ pub async fn trigger_skill_extraction(
         });
 
         // Extract skills (skip if already extracted unless force=true)
-        let result =
-            crate::ai::extract_skills(&db, &session_id_for_task, None, force, provider).await;
+        let Some(result) = task
+            .run(with_fallback("Skill extraction", &providers, |provider| {
+                crate::ai::extract_skills(
+                    &db,
+                    &session_id_for_task,
+                    None,
+                    force,
+                    provider,
+                    &ai,
+                    ai.lenient_json,
+                )
+            }))
+            .await
+        else {
+            let _ = ai_event_tx.send(AiEvent::SkillCancelled {
+                session_id: session_id_for_task,
+            });
+            return;
+        };
 
         // Emit completion or error event
         if let Some(error) = result.error {
@@ -3384,6 +6222,7 @@ pub async fn trigger_skill_extraction(
             let _ = ai_event_tx.send(AiEvent::SkillComplete {
                 session_id: session_id_for_task,
                 count: result.skills_extracted,
+                provider: provider_name(result.provider),
             });
         }
     });
@@ -3465,13 +6304,31 @@ pub async fn delete_marker(
     }
 }
 
-/// Trigger marker detection for a session (async, returns immediately)
+/// Trigger marker detection for a session (async, returns immediately).
+/// Without an installed AI provider, markers are detected by pattern instead.
 pub async fn trigger_marker_detection(
     State(state): State<AppState>,
     Path(session_id): Path<String>,
 ) -> impl IntoResponse {
-    if let Err(resp) = check_ai_feature(&state, AiFeature::MarkerDetection) {
-        return resp.into_response();
+    let config = match Config::from_file(&state.config_path) {
+        Ok(c) => c,
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": e.to_string() })),
+            )
+                .into_response()
+        }
+    };
+    // The pattern fallback needs no provider, only the toggle and db storage
+    if !config.ai.marker_detection || !config.storage.is_db() {
+        return (
+            StatusCode::FORBIDDEN,
+            Json(serde_json::json!({
+                "error": format!("AI feature '{:?}' is not active", AiFeature::MarkerDetection)
+            })),
+        )
+            .into_response();
     }
 
     // Verify session exists
@@ -3523,7 +6380,13 @@ pub async fn trigger_marker_detection(
     let db = state.db.clone().unwrap();
     let ai_event_tx = state.ai_event_tx.clone();
     let session_id_for_task = session_id.clone();
-    let provider = resolve_provider(&state);
+    let provider = config
+        .ai
+        .provider
+        .is_some()
+        .then(|| CliProvider::from_ai_config(&config.ai));
+    let ai = config.ai;
+    let task = state.ai_task_queue.track(&session_id, AiTaskKind::Marker);
 
     // Spawn background task for marker detection
     tokio::spawn(async move {
@@ -3536,10 +6399,31 @@ pub async fn trigger_marker_detection(
         });
 
         // Detect CLI
-        let cli = crate::ai::cli::detect_cli_sync(provider);
-
-        // Run marker detection
-        let result = crate::ai::detect_markers(&db, &session_id_for_task, cli, provider).await;
+        let cli = provider.and_then(|provider| crate::ai::cli::detect_cli_sync(provider, &ai));
+
+        // Run marker detection, by pattern when no provider is available
+        let detection = async {
+            match (cli, provider) {
+                (Some(cli), Some(provider)) => {
+                    crate::ai::detect_markers(&db, &session_id_for_task, Some(cli), provider, &ai)
+                        .await
+                }
+                _ => {
+                    crate::ai::marker::detect_markers_heuristic(
+                        &db,
+                        &session_id_for_task,
+                        &ai.marker_patterns,
+                    )
+                    .await
+                }
+            }
+        };
+        let Some(result) = task.run(detection).await else {
+            let _ = ai_event_tx.send(AiEvent::MarkerCancelled {
+                session_id: session_id_for_task,
+            });
+            return;
+        };
 
         // Emit completion event
         let _ = ai_event_tx.send(AiEvent::MarkerComplete {
@@ -3560,51 +6444,249 @@ pub async fn trigger_marker_detection(
         .into_response()
 }
 
+/// Cancel running AI tasks (title, memories, skills, markers) for a session.
+/// Each cancelled task emits its `ai:*:cancelled` event and releases its queue slot.
+pub async fn cancel_session_ai_tasks(
+    State(state): State<AppState>,
+    Path(session_id): Path<String>,
+) -> impl IntoResponse {
+    let cancelled = state.ai_task_queue.cancel_session(&session_id);
+    Json(serde_json::json!({
+        "session_id": session_id,
+        "cancelled": cancelled,
+    }))
+}
+
 // ============================================================================
-// Memory Ranking
+// Session Intents
 // ============================================================================
 
-#[derive(Debug, Deserialize)]
-pub struct RankMemoriesQuery {
-    pub batch_size: Option<usize>,
-}
-
-/// Trigger memory ranking for a project
-pub async fn rank_project_memories(
+/// List the requests extracted from a session's early user messages
+pub async fn get_session_intents(
     State(state): State<AppState>,
-    Path(project_id): Path<String>,
-    Query(query): Query<RankMemoriesQuery>,
+    Path(session_id): Path<String>,
 ) -> impl IntoResponse {
     if state.db.is_none() {
-        return Json(serde_json::json!({ "promoted": 0, "demoted": 0, "removed": 0 }))
-            .into_response();
+        return Json(serde_json::json!({ "intents": [] })).into_response();
     }
 
-    let batch_size = query.batch_size.unwrap_or(500);
-    let project_id_clone = project_id.clone();
-
-    // Verify project exists
-    let exists = state
+    let result = state
         .db
         .as_ref()
         .unwrap()
-        .with_read_conn(move |conn| {
-            conn.query_row(
-                "SELECT 1 FROM projects WHERE id = ?",
-                [&project_id_clone],
-                |_| Ok(true),
-            )
-            .unwrap_or(false)
-        })
+        .with_read_conn(move |conn| crate::ai::intent::get_intents(conn, &session_id))
         .await;
 
-    if !exists {
-        return (
-            StatusCode::NOT_FOUND,
-            Json(serde_json::json!({
-                "error": "Project not found"
-            })),
-        )
+    match result {
+        Ok(intents) => Json(serde_json::json!({ "intents": intents })).into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({ "error": e.to_string() })),
+        )
+            .into_response(),
+    }
+}
+
+/// Re-extract a session's intents, with AI when `ai.intent_extraction` is active
+/// and the heuristic otherwise. Replaces the stored intents.
+pub async fn extract_session_intents(
+    State(state): State<AppState>,
+    Path(session_id): Path<String>,
+) -> impl IntoResponse {
+    let Some(db) = state.db.clone() else {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": "Intent extraction requires storage = \"db\"" })),
+        )
+            .into_response();
+    };
+
+    let config = Config::from_file(&state.config_path).unwrap_or_default();
+    let result = if config.is_feature_active(AiFeature::IntentExtraction) {
+        let _permit = match state.ai_task_queue.acquire().await {
+            Ok(p) => p,
+            Err(e) => {
+                return (
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    Json(serde_json::json!({ "error": e })),
+                )
+                    .into_response()
+            }
+        };
+        crate::ai::intent::extract_intents(
+            &db,
+            &session_id,
+            None,
+            CliProvider::from_ai_config(&config.ai),
+            &config.ai,
+            config.ai.lenient_json,
+        )
+        .await
+    } else {
+        crate::ai::intent::extract_heuristic_intents(&db, &session_id).await
+    };
+
+    if result.intents.is_empty() {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({
+                "error": result.error.unwrap_or_else(|| "No intents found".to_string())
+            })),
+        )
+            .into_response();
+    }
+    if let Err(e) = crate::ai::intent::store_intents(&db, &result).await {
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({ "error": e })),
+        )
+            .into_response();
+    }
+    Json(result).into_response()
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SearchIntentsQuery {
+    pub q: String,
+    pub project_id: Option<String>,
+    pub limit: Option<i64>,
+}
+
+/// Find sessions by what the user asked for. Every word of `q` must appear in the intent.
+pub async fn search_intents(
+    State(state): State<AppState>,
+    Query(query): Query<SearchIntentsQuery>,
+) -> impl IntoResponse {
+    if query.q.trim().is_empty() {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": "q must not be empty" })),
+        )
+            .into_response();
+    }
+    if state.db.is_none() {
+        return Json(serde_json::json!({ "results": [] })).into_response();
+    }
+
+    let limit = query.limit.unwrap_or(20);
+    let result = state
+        .db
+        .as_ref()
+        .unwrap()
+        .with_read_conn(move |conn| {
+            let mut conditions = vec!["s.is_hidden = 0 AND s.deleted_at IS NULL".to_string()];
+            let mut params: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
+            for word in query.q.split_whitespace() {
+                conditions.push("i.intent LIKE ? ESCAPE '\\'".to_string());
+                let escaped = word
+                    .replace('\\', "\\\\")
+                    .replace('%', "\\%")
+                    .replace('_', "\\_");
+                params.push(Box::new(format!("%{}%", escaped)));
+            }
+            if let Some(pid) = &query.project_id {
+                let Some(pid) = resolve_project_id(conn, pid) else {
+                    return Ok(None);
+                };
+                conditions.push("s.project_id = ?".to_string());
+                params.push(Box::new(pid));
+            }
+            params.push(Box::new(limit));
+
+            let sql = format!(
+                "SELECT i.session_id, i.sequence_num, i.intent, s.title, s.project_id, s.created_at
+                 FROM session_intents i
+                 JOIN sessions s ON s.id = i.session_id
+                 WHERE {}
+                 ORDER BY s.created_at DESC, i.sequence_num
+                 LIMIT ?",
+                conditions.join(" AND ")
+            );
+            let params_refs: Vec<&dyn rusqlite::ToSql> =
+                params.iter().map(|p| p.as_ref()).collect();
+            let mut stmt = conn.prepare(&sql)?;
+            let results: Vec<serde_json::Value> = stmt
+                .query_map(params_refs.as_slice(), |row| {
+                    Ok(serde_json::json!({
+                        "session_id": row.get::<_, String>(0)?,
+                        "sequence_num": row.get::<_, Option<i64>>(1)?,
+                        "intent": row.get::<_, String>(2)?,
+                        "title": row.get::<_, Option<String>>(3)?,
+                        "project_id": row.get::<_, String>(4)?,
+                        "created_at": row.get::<_, String>(5)?,
+                    }))
+                })?
+                .filter_map(|r| r.ok())
+                .collect();
+            Ok::<_, rusqlite::Error>(Some(results))
+        })
+        .await;
+
+    match result {
+        Ok(Some(results)) => Json(serde_json::json!({ "results": results })).into_response(),
+        Ok(None) => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": "Project not found" })),
+        )
+            .into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({ "error": e.to_string() })),
+        )
+            .into_response(),
+    }
+}
+
+// ============================================================================
+// Memory Ranking
+// ============================================================================
+
+#[derive(Debug, Deserialize)]
+pub struct RankMemoriesQuery {
+    pub batch_size: Option<usize>,
+}
+
+/// Trigger memory ranking for a project
+pub async fn rank_project_memories(
+    State(state): State<AppState>,
+    Path(project_id): Path<String>,
+    Query(query): Query<RankMemoriesQuery>,
+) -> impl IntoResponse {
+    if state.db.is_none() {
+        return Json(serde_json::json!({ "promoted": 0, "demoted": 0, "removed": 0 }))
+            .into_response();
+    }
+
+    let ranking = Config::from_file(&state.config_path)
+        .unwrap_or_default()
+        .scheduler
+        .ranking;
+    let batch_size = query.batch_size.unwrap_or(ranking.batch_size);
+    let thresholds = crate::ai::RankingConfig::from(&ranking);
+    let project_id_clone = project_id.clone();
+
+    // Verify project exists
+    let exists = state
+        .db
+        .as_ref()
+        .unwrap()
+        .with_read_conn(move |conn| {
+            conn.query_row(
+                "SELECT 1 FROM projects WHERE id = ?",
+                [&project_id_clone],
+                |_| Ok(true),
+            )
+            .unwrap_or(false)
+        })
+        .await;
+
+    if !exists {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({
+                "error": "Project not found"
+            })),
+        )
             .into_response();
     }
 
@@ -3613,7 +6695,12 @@ pub async fn rank_project_memories(
     let project_id_for_ranking = project_id.clone();
 
     let result = tokio::task::spawn_blocking(move || {
-        crate::ai::ranking::rank_project_memories(&db, &project_id_for_ranking, batch_size)
+        crate::ai::ranking::rank_project_memories(
+            &db,
+            &project_id_for_ranking,
+            batch_size,
+            &thresholds,
+        )
     })
     .await;
 
@@ -3982,13 +7069,28 @@ pub async fn get_skill_stats(
     }
 }
 
+#[derive(Debug, Default, Deserialize)]
+pub struct BackfillEmbeddingsRequest {
+    /// Memories embedded per model call (default 32)
+    pub batch_size: Option<usize>,
+}
+
+const DEFAULT_BACKFILL_BATCH_SIZE: usize = 32;
+
 /// Backfill embeddings for memories that don't have them yet
-pub async fn backfill_embeddings(State(state): State<AppState>) -> impl IntoResponse {
+pub async fn backfill_embeddings(
+    State(state): State<AppState>,
+    body: Option<Json<BackfillEmbeddingsRequest>>,
+) -> impl IntoResponse {
     if state.db.is_none() {
         return Json(serde_json::json!({ "processed": 0 })).into_response();
     }
 
     let db = state.db.clone().unwrap();
+    let batch_size = body
+        .and_then(|Json(req)| req.batch_size)
+        .unwrap_or(DEFAULT_BACKFILL_BATCH_SIZE)
+        .max(1);
 
     // Get memories without embeddings
     let memories_to_embed: Vec<(i64, String, String)> = match db
@@ -4025,47 +7127,161 @@ pub async fn backfill_embeddings(State(state): State<AppState>) -> impl IntoResp
         .into_response();
     }
 
-    // Generate embeddings in a blocking task
-    let db_clone = db.clone();
-    let result = tokio::task::spawn_blocking(move || {
+    let result = backfill_embeddings_with(
+        db,
+        memories_to_embed,
+        batch_size,
+        state.ai_event_tx.clone(),
+        crate::embeddings::embed_texts,
+    )
+    .await;
+
+    match result {
+        Ok((success, failed)) => Json(serde_json::json!({
+            "message": format!("Backfill complete: {} embedded, {} failed out of {} total", success, failed, total),
+            "backfilled": success,
+            "failed": failed,
+            "total": total
+        }))
+        .into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({ "error": format!("Backfill task failed: {}", e) })),
+        )
+            .into_response(),
+    }
+}
+
+/// Embed `(id, title, content)` memories `batch_size` at a time with `embed` and
+/// store the vectors, sending `EmbeddingProgress` after each batch and
+/// `EmbeddingComplete` at the end. Returns (embedded, failed).
+async fn backfill_embeddings_with<F>(
+    db: std::sync::Arc<crate::db::Database>,
+    memories: Vec<(i64, String, String)>,
+    batch_size: usize,
+    ai_event_tx: tokio::sync::broadcast::Sender<AiEvent>,
+    embed: F,
+) -> Result<(usize, usize), tokio::task::JoinError>
+where
+    F: Fn(&[&str]) -> Result<Vec<Vec<f32>>, String> + Send + 'static,
+{
+    tokio::task::spawn_blocking(move || {
+        let total = memories.len();
         let mut success = 0usize;
         let mut failed = 0usize;
 
-        for (memory_id, title, content) in &memories_to_embed {
-            let text = format!("{}\n{}", title, content);
-            match crate::embeddings::embed_text(&text) {
-                Ok(embedding) => {
-                    let bytes = crate::embeddings::embedding_to_bytes(&embedding);
-                    let db_inner = db_clone.clone();
-                    let mid = *memory_id;
+        for batch in memories.chunks(batch_size) {
+            let texts: Vec<String> = batch
+                .iter()
+                .map(|(_, title, content)| format!("{}\n{}", title, content))
+                .collect();
+            let texts: Vec<&str> = texts.iter().map(|t| t.as_str()).collect();
+            match embed(&texts) {
+                Ok(embeddings) => {
                     // Use blocking conn since we're already in spawn_blocking
                     #[allow(deprecated)]
-                    let conn = db_inner.conn();
-                    match conn.execute(
-                        "INSERT OR REPLACE INTO memory_embeddings (memory_id, embedding) VALUES (?, ?)",
-                        rusqlite::params![mid, bytes],
-                    ) {
-                        Ok(_) => success += 1,
-                        Err(e) => {
-                            tracing::warn!("Failed to store embedding for memory {}: {}", mid, e);
-                            failed += 1;
+                    let conn = db.conn();
+                    for ((memory_id, _, _), embedding) in batch.iter().zip(&embeddings) {
+                        let bytes = crate::embeddings::embedding_to_bytes(embedding);
+                        match conn.execute(
+                            "INSERT OR REPLACE INTO memory_embeddings (memory_id, embedding) VALUES (?, ?)",
+                            rusqlite::params![memory_id, bytes],
+                        ) {
+                            Ok(_) => {
+                                db.index_memory_embedding(*memory_id, embedding);
+                                success += 1;
+                            }
+                            Err(e) => {
+                                tracing::warn!(
+                                    "Failed to store embedding for memory {}: {}",
+                                    memory_id,
+                                    e
+                                );
+                                failed += 1;
+                            }
                         }
                     }
+                    failed += batch.len().saturating_sub(embeddings.len());
                 }
                 Err(e) => {
-                    tracing::warn!("Failed to embed memory {}: {}", memory_id, e);
-                    failed += 1;
+                    tracing::warn!("Failed to embed batch of {} memories: {}", batch.len(), e);
+                    failed += batch.len();
                 }
             }
+            let _ = ai_event_tx.send(AiEvent::EmbeddingProgress {
+                done: success + failed,
+                total,
+            });
         }
 
+        let _ = ai_event_tx.send(AiEvent::EmbeddingComplete {
+            embedded: success,
+            failed,
+            total,
+        });
         (success, failed)
     })
-    .await;
+    .await
+}
 
-    match result {
+/// Embed a session's messages that don't have embeddings yet, for
+/// `semantic_search_session`
+pub async fn backfill_session_embeddings(
+    State(state): State<AppState>,
+    Path(session_id): Path<String>,
+    body: Option<Json<BackfillEmbeddingsRequest>>,
+) -> impl IntoResponse {
+    let Some(db) = state.db.clone() else {
+        return (
+            StatusCode::NOT_IMPLEMENTED,
+            Json(serde_json::json!({ "error": "Not available in ephemeral mode" })),
+        )
+            .into_response();
+    };
+    let batch_size = body
+        .and_then(|Json(req)| req.batch_size)
+        .unwrap_or(DEFAULT_BACKFILL_BATCH_SIZE)
+        .max(1);
+
+    let sid = session_id.clone();
+    let messages: Vec<(i64, String)> = match db
+        .with_read_conn(move |conn| {
+            conn.query_row("SELECT 1 FROM sessions WHERE id = ?", [&sid], |_| Ok(()))?;
+            let mut stmt = conn.prepare(
+                "SELECT m.id, m.search_content FROM session_messages m
+                 LEFT JOIN message_embeddings e ON e.message_id = m.id
+                 WHERE m.session_id = ? AND e.message_id IS NULL
+                   AND m.search_content IS NOT NULL AND m.search_content <> ''
+                 ORDER BY m.sequence_num",
+            )?;
+            let rows = stmt.query_map([&sid], |row| Ok((row.get(0)?, row.get(1)?)))?;
+            rows.collect::<rusqlite::Result<_>>()
+        })
+        .await
+    {
+        Ok(messages) => messages,
+        Err(rusqlite::Error::QueryReturnedNoRows) => {
+            return (
+                StatusCode::NOT_FOUND,
+                Json(serde_json::json!({ "error": "Session not found" })),
+            )
+                .into_response()
+        }
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": format!("Failed to query: {}", e) })),
+            )
+                .into_response()
+        }
+    };
+
+    let total = messages.len();
+    match embed_session_messages_with(db, messages, batch_size, crate::embeddings::embed_texts)
+        .await
+    {
         Ok((success, failed)) => Json(serde_json::json!({
-            "message": format!("Backfill complete: {} embedded, {} failed out of {} total", success, failed, total),
+            "session_id": session_id,
             "backfilled": success,
             "failed": failed,
             "total": total
@@ -4079,6 +7295,58 @@ pub async fn backfill_embeddings(State(state): State<AppState>) -> impl IntoResp
     }
 }
 
+/// Embed `(message id, search content)` pairs `batch_size` at a time with
+/// `embed` and store the vectors. Returns (embedded, failed).
+async fn embed_session_messages_with<F>(
+    db: std::sync::Arc<crate::db::Database>,
+    messages: Vec<(i64, String)>,
+    batch_size: usize,
+    embed: F,
+) -> Result<(usize, usize), tokio::task::JoinError>
+where
+    F: Fn(&[&str]) -> Result<Vec<Vec<f32>>, String> + Send + 'static,
+{
+    tokio::task::spawn_blocking(move || {
+        let mut success = 0usize;
+        let mut failed = 0usize;
+
+        for batch in messages.chunks(batch_size) {
+            let texts: Vec<&str> = batch.iter().map(|(_, text)| text.as_str()).collect();
+            match embed(&texts) {
+                Ok(embeddings) => {
+                    // Use blocking conn since we're already in spawn_blocking
+                    #[allow(deprecated)]
+                    let conn = db.conn();
+                    for ((message_id, _), embedding) in batch.iter().zip(&embeddings) {
+                        let bytes = crate::embeddings::embedding_to_bytes(embedding);
+                        match conn.execute(
+                            "INSERT OR REPLACE INTO message_embeddings (message_id, embedding) VALUES (?, ?)",
+                            rusqlite::params![message_id, bytes],
+                        ) {
+                            Ok(_) => success += 1,
+                            Err(e) => {
+                                tracing::warn!(
+                                    "Failed to store embedding for message {}: {}",
+                                    message_id,
+                                    e
+                                );
+                                failed += 1;
+                            }
+                        }
+                    }
+                    failed += batch.len().saturating_sub(embeddings.len());
+                }
+                Err(e) => {
+                    tracing::warn!("Failed to embed batch of {} messages: {}", batch.len(), e);
+                    failed += batch.len();
+                }
+            }
+        }
+        (success, failed)
+    })
+    .await
+}
+
 /// Delete a skill by ID
 pub async fn delete_skill_by_id(
     State(state): State<AppState>,
@@ -4113,3 +7381,3090 @@ pub async fn delete_skill_by_id(
             .into_response(),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api_types::default_search_type;
+    use crate::db::Database;
+    use crate::test_support;
+    use std::sync::Arc;
+
+    fn test_state() -> (tempfile::TempDir, AppState) {
+        let tmp = tempfile::tempdir().unwrap();
+        let state = test_support::app_state(tmp.path());
+        (tmp, state)
+    }
+
+    /// Insert a project, one session, and its messages (role, search_content)
+    async fn seed_session(state: &AppState, session_id: &str, messages: &[(&str, &str)]) {
+        let sid = session_id.to_string();
+        let messages: Vec<(String, String)> = messages
+            .iter()
+            .map(|(r, c)| (r.to_string(), c.to_string()))
+            .collect();
+        state
+            .db
+            .as_ref()
+            .unwrap()
+            .with_conn(move |conn| {
+                test_support::seed_session(conn, &sid);
+                conn.execute(
+                    "UPDATE sessions SET message_count = ?1 WHERE id = ?2",
+                    rusqlite::params![messages.len() as i64, sid],
+                )
+                .unwrap();
+                for (seq, (role, content)) in messages.iter().enumerate() {
+                    conn.execute(
+                        "INSERT INTO session_messages (session_id, sequence_num, role, content_preview, search_content, timestamp)
+                         VALUES (?1, ?2, ?3, ?4, ?4, '2024-01-01T00:00:00Z')",
+                        rusqlite::params![sid, seq as i64, role, content],
+                    )
+                    .unwrap();
+                }
+            })
+            .await;
+    }
+
+    async fn response_json(resp: axum::response::Response) -> serde_json::Value {
+        let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_export_session_markdown() {
+        let (_tmp, state) = test_state();
+        seed_session(
+            &state,
+            "s1",
+            &[
+                ("user", "How do I add two numbers?"),
+                ("assistant", "fn add(a: i32, b: i32) -> i32 { a + b }"),
+                ("assistant", "{\"command\": \"cargo test\"}"),
+            ],
+        )
+        .await;
+        state
+            .db
+            .as_ref()
+            .unwrap()
+            .with_conn(|conn| {
+                conn.execute_batch(
+                    "UPDATE sessions SET title = 'Adding numbers: a guide' WHERE id = 's1';
+                     UPDATE session_messages SET has_code = 1 WHERE sequence_num = 1;
+                     UPDATE session_messages SET tool_name = 'Bash', tool_type = 'use',
+                         tool_summary = 'cargo test' WHERE sequence_num = 2;",
+                )
+                .unwrap()
+            })
+            .await;
+
+        let resp = export_session(
+            State(state),
+            Path("s1".to_string()),
+            Query(ExportSessionQuery { format: None }),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers()["content-type"],
+            "text/markdown; charset=utf-8"
+        );
+        assert_eq!(
+            resp.headers()["content-disposition"],
+            "attachment; filename=\"adding-numbers-a-guide.md\""
+        );
+
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let markdown = String::from_utf8(body.to_vec()).unwrap();
+        assert!(markdown.starts_with("# Adding numbers: a guide"));
+        assert!(markdown.contains("## User\n\nHow do I add two numbers?"));
+        assert!(
+            markdown.contains("## Assistant\n\n```\nfn add(a: i32, b: i32) -> i32 { a + b }\n```")
+        );
+        assert!(markdown.contains("> **Tool call** `Bash`: cargo test"));
+    }
+
+    #[tokio::test]
+    async fn test_export_skips_soft_deleted_session() {
+        let (_tmp, state) = test_state();
+        seed_session(&state, "s1", &[("user", "hello")]).await;
+        state
+            .db
+            .as_ref()
+            .unwrap()
+            .with_conn(|conn| {
+                conn.execute(
+                    "UPDATE sessions SET deleted_at = '2024-02-01T00:00:00Z' WHERE id = 's1'",
+                    [],
+                )
+                .unwrap()
+            })
+            .await;
+
+        for format in ["markdown", "json"] {
+            let resp = export_session(
+                State(state.clone()),
+                Path("s1".to_string()),
+                Query(ExportSessionQuery {
+                    format: Some(format.to_string()),
+                }),
+            )
+            .await;
+            assert_eq!(resp.status(), StatusCode::NOT_FOUND, "{}", format);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_export_session_json_bundle() {
+        let (_tmp, state) = test_state();
+        seed_session(
+            &state,
+            "s1",
+            &[
+                ("user", "first"),
+                ("assistant", "second"),
+                ("user", "third"),
+            ],
+        )
+        .await;
+        state
+            .db
+            .as_ref()
+            .unwrap()
+            .with_conn(|conn| {
+                conn.execute_batch(
+                    "INSERT INTO memories (project_id, session_id, memory_type, title, content, extracted_at)
+                     VALUES ('p1', 's1', 'fact', 'A fact', 'Details', '2024-01-01T00:00:00Z');",
+                )
+                .unwrap()
+            })
+            .await;
+
+        let resp = export_session(
+            State(state),
+            Path("s1".to_string()),
+            Query(ExportSessionQuery {
+                format: Some("json".to_string()),
+            }),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let bundle = response_json(resp).await;
+
+        for key in ["session", "messages", "memories", "skills", "markers"] {
+            assert!(bundle.get(key).is_some(), "missing {}", key);
+        }
+        assert_eq!(bundle["session"]["id"], "s1");
+        let sequence: Vec<i64> = bundle["messages"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|m| m["sequence_num"].as_i64().unwrap())
+            .collect();
+        assert_eq!(sequence, vec![0, 1, 2]);
+        assert_eq!(bundle["messages"][2]["content_preview"], "third");
+        assert_eq!(bundle["memories"][0]["title"], "A fact");
+        // No skills or markers were extracted, so the lists are present but empty
+        assert_eq!(bundle["skills"], serde_json::json!([]));
+        assert_eq!(bundle["markers"], serde_json::json!([]));
+    }
+
+    #[tokio::test]
+    async fn test_unreadable_message_rows_are_not_dropped() {
+        let (_tmp, state) = test_state();
+        seed_session(&state, "s1", &[("user", "first"), ("assistant", "second")]).await;
+        state
+            .db
+            .as_ref()
+            .unwrap()
+            .with_conn(|conn| {
+                conn.execute(
+                    "UPDATE session_messages SET has_code = 'yes' WHERE sequence_num = 1",
+                    [],
+                )
+                .unwrap()
+            })
+            .await;
+
+        let resp = get_session_messages(
+            State(state.clone()),
+            Path("s1".to_string()),
+            Query(GetMessagesQuery {
+                limit: None,
+                offset: None,
+            }),
+            axum::http::HeaderMap::new(),
+        )
+        .await
+        .into_response();
+        assert_eq!(resp.status(), StatusCode::INTERNAL_SERVER_ERROR);
+
+        // Exports stream, so the failure aborts the body instead
+        for format in ["json", "markdown"] {
+            let resp = export_session(
+                State(state.clone()),
+                Path("s1".to_string()),
+                Query(ExportSessionQuery {
+                    format: Some(format.to_string()),
+                }),
+            )
+            .await;
+            assert_eq!(resp.status(), StatusCode::OK);
+            assert!(
+                axum::body::to_bytes(resp.into_body(), usize::MAX)
+                    .await
+                    .is_err(),
+                "{format} export completed"
+            );
+        }
+    }
+
+    async fn export_bundle(state: &AppState, session_id: &str) -> serde_json::Value {
+        let resp = export_session(
+            State(state.clone()),
+            Path(session_id.to_string()),
+            Query(ExportSessionQuery {
+                format: Some("json".to_string()),
+            }),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        response_json(resp).await
+    }
+
+    #[tokio::test]
+    async fn test_import_session_bundle_round_trip() {
+        use crate::api::admin_routes::{import_session, ImportRequest};
+
+        let (_source_tmp, source) = test_state();
+        seed_session(&source, "s1", &[("user", "first"), ("assistant", "second")]).await;
+        source
+            .db
+            .as_ref()
+            .unwrap()
+            .with_conn(|conn| {
+                conn.execute_batch(
+                    "INSERT INTO memories (id, project_id, session_id, memory_type, title, content, tags, extracted_at)
+                     VALUES (7, 'p1', 's1', 'fact', 'A fact', 'Details', '[\"db\"]', '2024-01-01T00:00:00Z');
+                     INSERT INTO skills (id, project_id, session_id, name, description, steps, extracted_at)
+                     VALUES (3, 'p1', 's1', 'Deploy', 'Ship it', '[\"build\",\"push\"]', '2024-01-01T00:00:00Z');
+                     INSERT INTO session_markers (id, session_id, event_index, marker_type, label, created_at)
+                     VALUES (5, 's1', 1, 'ship', 'Shipped', '2024-01-01T00:00:00Z');",
+                )
+                .unwrap()
+            })
+            .await;
+        let exported = export_bundle(&source, "s1").await;
+
+        let (_target_tmp, target) = test_state();
+        target
+            .db
+            .as_ref()
+            .unwrap()
+            .with_conn(|conn| {
+                conn.execute(
+                    "INSERT INTO projects (id, name, folder_path, created_at, updated_at)
+                     VALUES ('p2', 'other', '/srv/other', '2024-01-01T00:00:00Z', '2024-01-01T00:00:00Z')",
+                    [],
+                )
+                .unwrap()
+            })
+            .await;
+        let import = |mode: &str| {
+            let mut body = exported.clone();
+            body["project_id"] = "other".into();
+            body["mode"] = mode.into();
+            let req: ImportRequest = serde_json::from_value(body).unwrap();
+            import_session(State(target.clone()), Json(req))
+        };
+
+        let resp = import("skip").await.into_response();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = response_json(resp).await;
+        assert_eq!(body["project_id"], "p2");
+        assert_eq!(
+            body["imported"],
+            serde_json::json!({ "session": 1, "messages": 2, "memories": 1, "skills": 1, "markers": 1 })
+        );
+
+        // Same bundle, session id preserved, project remapped
+        let mut expected = without_row_ids(exported.clone());
+        expected["session"]["project_id"] = "p2".into();
+        expected["memories"][0]["project_id"] = "p2".into();
+        expected["skills"][0]["project_id"] = "p2".into();
+        assert_eq!(
+            without_row_ids(export_bundle(&target, "s1").await),
+            expected
+        );
+
+        // Importing again skips everything, or rewrites it in overwrite mode
+        let body = response_json(import("skip").await.into_response()).await;
+        assert_eq!(body["imported"]["session"], 0);
+        assert_eq!(body["skipped"]["messages"], 2);
+        let body = response_json(import("overwrite").await.into_response()).await;
+        assert_eq!(body["imported"]["messages"], 2);
+        assert_eq!(body["skipped"]["memories"], 0);
+        assert_eq!(
+            without_row_ids(export_bundle(&target, "s1").await),
+            expected
+        );
+    }
+
+    /// `bundle` without the ids of its child rows, which are local to each instance
+    fn without_row_ids(mut bundle: serde_json::Value) -> serde_json::Value {
+        for key in ["messages", "memories", "skills", "markers"] {
+            for row in bundle[key].as_array_mut().unwrap() {
+                row.as_object_mut().unwrap().remove("id");
+            }
+        }
+        bundle
+    }
+
+    #[tokio::test]
+    async fn test_import_into_database_with_existing_rows() {
+        use crate::api::admin_routes::{import_session, ImportRequest};
+
+        let (_source_tmp, source) = test_state();
+        seed_session(&source, "s1", &[("user", "first"), ("assistant", "second")]).await;
+        source
+            .db
+            .as_ref()
+            .unwrap()
+            .with_conn(|conn| {
+                conn.execute_batch(
+                    "INSERT INTO memories (id, project_id, session_id, memory_type, title, content, extracted_at)
+                     VALUES (1, 'p1', 's1', 'fact', 'A fact', 'Details', '2024-01-01T00:00:00Z');
+                     INSERT INTO skills (id, project_id, session_id, name, description, extracted_at)
+                     VALUES (1, 'p1', 's1', 'Deploy', 'Ship it', '2024-01-01T00:00:00Z');
+                     INSERT INTO session_markers (id, session_id, event_index, marker_type, label, created_at)
+                     VALUES (1, 's1', 1, 'ship', 'Shipped', '2024-01-01T00:00:00Z');",
+                )
+                .unwrap()
+            })
+            .await;
+        let exported = export_bundle(&source, "s1").await;
+
+        // The target's own rows hold the same ids as the exported ones
+        let (_target_tmp, target) = test_state();
+        seed_session(&target, "t1", &[("user", "local"), ("assistant", "rows")]).await;
+        target
+            .db
+            .as_ref()
+            .unwrap()
+            .with_conn(|conn| {
+                conn.execute_batch(
+                    "INSERT INTO memories (id, project_id, session_id, memory_type, title, content, extracted_at)
+                     VALUES (1, 'p1', 't1', 'fact', 'Local fact', 'Kept', '2024-01-01T00:00:00Z');
+                     INSERT INTO skills (id, project_id, session_id, name, description, extracted_at)
+                     VALUES (1, 'p1', 't1', 'Test', 'Run tests', '2024-01-01T00:00:00Z');
+                     INSERT INTO session_markers (id, session_id, event_index, marker_type, label, created_at)
+                     VALUES (1, 't1', 0, 'bug', 'Local bug', '2024-01-01T00:00:00Z');",
+                )
+                .unwrap()
+            })
+            .await;
+        let before = export_bundle(&target, "t1").await;
+
+        let mut body = exported.clone();
+        body["project_id"] = "p1".into();
+        let req: ImportRequest = serde_json::from_value(body).unwrap();
+        let resp = import_session(State(target.clone()), Json(req))
+            .await
+            .into_response();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = response_json(resp).await;
+        assert_eq!(
+            body["imported"],
+            serde_json::json!({ "session": 1, "messages": 2, "memories": 1, "skills": 1, "markers": 1 })
+        );
+
+        // Every child row is new; the existing session is untouched
+        assert_eq!(
+            without_row_ids(export_bundle(&target, "s1").await),
+            without_row_ids(exported)
+        );
+        assert_eq!(export_bundle(&target, "t1").await, before);
+    }
+
+    async fn read_bytes(state: &AppState, range: Option<&str>) -> axum::response::Response {
+        let mut headers = axum::http::HeaderMap::new();
+        if let Some(range) = range {
+            headers.insert(axum::http::header::RANGE, range.parse().unwrap());
+        }
+        read_session_bytes(
+            State(state.clone()),
+            Path("s1".to_string()),
+            Query(ReadBytesQuery { offset: None }),
+            headers,
+        )
+        .await
+        .into_response()
+    }
+
+    #[tokio::test]
+    async fn test_read_session_bytes_honors_range() {
+        let (tmp, state) = test_state();
+        seed_session(&state, "s1", &[]).await;
+        let file = tmp.path().join("s1.jsonl");
+        std::fs::write(&file, "0123456789").unwrap();
+        let path = file.to_string_lossy().to_string();
+        state
+            .db
+            .as_ref()
+            .unwrap()
+            .with_conn(move |conn| {
+                conn.execute("UPDATE sessions SET file_path = ? WHERE id = 's1'", [&path])
+                    .unwrap()
+            })
+            .await;
+
+        let resp = read_bytes(&state, Some("bytes=2-5")).await;
+        assert_eq!(resp.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(resp.headers()["content-range"], "bytes 2-5/10");
+        assert_eq!(resp.headers()["accept-ranges"], "bytes");
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(&body[..], b"2345");
+
+        // Ends past the file are clamped; suffix ranges count from the end
+        let resp = read_bytes(&state, Some("bytes=7-100")).await;
+        assert_eq!(resp.headers()["content-range"], "bytes 7-9/10");
+        let resp = read_bytes(&state, Some("bytes=-3")).await;
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(&body[..], b"789");
+
+        let resp = read_bytes(&state, Some("bytes=10-")).await;
+        assert_eq!(resp.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+        assert_eq!(resp.headers()["content-range"], "bytes */10");
+
+        // Ranges larger than a chunk are streamed whole
+        let content: Vec<u8> = (0..BYTE_RANGE_CHUNK * 2 + 10)
+            .map(|i| b'a' + (i % 26) as u8)
+            .collect();
+        std::fs::write(&file, &content).unwrap();
+        let resp = read_bytes(&state, Some("bytes=5-")).await;
+        assert_eq!(resp.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(
+            resp.headers()["content-length"],
+            (content.len() - 5).to_string()
+        );
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(&body[..], &content[5..]);
+
+        // Nothing to serve from an empty file
+        std::fs::write(&file, "").unwrap();
+        let resp = read_bytes(&state, Some("bytes=0-")).await;
+        assert_eq!(resp.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+        assert_eq!(resp.headers()["content-range"], "bytes */0");
+        std::fs::write(&file, "0123456789").unwrap();
+
+        // Without a Range header the line-based JSON response is unchanged
+        let resp = read_bytes(&state, None).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let json = response_json(resp).await;
+        assert_eq!(json["file_size"], 10);
+        assert_eq!(json["lines"][0]["content"], "0123456789");
+    }
+
+    #[tokio::test]
+    async fn test_read_session_bytes_decompresses_gzipped_sessions() {
+        use flate2::{write::GzEncoder, Compression};
+        use std::io::Write;
+
+        let (tmp, state) = test_state();
+        seed_session(&state, "s1", &[]).await;
+        let content = "{\"a\":1}\n{\"b\":2}\n";
+        let file = tmp.path().join("s1.jsonl.gz");
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(content.as_bytes()).unwrap();
+        std::fs::write(&file, encoder.finish().unwrap()).unwrap();
+        let path = file.to_string_lossy().to_string();
+        state
+            .db
+            .as_ref()
+            .unwrap()
+            .with_conn(move |conn| {
+                conn.execute("UPDATE sessions SET file_path = ? WHERE id = 's1'", [&path])
+                    .unwrap()
+            })
+            .await;
+
+        // Ranges and sizes address the decompressed content
+        let resp = read_bytes(&state, Some("bytes=8-14")).await;
+        assert_eq!(resp.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(resp.headers()["content-range"], "bytes 8-14/16");
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(&body[..], b"{\"b\":2}");
+
+        let resp = read_bytes(&state, None).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let json = response_json(resp).await;
+        assert_eq!(json["file_size"], 16);
+        assert_eq!(json["lines"][0]["content"], "{\"a\":1}");
+        assert_eq!(json["lines"][1]["content"], "{\"b\":2}");
+        assert_eq!(json["lines"][1]["byte_offset"], 8);
+    }
+
+    #[tokio::test]
+    async fn test_search_includes_raw_and_normalized_scores() {
+        let (_tmp, state) = test_state();
+        seed_session(
+            &state,
+            "s1",
+            &[
+                ("user", "how do I configure the watcher"),
+                ("assistant", "the watcher reads config.toml"),
+            ],
+        )
+        .await;
+
+        let req = SearchRequest {
+            query: "watcher".to_string(),
+            project_id: None,
+            search_type: default_search_type(),
+            limit: None,
+            role: None,
+            has_code: None,
+            language: None,
+            tool_name: None,
+            tool_type: None,
+            vector_weight: None,
+            created_after: None,
+            created_before: None,
+            offset: None,
+            raw: false,
+        };
+        let body = response_json(search(State(state), Json(req)).await.into_response()).await;
+
+        let results = body["results"].as_array().unwrap();
+        assert_eq!(results.len(), 2);
+        for r in results {
+            let normalized = r["relevance_score"].as_f64().unwrap();
+            let raw = r["bm25_score"].as_f64().unwrap();
+            assert!((0.0..=1.0).contains(&normalized));
+            assert!((normalized - 1.0 / (1.0 + raw.abs())).abs() < 1e-9);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_search_pages_with_total_count() {
+        let (_tmp, state) = test_state();
+        let messages: Vec<(&str, String)> = (0..5)
+            .map(|i| ("user", format!("watcher question {}", i)))
+            .collect();
+        let messages: Vec<(&str, &str)> = messages.iter().map(|(r, c)| (*r, c.as_str())).collect();
+        seed_session(&state, "s1", &messages).await;
+
+        let page = |offset: Option<i64>| {
+            let req = SearchRequest {
+                query: "watcher".to_string(),
+                project_id: None,
+                search_type: default_search_type(),
+                limit: Some(2),
+                role: None,
+                has_code: None,
+                language: None,
+                tool_name: None,
+                tool_type: None,
+                vector_weight: None,
+                created_after: None,
+                created_before: None,
+                offset,
+                raw: false,
+            };
+            let state = state.clone();
+            async move { response_json(search(State(state), Json(req)).await.into_response()).await }
+        };
+        let lines = |body: &serde_json::Value| -> Vec<i64> {
+            body["results"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|r| r["line_number"].as_i64().unwrap())
+                .collect()
+        };
+
+        let first = page(None).await;
+        assert_eq!(first["total_count"], 5);
+        assert_eq!(lines(&first).len(), 2);
+        let second = page(Some(2)).await;
+        assert_eq!(second["total_count"], 5);
+        assert_eq!(lines(&second).len(), 2);
+        let last = page(Some(4)).await;
+        assert_eq!(lines(&last).len(), 1);
+
+        // Pages don't overlap
+        let mut seen: Vec<i64> = [first, second, last].iter().flat_map(lines).collect();
+        seen.sort();
+        seen.dedup();
+        assert_eq!(seen.len(), 5);
+    }
+
+    #[tokio::test]
+    async fn test_merge_memories() {
+        let (_tmp, state) = test_state();
+        seed_session(&state, "s1", &[]).await;
+        let db = state.db.clone().unwrap();
+        db.with_conn(|conn| {
+            conn.execute_batch(
+                "INSERT INTO memories (id, project_id, session_id, memory_type, title, content, tags, confidence, access_count, extracted_at)
+                 VALUES (1, 'p1', 's1', 'fact', 'Uses WAL', 'sqlite runs in WAL mode', '[\"a\",\"b\"]', 0.5, 2, '2024-01-01T00:00:00Z');
+                 INSERT INTO memories (id, project_id, session_id, memory_type, title, content, tags, confidence, access_count, extracted_at)
+                 VALUES (2, 'p1', 's1', 'fact', 'WAL mode', 'the db uses WAL', '[\"b\",\"c\"]', 0.9, 3, '2024-01-01T00:00:00Z');",
+            )
+        })
+        .await
+        .unwrap();
+
+        let resp = merge_memories(
+            State(state.clone()),
+            Json(MergeMemoriesRequest {
+                primary_id: 1,
+                duplicate_ids: vec![1, 2],
+            }),
+        )
+        .await
+        .into_response();
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        let resp = merge_memories(
+            State(state.clone()),
+            Json(MergeMemoriesRequest {
+                primary_id: 1,
+                duplicate_ids: vec![2],
+            }),
+        )
+        .await
+        .into_response();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = response_json(resp).await;
+        assert_eq!(body["merged"], 1);
+
+        let (tags, confidence, access_count, duplicate_state) = db
+            .with_conn(|conn| {
+                let (tags, confidence, access_count) = conn
+                    .query_row(
+                        "SELECT tags, confidence, access_count FROM memories WHERE id = 1",
+                        [],
+                        |row| {
+                            Ok((
+                                row.get::<_, String>(0)?,
+                                row.get::<_, f64>(1)?,
+                                row.get::<_, i64>(2)?,
+                            ))
+                        },
+                    )
+                    .unwrap();
+                let state: String = conn
+                    .query_row("SELECT state FROM memories WHERE id = 2", [], |row| {
+                        row.get(0)
+                    })
+                    .unwrap();
+                (tags, confidence, access_count, state)
+            })
+            .await;
+        assert_eq!(tags, r#"["a","b","c"]"#);
+        assert!((confidence - 0.9).abs() < 1e-9);
+        assert_eq!(access_count, 5);
+        assert_eq!(duplicate_state, "removed");
+
+        // An already merged duplicate can't be merged again
+        let resp = merge_memories(
+            State(state.clone()),
+            Json(MergeMemoriesRequest {
+                primary_id: 1,
+                duplicate_ids: vec![2],
+            }),
+        )
+        .await
+        .into_response();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_removed_memories_leave_ann_index() {
+        let (tmp, mut state) = test_state();
+        seed_session(&state, "s1", &[]).await;
+        state
+            .db
+            .clone()
+            .unwrap()
+            .with_conn(|conn| {
+                for id in 1..=3 {
+                    conn.execute(
+                        "INSERT INTO memories (id, project_id, session_id, memory_type, title, content, extracted_at)
+                         VALUES (?1, 'p1', 's1', 'fact', 'title', 'content', '2024-01-01T00:00:00Z')",
+                        [id],
+                    )?;
+                    conn.execute(
+                        "INSERT INTO memory_embeddings (memory_id, embedding) VALUES (?1, ?2)",
+                        rusqlite::params![
+                            id,
+                            crate::embeddings::embedding_to_bytes(&[1.0, id as f32, 0.0])
+                        ],
+                    )?;
+                }
+                Ok::<_, rusqlite::Error>(())
+            })
+            .await
+            .unwrap();
+        let db = Arc::new(
+            Database::new(tmp.path().join("test.db"))
+                .unwrap()
+                .with_memory_ann_index()
+                .unwrap(),
+        );
+        state.db = Some(db.clone());
+        let indexed = || {
+            let mut ids: Vec<i64> = db
+                .search_memory_ann(&[1.0, 0.0, 0.0], 10)
+                .unwrap_or_default()
+                .into_iter()
+                .map(|(id, _)| id)
+                .collect();
+            ids.sort();
+            ids
+        };
+        assert_eq!(indexed(), vec![1, 2, 3]);
+
+        let resp = delete_memory(State(state.clone()), Path(1))
+            .await
+            .into_response();
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+        assert_eq!(indexed(), vec![2, 3]);
+
+        let resp = merge_memories(
+            State(state.clone()),
+            Json(MergeMemoriesRequest {
+                primary_id: 2,
+                duplicate_ids: vec![3],
+            }),
+        )
+        .await
+        .into_response();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(indexed(), vec![2]);
+
+        // Moving a memory out of 'removed' indexes its stored embedding again
+        let resp = update_memory(
+            State(state.clone()),
+            Path(1),
+            Json(UpdateMemoryRequest {
+                title: None,
+                content: None,
+                state: Some("new".to_string()),
+                confidence: None,
+                is_validated: None,
+            }),
+        )
+        .await
+        .into_response();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(indexed(), vec![1, 2]);
+
+        let resp = delete_project(State(state.clone()), Path("p1".to_string()))
+            .await
+            .into_response();
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+        assert!(indexed().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_export_project_memories_claude_md() {
+        let (_tmp, state) = test_state();
+        seed_session(&state, "s1", &[]).await;
+        state
+            .db
+            .as_ref()
+            .unwrap()
+            .with_conn(|conn| {
+                conn.execute_batch(
+                    "INSERT INTO memories (project_id, session_id, memory_type, title, content, tags, confidence, state, is_validated, extracted_at)
+                     VALUES ('p1', 's1', 'decision', 'Use SQLite', 'WAL mode for\nconcurrent readers', '[\"db\"]', 0.8, 'high', 1, '2024-01-01T00:00:00Z'),
+                            ('p1', 's1', 'decision', 'Use axum', 'The HTTP layer is axum', '[]', 0.95, 'high', 1, '2024-01-01T00:00:00Z'),
+                            ('p1', 's1', 'fact', 'Port', 'The API listens on 19420', '[]', 0.7, 'high', 1, '2024-01-01T00:00:00Z'),
+                            ('p1', 's1', 'fact', 'Unvalidated', 'Not exported', '[]', 0.9, 'high', 0, '2024-01-01T00:00:00Z'),
+                            ('p1', 's1', 'fact', 'Low', 'Not exported either', '[]', 0.9, 'low', 1, '2024-01-01T00:00:00Z');",
+                )
+                .unwrap()
+            })
+            .await;
+
+        let resp = export_project_memories(
+            State(state.clone()),
+            Path("p1".to_string()),
+            Query(ExportMemoriesQuery {
+                format: Some("claude_md".to_string()),
+            }),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers()[axum::http::header::CONTENT_TYPE],
+            "text/markdown; charset=utf-8"
+        );
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let markdown = String::from_utf8(body.to_vec()).unwrap();
+        assert!(markdown.starts_with("# proj\n"));
+        assert!(markdown.contains(
+            "## Decisions\n\n- **Use axum**: The HTTP layer is axum\n- **Use SQLite**: WAL mode for concurrent readers (tags: `db`)\n"
+        ));
+        assert!(markdown.contains("## Facts\n\n- **Port**: The API listens on 19420\n"));
+        assert!(markdown.find("## Decisions").unwrap() < markdown.find("## Facts").unwrap());
+        assert!(!markdown.contains("Not exported"));
+        assert!(!markdown.contains("## Tasks"));
+
+        let resp = export_project_memories(
+            State(state.clone()),
+            Path("p1".to_string()),
+            Query(ExportMemoriesQuery {
+                format: Some("pdf".to_string()),
+            }),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        let resp = export_project_memories(
+            State(state),
+            Path("missing".to_string()),
+            Query(ExportMemoriesQuery { format: None }),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_bulk_delete_sessions() {
+        let (_tmp, state) = test_state();
+        for sid in ["s1", "s2", "s3"] {
+            seed_session(&state, sid, &[("user", "hello")]).await;
+        }
+        let db = state.db.clone().unwrap();
+        db.with_conn(|conn| {
+            conn.execute_batch(
+                "INSERT INTO memories (project_id, session_id, memory_type, title, content, extracted_at)
+                 VALUES ('p1', 's1', 'fact', 'A fact', 'Content', '2024-01-01T00:00:00Z');
+                 INSERT INTO skills (project_id, session_id, name, description, extracted_at)
+                 VALUES ('p1', 's1', 'A skill', 'Steps', '2024-01-01T00:00:00Z');
+                 INSERT INTO session_markers (session_id, event_index, marker_type, label, created_at)
+                 VALUES ('s1', 0, 'ship', 'Shipped', '2024-01-01T00:00:00Z');
+                 UPDATE sessions SET created_at = '2024-06-01T00:00:00Z' WHERE id = 's3';",
+            )
+            .unwrap()
+        })
+        .await;
+        let count = |table: &'static str| {
+            db.with_conn(move |conn| {
+                conn.query_row(&format!("SELECT COUNT(*) FROM {table}"), [], |row| {
+                    row.get::<_, i64>(0)
+                })
+                .unwrap()
+            })
+        };
+        let bulk_delete = |req: serde_json::Value| {
+            bulk_delete_sessions(
+                State(state.clone()),
+                Json(serde_json::from_value(req).unwrap()),
+            )
+        };
+
+        // Neither or both selectors is rejected
+        let resp = bulk_delete(serde_json::json!({})).await.into_response();
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        let resp = bulk_delete(
+            serde_json::json!({ "session_ids": ["s1"], "before": "2024-03-01T00:00:00Z" }),
+        )
+        .await
+        .into_response();
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        // By id; unknown ids are skipped
+        let body = response_json(
+            bulk_delete(serde_json::json!({ "session_ids": ["s1", "missing"] }))
+                .await
+                .into_response(),
+        )
+        .await;
+        assert_eq!(body["deleted"], 1);
+        let deleted = || {
+            db.with_conn(|conn| {
+                let mut stmt = conn
+                    .prepare("SELECT id FROM sessions WHERE deleted_at IS NOT NULL ORDER BY id")?;
+                let ids = stmt.query_map([], |row| row.get::<_, String>(0))?;
+                ids.collect::<rusqlite::Result<Vec<_>>>()
+            })
+        };
+        assert_eq!(deleted().await.unwrap(), vec!["s1"]);
+        // Soft-deleted: the rows stay until the session is purged
+        assert_eq!(count("sessions").await, 3);
+        assert_eq!(count("session_messages").await, 3);
+        assert_eq!(count("memories").await, 1);
+        assert_eq!(count("skills").await, 1);
+        assert_eq!(count("session_markers").await, 1);
+
+        // Deleting again doesn't count
+        let body = response_json(
+            bulk_delete(serde_json::json!({ "session_ids": ["s1"] }))
+                .await
+                .into_response(),
+        )
+        .await;
+        assert_eq!(body["deleted"], 0);
+
+        // By date cutoff, scoped to a project
+        let body = response_json(
+            bulk_delete(
+                serde_json::json!({ "before": "2024-03-01T00:00:00Z", "project_id": "other" }),
+            )
+            .await
+            .into_response(),
+        )
+        .await;
+        assert_eq!(body["deleted"], 0);
+        // The project's folder name resolves to its id
+        let body = response_json(
+            bulk_delete(
+                serde_json::json!({ "before": "2024-03-01T00:00:00Z", "project_id": "proj" }),
+            )
+            .await
+            .into_response(),
+        )
+        .await;
+        assert_eq!(body["deleted"], 1);
+        assert_eq!(deleted().await.unwrap(), vec!["s1", "s2"]);
+        let tombstones = db
+            .with_conn(|conn| {
+                let mut stmt = conn.prepare(
+                    "SELECT entity_id, project_id FROM tombstones
+                     WHERE entity_type = 'session' ORDER BY entity_id",
+                )?;
+                let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+                rows.collect::<rusqlite::Result<Vec<(String, String)>>>()
+            })
+            .await
+            .unwrap();
+        assert_eq!(
+            tombstones,
+            vec![
+                ("s1".to_string(), "p1".to_string()),
+                ("s2".to_string(), "p1".to_string())
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_soft_delete_and_restore_session() {
+        let (_tmp, state) = test_state();
+        seed_session(&state, "s1", &[("user", "hello world")]).await;
+        seed_session(&state, "s2", &[("user", "hello again")]).await;
+        let db = state.db.clone().unwrap();
+
+        let list = |deleted: bool| {
+            let query = ListSessionsQuery {
+                project_id: None,
+                limit: None,
+                offset: None,
+                include_hidden: Some(true),
+                cursor: None,
+                created_after: None,
+                created_before: None,
+                sort_by: None,
+                sort_order: None,
+                tags: None,
+                deleted,
+                branch: None,
+            };
+            let state = state.clone();
+            async move {
+                let body = response_json(
+                    list_sessions(State(state), Query(query))
+                        .await
+                        .into_response(),
+                )
+                .await;
+                body["sessions"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .map(|s| s["id"].as_str().unwrap().to_string())
+                    .collect::<Vec<_>>()
+            }
+        };
+        let delete = |id: &str, permanent: bool| {
+            delete_session(
+                State(state.clone()),
+                Path(id.to_string()),
+                Query(DeleteSessionQuery { permanent }),
+            )
+        };
+        let search = || {
+            db.with_read_conn(|conn| {
+                fulltext_search(
+                    conn,
+                    "hello",
+                    None,
+                    &SearchFilters::default(),
+                    &CreatedRange::default(),
+                    10,
+                    0,
+                )
+                .unwrap()
+                .iter()
+                .map(|r| r["session_id"].as_str().unwrap().to_string())
+                .collect::<Vec<_>>()
+            })
+        };
+
+        let resp = delete("s1", false).await.into_response();
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+        // Hidden from lists, search and lookups, but still stored
+        assert_eq!(list(false).await, vec!["s2"]);
+        assert_eq!(list(true).await, vec!["s1"]);
+        assert_eq!(search().await, vec!["s2"]);
+        let resp = get_session(State(state.clone()), Path("s1".to_string()))
+            .await
+            .into_response();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        let resp = delete("s1", false).await.into_response();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        let resp = restore_session(State(state.clone()), Path("s1".to_string()))
+            .await
+            .into_response();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(response_json(resp).await["id"], "s1");
+        let mut live = list(false).await;
+        live.sort();
+        assert_eq!(live, vec!["s1", "s2"]);
+        let resp = restore_session(State(state.clone()), Path("s1".to_string()))
+            .await
+            .into_response();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        // Permanent deletes remove the row
+        let resp = delete("s2", true).await.into_response();
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+        assert!(list(true).await.is_empty());
+        assert_eq!(list(false).await, vec!["s1"]);
+    }
+
+    #[tokio::test]
+    async fn test_soft_deleted_sessions_leave_agents_pending_and_memory_search() {
+        let (_tmp, state) = test_state();
+        for sid in ["parent", "agent", "gone"] {
+            seed_session(&state, sid, &[("user", "hello")]).await;
+        }
+        let db = state.db.clone().unwrap();
+        db.with_conn(|conn| {
+            conn.execute_batch(
+                "UPDATE sessions SET parent_session_id = 'parent' WHERE id IN ('agent', 'gone');
+                 UPDATE sessions SET message_count = 40;
+                 INSERT INTO memories (project_id, session_id, memory_type, title, content, extracted_at)
+                 VALUES ('p1', 'agent', 'fact', 'Kept fact', 'Retries', '2024-01-01T00:00:00Z'),
+                        ('p1', 'gone', 'fact', 'Deleted fact', 'Retries', '2024-01-01T00:00:00Z');",
+            )
+            .unwrap()
+        })
+        .await;
+        let resp = delete_session(
+            State(state.clone()),
+            Path("gone".to_string()),
+            Query(DeleteSessionQuery { permanent: false }),
+        )
+        .await
+        .into_response();
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+
+        let body = response_json(
+            get_session_agents(State(state.clone()), Path("parent".to_string()))
+                .await
+                .into_response(),
+        )
+        .await;
+        let agents: Vec<&str> = body["agents"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|a| a["id"].as_str().unwrap())
+            .collect();
+        assert_eq!(agents, vec!["agent"]);
+
+        let body = response_json(
+            get_pending_ai_sessions(State(state.clone()))
+                .await
+                .into_response(),
+        )
+        .await;
+        let pending: Vec<&str> = body["sessions"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|s| s["session_id"].as_str().unwrap())
+            .collect();
+        assert!(!pending.contains(&"gone"), "{:?}", pending);
+        assert!(pending.contains(&"agent"), "{:?}", pending);
+
+        // Cross-project search (project-scoped search goes through McpDb)
+        let body = response_json(
+            search_memories(
+                State(state),
+                Json(SearchMemoriesRequest {
+                    query: "retries".to_string(),
+                    project_id: None,
+                    tags: None,
+                    limit: None,
+                }),
+            )
+            .await
+            .into_response(),
+        )
+        .await;
+        let titles: Vec<&str> = body["memories"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|m| m["title"].as_str().unwrap())
+            .collect();
+        assert_eq!(titles, vec!["Kept fact"]);
+    }
+
+    #[tokio::test]
+    async fn test_search_tool_filters_and_facets() {
+        let (_tmp, state) = test_state();
+        seed_session(
+            &state,
+            "s1",
+            &[
+                ("assistant", "run the migration"),
+                ("user", "migration applied"),
+                ("assistant", "read the migration file"),
+                ("user", "migration looks good"),
+            ],
+        )
+        .await;
+        state
+            .db
+            .as_ref()
+            .unwrap()
+            .with_conn(|conn| {
+                conn.execute_batch(
+                    "UPDATE session_messages SET tool_name = 'Bash', tool_type = 'use' WHERE sequence_num = 0;
+                     UPDATE session_messages SET tool_name = 'Bash', tool_type = 'result' WHERE sequence_num = 1;
+                     UPDATE session_messages SET tool_name = 'Read', tool_type = 'use' WHERE sequence_num = 2;",
+                )
+                .unwrap()
+            })
+            .await;
+
+        let search_tool = |tool_name: &str, tool_type: Option<&str>| SearchRequest {
+            query: "migration".to_string(),
+            project_id: None,
+            search_type: default_search_type(),
+            limit: None,
+            role: None,
+            has_code: None,
+            language: None,
+            tool_name: Some(tool_name.to_string()),
+            tool_type: tool_type.map(String::from),
+            vector_weight: None,
+            created_after: None,
+            created_before: None,
+            offset: None,
+            raw: false,
+        };
+        let lines = |body: serde_json::Value| -> Vec<i64> {
+            let mut lines: Vec<i64> = body["results"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|r| r["line_number"].as_i64().unwrap())
+                .collect();
+            lines.sort();
+            lines
+        };
+        let body = response_json(
+            search(State(state.clone()), Json(search_tool("Bash", None)))
+                .await
+                .into_response(),
+        )
+        .await;
+        assert_eq!(lines(body), vec![0, 1]);
+        let body = response_json(
+            search(
+                State(state.clone()),
+                Json(search_tool("Bash", Some("result"))),
+            )
+            .await
+            .into_response(),
+        )
+        .await;
+        assert_eq!(lines(body), vec![1]);
+
+        let facets = |project_id: Option<&str>| {
+            search_facets(
+                State(state.clone()),
+                Query(SearchFacetsQuery {
+                    project_id: project_id.map(String::from),
+                }),
+            )
+        };
+        let body = response_json(facets(None).await.into_response()).await;
+        assert_eq!(
+            body["tool_names"],
+            serde_json::json!([
+                { "tool_name": "Bash", "count": 2 },
+                { "tool_name": "Read", "count": 1 },
+            ])
+        );
+        let body = response_json(facets(Some("p1")).await.into_response()).await;
+        assert_eq!(body["tool_names"].as_array().unwrap().len(), 2);
+        let body = response_json(facets(Some("other")).await.into_response()).await;
+        assert!(body["tool_names"].as_array().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_search_filters_by_language() {
+        let (_tmp, state) = test_state();
+        seed_session(
+            &state,
+            "s1",
+            &[
+                ("assistant", "the watcher loop in python"),
+                ("assistant", "the watcher loop in rust"),
+            ],
+        )
+        .await;
+        state
+            .db
+            .as_ref()
+            .unwrap()
+            .with_conn(|conn| {
+                conn.execute_batch(
+                    "UPDATE session_messages SET has_code = 1, code_language = 'python' WHERE sequence_num = 0;
+                     UPDATE session_messages SET has_code = 1, code_language = 'rust' WHERE sequence_num = 1;",
+                )
+                .unwrap()
+            })
+            .await;
+
+        let search_language = |language: &str| SearchRequest {
+            query: "watcher".to_string(),
+            project_id: None,
+            search_type: default_search_type(),
+            limit: None,
+            role: None,
+            has_code: None,
+            language: Some(language.to_string()),
+            tool_name: None,
+            tool_type: None,
+            vector_weight: None,
+            created_after: None,
+            created_before: None,
+            offset: None,
+            raw: false,
+        };
+        // Aliases are normalized
+        let body = response_json(
+            search(State(state.clone()), Json(search_language("py")))
+                .await
+                .into_response(),
+        )
+        .await;
+        let results = body["results"].as_array().unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0]["line_number"], 0);
+
+        let body = response_json(
+            search(State(state.clone()), Json(search_language("go")))
+                .await
+                .into_response(),
+        )
+        .await;
+        assert!(body["results"].as_array().unwrap().is_empty());
+
+        let body = response_json(
+            get_session_messages(
+                State(state),
+                Path("s1".to_string()),
+                Query(GetMessagesQuery {
+                    offset: None,
+                    limit: None,
+                }),
+                axum::http::HeaderMap::new(),
+            )
+            .await
+            .into_response(),
+        )
+        .await;
+        assert_eq!(body["messages"][1]["code_language"], "rust");
+    }
+
+    #[tokio::test]
+    async fn test_search_with_bad_fts_syntax() {
+        let (_tmp, state) = test_state();
+        seed_session(&state, "s1", &[("user", "why does the watcher deadlock")]).await;
+        let search_for = |query: &str, raw: bool| {
+            let state = state.clone();
+            let req = SearchRequest {
+                raw,
+                ..SearchRequest::new(query)
+            };
+            async move { search(State(state), Json(req)).await.into_response() }
+        };
+
+        // Unbalanced parens and dangling operators are cleaned up
+        for query in ["watcher)", "(watcher", "watcher AND", "OR deadlock"] {
+            let resp = search_for(query, false).await;
+            assert_eq!(resp.status(), StatusCode::OK, "{}", query);
+            assert_eq!(response_json(resp).await["total_count"], 1, "{}", query);
+        }
+        let resp = search_for("AND", false).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(response_json(resp).await["total_count"], 0);
+
+        // Raw queries keep FTS operators but report bad syntax as a 400
+        let resp = search_for("watcher OR nothing", true).await;
+        assert_eq!(response_json(resp).await["total_count"], 1);
+        let resp = search_for("watcher)", true).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        let body = response_json(resp).await;
+        assert!(body["error"]
+            .as_str()
+            .unwrap()
+            .starts_with("Invalid search syntax"));
+        let resp = search_for("AND", true).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_search_highlights_matched_terms() {
+        let (_tmp, state) = test_state();
+        seed_session(
+            &state,
+            "s1",
+            &[("user", "how do I configure the watcher for new projects")],
+        )
+        .await;
+
+        let req = SearchRequest {
+            query: "watcher".to_string(),
+            project_id: None,
+            search_type: default_search_type(),
+            limit: None,
+            role: None,
+            has_code: None,
+            language: None,
+            tool_name: None,
+            tool_type: None,
+            vector_weight: None,
+            created_after: None,
+            created_before: None,
+            offset: None,
+            raw: false,
+        };
+        let body = response_json(
+            search(State(state.clone()), Json(req))
+                .await
+                .into_response(),
+        )
+        .await;
+        let snippet = body["results"][0]["highlighted_snippet"].as_str().unwrap();
+        assert!(snippet.contains("[match]watcher[/match]"), "{snippet}");
+        assert!(body["results"][0]["relevance_score"].as_f64().is_some());
+
+        let body = response_json(
+            search_session(
+                State(state),
+                Path("s1".to_string()),
+                Query(SearchSessionQuery {
+                    q: "watcher".to_string(),
+                    limit: None,
+                }),
+            )
+            .await
+            .into_response(),
+        )
+        .await;
+        let snippet = body["results"][0]["highlighted_snippet"].as_str().unwrap();
+        assert!(snippet.contains("[match]watcher[/match]"), "{snippet}");
+    }
+
+    #[tokio::test]
+    async fn test_namespaced_session_id_in_path_params() {
+        use tower::ServiceExt;
+
+        let (_tmp, state) = test_state();
+        let session_id = "3f2a9c1e-project:session";
+        seed_session(&state, session_id, &[("user", "hello")]).await;
+        let app = crate::api::create_router(state, &crate::config::ServerConfig::default());
+
+        for uri in [
+            "/api/sessions/3f2a9c1e-project:session",
+            "/api/sessions/3f2a9c1e-project%3Asession",
+            "/api/sessions/3f2a9c1e-project:session/messages",
+        ] {
+            let resp = app
+                .clone()
+                .oneshot(
+                    axum::http::Request::get(uri)
+                        .body(axum::body::Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            assert_eq!(resp.status(), StatusCode::OK, "{}", uri);
+        }
+
+        let resp = app
+            .oneshot(
+                axum::http::Request::get("/api/sessions/3f2a9c1e-project:session")
+                    .body(axum::body::Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let body = response_json(resp).await;
+        assert_eq!(body["id"], session_id);
+    }
+
+    #[tokio::test]
+    async fn test_oversized_request_body_returns_413() {
+        use tower::ServiceExt;
+
+        let (_tmp, state) = test_state();
+        let server = crate::config::ServerConfig {
+            max_body_bytes: 1024,
+            max_import_bytes: 64 * 1024,
+            ..Default::default()
+        };
+        let app = crate::api::create_router(state, &server);
+        let post = |uri: &str, body: String| {
+            app.clone().oneshot(
+                axum::http::Request::post(uri)
+                    .header("content-type", "application/json")
+                    .body(axum::body::Body::from(body))
+                    .unwrap(),
+            )
+        };
+
+        let body = serde_json::json!({ "query": "x".repeat(4096) }).to_string();
+        let resp = post("/api/search", body).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        let body = response_json(resp).await;
+        assert_eq!(
+            body["error"],
+            "Request body too large (limit is 1024 bytes)"
+        );
+
+        // Imports have their own, larger limit; this bundle gets as far as parsing
+        let body = serde_json::json!({ "project_id": "x".repeat(4096) }).to_string();
+        let resp = post("/api/admin/import", body).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        let body = serde_json::json!({ "project_id": "x".repeat(128 * 1024) }).to_string();
+        let resp = post("/api/admin/import", body).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        let body = response_json(resp).await;
+        assert_eq!(
+            body["error"],
+            "Request body too large (limit is 65536 bytes)"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_list_sessions_gzip_compressed() {
+        use std::io::Read;
+        use tower::ServiceExt;
+
+        let (_tmp, state) = test_state();
+        for i in 0..20 {
+            seed_session(&state, &format!("session-{}", i), &[("user", "hello")]).await;
+        }
+        let app = crate::api::create_router(state, &crate::config::ServerConfig::default());
+
+        let resp = app
+            .oneshot(
+                axum::http::Request::get("/api/sessions")
+                    .header("Accept-Encoding", "gzip")
+                    .body(axum::body::Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers()["content-encoding"], "gzip");
+
+        let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let mut json = String::new();
+        flate2::read::GzDecoder::new(&bytes[..])
+            .read_to_string(&mut json)
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(body["sessions"].as_array().unwrap().len(), 20);
+    }
+
+    #[tokio::test]
+    async fn test_read_scoped_key_cannot_mutate() {
+        use crate::config::{ApiKeyConfig, ApiKeyScope};
+        use tower::ServiceExt;
+
+        let (_tmp, mut state) = test_state();
+        seed_session(&state, "s1", &[("user", "hello")]).await;
+        state.api_keys = vec![
+            ApiKeyConfig {
+                key: "admin-key".to_string(),
+                scope: ApiKeyScope::Admin,
+            },
+            ApiKeyConfig {
+                key: "read-key".to_string(),
+                scope: ApiKeyScope::Read,
+            },
+        ];
+        let app = crate::api::create_router(state, &crate::config::ServerConfig::default());
+        let send = |method: &str, uri: &str, key: &str| {
+            app.clone().oneshot(
+                axum::http::Request::builder()
+                    .method(method)
+                    .uri(uri)
+                    .header("Authorization", format!("Bearer {}", key))
+                    .body(axum::body::Body::empty())
+                    .unwrap(),
+            )
+        };
+
+        let resp = send("GET", "/api/sessions", "read-key").await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let resp = send("DELETE", "/api/sessions/s1", "read-key")
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+        assert_eq!(response_json(resp).await["error"], "API key is read-only");
+
+        // Query POSTs only read
+        let resp = app
+            .clone()
+            .oneshot(
+                axum::http::Request::builder()
+                    .method("POST")
+                    .uri("/api/search")
+                    .header("Authorization", "Bearer read-key")
+                    .header("Content-Type", "application/json")
+                    .body(axum::body::Body::from(r#"{"query":"hello"}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let resp = send("GET", "/api/sessions", "wrong-key").await.unwrap();
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+        let resp = send("DELETE", "/api/sessions/s1", "admin-key")
+            .await
+            .unwrap();
+        assert!(resp.status().is_success());
+    }
+
+    async fn seed_projects(state: &AppState, projects: &[(&str, &str)]) {
+        let projects: Vec<(String, String)> = projects
+            .iter()
+            .map(|(id, folder)| (id.to_string(), folder.to_string()))
+            .collect();
+        state
+            .db
+            .as_ref()
+            .unwrap()
+            .with_conn(move |conn| {
+                for (id, folder) in &projects {
+                    conn.execute(
+                        "INSERT INTO projects (id, name, folder_path, created_at, updated_at)
+                         VALUES (?1, ?1, ?2, '2024-01-01T00:00:00Z', '2024-01-01T00:00:00Z')",
+                        rusqlite::params![id, folder],
+                    )
+                    .unwrap();
+                }
+            })
+            .await;
+    }
+
+    async fn resolve(state: &AppState, path: &str, fuzzy: bool) -> axum::response::Response {
+        resolve_project(
+            State(state.clone()),
+            Query(ResolveProjectQuery {
+                path: path.to_string(),
+                fuzzy,
+            }),
+        )
+        .await
+        .into_response()
+    }
+
+    #[tokio::test]
+    async fn test_fuzzy_resolve_nested_path() {
+        let (_tmp, state) = test_state();
+        seed_projects(
+            &state,
+            &[
+                ("outer", "/work/repo"),
+                ("inner", "/work/repo/packages/api"),
+            ],
+        )
+        .await;
+
+        let resp = resolve(&state, "/work/repo/packages/api/src/handlers/", true).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(response_json(resp).await["id"], "inner");
+
+        let resp = resolve(&state, "/work/repo/docs", true).await;
+        assert_eq!(response_json(resp).await["id"], "outer");
+
+        // Matches whole path components only
+        let resp = resolve(&state, "/work/repository/src", true).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_fuzzy_resolve_ambiguous_path() {
+        let (_tmp, state) = test_state();
+        // The same folder registered directly and as a Claude Code project directory
+        let claude_folder = crate::mcp::db::convert_to_claude_project_path("/work/app").unwrap();
+        seed_projects(&state, &[("a", "/work/app"), ("b", &claude_folder)]).await;
+
+        let resp = resolve(&state, "/work/app/src/main.rs", true).await;
+        assert_eq!(resp.status(), StatusCode::CONFLICT);
+        let body = response_json(resp).await;
+        let ids: Vec<&str> = body["candidates"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|c| c["id"].as_str().unwrap())
+            .collect();
+        assert_eq!(ids.len(), 2);
+        assert!(ids.contains(&"a") && ids.contains(&"b"));
+    }
+
+    #[tokio::test]
+    async fn test_session_composition_histogram() {
+        let (_tmp, state) = test_state();
+        seed_session(
+            &state,
+            "s1",
+            &[
+                ("user", "fix the failing test"),
+                ("assistant", "running the tests"),
+                ("assistant", "Bash: cargo test"),
+                ("user", "tool result"),
+                ("assistant", "Read: src/lib.rs"),
+                ("assistant", "Bash: cargo test"),
+                ("user", "tool result"),
+                ("system", "compacted"),
+            ],
+        )
+        .await;
+        state
+            .db
+            .as_ref()
+            .unwrap()
+            .with_conn(|conn| {
+                conn.execute_batch(
+                    "UPDATE session_messages SET tool_type = 'use', tool_name = 'Bash' WHERE sequence_num IN (2, 5);
+                     UPDATE session_messages SET tool_type = 'use', tool_name = 'Read' WHERE sequence_num = 4;
+                     UPDATE session_messages SET tool_type = 'result', has_error = 1 WHERE sequence_num = 3;
+                     UPDATE session_messages SET tool_type = 'result' WHERE sequence_num = 6;",
+                )
+                .unwrap();
+            })
+            .await;
+
+        let resp = get_session_composition(State(state.clone()), Path("s1".to_string()))
+            .await
+            .into_response();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = response_json(resp).await;
+        assert_eq!(body["total_messages"], 8);
+        assert_eq!(
+            body["roles"],
+            serde_json::json!({ "assistant": 4, "system": 1, "user": 3 })
+        );
+        assert_eq!(
+            body["tool_types"],
+            serde_json::json!({ "result": 2, "use": 3 })
+        );
+        assert_eq!(body["tools"], serde_json::json!({ "Bash": 2, "Read": 1 }));
+        assert_eq!(body["errors"], 1);
+
+        let resp = get_session_composition(State(state), Path("missing".to_string()))
+            .await
+            .into_response();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_session_messages_as_ndjson() {
+        let (_tmp, state) = test_state();
+        seed_session(
+            &state,
+            "s1",
+            &[
+                ("user", "first"),
+                ("assistant", "second"),
+                ("user", "third"),
+                ("assistant", "fourth"),
+            ],
+        )
+        .await;
+
+        let mut headers = axum::http::HeaderMap::new();
+        headers.insert(
+            axum::http::header::ACCEPT,
+            "application/x-ndjson".parse().unwrap(),
+        );
+        let resp = get_session_messages(
+            State(state),
+            Path("s1".to_string()),
+            Query(GetMessagesQuery {
+                offset: Some(1),
+                limit: None,
+            }),
+            headers,
+        )
+        .await
+        .into_response();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers()[axum::http::header::CONTENT_TYPE],
+            "application/x-ndjson"
+        );
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body = String::from_utf8(body.to_vec()).unwrap();
+        assert!(body.ends_with('\n'));
+        let messages: Vec<serde_json::Value> = body
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        let sequence: Vec<i64> = messages
+            .iter()
+            .map(|m| m["sequence_num"].as_i64().unwrap())
+            .collect();
+        assert_eq!(sequence, vec![1, 2, 3]);
+        assert_eq!(messages[0]["search_content"], "second");
+        assert_eq!(messages[0]["session_id"], "s1");
+    }
+
+    #[tokio::test]
+    async fn test_ndjson_messages_span_several_reads() {
+        let (_tmp, state) = test_state();
+        let messages: Vec<(&str, String)> =
+            (0..9).map(|i| ("user", format!("message {}", i))).collect();
+        let messages: Vec<(&str, &str)> = messages.iter().map(|(r, c)| (*r, c.as_str())).collect();
+        seed_session(&state, "s1", &messages).await;
+
+        // Two rows per read: offset 1, then keyset reads after 2, 4, 6
+        let db = state.db.clone().unwrap();
+        let resp = stream_session_messages(db, "s1".to_string(), 6, 1, 2);
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let sequence: Vec<i64> = String::from_utf8(body.to_vec())
+            .unwrap()
+            .lines()
+            .map(|line| {
+                serde_json::from_str::<serde_json::Value>(line).unwrap()["sequence_num"]
+                    .as_i64()
+                    .unwrap()
+            })
+            .collect();
+        assert_eq!(sequence, vec![1, 2, 3, 4, 5, 6]);
+
+        // A short final read ends the stream
+        let db = state.db.clone().unwrap();
+        let resp = stream_session_messages(db, "s1".to_string(), i64::MAX, 4, 2);
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(String::from_utf8(body.to_vec()).unwrap().lines().count(), 5);
+    }
+
+    #[tokio::test]
+    async fn test_session_summary_totals() {
+        let (_tmp, state) = test_state();
+        let mut config = Config::default();
+        config.ai.model_pricing.insert(
+            "claude-sonnet".to_string(),
+            crate::config::ModelPricing {
+                input: 3.0,
+                output: 15.0,
+                cache_read: 0.3,
+                cache_creation: 3.75,
+            },
+        );
+        std::fs::write(&state.config_path, toml::to_string(&config).unwrap()).unwrap();
+        seed_session(
+            &state,
+            "s1",
+            &[
+                ("user", "fix the failing test"),
+                ("assistant", "Bash: cargo test"),
+                ("user", "tool result"),
+                ("assistant", "fixed"),
+            ],
+        )
+        .await;
+        state
+            .db
+            .as_ref()
+            .unwrap()
+            .with_conn(|conn| {
+                conn.execute_batch(
+                    "UPDATE sessions SET duration_ms = 90000 WHERE id = 's1';
+                     UPDATE session_messages SET tool_type = 'use', tool_name = 'Bash' WHERE sequence_num = 1;
+                     UPDATE session_messages SET tool_type = 'result' WHERE sequence_num = 2;
+                     UPDATE session_messages SET model = 'claude-sonnet', input_tokens = 1000000,
+                            output_tokens = 100000, cache_read_tokens = 2000000,
+                            cache_creation_tokens = 400000
+                     WHERE sequence_num = 1;
+                     UPDATE session_messages SET model = 'claude-sonnet', input_tokens = 500000,
+                            output_tokens = 100000, tokens_estimated = 1
+                     WHERE sequence_num = 3;",
+                )
+                .unwrap();
+            })
+            .await;
+
+        let resp = get_session_summary(State(state.clone()), Path("s1".to_string()))
+            .await
+            .into_response();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = response_json(resp).await;
+        assert_eq!(body["session_id"], "s1");
+        assert_eq!(body["duration_ms"], 90000);
+        assert_eq!(body["total_messages"], 4);
+        assert_eq!(
+            body["roles"],
+            serde_json::json!({ "assistant": 2, "user": 2 })
+        );
+        assert_eq!(body["tools"], serde_json::json!({ "Bash": 1 }));
+        assert_eq!(body["total_input_tokens"], 1500000);
+        assert_eq!(body["total_output_tokens"], 200000);
+        assert_eq!(body["total_cache_read_tokens"], 2000000);
+        assert_eq!(body["total_cache_creation_tokens"], 400000);
+        assert_eq!(body["estimated_output_tokens"], 100000);
+        // 1.5M * $3 + 0.2M * $15 + 2M * $0.30 + 0.4M * $3.75 = $9.60
+        let expected = 4.5 + 3.0 + 0.6 + 1.5;
+        assert!((body["estimated_cost_usd"].as_f64().unwrap() - expected).abs() < 1e-9);
+        assert_eq!(body["model_costs"].as_array().unwrap().len(), 1);
+        assert_eq!(body["unpriced_models"], serde_json::json!([]));
+
+        let resp = get_session_summary(State(state), Path("missing".to_string()))
+            .await
+            .into_response();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_intents_extract_search_and_subtitle() {
+        let (_tmp, state) = test_state();
+        seed_session(
+            &state,
+            "s1",
+            &[
+                ("user", "Can you add OAuth login to the settings page?"),
+                ("assistant", "Done"),
+                ("user", "Now refactor the token cache"),
+            ],
+        )
+        .await;
+
+        // No provider configured: the heuristic does the extraction
+        let resp = extract_session_intents(State(state.clone()), Path("s1".to_string()))
+            .await
+            .into_response();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = response_json(resp).await;
+        assert_eq!(body["source"], "heuristic");
+        assert_eq!(body["intents"].as_array().unwrap().len(), 2);
+
+        let search_in = |q: &str, project_id: Option<&str>| {
+            search_intents(
+                State(state.clone()),
+                Query(SearchIntentsQuery {
+                    q: q.to_string(),
+                    project_id: project_id.map(str::to_string),
+                    limit: None,
+                }),
+            )
+        };
+        let search = |q: &str| search_in(q, None);
+        let body = response_json(search("oauth ADD").await.into_response()).await;
+        let results = body["results"].as_array().unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0]["session_id"], "s1");
+        assert_eq!(results[0]["intent"], "Add OAuth login to the settings page");
+        let body = response_json(search("oauth_").await.into_response()).await;
+        assert!(body["results"].as_array().unwrap().is_empty());
+        let body = response_json(search_in("oauth", Some("p1")).await.into_response()).await;
+        assert_eq!(body["results"].as_array().unwrap().len(), 1);
+        assert_eq!(
+            search_in("oauth", Some("missing"))
+                .await
+                .into_response()
+                .status(),
+            StatusCode::NOT_FOUND
+        );
+        assert_eq!(
+            search("  ").await.into_response().status(),
+            StatusCode::BAD_REQUEST
+        );
+
+        let body = response_json(
+            get_session(State(state.clone()), Path("s1".to_string()))
+                .await
+                .into_response(),
+        )
+        .await;
+        assert_eq!(body["subtitle"], "Add OAuth login to the settings page");
+
+        let resp = extract_session_intents(State(state), Path("missing".to_string()))
+            .await
+            .into_response();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    async fn create(state: &AppState, name: &str, folder_path: &str) -> axum::response::Response {
+        create_project(
+            State(state.clone()),
+            Json(CreateProjectRequest {
+                name: name.to_string(),
+                folder_path: folder_path.to_string(),
+                description: None,
+                repo_url: None,
+                language: None,
+                framework: None,
+            }),
+        )
+        .await
+        .into_response()
+    }
+
+    #[tokio::test]
+    async fn test_create_project_duplicate_folder_conflicts() {
+        let (_tmp, state) = test_state();
+
+        let resp = create(&state, "app", "/work/app").await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let first_id = response_json(resp).await["id"].clone();
+
+        let resp = create(&state, "app again", "/work/app").await;
+        assert_eq!(resp.status(), StatusCode::CONFLICT);
+        assert_eq!(response_json(resp).await["project_id"], first_id);
+    }
+
+    #[tokio::test]
+    async fn test_create_project_duplicate_folder_upserts() {
+        let (_tmp, state) = test_state();
+        let mut config = Config::default();
+        config.database.duplicate_projects = crate::config::DuplicateProjectPolicy::Upsert;
+        std::fs::write(&state.config_path, toml::to_string(&config).unwrap()).unwrap();
+
+        let first_id = response_json(create(&state, "app", "/work/app").await).await["id"].clone();
+        let resp = create(&state, "renamed", "/work/app").await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = response_json(resp).await;
+        assert_eq!(body["id"], first_id);
+        assert_eq!(body["name"], "renamed");
+    }
+
+    #[test]
+    fn test_longest_streak() {
+        let dates = |ds: &[&str]| ds.iter().map(|d| d.to_string()).collect::<Vec<_>>();
+        assert_eq!(longest_streak(&[]), 0);
+        assert_eq!(longest_streak(&dates(&["2024-03-05"])), 1);
+        // Newest first, as analytics returns them; the gap splits 2 + 3 days
+        assert_eq!(
+            longest_streak(&dates(&[
+                "2024-03-05",
+                "2024-03-04",
+                "2024-03-01",
+                "2024-02-29",
+                "2024-02-28",
+            ])),
+            3
+        );
+        // Month boundary and duplicates
+        assert_eq!(
+            longest_streak(&dates(&["2024-01-31", "2024-02-01", "2024-02-01"])),
+            2
+        );
+    }
+
+    #[tokio::test]
+    async fn test_project_longest_streak_is_computed_on_read() {
+        let (_tmp, state) = test_state();
+        seed_sessions_at(
+            &state,
+            &[
+                ("a", "2024-03-01T10:00:00Z"),
+                ("b", "2024-03-02T10:00:00Z"),
+                ("c", "2024-03-03T23:00:00Z"),
+                ("d", "2024-03-05T10:00:00Z"),
+            ],
+        )
+        .await;
+
+        let resp = get_project(State(state.clone()), Path("p1".to_string()))
+            .await
+            .into_response();
+        assert_eq!(response_json(resp).await["longest_streak"], 3);
+        let resp = get_project_analytics(State(state.clone()), Path("p1".to_string()))
+            .await
+            .into_response();
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        // Reads leave the stored column alone
+        let stored: i64 = state
+            .db
+            .as_ref()
+            .unwrap()
+            .with_read_conn(|conn| {
+                conn.query_row(
+                    "SELECT longest_streak FROM projects WHERE id = 'p1'",
+                    [],
+                    |row| row.get(0),
+                )
+                .unwrap()
+            })
+            .await;
+        assert_eq!(stored, 0);
+    }
+
+    #[tokio::test]
+    async fn test_project_analytics_estimates_model_cost() {
+        let (_tmp, state) = test_state();
+        let mut config = Config::default();
+        config.ai.model_pricing.insert(
+            "claude-sonnet".to_string(),
+            crate::config::ModelPricing {
+                input: 3.0,
+                output: 15.0,
+                cache_read: 0.3,
+                cache_creation: 3.75,
+            },
+        );
+        std::fs::write(&state.config_path, toml::to_string(&config).unwrap()).unwrap();
+        seed_session(
+            &state,
+            "s1",
+            &[("assistant", "priced"), ("assistant", "unpriced")],
+        )
+        .await;
+        state
+            .db
+            .as_ref()
+            .unwrap()
+            .with_conn(|conn| {
+                conn.execute(
+                    "UPDATE session_messages SET model = 'claude-sonnet', input_tokens = 1000000,
+                            output_tokens = 200000, cache_read_tokens = 2000000,
+                            cache_creation_tokens = 400000
+                     WHERE sequence_num = 0",
+                    [],
+                )
+                .unwrap();
+                conn.execute(
+                    "UPDATE session_messages SET model = 'mystery-model', input_tokens = 5000
+                     WHERE sequence_num = 1",
+                    [],
+                )
+                .unwrap();
+            })
+            .await;
+
+        let body = response_json(
+            get_project_analytics(State(state), Path("p1".to_string()))
+                .await
+                .into_response(),
+        )
+        .await;
+        let stats = &body["stats"];
+        // 1M * $3 + 0.2M * $15 + 2M * $0.30 + 0.4M * $3.75 = $8.10
+        let expected = 3.0 + 3.0 + 0.6 + 1.5;
+        assert!((stats["estimated_cost_usd"].as_f64().unwrap() - expected).abs() < 1e-9);
+        let costs = stats["model_costs"].as_array().unwrap();
+        assert_eq!(costs[0]["model"], "claude-sonnet");
+        assert!((costs[0]["estimated_cost_usd"].as_f64().unwrap() - expected).abs() < 1e-9);
+        assert_eq!(costs[1]["model"], "mystery-model");
+        assert_eq!(costs[1]["estimated_cost_usd"], 0.0);
+        assert_eq!(
+            stats["unpriced_models"],
+            serde_json::json!(["mystery-model"])
+        );
+    }
+
+    /// Insert sessions (id, created_at) under project p1
+    async fn seed_sessions_at(state: &AppState, sessions: &[(&str, &str)]) {
+        let sessions: Vec<(String, String)> = sessions
+            .iter()
+            .map(|(id, at)| (id.to_string(), at.to_string()))
+            .collect();
+        state
+            .db
+            .as_ref()
+            .unwrap()
+            .with_conn(move |conn| {
+                test_support::seed_project(conn);
+                for (id, at) in &sessions {
+                    conn.execute(
+                        "INSERT INTO sessions (id, project_id, file_path, ai_tool, created_at, indexed_at)
+                         VALUES (?1, 'p1', ?2, 'Claude Code', ?3, ?3)",
+                        rusqlite::params![id, format!("/tmp/proj/{}.jsonl", id), at],
+                    )
+                    .unwrap();
+                }
+            })
+            .await;
+    }
+
+    #[tokio::test]
+    async fn test_list_sessions_created_window() {
+        let (_tmp, state) = test_state();
+        seed_sessions_at(
+            &state,
+            &[
+                ("old", "2024-01-01T09:00:00Z"),
+                ("mid", "2024-01-08T09:00:00+00:00"),
+                ("new", "2024-01-15T09:00:00.500Z"),
+            ],
+        )
+        .await;
+
+        let list = |after: Option<&str>, before: Option<&str>| {
+            let query = ListSessionsQuery {
+                project_id: None,
+                limit: None,
+                offset: None,
+                include_hidden: None,
+                cursor: None,
+                created_after: after.map(String::from),
+                created_before: before.map(String::from),
+                sort_by: None,
+                sort_order: None,
+                tags: None,
+                deleted: false,
+                branch: None,
+            };
+            list_sessions(State(state.clone()), Query(query))
+        };
+        let ids = |body: serde_json::Value| -> Vec<String> {
+            body["sessions"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|s| s["id"].as_str().unwrap().to_string())
+                .collect()
+        };
+
+        let body = response_json(
+            list(
+                Some("2024-01-05T00:00:00Z"),
+                Some("2024-01-10T00:00:00+02:00"),
+            )
+            .await
+            .into_response(),
+        )
+        .await;
+        assert_eq!(ids(body.clone()), vec!["mid"]);
+        assert_eq!(body["total"], 1);
+
+        // Bounds are inclusive
+        let body = response_json(
+            list(Some("2024-01-08T09:00:00Z"), None)
+                .await
+                .into_response(),
+        )
+        .await;
+        assert_eq!(ids(body), vec!["new", "mid"]);
+
+        let resp = list(Some("last week"), None).await.into_response();
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(
+            response_json(resp).await["error"],
+            "created_after must be an RFC 3339 timestamp"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_session_tags() {
+        let (_tmp, state) = test_state();
+        seed_sessions_at(
+            &state,
+            &[("a", "2024-01-01T09:00:00Z"), ("b", "2024-01-02T09:00:00Z")],
+        )
+        .await;
+
+        let tag = |id: &str, tags: &[&str]| {
+            add_session_tags(
+                State(state.clone()),
+                Path(id.to_string()),
+                Json(AddSessionTagsRequest {
+                    tags: tags.iter().map(|t| t.to_string()).collect(),
+                }),
+            )
+        };
+        let body = response_json(
+            tag("a", &["release", " bug ", "release"])
+                .await
+                .into_response(),
+        )
+        .await;
+        assert_eq!(body["tags"], serde_json::json!(["bug", "release"]));
+        tag("b", &["release"]).await;
+
+        let resp = tag("missing", &["bug"]).await.into_response();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        let resp = tag("a", &[" "]).await.into_response();
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        let list = |tags: &str| {
+            let query = ListSessionsQuery {
+                project_id: None,
+                limit: None,
+                offset: None,
+                include_hidden: None,
+                cursor: None,
+                created_after: None,
+                created_before: None,
+                sort_by: None,
+                sort_order: None,
+                tags: Some(tags.to_string()),
+                deleted: false,
+                branch: None,
+            };
+            list_sessions(State(state.clone()), Query(query))
+        };
+        let ids = |body: &serde_json::Value| -> Vec<String> {
+            body["sessions"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|s| s["id"].as_str().unwrap().to_string())
+                .collect()
+        };
+
+        let body = response_json(list("release").await.into_response()).await;
+        assert_eq!(ids(&body), vec!["b", "a"]);
+        assert_eq!(
+            body["sessions"][1]["tags"],
+            serde_json::json!(["bug", "release"])
+        );
+        // Every listed tag must be present
+        let body = response_json(list("release,bug").await.into_response()).await;
+        assert_eq!(ids(&body), vec!["a"]);
+        assert_eq!(body["total"], 1);
+
+        // Removing a tag drops the session from that filter
+        let resp = remove_session_tag(
+            State(state.clone()),
+            Path(("a".to_string(), "bug".to_string())),
+        )
+        .await
+        .into_response();
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+        let body = response_json(list("bug").await.into_response()).await;
+        assert!(ids(&body).is_empty());
+
+        let resp = remove_session_tag(
+            State(state.clone()),
+            Path(("a".to_string(), "bug".to_string())),
+        )
+        .await
+        .into_response();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        let session = response_json(
+            get_session(State(state.clone()), Path("a".to_string()))
+                .await
+                .into_response(),
+        )
+        .await;
+        assert_eq!(session["tags"], serde_json::json!(["release"]));
+    }
+
+    #[tokio::test]
+    async fn test_search_created_window() {
+        let (_tmp, state) = test_state();
+        for (id, at) in [
+            ("jan", "2024-01-02T00:00:00Z"),
+            ("feb", "2024-02-02T00:00:00Z"),
+        ] {
+            seed_session(&state, id, &[("user", "deploy the watcher")]).await;
+            let (id, at) = (id.to_string(), at.to_string());
+            state
+                .db
+                .as_ref()
+                .unwrap()
+                .with_conn(move |conn| {
+                    conn.execute(
+                        "UPDATE sessions SET created_at = ?1 WHERE id = ?2",
+                        rusqlite::params![at, id],
+                    )
+                })
+                .await
+                .unwrap();
+        }
+
+        let search_window = |after: Option<&str>, before: Option<&str>| {
+            let req = SearchRequest {
+                query: "watcher".to_string(),
+                project_id: None,
+                search_type: default_search_type(),
+                limit: None,
+                role: None,
+                has_code: None,
+                language: None,
+                tool_name: None,
+                tool_type: None,
+                vector_weight: None,
+                created_after: after.map(String::from),
+                created_before: before.map(String::from),
+                offset: None,
+                raw: false,
+            };
+            search(State(state.clone()), Json(req))
+        };
+
+        let body = response_json(
+            search_window(Some("2024-01-15T00:00:00Z"), None)
+                .await
+                .into_response(),
+        )
+        .await;
+        let results = body["results"].as_array().unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0]["session_id"], "feb");
+
+        let body = response_json(
+            search_window(None, Some("2024-01-15T00:00:00Z"))
+                .await
+                .into_response(),
+        )
+        .await;
+        let results = body["results"].as_array().unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0]["session_id"], "jan");
+
+        let resp = search_window(None, Some("2024-13-01"))
+            .await
+            .into_response();
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_list_sessions_sort_columns() {
+        let (_tmp, state) = test_state();
+        // (id, created_at, indexed_at, message_count, duration_ms)
+        let rows = [
+            ("a", "2024-01-01T00:00:00Z", "2024-03-02T00:00:00Z", 30, 100),
+            ("b", "2024-01-02T00:00:00Z", "2024-03-03T00:00:00Z", 10, 300),
+            ("c", "2024-01-03T00:00:00Z", "2024-03-01T00:00:00Z", 20, 200),
+        ];
+        state
+            .db
+            .as_ref()
+            .unwrap()
+            .with_conn(move |conn| {
+                test_support::seed_project(conn);
+                for (id, created, indexed, count, duration) in rows {
+                    conn.execute(
+                        "INSERT INTO sessions (id, project_id, file_path, ai_tool, message_count,
+                                               duration_ms, created_at, indexed_at)
+                         VALUES (?1, 'p1', ?1, 'Claude Code', ?2, ?3, ?4, ?5)",
+                        rusqlite::params![id, count, duration, created, indexed],
+                    )
+                    .unwrap();
+                }
+            })
+            .await;
+
+        let order = |sort_by: &str, sort_order: Option<&str>| {
+            let query = ListSessionsQuery {
+                project_id: None,
+                limit: None,
+                offset: None,
+                include_hidden: None,
+                cursor: None,
+                created_after: None,
+                created_before: None,
+                sort_by: Some(sort_by.to_string()),
+                sort_order: sort_order.map(String::from),
+                tags: None,
+                deleted: false,
+                branch: None,
+            };
+            let state = state.clone();
+            async move {
+                let body = response_json(
+                    list_sessions(State(state), Query(query))
+                        .await
+                        .into_response(),
+                )
+                .await;
+                body["sessions"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .map(|s| s["id"].as_str().unwrap().to_string())
+                    .collect::<Vec<_>>()
+            }
+        };
+
+        assert_eq!(order("created_at", None).await, ["c", "b", "a"]);
+        assert_eq!(order("created_at", Some("asc")).await, ["a", "b", "c"]);
+        assert_eq!(order("message_count", None).await, ["a", "c", "b"]);
+        assert_eq!(order("message_count", Some("asc")).await, ["b", "c", "a"]);
+        assert_eq!(order("duration_ms", None).await, ["b", "c", "a"]);
+        assert_eq!(order("indexed_at", None).await, ["b", "a", "c"]);
+        // Unknown values fall back to created_at DESC instead of reaching the SQL
+        assert_eq!(
+            order("id; DROP TABLE sessions", Some("sideways")).await,
+            ["c", "b", "a"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_list_sessions_by_git_branch() {
+        let (_tmp, state) = test_state();
+        seed_sessions_at(
+            &state,
+            &[
+                ("a", "2024-01-01T00:00:01Z"),
+                ("b", "2024-01-01T00:00:02Z"),
+                ("c", "2024-01-01T00:00:03Z"),
+            ],
+        )
+        .await;
+        state
+            .db
+            .as_ref()
+            .unwrap()
+            .with_conn(|conn| {
+                conn.execute_batch(
+                    "UPDATE sessions SET git_branch = 'feature/auth', git_commit = '3f9c2ab' WHERE id = 'a';
+                     UPDATE sessions SET git_branch = 'main' WHERE id = 'b';",
+                )
+                .unwrap();
+            })
+            .await;
+
+        let query = ListSessionsQuery {
+            project_id: None,
+            limit: None,
+            offset: None,
+            include_hidden: None,
+            cursor: None,
+            created_after: None,
+            created_before: None,
+            sort_by: None,
+            sort_order: None,
+            tags: None,
+            deleted: false,
+            branch: Some("feature/auth".to_string()),
+        };
+        let body = response_json(
+            list_sessions(State(state), Query(query))
+                .await
+                .into_response(),
+        )
+        .await;
+        assert_eq!(body["total"], 1);
+        let session = &body["sessions"][0];
+        assert_eq!(session["id"], "a");
+        assert_eq!(session["git_branch"], "feature/auth");
+        assert_eq!(session["git_commit"], "3f9c2ab");
+    }
+
+    async fn sessions_page(state: &AppState, cursor: Option<String>) -> serde_json::Value {
+        let query = ListSessionsQuery {
+            project_id: None,
+            limit: Some(2),
+            offset: None,
+            include_hidden: None,
+            cursor,
+            created_after: None,
+            created_before: None,
+            sort_by: None,
+            sort_order: None,
+            tags: None,
+            deleted: false,
+            branch: None,
+        };
+        response_json(
+            list_sessions(State(state.clone()), Query(query))
+                .await
+                .into_response(),
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_session_cursor_pagination_across_inserts() {
+        let (_tmp, state) = test_state();
+        seed_sessions_at(
+            &state,
+            &[
+                ("a", "2024-01-01T00:00:01Z"),
+                ("b", "2024-01-01T00:00:02Z"),
+                ("c", "2024-01-01T00:00:02Z"),
+                ("d", "2024-01-01T00:00:03Z"),
+                ("e", "2024-01-01T00:00:04Z"),
+            ],
+        )
+        .await;
+
+        let mut seen: Vec<String> = Vec::new();
+        let mut cursor = None;
+        loop {
+            let page = sessions_page(&state, cursor).await;
+            seen.extend(
+                page["sessions"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .map(|s| s["id"].as_str().unwrap().to_string()),
+            );
+            if seen.len() == 2 {
+                // New sessions arrive between pages, including one tied with the cursor
+                seed_sessions_at(
+                    &state,
+                    &[("f", "2024-01-01T00:00:05Z"), ("z", "2024-01-01T00:00:04Z")],
+                )
+                .await;
+            }
+            match page["next_cursor"].as_str() {
+                Some(next) => cursor = Some(next.to_string()),
+                None => break,
+            }
+        }
+
+        assert_eq!(seen, vec!["e", "d", "c", "b", "a"]);
+
+        let with_cursor = |cursor: &str, sort_by: Option<&str>| ListSessionsQuery {
+            project_id: None,
+            limit: None,
+            offset: None,
+            include_hidden: None,
+            cursor: Some(cursor.to_string()),
+            created_after: None,
+            created_before: None,
+            sort_by: sort_by.map(str::to_string),
+            sort_order: None,
+            tags: None,
+            deleted: false,
+            branch: None,
+        };
+        let resp = list_sessions(State(state.clone()), Query(with_cursor("garbage", None)))
+            .await
+            .into_response();
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        // A cursor only marks a position in the default order
+        let cursor = sessions_page(&state, None).await["next_cursor"]
+            .as_str()
+            .unwrap()
+            .to_string();
+        let resp = list_sessions(
+            State(state),
+            Query(with_cursor(&cursor, Some("message_count"))),
+        )
+        .await
+        .into_response();
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_memory_cursor_pagination_across_inserts() {
+        let (_tmp, state) = test_state();
+        seed_session(&state, "s1", &[]).await;
+        let insert = |title: &'static str, at: &'static str| {
+            let db = state.db.clone().unwrap();
+            async move {
+                db.with_conn(move |conn| {
+                    conn.execute(
+                        "INSERT INTO memories (project_id, session_id, memory_type, title, content, extracted_at)
+                         VALUES ('p1', 's1', 'fact', ?1, ?1, ?2)",
+                        rusqlite::params![title, at],
+                    )
+                    .unwrap();
+                })
+                .await
+            }
+        };
+        insert("m1", "2024-01-01T00:00:01Z").await;
+        insert("m2", "2024-01-01T00:00:02Z").await;
+        insert("m3", "2024-01-01T00:00:02Z").await;
+        insert("m4", "2024-01-01T00:00:03Z").await;
+
+        let mut seen: Vec<String> = Vec::new();
+        let mut cursor = None;
+        loop {
+            let query: ListMemoriesQuery = serde_json::from_value(serde_json::json!({
+                "limit": 2,
+                "sort_by": "extracted_at",
+                "cursor": cursor,
+            }))
+            .unwrap();
+            let page = response_json(
+                list_memories(State(state.clone()), Query(query))
+                    .await
+                    .into_response(),
+            )
+            .await;
+            seen.extend(
+                page["memories"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .map(|m| m["title"].as_str().unwrap().to_string()),
+            );
+            if seen.len() == 2 {
+                insert("m5", "2024-01-01T00:00:04Z").await;
+            }
+            match page["next_cursor"].as_str() {
+                Some(next) => cursor = Some(next.to_string()),
+                None => break,
+            }
+        }
+
+        assert_eq!(seen, vec!["m4", "m3", "m2", "m1"]);
+
+        // A cursor only follows newest-first extracted_at order
+        let cursor = first_memory_cursor(&state).await;
+        for (sort_by, sort_order) in [(Some("confidence"), None), (None, Some("asc"))] {
+            let query: ListMemoriesQuery = serde_json::from_value(serde_json::json!({
+                "cursor": cursor,
+                "sort_by": sort_by,
+                "sort_order": sort_order,
+            }))
+            .unwrap();
+            let resp = list_memories(State(state.clone()), Query(query))
+                .await
+                .into_response();
+            assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        }
+    }
+
+    /// `next_cursor` of the first one-memory page in extracted_at order
+    async fn first_memory_cursor(state: &AppState) -> String {
+        let query: ListMemoriesQuery = serde_json::from_value(serde_json::json!({
+            "limit": 1,
+            "sort_by": "extracted_at",
+        }))
+        .unwrap();
+        let page = response_json(
+            list_memories(State(state.clone()), Query(query))
+                .await
+                .into_response(),
+        )
+        .await;
+        page["next_cursor"].as_str().unwrap().to_string()
+    }
+
+    #[tokio::test]
+    async fn test_semantic_search_requires_backfilled_embeddings() {
+        let (_tmp, state) = test_state();
+        seed_session(&state, "s1", &[]).await;
+
+        let search = |project_id: &str| {
+            semantic_search_memories(
+                State(state.clone()),
+                Json(SemanticSearchRequest {
+                    query: "database locking".to_string(),
+                    project_id: project_id.to_string(),
+                    limit: None,
+                }),
+            )
+        };
+
+        let resp = search("p1").await.into_response();
+        assert_eq!(resp.status(), StatusCode::CONFLICT);
+        assert!(response_json(resp).await["error"]
+            .as_str()
+            .unwrap()
+            .contains("embedding_refresh"));
+
+        let resp = search("missing").await.into_response();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    /// Deterministic stand-in for the embedding model: hashed bag of words
+    fn fake_embed(text: &str) -> Result<Vec<f32>, String> {
+        use std::hash::{Hash, Hasher};
+        let mut v = vec![0.0f32; crate::embeddings::EMBEDDING_DIM];
+        for word in text.to_lowercase().split_whitespace() {
+            let mut h = std::collections::hash_map::DefaultHasher::new();
+            word.hash(&mut h);
+            v[h.finish() as usize % crate::embeddings::EMBEDDING_DIM] += 1.0;
+        }
+        Ok(v)
+    }
+
+    async fn stored_embedding(state: &AppState, id: i64) -> Vec<f32> {
+        let bytes: Vec<u8> = state
+            .db
+            .as_ref()
+            .unwrap()
+            .with_conn(move |conn| {
+                conn.query_row(
+                    "SELECT embedding FROM memory_embeddings WHERE memory_id = ?",
+                    [id],
+                    |row| row.get(0),
+                )
+            })
+            .await
+            .unwrap();
+        crate::embeddings::bytes_to_embedding(&bytes)
+    }
+
+    #[tokio::test]
+    async fn test_reembed_after_content_edit_updates_vector() {
+        let (_tmp, state) = test_state();
+        seed_session(&state, "s1", &[]).await;
+        state
+            .db
+            .as_ref()
+            .unwrap()
+            .with_conn(|conn| {
+                conn.execute(
+                    "INSERT INTO memories (id, project_id, session_id, memory_type, title, content, confidence, extracted_at)
+                     VALUES (1, 'p1', 's1', 'decision', 'Database', 'sqlite wal locking', 0.9, '2024-01-01T00:00:00Z')",
+                    [],
+                )
+            })
+            .await
+            .unwrap();
+        let db = state.db.clone().unwrap();
+        let similarity = |v: &[f32], query: &str| {
+            crate::embeddings::cosine_similarity(v, &fake_embed(query).unwrap())
+        };
+
+        let dimension = reembed_memory_with(&db, 1, fake_embed).await.unwrap();
+        assert_eq!(dimension, Some(crate::embeddings::EMBEDDING_DIM));
+        let before = stored_embedding(&state, 1).await;
+
+        let resp = update_memory(
+            State(state.clone()),
+            Path(1),
+            Json(UpdateMemoryRequest {
+                title: None,
+                content: Some("prefer tabs for indentation".to_string()),
+                state: None,
+                confidence: None,
+                is_validated: None,
+            }),
+        )
+        .await
+        .into_response();
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        reembed_memory_with(&db, 1, fake_embed).await.unwrap();
+        let after = stored_embedding(&state, 1).await;
+        assert_ne!(before, after);
+        assert!(similarity(&after, "tabs indentation") > similarity(&before, "tabs indentation"));
+        assert!(similarity(&after, "wal locking") < similarity(&before, "wal locking"));
+
+        assert_eq!(reembed_memory_with(&db, 99, fake_embed).await, Ok(None));
+    }
+
+    #[tokio::test]
+    async fn test_backfill_emits_progress_per_batch() {
+        let (_tmp, state) = test_state();
+        seed_session(&state, "s1", &[]).await;
+        state
+            .db
+            .as_ref()
+            .unwrap()
+            .with_conn(|conn| {
+                for i in 0..5 {
+                    conn.execute(
+                        "INSERT INTO memories (project_id, session_id, memory_type, title, content, extracted_at)
+                         VALUES ('p1', 's1', 'fact', ?1, 'content', '2024-01-01T00:00:00Z')",
+                        [format!("memory {}", i)],
+                    )
+                    .unwrap();
+                }
+            })
+            .await;
+        let memories: Vec<(i64, String, String)> = (1..=5)
+            .map(|id| (id, format!("memory {}", id - 1), "content".to_string()))
+            .collect();
+        let mut rx = state.ai_event_tx.subscribe();
+
+        let result = backfill_embeddings_with(
+            state.db.clone().unwrap(),
+            memories,
+            2,
+            state.ai_event_tx.clone(),
+            |texts: &[&str]| texts.iter().map(|t| fake_embed(t)).collect(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(result, (5, 0));
+
+        let mut progress = Vec::new();
+        let mut complete = None;
+        while let Ok(event) = rx.try_recv() {
+            match event {
+                AiEvent::EmbeddingProgress { done, total } => progress.push((done, total)),
+                AiEvent::EmbeddingComplete {
+                    embedded,
+                    failed,
+                    total,
+                } => complete = Some((embedded, failed, total)),
+                other => panic!("unexpected event {:?}", other),
+            }
+        }
+        assert_eq!(progress, vec![(2, 5), (4, 5), (5, 5)]);
+        assert_eq!(complete, Some((5, 0, 5)));
+        assert_eq!(
+            stored_embedding(&state, 5).await.len(),
+            crate::embeddings::EMBEDDING_DIM
+        );
+    }
+
+    #[tokio::test]
+    async fn test_session_semantic_search_ranks_messages() {
+        let (_tmp, state) = test_state();
+        seed_session(
+            &state,
+            "s1",
+            &[
+                ("user", "where do we validate auth tokens"),
+                ("assistant", "the build runs cargo test"),
+                ("assistant", "checking auth tokens"),
+            ],
+        )
+        .await;
+        let search = |id: &str| {
+            semantic_search_session(
+                State(state.clone()),
+                Path(id.to_string()),
+                Json(SessionSemanticSearchRequest {
+                    query: "auth tokens".to_string(),
+                    limit: None,
+                }),
+            )
+        };
+        assert_eq!(
+            search("missing").await.into_response().status(),
+            StatusCode::NOT_FOUND
+        );
+        // Nothing embedded yet
+        assert_eq!(
+            search("s1").await.into_response().status(),
+            StatusCode::CONFLICT
+        );
+
+        let db = state.db.clone().unwrap();
+        let messages: Vec<(i64, String)> = db
+            .with_conn(|conn| {
+                let mut stmt = conn
+                    .prepare(
+                        "SELECT id, search_content FROM session_messages ORDER BY sequence_num",
+                    )
+                    .unwrap();
+                stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+                    .unwrap()
+                    .map(|r| r.unwrap())
+                    .collect()
+            })
+            .await;
+        let result = embed_session_messages_with(db.clone(), messages, 2, |texts: &[&str]| {
+            texts.iter().map(|t| fake_embed(t)).collect()
+        })
+        .await
+        .unwrap();
+        assert_eq!(result, (3, 0));
+
+        let query = fake_embed("auth tokens").unwrap();
+        let ranked = db
+            .with_read_conn(move |conn| rank_session_messages(conn, "s1", &query, 10))
+            .await
+            .unwrap();
+        let order: Vec<i64> = ranked
+            .iter()
+            .map(|r| r["message_seq"].as_i64().unwrap())
+            .collect();
+        assert_eq!(order, vec![2, 0, 1]);
+        let scores: Vec<f64> = ranked
+            .iter()
+            .map(|r| r["score"].as_f64().unwrap())
+            .collect();
+        assert!(scores.windows(2).all(|w| w[0] >= w[1]));
+        assert_eq!(ranked[0]["snippet"], "checking auth tokens");
+    }
+
+    #[test]
+    fn test_hybrid_rank_scales_agreement_to_one() {
+        // Agreement at rank one in both lists scores exactly 1.0
+        let agree = hybrid_rank(&[0.9, 0.1], 0.5);
+        assert_eq!(agree[0].0, 0);
+        assert!((agree[0].1 - 1.0).abs() < 1e-9);
+    }
+
+    #[tokio::test]
+    #[ignore = "downloads the embedding model"]
+    async fn test_hybrid_search_promotes_paraphrase_ranked_low_by_bm25() {
+        let (_tmp, state) = test_state();
+        seed_session(
+            &state,
+            "s1",
+            &[
+                ("user", "the lunch menu is on the database wiki"),
+                (
+                    "assistant",
+                    "another writer held the sqlite lock so the write failed",
+                ),
+                ("user", "is it raining"),
+            ],
+        )
+        .await;
+
+        let hybrid = |vector_weight: f64| SearchRequest {
+            search_type: "hybrid".to_string(),
+            vector_weight: Some(vector_weight),
+            ..SearchRequest::new("database is locked")
+        };
+        let body = response_json(
+            search(State(state.clone()), Json(hybrid(1.0)))
+                .await
+                .into_response(),
+        )
+        .await;
+        let results = body["results"].as_array().unwrap();
+        assert_eq!(results.len(), 3);
+        // Shares only the stem of "locked" with the query, but means the same
+        assert_eq!(results[0]["line_number"], 1);
+        let vector_scores: Vec<f64> = results
+            .iter()
+            .map(|r| r["vector_score"].as_f64().unwrap())
+            .collect();
+        assert!(vector_scores.windows(2).all(|w| w[0] >= w[1]));
+
+        // Without the vector list, BM25 order stands
+        let body = response_json(
+            search(State(state), Json(hybrid(0.0)))
+                .await
+                .into_response(),
+        )
+        .await;
+        assert_ne!(body["results"][0]["line_number"], 1);
+    }
+
+    #[test]
+    fn test_fts_any_word_query() {
+        assert_eq!(
+            fts_any_word_query("fix db \"lock\""),
+            "\"fix\" OR \"db\" OR \"lock\""
+        );
+        assert_eq!(fts_any_word_query("  \"\" "), "");
+    }
+
+    #[tokio::test]
+    async fn test_hybrid_pages_stay_within_the_candidates() {
+        let (_tmp, state) = test_state();
+        let contents: Vec<String> = (0..HYBRID_CANDIDATES + 10)
+            .map(|i| format!("lock timeout number {i}"))
+            .collect();
+        let messages: Vec<(&str, &str)> = contents.iter().map(|c| ("user", c.as_str())).collect();
+        seed_session(&state, "s1", &messages).await;
+
+        let page = |offset: i64| SearchRequest {
+            search_type: "hybrid".to_string(),
+            limit: Some(300),
+            offset: Some(offset),
+            ..SearchRequest::new("lock")
+        };
+        let mut seen = std::collections::HashSet::new();
+        for (offset, expected) in [(0, 300), (300, HYBRID_CANDIDATES - 300), (600, 0)] {
+            let body = response_json(
+                search(State(state.clone()), Json(page(offset)))
+                    .await
+                    .into_response(),
+            )
+            .await;
+            assert_eq!(body["total_count"], HYBRID_CANDIDATES);
+            let results = body["results"].as_array().unwrap();
+            assert_eq!(results.len(), expected, "offset {offset}");
+            for r in results {
+                assert!(seen.insert(r["line_number"].as_i64().unwrap()));
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_hybrid_search_surfaces_partial_word_matches() {
+        let (_tmp, state) = test_state();
+        seed_session(
+            &state,
+            "s1",
+            &[
+                ("user", "the sqlite database is locked again"),
+                ("assistant", "writers wait on the busy timeout"),
+                ("user", "unrelated chatter about lunch"),
+            ],
+        )
+        .await;
+
+        let hybrid = |vector_weight: Option<f64>| SearchRequest {
+            query: "database busy".to_string(),
+            project_id: None,
+            search_type: "hybrid".to_string(),
+            limit: None,
+            role: None,
+            has_code: None,
+            language: None,
+            tool_name: None,
+            tool_type: None,
+            vector_weight,
+            created_after: None,
+            created_before: None,
+            offset: None,
+            raw: false,
+        };
+
+        let resp = search(State(state.clone()), Json(hybrid(Some(1.5))))
+            .await
+            .into_response();
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        // Neither message contains both words, so an implicit-AND BM25 query finds nothing
+        let body = response_json(
+            search(State(state), Json(hybrid(Some(0.5))))
+                .await
+                .into_response(),
+        )
+        .await;
+        let results = body["results"].as_array().unwrap();
+        assert_eq!(results.len(), 2);
+        assert!(results
+            .iter()
+            .all(|r| r["relevance_score"].as_f64().unwrap() > 0.0));
+    }
+}
@@ -2,331 +2,181 @@
 
 use super::AppState;
 use crate::ai::types::AiEvent;
+use crate::events::get_event_type;
+pub use crate::events::SseEvent;
 use crate::watcher::WatcherEvent;
 use axum::{
     extract::State,
+    http::HeaderMap,
     response::sse::{Event, Sse},
 };
 use futures::stream::Stream;
+use std::collections::VecDeque;
 use std::convert::Infallible;
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio_stream::wrappers::errors::BroadcastStreamRecvError;
 use tokio_stream::wrappers::BroadcastStream;
 use tokio_stream::StreamExt;
 
-/// SSE event types sent to clients
-#[derive(Debug, Clone, serde::Serialize)]
-#[serde(tag = "type", rename_all = "snake_case")]
-pub enum SseEvent {
-    /// Heartbeat to keep connection alive
-    Heartbeat { timestamp: String },
-    /// New session file detected
-    SessionNew {
-        project_id: String,
-        file_path: String,
-        file_name: String,
-    },
-    /// Session file changed (grew)
-    SessionChanged {
-        session_id: String,
-        file_path: String,
-        previous_size: u64,
-        new_size: u64,
-    },
-    /// Session parsing completed
-    SessionParsed {
-        session_id: String,
-        message_count: usize,
-    },
-    /// Watcher error
-    WatcherError { file_path: String, error: String },
-    // AI Events
-    /// Title generation started
-    AiTitleStart { session_id: String },
-    /// Title generation completed
-    AiTitleComplete { session_id: String, title: String },
-    /// Title generation failed
-    AiTitleError { session_id: String, error: String },
-    /// Memory extraction started
-    AiMemoryStart { session_id: String },
-    /// Memory extraction completed
-    AiMemoryComplete { session_id: String, count: usize },
-    /// Memory extraction failed
-    AiMemoryError { session_id: String, error: String },
-    /// Skill extraction started
-    AiSkillStart { session_id: String },
-    /// Skill extraction completed
-    AiSkillComplete { session_id: String, count: usize },
-    /// Skill extraction failed
-    AiSkillError { session_id: String, error: String },
-    /// Marker detection started
-    AiMarkerStart { session_id: String },
-    /// Marker detection completed
-    AiMarkerComplete { session_id: String, count: usize },
-    /// Marker detection failed
-    AiMarkerError { session_id: String, error: String },
-    /// Export generation started
-    AiExportStart { session_id: String, format: String },
-    /// Export generation completed
-    AiExportComplete {
-        session_id: String,
-        format: String,
-        content: String,
-        provider: String,
-        generation_time_ms: u64,
-    },
-    /// Export generation failed
-    AiExportError {
-        session_id: String,
-        format: String,
-        error: String,
-    },
-    /// Memory ranking started
-    RankingStart { project_id: String },
-    /// Memory ranking completed
-    RankingComplete {
-        project_id: String,
-        promoted: usize,
-        demoted: usize,
-        removed: usize,
-    },
-    /// Memory ranking error
-    RankingError { project_id: String, error: String },
-    /// Scheduler task started
-    SchedulerTaskStart {
-        task_name: String,
-        project_id: String,
-    },
-    /// Scheduler task completed
-    SchedulerTaskComplete {
-        task_name: String,
-        project_id: String,
-        detail: String,
-    },
-    /// Scheduler task error
-    SchedulerTaskError {
-        task_name: String,
-        project_id: String,
-        error: String,
-    },
+/// A broadcast event with its stream-wide id, ready to send
+#[derive(Debug, Clone)]
+pub struct LoggedEvent {
+    pub id: u64,
+    event_type: &'static str,
+    data: String,
 }
 
-impl From<WatcherEvent> for SseEvent {
-    fn from(event: WatcherEvent) -> Self {
-        match event {
-            WatcherEvent::NewSession {
-                project_id,
-                file_path,
-                file_name,
-            } => SseEvent::SessionNew {
-                project_id,
-                file_path,
-                file_name,
-            },
-            WatcherEvent::SessionChanged {
-                session_id,
-                file_path,
-                previous_size,
-                new_size,
-            } => SseEvent::SessionChanged {
-                session_id,
-                file_path,
-                previous_size,
-                new_size,
-            },
-            WatcherEvent::SessionParsed {
-                session_id,
-                message_count,
-            } => SseEvent::SessionParsed {
-                session_id,
-                message_count,
-            },
-            WatcherEvent::Error { file_path, error } => SseEvent::WatcherError { file_path, error },
-            WatcherEvent::RankingStart { project_id } => SseEvent::RankingStart { project_id },
-            WatcherEvent::RankingComplete {
-                project_id,
-                promoted,
-                demoted,
-                removed,
-            } => SseEvent::RankingComplete {
-                project_id,
-                promoted,
-                demoted,
-                removed,
-            },
-            WatcherEvent::RankingError { project_id, error } => {
-                SseEvent::RankingError { project_id, error }
-            }
-            WatcherEvent::SchedulerTaskStart {
-                task_name,
-                project_id,
-            } => SseEvent::SchedulerTaskStart {
-                task_name,
-                project_id,
-            },
-            WatcherEvent::SchedulerTaskComplete {
-                task_name,
-                project_id,
-                detail,
-            } => SseEvent::SchedulerTaskComplete {
-                task_name,
-                project_id,
-                detail,
-            },
-            WatcherEvent::SchedulerTaskError {
-                task_name,
-                project_id,
-                error,
-            } => SseEvent::SchedulerTaskError {
-                task_name,
-                project_id,
-                error,
-            },
-        }
+impl LoggedEvent {
+    fn to_sse(&self) -> Event {
+        Event::default()
+            .id(self.id.to_string())
+            .event(self.event_type)
+            .data(&self.data)
     }
 }
 
-impl From<AiEvent> for SseEvent {
-    fn from(event: AiEvent) -> Self {
-        match event {
-            AiEvent::TitleStart { session_id } => SseEvent::AiTitleStart { session_id },
-            AiEvent::TitleComplete { session_id, title } => {
-                SseEvent::AiTitleComplete { session_id, title }
-            }
-            AiEvent::TitleError { session_id, error } => {
-                SseEvent::AiTitleError { session_id, error }
-            }
-            AiEvent::MemoryStart { session_id } => SseEvent::AiMemoryStart { session_id },
-            AiEvent::MemoryComplete { session_id, count } => {
-                SseEvent::AiMemoryComplete { session_id, count }
-            }
-            AiEvent::MemoryError { session_id, error } => {
-                SseEvent::AiMemoryError { session_id, error }
-            }
-            AiEvent::SkillStart { session_id } => SseEvent::AiSkillStart { session_id },
-            AiEvent::SkillComplete { session_id, count } => {
-                SseEvent::AiSkillComplete { session_id, count }
-            }
-            AiEvent::SkillError { session_id, error } => {
-                SseEvent::AiSkillError { session_id, error }
-            }
-            AiEvent::MarkerStart { session_id } => SseEvent::AiMarkerStart { session_id },
-            AiEvent::MarkerComplete { session_id, count } => {
-                SseEvent::AiMarkerComplete { session_id, count }
-            }
-            AiEvent::MarkerError { session_id, error } => {
-                SseEvent::AiMarkerError { session_id, error }
-            }
-            AiEvent::ExportStart { session_id, format } => {
-                SseEvent::AiExportStart { session_id, format }
+struct EventLogInner {
+    next_id: u64,
+    buffer: VecDeque<LoggedEvent>,
+    capacity: usize,
+}
+
+/// Numbers watcher and AI events and keeps the most recent ones, so clients
+/// reconnecting with `Last-Event-ID` can replay what they missed.
+///
+/// Ids start at the server's start time in microseconds since the Unix epoch,
+/// so they keep increasing across restarts (unless events average more than
+/// one per microsecond).
+#[derive(Clone)]
+pub struct EventLog {
+    inner: Arc<Mutex<EventLogInner>>,
+    tx: broadcast::Sender<LoggedEvent>,
+}
+
+impl EventLog {
+    /// Keep up to `capacity` events for replay (0 disables replay)
+    pub fn new(capacity: usize) -> Self {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_micros() as u64)
+            .unwrap_or(0);
+        Self::with_first_id(capacity, now.max(1))
+    }
+
+    /// Like `new`, numbering events from `first_id`
+    pub(crate) fn with_first_id(capacity: usize, first_id: u64) -> Self {
+        let (tx, _) = broadcast::channel(256);
+        Self {
+            inner: Arc::new(Mutex::new(EventLogInner {
+                next_id: first_id,
+                buffer: VecDeque::with_capacity(capacity),
+                capacity,
+            })),
+            tx,
+        }
+    }
+
+    /// Assign the next id, buffer the event, and send it to live streams
+    fn record(&self, event: SseEvent) {
+        let mut inner = self.inner.lock().unwrap();
+        let logged = LoggedEvent {
+            id: inner.next_id,
+            event_type: get_event_type(&event),
+            data: serde_json::to_string(&event).unwrap_or_default(),
+        };
+        inner.next_id += 1;
+        if inner.capacity > 0 {
+            if inner.buffer.len() == inner.capacity {
+                inner.buffer.pop_front();
             }
-            AiEvent::ExportComplete {
-                session_id,
-                format,
-                content,
-                provider,
-                generation_time_ms,
-            } => SseEvent::AiExportComplete {
-                session_id,
-                format,
-                content,
-                provider,
-                generation_time_ms,
-            },
-            AiEvent::ExportError {
-                session_id,
-                format,
-                error,
-            } => SseEvent::AiExportError {
-                session_id,
-                format,
-                error,
-            },
+            inner.buffer.push_back(logged.clone());
         }
+        // Sent under the lock so a subscriber sees each event exactly once:
+        // either in its replay snapshot or on the live channel
+        let _ = self.tx.send(logged);
+    }
+
+    /// Buffered events after `last_id`, whether events after `last_id` were
+    /// lost, and a receiver for everything newer.
+    ///
+    /// Events are lost when they have already left the buffer or were sent
+    /// before a restart. An id ahead of ours replays the whole buffer.
+    pub fn subscribe_since(
+        &self,
+        last_id: Option<u64>,
+    ) -> (Vec<LoggedEvent>, bool, broadcast::Receiver<LoggedEvent>) {
+        let inner = self.inner.lock().unwrap();
+        let rx = self.tx.subscribe();
+        let Some(last_id) = last_id else {
+            return (Vec::new(), false, rx);
+        };
+        let last_id = if last_id >= inner.next_id { 0 } else { last_id };
+        // Oldest id a client could still be given
+        let oldest = inner.buffer.front().map_or(inner.next_id, |e| e.id);
+        let missed = last_id + 1 < oldest;
+        let replay = inner
+            .buffer
+            .iter()
+            .filter(|e| e.id > last_id)
+            .cloned()
+            .collect();
+        (replay, missed, rx)
     }
-}
 
-/// Get the SSE event type name
-fn get_event_type(event: &SseEvent) -> &'static str {
-    match event {
-        SseEvent::Heartbeat { .. } => "heartbeat",
-        SseEvent::SessionNew { .. } => "session:new",
-        SseEvent::SessionChanged { .. } => "session:changed",
-        SseEvent::SessionParsed { .. } => "session:parsed",
-        SseEvent::WatcherError { .. } => "watcher:error",
-        // AI events
-        SseEvent::AiTitleStart { .. } => "ai:title:start",
-        SseEvent::AiTitleComplete { .. } => "ai:title:complete",
-        SseEvent::AiTitleError { .. } => "ai:title:error",
-        SseEvent::AiMemoryStart { .. } => "ai:memory:start",
-        SseEvent::AiMemoryComplete { .. } => "ai:memory:complete",
-        SseEvent::AiMemoryError { .. } => "ai:memory:error",
-        SseEvent::AiSkillStart { .. } => "ai:skill:start",
-        SseEvent::AiSkillComplete { .. } => "ai:skill:complete",
-        SseEvent::AiSkillError { .. } => "ai:skill:error",
-        SseEvent::AiMarkerStart { .. } => "ai:markers:start",
-        SseEvent::AiMarkerComplete { .. } => "ai:markers:complete",
-        SseEvent::AiMarkerError { .. } => "ai:markers:error",
-        // Ranking events
-        SseEvent::RankingStart { .. } => "ai:ranking:start",
-        SseEvent::RankingComplete { .. } => "ai:ranking:complete",
-        SseEvent::RankingError { .. } => "ai:ranking:error",
-        // Scheduler events
-        SseEvent::SchedulerTaskStart { .. } => "scheduler:start",
-        SseEvent::SchedulerTaskComplete { .. } => "scheduler:complete",
-        SseEvent::SchedulerTaskError { .. } => "scheduler:error",
-        // Export events
-        SseEvent::AiExportStart { .. } => "ai:export:start",
-        SseEvent::AiExportComplete { .. } => "ai:export:complete",
-        SseEvent::AiExportError { .. } => "ai:export:error",
+    /// Record watcher and AI events until both channels close
+    pub fn spawn_forwarder(
+        &self,
+        event_tx: &broadcast::Sender<WatcherEvent>,
+        ai_event_tx: &broadcast::Sender<AiEvent>,
+    ) -> tokio::task::JoinHandle<()> {
+        let log = self.clone();
+        // Lagged errors are skipped
+        let watcher = BroadcastStream::new(event_tx.subscribe())
+            .filter_map(|result| result.ok().map(SseEvent::from));
+        let ai = BroadcastStream::new(ai_event_tx.subscribe())
+            .filter_map(|result| result.ok().map(SseEvent::from));
+        let mut events = futures::stream::select(watcher, ai);
+
+        tokio::spawn(async move {
+            while let Some(event) = events.next().await {
+                log.record(event);
+            }
+        })
     }
 }
 
 /// SSE events handler
+///
+/// Every event carries an `id`; reconnecting with a `Last-Event-ID` header
+/// replays buffered events after that id before the live stream resumes.
+/// Events that can't be delivered, because they left the replay buffer or the
+/// client fell behind the live stream, are reported with `events:missed`.
 pub async fn events_handler(
     State(state): State<AppState>,
+    headers: HeaderMap,
 ) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
-    // Subscribe to the watcher broadcast channel
-    let watcher_rx = state.event_tx.subscribe();
-
-    // Subscribe to the AI broadcast channel
-    let ai_rx = state.ai_event_tx.subscribe();
+    let last_id = headers
+        .get("last-event-id")
+        .and_then(|h| h.to_str().ok())
+        .and_then(|id| id.trim().parse::<u64>().ok());
+    let (replay, missed, rx) = state.event_log.subscribe_since(last_id);
 
-    // Create stream from watcher broadcast receiver
-    // Filter out events for untracked sessions (project_id starting with "watch_")
-    let watcher_stream = BroadcastStream::new(watcher_rx).filter_map(|result| {
-        match result {
-            Ok(watcher_event) => {
-                // Skip events for untracked sessions (temp directories, etc.)
-                if let WatcherEvent::NewSession { ref project_id, .. } = watcher_event {
-                    if project_id.starts_with("watch_") {
-                        return None;
-                    }
-                }
-                let sse_event: SseEvent = watcher_event.into();
-                let event_type = get_event_type(&sse_event);
-                let data = serde_json::to_string(&sse_event).unwrap_or_default();
-                Some(Ok(Event::default().event(event_type).data(data)))
-            }
-            Err(_) => None, // Lagged, skip
-        }
-    });
-
-    // Create stream from AI broadcast receiver
-    let ai_stream = BroadcastStream::new(ai_rx).filter_map(|result| {
-        match result {
-            Ok(ai_event) => {
-                let sse_event: SseEvent = ai_event.into();
-                let event_type = get_event_type(&sse_event);
-                let data = serde_json::to_string(&sse_event).unwrap_or_default();
-                Some(Ok(Event::default().event(event_type).data(data)))
-            }
-            Err(_) => None, // Lagged, skip
-        }
+    let missed_event = missed.then(|| events_missed(None));
+    let replay_stream = tokio_stream::iter(
+        missed_event
+            .into_iter()
+            .chain(replay.into_iter().map(|event| event.to_sse())),
+    )
+    .map(Ok::<_, Infallible>);
+    let live_stream = BroadcastStream::new(rx).map(|result| {
+        Ok(match result {
+            Ok(event) => event.to_sse(),
+            Err(BroadcastStreamRecvError::Lagged(count)) => events_missed(Some(count)),
+        })
     });
 
-    // Merge watcher and AI streams
-    let broadcast_stream = futures::stream::select(watcher_stream, ai_stream);
-
     // Create heartbeat stream
     let heartbeat_stream =
         tokio_stream::wrappers::IntervalStream::new(tokio::time::interval(Duration::from_secs(30)))
@@ -339,8 +189,13 @@ pub async fn events_handler(
                     .data(serde_json::to_string(&event).unwrap_or_default()))
             });
 
-    // Merge both streams
-    let merged_stream = futures::stream::select(broadcast_stream, heartbeat_stream);
+    // Replay first, then merge live events with heartbeats, ending when the
+    // server shuts down so graceful shutdown isn't held open by clients that
+    // never disconnect
+    let merged_stream = futures::StreamExt::take_until(
+        replay_stream.chain(futures::stream::select(live_stream, heartbeat_stream)),
+        state.shutdown.cancelled_owned(),
+    );
 
     Sse::new(merged_stream).keep_alive(
         axum::response::sse::KeepAlive::new()
@@ -348,3 +203,62 @@ pub async fn events_handler(
             .text("keep-alive"),
     )
 }
+
+/// `events:missed` for `count` undelivered events (None when unknown)
+fn events_missed(count: Option<u64>) -> Event {
+    let event = SseEvent::EventsMissed { count };
+    Event::default()
+        .event(get_event_type(&event))
+        .data(serde_json::to_string(&event).unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn removed(session_id: &str) -> SseEvent {
+        SseEvent::SessionRemoved {
+            session_id: session_id.to_string(),
+        }
+    }
+
+    fn ids(events: &[LoggedEvent]) -> Vec<u64> {
+        events.iter().map(|e| e.id).collect()
+    }
+
+    #[test]
+    fn test_ids_continue_across_restarts() {
+        let before = EventLog::new(4);
+        before.record(removed("s1"));
+        let (replay, _, _) = before.subscribe_since(Some(0));
+        let last_id = replay[0].id;
+
+        // A restart takes longer than this
+        std::thread::sleep(Duration::from_millis(2));
+        let after = EventLog::new(4);
+        after.record(removed("s2"));
+        let (replay, missed, _) = after.subscribe_since(Some(last_id));
+        assert!(replay[0].id > last_id);
+        // Whatever was sent after `last_id` before the restart is gone
+        assert!(missed);
+    }
+
+    #[test]
+    fn test_overrun_buffer_reports_missed_events() {
+        let log = EventLog::with_first_id(2, 1);
+        for session_id in ["s1", "s2", "s3", "s4"] {
+            log.record(removed(session_id));
+        }
+
+        // 3 and 4 are buffered; 2 was dropped
+        let (replay, missed, _) = log.subscribe_since(Some(1));
+        assert_eq!((ids(&replay), missed), (vec![3, 4], true));
+        let (replay, missed, _) = log.subscribe_since(Some(2));
+        assert_eq!((ids(&replay), missed), (vec![3, 4], false));
+        let (replay, missed, _) = log.subscribe_since(Some(4));
+        assert_eq!((ids(&replay), missed), (vec![], false));
+        // Not connected before: nothing to replay or miss
+        let (replay, missed, _) = log.subscribe_since(None);
+        assert_eq!((ids(&replay), missed), (vec![], false));
+    }
+}
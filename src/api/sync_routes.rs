@@ -0,0 +1,347 @@
+//! Delta sync route handlers
+//!
+//! Lets a client that caches yocore data (Desktop, peers) fetch only what changed
+//! since its last sync. DB mode only.
+
+use super::AppState;
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::Deserialize;
+
+/// How far the returned cursor trails the sync time. Writes that took their
+/// timestamp just before the sync but committed after it are picked up next time;
+/// clients should apply changes idempotently.
+const SYNC_OVERLAP: chrono::Duration = chrono::Duration::seconds(2);
+
+#[derive(Debug, Deserialize)]
+pub struct SyncChangesQuery {
+    /// RFC 3339 cursor from a previous sync; omit for a full sync
+    pub since: Option<String>,
+}
+
+/// GET /api/sync/changes?since=<rfc3339>
+/// Sessions (by `indexed_at`), memories and skills (by `updated_at`) changed
+/// after `since`, tombstones for rows deleted (or sessions soft-deleted) after
+/// it, and the next cursor.
+pub async fn get_changes(
+    State(state): State<AppState>,
+    Query(query): Query<SyncChangesQuery>,
+) -> impl IntoResponse {
+    let Some(db) = state.db.clone() else {
+        return (
+            StatusCode::NOT_IMPLEMENTED,
+            Json(serde_json::json!({ "error": "Not available in ephemeral mode" })),
+        )
+            .into_response();
+    };
+
+    let since = match query.since.as_deref().map(DateTime::parse_from_rfc3339) {
+        Some(Ok(since)) => since.with_timezone(&Utc),
+        Some(Err(_)) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({ "error": "since must be an RFC 3339 timestamp" })),
+            )
+                .into_response();
+        }
+        None => DateTime::<Utc>::UNIX_EPOCH,
+    };
+    // Taken before reading so nothing written during the sync is skipped
+    let cursor = (Utc::now() - SYNC_OVERLAP).to_rfc3339_opts(SecondsFormat::Millis, true);
+    let since = since.to_rfc3339_opts(SecondsFormat::Millis, true);
+
+    let result = db
+        .with_read_conn(move |conn| {
+            // julianday() compares timestamps regardless of offset style or precision
+            let mut stmt = conn.prepare(
+                "SELECT id, project_id, file_path, title, ai_tool, message_count, duration_ms,
+                        has_code, has_errors, is_hidden, parent_session_id, created_at, indexed_at
+                 FROM sessions
+                 WHERE julianday(indexed_at) > julianday(?1) AND deleted_at IS NULL
+                 ORDER BY indexed_at",
+            )?;
+            let sessions: Vec<serde_json::Value> = stmt
+                .query_map([&since], |row| {
+                    Ok(serde_json::json!({
+                        "id": row.get::<_, String>(0)?,
+                        "project_id": row.get::<_, String>(1)?,
+                        "file_path": row.get::<_, String>(2)?,
+                        "title": row.get::<_, Option<String>>(3)?,
+                        "ai_tool": row.get::<_, String>(4)?,
+                        "message_count": row.get::<_, i64>(5)?,
+                        "duration_ms": row.get::<_, Option<i64>>(6)?,
+                        "has_code": row.get::<_, bool>(7)?,
+                        "has_errors": row.get::<_, bool>(8)?,
+                        "is_hidden": row.get::<_, bool>(9)?,
+                        "parent_session_id": row.get::<_, Option<String>>(10)?,
+                        "created_at": row.get::<_, String>(11)?,
+                        "indexed_at": row.get::<_, String>(12)?,
+                    }))
+                })?
+                .collect::<Result<_, _>>()?;
+
+            let mut stmt = conn.prepare(
+                "SELECT id, project_id, session_id, memory_type, title, content,
+                        context, tags, confidence, is_validated, state, extracted_at, updated_at
+                 FROM memories
+                 WHERE julianday(updated_at) > julianday(?1) AND state != 'removed'
+                 ORDER BY updated_at",
+            )?;
+            let memories: Vec<serde_json::Value> = stmt
+                .query_map([&since], |row| {
+                    Ok(serde_json::json!({
+                        "id": row.get::<_, i64>(0)?,
+                        "project_id": row.get::<_, String>(1)?,
+                        "session_id": row.get::<_, String>(2)?,
+                        "memory_type": row.get::<_, String>(3)?,
+                        "title": row.get::<_, String>(4)?,
+                        "content": row.get::<_, String>(5)?,
+                        "context": row.get::<_, Option<String>>(6)?,
+                        "tags": row.get::<_, String>(7)?,
+                        "confidence": row.get::<_, f64>(8)?,
+                        "is_validated": row.get::<_, bool>(9)?,
+                        "state": row.get::<_, String>(10)?,
+                        "extracted_at": row.get::<_, String>(11)?,
+                        "updated_at": row.get::<_, String>(12)?,
+                    }))
+                })?
+                .collect::<Result<_, _>>()?;
+
+            let mut stmt = conn.prepare(
+                "SELECT id, project_id, session_id, name, description, steps, confidence,
+                        extracted_at, updated_at
+                 FROM skills WHERE julianday(updated_at) > julianday(?1)
+                 ORDER BY updated_at",
+            )?;
+            let skills: Vec<serde_json::Value> = stmt
+                .query_map([&since], |row| {
+                    Ok(serde_json::json!({
+                        "id": row.get::<_, i64>(0)?,
+                        "project_id": row.get::<_, String>(1)?,
+                        "session_id": row.get::<_, String>(2)?,
+                        "name": row.get::<_, String>(3)?,
+                        "description": row.get::<_, String>(4)?,
+                        "steps": row.get::<_, String>(5)?,
+                        "confidence": row.get::<_, f64>(6)?,
+                        "extracted_at": row.get::<_, String>(7)?,
+                        "updated_at": row.get::<_, String>(8)?,
+                    }))
+                })?
+                .collect::<Result<_, _>>()?;
+
+            let mut stmt = conn.prepare(
+                "SELECT entity_type, entity_id, project_id, deleted_at
+                 FROM tombstones WHERE julianday(deleted_at) > julianday(?1)
+                 ORDER BY id",
+            )?;
+            let tombstones: Vec<serde_json::Value> = stmt
+                .query_map([&since], |row| {
+                    Ok(serde_json::json!({
+                        "entity_type": row.get::<_, String>(0)?,
+                        "entity_id": row.get::<_, String>(1)?,
+                        "project_id": row.get::<_, Option<String>>(2)?,
+                        "deleted_at": row.get::<_, String>(3)?,
+                    }))
+                })?
+                .collect::<Result<_, _>>()?;
+
+            Ok::<_, rusqlite::Error>(serde_json::json!({
+                "sessions": sessions,
+                "memories": memories,
+                "skills": skills,
+                "tombstones": tombstones,
+                "cursor": cursor,
+            }))
+        })
+        .await;
+
+    match result {
+        Ok(changes) => Json(changes).into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({ "error": e.to_string() })),
+        )
+            .into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support;
+
+    async fn changes(state: &AppState, since: Option<&str>) -> axum::response::Response {
+        get_changes(
+            State(state.clone()),
+            Query(SyncChangesQuery {
+                since: since.map(String::from),
+            }),
+        )
+        .await
+        .into_response()
+    }
+
+    fn ids(body: &serde_json::Value, key: &str) -> Vec<String> {
+        body[key]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|v| match &v["id"] {
+                serde_json::Value::String(s) => s.clone(),
+                other => other.to_string(),
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_changes_since_cursor_include_tombstones() {
+        let tmp = tempfile::tempdir().unwrap();
+        let state = test_support::app_state(tmp.path());
+        state
+            .db
+            .as_ref()
+            .unwrap()
+            .with_conn(|conn| {
+                test_support::seed_project(conn);
+                conn.execute_batch(
+                    "INSERT INTO sessions (id, project_id, file_path, ai_tool, created_at, indexed_at) VALUES
+                        ('old', 'p1', '/tmp/proj/old.jsonl', 'Claude Code', '2024-01-01T00:00:00Z', '2024-01-01T00:00:00+00:00'),
+                        ('new', 'p1', '/tmp/proj/new.jsonl', 'Claude Code', '2024-01-01T00:00:00Z', '2024-06-01T00:00:00.123456+00:00');
+                     INSERT INTO memories (id, project_id, session_id, memory_type, title, content, extracted_at) VALUES
+                        (1, 'p1', 'old', 'fact', 'old fact', 'x', '2024-01-01T00:00:00Z'),
+                        (2, 'p1', 'new', 'fact', 'new fact', 'y', '2024-06-01T00:00:00Z'),
+                        (3, 'p1', 'old', 'fact', 'doomed fact', 'z', '2024-01-01T00:00:00Z');
+                     INSERT INTO skills (id, project_id, session_id, name, description, extracted_at) VALUES
+                        (1, 'p1', 'old', 'old skill', 'd', '2024-01-01T00:00:00Z'),
+                        (2, 'p1', 'new', 'new skill', 'd', '2024-06-01T00:00:00Z');
+                     UPDATE memories SET updated_at = extracted_at;
+                     UPDATE skills SET updated_at = extracted_at;
+                     UPDATE memories SET state = 'removed' WHERE id = 3;",
+                )
+            })
+            .await
+            .unwrap();
+
+        let resp = changes(&state, Some("2024-03-01T00:00:00Z")).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+
+        assert_eq!(ids(&body, "sessions"), vec!["new"]);
+        assert_eq!(ids(&body, "memories"), vec!["2"]);
+        assert_eq!(ids(&body, "skills"), vec!["2"]);
+        assert_eq!(body["memories"][0]["updated_at"], "2024-06-01T00:00:00Z");
+        let tombstones = body["tombstones"].as_array().unwrap();
+        assert_eq!(tombstones.len(), 1);
+        assert_eq!(tombstones[0]["entity_type"], "memory");
+        assert_eq!(tombstones[0]["entity_id"], "3");
+
+        // Syncing from the returned cursor picks up later edits and deletions
+        let cursor = body["cursor"].as_str().unwrap().to_string();
+        state
+            .db
+            .as_ref()
+            .unwrap()
+            .with_conn(|conn| {
+                conn.execute_batch(
+                    "DELETE FROM sessions WHERE id = 'new';
+                     UPDATE memories SET title = 'edited fact' WHERE id = 1;",
+                )
+            })
+            .await
+            .unwrap();
+        let resp = changes(&state, Some(&cursor)).await;
+        let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert!(body["sessions"].as_array().unwrap().is_empty());
+        assert_eq!(ids(&body, "memories"), vec!["1"]);
+        assert_eq!(body["memories"][0]["title"], "edited fact");
+        assert!(body["skills"].as_array().unwrap().is_empty());
+        let deleted: Vec<(&str, &str)> = body["tombstones"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|t| {
+                (
+                    t["entity_type"].as_str().unwrap(),
+                    t["entity_id"].as_str().unwrap(),
+                )
+            })
+            .collect();
+        // The session delete cascades to its memory and skill
+        assert!(deleted.contains(&("session", "new")));
+        assert!(deleted.contains(&("memory", "2")));
+        assert!(deleted.contains(&("skill", "2")));
+
+        // Soft deletes are reported too
+        let cursor = body["cursor"].as_str().unwrap().to_string();
+        state
+            .db
+            .as_ref()
+            .unwrap()
+            .with_conn(|conn| {
+                conn.execute(
+                    "UPDATE sessions SET deleted_at = '2024-07-01T00:00:00Z' WHERE id = 'old'",
+                    [],
+                )
+            })
+            .await
+            .unwrap();
+        let resp = changes(&state, Some(&cursor)).await;
+        let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        let tombstones = body["tombstones"].as_array().unwrap();
+        assert!(tombstones
+            .iter()
+            .any(|t| t["entity_type"] == "session" && t["entity_id"] == "old"));
+        let resp = changes(&state, None).await;
+        let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert!(ids(&body, "sessions").is_empty());
+
+        let resp = changes(&state, Some("yesterday")).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_changes_report_namespaced_session_rename() {
+        let tmp = tempfile::tempdir().unwrap();
+        let state = test_support::app_state(tmp.path());
+        let db = state.db.clone().unwrap();
+        db.with_conn(|conn| test_support::seed_session(conn, "abc"))
+            .await;
+
+        let migrated = crate::watcher::storage::db_namespace_session_ids(
+            &db,
+            std::path::Path::new("/tmp/proj"),
+        )
+        .await
+        .unwrap();
+        assert_eq!(migrated, 1);
+
+        let resp = changes(&state, Some("2024-03-01T00:00:00Z")).await;
+        let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(ids(&body, "sessions"), vec!["p1:abc"]);
+        let tombstones = body["tombstones"].as_array().unwrap();
+        assert_eq!(tombstones.len(), 1);
+        assert_eq!(tombstones[0]["entity_type"], "session");
+        assert_eq!(tombstones[0]["entity_id"], "abc");
+        assert_eq!(tombstones[0]["project_id"], "p1");
+    }
+}
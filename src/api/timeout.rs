@@ -0,0 +1,85 @@
+//! Request timeout middleware for API handlers
+
+use axum::{
+    body::Body,
+    extract::State,
+    http::{Request, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use std::time::Duration;
+
+/// Request timeout middleware
+///
+/// Drops the handler future and responds with 504 once `limit` elapses.
+/// Database work already handed to `spawn_blocking` keeps running until it
+/// finishes (writes) or is interrupted (reads), so connections are released.
+///
+/// If no limit is configured, all requests run to completion.
+pub async fn request_timeout(
+    State(limit): State<Option<Duration>>,
+    request: Request<Body>,
+    next: Next,
+) -> Response {
+    let Some(limit) = limit else {
+        return next.run(request).await;
+    };
+
+    match tokio::time::timeout(limit, next.run(request)).await {
+        Ok(response) => response,
+        Err(_) => {
+            tracing::warn!("Request timed out after {}ms", limit.as_millis());
+            (
+                StatusCode::GATEWAY_TIMEOUT,
+                Json(serde_json::json!({
+                    "error": format!("Request timed out after {}s", limit.as_secs())
+                })),
+            )
+                .into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{middleware, routing::get, Router};
+    use tower::ServiceExt;
+
+    async fn slow() -> &'static str {
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        "done"
+    }
+
+    fn app(limit: Option<Duration>) -> Router {
+        Router::new()
+            .route("/slow", get(slow))
+            .layer(middleware::from_fn_with_state(limit, request_timeout))
+            .route("/exempt", get(slow))
+    }
+
+    async fn status(app: Router, uri: &str) -> StatusCode {
+        app.oneshot(Request::get(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap()
+            .status()
+    }
+
+    #[tokio::test]
+    async fn test_slow_handler_times_out() {
+        let app = app(Some(Duration::from_millis(50)));
+
+        assert_eq!(
+            status(app.clone(), "/slow").await,
+            StatusCode::GATEWAY_TIMEOUT
+        );
+        // Routes added after the layer are not bounded
+        assert_eq!(status(app, "/exempt").await, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_no_limit_runs_to_completion() {
+        assert_eq!(status(app(None), "/slow").await, StatusCode::OK);
+    }
+}
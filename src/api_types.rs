@@ -0,0 +1,105 @@
+//! Request types of the HTTP API
+//!
+//! Shared by the route handlers in `api::routes` and the typed client, so the
+//! client can be built without the server (`client` feature alone).
+
+use serde::{Deserialize, Serialize};
+
+/// `GET /api/projects`
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ListProjectsQuery {
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+/// `GET /api/sessions`
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ListSessionsQuery {
+    pub project_id: Option<String>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+    pub include_hidden: Option<bool>,
+    /// Opaque keyset cursor from a previous page's `next_cursor` (takes precedence over offset)
+    pub cursor: Option<String>,
+    /// Only sessions created at or after this RFC 3339 timestamp
+    pub created_after: Option<String>,
+    /// Only sessions created at or before this RFC 3339 timestamp
+    pub created_before: Option<String>,
+    /// Sort by field: "created_at" (default), "message_count", "duration_ms", "indexed_at"
+    pub sort_by: Option<String>,
+    /// Sort order: "asc" or "desc" (default)
+    pub sort_order: Option<String>,
+    /// Comma-separated tags; only sessions carrying all of them
+    pub tags: Option<String>,
+    /// List soft-deleted sessions instead of live ones
+    #[serde(default)]
+    pub deleted: bool,
+    /// Only sessions whose detected git branch is this one
+    pub branch: Option<String>,
+}
+
+/// `POST /api/search`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SearchRequest {
+    pub query: String,
+    pub project_id: Option<String>,
+    #[serde(rename = "type", default = "default_search_type")]
+    pub search_type: String,
+    pub limit: Option<i64>,
+    /// Filter by role: "all", "user", "assistant", "tool"
+    pub role: Option<String>,
+    /// Only return messages with code
+    pub has_code: Option<bool>,
+    /// Only return messages whose code is in this language (e.g. "rust", "python")
+    pub language: Option<String>,
+    /// Only return messages from this tool (e.g. "Bash")
+    pub tool_name: Option<String>,
+    /// Only return tool messages of this type: "use" or "result"
+    pub tool_type: Option<String>,
+    /// Share of the hybrid score given to vector similarity (0.0–1.0, default 0.5).
+    /// Only used with `"type": "hybrid"`.
+    pub vector_weight: Option<f64>,
+    /// Only messages from sessions created at or after this RFC 3339 timestamp
+    pub created_after: Option<String>,
+    /// Only messages from sessions created at or before this RFC 3339 timestamp
+    pub created_before: Option<String>,
+    /// Matches to skip, for paging through results
+    pub offset: Option<i64>,
+    /// Pass `query` to FTS5 as written, operators and all, instead of
+    /// sanitizing it. Invalid syntax is a `400`.
+    #[serde(default)]
+    pub raw: bool,
+}
+
+pub(crate) fn default_search_type() -> String {
+    "fulltext".to_string()
+}
+
+impl SearchRequest {
+    /// A full-text search for `query` with no filters
+    pub fn new(query: impl Into<String>) -> Self {
+        SearchRequest {
+            query: query.into(),
+            project_id: None,
+            search_type: default_search_type(),
+            limit: None,
+            role: None,
+            has_code: None,
+            language: None,
+            tool_name: None,
+            tool_type: None,
+            vector_weight: None,
+            created_after: None,
+            created_before: None,
+            offset: None,
+            raw: false,
+        }
+    }
+}
+
+/// `POST /api/ai/sessions/:id/title`
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct TitleGenerationRequest {
+    #[serde(default)]
+    pub force: bool,
+}
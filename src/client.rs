@@ -0,0 +1,284 @@
+//! Typed client for the HTTP API (`client` feature)
+//!
+//! For tools that talk to a running yocore over HTTP (Desktop, CLI) rather than
+//! embedding it. Requests reuse the route handlers' query and body types from
+//! [`crate::api_types`]; responses are decoded into the envelopes below. List
+//! items stay JSON objects, since their fields differ between DB and ephemeral
+//! storage. With `default-features = false`, only the client is built.
+//!
+//! ```ignore
+//! use yocore::api_types::SearchRequest;
+//! use yocore::client::YocoreClient;
+//!
+//! let client = YocoreClient::new("http://127.0.0.1:19420")?.with_api_key("secret");
+//! let results = client.search(&SearchRequest::new("deadlock")).await?;
+//! ```
+
+use crate::api_types::{
+    ListProjectsQuery, ListSessionsQuery, SearchRequest, TitleGenerationRequest,
+};
+use reqwest::{Method, RequestBuilder, Url};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use thiserror::Error;
+
+/// Client error
+#[derive(Error, Debug)]
+pub enum ClientError {
+    /// The base URL can't have API paths appended
+    #[error("Invalid base URL: {0}")]
+    InvalidUrl(String),
+
+    /// The request couldn't be sent or its response couldn't be decoded
+    #[error("HTTP error: {0}")]
+    Http(#[from] reqwest::Error),
+
+    /// The server answered with an error status
+    #[error("API error ({status}): {message}")]
+    Api { status: u16, message: String },
+}
+
+/// `GET /api/projects`
+#[derive(Debug, Deserialize)]
+pub struct ProjectList {
+    pub projects: Vec<serde_json::Value>,
+    pub total: i64,
+}
+
+/// `GET /api/sessions`
+#[derive(Debug, Deserialize)]
+pub struct SessionList {
+    pub sessions: Vec<serde_json::Value>,
+    pub total: i64,
+    /// Cursor for the next page, if there is one
+    pub next_cursor: Option<String>,
+}
+
+/// `POST /api/search`
+#[derive(Debug, Deserialize)]
+pub struct SearchResults {
+    pub results: Vec<serde_json::Value>,
+    /// Matches across all pages
+    pub total_count: i64,
+}
+
+/// Response of endpoints that start AI work in the background
+#[derive(Debug, Deserialize)]
+pub struct AiTaskStatus {
+    /// `started`, or `skipped` when there was nothing to do
+    pub status: String,
+    pub message: Option<String>,
+}
+
+/// Client for one yocore instance
+#[derive(Debug, Clone)]
+pub struct YocoreClient {
+    http: reqwest::Client,
+    base_url: Url,
+    api_key: Option<String>,
+}
+
+impl YocoreClient {
+    /// A client for the instance at `base_url`, e.g. `http://127.0.0.1:19420`
+    pub fn new(base_url: &str) -> Result<Self, ClientError> {
+        let base_url = Url::parse(base_url)
+            .map_err(|e| ClientError::InvalidUrl(format!("{base_url}: {e}")))?;
+        if base_url.cannot_be_a_base() {
+            return Err(ClientError::InvalidUrl(base_url.to_string()));
+        }
+        Ok(YocoreClient {
+            http: reqwest::Client::new(),
+            base_url,
+            api_key: None,
+        })
+    }
+
+    /// Send `key` as `Authorization: Bearer <key>` with every request
+    pub fn with_api_key(mut self, key: impl Into<String>) -> Self {
+        self.api_key = Some(key.into());
+        self
+    }
+
+    /// `GET /health`
+    pub async fn health(&self) -> Result<serde_json::Value, ClientError> {
+        self.send(self.request(Method::GET, &["health"])).await
+    }
+
+    /// `GET /api/projects`
+    pub async fn list_projects(
+        &self,
+        query: &ListProjectsQuery,
+    ) -> Result<ProjectList, ClientError> {
+        self.send(self.request(Method::GET, &["api", "projects"]).query(query))
+            .await
+    }
+
+    /// `GET /api/projects/:id`
+    pub async fn get_project(&self, id: &str) -> Result<serde_json::Value, ClientError> {
+        self.send(self.request(Method::GET, &["api", "projects", id]))
+            .await
+    }
+
+    /// `GET /api/sessions`
+    pub async fn list_sessions(
+        &self,
+        query: &ListSessionsQuery,
+    ) -> Result<SessionList, ClientError> {
+        self.send(self.request(Method::GET, &["api", "sessions"]).query(query))
+            .await
+    }
+
+    /// `GET /api/sessions/:id`
+    pub async fn get_session(&self, id: &str) -> Result<serde_json::Value, ClientError> {
+        self.send(self.request(Method::GET, &["api", "sessions", id]))
+            .await
+    }
+
+    /// `POST /api/search`
+    pub async fn search(&self, request: &SearchRequest) -> Result<SearchResults, ClientError> {
+        self.send(self.request(Method::POST, &["api", "search"]).json(request))
+            .await
+    }
+
+    /// `POST /api/ai/sessions/:id/title`; progress is reported over SSE
+    pub async fn trigger_title_generation(
+        &self,
+        session_id: &str,
+        request: &TitleGenerationRequest,
+    ) -> Result<AiTaskStatus, ClientError> {
+        self.send(
+            self.request(
+                Method::POST,
+                &["api", "ai", "sessions", session_id, "title"],
+            )
+            .json(request),
+        )
+        .await
+    }
+
+    /// A request to the base URL plus `segments`, each percent-encoded
+    fn request(&self, method: Method, segments: &[&str]) -> RequestBuilder {
+        let mut url = self.base_url.clone();
+        url.path_segments_mut()
+            .expect("checked in new()")
+            .pop_if_empty()
+            .extend(segments);
+        let request = self.http.request(method, url);
+        match &self.api_key {
+            Some(key) => request.bearer_auth(key),
+            None => request,
+        }
+    }
+
+    /// Send `request` and decode a successful response as `T`
+    async fn send<T: DeserializeOwned>(&self, request: RequestBuilder) -> Result<T, ClientError> {
+        let resp = request.send().await?;
+        let status = resp.status();
+        if status.is_success() {
+            return Ok(resp.json().await?);
+        }
+        // Error responses are `{"error": "..."}`
+        let body = resp.text().await.unwrap_or_default();
+        let message = serde_json::from_str::<serde_json::Value>(&body)
+            .ok()
+            .and_then(|v| v["error"].as_str().map(str::to_string))
+            .unwrap_or(body);
+        Err(ClientError::Api {
+            status: status.as_u16(),
+            message,
+        })
+    }
+}
+
+#[cfg(all(test, feature = "server"))]
+mod tests {
+    use super::*;
+    use crate::api::AppState;
+    use crate::config::{ApiKeyConfig, ApiKeyScope, ServerConfig};
+    use crate::test_support;
+
+    /// Serve a seeded database on a random port; returns its base URL
+    async fn start_server(tmp: &std::path::Path, api_keys: Vec<ApiKeyConfig>) -> String {
+        let state = AppState {
+            api_keys,
+            ..test_support::app_state(tmp)
+        };
+        state
+            .db
+            .as_ref()
+            .unwrap()
+            .with_conn(|conn| {
+                test_support::seed_session(conn, "s1");
+                conn.execute(
+                    "INSERT INTO session_messages (session_id, sequence_num, role, content_preview, search_content, timestamp)
+                     VALUES ('s1', 0, 'user', 'Why does the watcher deadlock?', 'Why does the watcher deadlock?', 'now')",
+                    [],
+                )
+                .unwrap();
+            })
+            .await;
+        let app = crate::api::create_router(state, &ServerConfig::default());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        url
+    }
+
+    #[tokio::test]
+    async fn test_list_projects_and_search() {
+        let tmp = tempfile::tempdir().unwrap();
+        let client = YocoreClient::new(&start_server(tmp.path(), Vec::new()).await).unwrap();
+
+        let projects = client
+            .list_projects(&ListProjectsQuery::default())
+            .await
+            .unwrap();
+        assert_eq!(projects.total, 1);
+        assert_eq!(projects.projects[0]["id"], "p1");
+
+        let results = client
+            .search(&SearchRequest::new("deadlock"))
+            .await
+            .unwrap();
+        assert_eq!(results.total_count, 1);
+        assert_eq!(results.results[0]["session_id"], "s1");
+
+        let err = client.get_session("missing").await.unwrap_err();
+        assert!(
+            matches!(err, ClientError::Api { status: 404, .. }),
+            "{:?}",
+            err
+        );
+    }
+
+    #[tokio::test]
+    async fn test_api_key_is_sent() {
+        let tmp = tempfile::tempdir().unwrap();
+        let url = start_server(
+            tmp.path(),
+            vec![ApiKeyConfig {
+                key: "s3cret".to_string(),
+                scope: ApiKeyScope::default(),
+            }],
+        )
+        .await;
+
+        let anonymous = YocoreClient::new(&url).unwrap();
+        let err = anonymous
+            .list_projects(&ListProjectsQuery::default())
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(err, ClientError::Api { status: 401, .. }),
+            "{:?}",
+            err
+        );
+
+        let client = anonymous.with_api_key("s3cret");
+        let projects = client
+            .list_projects(&ListProjectsQuery::default())
+            .await
+            .unwrap();
+        assert_eq!(projects.total, 1);
+    }
+}
@@ -28,16 +28,114 @@ impl Storage {
     }
 }
 
+/// ID format for newly created rows
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum IdType {
+    /// Random UUIDv4 (default)
+    #[default]
+    Uuid,
+    /// Lexicographically time-sortable ULID
+    Ulid,
+}
+
+/// What `POST /api/projects` does when a project already uses the folder
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum DuplicateProjectPolicy {
+    /// Reject with 409 Conflict (default)
+    #[default]
+    Conflict,
+    /// Update the existing project's fields and return it
+    Upsert,
+}
+
+/// Tokenizer for the full-text search indexes
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum FtsTokenizer {
+    /// Unicode words with English stemming (default)
+    #[default]
+    Porter,
+    /// Unicode words, no stemming
+    Unicode61,
+    /// Every three-character sequence: matches substrings such as part of an
+    /// identifier, but not terms shorter than three characters
+    Trigram,
+}
+
+impl FtsTokenizer {
+    /// The FTS5 `tokenize` option
+    pub fn spec(self) -> &'static str {
+        match self {
+            FtsTokenizer::Porter => "porter unicode61",
+            FtsTokenizer::Unicode61 => "unicode61",
+            FtsTokenizer::Trigram => "trigram",
+        }
+    }
+}
+
+/// What an API key is allowed to do
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ApiKeyScope {
+    /// Read-only: GET/HEAD requests only
+    Read,
+    /// Full access (default)
+    #[default]
+    Admin,
+}
+
+/// An API key entry under `[[server.api_keys]]`
+#[derive(Clone, Serialize, Deserialize, PartialEq)]
+pub struct ApiKeyConfig {
+    /// Bearer token value
+    pub key: String,
+
+    /// Access scope (default: admin)
+    #[serde(default)]
+    pub scope: ApiKeyScope,
+}
+
+impl std::fmt::Debug for ApiKeyConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ApiKeyConfig")
+            .field("key", &"<redacted>")
+            .field("scope", &self.scope)
+            .finish()
+    }
+}
+
+/// Which session ephemeral storage evicts when `max_sessions` is reached
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum EvictionPolicy {
+    /// Least recently accessed (default)
+    #[default]
+    Lru,
+    /// Least frequently accessed, oldest access breaking ties
+    Lfu,
+}
+
 /// Ephemeral storage limits (only used when storage = "ephemeral")
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EphemeralConfig {
-    /// Maximum sessions to keep in memory (LRU eviction)
+    /// Maximum sessions to keep in memory (evicted per `eviction_policy`)
     #[serde(default = "default_max_sessions")]
     pub max_sessions: usize,
 
+    /// Which session to evict once `max_sessions` is reached
+    #[serde(default)]
+    pub eviction_policy: EvictionPolicy,
+
     /// Maximum messages per session
     #[serde(default = "default_max_messages_per_session")]
     pub max_messages_per_session: usize,
+
+    /// Snapshot file written on graceful shutdown and restored on startup
+    /// (no snapshot when unset)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snapshot_path: Option<PathBuf>,
 }
 
 fn default_max_sessions() -> usize {
@@ -52,11 +150,169 @@ impl Default for EphemeralConfig {
     fn default() -> Self {
         EphemeralConfig {
             max_sessions: default_max_sessions(),
+            eviction_policy: EvictionPolicy::default(),
             max_messages_per_session: default_max_messages_per_session(),
+            snapshot_path: None,
+        }
+    }
+}
+
+/// SQLite database settings (only used when storage = "db")
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DatabaseConfig {
+    /// Number of read connections used by API queries
+    #[serde(default = "default_read_pool_size")]
+    pub read_pool_size: usize,
+
+    /// ID format for new project rows (and the namespaced session IDs derived from them).
+    /// Existing rows keep their IDs.
+    #[serde(default)]
+    pub id_type: IdType,
+
+    /// Directory that `POST /api/admin/backup` may write into (default: `{data_dir}/backups`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backup_dir: Option<PathBuf>,
+
+    /// Creating a project for a folder that already has one: "conflict" or "upsert"
+    #[serde(default)]
+    pub duplicate_projects: DuplicateProjectPolicy,
+
+    /// Keep an in-memory ANN (HNSW) index of memory embeddings for semantic search
+    /// instead of scanning every embedding
+    #[serde(default)]
+    pub memory_ann_index: bool,
+}
+
+fn default_read_pool_size() -> usize {
+    crate::db::DEFAULT_READ_POOL_SIZE
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        DatabaseConfig {
+            read_pool_size: default_read_pool_size(),
+            id_type: IdType::default(),
+            backup_dir: None,
+            duplicate_projects: DuplicateProjectPolicy::default(),
+            memory_ann_index: false,
+        }
+    }
+}
+
+/// MCP server limits (protect the assistant's context window)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct McpConfig {
+    /// Hard cap on memories returned by any MCP tool, whatever `limit` is requested
+    #[serde(default = "default_mcp_max_results")]
+    pub max_results: usize,
+
+    /// Memory contents longer than this (in characters) are truncated with a note
+    #[serde(default = "default_mcp_max_content_chars")]
+    pub max_content_chars: usize,
+}
+
+fn default_mcp_max_results() -> usize {
+    50
+}
+
+fn default_mcp_max_content_chars() -> usize {
+    2000
+}
+
+impl Default for McpConfig {
+    fn default() -> Self {
+        McpConfig {
+            max_results: default_mcp_max_results(),
+            max_content_chars: default_mcp_max_content_chars(),
         }
     }
 }
 
+/// Embedding model settings
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EmbeddingsConfig {
+    /// Directory holding `config.json`, `tokenizer.json`, and `model.safetensors`
+    /// for all-MiniLM-L6-v2. When set, the model loads from here instead of
+    /// being downloaded from HuggingFace Hub
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model_path: Option<PathBuf>,
+    /// Load the model in the background at startup instead of on the first
+    /// request that needs it
+    #[serde(default)]
+    pub preload: bool,
+}
+
+/// A webhook entry under `[[webhooks]]`
+#[derive(Clone, Serialize, Deserialize, PartialEq)]
+pub struct WebhookConfig {
+    /// URL events are POSTed to
+    pub url: String,
+
+    /// Event names to send, e.g. `session:parsed` (default: all events)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub events: Vec<String>,
+
+    /// Signs each body in the `X-Yocore-Signature` header when set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+}
+
+impl WebhookConfig {
+    /// Whether events named `event` are sent to this webhook
+    pub fn matches(&self, event: &str) -> bool {
+        self.events.is_empty() || self.events.iter().any(|e| e == event)
+    }
+}
+
+impl std::fmt::Debug for WebhookConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WebhookConfig")
+            .field("url", &self.url)
+            .field("events", &self.events)
+            .field("secret", &self.secret.as_ref().map(|_| "<redacted>"))
+            .finish()
+    }
+}
+
+/// AI export settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportConfig {
+    /// Characters each chunk repeats from the end of the previous one, so events
+    /// at a chunk boundary are seen whole by one of the two chunk prompts
+    #[serde(default = "default_chunk_overlap_chars")]
+    pub chunk_overlap_chars: usize,
+}
+
+fn default_chunk_overlap_chars() -> usize {
+    2000
+}
+
+impl Default for ExportConfig {
+    fn default() -> Self {
+        ExportConfig {
+            chunk_overlap_chars: default_chunk_overlap_chars(),
+        }
+    }
+}
+
+/// Full-text search settings
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SearchConfig {
+    /// Tokenizer for `session_messages_fts` and `memories_fts`. Changing it
+    /// rebuilds both indexes at the next startup.
+    #[serde(default)]
+    pub fts_tokenizer: FtsTokenizer,
+}
+
+/// Session parsing settings
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ParserConfig {
+    /// Estimate output tokens from the text of assistant messages that report
+    /// no usage; estimated counts are flagged `tokens_estimated`
+    #[serde(default)]
+    pub estimate_tokens: bool,
+}
+
 /// AI feature identifier for feature gating
 #[derive(Debug, Clone, Copy)]
 pub enum AiFeature {
@@ -64,6 +320,58 @@ pub enum AiFeature {
     MarkerDetection,
     MemoryExtraction,
     SkillsDiscovery,
+    IntentExtraction,
+}
+
+/// Values accepted for `ai.provider`
+pub const KNOWN_PROVIDERS: &[&str] = &["claude_code", "openclaw", "ollama", "openai"];
+
+/// `ai.provider`: a single provider, or a fallback chain tried in order until
+/// one succeeds
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ProviderChain {
+    Single(String),
+    Chain(Vec<String>),
+}
+
+impl ProviderChain {
+    /// Provider names in the order they're tried
+    pub fn names(&self) -> &[String] {
+        match self {
+            ProviderChain::Single(name) => std::slice::from_ref(name),
+            ProviderChain::Chain(names) => names,
+        }
+    }
+}
+
+impl From<&str> for ProviderChain {
+    fn from(name: &str) -> Self {
+        ProviderChain::Single(name.to_string())
+    }
+}
+
+/// A problem found by [`Config::validate`]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ConfigProblem {
+    /// Dotted path of the offending setting
+    pub key: String,
+    pub message: String,
+}
+
+impl ConfigProblem {
+    fn new(key: impl Into<String>, message: impl Into<String>) -> Self {
+        ConfigProblem {
+            key: key.into(),
+            message: message.into(),
+        }
+    }
+}
+
+impl std::fmt::Display for ConfigProblem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.key, self.message)
+    }
 }
 
 /// Main configuration structure
@@ -93,6 +401,34 @@ pub struct Config {
     #[serde(default)]
     pub ephemeral: EphemeralConfig,
 
+    /// SQLite database settings
+    #[serde(default)]
+    pub database: DatabaseConfig,
+
+    /// Full-text search settings
+    #[serde(default)]
+    pub search: SearchConfig,
+
+    /// MCP server limits
+    #[serde(default)]
+    pub mcp: McpConfig,
+
+    /// AI export settings
+    #[serde(default)]
+    pub export: ExportConfig,
+
+    /// Session parsing settings
+    #[serde(default)]
+    pub parser: ParserConfig,
+
+    /// Embedding model settings
+    #[serde(default)]
+    pub embeddings: EmbeddingsConfig,
+
+    /// Endpoints notified of watcher and AI events
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub webhooks: Vec<WebhookConfig>,
+
     /// Data directory (defaults to ~/.yolog)
     #[serde(default = "default_data_dir")]
     pub data_dir: PathBuf,
@@ -122,6 +458,11 @@ pub struct ServerConfig {
     #[serde(default)]
     pub api_key: Option<String>,
 
+    /// Additional API keys, each with its own scope. `api_key` (if set)
+    /// is treated as one more admin-scoped key.
+    #[serde(default)]
+    pub api_keys: Vec<ApiKeyConfig>,
+
     /// Enable mDNS/Bonjour service discovery on the local network.
     /// Auto-disabled when host is 127.0.0.1 (localhost-only).
     #[serde(default = "default_true")]
@@ -131,6 +472,79 @@ pub struct ServerConfig {
     /// If not set, uses "Yocore-{hostname}-{short_uuid}".
     #[serde(default)]
     pub instance_name: Option<String>,
+
+    /// Seconds to wait for in-flight requests on shutdown before closing them
+    #[serde(default = "default_shutdown_timeout_secs")]
+    pub shutdown_timeout_secs: u64,
+
+    /// Seconds to wait on shutdown for running AI tasks to finish before
+    /// cancelling them
+    #[serde(default = "default_shutdown_grace_secs")]
+    pub shutdown_grace_secs: u64,
+
+    /// Seconds an API request may run before it is aborted with 504 (0 = no limit).
+    /// SSE, backups, exports, and other long-running AI endpoints are exempt.
+    #[serde(default = "default_request_timeout_secs")]
+    pub request_timeout_secs: u64,
+
+    /// Recent SSE events kept for clients reconnecting with `Last-Event-ID` (0 = no replay)
+    #[serde(default = "default_sse_replay_buffer")]
+    pub sse_replay_buffer: usize,
+
+    /// Largest request body accepted, in bytes; bigger requests get 413
+    #[serde(default = "default_max_body_bytes")]
+    pub max_body_bytes: usize,
+
+    /// Largest session bundle `POST /api/admin/import` accepts, in bytes
+    #[serde(default = "default_max_import_bytes")]
+    pub max_import_bytes: usize,
+
+    /// Viewer mode: reject every API request that writes with 403 (whatever
+    /// the API key) and don't start the watcher, scheduler or AI recovery
+    #[serde(default)]
+    pub read_only: bool,
+
+    /// Per-client request rate limit (off by default)
+    #[serde(default)]
+    pub rate_limit: RateLimitConfig,
+}
+
+/// Token-bucket rate limit under `[server.rate_limit]`, applied per API key, or
+/// per client IP for requests without one
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct RateLimitConfig {
+    /// Sustained requests per minute each client may make (0 = no limit)
+    #[serde(default)]
+    pub requests_per_min: u32,
+
+    /// Requests a client may make in a burst before being held to the rate
+    /// (default: `requests_per_min`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub burst: Option<u32>,
+}
+
+fn default_shutdown_timeout_secs() -> u64 {
+    10
+}
+
+fn default_shutdown_grace_secs() -> u64 {
+    30
+}
+
+fn default_request_timeout_secs() -> u64 {
+    30
+}
+
+fn default_sse_replay_buffer() -> usize {
+    256
+}
+
+fn default_max_body_bytes() -> usize {
+    16 * 1024 * 1024
+}
+
+fn default_max_import_bytes() -> usize {
+    256 * 1024 * 1024
 }
 
 fn default_port() -> u16 {
@@ -142,6 +556,19 @@ fn default_host() -> String {
 }
 
 impl ServerConfig {
+    /// All accepted API keys: `api_key` (admin scope) followed by `api_keys`.
+    /// Empty means authentication is disabled.
+    pub fn effective_api_keys(&self) -> Vec<ApiKeyConfig> {
+        self.api_key
+            .iter()
+            .map(|key| ApiKeyConfig {
+                key: key.clone(),
+                scope: ApiKeyScope::Admin,
+            })
+            .chain(self.api_keys.iter().cloned())
+            .collect()
+    }
+
     /// Check if mDNS should be active based on host binding and config.
     /// Returns false for localhost-only bindings since there's nothing to discover.
     pub fn should_enable_mdns(&self) -> bool {
@@ -158,8 +585,17 @@ impl Default for ServerConfig {
             port: default_port(),
             host: default_host(),
             api_key: None,
+            api_keys: Vec::new(),
             mdns_enabled: true,
             instance_name: None,
+            shutdown_timeout_secs: default_shutdown_timeout_secs(),
+            shutdown_grace_secs: default_shutdown_grace_secs(),
+            request_timeout_secs: default_request_timeout_secs(),
+            sse_replay_buffer: default_sse_replay_buffer(),
+            max_body_bytes: default_max_body_bytes(),
+            max_import_bytes: default_max_import_bytes(),
+            read_only: false,
+            rate_limit: RateLimitConfig::default(),
         }
     }
 }
@@ -177,6 +613,36 @@ pub struct WatchConfig {
     /// Whether this watch path is enabled
     #[serde(default = "default_true")]
     pub enabled: bool,
+
+    /// Delete the session row when its file is removed from disk
+    /// (otherwise only a `SessionRemoved` event is emitted)
+    #[serde(default)]
+    pub delete_on_remove: bool,
+
+    /// Store session IDs as `{project_id}:{file_stem}` instead of the bare file stem.
+    /// Existing sessions under this path are migrated on startup. Even when off,
+    /// a stem that collides with another file's session is namespaced automatically.
+    #[serde(default)]
+    pub namespace_session_ids: bool,
+
+    /// Index sub-agent transcripts (`agent-*.jsonl`) and link them to their
+    /// parent session via `parent_session_id`
+    #[serde(default)]
+    pub parse_agents: bool,
+
+    /// Skip new session files whose content (blake3 hash) matches a session
+    /// already indexed from another path. DB mode only.
+    #[serde(default)]
+    pub dedupe_by_hash: bool,
+
+    /// Glob patterns (relative to `path`) a session file must match to be tracked.
+    /// Empty means all files are included.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub include: Vec<String>,
+
+    /// Glob patterns (relative to `path`) for files to ignore. Takes precedence over `include`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exclude: Vec<String>,
 }
 
 fn default_parser() -> String {
@@ -194,9 +660,19 @@ fn default_true() -> bool {
 /// are automatically skipped when `storage = "ephemeral"`.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AiConfig {
-    /// AI provider ("claude_code" for CLI mode). Required for any AI feature.
+    /// AI provider ("claude_code"/"openclaw" for CLI mode, "ollama" for a local
+    /// Ollama server, "openai" for an OpenAI-compatible API), or a list of them
+    /// tried in order. Required for any AI feature.
+    #[serde(default)]
+    pub provider: Option<ProviderChain>,
+
+    /// Ollama server settings, used when `provider = "ollama"`
+    #[serde(default)]
+    pub ollama: OllamaConfig,
+
+    /// OpenAI-compatible API settings, used when `provider = "openai"`
     #[serde(default)]
-    pub provider: Option<String>,
+    pub openai: OpenAiConfig,
 
     /// Generate session titles (works with both storage modes)
     #[serde(default = "default_true")]
@@ -214,6 +690,41 @@ pub struct AiConfig {
     #[serde(default = "default_true")]
     pub skills_discovery: bool,
 
+    /// Use AI for session intent extraction (requires storage = "db"). Off by
+    /// default: a heuristic extracts intents without AI either way
+    #[serde(default)]
+    pub intent_extraction: bool,
+
+    /// Repair slightly malformed JSON in AI output (trailing commas, raw newlines)
+    /// before giving up on memory/skill extraction
+    #[serde(default = "default_true")]
+    pub lenient_json: bool,
+
+    /// Maximum AI tasks (titles, memories, skills, markers) running at once
+    #[serde(default = "default_max_concurrent_tasks")]
+    pub max_concurrent_tasks: usize,
+
+    /// Run AI tasks on sessions the user has hidden (skipped by default)
+    #[serde(default)]
+    pub process_hidden_sessions: bool,
+
+    /// Messages a session needs before titles are generated for it and before
+    /// startup recovery picks it up
+    #[serde(default = "default_min_messages_for_processing")]
+    pub min_messages_for_processing: usize,
+
+    /// Patterns for marker detection when no AI provider is available
+    #[serde(
+        default = "default_marker_patterns",
+        skip_serializing_if = "is_default_marker_patterns"
+    )]
+    pub marker_patterns: Vec<MarkerPattern>,
+
+    /// Per-model token prices used for analytics cost estimates, keyed by the
+    /// model name recorded on session messages
+    #[serde(default, skip_serializing_if = "std::collections::HashMap::is_empty")]
+    pub model_pricing: std::collections::HashMap<String, ModelPricing>,
+
     // Legacy fields for backward compatibility — not serialized
     /// Deprecated: AI is now active when provider is set + any feature is on
     #[serde(default, skip_serializing)]
@@ -224,6 +735,164 @@ pub struct AiConfig {
     features: Option<LegacyAiFeatures>,
 }
 
+/// Ollama HTTP provider configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OllamaConfig {
+    /// Base URL of the Ollama server
+    #[serde(default = "default_ollama_base_url")]
+    pub base_url: String,
+
+    /// Model passed to `/api/chat`
+    #[serde(default = "default_ollama_model")]
+    pub model: String,
+}
+
+fn default_ollama_base_url() -> String {
+    "http://localhost:11434".to_string()
+}
+
+fn default_ollama_model() -> String {
+    "llama3.1".to_string()
+}
+
+impl Default for OllamaConfig {
+    fn default() -> Self {
+        OllamaConfig {
+            base_url: default_ollama_base_url(),
+            model: default_ollama_model(),
+        }
+    }
+}
+
+/// A regex that marks any message it matches, for marker detection without AI.
+/// Message search content and tool summaries are scanned.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MarkerPattern {
+    /// Marker label, e.g. "Deploy"
+    pub label: String,
+
+    /// Marker type: breakthrough, ship, decision, bug or stuck
+    pub marker_type: String,
+
+    /// Regular expression (Rust `regex` syntax)
+    pub pattern: String,
+}
+
+impl MarkerPattern {
+    fn new(label: &str, marker_type: &str, pattern: &str) -> Self {
+        MarkerPattern {
+            label: label.to_string(),
+            marker_type: marker_type.to_string(),
+            pattern: pattern.to_string(),
+        }
+    }
+}
+
+fn default_marker_patterns() -> Vec<MarkerPattern> {
+    vec![
+        MarkerPattern::new(
+            "Deploy",
+            "ship",
+            r"(?i)\b(deployed|deploying) (to|on)\b|\bdeploy(ment)? (succeeded|successful|complete)",
+        ),
+        MarkerPattern::new(
+            "Build failed",
+            "bug",
+            r"(?i)\bbuild failed\b|\bcould not compile\b|\bcompilation failed\b",
+        ),
+        MarkerPattern::new(
+            "Git revert",
+            "decision",
+            r"(?i)\bgit revert\b|\brevert(ed|ing)? (the )?(last )?commit\b",
+        ),
+        MarkerPattern::new(
+            "Tests passed",
+            "breakthrough",
+            r"(?i)\ball (\d+ )?tests (pass|passed|passing)\b|\btest result: ok\b|\b\d+ passed, 0 failed\b",
+        ),
+    ]
+}
+
+fn is_default_marker_patterns(patterns: &[MarkerPattern]) -> bool {
+    patterns == default_marker_patterns().as_slice()
+}
+
+/// USD price per million tokens for one model
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ModelPricing {
+    /// Uncached input tokens
+    #[serde(default)]
+    pub input: f64,
+
+    /// Output tokens
+    #[serde(default)]
+    pub output: f64,
+
+    /// Tokens read from the prompt cache
+    #[serde(default)]
+    pub cache_read: f64,
+
+    /// Tokens written to the prompt cache
+    #[serde(default)]
+    pub cache_creation: f64,
+}
+
+impl ModelPricing {
+    /// Cost in USD of the given token counts
+    pub fn cost(&self, input: i64, output: i64, cache_read: i64, cache_creation: i64) -> f64 {
+        (input as f64 * self.input
+            + output as f64 * self.output
+            + cache_read as f64 * self.cache_read
+            + cache_creation as f64 * self.cache_creation)
+            / 1_000_000.0
+    }
+}
+
+/// OpenAI-compatible HTTP provider configuration
+#[derive(Clone, Serialize, Deserialize)]
+pub struct OpenAiConfig {
+    /// API base URL (the part before `/chat/completions`)
+    #[serde(default = "default_openai_base_url")]
+    pub base_url: String,
+
+    /// Model passed to `/chat/completions`
+    #[serde(default = "default_openai_model")]
+    pub model: String,
+
+    /// API key; falls back to the `OPENAI_API_KEY` environment variable
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key: Option<String>,
+}
+
+fn default_openai_base_url() -> String {
+    "https://api.openai.com/v1".to_string()
+}
+
+fn default_openai_model() -> String {
+    "gpt-4o-mini".to_string()
+}
+
+impl Default for OpenAiConfig {
+    fn default() -> Self {
+        OpenAiConfig {
+            base_url: default_openai_base_url(),
+            model: default_openai_model(),
+            api_key: None,
+        }
+    }
+}
+
+// Keep the API key out of logs and debug output
+impl std::fmt::Debug for OpenAiConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OpenAiConfig")
+            .field("base_url", &self.base_url)
+            .field("model", &self.model)
+            .field("api_key", &self.api_key.as_ref().map(|_| "<redacted>"))
+            .finish()
+    }
+}
+
 /// Legacy [ai.features] section — only used for backward-compatible deserialization
 #[derive(Debug, Clone, Deserialize)]
 struct LegacyAiFeatures {
@@ -236,7 +905,7 @@ struct LegacyAiFeatures {
 }
 
 /// Background scheduler configuration
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SchedulerConfig {
     /// Memory ranking configuration
     #[serde(default)]
@@ -253,6 +922,36 @@ pub struct SchedulerConfig {
     /// Duplicate skill cleanup configuration
     #[serde(default)]
     pub skill_cleanup: SkillCleanupConfig,
+
+    /// Database maintenance (VACUUM/ANALYZE) configuration
+    #[serde(default)]
+    pub maintenance: DbMaintenanceConfig,
+
+    /// Soft-deleted session purge configuration
+    #[serde(default)]
+    pub session_purge: SessionPurgeConfig,
+
+    /// Runs kept per task in the `scheduler_runs` history table
+    #[serde(default = "default_history_retention")]
+    pub history_retention: usize,
+}
+
+fn default_history_retention() -> usize {
+    100
+}
+
+impl Default for SchedulerConfig {
+    fn default() -> Self {
+        Self {
+            ranking: RankingConfig::default(),
+            duplicate_cleanup: DuplicateCleanupConfig::default(),
+            embedding_refresh: EmbeddingRefreshConfig::default(),
+            skill_cleanup: SkillCleanupConfig::default(),
+            maintenance: DbMaintenanceConfig::default(),
+            session_purge: SessionPurgeConfig::default(),
+            history_retention: default_history_retention(),
+        }
+    }
 }
 
 /// Memory ranking configuration
@@ -264,15 +963,75 @@ pub struct RankingConfig {
     #[serde(default = "default_ranking_interval")]
     pub interval_hours: u32,
 
+    /// Cron expression for run times, overriding `interval_hours` when set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cron: Option<String>,
+
     /// Number of memories to process per batch
     #[serde(default = "default_batch_size")]
     pub batch_size: usize,
+
+    /// Score at or above which new memories are promoted to high
+    #[serde(default = "default_high_threshold")]
+    pub high_threshold: f64,
+
+    /// Minimum access count for promotion to high
+    #[serde(default = "default_min_access_for_high")]
+    pub min_access_for_high: i64,
+
+    /// Score below which memories are demoted to low
+    #[serde(default = "default_demotion_threshold")]
+    pub demotion_threshold: f64,
+
+    /// Score below which never-accessed memories are removed
+    #[serde(default = "default_removal_threshold")]
+    pub removal_threshold: f64,
+
+    /// Days without access before a high memory counts as stale
+    #[serde(default = "default_stale_days")]
+    pub stale_days: i64,
+
+    /// Days before a new low-score memory can be demoted
+    #[serde(default = "default_demotion_age_days")]
+    pub demotion_age_days: i64,
+
+    /// Days before a new never-accessed memory can be removed
+    #[serde(default = "default_removal_age_days")]
+    pub removal_age_days: i64,
 }
 
 fn default_ranking_interval() -> u32 {
     6 // Every 6 hours
 }
 
+fn default_high_threshold() -> f64 {
+    0.7
+}
+
+fn default_min_access_for_high() -> i64 {
+    3
+}
+
+fn default_demotion_threshold() -> f64 {
+    0.4
+}
+
+fn default_removal_threshold() -> f64 {
+    0.3
+}
+
+fn default_stale_days() -> i64 {
+    90
+}
+
+fn default_demotion_age_days() -> i64 {
+    14
+}
+
+fn default_removal_age_days() -> i64 {
+    30
+}
+
 fn default_batch_size() -> usize {
     500
 }
@@ -281,7 +1040,15 @@ impl Default for RankingConfig {
     fn default() -> Self {
         RankingConfig {
             interval_hours: default_ranking_interval(),
+            cron: None,
             batch_size: default_batch_size(),
+            high_threshold: default_high_threshold(),
+            min_access_for_high: default_min_access_for_high(),
+            demotion_threshold: default_demotion_threshold(),
+            removal_threshold: default_removal_threshold(),
+            stale_days: default_stale_days(),
+            demotion_age_days: default_demotion_age_days(),
+            removal_age_days: default_removal_age_days(),
         }
     }
 }
@@ -295,6 +1062,10 @@ pub struct DuplicateCleanupConfig {
     #[serde(default = "default_cleanup_interval")]
     pub interval_hours: u32,
 
+    /// Cron expression for run times, overriding `interval_hours` when set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cron: Option<String>,
+
     /// Similarity threshold for detecting duplicates (stricter than extraction's 0.65)
     #[serde(default = "default_similarity_threshold")]
     pub similarity_threshold: f64,
@@ -316,6 +1087,7 @@ impl Default for DuplicateCleanupConfig {
     fn default() -> Self {
         DuplicateCleanupConfig {
             interval_hours: default_cleanup_interval(),
+            cron: None,
             similarity_threshold: default_similarity_threshold(),
             batch_size: default_batch_size(),
         }
@@ -331,6 +1103,10 @@ pub struct EmbeddingRefreshConfig {
     #[serde(default = "default_refresh_interval")]
     pub interval_hours: u32,
 
+    /// Cron expression for run times, overriding `interval_hours` when set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cron: Option<String>,
+
     /// Number of memories to embed per batch (lower than ranking — embeddings are CPU-heavy)
     #[serde(default = "default_embed_batch_size")]
     pub batch_size: usize,
@@ -348,6 +1124,7 @@ impl Default for EmbeddingRefreshConfig {
     fn default() -> Self {
         EmbeddingRefreshConfig {
             interval_hours: default_refresh_interval(),
+            cron: None,
             batch_size: default_embed_batch_size(),
         }
     }
@@ -362,6 +1139,10 @@ pub struct SkillCleanupConfig {
     #[serde(default = "default_cleanup_interval")]
     pub interval_hours: u32,
 
+    /// Cron expression for run times, overriding `interval_hours` when set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cron: Option<String>,
+
     /// Similarity threshold for detecting duplicate skills (stricter than extraction's 0.70)
     #[serde(default = "default_skill_similarity_threshold")]
     pub similarity_threshold: f64,
@@ -379,20 +1160,103 @@ impl Default for SkillCleanupConfig {
     fn default() -> Self {
         SkillCleanupConfig {
             interval_hours: default_cleanup_interval(),
+            cron: None,
             similarity_threshold: default_skill_similarity_threshold(),
             batch_size: default_batch_size(),
         }
     }
 }
 
+/// Database maintenance configuration
+///
+/// Not tied to any AI feature — runs whenever `storage = "db"` and `enabled` is set.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DbMaintenanceConfig {
+    /// Run incremental_vacuum, ANALYZE and PRAGMA optimize periodically
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+
+    /// Interval in hours between maintenance runs
+    #[serde(default = "default_cleanup_interval")]
+    pub interval_hours: u32,
+
+    /// Cron expression for run times, overriding `interval_hours` when set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cron: Option<String>,
+}
+
+impl Default for DbMaintenanceConfig {
+    fn default() -> Self {
+        DbMaintenanceConfig {
+            enabled: true,
+            interval_hours: default_cleanup_interval(),
+            cron: None,
+        }
+    }
+}
+
+/// Purge of soft-deleted sessions
+///
+/// Not tied to any AI feature — runs whenever `storage = "db"` and `enabled` is set.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionPurgeConfig {
+    /// Permanently delete soft-deleted sessions periodically
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+
+    /// Days a deleted session can still be restored before it is purged
+    #[serde(default = "default_purge_retention_days")]
+    pub retention_days: u32,
+
+    /// Interval in hours between purge runs
+    #[serde(default = "default_cleanup_interval")]
+    pub interval_hours: u32,
+
+    /// Cron expression for run times, overriding `interval_hours` when set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cron: Option<String>,
+}
+
+fn default_purge_retention_days() -> u32 {
+    30
+}
+
+impl Default for SessionPurgeConfig {
+    fn default() -> Self {
+        SessionPurgeConfig {
+            enabled: true,
+            retention_days: default_purge_retention_days(),
+            interval_hours: default_cleanup_interval(),
+            cron: None,
+        }
+    }
+}
+
+fn default_max_concurrent_tasks() -> usize {
+    3
+}
+
+fn default_min_messages_for_processing() -> usize {
+    25
+}
+
 impl Default for AiConfig {
     fn default() -> Self {
         AiConfig {
             provider: None,
+            ollama: OllamaConfig::default(),
+            openai: OpenAiConfig::default(),
             title_generation: true,
             marker_detection: true,
             memory_extraction: true,
             skills_discovery: true,
+            intent_extraction: false,
+            lenient_json: true,
+            max_concurrent_tasks: default_max_concurrent_tasks(),
+            process_hidden_sessions: false,
+            min_messages_for_processing: default_min_messages_for_processing(),
+            marker_patterns: default_marker_patterns(),
+            model_pricing: std::collections::HashMap::new(),
             enabled: None,
             features: None,
         }
@@ -428,14 +1292,29 @@ impl Default for Config {
             ai: AiConfig::default(),
             scheduler: SchedulerConfig::default(),
             ephemeral: EphemeralConfig::default(),
+            database: DatabaseConfig::default(),
+            search: SearchConfig::default(),
+            mcp: McpConfig::default(),
+            export: ExportConfig::default(),
+            parser: ParserConfig::default(),
+            embeddings: EmbeddingsConfig::default(),
+            webhooks: vec![],
             data_dir: default_data_dir(),
         }
     }
 }
 
 impl Config {
-    /// Load configuration from a TOML file
+    /// Load configuration from a TOML file, rejecting it if [`validate`](Self::validate)
+    /// finds errors
     pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let config = Self::parse_file(path)?;
+        config.ensure_valid()?;
+        Ok(config)
+    }
+
+    /// Load configuration from a TOML file without validating it
+    pub fn parse_file<P: AsRef<Path>>(path: P) -> Result<Self> {
         let path = path.as_ref();
 
         // Expand ~ to home directory
@@ -463,19 +1342,208 @@ impl Config {
         Ok(config)
     }
 
+    /// Check the config for problems. Errors are settings that would leave the
+    /// server unable to work; warnings are settings that will be partly ignored.
+    /// Returns the warnings when there are no errors.
+    pub fn validate(&self) -> std::result::Result<Vec<ConfigProblem>, Vec<ConfigProblem>> {
+        let mut errors = Vec::new();
+        let mut warnings = Vec::new();
+
+        if self.ai.max_concurrent_tasks == 0 {
+            errors.push(ConfigProblem::new(
+                "ai.max_concurrent_tasks",
+                "must be at least 1",
+            ));
+        }
+        if self.ai.min_messages_for_processing == 0 {
+            errors.push(ConfigProblem::new(
+                "ai.min_messages_for_processing",
+                "must be at least 1",
+            ));
+        }
+        if let Some(chain) = &self.ai.provider {
+            if chain.names().is_empty() {
+                errors.push(ConfigProblem::new(
+                    "ai.provider",
+                    "must name at least one provider",
+                ));
+            }
+            for provider in chain.names() {
+                if !KNOWN_PROVIDERS.contains(&provider.as_str()) {
+                    errors.push(ConfigProblem::new(
+                        "ai.provider",
+                        format!(
+                            "unknown provider '{}' (expected one of: {})",
+                            provider,
+                            KNOWN_PROVIDERS.join(", ")
+                        ),
+                    ));
+                }
+            }
+        }
+
+        for (i, marker) in self.ai.marker_patterns.iter().enumerate() {
+            if crate::ai::marker::MarkerType::parse(&marker.marker_type).is_none() {
+                errors.push(ConfigProblem::new(
+                    format!("ai.marker_patterns[{}].marker_type", i),
+                    format!("unknown marker type '{}'", marker.marker_type),
+                ));
+            }
+            if let Err(e) = regex::Regex::new(&marker.pattern) {
+                errors.push(ConfigProblem::new(
+                    format!("ai.marker_patterns[{}].pattern", i),
+                    format!("invalid regex: {}", e),
+                ));
+            }
+        }
+
+        let schedules = [
+            (
+                "ranking",
+                self.scheduler.ranking.interval_hours,
+                &self.scheduler.ranking.cron,
+            ),
+            (
+                "duplicate_cleanup",
+                self.scheduler.duplicate_cleanup.interval_hours,
+                &self.scheduler.duplicate_cleanup.cron,
+            ),
+            (
+                "embedding_refresh",
+                self.scheduler.embedding_refresh.interval_hours,
+                &self.scheduler.embedding_refresh.cron,
+            ),
+            (
+                "skill_cleanup",
+                self.scheduler.skill_cleanup.interval_hours,
+                &self.scheduler.skill_cleanup.cron,
+            ),
+            (
+                "maintenance",
+                self.scheduler.maintenance.interval_hours,
+                &self.scheduler.maintenance.cron,
+            ),
+            (
+                "session_purge",
+                self.scheduler.session_purge.interval_hours,
+                &self.scheduler.session_purge.cron,
+            ),
+        ];
+        for (task, hours, cron) in schedules {
+            if hours == 0 {
+                errors.push(ConfigProblem::new(
+                    format!("scheduler.{}.interval_hours", task),
+                    "must be at least 1",
+                ));
+            }
+            if let Some(Err(e)) = cron.as_deref().map(crate::scheduler::parse_cron) {
+                errors.push(ConfigProblem::new(
+                    format!("scheduler.{}.cron", task),
+                    format!("invalid cron expression: {}", e),
+                ));
+            }
+        }
+
+        if self.ephemeral.max_sessions == 0 {
+            errors.push(ConfigProblem::new(
+                "ephemeral.max_sessions",
+                "must be at least 1",
+            ));
+        }
+        if self.ephemeral.max_messages_per_session == 0 {
+            errors.push(ConfigProblem::new(
+                "ephemeral.max_messages_per_session",
+                "must be at least 1",
+            ));
+        }
+
+        if self.server.rate_limit.requests_per_min > 0 && self.server.rate_limit.burst == Some(0) {
+            errors.push(ConfigProblem::new(
+                "server.rate_limit.burst",
+                "must be at least 1",
+            ));
+        }
+
+        for (i, webhook) in self.webhooks.iter().enumerate() {
+            if !webhook.url.starts_with("http://") && !webhook.url.starts_with("https://") {
+                errors.push(ConfigProblem::new(
+                    format!("webhooks[{}].url", i),
+                    format!("'{}' is not an http(s) URL", webhook.url),
+                ));
+            }
+        }
+
+        for (i, watch) in self.watch.iter().enumerate() {
+            for (field, patterns) in [("include", &watch.include), ("exclude", &watch.exclude)] {
+                for (j, pattern) in patterns.iter().enumerate() {
+                    if let Err(e) = globset::Glob::new(pattern) {
+                        errors.push(ConfigProblem::new(
+                            format!("watch[{}].{}[{}]", i, field, j),
+                            format!("invalid glob: {}", e),
+                        ));
+                    }
+                }
+            }
+            if !watch.enabled {
+                continue;
+            }
+            let path = expand_path(&watch.path);
+            if !path.is_dir() {
+                warnings.push(ConfigProblem::new(
+                    format!("watch[{}].path", i),
+                    format!(
+                        "{} is not a directory and will not be watched",
+                        path.display()
+                    ),
+                ));
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(warnings)
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// [`validate`](Self::validate) as a single `CoreError` listing the errors
+    pub fn ensure_valid(&self) -> Result<()> {
+        match self.validate() {
+            Ok(_) => Ok(()),
+            Err(errors) => {
+                let messages: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+                Err(CoreError::Config(messages.join("; ")))
+            }
+        }
+    }
+
     /// Check if a specific AI feature is active given current config.
     ///
     /// Returns false if provider is not set, or if the feature requires
     /// db storage but storage is ephemeral.
     pub fn is_feature_active(&self, feature: AiFeature) -> bool {
+        let enabled = match feature {
+            AiFeature::TitleGeneration => self.ai.title_generation,
+            AiFeature::MarkerDetection => self.ai.marker_detection,
+            AiFeature::MemoryExtraction => self.ai.memory_extraction,
+            AiFeature::SkillsDiscovery => self.ai.skills_discovery,
+            AiFeature::IntentExtraction => self.ai.intent_extraction,
+        };
+        enabled && self.is_feature_available(feature)
+    }
+
+    /// Check if a feature could run regardless of its toggle: a provider is
+    /// set and, for features that need persistence, storage is db.
+    pub fn is_feature_available(&self, feature: AiFeature) -> bool {
         if self.ai.provider.is_none() {
             return false;
         }
         match feature {
-            AiFeature::TitleGeneration => self.ai.title_generation,
-            AiFeature::MarkerDetection => self.ai.marker_detection && self.storage.is_db(),
-            AiFeature::MemoryExtraction => self.ai.memory_extraction && self.storage.is_db(),
-            AiFeature::SkillsDiscovery => self.ai.skills_discovery && self.storage.is_db(),
+            AiFeature::TitleGeneration => true,
+            AiFeature::MarkerDetection
+            | AiFeature::MemoryExtraction
+            | AiFeature::SkillsDiscovery
+            | AiFeature::IntentExtraction => self.storage.is_db(),
         }
     }
 
@@ -505,6 +1573,14 @@ impl Config {
         expand_path(&self.data_dir)
     }
 
+    /// Get the directory database backups are confined to (with ~ expanded)
+    pub fn backup_dir(&self) -> PathBuf {
+        match &self.database.backup_dir {
+            Some(dir) => expand_path(dir),
+            None => self.data_dir().join("backups"),
+        }
+    }
+
     /// Get the server socket address
     pub fn server_addr(&self) -> SocketAddr {
         use std::net::ToSocketAddrs;
@@ -516,12 +1592,15 @@ impl Config {
             .unwrap_or_else(|| SocketAddr::from(([127, 0, 0, 1], self.server.port)))
     }
 
-    /// Get expanded watch paths
-    pub fn watch_paths(&self) -> Vec<(PathBuf, String)> {
+    /// Get enabled watch entries with their paths expanded
+    pub fn watch_paths(&self) -> Vec<WatchConfig> {
         self.watch
             .iter()
             .filter(|w| w.enabled)
-            .map(|w| (expand_path(&w.path), w.parser.clone()))
+            .map(|w| WatchConfig {
+                path: expand_path(&w.path),
+                ..w.clone()
+            })
             .collect()
     }
 
@@ -602,15 +1681,53 @@ host = "127.0.0.1"
 # If set, clients must send: Authorization: Bearer <api_key>
 # api_key = "your-secret-key"
 
+# Seconds to wait for in-flight requests on shutdown before closing them
+# shutdown_timeout_secs = 10
+
+# Seconds to wait on shutdown for running AI tasks before cancelling them
+# shutdown_grace_secs = 30
+
+# Seconds an API request may run before it is aborted with 504 (0 = no limit)
+# request_timeout_secs = 30
+
+# Recent SSE events replayed to clients reconnecting with Last-Event-ID (0 = no replay)
+# sse_replay_buffer = 256
+
+# Largest request body accepted, in bytes; bigger requests get 413
+# max_body_bytes = 16777216
+
+# Largest session bundle accepted by /api/admin/import, in bytes
+# max_import_bytes = 268435456
+
+# Read-only viewer mode: writes get 403 and no files are watched or indexed
+# read_only = false
+
 # Friendly nickname for this instance (shown in mDNS discovery)
 # instance_name = "My Mac mini"
 
+# Per-client rate limit: each API key (or client IP, without a key) gets a
+# token bucket; requests beyond it get 429 with Retry-After. /health and SSE are exempt
+# [server.rate_limit]
+# requests_per_min = 0         # 0 = no limit
+# burst = 20                   # Requests allowed at once (default: requests_per_min)
+
+# Additional keys with a scope: "read" (GET only) or "admin" (full access)
+# [[server.api_keys]]
+# key = "dashboard-token"
+# scope = "read"
+
 # Directories to watch for session files
 # Projects are auto-created when sessions are discovered.
 [[watch]]
 path = "~/.claude/projects"
 parser = "claude_code"
 enabled = true
+# delete_on_remove = false     # Delete sessions whose files are removed
+# namespace_session_ids = false # Store session IDs as "{project_id}:{file_stem}"
+# parse_agents = false         # Index sub-agent transcripts linked to their parent
+# dedupe_by_hash = false       # Skip files identical to an already indexed session
+# include = ["**/*.jsonl"]     # Glob patterns relative to path (default: all)
+# exclude = ["**/archive/**"]  # Glob patterns to ignore
 
 # Add more watch paths as needed:
 # [[watch]]
@@ -623,27 +1740,88 @@ enabled = true
 # max_sessions = 100
 # max_messages_per_session = 50
 
+# SQLite settings (only used when storage = "db")
+# [database]
+# read_pool_size = 4           # Read connections for concurrent API queries
+# id_type = "uuid"            # "uuid" or "ulid" (time-sortable) for new projects
+# backup_dir = "~/.yolog/backups" # Where POST /api/admin/backup may write
+# duplicate_projects = "conflict" # Or "upsert": reuse the project already using the folder
+
+# Full-text search (only used when storage = "db")
+# [search]
+# Changing the tokenizer rebuilds the search indexes at startup, which is slow on large databases
+# fts_tokenizer = "porter"     # Or "unicode61" (no stemming) or "trigram" (substring matches)
+
+# MCP server limits (yocore --mcp)
+# [mcp]
+# max_results = 50             # Cap on memories per tool call, whatever limit is requested
+# max_content_chars = 2000     # Longer memory contents are truncated with a note
+
+# AI export chunking (for clients splitting sessions too large for one prompt)
+# [export]
+# chunk_overlap_chars = 2000   # Text shared by consecutive chunks; 0 disables overlap
+
+# Embedding model for semantic search
+# [embeddings]
+# model_path = "~/models/all-MiniLM-L6-v2" # Load from here instead of HuggingFace Hub
+# preload = false              # Load the model at startup rather than on first use
+
+# Session parsing
+# [parser]
+# estimate_tokens = false      # Estimate output tokens for messages without usage data
+
+# POST events to an HTTP endpoint (repeat for more endpoints)
+# [[webhooks]]
+# url = "https://hooks.example.com/yocore"
+# events = ["session:parsed", "ai:memories:complete"] # Default: all events
+# secret = "change-me"         # Sign bodies in the X-Yocore-Signature header
+
 # AI features — each toggle is independent, some require storage = "db"
 # AI is active when provider is set and at least one feature is enabled.
 [ai]
 # provider = "claude_code"     # Claude Code CLI
 # provider = "openclaw"        # OpenClaw CLI (requires gateway)
+# provider = "ollama"          # Local Ollama server over HTTP (see [ai.ollama])
+# provider = "openai"          # OpenAI-compatible API (see [ai.openai])
 title_generation = true
 marker_detection = true
 memory_extraction = true
 skills_discovery = true
+intent_extraction = false      # Use AI for session intents (a heuristic runs regardless)
+lenient_json = true            # Repair malformed JSON in AI output before failing
+max_concurrent_tasks = 3       # AI tasks allowed to run at once
+process_hidden_sessions = false # Also run AI on sessions you've hidden
+min_messages_for_processing = 25 # Messages before a session gets a title or is recovered
+
+# [ai.ollama]
+# base_url = "http://localhost:11434"
+# model = "llama3.1"
+
+# [ai.openai]
+# base_url = "https://api.openai.com/v1"
+# model = "gpt-4o-mini"
+# api_key = "sk-..."           # Or set OPENAI_API_KEY
 
 # Background scheduler tasks
 # Auto-activated by their parent AI features — no individual enabled flags.
 # memory_extraction activates: ranking, duplicate_cleanup, embedding_refresh
 # skills_discovery activates: skill_cleanup
+# maintenance has no AI dependency and only needs storage = "db"
 
 [scheduler.ranking]
 interval_hours = 6
 batch_size = 500
+# high_threshold = 0.7         # Promote new memories scoring at least this...
+# min_access_for_high = 3      # ...with at least this many accesses
+# demotion_threshold = 0.4     # Demote below this score
+# removal_threshold = 0.3      # Remove never-accessed memories below this score
+# stale_days = 90              # Days without access before high memories can be demoted
+# demotion_age_days = 14       # Minimum age before new memories can be demoted
+# removal_age_days = 30        # Minimum age before new memories can be removed
 
 [scheduler.duplicate_cleanup]
 interval_hours = 24
+# cron = "0 3 * * *"           # Run at 3am daily instead (overrides interval_hours)
 similarity_threshold = 0.75
 batch_size = 500
 
@@ -655,6 +1833,15 @@ batch_size = 100
 interval_hours = 24
 similarity_threshold = 0.80
 batch_size = 500
+
+[scheduler.maintenance]
+enabled = true                 # incremental_vacuum + ANALYZE + PRAGMA optimize
+interval_hours = 24
+
+[scheduler.session_purge]
+enabled = true                 # Permanently delete soft-deleted sessions
+retention_days = 30            # Days a deleted session can be restored
+interval_hours = 24
 "#;
 
         let path = path.as_ref();
@@ -716,11 +1903,37 @@ skills_discovery = false
         assert_eq!(config.storage, Storage::Db);
         assert_eq!(config.watch.len(), 1);
         assert_eq!(config.watch[0].parser, "claude_code");
-        assert_eq!(config.ai.provider.as_deref(), Some("claude_code"));
+        assert_eq!(config.ai.provider, Some("claude_code".into()));
         assert!(config.ai.title_generation);
         assert!(!config.ai.skills_discovery);
     }
 
+    #[test]
+    fn test_parse_scoped_api_keys() {
+        let toml = r#"
+[server]
+api_key = "legacy"
+
+[[server.api_keys]]
+key = "dashboard"
+scope = "read"
+
+[[server.api_keys]]
+key = "ops"
+"#;
+        let config: Config = toml::from_str(toml).unwrap();
+        let keys = config.server.effective_api_keys();
+        let scopes: Vec<_> = keys.iter().map(|k| (k.key.as_str(), k.scope)).collect();
+        assert_eq!(
+            scopes,
+            vec![
+                ("legacy", ApiKeyScope::Admin),
+                ("dashboard", ApiKeyScope::Read),
+                ("ops", ApiKeyScope::Admin),
+            ]
+        );
+    }
+
     #[test]
     fn test_parse_legacy_config_format() {
         let toml = r#"
@@ -740,7 +1953,7 @@ memory_extraction = true
         let mut config: Config = toml::from_str(toml).unwrap();
         config.ai.apply_legacy();
 
-        assert_eq!(config.ai.provider.as_deref(), Some("claude_code"));
+        assert_eq!(config.ai.provider, Some("claude_code".into()));
         assert!(config.ai.title_generation);
         assert!(!config.ai.skills_discovery);
         assert!(config.ai.memory_extraction);
@@ -768,15 +1981,193 @@ provider = "openclaw"
 title_generation = true
 "#;
         let config: Config = toml::from_str(toml).unwrap();
-        assert_eq!(config.ai.provider.as_deref(), Some("openclaw"));
+        assert_eq!(config.ai.provider, Some("openclaw".into()));
         assert!(config.is_ai_active());
         assert!(config.is_feature_active(AiFeature::TitleGeneration));
     }
 
+    #[test]
+    fn test_parse_ollama_provider() {
+        let toml = r#"
+[ai]
+provider = "ollama"
+
+[ai.ollama]
+model = "qwen2.5-coder"
+"#;
+        let config: Config = toml::from_str(toml).unwrap();
+        assert_eq!(config.ai.provider, Some("ollama".into()));
+        assert_eq!(config.ai.ollama.model, "qwen2.5-coder");
+        assert_eq!(config.ai.ollama.base_url, "http://localhost:11434");
+        assert!(config.is_feature_active(AiFeature::TitleGeneration));
+    }
+
+    #[test]
+    fn test_parse_openai_provider() {
+        let toml = r#"
+[ai]
+provider = "openai"
+
+[ai.openai]
+base_url = "https://example.openai.azure.com/openai/v1"
+api_key = "secret"
+"#;
+        let config: Config = toml::from_str(toml).unwrap();
+        assert_eq!(config.ai.provider, Some("openai".into()));
+        assert_eq!(config.ai.openai.model, "gpt-4o-mini");
+        assert_eq!(config.ai.openai.api_key.as_deref(), Some("secret"));
+        assert!(!format!("{:?}", config.ai.openai).contains("secret"));
+    }
+
+    #[test]
+    fn test_max_concurrent_tasks_validated() {
+        assert_eq!(Config::default().ai.max_concurrent_tasks, 3);
+        assert!(Config::default().validate().is_ok());
+
+        let config: Config = toml::from_str("[ai]\nmax_concurrent_tasks = 0\n").unwrap();
+        let err = config.ensure_valid().unwrap_err().to_string();
+        assert!(err.contains("max_concurrent_tasks"));
+
+        let config: Config = toml::from_str("[ai]\nmin_messages_for_processing = 0\n").unwrap();
+        let err = config.ensure_valid().unwrap_err().to_string();
+        assert!(err.contains("min_messages_for_processing"));
+    }
+
+    fn error_keys(config: &Config) -> Vec<String> {
+        config
+            .validate()
+            .unwrap_err()
+            .into_iter()
+            .map(|e| e.key)
+            .collect()
+    }
+
+    #[test]
+    fn test_validate_accepts_good_config() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mut config = Config::default();
+        config.ai.provider = Some("ollama".into());
+        config.watch.push(WatchConfig {
+            path: tmp.path().to_path_buf(),
+            parser: default_parser(),
+            enabled: true,
+            delete_on_remove: false,
+            namespace_session_ids: false,
+            parse_agents: false,
+            dedupe_by_hash: false,
+            include: Vec::new(),
+            exclude: Vec::new(),
+        });
+        assert_eq!(config.validate(), Ok(Vec::new()));
+    }
+
+    #[test]
+    fn test_validate_warns_on_missing_watch_path() {
+        let config: Config = toml::from_str(
+            "[[watch]]\npath = \"/nonexistent/yocore-test\"\n\n[[watch]]\npath = \"/nonexistent/disabled\"\nenabled = false\n",
+        )
+        .unwrap();
+        let warnings = config.validate().unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].key, "watch[0].path");
+    }
+
+    #[test]
+    fn test_validate_rejects_unknown_provider() {
+        let config: Config = toml::from_str("[ai]\nprovider = \"gpt\"\n").unwrap();
+        assert_eq!(error_keys(&config), vec!["ai.provider"]);
+    }
+
+    #[test]
+    fn test_provider_chain() {
+        let config: Config =
+            toml::from_str("[ai]\nprovider = [\"claude_code\", \"openai\"]\n").unwrap();
+        let chain = config.ai.provider.as_ref().unwrap();
+        assert_eq!(chain.names(), ["claude_code", "openai"]);
+        assert_eq!(config.validate(), Ok(Vec::new()));
+
+        let config: Config =
+            toml::from_str("[ai]\nprovider = [\"claude_code\", \"gpt\"]\n").unwrap();
+        assert_eq!(error_keys(&config), vec!["ai.provider"]);
+        let config: Config = toml::from_str("[ai]\nprovider = []\n").unwrap();
+        assert_eq!(error_keys(&config), vec!["ai.provider"]);
+    }
+
+    #[test]
+    fn test_validate_rejects_bad_marker_patterns() {
+        let config: Config = toml::from_str(
+            "[[ai.marker_patterns]]\nlabel = \"Oops\"\nmarker_type = \"oops\"\npattern = \"(unclosed\"\n",
+        )
+        .unwrap();
+        assert_eq!(
+            error_keys(&config),
+            vec![
+                "ai.marker_patterns[0].marker_type",
+                "ai.marker_patterns[0].pattern"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validate_rejects_bad_watch_globs() {
+        let config: Config = toml::from_str(
+            "[[watch]]\npath = \"/tmp\"\nenabled = false\ninclude = [\"**/*.jsonl\", \"a/{b\"]\nexclude = [\"[z-a]\"]\n",
+        )
+        .unwrap();
+        assert_eq!(
+            error_keys(&config),
+            vec!["watch[0].include[1]", "watch[0].exclude[0]"]
+        );
+    }
+
+    #[test]
+    fn test_validate_rejects_zero_intervals() {
+        let config: Config = toml::from_str(
+            "[scheduler.ranking]\ninterval_hours = 0\n\n[scheduler.maintenance]\ninterval_hours = 0\n",
+        )
+        .unwrap();
+        assert_eq!(
+            error_keys(&config),
+            vec![
+                "scheduler.ranking.interval_hours",
+                "scheduler.maintenance.interval_hours"
+            ]
+        );
+        // Negative values don't parse at all
+        assert!(toml::from_str::<Config>("[scheduler.ranking]\ninterval_hours = -1\n").is_err());
+    }
+
+    #[test]
+    fn test_validate_cron_expressions() {
+        let config: Config = toml::from_str(
+            "[scheduler.duplicate_cleanup]\ncron = \"0 3 * * *\"\n\n[scheduler.maintenance]\ncron = \"every night\"\n",
+        )
+        .unwrap();
+        assert_eq!(
+            config.scheduler.duplicate_cleanup.cron.as_deref(),
+            Some("0 3 * * *")
+        );
+        assert_eq!(error_keys(&config), vec!["scheduler.maintenance.cron"]);
+    }
+
+    #[test]
+    fn test_validate_rejects_zero_ephemeral_limits() {
+        let config: Config =
+            toml::from_str("[ephemeral]\nmax_sessions = 0\nmax_messages_per_session = 0\n")
+                .unwrap();
+        assert_eq!(
+            error_keys(&config),
+            vec![
+                "ephemeral.max_sessions",
+                "ephemeral.max_messages_per_session"
+            ]
+        );
+    }
+
     #[test]
     fn test_is_feature_active() {
         let mut config = Config::default();
-        config.ai.provider = Some("claude_code".to_string());
+        config.ai.provider = Some("claude_code".into());
 
         // All features active with db storage + provider
         assert!(config.is_feature_active(AiFeature::TitleGeneration));
@@ -0,0 +1,31 @@
+//! Memory row helpers shared by the HTTP API and the MCP server
+
+use crate::mcp::types::{Memory, MemoryType};
+
+/// Condition on `memories m` leaving out memories of soft-deleted sessions
+pub const LIVE_SESSION_MEMORY: &str =
+    "NOT EXISTS (SELECT 1 FROM sessions ds WHERE ds.id = m.session_id AND ds.deleted_at IS NOT NULL)";
+
+/// Convert a database row to Memory
+/// Expects columns: id, project_id, session_id, memory_type, title, content,
+///                   context, tags, confidence, is_validated, extracted_at, file_reference, state
+pub fn row_to_memory(row: &rusqlite::Row) -> rusqlite::Result<Memory> {
+    let memory_type_str: String = row.get(3)?;
+    let tags_json: String = row.get(7)?;
+
+    Ok(Memory {
+        id: row.get(0)?,
+        project_id: row.get(1)?,
+        session_id: row.get(2)?,
+        memory_type: MemoryType::from_str(&memory_type_str).unwrap_or(MemoryType::Context),
+        title: row.get(4)?,
+        content: row.get(5)?,
+        context: row.get(6)?,
+        tags: serde_json::from_str(&tags_json).unwrap_or_default(),
+        confidence: row.get(8)?,
+        is_validated: row.get(9)?,
+        extracted_at: row.get(10)?,
+        file_reference: row.get(11)?,
+        state: row.get(12)?,
+    })
+}
@@ -2,29 +2,185 @@
 //!
 //! Provides SQLite storage for projects, sessions, memories, and skills.
 //!
-//! Uses two connections with SQLite WAL mode for concurrent read/write access:
+//! Uses SQLite WAL mode for concurrent read/write access:
 //! - **Write connection** (`with_conn`): for watcher, AI tasks, and any INSERT/UPDATE/DELETE
-//! - **Read connection** (`with_read_conn`): for API queries — never blocked by writes
+//! - **Read pool** (`with_read_conn`): for API queries — never blocked by writes or each other
 
+pub mod memories;
 pub mod schema;
+pub mod search;
 
+use crate::config::{FtsTokenizer, IdType};
+use crate::embeddings::AnnIndex;
 use crate::error::Result;
 use rusqlite::Connection;
 use std::path::PathBuf;
-use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
 
-/// Database manager with separate read/write connections.
+/// Default number of read connections (see `DatabaseConfig::read_pool_size`)
+pub const DEFAULT_READ_POOL_SIZE: usize = 4;
+
+/// Database manager with a single write connection and a pool of read connections.
 ///
-/// SQLite WAL mode allows concurrent reads while a write is in progress.
-/// Two connections exploit this: API reads proceed even during long writes.
+/// SQLite WAL mode allows any number of readers alongside one writer.
+/// API reads proceed during long writes, and up to `read_pool_size` reads run in parallel.
 pub struct Database {
     write_conn: Arc<Mutex<Connection>>,
-    read_conn: Arc<Mutex<Connection>>,
+    read_conns: Arc<Vec<Mutex<Connection>>>,
+    next_read: Arc<AtomicUsize>,
+    id_type: IdType,
     path: PathBuf,
+    /// In-memory ANN index over `memory_embeddings` (None unless enabled)
+    memory_ann: Option<Arc<RwLock<AnnIndex>>>,
+}
+
+/// Interrupts the read running on a pooled connection when dropped.
+///
+/// Holds the connection's interrupt handle only while the read closure runs,
+/// so dropping it after completion is a no-op.
+#[derive(Default)]
+struct InterruptOnDrop(Arc<Mutex<Option<rusqlite::InterruptHandle>>>);
+
+impl Drop for InterruptOnDrop {
+    fn drop(&mut self) {
+        if let Some(handle) = self.0.lock().unwrap().as_ref() {
+            handle.interrupt();
+        }
+    }
+}
+
+/// Monotonic so ULIDs created within the same millisecond still sort in order
+static ULID_GENERATOR: Mutex<ulid::Generator> = Mutex::new(ulid::Generator::new());
+
+/// Generate an ID for a new row
+pub fn generate_id(id_type: IdType) -> String {
+    match id_type {
+        IdType::Uuid => uuid::Uuid::new_v4().to_string(),
+        IdType::Ulid => ULID_GENERATOR
+            .lock()
+            .unwrap()
+            .generate()
+            .unwrap_or_else(|_| ulid::Ulid::new())
+            .to_string(),
+    }
+}
+
+/// Ids of the memories matching `filter` (a `WHERE` clause with one `?`).
+///
+/// Collected before deleting rows that cascade to memories, so the deleted ids
+/// can be dropped from the ANN index (`Database::unindex_memories`) afterwards.
+pub fn memory_ids_where(
+    conn: &Connection,
+    filter: &str,
+    param: &str,
+) -> rusqlite::Result<Vec<i64>> {
+    let mut stmt = conn.prepare(&format!("SELECT id FROM memories WHERE {}", filter))?;
+    let ids = stmt.query_map([param], |row| row.get(0))?;
+    ids.collect()
+}
+
+/// How long SQLite itself waits on a lock before returning SQLITE_BUSY
+const BUSY_TIMEOUT: Duration = Duration::from_millis(5000);
+
+/// Attempts (including the first) for operations that hit SQLITE_BUSY/SQLITE_LOCKED
+const BUSY_RETRY_ATTEMPTS: u32 = 5;
+const BUSY_RETRY_MIN_DELAY: Duration = Duration::from_millis(10);
+const BUSY_RETRY_MAX_DELAY: Duration = Duration::from_millis(200);
+
+/// Whether an error is transient lock contention (worth retrying) rather than a logic error
+pub(crate) fn is_busy(e: &rusqlite::Error) -> bool {
+    matches!(
+        e.sqlite_error_code(),
+        Some(rusqlite::ErrorCode::DatabaseBusy | rusqlite::ErrorCode::DatabaseLocked)
+    )
+}
+
+/// Run `op`, retrying with exponential backoff while it fails with SQLITE_BUSY/SQLITE_LOCKED.
+/// Blocks the current thread between attempts; call from `spawn_blocking` only.
+fn retry_on_busy<T: BusyOutcome>(mut op: impl FnMut() -> T) -> T {
+    let mut delay = BUSY_RETRY_MIN_DELAY;
+    let mut attempt = 1;
+    loop {
+        let result = op();
+        if !result.is_busy() || attempt >= BUSY_RETRY_ATTEMPTS {
+            return result;
+        }
+        tracing::debug!("Database busy (attempt {}), retrying", attempt);
+        std::thread::sleep(delay);
+        delay = (delay * 2).min(BUSY_RETRY_MAX_DELAY);
+        attempt += 1;
+    }
+}
+
+/// What a closure passed to `with_conn` or `with_read_conn` returns. Only an
+/// `Err` holding a busy/locked error makes the closure run again: its work was
+/// abandoned, where errors the closure swallowed may have left some behind.
+pub trait BusyOutcome {
+    /// Whether this failed on SQLITE_BUSY/SQLITE_LOCKED
+    fn is_busy(&self) -> bool {
+        false
+    }
+}
+
+/// Errors that can tell lock contention from other failures
+pub trait BusyError {
+    fn is_busy(&self) -> bool;
 }
 
+impl BusyError for rusqlite::Error {
+    fn is_busy(&self) -> bool {
+        is_busy(self)
+    }
+}
+
+impl BusyError for crate::error::CoreError {
+    fn is_busy(&self) -> bool {
+        matches!(self, crate::error::CoreError::DbBusy(_))
+    }
+}
+
+/// A rusqlite error already turned into text with `to_string()`
+impl BusyError for String {
+    fn is_busy(&self) -> bool {
+        self.contains("database is locked") || self.contains("database table is locked")
+    }
+}
+
+/// An error response built from such text, e.g. `(StatusCode, String)`
+impl<S> BusyError for (S, String) {
+    fn is_busy(&self) -> bool {
+        BusyError::is_busy(&self.1)
+    }
+}
+
+impl<T, E: BusyError> BusyOutcome for std::result::Result<T, E> {
+    fn is_busy(&self) -> bool {
+        self.as_ref().err().is_some_and(E::is_busy)
+    }
+}
+
+macro_rules! never_busy {
+    ($($ty:ty),* $(,)?) => {
+        $(impl BusyOutcome for $ty {})*
+    };
+}
+
+never_busy!((), bool, i64, usize, String);
+
+impl<T> BusyOutcome for Option<T> {}
+impl<T> BusyOutcome for Vec<T> {}
+impl<A, B> BusyOutcome for (A, B) {}
+impl<A, B, C> BusyOutcome for (A, B, C) {}
+impl<A, B, C, D> BusyOutcome for (A, B, C, D) {}
+
 /// Configure common PRAGMAs on a connection
 fn configure_connection(conn: &Connection) -> std::result::Result<(), rusqlite::Error> {
+    conn.busy_timeout(BUSY_TIMEOUT)?;
+    // Only takes effect on a fresh database (before any table exists); lets the
+    // scheduled maintenance task reclaim free pages with incremental_vacuum
+    conn.execute("PRAGMA auto_vacuum = INCREMENTAL", [])?;
     conn.execute("PRAGMA foreign_keys = ON", [])?;
     let _: String = conn.query_row("PRAGMA journal_mode = WAL", [], |row| row.get(0))?;
     let _: i64 = conn.query_row("PRAGMA wal_autocheckpoint = 100", [], |row| row.get(0))?;
@@ -35,8 +191,13 @@ fn configure_connection(conn: &Connection) -> std::result::Result<(), rusqlite::
 }
 
 impl Database {
-    /// Create a new database with separate read and write connections
+    /// Create a new database with the default read pool size
     pub fn new(db_path: PathBuf) -> Result<Self> {
+        Self::with_read_pool(db_path, DEFAULT_READ_POOL_SIZE)
+    }
+
+    /// Create a new database with `read_pool_size` read connections (at least one)
+    pub fn with_read_pool(db_path: PathBuf, read_pool_size: usize) -> Result<Self> {
         // Ensure parent directory exists
         if let Some(parent) = db_path.parent() {
             std::fs::create_dir_all(parent)?;
@@ -49,14 +210,22 @@ impl Database {
         // Initialize schema on write connection
         schema::init_db(&write_conn)?;
 
-        // Read connection — used by API queries, never blocked by writes
-        let read_conn = Connection::open(&db_path)?;
-        configure_connection(&read_conn)?;
+        // Read connections — used by API queries, never blocked by writes
+        let read_conns = (0..read_pool_size.max(1))
+            .map(|_| {
+                let conn = Connection::open(&db_path)?;
+                configure_connection(&conn)?;
+                Ok(Mutex::new(conn))
+            })
+            .collect::<Result<Vec<_>>>()?;
 
         Ok(Database {
             write_conn: Arc::new(Mutex::new(write_conn)),
-            read_conn: Arc::new(Mutex::new(read_conn)),
+            read_conns: Arc::new(read_conns),
+            next_read: Arc::new(AtomicUsize::new(0)),
+            id_type: IdType::default(),
             path: db_path,
+            memory_ann: None,
         })
     }
 
@@ -65,21 +234,24 @@ impl Database {
     /// Uses the write connection. Other write operations will wait for the lock,
     /// but read operations via `with_read_conn` proceed concurrently.
     ///
+    /// If `f` returns an error for SQLITE_BUSY/SQLITE_LOCKED (see [`BusyOutcome`]),
+    /// it is run again with exponential backoff, up to 5 attempts in all.
+    ///
     /// # Example
     /// ```ignore
     /// db.with_conn(|conn| {
     ///     conn.execute("INSERT INTO projects ...", params![...])
     /// }).await;
     /// ```
-    pub async fn with_conn<F, T>(&self, f: F) -> T
+    pub async fn with_conn<F, T>(&self, mut f: F) -> T
     where
-        F: FnOnce(&Connection) -> T + Send + 'static,
-        T: Send + 'static,
+        F: FnMut(&Connection) -> T + Send + 'static,
+        T: BusyOutcome + Send + 'static,
     {
         let conn = Arc::clone(&self.write_conn);
         tokio::task::spawn_blocking(move || {
             let guard = conn.lock().unwrap();
-            f(&guard)
+            retry_on_busy(|| f(&guard))
         })
         .await
         .expect("spawn_blocking task panicked")
@@ -87,18 +259,35 @@ impl Database {
 
     /// Run a read-only operation asynchronously (API queries).
     ///
-    /// Uses a separate read connection that is never blocked by writes.
-    /// Thanks to SQLite WAL mode, reads see a consistent snapshot even
-    /// while the write connection is mid-transaction.
-    pub async fn with_read_conn<F, T>(&self, f: F) -> T
+    /// Checks out an idle connection from the read pool, waiting on one
+    /// (round-robin) only when all are busy. Thanks to SQLite WAL mode, reads
+    /// see a consistent snapshot even while the write connection is mid-transaction.
+    ///
+    /// If the returned future is dropped (e.g. the request timed out), the
+    /// statement in progress is interrupted so the connection returns to the
+    /// pool instead of staying busy with a result nobody will read. Busy errors
+    /// are retried like in `with_conn`.
+    pub async fn with_read_conn<F, T>(&self, mut f: F) -> T
     where
-        F: FnOnce(&Connection) -> T + Send + 'static,
-        T: Send + 'static,
+        F: FnMut(&Connection) -> T + Send + 'static,
+        T: BusyOutcome + Send + 'static,
     {
-        let conn = Arc::clone(&self.read_conn);
+        let conns = Arc::clone(&self.read_conns);
+        let start = self.next_read.fetch_add(1, Ordering::Relaxed) % conns.len();
+        let running = InterruptOnDrop::default();
+        let handle = Arc::clone(&running.0);
         tokio::task::spawn_blocking(move || {
-            let guard = conn.lock().unwrap();
-            f(&guard)
+            let idle = (0..conns.len())
+                .map(|i| (start + i) % conns.len())
+                .find_map(|i| conns[i].try_lock().ok());
+            let guard = match idle {
+                Some(guard) => guard,
+                None => conns[start].lock().unwrap(),
+            };
+            *handle.lock().unwrap() = Some(guard.get_interrupt_handle());
+            let result = retry_on_busy(|| f(&guard));
+            *handle.lock().unwrap() = None;
+            result
         })
         .await
         .expect("spawn_blocking task panicked")
@@ -107,7 +296,7 @@ impl Database {
     /// Run a write operation that returns a Result asynchronously.
     pub async fn with_conn_result<F, T>(&self, f: F) -> rusqlite::Result<T>
     where
-        F: FnOnce(&Connection) -> rusqlite::Result<T> + Send + 'static,
+        F: FnMut(&Connection) -> rusqlite::Result<T> + Send + 'static,
         T: Send + 'static,
     {
         self.with_conn(f).await
@@ -121,6 +310,93 @@ impl Database {
         self.write_conn.lock().unwrap()
     }
 
+    /// Use `id_type` for rows created from now on
+    pub fn with_id_type(mut self, id_type: IdType) -> Self {
+        self.id_type = id_type;
+        self
+    }
+
+    /// Use `tokenizer` for the message and memory full-text indexes, rebuilding
+    /// them if they were created with another one
+    pub fn with_fts_tokenizer(self, tokenizer: FtsTokenizer) -> Result<Self> {
+        let rebuilt = schema::set_fts_tokenizer(&self.write_conn.lock().unwrap(), tokenizer)?;
+        if rebuilt > 0 {
+            tracing::info!(
+                "Rebuilt {} full-text index(es) with tokenizer '{}'",
+                rebuilt,
+                tokenizer.spec()
+            );
+        }
+        Ok(self)
+    }
+
+    /// Enable the memory ANN index, building it from the stored embeddings
+    pub fn with_memory_ann_index(mut self) -> Result<Self> {
+        let mut index = AnnIndex::new();
+        {
+            let conn = self.write_conn.lock().unwrap();
+            let mut stmt = conn.prepare(
+                "SELECT me.memory_id, me.embedding FROM memory_embeddings me
+                 JOIN memories m ON m.id = me.memory_id
+                 WHERE m.state != 'removed'",
+            )?;
+            let rows = stmt.query_map([], |row| {
+                Ok((row.get::<_, i64>(0)?, row.get::<_, Vec<u8>>(1)?))
+            })?;
+            for (id, bytes) in rows.flatten() {
+                index.add(id, &crate::embeddings::bytes_to_embedding(&bytes));
+            }
+        }
+        tracing::info!("Memory ANN index built with {} embeddings", index.len());
+        self.memory_ann = Some(Arc::new(RwLock::new(index)));
+        Ok(self)
+    }
+
+    /// Record a memory embedding just written to `memory_embeddings` in the ANN
+    /// index (no-op when the index is disabled)
+    pub fn index_memory_embedding(&self, memory_id: i64, embedding: &[f32]) {
+        if let Some(ann) = &self.memory_ann {
+            ann.write().unwrap().add(memory_id, embedding);
+        }
+    }
+
+    /// Drop memories that were deleted, removed, or lost their embedding from
+    /// the ANN index (no-op when the index is disabled)
+    pub fn unindex_memories(&self, memory_ids: &[i64]) {
+        if let Some(ann) = &self.memory_ann {
+            let mut ann = ann.write().unwrap();
+            for &id in memory_ids {
+                ann.remove(id);
+            }
+        }
+    }
+
+    /// Approximate top-`k` memory ids by similarity to `query`.
+    ///
+    /// None when the index is disabled or empty; callers fall back to brute force.
+    pub fn search_memory_ann(&self, query: &[f32], k: usize) -> Option<Vec<(i64, f32)>> {
+        let ann = self.memory_ann.as_ref()?.read().unwrap();
+        if ann.is_empty() {
+            return None;
+        }
+        Some(ann.search(query, k))
+    }
+
+    /// ID format for new rows
+    pub fn id_type(&self) -> IdType {
+        self.id_type
+    }
+
+    /// Generate an ID for a new row in this database's configured format
+    pub fn new_id(&self) -> String {
+        generate_id(self.id_type)
+    }
+
+    /// Number of connections in the read pool
+    pub fn read_pool_size(&self) -> usize {
+        self.read_conns.len()
+    }
+
     /// Get the database file path
     pub fn path(&self) -> &PathBuf {
         &self.path
@@ -145,4 +421,158 @@ mod tests {
         // Cleanup
         let _ = std::fs::remove_file(db_path);
     }
+
+    /// Run `n` concurrent reads that each hold their connection for a while and
+    /// return the peak number running at once.
+    async fn peak_concurrent_reads(db: Arc<Database>, n: usize) -> usize {
+        let active = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+
+        let reads = (0..n).map(|_| {
+            let db = Arc::clone(&db);
+            let active = Arc::clone(&active);
+            let peak = Arc::clone(&peak);
+            tokio::spawn(async move {
+                db.with_read_conn(move |conn| {
+                    let now = active.fetch_add(1, Ordering::SeqCst) + 1;
+                    peak.fetch_max(now, Ordering::SeqCst);
+                    let _: i64 = conn
+                        .query_row("SELECT COUNT(*) FROM sessions", [], |row| row.get(0))
+                        .unwrap();
+                    std::thread::sleep(std::time::Duration::from_millis(100));
+                    active.fetch_sub(1, Ordering::SeqCst);
+                })
+                .await
+            })
+        });
+        for read in reads.collect::<Vec<_>>() {
+            read.await.unwrap();
+        }
+
+        peak.load(Ordering::SeqCst)
+    }
+
+    #[tokio::test]
+    async fn test_read_pool_runs_reads_concurrently() {
+        let tmp = tempfile::tempdir().unwrap();
+        let db = Arc::new(Database::with_read_pool(tmp.path().join("test.db"), 4).unwrap());
+        assert_eq!(db.read_pool_size(), 4);
+
+        let started = std::time::Instant::now();
+        assert_eq!(peak_concurrent_reads(db, 4).await, 4);
+        // Serialized reads would take at least 400ms
+        assert!(started.elapsed() < std::time::Duration::from_millis(400));
+    }
+
+    #[tokio::test]
+    async fn test_single_read_connection_serializes() {
+        let tmp = tempfile::tempdir().unwrap();
+        let db = Arc::new(Database::with_read_pool(tmp.path().join("test.db"), 0).unwrap());
+        assert_eq!(db.read_pool_size(), 1);
+
+        assert_eq!(peak_concurrent_reads(db, 3).await, 1);
+    }
+
+    #[tokio::test]
+    async fn test_dropped_read_releases_connection() {
+        let tmp = tempfile::tempdir().unwrap();
+        let db = Database::with_read_pool(tmp.path().join("test.db"), 1).unwrap();
+
+        // A query that never finishes on its own, abandoned by its caller
+        let endless = db.with_read_conn(|conn| {
+            conn.query_row(
+                "WITH RECURSIVE n(x) AS (SELECT 1 UNION ALL SELECT x + 1 FROM n) \
+                 SELECT COUNT(*) FROM n",
+                [],
+                |row| row.get::<_, i64>(0),
+            )
+        });
+        let abandoned = tokio::time::timeout(std::time::Duration::from_millis(100), endless).await;
+        assert!(abandoned.is_err());
+
+        // The only read connection is free again
+        let count: i64 = tokio::time::timeout(
+            std::time::Duration::from_secs(5),
+            db.with_read_conn(|conn| {
+                conn.query_row("SELECT COUNT(*) FROM projects", [], |row| row.get(0))
+                    .unwrap()
+            }),
+        )
+        .await
+        .expect("read connection still busy");
+        assert_eq!(count, 0);
+    }
+
+    #[test]
+    fn test_ulids_sort_in_creation_order() {
+        let ids: Vec<String> = (0..1000).map(|_| generate_id(IdType::Ulid)).collect();
+        let mut sorted = ids.clone();
+        sorted.sort();
+        assert_eq!(ids, sorted);
+        assert!(ids.iter().all(|id| id.len() == 26));
+    }
+
+    #[tokio::test]
+    async fn test_busy_write_succeeds_after_retry() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("test.db");
+        let db = Database::new(path.clone()).unwrap();
+
+        // Another connection holds the write lock
+        let holder = Connection::open(&path).unwrap();
+        holder.execute_batch("BEGIN IMMEDIATE").unwrap();
+
+        // WAL readers are not blocked by the held write lock
+        let count: i64 = db
+            .with_read_conn(|conn| {
+                conn.query_row("SELECT COUNT(*) FROM projects", [], |row| row.get(0))
+            })
+            .await
+            .unwrap();
+        assert_eq!(count, 0);
+
+        let release = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(40));
+            holder.execute_batch("COMMIT").unwrap();
+        });
+
+        let attempts = Arc::new(AtomicUsize::new(0));
+        let counter = attempts.clone();
+        let inserted = db
+            .with_conn(move |conn| {
+                counter.fetch_add(1, Ordering::Relaxed);
+                // Fail fast instead of waiting out the busy timeout, so only the retry helps
+                conn.busy_timeout(Duration::ZERO)?;
+                let inserted = conn.execute(
+                    "INSERT INTO projects (id, name, folder_path, created_at, updated_at)
+                     VALUES ('p1', 'p', '/tmp/p', 'now', 'now')",
+                    [],
+                );
+                conn.busy_timeout(BUSY_TIMEOUT)?;
+                inserted
+            })
+            .await;
+        release.join().unwrap();
+
+        assert_eq!(inserted.unwrap(), 1);
+        assert!(attempts.load(Ordering::Relaxed) > 1);
+    }
+
+    #[test]
+    fn test_logic_errors_are_not_retried() {
+        let conn = Connection::open_in_memory().unwrap();
+        let mut attempts = 0;
+        let result = retry_on_busy(|| {
+            attempts += 1;
+            conn.execute("INSERT INTO missing_table VALUES (1)", [])
+        });
+        assert!(result.is_err());
+        assert_eq!(attempts, 1);
+
+        let busy = rusqlite::Error::SqliteFailure(
+            rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_BUSY),
+            None,
+        );
+        assert!(is_busy(&busy));
+    }
 }
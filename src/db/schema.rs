@@ -3,6 +3,7 @@
 //! Manages projects, sessions, memories, and skills.
 //! This schema is compatible with the Desktop app schema.
 
+use crate::config::FtsTokenizer;
 use rusqlite::{Connection, Result};
 
 /// Initialize the database with required tables
@@ -53,6 +54,9 @@ pub fn init_db(conn: &Connection) -> Result<()> {
             import_status TEXT DEFAULT 'success' CHECK (import_status IN ('success', 'failed')),
             import_error TEXT,
             is_hidden BOOLEAN NOT NULL DEFAULT 0,
+            parent_session_id TEXT,
+            parse_errors INTEGER NOT NULL DEFAULT 0,
+            parse_error_lines TEXT,
             created_at TEXT NOT NULL,
             indexed_at TEXT NOT NULL,
             FOREIGN KEY (project_id) REFERENCES projects(id) ON DELETE CASCADE
@@ -83,6 +87,8 @@ pub fn init_db(conn: &Connection) -> Result<()> {
             cache_creation_tokens INTEGER,
             model TEXT,
             timestamp TEXT NOT NULL,
+            parent_sequence_num INTEGER,
+            code_language TEXT,
             FOREIGN KEY (session_id) REFERENCES sessions(id) ON DELETE CASCADE,
             UNIQUE(session_id, sequence_num)
         )",
@@ -241,26 +247,478 @@ pub fn init_db(conn: &Connection) -> Result<()> {
     Ok(())
 }
 
+/// A versioned schema change, applied once per database.
+pub struct Migration {
+    /// Schema version after this migration (recorded in `PRAGMA user_version`)
+    pub version: u32,
+    pub description: &'static str,
+    pub up: fn(&Connection) -> Result<()>,
+}
+
+/// Schema migrations, in order. Append new entries; never edit or reorder applied ones.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "backfill session columns added since the original schema",
+        up: backfill_session_columns,
+    },
+    Migration {
+        version: 2,
+        description: "merge projects sharing a folder_path and enforce uniqueness",
+        up: merge_duplicate_projects,
+    },
+    Migration {
+        version: 3,
+        description: "record deleted sessions, memories, and skills as tombstones",
+        up: create_tombstones,
+    },
+    Migration {
+        version: 4,
+        description: "link tool result messages to their tool_use message",
+        up: add_message_parent_sequence,
+    },
+    Migration {
+        version: 5,
+        description: "store the user requests (intents) made in each session",
+        up: create_session_intents,
+    },
+    Migration {
+        version: 6,
+        description: "record scheduler task runs",
+        up: create_scheduler_runs,
+    },
+    Migration {
+        version: 7,
+        description: "flag scheduler ticks skipped because the previous run was still active",
+        up: add_scheduler_run_skipped,
+    },
+    Migration {
+        version: 8,
+        description: "per-project AI feature overrides",
+        up: create_project_ai_settings,
+    },
+    Migration {
+        version: 9,
+        description: "count lines that failed to parse in each session",
+        up: add_session_parse_errors,
+    },
+    Migration {
+        version: 10,
+        description: "record the code language of each message",
+        up: add_message_code_language,
+    },
+    Migration {
+        version: 11,
+        description: "hash session file contents to detect duplicate copies",
+        up: add_session_content_hash,
+    },
+    Migration {
+        version: 12,
+        description: "store user-assigned session tags",
+        up: create_session_tags,
+    },
+    Migration {
+        version: 13,
+        description: "flag messages whose token counts were estimated",
+        up: add_message_tokens_estimated,
+    },
+    Migration {
+        version: 14,
+        description: "create message_embeddings",
+        up: create_message_embeddings,
+    },
+    Migration {
+        version: 15,
+        description: "soft-delete sessions",
+        up: add_session_deleted_at,
+    },
+    Migration {
+        version: 16,
+        description: "record the git branch and commit of each session",
+        up: add_session_git_context,
+    },
+    Migration {
+        version: 17,
+        description: "record soft-deleted sessions as tombstones",
+        up: create_session_soft_delete_tombstone,
+    },
+    Migration {
+        version: 18,
+        description: "track when memories and skills last changed",
+        up: add_memory_skill_updated_at,
+    },
+    Migration {
+        version: 19,
+        description: "keep the parser's git detection state between appends",
+        up: add_session_git_state,
+    },
+];
+
+/// Current schema version (the last migration)
+pub fn latest_version() -> u32 {
+    MIGRATIONS.last().map(|m| m.version).unwrap_or(0)
+}
+
+/// Migration 2: fold projects that share a `folder_path` into the oldest one,
+/// then add a unique index (databases predating the UNIQUE column constraint lack it).
+fn merge_duplicate_projects(conn: &Connection) -> Result<()> {
+    let merges: Vec<(String, String)> = {
+        let mut stmt = conn.prepare(
+            "SELECT p.id,
+                    (SELECT k.id FROM projects k WHERE k.folder_path = p.folder_path
+                     ORDER BY k.created_at, k.id LIMIT 1)
+             FROM projects p",
+        )?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        rows.filter(|r| r.as_ref().map_or(true, |(old, keep)| old != keep))
+            .collect::<Result<_>>()?
+    };
+
+    for (old_id, keep_id) in &merges {
+        for table in ["sessions", "memories", "skills", "session_context"] {
+            conn.execute(
+                &format!("UPDATE {} SET project_id = ?1 WHERE project_id = ?2", table),
+                [keep_id, old_id],
+            )?;
+        }
+        conn.execute("DELETE FROM projects WHERE id = ?", [old_id])?;
+        tracing::info!("Merged duplicate project {} into {}", old_id, keep_id);
+    }
+
+    conn.execute(
+        "CREATE UNIQUE INDEX IF NOT EXISTS idx_projects_folder_path ON projects(folder_path)",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Migration 3: tombstones for delta sync (`GET /api/sync/changes`).
+/// Triggers record hard deletes (including FK cascades) and memories soft-removed
+/// via `state = 'removed'`.
+fn create_tombstones(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS tombstones (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            entity_type TEXT NOT NULL CHECK (entity_type IN ('session', 'memory', 'skill')),
+            entity_id TEXT NOT NULL,
+            project_id TEXT,
+            deleted_at TEXT NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS idx_tombstones_deleted_at ON tombstones(deleted_at);
+
+        CREATE TRIGGER IF NOT EXISTS tombstone_session_delete AFTER DELETE ON sessions BEGIN
+            INSERT INTO tombstones (entity_type, entity_id, project_id, deleted_at)
+            VALUES ('session', OLD.id, OLD.project_id, strftime('%Y-%m-%dT%H:%M:%fZ', 'now'));
+        END;
+        CREATE TRIGGER IF NOT EXISTS tombstone_memory_delete AFTER DELETE ON memories
+        WHEN OLD.state != 'removed' BEGIN
+            INSERT INTO tombstones (entity_type, entity_id, project_id, deleted_at)
+            VALUES ('memory', OLD.id, OLD.project_id, strftime('%Y-%m-%dT%H:%M:%fZ', 'now'));
+        END;
+        CREATE TRIGGER IF NOT EXISTS tombstone_memory_remove AFTER UPDATE OF state ON memories
+        WHEN NEW.state = 'removed' AND OLD.state != 'removed' BEGIN
+            INSERT INTO tombstones (entity_type, entity_id, project_id, deleted_at)
+            VALUES ('memory', OLD.id, OLD.project_id, strftime('%Y-%m-%dT%H:%M:%fZ', 'now'));
+        END;
+        CREATE TRIGGER IF NOT EXISTS tombstone_skill_delete AFTER DELETE ON skills BEGIN
+            INSERT INTO tombstones (entity_type, entity_id, project_id, deleted_at)
+            VALUES ('skill', OLD.id, OLD.project_id, strftime('%Y-%m-%dT%H:%M:%fZ', 'now'));
+        END;",
+    )?;
+    Ok(())
+}
+
+/// Migration 4: `session_messages.parent_sequence_num` pairs a tool result with
+/// the `tool_use` it answers. Existing rows stay NULL until their session is re-parsed.
+fn add_message_parent_sequence(conn: &Connection) -> Result<()> {
+    add_column_if_missing(conn, "session_messages", "parent_sequence_num", "INTEGER")
+}
+
+/// Migration 5: `session_intents`, the requests a user made in a session
+/// (see `ai::intent`). `source` is `heuristic` or `ai`.
+fn create_session_intents(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS session_intents (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            session_id TEXT NOT NULL,
+            sequence_num INTEGER,
+            intent TEXT NOT NULL,
+            source TEXT NOT NULL CHECK (source IN ('heuristic', 'ai')),
+            created_at TEXT NOT NULL,
+            FOREIGN KEY (session_id) REFERENCES sessions(id) ON DELETE CASCADE
+        );
+        CREATE INDEX IF NOT EXISTS idx_session_intents_session ON session_intents(session_id);",
+    )?;
+    Ok(())
+}
+
+/// Migration 6: `scheduler_runs`, one row per scheduler task execution
+/// (see `scheduler::record_run`), trimmed to `scheduler.history_retention` per task.
+fn create_scheduler_runs(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS scheduler_runs (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            task_name TEXT NOT NULL,
+            started_at TEXT NOT NULL,
+            finished_at TEXT NOT NULL,
+            items_processed INTEGER NOT NULL,
+            items_affected INTEGER NOT NULL,
+            errors INTEGER NOT NULL,
+            detail TEXT NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS idx_scheduler_runs_task ON scheduler_runs(task_name, id);",
+    )?;
+    Ok(())
+}
+
+/// Migration 7: `scheduler_runs.skipped` marks ticks that didn't run because the
+/// task's previous run was still active.
+fn add_scheduler_run_skipped(conn: &Connection) -> Result<()> {
+    add_column_if_missing(
+        conn,
+        "scheduler_runs",
+        "skipped",
+        "INTEGER NOT NULL DEFAULT 0",
+    )
+}
+
+/// Migration 8: `project_ai_settings` overrides the global AI feature toggles
+/// for one project. NULL columns fall back to the global setting.
+fn create_project_ai_settings(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS project_ai_settings (
+            project_id TEXT PRIMARY KEY,
+            title_generation INTEGER,
+            memory_extraction INTEGER,
+            skills_discovery INTEGER,
+            updated_at TEXT NOT NULL,
+            FOREIGN KEY (project_id) REFERENCES projects(id) ON DELETE CASCADE
+        );",
+    )?;
+    Ok(())
+}
+
+/// Migration 9: `sessions.parse_errors` counts the file's unparseable lines and
+/// `parse_error_lines` holds a JSON array sampling their line numbers.
+fn add_session_parse_errors(conn: &Connection) -> Result<()> {
+    add_column_if_missing(
+        conn,
+        "sessions",
+        "parse_errors",
+        "INTEGER NOT NULL DEFAULT 0",
+    )?;
+    add_column_if_missing(conn, "sessions", "parse_error_lines", "TEXT")
+}
+
+/// Migration 10: `session_messages.code_language`, detected by the parser from
+/// fenced-code info strings and tool file paths. Existing rows stay NULL until
+/// their session is re-parsed.
+fn add_message_code_language(conn: &Connection) -> Result<()> {
+    add_column_if_missing(conn, "session_messages", "code_language", "TEXT")
+}
+
+/// Migration 11: `sessions.content_hash`, the blake3 hash of the session file
+/// as of its last parse, used by `watch.dedupe_by_hash`. Existing rows stay
+/// NULL until their file changes.
+fn add_session_content_hash(conn: &Connection) -> Result<()> {
+    add_column_if_missing(conn, "sessions", "content_hash", "TEXT")?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_sessions_content_hash ON sessions(content_hash)
+         WHERE content_hash IS NOT NULL",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Migration 12: `session_tags` holds the tags users put on sessions, one row
+/// per (session, tag).
+fn create_session_tags(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS session_tags (
+            session_id TEXT NOT NULL,
+            tag TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            PRIMARY KEY (session_id, tag),
+            FOREIGN KEY (session_id) REFERENCES sessions(id) ON DELETE CASCADE
+        );
+        CREATE INDEX IF NOT EXISTS idx_session_tags_tag ON session_tags(tag);",
+    )?;
+    Ok(())
+}
+
+/// Migration 13: `session_messages.tokens_estimated` marks token counts
+/// estimated from the message text (`parser.estimate_tokens`) rather than
+/// reported by the provider.
+fn add_message_tokens_estimated(conn: &Connection) -> Result<()> {
+    add_column_if_missing(
+        conn,
+        "session_messages",
+        "tokens_estimated",
+        "INTEGER NOT NULL DEFAULT 0",
+    )
+}
+
+/// Migration 14: `message_embeddings` holds per-message vectors for semantic
+/// search within a session. Rows go away with their message, so a full
+/// re-parse of the session drops them.
+fn create_message_embeddings(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS message_embeddings (
+            message_id INTEGER PRIMARY KEY,
+            embedding BLOB NOT NULL,
+            FOREIGN KEY (message_id) REFERENCES session_messages(id) ON DELETE CASCADE
+        );",
+    )?;
+    Ok(())
+}
+
+/// Migration 15: `sessions.deleted_at` marks a session deleted through the API
+/// but kept for restore until the `session_purge` scheduler task removes it.
+fn add_session_deleted_at(conn: &Connection) -> Result<()> {
+    add_column_if_missing(conn, "sessions", "deleted_at", "TEXT")
+}
+
+/// Migration 16: `sessions.git_branch` and `sessions.git_commit`, detected from
+/// the transcript by the parser (NULL when unknown or ambiguous).
+fn add_session_git_context(conn: &Connection) -> Result<()> {
+    add_column_if_missing(conn, "sessions", "git_branch", "TEXT")?;
+    add_column_if_missing(conn, "sessions", "git_commit", "TEXT")?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_sessions_git_branch ON sessions(git_branch) WHERE git_branch IS NOT NULL",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Migration 17: a tombstone when a session is soft-deleted, so delta sync
+/// reports it like a hard delete. Restoring bumps `indexed_at`, which sends the
+/// session again.
+fn create_session_soft_delete_tombstone(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TRIGGER IF NOT EXISTS tombstone_session_soft_delete AFTER UPDATE OF deleted_at ON sessions
+        WHEN NEW.deleted_at IS NOT NULL AND OLD.deleted_at IS NULL BEGIN
+            INSERT INTO tombstones (entity_type, entity_id, project_id, deleted_at)
+            VALUES ('session', OLD.id, OLD.project_id, strftime('%Y-%m-%dT%H:%M:%fZ', 'now'));
+        END;",
+    )?;
+    Ok(())
+}
+
+/// Migration 18: `updated_at` on memories and skills, for delta sync. Triggers
+/// set it on insert and whenever a synced column changes (not on access
+/// tracking); existing rows start at their `extracted_at`.
+fn add_memory_skill_updated_at(conn: &Connection) -> Result<()> {
+    add_column_if_missing(conn, "memories", "updated_at", "TEXT")?;
+    add_column_if_missing(conn, "skills", "updated_at", "TEXT")?;
+    conn.execute_batch(
+        "UPDATE memories SET updated_at = extracted_at WHERE updated_at IS NULL;
+        UPDATE skills SET updated_at = extracted_at WHERE updated_at IS NULL;
+        CREATE INDEX IF NOT EXISTS idx_memories_updated_at ON memories(updated_at);
+        CREATE INDEX IF NOT EXISTS idx_skills_updated_at ON skills(updated_at);
+
+        CREATE TRIGGER IF NOT EXISTS memories_updated_at_insert AFTER INSERT ON memories
+        WHEN NEW.updated_at IS NULL BEGIN
+            UPDATE memories SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now') WHERE id = NEW.id;
+        END;
+        CREATE TRIGGER IF NOT EXISTS memories_updated_at_update
+        AFTER UPDATE OF project_id, session_id, memory_type, title, content, context, tags,
+            confidence, is_validated, state, file_reference ON memories
+        WHEN NEW.updated_at IS OLD.updated_at BEGIN
+            UPDATE memories SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now') WHERE id = NEW.id;
+        END;
+        CREATE TRIGGER IF NOT EXISTS skills_updated_at_insert AFTER INSERT ON skills
+        WHEN NEW.updated_at IS NULL BEGIN
+            UPDATE skills SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now') WHERE id = NEW.id;
+        END;
+        CREATE TRIGGER IF NOT EXISTS skills_updated_at_update
+        AFTER UPDATE OF project_id, session_id, name, description, steps, confidence ON skills
+        WHEN NEW.updated_at IS OLD.updated_at BEGIN
+            UPDATE skills SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now') WHERE id = NEW.id;
+        END;",
+    )?;
+    Ok(())
+}
+
+/// Migration 19: `sessions.git_state`, the parser's git detection state (JSON)
+/// at the end of the file, so appended lines continue from it instead of
+/// starting over.
+fn add_session_git_state(conn: &Connection) -> Result<()> {
+    add_column_if_missing(conn, "sessions", "git_state", "TEXT")
+}
+
 /// Run schema migrations for existing databases
 fn run_migrations(conn: &Connection) -> Result<()> {
-    // Add title_ai_generated column if missing (existing DBs won't have it)
-    let has_column: bool = conn
-        .prepare(
-            "SELECT COUNT(*) FROM pragma_table_info('sessions') WHERE name = 'title_ai_generated'",
-        )?
-        .query_row([], |row| row.get::<_, i64>(0))
-        .map(|count| count > 0)?;
+    apply_migrations(conn, MIGRATIONS)?;
+    Ok(())
+}
+
+/// Apply migrations newer than the database's `user_version`, each in its own
+/// transaction together with the version bump. Returns the number applied.
+fn apply_migrations(conn: &Connection, migrations: &[Migration]) -> Result<usize> {
+    let current: u32 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    let latest = migrations.last().map(|m| m.version).unwrap_or(0);
+    if current > latest {
+        tracing::warn!(
+            "Database schema version {} is newer than this build ({})",
+            current,
+            latest
+        );
+    }
+
+    let mut applied = 0;
+    for migration in migrations.iter().filter(|m| m.version > current) {
+        let tx = conn.unchecked_transaction()?;
+        (migration.up)(&tx)?;
+        tx.pragma_update(None, "user_version", migration.version)?;
+        tx.commit()?;
+        tracing::info!(
+            "Applied schema migration {}: {}",
+            migration.version,
+            migration.description
+        );
+        applied += 1;
+    }
+    Ok(applied)
+}
 
-    if !has_column {
+/// Add a column unless the table already has it
+fn add_column_if_missing(
+    conn: &Connection,
+    table: &str,
+    column: &str,
+    definition: &str,
+) -> Result<()> {
+    let exists: bool = conn.query_row(
+        "SELECT COUNT(*) > 0 FROM pragma_table_info(?1) WHERE name = ?2",
+        [table, column],
+        |row| row.get(0),
+    )?;
+    if !exists {
         conn.execute(
-            "ALTER TABLE sessions ADD COLUMN title_ai_generated BOOLEAN NOT NULL DEFAULT 0",
+            &format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition),
             [],
         )?;
     }
+    Ok(())
+}
 
-    // Yolo mode: all projects always sync (auto_sync = 1)
-    conn.execute("UPDATE projects SET auto_sync = 1 WHERE auto_sync = 0", [])?;
-
+/// Migration 1: columns that databases created by older releases may lack.
+/// Includes everything `Core::recover_pending_ai_tasks` queries.
+fn backfill_session_columns(conn: &Connection) -> Result<()> {
+    for (column, definition) in [
+        ("title_edited", "BOOLEAN NOT NULL DEFAULT 0"),
+        ("title_ai_generated", "BOOLEAN NOT NULL DEFAULT 0"),
+        ("memories_extracted_at", "TEXT"),
+        ("memories_extracted_count", "INTEGER DEFAULT 0"),
+        ("skills_extracted_at", "TEXT"),
+        ("skills_extracted_count", "INTEGER DEFAULT 0"),
+        ("import_status", "TEXT DEFAULT 'success'"),
+        ("import_error", "TEXT"),
+        ("is_hidden", "BOOLEAN NOT NULL DEFAULT 0"),
+        ("parent_session_id", "TEXT"),
+    ] {
+        add_column_if_missing(conn, "sessions", column, definition)?;
+    }
     Ok(())
 }
 
@@ -277,6 +735,11 @@ fn create_indexes(conn: &Connection) -> Result<()> {
         [],
     )?;
 
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_sessions_parent ON sessions(parent_session_id) WHERE parent_session_id IS NOT NULL",
+        [],
+    )?;
+
     // Message indexes
     conn.execute(
         "CREATE INDEX IF NOT EXISTS idx_messages_session ON session_messages(session_id, sequence_num)",
@@ -367,6 +830,8 @@ fn create_indexes(conn: &Connection) -> Result<()> {
 
 /// Initialize FTS5 virtual tables for full-text search
 fn init_fts_tables(conn: &Connection) -> Result<()> {
+    let tokenizer = FtsTokenizer::default();
+
     // Check if FTS5 tables already exist
     let messages_fts_exists: bool = conn
         .prepare(
@@ -376,42 +841,7 @@ fn init_fts_tables(conn: &Connection) -> Result<()> {
         .unwrap_or(false);
 
     if !messages_fts_exists {
-        // FTS5 for session messages
-        conn.execute(
-            "CREATE VIRTUAL TABLE session_messages_fts USING fts5(
-                search_content,
-                content='session_messages', content_rowid='id',
-                tokenize='porter unicode61'
-            )",
-            [],
-        )?;
-
-        // Triggers for session_messages_fts
-        conn.execute(
-            "CREATE TRIGGER session_messages_fts_ai AFTER INSERT ON session_messages BEGIN
-                INSERT INTO session_messages_fts(rowid, search_content)
-                VALUES (new.id, new.search_content);
-            END",
-            [],
-        )?;
-
-        conn.execute(
-            "CREATE TRIGGER session_messages_fts_ad AFTER DELETE ON session_messages BEGIN
-                INSERT INTO session_messages_fts(session_messages_fts, rowid, search_content)
-                VALUES ('delete', old.id, old.search_content);
-            END",
-            [],
-        )?;
-
-        conn.execute(
-            "CREATE TRIGGER session_messages_fts_au AFTER UPDATE ON session_messages BEGIN
-                INSERT INTO session_messages_fts(session_messages_fts, rowid, search_content)
-                VALUES ('delete', old.id, old.search_content);
-                INSERT INTO session_messages_fts(rowid, search_content)
-                VALUES (new.id, new.search_content);
-            END",
-            [],
-        )?;
+        create_session_messages_fts(conn, tokenizer)?;
     }
 
     // Check if memories FTS exists
@@ -421,42 +851,7 @@ fn init_fts_tables(conn: &Connection) -> Result<()> {
         .unwrap_or(false);
 
     if !memories_fts_exists {
-        // FTS5 for memories
-        conn.execute(
-            "CREATE VIRTUAL TABLE memories_fts USING fts5(
-                title, content, context, tags,
-                content='memories', content_rowid='id',
-                tokenize='porter unicode61'
-            )",
-            [],
-        )?;
-
-        // Triggers for memories_fts
-        conn.execute(
-            "CREATE TRIGGER memories_ai AFTER INSERT ON memories BEGIN
-                INSERT INTO memories_fts(rowid, title, content, context, tags)
-                VALUES (new.id, new.title, new.content, new.context, new.tags);
-            END",
-            [],
-        )?;
-
-        conn.execute(
-            "CREATE TRIGGER memories_ad AFTER DELETE ON memories BEGIN
-                INSERT INTO memories_fts(memories_fts, rowid, title, content, context, tags)
-                VALUES ('delete', old.id, old.title, old.content, old.context, old.tags);
-            END",
-            [],
-        )?;
-
-        conn.execute(
-            "CREATE TRIGGER memories_au AFTER UPDATE ON memories BEGIN
-                INSERT INTO memories_fts(memories_fts, rowid, title, content, context, tags)
-                VALUES ('delete', old.id, old.title, old.content, old.context, old.tags);
-                INSERT INTO memories_fts(rowid, title, content, context, tags)
-                VALUES (new.id, new.title, new.content, new.context, new.tags);
-            END",
-            [],
-        )?;
+        create_memories_fts(conn, tokenizer)?;
     }
 
     // Check if skills FTS exists
@@ -507,6 +902,193 @@ fn init_fts_tables(conn: &Connection) -> Result<()> {
     Ok(())
 }
 
+/// FTS5 index over `session_messages.search_content`, kept in sync by triggers
+fn create_session_messages_fts(conn: &Connection, tokenizer: FtsTokenizer) -> Result<()> {
+    conn.execute(
+        &format!(
+            "CREATE VIRTUAL TABLE session_messages_fts USING fts5(
+                search_content,
+                content='session_messages', content_rowid='id',
+                tokenize='{}'
+            )",
+            tokenizer.spec()
+        ),
+        [],
+    )?;
+
+    // Triggers for session_messages_fts
+    conn.execute(
+        "CREATE TRIGGER session_messages_fts_ai AFTER INSERT ON session_messages BEGIN
+            INSERT INTO session_messages_fts(rowid, search_content)
+            VALUES (new.id, new.search_content);
+        END",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TRIGGER session_messages_fts_ad AFTER DELETE ON session_messages BEGIN
+            INSERT INTO session_messages_fts(session_messages_fts, rowid, search_content)
+            VALUES ('delete', old.id, old.search_content);
+        END",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TRIGGER session_messages_fts_au AFTER UPDATE ON session_messages BEGIN
+            INSERT INTO session_messages_fts(session_messages_fts, rowid, search_content)
+            VALUES ('delete', old.id, old.search_content);
+            INSERT INTO session_messages_fts(rowid, search_content)
+            VALUES (new.id, new.search_content);
+        END",
+        [],
+    )?;
+    Ok(())
+}
+
+/// FTS5 index over memory titles, contents, contexts and tags, kept in sync by triggers
+fn create_memories_fts(conn: &Connection, tokenizer: FtsTokenizer) -> Result<()> {
+    conn.execute(
+        &format!(
+            "CREATE VIRTUAL TABLE memories_fts USING fts5(
+                title, content, context, tags,
+                content='memories', content_rowid='id',
+                tokenize='{}'
+            )",
+            tokenizer.spec()
+        ),
+        [],
+    )?;
+
+    // Triggers for memories_fts
+    conn.execute(
+        "CREATE TRIGGER memories_ai AFTER INSERT ON memories BEGIN
+            INSERT INTO memories_fts(rowid, title, content, context, tags)
+            VALUES (new.id, new.title, new.content, new.context, new.tags);
+        END",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TRIGGER memories_ad AFTER DELETE ON memories BEGIN
+            INSERT INTO memories_fts(memories_fts, rowid, title, content, context, tags)
+            VALUES ('delete', old.id, old.title, old.content, old.context, old.tags);
+        END",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TRIGGER memories_au AFTER UPDATE ON memories BEGIN
+            INSERT INTO memories_fts(memories_fts, rowid, title, content, context, tags)
+            VALUES ('delete', old.id, old.title, old.content, old.context, old.tags);
+            INSERT INTO memories_fts(rowid, title, content, context, tags)
+            VALUES (new.id, new.title, new.content, new.context, new.tags);
+        END",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Switch `session_messages_fts` and `memories_fts` to `tokenizer`, rebuilding
+/// each index whose tokenizer differs. A rebuild re-tokenizes every message or
+/// memory, which can take minutes on a large database. Returns the number of
+/// indexes rebuilt.
+pub fn set_fts_tokenizer(conn: &Connection, tokenizer: FtsTokenizer) -> Result<usize> {
+    type CreateFts = fn(&Connection, FtsTokenizer) -> Result<()>;
+    let indexes: [(&str, &str, CreateFts); 2] = [
+        (
+            "session_messages_fts",
+            "session_messages_fts_a",
+            create_session_messages_fts,
+        ),
+        ("memories_fts", "memories_a", create_memories_fts),
+    ];
+
+    let mut rebuilt = 0;
+    for (table, trigger_prefix, create) in indexes {
+        let sql: String = conn.query_row(
+            "SELECT sql FROM sqlite_master WHERE type = 'table' AND name = ?",
+            [table],
+            |row| row.get(0),
+        )?;
+        if sql.contains(&format!("tokenize='{}'", tokenizer.spec())) {
+            continue;
+        }
+
+        tracing::warn!(
+            "Rebuilding {} with tokenizer '{}', this may take a while",
+            table,
+            tokenizer.spec()
+        );
+        let tx = conn.unchecked_transaction()?;
+        for suffix in ["i", "d", "u"] {
+            tx.execute(
+                &format!("DROP TRIGGER IF EXISTS {}{}", trigger_prefix, suffix),
+                [],
+            )?;
+        }
+        tx.execute(&format!("DROP TABLE {}", table), [])?;
+        create(&tx, tokenizer)?;
+        tx.execute(
+            &format!("INSERT INTO {table}({table}) VALUES ('rebuild')"),
+            [],
+        )?;
+        tx.commit()?;
+        rebuilt += 1;
+    }
+    Ok(rebuilt)
+}
+
+/// Base tables past this many rows get a warning before their index is rebuilt
+const LARGE_FTS_REBUILD_ROWS: usize = 100_000;
+
+/// One full-text index rebuilt by [`rebuild_fts`]
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct FtsRebuild {
+    pub table: &'static str,
+    /// Rows in the base table that were re-indexed
+    pub rows: usize,
+    pub duration_ms: u64,
+}
+
+/// Rebuild `session_messages_fts` and `memories_fts` from their base tables,
+/// e.g. after the index has drifted. Each index is rebuilt in its own
+/// transaction, so searches see either the old or the new index.
+pub fn rebuild_fts(conn: &Connection) -> Result<Vec<FtsRebuild>> {
+    let mut rebuilt = Vec::new();
+    for (table, base) in [
+        ("session_messages_fts", "session_messages"),
+        ("memories_fts", "memories"),
+    ] {
+        let started = std::time::Instant::now();
+        let rows: i64 = conn.query_row(&format!("SELECT COUNT(*) FROM {}", base), [], |row| {
+            row.get(0)
+        })?;
+        let rows = rows as usize;
+        if rows >= LARGE_FTS_REBUILD_ROWS {
+            tracing::warn!(
+                "Rebuilding {} over {} rows, this may take a while",
+                table,
+                rows
+            );
+        } else {
+            tracing::info!("Rebuilding {} over {} rows", table, rows);
+        }
+
+        let tx = conn.unchecked_transaction()?;
+        tx.execute(
+            &format!("INSERT INTO {table}({table}) VALUES ('rebuild')"),
+            [],
+        )?;
+        tx.commit()?;
+        rebuilt.push(FtsRebuild {
+            table,
+            rows,
+            duration_ms: started.elapsed().as_millis() as u64,
+        });
+    }
+    Ok(rebuilt)
+}
+
 /// Get or create a persistent instance UUID.
 /// Generated on first startup, persists across restarts.
 pub fn get_or_create_instance_uuid(conn: &Connection) -> Result<String> {
@@ -543,6 +1125,7 @@ pub fn set_instance_name(conn: &Connection, name: Option<&str>) -> Result<()> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_support;
 
     #[test]
     fn test_init_db() {
@@ -564,4 +1147,172 @@ mod tests {
             table_count
         );
     }
+
+    static TEST_MIGRATION_RUNS: std::sync::atomic::AtomicUsize =
+        std::sync::atomic::AtomicUsize::new(0);
+
+    fn counting_migration(conn: &Connection) -> Result<()> {
+        TEST_MIGRATION_RUNS.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        conn.execute("CREATE TABLE counted (id INTEGER PRIMARY KEY)", [])?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_old_schema_is_migrated_once() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("old.db");
+
+        // A sessions table as shipped before the AI and sub-agent columns
+        {
+            let conn = Connection::open(&path).unwrap();
+            conn.execute_batch(
+                "CREATE TABLE projects (
+                    id TEXT PRIMARY KEY, name TEXT NOT NULL, folder_path TEXT NOT NULL UNIQUE,
+                    description TEXT, repo_url TEXT, language TEXT, framework TEXT,
+                    auto_sync BOOLEAN NOT NULL DEFAULT 1, longest_streak INTEGER NOT NULL DEFAULT 0,
+                    created_at TEXT NOT NULL, updated_at TEXT NOT NULL
+                );
+                CREATE TABLE sessions (
+                    id TEXT PRIMARY KEY, project_id TEXT NOT NULL, file_path TEXT NOT NULL UNIQUE,
+                    title TEXT, ai_tool TEXT NOT NULL, message_count INTEGER NOT NULL DEFAULT 0,
+                    duration_ms INTEGER, has_code BOOLEAN NOT NULL DEFAULT 0,
+                    has_errors BOOLEAN NOT NULL DEFAULT 0, file_size INTEGER, file_modified TEXT,
+                    archived_file_path TEXT, archived_at TEXT,
+                    created_at TEXT NOT NULL, indexed_at TEXT NOT NULL
+                );
+                INSERT INTO projects (id, name, folder_path, created_at, updated_at)
+                    VALUES ('p1', 'proj', '/tmp/proj', 'now', 'now');
+                INSERT INTO sessions (id, project_id, file_path, ai_tool, created_at, indexed_at)
+                    VALUES ('s1', 'p1', '/tmp/proj/s1.jsonl', 'Claude Code', 'now', 'now');",
+            )
+            .unwrap();
+        }
+
+        let conn = Connection::open(&path).unwrap();
+        init_db(&conn).unwrap();
+
+        let version: u32 = conn
+            .query_row("PRAGMA user_version", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(version, latest_version());
+        // Columns used by AI task recovery and sub-agent linking now exist
+        let (needs_memory, parent): (bool, Option<String>) = conn
+            .query_row(
+                "SELECT memories_extracted_at IS NULL, parent_session_id FROM sessions WHERE id = 's1'",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert!(needs_memory);
+        assert!(parent.is_none());
+
+        // Reopening applies nothing
+        assert_eq!(apply_migrations(&conn, MIGRATIONS).unwrap(), 0);
+
+        // A new migration runs exactly once across restarts
+        let migrations = [Migration {
+            version: latest_version() + 1,
+            description: "counted",
+            up: counting_migration,
+        }];
+        assert_eq!(apply_migrations(&conn, &migrations).unwrap(), 1);
+        assert_eq!(apply_migrations(&conn, &migrations).unwrap(), 0);
+        assert_eq!(
+            TEST_MIGRATION_RUNS.load(std::sync::atomic::Ordering::SeqCst),
+            1
+        );
+    }
+
+    #[test]
+    fn test_duplicate_projects_are_merged() {
+        let conn = Connection::open_in_memory().unwrap();
+        // Tables as created before projects.folder_path was UNIQUE
+        conn.execute_batch(
+            "CREATE TABLE projects (
+                id TEXT PRIMARY KEY, name TEXT NOT NULL, folder_path TEXT NOT NULL,
+                description TEXT, repo_url TEXT, language TEXT, framework TEXT,
+                auto_sync BOOLEAN NOT NULL DEFAULT 1, longest_streak INTEGER NOT NULL DEFAULT 0,
+                created_at TEXT NOT NULL, updated_at TEXT NOT NULL
+            );
+            CREATE TABLE sessions (
+                id TEXT PRIMARY KEY, project_id TEXT NOT NULL, file_path TEXT NOT NULL UNIQUE,
+                title TEXT, ai_tool TEXT NOT NULL, message_count INTEGER NOT NULL DEFAULT 0,
+                duration_ms INTEGER, has_code BOOLEAN NOT NULL DEFAULT 0,
+                has_errors BOOLEAN NOT NULL DEFAULT 0, file_size INTEGER, file_modified TEXT,
+                archived_file_path TEXT, archived_at TEXT,
+                created_at TEXT NOT NULL, indexed_at TEXT NOT NULL
+            );
+            INSERT INTO projects (id, name, folder_path, created_at, updated_at) VALUES
+                ('newer', 'b', '/work/app', '2024-02-01', '2024-02-01'),
+                ('older', 'a', '/work/app', '2024-01-01', '2024-01-01'),
+                ('other', 'c', '/work/other', '2024-01-01', '2024-01-01');
+            INSERT INTO sessions (id, project_id, file_path, ai_tool, created_at, indexed_at)
+                VALUES ('s1', 'newer', '/work/app/s1.jsonl', 'Claude Code', 'now', 'now');",
+        )
+        .unwrap();
+        init_db(&conn).unwrap();
+
+        let ids: Vec<String> = conn
+            .prepare("SELECT id FROM projects ORDER BY id")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .map(|r| r.unwrap())
+            .collect();
+        assert_eq!(ids, vec!["older", "other"]);
+
+        let session_project: String = conn
+            .query_row(
+                "SELECT project_id FROM sessions WHERE id = 's1'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(session_project, "older");
+
+        let duplicate = conn.execute(
+            "INSERT INTO projects (id, name, folder_path, created_at, updated_at)
+             VALUES ('again', 'd', '/work/app', 'now', 'now')",
+            [],
+        );
+        assert!(duplicate.is_err());
+    }
+
+    #[test]
+    fn test_trigram_tokenizer_matches_mid_identifier() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_db(&conn).unwrap();
+        test_support::seed_session(&conn, "s1");
+        conn.execute_batch(
+            "INSERT INTO session_messages (session_id, sequence_num, role, content_preview, search_content, timestamp)
+                VALUES ('s1', 0, 'assistant', '', 'Renamed ProviderChainFallback in ai/mod.rs', 'now');",
+        )
+        .unwrap();
+        let matches = |query: &str| -> i64 {
+            conn.query_row(
+                "SELECT COUNT(*) FROM session_messages_fts WHERE session_messages_fts MATCH ?",
+                [query],
+                |row| row.get(0),
+            )
+            .unwrap()
+        };
+        // The default tokenizer only matches whole words
+        assert_eq!(matches("ChainFall"), 0);
+
+        // Switching rebuilds the index over existing rows
+        assert_eq!(set_fts_tokenizer(&conn, FtsTokenizer::Trigram).unwrap(), 2);
+        assert_eq!(matches("ChainFall"), 1);
+        assert_eq!(matches("NotThere"), 0);
+        // New rows go through the recreated triggers
+        conn.execute(
+            "INSERT INTO session_messages (session_id, sequence_num, role, content_preview, search_content, timestamp)
+                VALUES ('s1', 1, 'user', '', 'call ProviderChainFallback::new', 'now')",
+            [],
+        )
+        .unwrap();
+        assert_eq!(matches("ChainFall"), 2);
+
+        // Already using it: nothing to rebuild
+        assert_eq!(set_fts_tokenizer(&conn, FtsTokenizer::Trigram).unwrap(), 0);
+    }
 }
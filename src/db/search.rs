@@ -0,0 +1,350 @@
+//! Message search queries shared by the HTTP API and the MCP server
+
+/// `created_after` / `created_before` bounds on a session's `created_at`
+#[derive(Debug, Clone, Default)]
+pub(crate) struct CreatedRange {
+    after: Option<chrono::DateTime<chrono::Utc>>,
+    before: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl CreatedRange {
+    /// Parse RFC 3339 bounds; the error names the offending parameter
+    pub(crate) fn parse(after: Option<&str>, before: Option<&str>) -> Result<Self, String> {
+        let parse = |name: &str, value: Option<&str>| {
+            value
+                .map(|v| {
+                    chrono::DateTime::parse_from_rfc3339(v)
+                        .map(|t| t.with_timezone(&chrono::Utc))
+                        .map_err(|_| format!("{} must be an RFC 3339 timestamp", name))
+                })
+                .transpose()
+        };
+        Ok(Self {
+            after: parse("created_after", after)?,
+            before: parse("created_before", before)?,
+        })
+    }
+
+    pub(crate) fn bound(t: &chrono::DateTime<chrono::Utc>) -> String {
+        t.to_rfc3339_opts(chrono::SecondsFormat::Millis, true)
+    }
+
+    /// `" AND ..."` conditions on `column`, appending their bound values to `params`.
+    /// julianday() compares timestamps regardless of offset style or precision.
+    pub(crate) fn sql(&self, column: &str, params: &mut Vec<String>) -> String {
+        let mut sql = String::new();
+        if let Some(after) = &self.after {
+            sql.push_str(&format!(" AND julianday({column}) >= julianday(?)"));
+            params.push(Self::bound(after));
+        }
+        if let Some(before) = &self.before {
+            sql.push_str(&format!(" AND julianday({column}) <= julianday(?)"));
+            params.push(Self::bound(before));
+        }
+        sql
+    }
+
+    /// Whether an RFC 3339 `created_at` falls inside the range (ephemeral mode)
+    pub(crate) fn contains(&self, created_at: &str) -> bool {
+        let Ok(t) = chrono::DateTime::parse_from_rfc3339(created_at) else {
+            return self.after.is_none() && self.before.is_none();
+        };
+        self.after.is_none_or(|after| t >= after) && self.before.is_none_or(|before| t <= before)
+    }
+}
+
+/// Message filters shared by fulltext, hybrid, and ephemeral search
+#[derive(Debug, Clone, Default)]
+pub(crate) struct SearchFilters {
+    /// "all", "user", "assistant", or "tool"
+    pub role: Option<String>,
+    /// Only messages with code
+    pub has_code: Option<bool>,
+    /// Code language, aliases allowed ("py" matches "python")
+    pub language: Option<String>,
+    pub tool_name: Option<String>,
+    /// "use" or "result"
+    pub tool_type: Option<String>,
+}
+
+impl SearchFilters {
+    /// The language filter as stored in `code_language`
+    pub(crate) fn language_value(&self) -> Option<String> {
+        self.language.as_deref().map(|language| {
+            crate::parser::common::normalize_language(language)
+                .unwrap_or_else(|| language.to_lowercase())
+        })
+    }
+
+    /// `" AND ..."` conditions on the `m` (session_messages) and `s` (sessions)
+    /// aliases, appending their bound values to `params`
+    pub(crate) fn sql(&self, params: &mut Vec<String>) -> String {
+        let mut filter_clauses = String::new();
+
+        // Exclude soft-deleted sessions
+        filter_clauses.push_str(" AND s.deleted_at IS NULL");
+
+        // Exclude system messages
+        filter_clauses.push_str(" AND m.role != 'system'");
+
+        // Exclude Write/Edit tool_type='use' - redundant with tool_type='result'
+        filter_clauses.push_str(
+            " AND (m.tool_type IS NULL OR m.tool_type != 'use' OR m.tool_name NOT IN ('Write', 'Edit'))",
+        );
+
+        // Apply role filter
+        if let Some(role) = self.role.as_deref() {
+            match role {
+                "all" => {}
+                "tool" => filter_clauses.push_str(" AND m.tool_type IS NOT NULL"),
+                "user" => filter_clauses.push_str(" AND m.role = 'user' AND m.tool_type IS NULL"),
+                "assistant" => {
+                    filter_clauses.push_str(" AND m.role = 'assistant' AND m.tool_type IS NULL")
+                }
+                _ => {}
+            }
+        }
+
+        // Apply has_code filter
+        if self.has_code == Some(true) {
+            filter_clauses.push_str(" AND m.has_code = 1");
+        }
+
+        if let Some(language) = self.language_value() {
+            filter_clauses.push_str(" AND m.code_language = ?");
+            params.push(language);
+        }
+        if let Some(tool_name) = &self.tool_name {
+            filter_clauses.push_str(" AND m.tool_name = ?");
+            params.push(tool_name.clone());
+        }
+        if let Some(tool_type) = &self.tool_type {
+            filter_clauses.push_str(" AND m.tool_type = ?");
+            params.push(tool_type.clone());
+        }
+        filter_clauses
+    }
+}
+
+/// Rewrite a user's search query into FTS5 syntax that can't fail to parse.
+///
+/// Words and `"quoted phrases"` become quoted FTS5 strings, so punctuation and
+/// column-filter colons inside them are literal; a trailing `*` keeps prefix
+/// matching. `AND`, `OR` and `NOT` stay operators only between two terms, and
+/// parentheses and unmatched quotes are dropped. Returns an empty string when
+/// nothing searchable is left.
+pub(crate) fn fts_match_query(query: &str) -> String {
+    enum Token {
+        Term(String),
+        Operator(&'static str),
+    }
+
+    let is_separator = |c: char| c.is_whitespace() || c == '(' || c == ')';
+    let mut tokens = Vec::new();
+    let mut rest = query;
+    while let Some(start) = rest.find(|c: char| !is_separator(c)) {
+        rest = &rest[start..];
+        let closed_phrase = rest
+            .strip_prefix('"')
+            .and_then(|inner| Some((inner, inner.find('"')?)));
+        let (text, phrase) = match closed_phrase {
+            Some((inner, close)) => {
+                rest = &inner[close + 1..];
+                (&inner[..close], true)
+            }
+            None => {
+                let end = rest.find(is_separator).unwrap_or(rest.len());
+                let word = &rest[..end];
+                rest = &rest[end..];
+                (word, false)
+            }
+        };
+        // `word*` and `"phrase"*` ask for prefix matches
+        let (text, prefix) = if phrase {
+            match rest.strip_prefix('*') {
+                Some(after) => {
+                    rest = after;
+                    (text, true)
+                }
+                None => (text, false),
+            }
+        } else {
+            match text.strip_suffix('*') {
+                Some(stem) => (stem, true),
+                None => (text, false),
+            }
+        };
+
+        match text {
+            "AND" if !phrase => tokens.push(Token::Operator("AND")),
+            "OR" if !phrase => tokens.push(Token::Operator("OR")),
+            "NOT" if !phrase => tokens.push(Token::Operator("NOT")),
+            _ => {
+                // Unmatched quotes
+                let text = text.replace('"', "");
+                if !text.trim().is_empty() {
+                    let star = if prefix { "*" } else { "" };
+                    tokens.push(Token::Term(format!("\"{}\"{}", text, star)));
+                }
+            }
+        }
+    }
+
+    let mut parts: Vec<&str> = Vec::new();
+    let mut after_term = false;
+    for (i, token) in tokens.iter().enumerate() {
+        match token {
+            Token::Term(term) => {
+                parts.push(term);
+                after_term = true;
+            }
+            Token::Operator(op) => {
+                if after_term && matches!(tokens.get(i + 1), Some(Token::Term(_))) {
+                    parts.push(op);
+                    after_term = false;
+                }
+            }
+        }
+    }
+    parts.join(" ")
+}
+
+/// Whether `e` is FTS5 rejecting a `MATCH` query, rather than a database failure
+pub(crate) fn is_fts_syntax_error(e: &rusqlite::Error) -> bool {
+    match e {
+        rusqlite::Error::SqliteFailure(_, Some(message)) => {
+            message.starts_with("fts5:")
+                || message.starts_with("no such column")
+                || message.starts_with("unknown special query")
+        }
+        _ => false,
+    }
+}
+
+/// FTS5 `snippet()` call wrapping matched terms in `[match]...[/match]`
+pub(crate) const SNIPPET_SQL: &str =
+    "snippet(session_messages_fts, 0, '[match]', '[/match]', '...', 16) as highlighted_snippet";
+
+/// Map a search row (session_id .. highlighted snippet, columns 0-13) to the result JSON
+pub(crate) fn search_result_json(row: &rusqlite::Row) -> rusqlite::Result<serde_json::Value> {
+    let score: f64 = row.get(12)?;
+    // Normalize BM25 score (negative, lower is better) to 0-1 scale
+    let normalized_score = 1.0 / (1.0 + (-score).abs());
+
+    Ok(serde_json::json!({
+        "session_id": row.get::<_, String>(0)?,
+        "session_title": row.get::<_, Option<String>>(1)?,
+        "session_file_path": row.get::<_, String>(2)?,
+        "line_number": row.get::<_, i64>(3)?,
+        "preview": row.get::<_, Option<String>>(4)?.unwrap_or_default(),
+        "role": row.get::<_, String>(5)?,
+        "timestamp": row.get::<_, String>(6)?,
+        "tool_name": row.get::<_, Option<String>>(7)?,
+        "tool_type": row.get::<_, Option<String>>(8)?,
+        "has_code": row.get::<_, bool>(9)?,
+        "byte_offset": row.get::<_, i64>(10)?,
+        "byte_length": row.get::<_, i64>(11)?,
+        "relevance_score": normalized_score,
+        "bm25_score": score,
+        "highlighted_snippet": row.get::<_, Option<String>>(13)?,
+    }))
+}
+
+/// `FROM ... WHERE ...` selecting the messages matching an FTS `query` and the
+/// search filters, with its bound values. Rows are aliased `m` (session_messages)
+/// and `s` (sessions).
+pub(crate) fn fulltext_match_sql(
+    query: &str,
+    project_id: Option<&str>,
+    filters: &SearchFilters,
+    created: &CreatedRange,
+) -> (String, Vec<String>) {
+    let mut params = vec![query.to_string()];
+    let project_clause = if let Some(pid) = project_id {
+        params.push(pid.to_string());
+        " AND s.project_id = ?"
+    } else {
+        ""
+    };
+    let created_clause = created.sql("s.created_at", &mut params);
+    let filter_clauses = filters.sql(&mut params);
+    let sql = format!(
+        "FROM session_messages_fts fts
+         JOIN session_messages m ON m.id = fts.rowid
+         JOIN sessions s ON s.id = m.session_id
+         WHERE session_messages_fts MATCH ?{project_clause}{created_clause}{filter_clauses}"
+    );
+    (sql, params)
+}
+
+/// Fulltext search over session messages, best BM25 match first, skipping the
+/// first `offset` matches.
+///
+/// Shared by `POST /api/search` and the MCP `yolog_search_sessions` tool.
+pub(crate) fn fulltext_search(
+    conn: &rusqlite::Connection,
+    query: &str,
+    project_id: Option<&str>,
+    filters: &SearchFilters,
+    created: &CreatedRange,
+    limit: i64,
+    offset: i64,
+) -> rusqlite::Result<Vec<serde_json::Value>> {
+    let (match_sql, params) = fulltext_match_sql(query, project_id, filters, created);
+
+    // Build SQL with all fields needed by Desktop
+    let sql = format!(
+        "SELECT m.session_id, s.title, s.file_path, m.sequence_num, m.content_preview,
+                m.role, m.timestamp, m.tool_name, m.tool_type, m.has_code,
+                m.byte_offset, m.byte_length, bm25(session_messages_fts) as score,
+                {SNIPPET_SQL}
+         {match_sql}
+         ORDER BY score, m.id
+         LIMIT {limit} OFFSET {offset}"
+    );
+
+    let mut stmt = conn.prepare(&sql)?;
+    let results = stmt
+        .query_map(rusqlite::params_from_iter(params), search_result_json)?
+        .filter_map(|r| r.ok())
+        .collect();
+    Ok(results)
+}
+
+/// Number of messages [`fulltext_search`] would match without a limit
+pub(crate) fn fulltext_count(
+    conn: &rusqlite::Connection,
+    query: &str,
+    project_id: Option<&str>,
+    filters: &SearchFilters,
+    created: &CreatedRange,
+) -> rusqlite::Result<i64> {
+    let (match_sql, params) = fulltext_match_sql(query, project_id, filters, created);
+    conn.query_row(
+        &format!("SELECT COUNT(*) {match_sql}"),
+        rusqlite::params_from_iter(params),
+        |row| row.get(0),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fts_match_query() {
+        assert_eq!(
+            fts_match_query("watcher deadlock"),
+            r#""watcher" "deadlock""#
+        );
+        assert_eq!(fts_match_query("foo)"), r#""foo""#);
+        assert_eq!(fts_match_query("(foo OR bar"), r#""foo" OR "bar""#);
+        assert_eq!(fts_match_query("AND"), "");
+        assert_eq!(fts_match_query("foo AND"), r#""foo""#);
+        assert_eq!(fts_match_query("NOT foo NOT bar"), r#""foo" NOT "bar""#);
+        assert_eq!(
+            fts_match_query(r#""exact phrase"* conf* src/lib.rs:12 "unclosed"#),
+            r#""exact phrase"* "conf"* "src/lib.rs:12" "unclosed""#
+        );
+    }
+}
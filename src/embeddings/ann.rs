@@ -0,0 +1,374 @@
+//! Approximate nearest-neighbor index over embeddings.
+//!
+//! A small in-memory HNSW (hierarchical navigable small world) graph. Vectors are
+//! L2-normalized on insert, so similarity is a dot product and matches
+//! `cosine_similarity`. Replacing or removing an id leaves a tombstone that is
+//! skipped in results; the graph is rebuilt from live ids once tombstones pass
+//! `MAX_TOMBSTONE_RATIO` of them.
+
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+/// Neighbors kept per node on upper layers
+const M: usize = 16;
+/// Neighbors kept per node on layer 0
+const M0: usize = 2 * M;
+/// Candidate list size while inserting
+const EF_CONSTRUCTION: usize = 100;
+/// Minimum candidate list size while searching
+const EF_SEARCH: usize = 64;
+/// Upper bound on node levels
+const MAX_LEVEL: usize = 16;
+/// Rebuild once tombstones exceed this fraction of live ids
+const MAX_TOMBSTONE_RATIO: f64 = 0.25;
+
+struct Node {
+    id: i64,
+    vector: Vec<f32>,
+    /// Neighbor node indices, one list per layer this node is on
+    neighbors: Vec<Vec<usize>>,
+    deleted: bool,
+}
+
+/// A node and its distance (1 - similarity) to the current query
+#[derive(PartialEq)]
+struct Candidate {
+    dist: f32,
+    node: usize,
+}
+
+impl Eq for Candidate {}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.dist
+            .total_cmp(&other.dist)
+            .then(self.node.cmp(&other.node))
+    }
+}
+
+/// HNSW index mapping ids (memory ids) to embeddings
+pub struct AnnIndex {
+    nodes: Vec<Node>,
+    /// Live id → node index
+    by_id: HashMap<i64, usize>,
+    entry: Option<usize>,
+    /// splitmix64 state for level assignment (fixed seed keeps builds reproducible)
+    rng: u64,
+}
+
+impl Default for AnnIndex {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AnnIndex {
+    pub fn new() -> Self {
+        AnnIndex {
+            nodes: Vec::new(),
+            by_id: HashMap::new(),
+            entry: None,
+            rng: 0x2545_f491_4f6c_dd1d,
+        }
+    }
+
+    /// Number of live ids
+    pub fn len(&self) -> usize {
+        self.by_id.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.by_id.is_empty()
+    }
+
+    /// Insert `vector` under `id`, replacing any previous vector for it
+    pub fn add(&mut self, id: i64, vector: &[f32]) {
+        self.remove(id);
+
+        let level = self.random_level();
+        let idx = self.nodes.len();
+        self.nodes.push(Node {
+            id,
+            vector: normalize(vector),
+            neighbors: vec![Vec::new(); level + 1],
+            deleted: false,
+        });
+        self.by_id.insert(id, idx);
+
+        let Some(mut entry) = self.entry else {
+            self.entry = Some(idx);
+            return;
+        };
+        let top = self.nodes[entry].neighbors.len() - 1;
+        let query = self.nodes[idx].vector.clone();
+
+        for layer in (level + 1..=top).rev() {
+            entry = self.search_layer(&query, &[entry], 1, layer)[0].node;
+        }
+        let mut entries = vec![entry];
+        for layer in (0..=level.min(top)).rev() {
+            let candidates = self.search_layer(&query, &entries, EF_CONSTRUCTION, layer);
+            let max = if layer == 0 { M0 } else { M };
+            let selected: Vec<usize> = candidates.iter().take(M).map(|c| c.node).collect();
+            for &n in &selected {
+                self.nodes[n].neighbors[layer].push(idx);
+                if self.nodes[n].neighbors[layer].len() > max {
+                    self.prune(n, layer, max);
+                }
+            }
+            self.nodes[idx].neighbors[layer] = selected;
+            entries = candidates.into_iter().map(|c| c.node).collect();
+        }
+
+        if level > top {
+            self.entry = Some(idx);
+        }
+    }
+
+    /// Drop `id` from results. Its node stays in the graph to keep it connected
+    /// until enough tombstones pile up to rebuild without them.
+    pub fn remove(&mut self, id: i64) {
+        if let Some(idx) = self.by_id.remove(&id) {
+            self.nodes[idx].deleted = true;
+            if self.tombstones() as f64 > self.by_id.len() as f64 * MAX_TOMBSTONE_RATIO {
+                self.compact();
+            }
+        }
+    }
+
+    /// Nodes left behind by removed or replaced ids
+    fn tombstones(&self) -> usize {
+        self.nodes.len() - self.by_id.len()
+    }
+
+    /// Rebuild the graph from live nodes, dropping tombstones
+    fn compact(&mut self) {
+        let live: Vec<Node> = std::mem::take(&mut self.nodes)
+            .into_iter()
+            .filter(|n| !n.deleted)
+            .collect();
+        self.by_id.clear();
+        self.entry = None;
+        for node in live {
+            self.add(node.id, &node.vector);
+        }
+    }
+
+    /// The `k` ids most similar to `query` as `(id, cosine similarity)`, best first
+    pub fn search(&self, query: &[f32], k: usize) -> Vec<(i64, f32)> {
+        let Some(mut entry) = self.entry else {
+            return Vec::new();
+        };
+        if k == 0 {
+            return Vec::new();
+        }
+        let query = normalize(query);
+        let top = self.nodes[entry].neighbors.len() - 1;
+        for layer in (1..=top).rev() {
+            entry = self.search_layer(&query, &[entry], 1, layer)[0].node;
+        }
+        // Widen the search by the tombstone count so they can't crowd out live
+        // ids; compaction keeps that count a fraction of the live ones
+        let ef = EF_SEARCH.max(k) + self.tombstones();
+        self.search_layer(&query, &[entry], ef, 0)
+            .into_iter()
+            .filter(|c| !self.nodes[c.node].deleted)
+            .take(k)
+            .map(|c| (self.nodes[c.node].id, 1.0 - c.dist))
+            .collect()
+    }
+
+    fn distance(&self, query: &[f32], node: usize) -> f32 {
+        let dot: f32 = query
+            .iter()
+            .zip(&self.nodes[node].vector)
+            .map(|(a, b)| a * b)
+            .sum();
+        1.0 - dot
+    }
+
+    /// Best-first search of one layer, returning up to `ef` nodes nearest first
+    fn search_layer(
+        &self,
+        query: &[f32],
+        entries: &[usize],
+        ef: usize,
+        layer: usize,
+    ) -> Vec<Candidate> {
+        let mut visited: HashSet<usize> = entries.iter().copied().collect();
+        let mut candidates = BinaryHeap::new();
+        let mut results = BinaryHeap::new();
+        for &node in entries {
+            let dist = self.distance(query, node);
+            candidates.push(Reverse(Candidate { dist, node }));
+            results.push(Candidate { dist, node });
+        }
+        while results.len() > ef {
+            results.pop();
+        }
+
+        while let Some(Reverse(current)) = candidates.pop() {
+            let worst = results.peek().map_or(f32::INFINITY, |c| c.dist);
+            if results.len() >= ef && current.dist > worst {
+                break;
+            }
+            for &node in &self.nodes[current.node].neighbors[layer] {
+                if !visited.insert(node) {
+                    continue;
+                }
+                let dist = self.distance(query, node);
+                let worst = results.peek().map_or(f32::INFINITY, |c| c.dist);
+                if results.len() < ef || dist < worst {
+                    candidates.push(Reverse(Candidate { dist, node }));
+                    results.push(Candidate { dist, node });
+                    if results.len() > ef {
+                        results.pop();
+                    }
+                }
+            }
+        }
+        results.into_sorted_vec()
+    }
+
+    /// Keep only the `max` nearest neighbors of `node` on `layer`
+    fn prune(&mut self, node: usize, layer: usize, max: usize) {
+        let vector = self.nodes[node].vector.clone();
+        let mut scored: Vec<Candidate> = self.nodes[node].neighbors[layer]
+            .iter()
+            .map(|&n| Candidate {
+                dist: self.distance(&vector, n),
+                node: n,
+            })
+            .collect();
+        scored.sort();
+        self.nodes[node].neighbors[layer] = scored.into_iter().take(max).map(|c| c.node).collect();
+    }
+
+    /// Draw a level from the usual HNSW exponential distribution
+    fn random_level(&mut self) -> usize {
+        // splitmix64
+        self.rng = self.rng.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.rng;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^= z >> 31;
+        // Uniform in (0, 1]
+        let uniform = ((z >> 11) as f64 + 1.0) / (1u64 << 53) as f64;
+        let level = -uniform.ln() / (M as f64).ln();
+        (level as usize).min(MAX_LEVEL)
+    }
+}
+
+fn normalize(vector: &[f32]) -> Vec<f32> {
+    let norm = vector.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm < 1e-9 {
+        return vector.to_vec();
+    }
+    vector.iter().map(|x| x / norm).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::embeddings::cosine_similarity;
+
+    /// Deterministic pseudo-random vectors in [-1, 1)
+    fn seeded_vectors(count: usize, dim: usize, seed: u64) -> Vec<Vec<f32>> {
+        let mut state = seed;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state >> 40) as f32 / (1u64 << 24) as f32 * 2.0 - 1.0
+        };
+        (0..count)
+            .map(|_| (0..dim).map(|_| next()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_ann_recall_matches_brute_force() {
+        let vectors = seeded_vectors(500, 32, 7);
+        let mut index = AnnIndex::new();
+        for (id, v) in vectors.iter().enumerate() {
+            index.add(id as i64, v);
+        }
+        assert_eq!(index.len(), 500);
+
+        let k = 10;
+        let queries = seeded_vectors(20, 32, 99);
+        let mut hits = 0;
+        for q in &queries {
+            let mut exact: Vec<(i64, f32)> = vectors
+                .iter()
+                .enumerate()
+                .map(|(id, v)| (id as i64, cosine_similarity(q, v)))
+                .collect();
+            exact.sort_by(|a, b| b.1.total_cmp(&a.1));
+            let exact: HashSet<i64> = exact.iter().take(k).map(|(id, _)| *id).collect();
+
+            let approx = index.search(q, k);
+            assert_eq!(approx.len(), k);
+            // Scores are cosine similarities, best first
+            assert!(approx.windows(2).all(|w| w[0].1 >= w[1].1));
+            let (id, score) = approx[0];
+            assert!((score - cosine_similarity(q, &vectors[id as usize])).abs() < 1e-4);
+            hits += approx.iter().filter(|(id, _)| exact.contains(id)).count();
+        }
+        let recall = hits as f64 / (queries.len() * k) as f64;
+        assert!(recall >= 0.9, "recall {recall}");
+    }
+
+    #[test]
+    fn test_ann_replace_and_remove() {
+        let mut index = AnnIndex::new();
+        assert!(index.search(&[1.0, 0.0], 1).is_empty());
+
+        index.add(1, &[1.0, 0.0]);
+        index.add(2, &[0.0, 1.0]);
+        assert_eq!(index.search(&[1.0, 0.1], 1)[0].0, 1);
+
+        // Re-adding an id replaces its vector
+        index.add(1, &[0.0, -1.0]);
+        assert_eq!(index.len(), 2);
+        assert_eq!(index.search(&[1.0, 0.1], 1)[0].0, 2);
+
+        index.remove(2);
+        let results = index.search(&[0.0, 1.0], 5);
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].0, 1);
+    }
+
+    #[test]
+    fn test_ann_compacts_tombstones() {
+        let vectors = seeded_vectors(100, 16, 3);
+        let mut index = AnnIndex::new();
+        for (id, v) in vectors.iter().enumerate() {
+            index.add(id as i64, v);
+        }
+
+        // 20 of 80 live is at the threshold, so nothing is rebuilt yet
+        for id in 0..20 {
+            index.remove(id);
+        }
+        assert_eq!(index.tombstones(), 20);
+        assert_eq!(index.nodes.len(), 100);
+
+        // One more tips it over and the graph is rebuilt from live ids
+        index.remove(20);
+        assert_eq!(index.tombstones(), 0);
+        assert_eq!(index.nodes.len(), 79);
+        assert_eq!(index.len(), 79);
+
+        let results = index.search(&vectors[50], 5);
+        assert_eq!(results[0].0, 50);
+        assert!(results.iter().all(|(id, _)| *id > 20));
+    }
+}
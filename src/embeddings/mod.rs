@@ -3,10 +3,14 @@
 //! Uses all-MiniLM-L6-v2 model for generating 384-dimensional sentence embeddings.
 //! Ported from desktop/src-tauri/src/embeddings/mod.rs.
 
+mod ann;
+
+pub use ann::AnnIndex;
 use candle_core::{DType, Device, Tensor};
 use candle_nn::VarBuilder;
 use candle_transformers::models::bert::{BertModel, Config, DTYPE};
 use hf_hub::{api::sync::Api, Repo, RepoType};
+use std::path::{Path, PathBuf};
 use std::sync::OnceLock;
 use tokenizers::Tokenizer;
 
@@ -19,6 +23,79 @@ pub const EMBEDDING_DIM: usize = 384;
 /// Global embedding model instance (lazy loaded on first use)
 static EMBEDDING_MODEL: OnceLock<Result<EmbeddingModel, String>> = OnceLock::new();
 
+/// Local model directory (`embeddings.model_path`), set once at startup
+static MODEL_PATH: OnceLock<PathBuf> = OnceLock::new();
+
+/// Files the model is loaded from
+const CONFIG_FILE: &str = "config.json";
+const TOKENIZER_FILE: &str = "tokenizer.json";
+const WEIGHTS_FILE: &str = "model.safetensors";
+
+/// Load the global model from `dir` instead of HuggingFace Hub.
+///
+/// Must be called before the model is first used; later calls are ignored.
+pub fn set_model_path(dir: PathBuf) {
+    if MODEL_PATH.set(dir).is_err() {
+        tracing::warn!("Embedding model path already set; ignoring");
+    }
+}
+
+/// Paths of the config, tokenizer, and weights files
+struct ModelFiles {
+    config: PathBuf,
+    tokenizer: PathBuf,
+    weights: PathBuf,
+}
+
+impl ModelFiles {
+    /// Use the files in a local directory, failing if any is missing
+    fn local(dir: &Path) -> Result<Self, String> {
+        let file = |name: &str| {
+            let path = dir.join(name);
+            if path.is_file() {
+                Ok(path)
+            } else {
+                Err(format!(
+                    "Embedding model file {} not found in {}",
+                    name,
+                    dir.display()
+                ))
+            }
+        };
+        Ok(ModelFiles {
+            config: file(CONFIG_FILE)?,
+            tokenizer: file(TOKENIZER_FILE)?,
+            weights: file(WEIGHTS_FILE)?,
+        })
+    }
+
+    /// Download the files from HuggingFace Hub (cached after the first run)
+    fn download() -> Result<Self, String> {
+        let api = Api::new().map_err(|e| format!("Failed to create HF API: {}", e))?;
+        let repo = api.repo(Repo::new(MODEL_ID.to_string(), RepoType::Model));
+
+        Ok(ModelFiles {
+            config: repo
+                .get(CONFIG_FILE)
+                .map_err(|e| format!("Failed to download config: {}", e))?,
+            tokenizer: repo
+                .get(TOKENIZER_FILE)
+                .map_err(|e| format!("Failed to download tokenizer: {}", e))?,
+            weights: repo
+                .get(WEIGHTS_FILE)
+                .map_err(|e| format!("Failed to download weights: {}", e))?,
+        })
+    }
+
+    /// Local files when `model_path` is set, otherwise the Hub download
+    fn resolve(model_path: Option<&Path>) -> Result<Self, String> {
+        match model_path {
+            Some(dir) => Self::local(dir),
+            None => Self::download(),
+        }
+    }
+}
+
 /// Sentence embedding model wrapper
 pub struct EmbeddingModel {
     model: BertModel,
@@ -27,26 +104,27 @@ pub struct EmbeddingModel {
 }
 
 impl EmbeddingModel {
-    /// Load the embedding model from HuggingFace Hub
-    /// Downloads ~90MB of model files on first use, cached afterwards.
+    /// Load the embedding model from the directory given to `set_model_path`, or
+    /// else from HuggingFace Hub (downloads ~90MB on first use, cached afterwards).
     pub fn load() -> Result<Self, String> {
-        tracing::info!("Loading embedding model: {}", MODEL_ID);
+        Self::load_from(MODEL_PATH.get().map(|p| p.as_path()))
+    }
 
-        let device = Device::Cpu;
+    /// Load the embedding model from a local directory, or from HuggingFace Hub
+    /// when `model_path` is None
+    pub fn load_from(model_path: Option<&Path>) -> Result<Self, String> {
+        match model_path {
+            Some(dir) => tracing::info!("Loading embedding model from {}", dir.display()),
+            None => tracing::info!("Loading embedding model: {}", MODEL_ID),
+        }
 
-        // Download model files from HuggingFace
-        let api = Api::new().map_err(|e| format!("Failed to create HF API: {}", e))?;
-        let repo = api.repo(Repo::new(MODEL_ID.to_string(), RepoType::Model));
+        let device = Device::Cpu;
 
-        let config_path = repo
-            .get("config.json")
-            .map_err(|e| format!("Failed to download config: {}", e))?;
-        let tokenizer_path = repo
-            .get("tokenizer.json")
-            .map_err(|e| format!("Failed to download tokenizer: {}", e))?;
-        let weights_path = repo
-            .get("model.safetensors")
-            .map_err(|e| format!("Failed to download weights: {}", e))?;
+        let ModelFiles {
+            config: config_path,
+            tokenizer: tokenizer_path,
+            weights: weights_path,
+        } = ModelFiles::resolve(model_path)?;
 
         // Load config
         let config_str = std::fs::read_to_string(&config_path)
@@ -202,10 +280,26 @@ impl EmbeddingModel {
 
 /// Get or initialize the global embedding model (lazy loaded)
 pub fn get_model() -> Result<&'static EmbeddingModel, String> {
-    EMBEDDING_MODEL
-        .get_or_init(EmbeddingModel::load)
-        .as_ref()
-        .map_err(|e| e.clone())
+    get_or_load(&EMBEDDING_MODEL, EmbeddingModel::load)
+}
+
+/// The value in `cell`, running `load` only if nothing has been stored yet
+fn get_or_load<T>(
+    cell: &'static OnceLock<Result<T, String>>,
+    load: impl FnOnce() -> Result<T, String>,
+) -> Result<&'static T, String> {
+    cell.get_or_init(load).as_ref().map_err(|e| e.clone())
+}
+
+/// Load the global model now so the first request doesn't wait for it.
+///
+/// Blocks while the model downloads and loads; run it off the async runtime.
+pub fn preload() {
+    let start = std::time::Instant::now();
+    match get_model() {
+        Ok(_) => tracing::info!("Embedding model ready ({} ms)", start.elapsed().as_millis()),
+        Err(e) => tracing::warn!("Embedding model preload failed: {}", e),
+    }
 }
 
 /// Generate embedding for text (uses global model)
@@ -273,6 +367,46 @@ mod tests {
         assert_eq!(cosine_similarity(&a, &b), 0.0);
     }
 
+    #[test]
+    fn test_local_model_files() {
+        let tmp = tempfile::tempdir().unwrap();
+        for name in [CONFIG_FILE, TOKENIZER_FILE] {
+            std::fs::write(tmp.path().join(name), "{}").unwrap();
+        }
+
+        // The local branch never falls back to downloading a missing file
+        let err = ModelFiles::resolve(Some(tmp.path())).err().unwrap();
+        assert!(err.contains(WEIGHTS_FILE), "{err}");
+
+        std::fs::write(tmp.path().join(WEIGHTS_FILE), b"stub").unwrap();
+        let files = ModelFiles::resolve(Some(tmp.path())).unwrap();
+        assert_eq!(files.config, tmp.path().join(CONFIG_FILE));
+        assert_eq!(files.tokenizer, tmp.path().join(TOKENIZER_FILE));
+        assert_eq!(files.weights, tmp.path().join(WEIGHTS_FILE));
+
+        // Stub files get past resolution but fail to parse, with the error surfaced
+        let err = EmbeddingModel::load_from(Some(tmp.path())).err().unwrap();
+        assert!(err.starts_with("Failed to"), "{err}");
+    }
+
+    #[test]
+    fn test_preloaded_model_is_not_reloaded() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        static MODEL: OnceLock<Result<&str, String>> = OnceLock::new();
+        static LOADS: AtomicUsize = AtomicUsize::new(0);
+        let load = || {
+            LOADS.fetch_add(1, Ordering::SeqCst);
+            Ok("model")
+        };
+
+        // Preloading fills the cell; later lookups reuse it
+        assert_eq!(get_or_load(&MODEL, load), Ok(&"model"));
+        assert!(MODEL.get().is_some());
+        assert_eq!(get_or_load(&MODEL, load), Ok(&"model"));
+        assert_eq!(LOADS.load(Ordering::SeqCst), 1);
+    }
+
     #[test]
     fn test_embedding_serialization_roundtrip() {
         let embedding = vec![0.1, 0.2, 0.3, 0.4, -0.5];
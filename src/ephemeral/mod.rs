@@ -1,16 +1,22 @@
 //! Ephemeral in-memory storage backend.
 //!
 //! Provides volatile session/message storage when `storage = "ephemeral"`.
-//! All data is lost on restart. Uses LRU eviction to bound memory usage.
+//! All data is lost on restart unless `ephemeral.snapshot_path` is set, in which
+//! case a best-effort snapshot is saved on shutdown and restored on startup.
+//! Evicts sessions (LRU or LFU, per `ephemeral.eviction_policy`) to bound memory usage.
 
-use crate::config::EphemeralConfig;
+use crate::config::{EphemeralConfig, EvictionPolicy};
+use crate::error::Result;
 use crate::parser::ParsedEvent;
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::{BufReader, BufWriter, Write};
+use std::path::Path;
 use std::sync::RwLock;
 use std::time::Instant;
 
 /// In-memory project metadata
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProjectMeta {
     pub id: String,
     pub name: String,
@@ -19,7 +25,7 @@ pub struct ProjectMeta {
 }
 
 /// In-memory session metadata
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SessionMeta {
     pub id: String,
     pub project_id: String,
@@ -33,32 +39,72 @@ pub struct SessionMeta {
     pub is_hidden: bool,
     pub title_generated: bool,
     pub created_at: String,
-    /// For LRU eviction
+    /// For LRU eviction (not snapshotted; restored sessions count as just accessed)
+    #[serde(skip, default = "Instant::now")]
     last_accessed: Instant,
+    /// For LFU eviction
+    #[serde(default)]
+    access_count: u64,
+}
+
+impl SessionMeta {
+    fn touch(&mut self) {
+        self.last_accessed = Instant::now();
+        self.access_count += 1;
+    }
 }
 
 /// In-memory message metadata (mirrors session_messages schema)
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MessageMeta {
     pub sequence_num: i64,
     pub role: String,
     pub content_preview: Option<String>,
     pub has_code: bool,
+    #[serde(default)]
+    pub code_language: Option<String>,
     pub has_error: bool,
     pub has_file_changes: bool,
     pub tool_name: Option<String>,
     pub tool_type: Option<String>,
     pub tool_summary: Option<String>,
+    pub parent_sequence_num: Option<i64>,
     pub byte_offset: i64,
     pub byte_length: i64,
     pub input_tokens: Option<i64>,
     pub output_tokens: Option<i64>,
     pub cache_read_tokens: Option<i64>,
     pub cache_creation_tokens: Option<i64>,
+    #[serde(default)]
+    pub tokens_estimated: bool,
     pub model: Option<String>,
     pub timestamp: String,
 }
 
+/// A message matched by `EphemeralIndex::search`
+#[derive(Debug, Clone)]
+pub struct SearchHit {
+    pub session: SessionMeta,
+    pub message: MessageMeta,
+    /// 0-1, higher is better: 1.0 when every query term matches a whole word
+    pub score: f64,
+}
+
+/// On-disk form of an `EphemeralIndex`, written as gzip-compressed JSON
+#[derive(Serialize, Deserialize)]
+struct Snapshot {
+    projects: Vec<ProjectMeta>,
+    sessions: Vec<SessionMeta>,
+    messages: HashMap<String, Vec<MessageMeta>>,
+}
+
+/// Lowercased alphanumeric words of `text` (underscores kept, so identifiers stay whole)
+fn tokenize(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric() && c != '_')
+        .filter(|t| !t.is_empty())
+        .map(|t| t.to_lowercase())
+}
+
 impl From<&ParsedEvent> for MessageMeta {
     fn from(e: &ParsedEvent) -> Self {
         MessageMeta {
@@ -66,17 +112,20 @@ impl From<&ParsedEvent> for MessageMeta {
             role: e.role.clone(),
             content_preview: Some(e.content_preview.clone()),
             has_code: e.has_code,
+            code_language: e.code_language.clone(),
             has_error: e.has_error,
             has_file_changes: e.has_file_changes,
             tool_name: e.tool_name.clone(),
             tool_type: e.tool_type.clone(),
             tool_summary: e.tool_summary.clone(),
+            parent_sequence_num: e.parent_sequence.map(|s| s as i64),
             byte_offset: e.byte_offset,
             byte_length: e.byte_length,
             input_tokens: e.input_tokens,
             output_tokens: e.output_tokens,
             cache_read_tokens: e.cache_read_tokens,
             cache_creation_tokens: e.cache_creation_tokens,
+            tokens_estimated: e.tokens_estimated,
             model: e.model.clone(),
             timestamp: e.timestamp.clone(),
         }
@@ -85,13 +134,16 @@ impl From<&ParsedEvent> for MessageMeta {
 
 /// In-memory volatile index for ephemeral storage mode.
 ///
-/// Thread-safe via `RwLock`. Uses LRU eviction on sessions when `max_sessions` is exceeded.
+/// Thread-safe via `RwLock`. Evicts sessions per `eviction_policy` when `max_sessions` is exceeded.
 pub struct EphemeralIndex {
     projects: RwLock<HashMap<String, ProjectMeta>>,
     /// Maps folder_path → project_id for lookup
     folder_to_project: RwLock<HashMap<String, String>>,
     sessions: RwLock<HashMap<String, SessionMeta>>,
     messages: RwLock<HashMap<String, Vec<MessageMeta>>>,
+    /// Inverted index over `content_preview`: term → (session_id, sequence_num).
+    /// Ordered, so the terms a query term is a prefix of are one range.
+    terms: RwLock<BTreeMap<String, HashSet<(String, i64)>>>,
     config: EphemeralConfig,
 }
 
@@ -102,6 +154,7 @@ impl EphemeralIndex {
             folder_to_project: RwLock::new(HashMap::new()),
             sessions: RwLock::new(HashMap::new()),
             messages: RwLock::new(HashMap::new()),
+            terms: RwLock::new(BTreeMap::new()),
             config,
         }
     }
@@ -143,6 +196,16 @@ impl EphemeralIndex {
         id
     }
 
+    /// Find the session ID stored for a given file path
+    pub fn find_session_by_file_path(&self, file_path: &str) -> Option<String> {
+        self.sessions
+            .read()
+            .unwrap()
+            .values()
+            .find(|s| s.file_path == file_path)
+            .map(|s| s.id.clone())
+    }
+
     /// Get session state for incremental parsing
     pub fn get_session_state(&self, session_id: &str) -> (i64, i64, i64) {
         let sessions = self.sessions.read().unwrap();
@@ -182,6 +245,13 @@ impl EphemeralIndex {
 
         let now = chrono::Utc::now().to_rfc3339();
         let message_count = messages.len();
+        // A re-parse keeps the session's access history
+        let access_count = self
+            .sessions
+            .read()
+            .unwrap()
+            .get(session_id)
+            .map_or(0, |s| s.access_count);
 
         let session = SessionMeta {
             id: session_id.to_string(),
@@ -197,6 +267,7 @@ impl EphemeralIndex {
             title_generated: false,
             created_at: now,
             last_accessed: Instant::now(),
+            access_count,
         };
 
         self.sessions
@@ -212,6 +283,9 @@ impl EphemeralIndex {
         } else {
             messages
         };
+        // Unindex the replaced messages first: they share postings with the new ones
+        self.remove_messages(session_id);
+        self.index_messages(session_id, &trimmed);
         self.messages
             .write()
             .unwrap()
@@ -234,7 +308,7 @@ impl EphemeralIndex {
             session.file_size = new_file_size;
             session.has_code = session.has_code || has_code;
             session.has_errors = session.has_errors || has_errors;
-            session.last_accessed = Instant::now();
+            session.touch();
         }
         let total = sessions
             .get(session_id)
@@ -243,6 +317,7 @@ impl EphemeralIndex {
         drop(sessions);
 
         // Append new messages (incremental updates are small, no cap needed)
+        self.index_messages(session_id, &new_messages);
         let mut messages = self.messages.write().unwrap();
         let msgs = messages.entry(session_id.to_string()).or_default();
         msgs.extend(new_messages);
@@ -293,16 +368,20 @@ impl EphemeralIndex {
             .filter(|s| include_hidden || !s.is_hidden)
             .cloned()
             .collect();
-        // Sort by created_at descending
-        result.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+        // Sort by created_at descending (id breaks ties for stable cursors)
+        result.sort_by(|a, b| {
+            b.created_at
+                .cmp(&a.created_at)
+                .then_with(|| b.id.cmp(&a.id))
+        });
         result
     }
 
-    /// Get a single session by ID. Updates last_accessed for LRU.
+    /// Get a single session by ID. Counts as an access for eviction.
     pub fn get_session(&self, session_id: &str) -> Option<SessionMeta> {
         let mut sessions = self.sessions.write().unwrap();
         if let Some(session) = sessions.get_mut(session_id) {
-            session.last_accessed = Instant::now();
+            session.touch();
             Some(session.clone())
         } else {
             None
@@ -348,7 +427,7 @@ impl EphemeralIndex {
             if let Some(h) = is_hidden {
                 session.is_hidden = h;
             }
-            session.last_accessed = Instant::now();
+            session.touch();
             true
         } else {
             false
@@ -359,7 +438,7 @@ impl EphemeralIndex {
     pub fn delete_session(&self, session_id: &str) -> bool {
         let removed = self.sessions.write().unwrap().remove(session_id).is_some();
         if removed {
-            self.messages.write().unwrap().remove(session_id);
+            self.remove_messages(session_id);
         }
         removed
     }
@@ -396,10 +475,9 @@ impl EphemeralIndex {
                 .map(|s| s.id.clone())
                 .collect();
             let mut sessions = self.sessions.write().unwrap();
-            let mut messages = self.messages.write().unwrap();
             for sid in session_ids {
                 sessions.remove(&sid);
-                messages.remove(&sid);
+                self.remove_messages(&sid);
             }
         }
         removed
@@ -441,7 +519,7 @@ impl EphemeralIndex {
         };
 
         // Read first user messages from the JSONL file
-        let file = std::fs::File::open(&file_path).ok()?;
+        let file = crate::parser::file::open(Path::new(&file_path)).ok()?;
         let reader = std::io::BufReader::new(file);
 
         use std::io::BufRead;
@@ -497,7 +575,7 @@ impl EphemeralIndex {
         })
     }
 
-    /// Evict the oldest session if we've reached the limit
+    /// Evict one session per the eviction policy if we've reached the limit
     fn evict_if_needed(&self, incoming_session_id: &str) {
         let sessions = self.sessions.read().unwrap();
         if sessions.len() < self.config.max_sessions {
@@ -507,18 +585,241 @@ impl EphemeralIndex {
         if sessions.contains_key(incoming_session_id) {
             return;
         }
+        drop(sessions);
+        self.evict_one();
+    }
 
-        // Find the session with the oldest last_accessed
-        let oldest = sessions
-            .iter()
-            .min_by_key(|(_, s)| s.last_accessed)
-            .map(|(id, _)| id.clone());
+    /// Evict the session the eviction policy ranks lowest. Returns false if there are none.
+    fn evict_one(&self) -> bool {
+        let sessions = self.sessions.read().unwrap();
+        let policy = self.config.eviction_policy;
+        let victim = match policy {
+            EvictionPolicy::Lru => sessions.iter().min_by_key(|(_, s)| s.last_accessed),
+            EvictionPolicy::Lfu => sessions
+                .iter()
+                .min_by_key(|(_, s)| (s.access_count, s.last_accessed)),
+        }
+        .map(|(id, _)| id.clone());
         drop(sessions);
 
-        if let Some(oldest_id) = oldest {
-            tracing::debug!("Ephemeral: evicting session {} (LRU)", &oldest_id[..8]);
-            self.sessions.write().unwrap().remove(&oldest_id);
-            self.messages.write().unwrap().remove(&oldest_id);
+        if let Some(victim_id) = victim {
+            tracing::debug!(
+                "Ephemeral: evicting session {} ({:?})",
+                &victim_id[..8.min(victim_id.len())],
+                policy
+            );
+            self.sessions.write().unwrap().remove(&victim_id);
+            self.remove_messages(&victim_id);
+            true
+        } else {
+            false
+        }
+    }
+
+    // ========================================================================
+    // Snapshots
+    // ========================================================================
+
+    /// Write projects, sessions, and messages to `path`.
+    ///
+    /// The file is replaced atomically, so a failed write keeps the previous snapshot.
+    pub fn snapshot_to(&self, path: &Path) -> Result<()> {
+        let snapshot = Snapshot {
+            projects: self.projects.read().unwrap().values().cloned().collect(),
+            sessions: self.sessions.read().unwrap().values().cloned().collect(),
+            messages: self.messages.read().unwrap().clone(),
+        };
+
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let tmp = path.with_extension("tmp");
+        let file = std::fs::File::create(&tmp)?;
+        let mut encoder =
+            flate2::write::GzEncoder::new(BufWriter::new(file), flate2::Compression::default());
+        serde_json::to_writer(&mut encoder, &snapshot)?;
+        encoder.finish()?.flush()?;
+        std::fs::rename(&tmp, path)?;
+        Ok(())
+    }
+
+    /// Load a snapshot written by `snapshot_to`, replacing the current contents.
+    ///
+    /// Returns the number of sessions restored. Every restored session counts as
+    /// accessed now for LRU purposes; if the snapshot holds more than
+    /// `max_sessions`, the excess is evicted per the eviction policy.
+    pub fn restore_from(&self, path: &Path) -> Result<usize> {
+        let file = std::fs::File::open(path)?;
+        let snapshot: Snapshot =
+            serde_json::from_reader(flate2::read::GzDecoder::new(BufReader::new(file)))?;
+
+        let mut projects = self.projects.write().unwrap();
+        let mut folder_to_project = self.folder_to_project.write().unwrap();
+        let mut sessions = self.sessions.write().unwrap();
+        let mut messages = self.messages.write().unwrap();
+        projects.clear();
+        folder_to_project.clear();
+        sessions.clear();
+        messages.clear();
+        self.terms.write().unwrap().clear();
+
+        for project in snapshot.projects {
+            folder_to_project.insert(project.folder_path.clone(), project.id.clone());
+            projects.insert(project.id.clone(), project);
+        }
+        for session in snapshot.sessions {
+            sessions.insert(session.id.clone(), session);
+        }
+        for (session_id, msgs) in snapshot.messages {
+            self.index_messages(&session_id, &msgs);
+            messages.insert(session_id, msgs);
+        }
+        drop(messages);
+        drop(sessions);
+        drop(folder_to_project);
+        drop(projects);
+
+        while self.session_count() > self.config.max_sessions && self.evict_one() {}
+        Ok(self.session_count())
+    }
+
+    // ========================================================================
+    // Full-text search
+    // ========================================================================
+
+    /// Search message previews for all of the query's terms, best match first.
+    ///
+    /// A query term matches an indexed word it is a prefix of, so `"watch"`
+    /// finds `"watcher"`; whole-word matches score higher. System messages are
+    /// skipped, as in the database search, and so are hits `keep` rejects.
+    /// Returns the hits after the first `offset`, at most `limit` of them,
+    /// and the number of hits before paging.
+    pub fn search(
+        &self,
+        query: &str,
+        project_id: Option<&str>,
+        keep: impl Fn(&SessionMeta, &MessageMeta) -> bool,
+        offset: usize,
+        limit: usize,
+    ) -> (Vec<SearchHit>, usize) {
+        let query_terms: Vec<String> = tokenize(query).collect();
+        if query_terms.is_empty() {
+            return (Vec::new(), 0);
+        }
+
+        // (session_id, sequence_num) → summed per-term score; a posting survives
+        // only if every query term matched it
+        let mut matches: Option<HashMap<(String, i64), f64>> = None;
+        {
+            let terms = self.terms.read().unwrap();
+            for query_term in &query_terms {
+                let mut term_matches: HashMap<(String, i64), f64> = HashMap::new();
+                let prefixed = terms
+                    .range::<str, _>((
+                        std::ops::Bound::Included(query_term.as_str()),
+                        std::ops::Bound::Unbounded,
+                    ))
+                    .take_while(|(term, _)| term.starts_with(query_term.as_str()));
+                for (term, postings) in prefixed {
+                    let weight = if term == query_term { 1.0 } else { 0.5 };
+                    for posting in postings {
+                        let best = term_matches.entry(posting.clone()).or_insert(0.0);
+                        *best = best.max(weight);
+                    }
+                }
+                matches = Some(match matches {
+                    None => term_matches,
+                    Some(prev) => prev
+                        .into_iter()
+                        .filter_map(|(k, score)| term_matches.get(&k).map(|w| (k, score + w)))
+                        .collect(),
+                });
+            }
+        }
+
+        let sessions = self.sessions.read().unwrap();
+        let messages = self.messages.read().unwrap();
+        // Messages of the sessions with hits, by sequence number
+        let mut by_seq: HashMap<String, HashMap<i64, &MessageMeta>> = HashMap::new();
+        let mut hits: Vec<(f64, &SessionMeta, &MessageMeta)> = matches
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|((session_id, seq), score)| {
+                let session = sessions.get(&session_id)?;
+                if project_id.is_some_and(|pid| session.project_id != pid) {
+                    return None;
+                }
+                let message = *by_seq
+                    .entry(session_id)
+                    .or_insert_with_key(|session_id| {
+                        messages
+                            .get(session_id)
+                            .into_iter()
+                            .flatten()
+                            .map(|m| (m.sequence_num, m))
+                            .collect()
+                    })
+                    .get(&seq)?;
+                if message.role == "system" || !keep(session, message) {
+                    return None;
+                }
+                Some((score / query_terms.len() as f64, session, message))
+            })
+            .collect();
+        let total = hits.len();
+        hits.sort_by(|a, b| {
+            b.0.partial_cmp(&a.0)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then_with(|| b.1.created_at.cmp(&a.1.created_at))
+                .then_with(|| a.2.sequence_num.cmp(&b.2.sequence_num))
+        });
+        let page = hits
+            .into_iter()
+            .skip(offset)
+            .take(limit)
+            .map(|(score, session, message)| SearchHit {
+                session: session.clone(),
+                message: message.clone(),
+                score,
+            })
+            .collect();
+        (page, total)
+    }
+
+    /// Add messages' preview terms to the inverted index
+    fn index_messages(&self, session_id: &str, messages: &[MessageMeta]) {
+        let mut terms = self.terms.write().unwrap();
+        for msg in messages {
+            for term in tokenize(msg.content_preview.as_deref().unwrap_or_default()) {
+                terms
+                    .entry(term)
+                    .or_default()
+                    .insert((session_id.to_string(), msg.sequence_num));
+            }
+        }
+    }
+
+    /// Remove messages' preview terms from the inverted index
+    fn unindex_messages(&self, session_id: &str, messages: &[MessageMeta]) {
+        let mut terms = self.terms.write().unwrap();
+        for msg in messages {
+            let posting = (session_id.to_string(), msg.sequence_num);
+            for term in tokenize(msg.content_preview.as_deref().unwrap_or_default()) {
+                if let Some(postings) = terms.get_mut(&term) {
+                    postings.remove(&posting);
+                    if postings.is_empty() {
+                        terms.remove(&term);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Drop a session's messages and their index entries
+    fn remove_messages(&self, session_id: &str) {
+        let removed = self.messages.write().unwrap().remove(session_id);
+        if let Some(msgs) = removed {
+            self.unindex_messages(session_id, &msgs);
         }
     }
 }
@@ -531,7 +832,9 @@ mod tests {
     fn test_config() -> EphemeralConfig {
         EphemeralConfig {
             max_sessions: 3,
+            eviction_policy: EvictionPolicy::Lru,
             max_messages_per_session: 100,
+            snapshot_path: None,
         }
     }
 
@@ -564,17 +867,20 @@ mod tests {
             role: "user".to_string(),
             content_preview: Some("hello".to_string()),
             has_code: false,
+            code_language: None,
             has_error: false,
             has_file_changes: false,
             tool_name: None,
             tool_type: None,
             tool_summary: None,
+            parent_sequence_num: None,
             byte_offset: 0,
             byte_length: 100,
             input_tokens: None,
             output_tokens: None,
             cache_read_tokens: None,
             cache_creation_tokens: None,
+            tokens_estimated: false,
             model: None,
             timestamp: "2024-01-01T00:00:00Z".to_string(),
         };
@@ -597,6 +903,173 @@ mod tests {
         assert_eq!(ms, 0);
     }
 
+    fn text_message(sequence_num: i64, role: &str, content: &str) -> MessageMeta {
+        MessageMeta {
+            sequence_num,
+            role: role.to_string(),
+            content_preview: Some(content.to_string()),
+            has_code: false,
+            code_language: None,
+            has_error: false,
+            has_file_changes: false,
+            tool_name: None,
+            tool_type: None,
+            tool_summary: None,
+            parent_sequence_num: None,
+            byte_offset: 0,
+            byte_length: 0,
+            input_tokens: None,
+            output_tokens: None,
+            cache_read_tokens: None,
+            cache_creation_tokens: None,
+            tokens_estimated: false,
+            model: None,
+            timestamp: "2024-01-01T00:00:00Z".to_string(),
+        }
+    }
+
+    fn store(index: &EphemeralIndex, session_id: &str, project_id: &str, msgs: Vec<MessageMeta>) {
+        index.store_session(
+            session_id,
+            &format!("/{}.jsonl", session_id),
+            project_id,
+            None,
+            "Claude Code",
+            msgs,
+            100,
+            false,
+            false,
+        );
+    }
+
+    #[test]
+    fn test_search_matches_terms() {
+        let index = EphemeralIndex::new(test_config());
+        store(
+            &index,
+            "sess1",
+            "proj1",
+            vec![
+                text_message(0, "user", "How do I configure the watcher?"),
+                text_message(1, "assistant", "Edit config.toml and restart"),
+                text_message(2, "system", "watcher started"),
+            ],
+        );
+        store(
+            &index,
+            "sess2",
+            "proj2",
+            vec![text_message(0, "user", "the Watcher misses renamed files")],
+        );
+
+        let search = |query: &str, project_id: Option<&str>, limit: usize| {
+            index.search(query, project_id, |_, _| true, 0, limit).0
+        };
+        let hits = search("watcher", None, 10);
+        let found: Vec<_> = hits
+            .iter()
+            .map(|h| (h.session.id.as_str(), h.message.sequence_num))
+            .collect();
+        assert_eq!(found.len(), 2);
+        assert!(found.contains(&("sess1", 0)));
+        assert!(found.contains(&("sess2", 0)));
+
+        // All terms must match; prefixes of words count at a lower score
+        let hits = search("configure watch", None, 10);
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].message.sequence_num, 0);
+        assert!(hits[0].score < 1.0);
+
+        assert_eq!(search("watcher", Some("proj2"), 10).len(), 1);
+        assert!(search("nothing", None, 10).is_empty());
+        assert!(search("atcher", None, 10).is_empty());
+
+        // The total counts every hit `keep` lets through, before paging
+        let (page, total) = index.search("watcher", None, |_, _| true, 1, 1);
+        assert_eq!((page.len(), total), (1, 2));
+        let (page, total) = index.search("watcher", None, |s, _| s.id == "sess2", 0, 10);
+        assert_eq!((page[0].session.id.as_str(), total), ("sess2", 1));
+
+        // Appended messages are searchable; deleted sessions are not
+        index.append_messages(
+            "sess1",
+            vec![text_message(3, "assistant", "renamed files are rescanned")],
+            200,
+            false,
+            false,
+        );
+        assert_eq!(search("renamed", None, 10).len(), 2);
+        index.delete_session("sess2");
+        assert_eq!(search("renamed", None, 10).len(), 1);
+        assert!(!index.terms.read().unwrap().contains_key("misses"));
+    }
+
+    #[test]
+    fn test_snapshot_and_restore() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("ephemeral.snapshot");
+
+        let index = EphemeralIndex::new(test_config());
+        let project_id = index.get_or_create_project("/work/app", "app");
+        store(
+            &index,
+            "sess1",
+            &project_id,
+            vec![
+                text_message(0, "user", "fix the watcher"),
+                text_message(1, "assistant", "done"),
+            ],
+        );
+        index.update_session("sess1", Some("Watcher fix".to_string()), None);
+        index.snapshot_to(&path).unwrap();
+
+        let restored = EphemeralIndex::new(test_config());
+        assert_eq!(restored.restore_from(&path).unwrap(), 1);
+
+        assert_eq!(
+            restored.get_or_create_project("/work/app", "app"),
+            project_id
+        );
+        let session = restored.get_session("sess1").unwrap();
+        assert_eq!(session.title.as_deref(), Some("Watcher fix"));
+        assert_eq!(session.project_id, project_id);
+        assert_eq!(session.message_count, 2);
+        let msgs = restored.get_messages("sess1");
+        assert_eq!(msgs.len(), 2);
+        assert_eq!(msgs[1].content_preview.as_deref(), Some("done"));
+        assert_eq!(restored.search("watcher", None, |_, _| true, 0, 10).1, 1);
+    }
+
+    #[test]
+    fn test_restore_evicts_beyond_max_sessions() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("ephemeral.snapshot");
+
+        let index = EphemeralIndex::new(test_config()); // max_sessions = 3
+        for i in 0..3 {
+            store(
+                &index,
+                &format!("sess{}", i),
+                "proj1",
+                vec![text_message(0, "user", "watcher")],
+            );
+        }
+        // The LFU policy keeps the most-read sessions
+        index.get_session("sess0");
+        index.get_session("sess2");
+        index.snapshot_to(&path).unwrap();
+
+        let restored = EphemeralIndex::new(EphemeralConfig {
+            max_sessions: 2,
+            eviction_policy: EvictionPolicy::Lfu,
+            ..test_config()
+        });
+        assert_eq!(restored.restore_from(&path).unwrap(), 2);
+        assert!(restored.get_session("sess1").is_none());
+        assert!(restored.get_messages("sess1").is_empty());
+        assert_eq!(restored.search("watcher", None, |_, _| true, 0, 10).1, 2);
+    }
+
     #[test]
     fn test_lru_eviction() {
         let index = EphemeralIndex::new(test_config()); // max_sessions = 3
@@ -638,4 +1111,40 @@ mod tests {
         assert!(!sessions.contains_key("sess0"));
         assert!(sessions.contains_key("sess3"));
     }
+
+    #[test]
+    fn test_lfu_keeps_frequently_accessed_session() {
+        // sess0 is read often but long ago; sess1 and sess2 once, more recently
+        let fill = |policy| {
+            let index = EphemeralIndex::new(EphemeralConfig {
+                eviction_policy: policy,
+                ..test_config()
+            });
+            for i in 0..3 {
+                store(&index, &format!("sess{}", i), "proj1", vec![]);
+            }
+            for _ in 0..5 {
+                index.get_session("sess0");
+            }
+            std::thread::sleep(std::time::Duration::from_millis(1));
+            index.get_session("sess1");
+            std::thread::sleep(std::time::Duration::from_millis(1));
+            index.get_session("sess2");
+            store(&index, "sess3", "proj1", vec![]);
+            index
+        };
+
+        let lfu = fill(EvictionPolicy::Lfu);
+        let sessions = lfu.sessions.read().unwrap();
+        assert!(sessions.contains_key("sess0"));
+        // Tie between sess1 and sess2 goes to the older access
+        assert!(!sessions.contains_key("sess1"));
+        assert!(sessions.contains_key("sess2"));
+        assert!(sessions.contains_key("sess3"));
+
+        let lru = fill(EvictionPolicy::Lru);
+        let sessions = lru.sessions.read().unwrap();
+        assert!(!sessions.contains_key("sess0"));
+        assert!(sessions.contains_key("sess1"));
+    }
 }
@@ -7,7 +7,11 @@ use thiserror::Error;
 pub enum CoreError {
     /// Database error
     #[error("Database error: {0}")]
-    Database(#[from] rusqlite::Error),
+    Database(#[source] rusqlite::Error),
+
+    /// SQLite stayed busy or locked; retrying later may succeed
+    #[error("Database busy: {0}")]
+    DbBusy(#[source] rusqlite::Error),
 
     /// IO error
     #[error("IO error: {0}")]
@@ -29,6 +33,18 @@ pub enum CoreError {
     #[error("API error: {0}")]
     Api(String),
 
+    /// AI provider not installed, not configured, or not reachable
+    #[error("AI provider unavailable: {0}")]
+    ProviderUnavailable(String),
+
+    /// An operation (named first) didn't finish within its time limit
+    #[error("{0} timed out after {1} seconds")]
+    Timeout(String, u64),
+
+    /// Output (typically from an AI provider) couldn't be parsed
+    #[error("Failed to parse {0}")]
+    ParseFailed(String),
+
     /// Embedding error
     #[error("Embedding error: {0}")]
     Embedding(String),
@@ -53,6 +69,16 @@ pub enum CoreError {
 /// Result type alias for Core operations
 pub type Result<T> = std::result::Result<T, CoreError>;
 
+impl From<rusqlite::Error> for CoreError {
+    fn from(e: rusqlite::Error) -> Self {
+        if crate::db::is_busy(&e) {
+            CoreError::DbBusy(e)
+        } else {
+            CoreError::Database(e)
+        }
+    }
+}
+
 impl From<notify::Error> for CoreError {
     fn from(e: notify::Error) -> Self {
         CoreError::Watcher(e.to_string())
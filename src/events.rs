@@ -0,0 +1,367 @@
+//! Watcher and AI events as published to clients
+//!
+//! One event vocabulary shared by the SSE stream and outbound webhooks.
+
+use crate::ai::types::AiEvent;
+use crate::watcher::WatcherEvent;
+
+/// Events sent to clients
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SseEvent {
+    /// Heartbeat to keep connection alive
+    Heartbeat { timestamp: String },
+    /// Some events were not delivered to this stream; `count` is how many,
+    /// when known. Clients should refetch the state they track.
+    EventsMissed { count: Option<u64> },
+    /// New session file detected
+    SessionNew {
+        project_id: String,
+        file_path: String,
+        file_name: String,
+    },
+    /// Session file changed (grew)
+    SessionChanged {
+        session_id: String,
+        file_path: String,
+        previous_size: u64,
+        new_size: u64,
+    },
+    /// Progress through a large session file's parse
+    SessionParseProgress {
+        session_id: String,
+        bytes_done: u64,
+        bytes_total: u64,
+    },
+    /// Session parsing completed
+    SessionParsed {
+        session_id: String,
+        message_count: usize,
+    },
+    /// Session file deleted or renamed away
+    SessionRemoved { session_id: String },
+    /// Watcher error
+    WatcherError { file_path: String, error: String },
+    // AI Events
+    /// Title generation started
+    AiTitleStart { session_id: String },
+    /// Title generation completed
+    AiTitleComplete {
+        session_id: String,
+        title: String,
+        provider: String,
+    },
+    /// Title generation failed
+    AiTitleError { session_id: String, error: String },
+    /// Title generation cancelled
+    AiTitleCancelled { session_id: String },
+    /// Memory extraction started
+    AiMemoryStart { session_id: String },
+    /// Memory extraction completed
+    AiMemoryComplete {
+        session_id: String,
+        count: usize,
+        provider: String,
+    },
+    /// Memory extraction failed
+    AiMemoryError { session_id: String, error: String },
+    /// Memory extraction cancelled
+    AiMemoryCancelled { session_id: String },
+    /// Skill extraction started
+    AiSkillStart { session_id: String },
+    /// Skill extraction completed
+    AiSkillComplete {
+        session_id: String,
+        count: usize,
+        provider: String,
+    },
+    /// Skill extraction failed
+    AiSkillError { session_id: String, error: String },
+    /// Skill extraction cancelled
+    AiSkillCancelled { session_id: String },
+    /// Marker detection started
+    AiMarkerStart { session_id: String },
+    /// Marker detection completed
+    AiMarkerComplete { session_id: String, count: usize },
+    /// Marker detection failed
+    AiMarkerError { session_id: String, error: String },
+    /// Marker detection cancelled
+    AiMarkerCancelled { session_id: String },
+    /// Export generation started
+    AiExportStart { session_id: String, format: String },
+    /// Export generation completed
+    AiExportComplete {
+        session_id: String,
+        format: String,
+        content: String,
+        provider: String,
+        generation_time_ms: u64,
+    },
+    /// Export generation failed
+    AiExportError {
+        session_id: String,
+        format: String,
+        error: String,
+    },
+    /// Embedding backfill finished a batch
+    AiEmbeddingProgress { done: usize, total: usize },
+    /// Embedding backfill finished
+    AiEmbeddingComplete {
+        embedded: usize,
+        failed: usize,
+        total: usize,
+    },
+    /// Memory ranking started
+    RankingStart { project_id: String },
+    /// Memory ranking completed
+    RankingComplete {
+        project_id: String,
+        promoted: usize,
+        demoted: usize,
+        removed: usize,
+    },
+    /// Memory ranking error
+    RankingError { project_id: String, error: String },
+    /// Scheduler task started
+    SchedulerTaskStart {
+        task_name: String,
+        project_id: String,
+    },
+    /// Scheduler task completed
+    SchedulerTaskComplete {
+        task_name: String,
+        project_id: String,
+        detail: String,
+    },
+    /// Scheduler task error
+    SchedulerTaskError {
+        task_name: String,
+        project_id: String,
+        error: String,
+    },
+}
+
+impl From<WatcherEvent> for SseEvent {
+    fn from(event: WatcherEvent) -> Self {
+        match event {
+            WatcherEvent::NewSession {
+                project_id,
+                file_path,
+                file_name,
+            } => SseEvent::SessionNew {
+                project_id,
+                file_path,
+                file_name,
+            },
+            WatcherEvent::SessionChanged {
+                session_id,
+                file_path,
+                previous_size,
+                new_size,
+            } => SseEvent::SessionChanged {
+                session_id,
+                file_path,
+                previous_size,
+                new_size,
+            },
+            WatcherEvent::ParseProgress {
+                session_id,
+                bytes_done,
+                bytes_total,
+            } => SseEvent::SessionParseProgress {
+                session_id,
+                bytes_done,
+                bytes_total,
+            },
+            WatcherEvent::SessionParsed {
+                session_id,
+                message_count,
+            } => SseEvent::SessionParsed {
+                session_id,
+                message_count,
+            },
+            WatcherEvent::SessionRemoved { session_id } => SseEvent::SessionRemoved { session_id },
+            WatcherEvent::Error { file_path, error } => SseEvent::WatcherError { file_path, error },
+            WatcherEvent::RankingStart { project_id } => SseEvent::RankingStart { project_id },
+            WatcherEvent::RankingComplete {
+                project_id,
+                promoted,
+                demoted,
+                removed,
+            } => SseEvent::RankingComplete {
+                project_id,
+                promoted,
+                demoted,
+                removed,
+            },
+            WatcherEvent::RankingError { project_id, error } => {
+                SseEvent::RankingError { project_id, error }
+            }
+            WatcherEvent::SchedulerTaskStart {
+                task_name,
+                project_id,
+            } => SseEvent::SchedulerTaskStart {
+                task_name,
+                project_id,
+            },
+            WatcherEvent::SchedulerTaskComplete {
+                task_name,
+                project_id,
+                detail,
+            } => SseEvent::SchedulerTaskComplete {
+                task_name,
+                project_id,
+                detail,
+            },
+            WatcherEvent::SchedulerTaskError {
+                task_name,
+                project_id,
+                error,
+            } => SseEvent::SchedulerTaskError {
+                task_name,
+                project_id,
+                error,
+            },
+        }
+    }
+}
+
+impl From<AiEvent> for SseEvent {
+    fn from(event: AiEvent) -> Self {
+        match event {
+            AiEvent::TitleStart { session_id } => SseEvent::AiTitleStart { session_id },
+            AiEvent::TitleComplete {
+                session_id,
+                title,
+                provider,
+            } => SseEvent::AiTitleComplete {
+                session_id,
+                title,
+                provider,
+            },
+            AiEvent::TitleError { session_id, error } => {
+                SseEvent::AiTitleError { session_id, error }
+            }
+            AiEvent::TitleCancelled { session_id } => SseEvent::AiTitleCancelled { session_id },
+            AiEvent::MemoryStart { session_id } => SseEvent::AiMemoryStart { session_id },
+            AiEvent::MemoryComplete {
+                session_id,
+                count,
+                provider,
+            } => SseEvent::AiMemoryComplete {
+                session_id,
+                count,
+                provider,
+            },
+            AiEvent::MemoryError { session_id, error } => {
+                SseEvent::AiMemoryError { session_id, error }
+            }
+            AiEvent::MemoryCancelled { session_id } => SseEvent::AiMemoryCancelled { session_id },
+            AiEvent::SkillStart { session_id } => SseEvent::AiSkillStart { session_id },
+            AiEvent::SkillComplete {
+                session_id,
+                count,
+                provider,
+            } => SseEvent::AiSkillComplete {
+                session_id,
+                count,
+                provider,
+            },
+            AiEvent::SkillError { session_id, error } => {
+                SseEvent::AiSkillError { session_id, error }
+            }
+            AiEvent::SkillCancelled { session_id } => SseEvent::AiSkillCancelled { session_id },
+            AiEvent::MarkerStart { session_id } => SseEvent::AiMarkerStart { session_id },
+            AiEvent::MarkerComplete { session_id, count } => {
+                SseEvent::AiMarkerComplete { session_id, count }
+            }
+            AiEvent::MarkerError { session_id, error } => {
+                SseEvent::AiMarkerError { session_id, error }
+            }
+            AiEvent::MarkerCancelled { session_id } => SseEvent::AiMarkerCancelled { session_id },
+            AiEvent::ExportStart { session_id, format } => {
+                SseEvent::AiExportStart { session_id, format }
+            }
+            AiEvent::ExportComplete {
+                session_id,
+                format,
+                content,
+                provider,
+                generation_time_ms,
+            } => SseEvent::AiExportComplete {
+                session_id,
+                format,
+                content,
+                provider,
+                generation_time_ms,
+            },
+            AiEvent::ExportError {
+                session_id,
+                format,
+                error,
+            } => SseEvent::AiExportError {
+                session_id,
+                format,
+                error,
+            },
+            AiEvent::EmbeddingProgress { done, total } => {
+                SseEvent::AiEmbeddingProgress { done, total }
+            }
+            AiEvent::EmbeddingComplete {
+                embedded,
+                failed,
+                total,
+            } => SseEvent::AiEmbeddingComplete {
+                embedded,
+                failed,
+                total,
+            },
+        }
+    }
+}
+
+/// Get the SSE event type name
+pub(crate) fn get_event_type(event: &SseEvent) -> &'static str {
+    match event {
+        SseEvent::Heartbeat { .. } => "heartbeat",
+        SseEvent::EventsMissed { .. } => "events:missed",
+        SseEvent::SessionNew { .. } => "session:new",
+        SseEvent::SessionChanged { .. } => "session:changed",
+        SseEvent::SessionParseProgress { .. } => "session:parsing",
+        SseEvent::SessionParsed { .. } => "session:parsed",
+        SseEvent::SessionRemoved { .. } => "session:removed",
+        SseEvent::WatcherError { .. } => "watcher:error",
+        // AI events
+        SseEvent::AiTitleStart { .. } => "ai:title:start",
+        SseEvent::AiTitleComplete { .. } => "ai:title:complete",
+        SseEvent::AiTitleError { .. } => "ai:title:error",
+        SseEvent::AiTitleCancelled { .. } => "ai:title:cancelled",
+        SseEvent::AiMemoryStart { .. } => "ai:memory:start",
+        SseEvent::AiMemoryComplete { .. } => "ai:memory:complete",
+        SseEvent::AiMemoryError { .. } => "ai:memory:error",
+        SseEvent::AiMemoryCancelled { .. } => "ai:memory:cancelled",
+        SseEvent::AiSkillStart { .. } => "ai:skill:start",
+        SseEvent::AiSkillComplete { .. } => "ai:skill:complete",
+        SseEvent::AiSkillError { .. } => "ai:skill:error",
+        SseEvent::AiSkillCancelled { .. } => "ai:skill:cancelled",
+        SseEvent::AiMarkerStart { .. } => "ai:markers:start",
+        SseEvent::AiMarkerComplete { .. } => "ai:markers:complete",
+        SseEvent::AiMarkerError { .. } => "ai:markers:error",
+        SseEvent::AiMarkerCancelled { .. } => "ai:markers:cancelled",
+        // Ranking events
+        SseEvent::RankingStart { .. } => "ai:ranking:start",
+        SseEvent::RankingComplete { .. } => "ai:ranking:complete",
+        SseEvent::RankingError { .. } => "ai:ranking:error",
+        // Scheduler events
+        SseEvent::SchedulerTaskStart { .. } => "scheduler:start",
+        SseEvent::SchedulerTaskComplete { .. } => "scheduler:complete",
+        SseEvent::SchedulerTaskError { .. } => "scheduler:error",
+        // Export events
+        SseEvent::AiExportStart { .. } => "ai:export:start",
+        SseEvent::AiExportComplete { .. } => "ai:export:complete",
+        SseEvent::AiExportError { .. } => "ai:export:error",
+        // Embedding events
+        SseEvent::AiEmbeddingProgress { .. } => "ai:embedding:progress",
+        SseEvent::AiEmbeddingComplete { .. } => "ai:embedding:complete",
+    }
+}
@@ -25,37 +25,70 @@
 //! yocore --config ~/.yolog/config.toml
 //! ```
 
+#[cfg(feature = "server")]
 pub mod ai;
+#[cfg(feature = "server")]
 pub mod api;
+pub mod api_types;
+#[cfg(feature = "client")]
+pub mod client;
+#[cfg(feature = "server")]
 pub mod config;
+#[cfg(feature = "server")]
 pub mod db;
+#[cfg(feature = "server")]
 pub mod embeddings;
+#[cfg(feature = "server")]
 pub mod ephemeral;
+#[cfg(feature = "server")]
 pub mod error;
+#[cfg(feature = "server")]
+pub mod events;
+#[cfg(feature = "server")]
 pub mod handlers;
+#[cfg(feature = "server")]
+pub mod maintenance;
+#[cfg(feature = "server")]
 pub mod mcp;
+#[cfg(feature = "server")]
 pub mod mdns;
+#[cfg(feature = "server")]
 pub mod parser;
+#[cfg(feature = "server")]
+pub mod reload;
+#[cfg(feature = "server")]
 pub mod scheduler;
+#[cfg(all(test, feature = "server"))]
+mod test_support;
+#[cfg(feature = "server")]
 pub mod watcher;
+#[cfg(feature = "server")]
+pub mod webhooks;
 
 // Re-export main types for convenience
+#[cfg(feature = "server")]
 pub use config::Config;
+#[cfg(feature = "server")]
 pub use db::Database;
+#[cfg(feature = "server")]
 pub use error::{CoreError, Result};
 
-use ai::queue::AiTaskQueue;
-use ai::types::AiEvent;
-use ephemeral::EphemeralIndex;
-use std::path::PathBuf;
-use std::sync::Arc;
-use tokio::sync::{broadcast, RwLock};
+#[cfg(feature = "server")]
+use {
+    ai::queue::AiTaskQueue,
+    ai::types::AiEvent,
+    ephemeral::EphemeralIndex,
+    maintenance::MaintenanceMode,
+    parser::ParserRegistry,
+    reload::{ConfigReloader, ReloadSummary},
+    std::path::PathBuf,
+    std::sync::Arc,
+    tokio::sync::{broadcast, RwLock},
+};
 
 /// Core service that coordinates all Yolog functionality
+#[cfg(feature = "server")]
 pub struct Core {
-    /// Configuration
-    pub config: Config,
-
     /// Path to config file (for config API)
     pub config_path: PathBuf,
 
@@ -66,7 +99,7 @@ pub struct Core {
     pub ephemeral: Option<Arc<EphemeralIndex>>,
 
     /// File watcher state (optional, only when watching is active)
-    watcher_handle: RwLock<Option<watcher::WatcherHandle>>,
+    watcher_handle: Arc<RwLock<Option<watcher::WatcherHandle>>>,
 
     /// Broadcast channel for SSE events (from watcher to API clients)
     event_tx: broadcast::Sender<watcher::WatcherEvent>,
@@ -76,36 +109,84 @@ pub struct Core {
 
     /// AI task queue for concurrency control
     ai_task_queue: AiTaskQueue,
+
+    /// Pauses watcher, scheduler, and AI work while enabled
+    maintenance: MaintenanceMode,
+
+    /// Scheduler tasks with a run in progress, shared with the admin API
+    running_tasks: scheduler::RunningTasks,
+
+    /// Applies config file changes at runtime
+    reloader: ConfigReloader,
+
+    /// Session parsers by name, consulted by the watcher
+    parsers: Arc<ParserRegistry>,
 }
 
+#[cfg(feature = "server")]
 impl Core {
     /// Create a new Core instance with the given configuration.
     ///
     /// In `db` mode, creates a SQLite database. In `ephemeral` mode, creates
     /// an in-memory index with no persistence.
     pub fn new(config: Config, config_path: PathBuf) -> Result<Self> {
+        config.ensure_valid()?;
+        if let Some(path) = &config.embeddings.model_path {
+            embeddings::set_model_path(config::expand_path(path));
+        }
         let (event_tx, _) = broadcast::channel(256);
         let (ai_event_tx, _) = broadcast::channel(256);
-        let ai_task_queue = AiTaskQueue::new(3);
+        let maintenance = MaintenanceMode::new();
+        let ai_task_queue =
+            AiTaskQueue::new(config.ai.max_concurrent_tasks).with_maintenance(maintenance.clone());
 
         let (db, ephemeral) = if config.storage.is_db() {
             let db_path = config.data_dir().join("yolog.db");
-            let db = Database::new(db_path)?;
+            let mut db = Database::with_read_pool(db_path, config.database.read_pool_size)?
+                .with_id_type(config.database.id_type)
+                .with_fts_tokenizer(config.search.fts_tokenizer)?;
+            if config.database.memory_ann_index {
+                db = db.with_memory_ann_index()?;
+            }
             (Some(Arc::new(db)), None)
         } else {
             let idx = EphemeralIndex::new(config.ephemeral.clone());
+            if let Some(path) = &config.ephemeral.snapshot_path {
+                let path = config::expand_path(path);
+                if path.exists() {
+                    match idx.restore_from(&path) {
+                        Ok(n) => tracing::info!(
+                            "Ephemeral: restored {} session(s) from {}",
+                            n,
+                            path.display()
+                        ),
+                        Err(e) => tracing::warn!(
+                            "Ephemeral: failed to restore snapshot {}: {}",
+                            path.display(),
+                            e
+                        ),
+                    }
+                }
+            }
             (None, Some(Arc::new(idx)))
         };
 
+        let watcher_handle = Arc::new(RwLock::new(None));
+        let reloader =
+            ConfigReloader::new(config.clone(), config_path.clone(), watcher_handle.clone());
+
         Ok(Core {
-            config,
             config_path,
             db,
             ephemeral,
-            watcher_handle: RwLock::new(None),
+            watcher_handle,
             event_tx,
             ai_event_tx,
             ai_task_queue,
+            maintenance,
+            running_tasks: scheduler::RunningTasks::new(),
+            reloader,
+            parsers: Arc::new(ParserRegistry::default()),
         })
     }
 
@@ -113,21 +194,36 @@ impl Core {
     pub fn with_database(config: Config, config_path: PathBuf, db: Arc<Database>) -> Self {
         let (event_tx, _) = broadcast::channel(256);
         let (ai_event_tx, _) = broadcast::channel(256);
-        let ai_task_queue = AiTaskQueue::new(3);
+        let maintenance = MaintenanceMode::new();
+        // Infallible constructor: clamp instead of rejecting a zero limit
+        let ai_task_queue = AiTaskQueue::new(config.ai.max_concurrent_tasks.max(1))
+            .with_maintenance(maintenance.clone());
+        let watcher_handle = Arc::new(RwLock::new(None));
+        let reloader =
+            ConfigReloader::new(config.clone(), config_path.clone(), watcher_handle.clone());
         Core {
-            config,
             config_path,
             db: Some(db),
             ephemeral: None,
-            watcher_handle: RwLock::new(None),
+            watcher_handle,
             event_tx,
             ai_event_tx,
             ai_task_queue,
+            maintenance,
+            running_tasks: scheduler::RunningTasks::new(),
+            reloader,
+            parsers: Arc::new(ParserRegistry::default()),
         }
     }
 
-    /// Start the file watcher for configured watch paths
+    /// Start the file watcher for configured watch paths.
+    /// Does nothing when `server.read_only` is set.
     pub async fn start_watching(&self) -> Result<()> {
+        if self.config().server.read_only {
+            tracing::info!("Read-only mode: file watcher not started");
+            return Ok(());
+        }
+
         // Build the appropriate SessionStore based on storage mode
         let store = if let Some(db) = &self.db {
             Arc::new(watcher::store::SessionStore::Db(db.clone()))
@@ -138,13 +234,15 @@ impl Core {
         };
 
         let handle = watcher::start_watcher(
-            &self.config,
+            &self.reloader.current(),
             self.config_path.clone(),
             store,
+            self.parsers.clone(),
             self.db.clone(),
             self.event_tx.clone(),
             self.ai_event_tx.clone(),
             self.ai_task_queue.clone(),
+            self.maintenance.clone(),
         )
         .await?;
         *self.watcher_handle.write().await = Some(handle);
@@ -154,52 +252,29 @@ impl Core {
     /// Recover pending AI tasks on startup
     ///
     /// Checks for sessions that need title generation, memory extraction, or skill extraction
-    /// and triggers them based on config feature flags. Requires DB storage mode.
+    /// and triggers them based on config feature flags. Requires DB storage mode;
+    /// skipped when `server.read_only` is set.
     pub async fn recover_pending_ai_tasks(&self) {
+        if self.config().server.read_only {
+            return;
+        }
         // Requires DB + active AI
         let db = match &self.db {
             Some(db) => db.clone(),
             None => return,
         };
-        if !self.config.is_ai_active() {
+        // Projects may enable features that are off globally, so only a provider is required
+        let config = self.reloader.current();
+        if config.ai.provider.is_none() {
             return;
         }
 
         // Query pending sessions
+        let include_hidden = config.ai.process_hidden_sessions;
+        let min_messages = config.ai.min_messages_for_processing;
         let sessions = match db
-            .with_conn(|conn| {
-                let mut stmt = conn.prepare(
-                    "SELECT s.id, s.message_count,
-                        (COALESCE(s.title_ai_generated, 0) = 0 AND COALESCE(s.title_edited, 0) = 0) as needs_title,
-                        (s.memories_extracted_at IS NULL) as needs_memory,
-                        (s.skills_extracted_at IS NULL) as needs_skills
-                    FROM sessions s
-                    INNER JOIN projects p ON s.project_id = p.id
-                    WHERE COALESCE(s.import_status, 'success') = 'success'
-                      AND s.message_count >= 25
-                      AND (
-                        (COALESCE(s.title_ai_generated, 0) = 0 AND COALESCE(s.title_edited, 0) = 0)
-                        OR s.memories_extracted_at IS NULL
-                        OR s.skills_extracted_at IS NULL
-                      )
-                    ORDER BY s.created_at DESC
-                    LIMIT 50",
-                )?;
-
-                let results: Vec<(String, usize, bool, bool, bool)> = stmt
-                    .query_map([], |row| {
-                        Ok((
-                            row.get::<_, String>(0)?,
-                            row.get::<_, usize>(1)?,
-                            row.get::<_, i32>(2)? != 0,
-                            row.get::<_, i32>(3)? != 0,
-                            row.get::<_, i32>(4)? != 0,
-                        ))
-                    })?
-                    .filter_map(|r| r.ok())
-                    .collect();
-
-                Ok::<_, rusqlite::Error>(results)
+            .with_conn(move |conn| {
+                ai::auto_trigger::pending_ai_sessions(conn, include_hidden, min_messages)
             })
             .await
         {
@@ -219,44 +294,35 @@ impl Core {
             sessions.len()
         );
 
-        let provider = self
-            .config
-            .ai
-            .provider
-            .as_deref()
-            .and_then(ai::cli::CliProvider::from_config_str)
-            .unwrap_or(ai::cli::CliProvider::ClaudeCode);
+        let providers = ai::cli::CliProvider::chain_from_ai_config(&config.ai);
 
         let mut trigger = ai::AiAutoTrigger::new(
             self.config_path.clone(),
             db.clone(),
             self.ai_event_tx.clone(),
             self.ai_task_queue.clone(),
-            provider,
+            providers,
         );
 
-        for (session_id, message_count, needs_title, needs_memory, needs_skills) in sessions {
+        for ai::auto_trigger::PendingAiSession {
+            session_id,
+            message_count,
+            needs_title,
+            needs_memory,
+            needs_skills,
+            settings,
+        } in sessions
+        {
             let sid = &session_id[..8.min(session_id.len())];
+            let active = |feature| settings.is_feature_active(&config, feature);
 
-            if needs_title
-                && self
-                    .config
-                    .is_feature_active(config::AiFeature::TitleGeneration)
-            {
+            if needs_title && active(config::AiFeature::TitleGeneration) {
                 tracing::info!("AI recovery: triggering title for {}", sid);
                 trigger.on_session_parsed(&session_id, message_count).await;
-            } else if needs_memory
-                && self
-                    .config
-                    .is_feature_active(config::AiFeature::MemoryExtraction)
-            {
+            } else if needs_memory && active(config::AiFeature::MemoryExtraction) {
                 tracing::info!("AI recovery: triggering memory extraction for {}", sid);
                 trigger.on_session_parsed(&session_id, message_count).await;
-            } else if needs_skills
-                && self
-                    .config
-                    .is_feature_active(config::AiFeature::SkillsDiscovery)
-            {
+            } else if needs_skills && active(config::AiFeature::SkillsDiscovery) {
                 tracing::info!("AI recovery: triggering skill extraction for {}", sid);
                 trigger.on_session_parsed(&session_id, message_count).await;
             }
@@ -272,20 +338,46 @@ impl Core {
     }
 
     /// Start the HTTP API server
+    ///
+    /// Once the server has shut down, the ephemeral index is snapshotted if
+    /// `ephemeral.snapshot_path` is set.
     pub async fn start_api_server(&self) -> Result<()> {
-        let addr = self.config.server_addr();
+        let config = self.config();
+        let addr = config.server_addr();
         tracing::info!("Starting API server on {}", addr);
-        api::serve(
+        let result = api::serve(
             addr,
             self.db.clone(),
             self.ephemeral.clone(),
-            &self.config,
+            &config,
             self.config_path.clone(),
             self.event_tx.clone(),
             self.ai_event_tx.clone(),
             self.ai_task_queue.clone(),
+            self.maintenance.clone(),
+            self.running_tasks.clone(),
+            Some(self.reloader.clone()),
         )
-        .await
+        .await;
+        self.snapshot_ephemeral();
+        result
+    }
+
+    /// Save the ephemeral index to `ephemeral.snapshot_path`, if both are set
+    pub fn snapshot_ephemeral(&self) {
+        let (Some(idx), Some(path)) = (&self.ephemeral, self.config().ephemeral.snapshot_path)
+        else {
+            return;
+        };
+        let path = config::expand_path(&path);
+        match idx.snapshot_to(&path) {
+            Ok(()) => tracing::info!(
+                "Ephemeral: saved {} session(s) to {}",
+                idx.session_count(),
+                path.display()
+            ),
+            Err(e) => tracing::warn!("Ephemeral: failed to write snapshot: {}", e),
+        }
     }
 
     /// Get the AI event broadcaster (for emitting AI events)
@@ -298,16 +390,71 @@ impl Core {
         &self.ai_task_queue
     }
 
+    /// Get the maintenance mode switch
+    pub fn maintenance(&self) -> &MaintenanceMode {
+        &self.maintenance
+    }
+
+    /// Get the parser registry. Parsers registered here are available to
+    /// watch paths by name, including for files changed after watching started.
+    pub fn parsers(&self) -> &Arc<ParserRegistry> {
+        &self.parsers
+    }
+
+    /// A copy of the running config, including changes applied by reloads
+    pub fn config(&self) -> Config {
+        self.reloader.current()
+    }
+
+    /// Get the config reloader
+    pub fn config_reloader(&self) -> &ConfigReloader {
+        &self.reloader
+    }
+
+    /// Re-read the config file and apply runtime-changeable settings
+    /// (watch paths, AI features, scheduler tasks). See [`reload`].
+    pub async fn reload_config(&self) -> Result<ReloadSummary> {
+        self.reloader.reload().await
+    }
+
     /// Get a reference to the database (None in ephemeral mode)
     pub fn database(&self) -> Option<&Arc<Database>> {
         self.db.as_ref()
     }
 
     /// Start all enabled periodic background tasks (ranking, duplicate cleanup, embedding refresh).
-    /// Requires DB storage mode.
+    /// Requires DB storage mode; skipped when `server.read_only` is set.
     pub fn start_periodic_tasks(&self) {
+        if self.config().server.read_only {
+            tracing::info!("Read-only mode: scheduler not started");
+            return;
+        }
         if let Some(db) = &self.db {
-            scheduler::start_scheduler(self.config.clone(), db.clone(), self.event_tx.clone());
+            scheduler::start_scheduler(
+                self.reloader.shared(),
+                db.clone(),
+                self.event_tx.clone(),
+                self.maintenance.clone(),
+                self.running_tasks.clone(),
+            );
+        }
+    }
+
+    /// Load the embedding model in the background when `embeddings.preload` is set,
+    /// so the first semantic search doesn't pay for the download and load
+    pub fn preload_embeddings(&self) {
+        if self.config().embeddings.preload {
+            tracing::info!("Preloading embedding model");
+            tokio::task::spawn_blocking(embeddings::preload);
+        }
+    }
+
+    /// Start delivering events to the configured `[[webhooks]]`
+    pub fn start_webhooks(&self) {
+        let webhooks = self.config().webhooks;
+        if !webhooks.is_empty() {
+            tracing::info!("Sending events to {} webhook(s)", webhooks.len());
+            webhooks::spawn(webhooks, &self.event_tx, &self.ai_event_tx);
         }
     }
 
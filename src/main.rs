@@ -19,6 +19,10 @@ struct Args {
     #[arg(long)]
     mcp: bool,
 
+    /// Run in MCP server mode over HTTP (POST/GET /mcp on the server address)
+    #[arg(long, conflicts_with = "mcp")]
+    mcp_http: bool,
+
     /// Override server port
     #[arg(short, long)]
     port: Option<u16>,
@@ -69,7 +73,7 @@ async fn main() -> anyhow::Result<()> {
     // Load configuration
     let config_path = expand_path(&args.config);
     let mut config = if config_path.exists() {
-        Config::from_file(&config_path)?
+        Config::parse_file(&config_path)?
     } else {
         tracing::warn!(
             "Config file not found at {}, using defaults",
@@ -86,6 +90,21 @@ async fn main() -> anyhow::Result<()> {
         config.server.host = host;
     }
 
+    // Report config problems up front instead of at first use
+    match config.validate() {
+        Ok(warnings) => {
+            for warning in warnings {
+                tracing::warn!("Config: {}", warning);
+            }
+        }
+        Err(errors) => {
+            for error in &errors {
+                tracing::error!("Config: {}", error);
+            }
+            anyhow::bail!("Invalid configuration ({} error(s))", errors.len());
+        }
+    }
+
     // Create core instance
     let core = Core::new(config, config_path)?;
 
@@ -93,22 +112,36 @@ async fn main() -> anyhow::Result<()> {
         // MCP server mode - communicate over stdio
         tracing::info!("Starting MCP server mode");
         yocore::mcp::run_mcp_server(core).await?;
+    } else if args.mcp_http {
+        // MCP server mode - streamable HTTP, shared by any number of clients
+        let addr = core.config().server_addr();
+        tracing::info!("Starting MCP HTTP server mode");
+        // Keep the index current; stored and removed sessions are pushed to clients
+        core.start_watching().await?;
+        yocore::mcp::serve_http(addr, core).await?;
     } else {
         // HTTP server mode
         tracing::info!("Starting HTTP server mode");
 
-        // Start file watcher if watch paths are configured
-        if !core.config.watch.is_empty() {
-            tracing::info!(
-                "Starting file watcher for {} watch paths",
-                core.config.watch.len()
-            );
-            core.start_watching().await?;
-        }
+        // Re-read config.toml on SIGHUP
+        core.config_reloader().reload_on_sighup()?;
+
+        // Subscribe webhooks before the initial scan so its events are delivered
+        core.start_webhooks();
+
+        // Start file watcher (idle until a watch path is configured, possibly via reload)
+        tracing::info!(
+            "Starting file watcher for {} watch paths",
+            core.config().watch.len()
+        );
+        core.start_watching().await?;
 
         // Start periodic background tasks (ranking, duplicate cleanup, embedding refresh)
         core.start_periodic_tasks();
 
+        // Warm the embedding model before traffic arrives (embeddings.preload)
+        core.preload_embeddings();
+
         // Recover pending AI tasks (title, memory, skills) from previous sessions
         core.recover_pending_ai_tasks().await;
 
@@ -0,0 +1,78 @@
+//! Maintenance mode
+//!
+//! A process-wide switch that pauses background work (watcher parsing, scheduler
+//! tasks, AI task acquisition) without stopping the server, e.g. while taking a
+//! backup or editing the database by hand.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio::sync::Notify;
+
+/// Shared maintenance flag. Clones refer to the same flag.
+#[derive(Clone, Default)]
+pub struct MaintenanceMode {
+    enabled: Arc<AtomicBool>,
+    changed: Arc<Notify>,
+}
+
+impl MaintenanceMode {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether background work is currently paused
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::SeqCst)
+    }
+
+    /// Turn maintenance mode on or off. Returns the previous state.
+    pub fn set(&self, enabled: bool) -> bool {
+        let previous = self.enabled.swap(enabled, Ordering::SeqCst);
+        if previous != enabled {
+            tracing::info!(
+                "Maintenance mode {}",
+                if enabled { "enabled" } else { "disabled" }
+            );
+            self.changed.notify_waiters();
+        }
+        previous
+    }
+
+    /// Wait until maintenance mode is in the given state (returns immediately if it already is)
+    pub async fn wait_for(&self, enabled: bool) {
+        loop {
+            let notified = self.changed.notified();
+            tokio::pin!(notified);
+            // Register before checking so a change in between isn't missed
+            notified.as_mut().enable();
+            if self.is_enabled() == enabled {
+                return;
+            }
+            notified.await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_wait_for_resumes_on_disable() {
+        let maintenance = MaintenanceMode::new();
+        assert!(!maintenance.set(true));
+
+        let waiter = {
+            let maintenance = maintenance.clone();
+            tokio::spawn(async move { maintenance.wait_for(false).await })
+        };
+        tokio::task::yield_now().await;
+        assert!(!waiter.is_finished());
+
+        assert!(maintenance.set(false));
+        tokio::time::timeout(std::time::Duration::from_secs(1), waiter)
+            .await
+            .unwrap()
+            .unwrap();
+    }
+}
@@ -2,19 +2,80 @@
 //! Wraps yocore's Database with MCP-specific query methods
 #![allow(deprecated)] // conn() is fine in sync code
 
-use super::types::{Memory, MemoryType, Project, SessionContext};
+use super::types::{
+    Memory, MemoryType, Project, SessionContext, SessionMatch, SessionSummary, Skill,
+    TranscriptMessage,
+};
+use crate::config::McpConfig;
+use crate::db::memories::{row_to_memory, LIVE_SESSION_MEMORY};
+use crate::db::search::{fts_match_query, fulltext_search, CreatedRange, SearchFilters};
 use crate::db::Database;
 use std::collections::HashMap;
 use std::sync::Arc;
 
+/// ANN candidates fetched per requested result, since the index spans all projects
+const ANN_OVERFETCH: usize = 10;
+/// Minimum ANN candidates fetched
+const ANN_MIN_CANDIDATES: usize = 100;
+
 /// MCP database operations
 pub struct McpDb {
     db: Arc<Database>,
+    /// Cap applied to every memory query's limit (None = uncapped)
+    max_results: Option<usize>,
+    /// Memory contents longer than this are truncated (None = untouched)
+    max_content_chars: Option<usize>,
 }
 
 impl McpDb {
     pub fn new(db: Arc<Database>) -> Self {
-        Self { db }
+        Self {
+            db,
+            max_results: None,
+            max_content_chars: None,
+        }
+    }
+
+    /// Apply the `[mcp]` result cap and content truncation to all memory queries
+    pub fn with_limits(mut self, config: &McpConfig) -> Self {
+        self.max_results = Some(config.max_results);
+        self.max_content_chars = Some(config.max_content_chars);
+        self
+    }
+
+    /// Clamp a requested limit to the configured maximum
+    fn cap(&self, limit: usize) -> usize {
+        self.max_results.map_or(limit, |max| limit.min(max))
+    }
+
+    /// Truncate long memory contents, noting how much was cut
+    fn truncate_contents(&self, mut memories: Vec<Memory>) -> Vec<Memory> {
+        for m in &mut memories {
+            self.truncate_content(m);
+        }
+        memories
+    }
+
+    fn truncate_content(&self, m: &mut Memory) {
+        self.truncate_text(&mut m.content);
+    }
+
+    fn truncate_text(&self, text: &mut String) {
+        let Some(max_chars) = self.max_content_chars else {
+            return;
+        };
+        let total = text.chars().count();
+        if total > max_chars {
+            let end = text
+                .char_indices()
+                .nth(max_chars)
+                .map_or(text.len(), |(i, _)| i);
+            text.truncate(end);
+            text.push_str(&format!(
+                "… [truncated: showing {} of {} characters]",
+                max_chars, total
+            ));
+        }
     }
 
     /// Access the underlying database
@@ -93,6 +154,49 @@ impl McpDb {
         }
     }
 
+    /// Find the projects that own `path`, walking up one directory at a time.
+    ///
+    /// Each ancestor is matched on whole path components against `folder_path`, both
+    /// as-is and in Claude Code's encoded form. All projects at the deepest matching
+    /// level are returned, so more than one result means the path is ambiguous.
+    pub fn find_projects_for_path(&self, path: &str) -> Result<Vec<Project>, String> {
+        let conn = self.db.conn();
+        let mut stmt = conn
+            .prepare(
+                "SELECT id, name, folder_path FROM projects
+                 WHERE folder_path IN (?1, ?2)
+                 ORDER BY created_at ASC",
+            )
+            .map_err(|e| format!("Failed to prepare project lookup: {}", e))?;
+
+        let normalized_path = path.trim_end_matches('/');
+        for ancestor in std::path::Path::new(normalized_path).ancestors() {
+            let candidate = ancestor.to_string_lossy();
+            if candidate.is_empty() || candidate == "/" {
+                break;
+            }
+            let claude_path = convert_to_claude_project_path(&candidate);
+            let projects = stmt
+                .query_map(
+                    rusqlite::params![candidate.as_ref(), claude_path.as_deref()],
+                    |row| {
+                        Ok(Project {
+                            id: row.get(0)?,
+                            name: row.get(1)?,
+                            folder_path: row.get(2)?,
+                        })
+                    },
+                )
+                .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>())
+                .map_err(|e| format!("Failed to query project: {}", e))?;
+            if !projects.is_empty() {
+                return Ok(projects);
+            }
+        }
+
+        Ok(Vec::new())
+    }
+
     /// Get recent sessions for a project
     pub fn get_recent_sessions(
         &self,
@@ -119,6 +223,81 @@ impl McpDb {
         Ok(session_ids)
     }
 
+    /// Most recent visible sessions across all projects, newest first
+    pub fn list_sessions(&self, limit: usize) -> Result<Vec<SessionSummary>, String> {
+        let conn = self.db.conn();
+
+        let mut stmt = conn
+            .prepare(
+                "SELECT s.id, s.title, p.name, s.ai_tool, s.message_count, s.created_at
+                 FROM sessions s
+                 JOIN projects p ON p.id = s.project_id
+                 WHERE s.is_hidden = 0 AND s.deleted_at IS NULL
+                 ORDER BY s.created_at DESC
+                 LIMIT ?",
+            )
+            .map_err(|e| format!("Failed to prepare query: {}", e))?;
+
+        let sessions = stmt
+            .query_map([self.cap(limit) as i64], row_to_session_summary)
+            .map_err(|e| format!("Failed to query sessions: {}", e))?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        Ok(sessions)
+    }
+
+    /// A session with its full transcript, or None if it doesn't exist.
+    /// Message contents are truncated like memory contents.
+    pub fn get_session_transcript(
+        &self,
+        session_id: &str,
+    ) -> Result<Option<(SessionSummary, Vec<TranscriptMessage>)>, String> {
+        let conn = self.db.conn();
+
+        let session = match conn.query_row(
+            "SELECT s.id, s.title, p.name, s.ai_tool, s.message_count, s.created_at
+             FROM sessions s
+             JOIN projects p ON p.id = s.project_id
+             WHERE s.id = ?",
+            [session_id],
+            row_to_session_summary,
+        ) {
+            Ok(session) => session,
+            Err(rusqlite::Error::QueryReturnedNoRows) => return Ok(None),
+            Err(e) => return Err(format!("Failed to query session: {}", e)),
+        };
+
+        let mut stmt = conn
+            .prepare(
+                "SELECT sequence_num, role, tool_name,
+                        COALESCE(NULLIF(search_content, ''), content_preview, '')
+                 FROM session_messages
+                 WHERE session_id = ? AND role != 'system'
+                 ORDER BY sequence_num",
+            )
+            .map_err(|e| format!("Failed to prepare query: {}", e))?;
+
+        let messages = stmt
+            .query_map([session_id], |row| {
+                Ok(TranscriptMessage {
+                    sequence_num: row.get(0)?,
+                    role: row.get(1)?,
+                    tool_name: row.get(2)?,
+                    content: row.get(3)?,
+                })
+            })
+            .map_err(|e| format!("Failed to query messages: {}", e))?
+            .filter_map(|r| r.ok())
+            .map(|mut m| {
+                self.truncate_text(&mut m.content);
+                m
+            })
+            .collect();
+
+        Ok(Some((session, messages)))
+    }
+
     /// Get or create session context for a session
     pub fn get_or_create_session_context(
         &self,
@@ -134,21 +313,7 @@ impl McpDb {
                     resume_context, source, created_at, updated_at
              FROM session_context WHERE session_id = ?",
             [session_id],
-            |row| {
-                let decisions_json: String = row.get(3)?;
-                let questions_json: String = row.get(4)?;
-                Ok(SessionContext {
-                    session_id: row.get(0)?,
-                    project_id: row.get(1)?,
-                    active_task: row.get(2)?,
-                    recent_decisions: serde_json::from_str(&decisions_json).unwrap_or_default(),
-                    open_questions: serde_json::from_str(&questions_json).unwrap_or_default(),
-                    resume_context: row.get(5)?,
-                    source: row.get(6)?,
-                    created_at: row.get(7)?,
-                    updated_at: row.get(8)?,
-                })
-            },
+            row_to_session_context,
         );
 
         match result {
@@ -188,21 +353,7 @@ impl McpDb {
                     resume_context, source, created_at, updated_at
              FROM session_context WHERE session_id = ?",
             [session_id],
-            |row| {
-                let decisions_json: String = row.get(3)?;
-                let questions_json: String = row.get(4)?;
-                Ok(SessionContext {
-                    session_id: row.get(0)?,
-                    project_id: row.get(1)?,
-                    active_task: row.get(2)?,
-                    recent_decisions: serde_json::from_str(&decisions_json).unwrap_or_default(),
-                    open_questions: serde_json::from_str(&questions_json).unwrap_or_default(),
-                    resume_context: row.get(5)?,
-                    source: row.get(6)?,
-                    created_at: row.get(7)?,
-                    updated_at: row.get(8)?,
-                })
-            },
+            row_to_session_context,
         );
 
         match result {
@@ -226,6 +377,28 @@ impl McpDb {
         Ok(())
     }
 
+    /// Most recently updated session context in a project that has a saved lifeboat
+    pub fn get_latest_lifeboat(&self, project_id: &str) -> Result<Option<SessionContext>, String> {
+        let conn = self.db.conn();
+
+        let result = conn.query_row(
+            "SELECT session_id, project_id, active_task, recent_decisions, open_questions,
+                    resume_context, source, created_at, updated_at
+             FROM session_context
+             WHERE project_id = ? AND resume_context IS NOT NULL
+             ORDER BY updated_at DESC
+             LIMIT 1",
+            [project_id],
+            row_to_session_context,
+        );
+
+        match result {
+            Ok(ctx) => Ok(Some(ctx)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(format!("Failed to get lifeboat: {}", e)),
+        }
+    }
+
     /// Get recent sessions with context (excluding current)
     pub fn get_recent_sessions_with_context(
         &self,
@@ -294,14 +467,16 @@ impl McpDb {
         memory_types: Option<&[MemoryType]>,
         limit: usize,
     ) -> Result<Vec<Memory>, String> {
+        let limit = self.cap(limit);
         let conn = self.db.conn();
 
-        let mut sql = String::from(
+        let mut sql = format!(
             "SELECT m.id, m.project_id, m.session_id, m.memory_type, m.title, m.content,
                     m.context, m.tags, m.confidence, m.is_validated, m.extracted_at, m.file_reference, m.state
              FROM memories m
              JOIN memories_fts ON m.id = memories_fts.rowid
-             WHERE memories_fts MATCH ? AND m.state != 'removed' AND m.project_id = ?",
+             WHERE memories_fts MATCH ? AND m.state != 'removed' AND m.project_id = ?
+               AND {LIVE_SESSION_MEMORY}",
         );
 
         let mut params: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
@@ -336,7 +511,41 @@ impl McpDb {
             .filter_map(|r| r.ok())
             .collect();
 
-        Ok(memories)
+        Ok(self.truncate_contents(memories))
+    }
+
+    /// Search session transcripts with the same FTS query as `POST /api/search`
+    pub fn search_sessions(
+        &self,
+        query: &str,
+        project_id: Option<&str>,
+        role: Option<&str>,
+        has_code: Option<bool>,
+        limit: usize,
+    ) -> Result<Vec<SessionMatch>, String> {
+        let query = fts_match_query(query);
+        if query.is_empty() {
+            return Ok(Vec::new());
+        }
+        let conn = self.db.conn();
+        let rows = fulltext_search(
+            &conn,
+            &query,
+            project_id,
+            &SearchFilters {
+                role: role.map(String::from),
+                has_code,
+                ..Default::default()
+            },
+            &CreatedRange::default(),
+            self.cap(limit) as i64,
+            0,
+        )
+        .map_err(|e| format!("Failed to search sessions: {}", e))?;
+
+        rows.into_iter()
+            .map(|row| serde_json::from_value(row).map_err(|e| e.to_string()))
+            .collect()
     }
 
     /// Get memories by type
@@ -346,17 +555,19 @@ impl McpDb {
         memory_type: MemoryType,
         limit: usize,
     ) -> Result<Vec<Memory>, String> {
+        let limit = self.cap(limit);
         let conn = self.db.conn();
 
         let mut stmt = conn
-            .prepare(
+            .prepare(&format!(
                 "SELECT m.id, m.project_id, m.session_id, m.memory_type, m.title, m.content,
                         m.context, m.tags, m.confidence, m.is_validated, m.extracted_at, m.file_reference, m.state
                  FROM memories m
                  WHERE m.project_id = ? AND m.memory_type = ? AND m.state != 'removed'
+                   AND {LIVE_SESSION_MEMORY}
                  ORDER BY m.confidence DESC, m.extracted_at DESC
                  LIMIT ?",
-            )
+            ))
             .map_err(|e| format!("Failed to prepare query: {}", e))?;
 
         let memories = stmt
@@ -368,7 +579,7 @@ impl McpDb {
             .filter_map(|r| r.ok())
             .collect();
 
-        Ok(memories)
+        Ok(self.truncate_contents(memories))
     }
 
     /// Get memories from specific sessions
@@ -377,6 +588,7 @@ impl McpDb {
         session_ids: &[String],
         limit: usize,
     ) -> Result<Vec<Memory>, String> {
+        let limit = self.cap(limit);
         if session_ids.is_empty() {
             return Ok(vec![]);
         }
@@ -388,10 +600,11 @@ impl McpDb {
             "SELECT m.id, m.project_id, m.session_id, m.memory_type, m.title, m.content,
                     m.context, m.tags, m.confidence, m.is_validated, m.extracted_at, m.file_reference, m.state
              FROM memories m
-             WHERE m.session_id IN ({}) AND m.state != 'removed'
+             WHERE m.session_id IN ({}) AND m.state != 'removed' AND {}
              ORDER BY m.extracted_at DESC
              LIMIT ?",
-            placeholders.join(", ")
+            placeholders.join(", "),
+            LIVE_SESSION_MEMORY
         );
 
         let mut params: Vec<Box<dyn rusqlite::ToSql>> = session_ids
@@ -412,7 +625,7 @@ impl McpDb {
             .filter_map(|r| r.ok())
             .collect();
 
-        Ok(memories)
+        Ok(self.truncate_contents(memories))
     }
 
     /// Search memories using vector similarity
@@ -424,20 +637,89 @@ impl McpDb {
         limit: usize,
     ) -> Result<Vec<Memory>, String> {
         let query_embedding = crate::embeddings::embed_text(query)?;
+        Ok(self
+            .rank_memories_by_embedding(&query_embedding, project_id, memory_types, limit)?
+            .into_iter()
+            .map(|(m, _)| m)
+            .collect())
+    }
 
+    /// Count non-removed project memories that have an embedding
+    pub fn count_memory_embeddings(&self, project_id: &str) -> Result<usize, String> {
+        let conn = self.db.conn();
+        conn.query_row(
+            "SELECT COUNT(*)
+             FROM memory_embeddings me
+             JOIN memories m ON me.memory_id = m.id
+             WHERE m.project_id = ? AND m.state != 'removed'",
+            [project_id],
+            |row| row.get::<_, i64>(0),
+        )
+        .map(|n| n as usize)
+        .map_err(|e| format!("Failed to count embeddings: {}", e))
+    }
+
+    /// Rank a project's embedded memories by cosine similarity to `query_embedding`.
+    /// Returns (memory, score) pairs, best first.
+    ///
+    /// Uses the database's ANN index when enabled, falling back to scoring every
+    /// embedding when the index is empty or its candidates hold too few matches.
+    pub fn rank_memories_by_embedding(
+        &self,
+        query_embedding: &[f32],
+        project_id: &str,
+        memory_types: Option<&[MemoryType]>,
+        limit: usize,
+    ) -> Result<Vec<(Memory, f32)>, String> {
+        let limit = self.cap(limit);
+
+        // The index spans all projects, so over-fetch before filtering
+        let k = (limit * ANN_OVERFETCH).max(ANN_MIN_CANDIDATES);
+        if let Some(candidates) = self.db.search_memory_ann(query_embedding, k) {
+            let ids: Vec<i64> = candidates.into_iter().map(|(id, _)| id).collect();
+            let ranked =
+                self.score_memories(query_embedding, project_id, memory_types, Some(&ids), limit)?;
+            if ranked.len() >= limit {
+                return Ok(ranked);
+            }
+        }
+        self.score_memories(query_embedding, project_id, memory_types, None, limit)
+    }
+
+    /// Score a project's embedded memories (only `candidates`, if given) by cosine
+    /// similarity and return the top `limit`
+    fn score_memories(
+        &self,
+        query_embedding: &[f32],
+        project_id: &str,
+        memory_types: Option<&[MemoryType]>,
+        candidates: Option<&[i64]>,
+        limit: usize,
+    ) -> Result<Vec<(Memory, f32)>, String> {
         let conn = self.db.conn();
 
         // Load embeddings for project memories
-        let mut sql = String::from(
+        let mut sql = format!(
             "SELECT me.memory_id, me.embedding
              FROM memory_embeddings me
              JOIN memories m ON me.memory_id = m.id
-             WHERE m.project_id = ? AND m.state != 'removed'",
+             WHERE m.project_id = ? AND m.state != 'removed' AND {LIVE_SESSION_MEMORY}",
         );
 
         let mut params: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
         params.push(Box::new(project_id.to_string()));
 
+        if let Some(ids) = candidates {
+            let placeholders: Vec<&str> = ids.iter().map(|_| "?").collect();
+            sql.push_str(&format!(
+                " AND me.memory_id IN ({})",
+                placeholders.join(", ")
+            ));
+            for id in ids {
+                params.push(Box::new(*id));
+            }
+        }
+
         if let Some(types) = memory_types {
             if !types.is_empty() {
                 let placeholders: Vec<&str> = types.iter().map(|_| "?").collect();
@@ -467,7 +749,7 @@ impl McpDb {
             .filter_map(|r| r.ok())
             .map(|(id, bytes)| {
                 let embedding = crate::embeddings::bytes_to_embedding(&bytes);
-                let score = crate::embeddings::cosine_similarity(&query_embedding, &embedding);
+                let score = crate::embeddings::cosine_similarity(query_embedding, &embedding);
                 (score, id)
             })
             .collect();
@@ -499,7 +781,7 @@ impl McpDb {
         let mut fetch_stmt = conn
             .prepare(&fetch_sql)
             .map_err(|e| format!("Failed to prepare fetch: {}", e))?;
-        let memories_map: HashMap<i64, Memory> = fetch_stmt
+        let mut memories_map: HashMap<i64, Memory> = fetch_stmt
             .query_map(fetch_refs.as_slice(), row_to_memory)
             .map_err(|e| format!("Failed to fetch memories: {}", e))?
             .filter_map(|r| r.ok())
@@ -508,8 +790,12 @@ impl McpDb {
 
         // Return in similarity-score order
         Ok(scored_ids
-            .iter()
-            .filter_map(|(_, id)| memories_map.get(id).cloned())
+            .into_iter()
+            .filter_map(|(score, id)| {
+                let mut m = memories_map.remove(&id)?;
+                self.truncate_content(&mut m);
+                Some((m, score))
+            })
             .collect())
     }
 
@@ -522,6 +808,7 @@ impl McpDb {
         limit: usize,
     ) -> Result<Vec<Memory>, String> {
         const RRF_K: f64 = 60.0;
+        let limit = self.cap(limit);
 
         // Run both searches with larger limits for better fusion
         let search_limit = limit * 3;
@@ -575,13 +862,14 @@ impl McpDb {
         memory_types: Option<&[MemoryType]>,
         limit: usize,
     ) -> Result<Vec<Memory>, String> {
+        let limit = self.cap(limit);
         let conn = self.db.conn();
 
-        let mut sql = String::from(
+        let mut sql = format!(
             "SELECT m.id, m.project_id, m.session_id, m.memory_type, m.title, m.content,
                     m.context, m.tags, m.confidence, m.is_validated, m.extracted_at, m.file_reference, m.state
              FROM memories m
-             WHERE m.project_id = ? AND m.state != 'removed'",
+             WHERE m.project_id = ? AND m.state != 'removed' AND {LIVE_SESSION_MEMORY}",
         );
 
         let mut params: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
@@ -617,7 +905,7 @@ impl McpDb {
             .filter_map(|r| r.ok())
             .collect();
 
-        Ok(memories)
+        Ok(self.truncate_contents(memories))
     }
 
     /// Get high-state (persistent) memories for a project
@@ -626,17 +914,18 @@ impl McpDb {
         project_id: &str,
         limit: usize,
     ) -> Result<Vec<Memory>, String> {
+        let limit = self.cap(limit);
         let conn = self.db.conn();
 
         let mut stmt = conn
-            .prepare(
+            .prepare(&format!(
                 "SELECT m.id, m.project_id, m.session_id, m.memory_type, m.title, m.content,
                         m.context, m.tags, m.confidence, m.is_validated, m.extracted_at, m.file_reference, m.state
                  FROM memories m
-                 WHERE m.project_id = ? AND m.state = 'high'
+                 WHERE m.project_id = ? AND m.state = 'high' AND {LIVE_SESSION_MEMORY}
                  ORDER BY m.confidence DESC, m.extracted_at DESC
                  LIMIT ?",
-            )
+            ))
             .map_err(|e| format!("Failed to prepare query: {}", e))?;
 
         let memories = stmt
@@ -645,37 +934,70 @@ impl McpDb {
             .filter_map(|r| r.ok())
             .collect();
 
-        Ok(memories)
+        Ok(self.truncate_contents(memories))
     }
-}
 
-/// Convert a database row to Memory
-/// Expects columns: id, project_id, session_id, memory_type, title, content,
-///                   context, tags, confidence, is_validated, extracted_at, file_reference, state
-fn row_to_memory(row: &rusqlite::Row) -> rusqlite::Result<Memory> {
-    let memory_type_str: String = row.get(3)?;
-    let tags_json: String = row.get(7)?;
+    /// Most established skills for a project: seen in the most sessions, then most confident
+    pub fn get_top_skills(&self, project_id: &str, limit: usize) -> Result<Vec<Skill>, String> {
+        let conn = self.db.conn();
 
-    Ok(Memory {
-        id: row.get(0)?,
+        let mut stmt = conn
+            .prepare(
+                "SELECT s.id, s.name, s.description, s.steps, s.confidence,
+                        1 + (SELECT COUNT(*) FROM skill_sessions WHERE skill_id = s.id) AS session_count
+                 FROM skills s
+                 WHERE s.project_id = ?
+                 ORDER BY session_count DESC, s.confidence DESC, s.extracted_at DESC
+                 LIMIT ?",
+            )
+            .map_err(|e| format!("Failed to prepare query: {}", e))?;
+
+        let skills = stmt
+            .query_map(rusqlite::params![project_id, limit as i64], |row| {
+                let steps_json: String = row.get(3)?;
+                Ok(Skill {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                    description: row.get(2)?,
+                    steps: serde_json::from_str(&steps_json).unwrap_or_default(),
+                    confidence: row.get(4)?,
+                    session_count: row.get(5)?,
+                })
+            })
+            .map_err(|e| format!("Failed to execute query: {}", e))?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        Ok(skills)
+    }
+}
+
+/// Convert a session_context row (all columns, in table order) to SessionContext
+fn row_to_session_context(row: &rusqlite::Row) -> rusqlite::Result<SessionContext> {
+    let decisions_json: String = row.get(3)?;
+    let questions_json: String = row.get(4)?;
+    Ok(SessionContext {
+        session_id: row.get(0)?,
         project_id: row.get(1)?,
-        session_id: row.get(2)?,
-        memory_type: MemoryType::from_str(&memory_type_str).unwrap_or(MemoryType::Context),
-        title: row.get(4)?,
-        content: row.get(5)?,
-        context: row.get(6)?,
-        tags: serde_json::from_str(&tags_json).unwrap_or_default(),
-        confidence: row.get(8)?,
-        is_validated: row.get(9)?,
-        extracted_at: row.get(10)?,
-        file_reference: row.get(11)?,
-        state: row.get(12)?,
+        active_task: row.get(2)?,
+        recent_decisions: serde_json::from_str(&decisions_json).unwrap_or_default(),
+        open_questions: serde_json::from_str(&questions_json).unwrap_or_default(),
+        resume_context: row.get(5)?,
+        source: row.get(6)?,
+        created_at: row.get(7)?,
+        updated_at: row.get(8)?,
     })
 }
 
-/// Public wrapper for row_to_memory (used by HTTP API for cross-project search)
-pub fn row_to_memory_pub(row: &rusqlite::Row) -> rusqlite::Result<Memory> {
-    row_to_memory(row)
+fn row_to_session_summary(row: &rusqlite::Row) -> rusqlite::Result<SessionSummary> {
+    Ok(SessionSummary {
+        id: row.get(0)?,
+        title: row.get(1)?,
+        project_name: row.get(2)?,
+        ai_tool: row.get(3)?,
+        message_count: row.get(4)?,
+        created_at: row.get(5)?,
+    })
 }
 
 /// Build FTS5 query from user input
@@ -689,9 +1011,209 @@ fn build_fts_query(query: &str) -> String {
 }
 
 /// Convert filesystem path to Claude Code project path format
-fn convert_to_claude_project_path(path: &str) -> Option<String> {
+pub(crate) fn convert_to_claude_project_path(path: &str) -> Option<String> {
     let home = dirs::home_dir()?;
     let home_str = home.to_str()?;
     let path_component = path.replace('/', "-");
     Some(format!("{}/.claude/projects/{}", home_str, path_component))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support;
+
+    fn seeded_db(memories: usize) -> (tempfile::TempDir, Arc<Database>) {
+        let tmp = tempfile::tempdir().unwrap();
+        let db = Database::new(tmp.path().join("test.db")).unwrap();
+        {
+            let conn = db.conn();
+            test_support::seed_session(&conn, "s1");
+            for i in 0..memories {
+                conn.execute(
+                    "INSERT INTO memories (project_id, session_id, memory_type, title, content, extracted_at)
+                     VALUES ('p1', 's1', 'fact', ?1, ?2, '2024-01-01T00:00:00Z')",
+                    rusqlite::params![format!("memory {}", i), "x".repeat(100)],
+                )
+                .unwrap();
+            }
+        }
+        (tmp, Arc::new(db))
+    }
+
+    #[test]
+    fn test_excessive_limit_is_clamped() {
+        let (_tmp, db) = seeded_db(30);
+        let config = McpConfig {
+            max_results: 5,
+            max_content_chars: 10,
+        };
+        let mcp_db = McpDb::new(db.clone()).with_limits(&config);
+
+        let memories = mcp_db.browse_memories("p1", None, 10_000).unwrap();
+        assert_eq!(memories.len(), 5);
+        assert!(memories[0].content.starts_with("xxxxxxxxxx…"));
+        assert!(memories[0]
+            .content
+            .ends_with("[truncated: showing 10 of 100 characters]"));
+
+        let sessions = vec!["s1".to_string()];
+        assert_eq!(
+            mcp_db
+                .get_memories_by_sessions(&sessions, 10_000)
+                .unwrap()
+                .len(),
+            5
+        );
+
+        // Without limits (HTTP callers) the requested limit is honoured
+        let uncapped = McpDb::new(db).browse_memories("p1", None, 10_000).unwrap();
+        assert_eq!(uncapped.len(), 30);
+        assert_eq!(uncapped[0].content.len(), 100);
+    }
+
+    #[test]
+    fn test_memories_of_deleted_sessions_are_excluded() {
+        let (_tmp, db) = seeded_db(1);
+        {
+            let conn = db.conn();
+            test_support::seed_session(&conn, "gone");
+            conn.execute_batch(
+                "INSERT INTO memories (project_id, session_id, memory_type, title, content, state, extracted_at)
+                 VALUES ('p1', 'gone', 'fact', 'memory gone', 'deleted with its session', 'high',
+                         '2024-01-01T00:00:00Z');
+                 UPDATE sessions SET deleted_at = '2024-02-01T00:00:00Z' WHERE id = 'gone';",
+            )
+            .unwrap();
+        }
+        let mcp_db = McpDb::new(db);
+        let titles = |memories: Vec<Memory>| -> Vec<String> {
+            memories.into_iter().map(|m| m.title).collect()
+        };
+
+        assert_eq!(
+            titles(
+                mcp_db
+                    .search_memories_fts("memory", "p1", None, 10)
+                    .unwrap()
+            ),
+            vec!["memory 0"]
+        );
+        assert_eq!(
+            titles(mcp_db.browse_memories("p1", None, 10).unwrap()),
+            vec!["memory 0"]
+        );
+        assert_eq!(
+            titles(
+                mcp_db
+                    .get_memories_by_type("p1", MemoryType::Fact, 10)
+                    .unwrap()
+            ),
+            vec!["memory 0"]
+        );
+        assert!(mcp_db
+            .get_memories_by_sessions(&["gone".to_string()], 10)
+            .unwrap()
+            .is_empty());
+        assert!(mcp_db.get_persistent_memories("p1", 10).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_search_sessions_finds_phrase() {
+        let (_tmp, db) = seeded_db(0);
+        {
+            let conn = db.conn();
+            conn.execute_batch(
+                "UPDATE sessions SET title = 'Fix flaky login' WHERE id = 's1';
+                 INSERT INTO session_messages (session_id, sequence_num, role, content_preview,
+                     search_content, byte_offset, byte_length, timestamp)
+                 VALUES
+                     ('s1', 0, 'user', 'The login test is flaky', 'The login test is flaky',
+                      0, 10, '2024-01-01T00:00:00Z'),
+                     ('s1', 1, 'assistant', 'Fixed the race in the token refresh',
+                      'Fixed the race in the token refresh', 10, 10, '2024-01-01T00:00:01Z');",
+            )
+            .unwrap();
+        }
+        let mcp_db = McpDb::new(db);
+
+        let hits = mcp_db
+            .search_sessions("\"token refresh\"", Some("p1"), None, None, 10)
+            .unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].session_title.as_deref(), Some("Fix flaky login"));
+        assert_eq!(hits[0].sequence_num, 1);
+
+        // Role filter reuses the HTTP search semantics
+        let user_only = mcp_db
+            .search_sessions("token", None, Some("user"), None, 10)
+            .unwrap();
+        assert!(user_only.is_empty());
+    }
+
+    #[test]
+    fn test_rank_memories_by_embedding_orders_by_similarity() {
+        let (_tmp, db) = seeded_db(3);
+        {
+            let conn = db.conn();
+            for (id, embedding) in [
+                (1, [0.0f32, 1.0, 0.0]),
+                (2, [1.0, 0.0, 0.0]),
+                (3, [0.7, 0.7, 0.0]),
+            ] {
+                conn.execute(
+                    "INSERT INTO memory_embeddings (memory_id, embedding) VALUES (?1, ?2)",
+                    rusqlite::params![id, crate::embeddings::embedding_to_bytes(&embedding)],
+                )
+                .unwrap();
+            }
+        }
+        let mcp_db = McpDb::new(db);
+        assert_eq!(mcp_db.count_memory_embeddings("p1").unwrap(), 3);
+
+        let ranked = mcp_db
+            .rank_memories_by_embedding(&[1.0, 0.0, 0.0], "p1", None, 10)
+            .unwrap();
+        let ids: Vec<i64> = ranked.iter().map(|(m, _)| m.id).collect();
+        assert_eq!(ids, vec![2, 3, 1]);
+        assert!((ranked[0].1 - 1.0).abs() < 1e-6);
+        assert!(ranked[2].1.abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_rank_memories_with_ann_index() {
+        let (tmp, db) = seeded_db(3);
+        {
+            let conn = db.conn();
+            for (id, embedding) in [(1, [0.0f32, 1.0, 0.0]), (2, [1.0, 0.0, 0.0])] {
+                conn.execute(
+                    "INSERT INTO memory_embeddings (memory_id, embedding) VALUES (?1, ?2)",
+                    rusqlite::params![id, crate::embeddings::embedding_to_bytes(&embedding)],
+                )
+                .unwrap();
+            }
+        }
+        drop(db);
+
+        // Built from the stored embeddings, then updated on insert
+        let db = Database::new(tmp.path().join("test.db"))
+            .unwrap()
+            .with_memory_ann_index()
+            .unwrap();
+        assert_eq!(db.search_memory_ann(&[1.0, 0.0, 0.0], 1).unwrap()[0].0, 2);
+        let embedding = [0.7f32, 0.7, 0.0];
+        db.conn()
+            .execute(
+                "INSERT INTO memory_embeddings (memory_id, embedding) VALUES (3, ?1)",
+                [crate::embeddings::embedding_to_bytes(&embedding)],
+            )
+            .unwrap();
+        db.index_memory_embedding(3, &embedding);
+
+        let ranked = McpDb::new(Arc::new(db))
+            .rank_memories_by_embedding(&[1.0, 0.0, 0.0], "p1", None, 10)
+            .unwrap();
+        let ids: Vec<i64> = ranked.iter().map(|(m, _)| m.id).collect();
+        assert_eq!(ids, vec![2, 3, 1]);
+    }
+}
@@ -5,23 +5,29 @@ use serde_json::{json, Value};
 
 use super::db::McpDb;
 use super::protocol::{
-    InitializeResult, JsonRpcError, JsonRpcResponse, ResourceDefinition, ServerCapabilities,
-    ServerInfo, ToolCallResult, ToolDefinition, ToolsCapability,
+    InitializeResult, JsonRpcError, JsonRpcResponse, ResourceContents, ResourceDefinition,
+    ResourcesCapability, ServerCapabilities, ServerInfo, ToolCallResult, ToolDefinition,
+    ToolsCapability,
 };
 use super::types::{
     GetProjectContextParams, GetRecentMemoriesParams, GetSessionContextParams, MemoryType,
-    ProjectContext, SaveLifeboatParams, SearchMemoriesParams, SessionContextResult,
+    ProjectContext, SaveLifeboatParams, SearchMemoriesParams, SearchSessionsParams,
+    SessionContextResult,
 };
 
-/// Handle the initialize method
-pub fn handle_initialize(id: Value) -> JsonRpcResponse {
+/// Handle the initialize method. `push_notifications` is set by transports
+/// that send `notifications/resources/list_changed` (HTTP, not stdio).
+pub fn handle_initialize(id: Value, push_notifications: bool) -> JsonRpcResponse {
     let result = InitializeResult {
         protocol_version: "2024-11-05".to_string(),
         capabilities: ServerCapabilities {
             tools: ToolsCapability {
                 list_changed: Some(false),
             },
-            resources: None,
+            resources: Some(ResourcesCapability {
+                subscribe: false,
+                list_changed: Some(push_notifications),
+            }),
         },
         server_info: ServerInfo {
             name: "yocore".to_string(),
@@ -73,6 +79,38 @@ pub fn handle_tools_list(id: Value) -> JsonRpcResponse {
                 }
             }),
         },
+        ToolDefinition {
+            name: "yolog_search_sessions".to_string(),
+            description: "Full-text search over past session transcripts. Use to recall how something was done or fixed. Returns matching messages with their session title and sequence number.".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "query": {
+                        "type": "string",
+                        "description": "Search query; quote phrases (\"token refresh\"), `*` for prefixes, AND/OR/NOT between terms"
+                    },
+                    "project_path": {
+                        "type": "string",
+                        "description": "Project directory path (searches all projects when omitted)"
+                    },
+                    "role": {
+                        "type": "string",
+                        "enum": ["all", "user", "assistant", "tool"],
+                        "description": "Only match messages from this role"
+                    },
+                    "has_code": {
+                        "type": "boolean",
+                        "description": "Only match messages containing code"
+                    },
+                    "limit": {
+                        "type": "integer",
+                        "default": 10,
+                        "description": "Maximum number of results"
+                    }
+                },
+                "required": ["query"]
+            }),
+        },
         ToolDefinition {
             name: "yolog_get_project_context".to_string(),
             description: "Get high-level project context including key decisions, facts, and preferences.".to_string(),
@@ -178,6 +216,7 @@ pub fn handle_tools_call(id: Value, params: Option<Value>, db: &McpDb) -> JsonRp
 
     let result = match tool_name {
         "yolog_search_memories" => handle_search_memories(arguments, db),
+        "yolog_search_sessions" => handle_search_sessions(arguments, db),
         "yolog_get_project_context" => handle_get_project_context(arguments, db),
         "yolog_get_recent_memories" => handle_get_recent_memories(arguments, db),
         "yolog_get_session_context" => handle_get_session_context(arguments, db),
@@ -191,12 +230,89 @@ pub fn handle_tools_call(id: Value, params: Option<Value>, db: &McpDb) -> JsonRp
     }
 }
 
+/// URI prefix for session transcript resources (`yolog://session/{id}`)
+const SESSION_URI_PREFIX: &str = "yolog://session/";
+
+/// Number of recent sessions listed by resources/list
+const RESOURCE_LIST_LIMIT: usize = 50;
+
 /// Handle the resources/list method
-pub fn handle_resources_list(id: Value) -> JsonRpcResponse {
-    let resources: Vec<ResourceDefinition> = vec![];
+pub fn handle_resources_list(id: Value, db: &McpDb) -> JsonRpcResponse {
+    let sessions = match db.list_sessions(RESOURCE_LIST_LIMIT) {
+        Ok(s) => s,
+        Err(e) => return JsonRpcResponse::error(id, JsonRpcError::internal_error(e)),
+    };
+
+    let resources: Vec<ResourceDefinition> = sessions
+        .into_iter()
+        .map(|s| ResourceDefinition {
+            uri: format!("{}{}", SESSION_URI_PREFIX, s.id),
+            name: s.title.unwrap_or_else(|| "Untitled session".to_string()),
+            description: format!(
+                "{} session in '{}' ({} messages, {})",
+                s.ai_tool, s.project_name, s.message_count, s.created_at
+            ),
+            mime_type: Some("text/plain".to_string()),
+        })
+        .collect();
     JsonRpcResponse::success(id, json!({ "resources": resources }))
 }
 
+/// Handle the resources/read method
+pub fn handle_resources_read(id: Value, params: Option<Value>, db: &McpDb) -> JsonRpcResponse {
+    let Some(uri) = params
+        .as_ref()
+        .and_then(|p| p.get("uri"))
+        .and_then(|v| v.as_str())
+    else {
+        return JsonRpcResponse::error(
+            id,
+            JsonRpcError::invalid_params("Missing resource uri".to_string()),
+        );
+    };
+
+    let Some(session_id) = uri.strip_prefix(SESSION_URI_PREFIX) else {
+        return JsonRpcResponse::error(
+            id,
+            JsonRpcError::invalid_params(format!("Unknown resource uri: {}", uri)),
+        );
+    };
+
+    let (session, messages) = match db.get_session_transcript(session_id) {
+        Ok(Some(transcript)) => transcript,
+        Ok(None) => {
+            return JsonRpcResponse::error(
+                id,
+                JsonRpcError::invalid_params(format!("Resource not found: {}", uri)),
+            );
+        }
+        Err(e) => return JsonRpcResponse::error(id, JsonRpcError::internal_error(e)),
+    };
+
+    let mut text = format!(
+        "# {}\n\n{} session in '{}', {}\n\n",
+        session.title.as_deref().unwrap_or("Untitled session"),
+        session.ai_tool,
+        session.project_name,
+        session.created_at
+    );
+    for m in &messages {
+        match &m.tool_name {
+            Some(tool) => text.push_str(&format!("[#{} {} · {}]\n", m.sequence_num, m.role, tool)),
+            None => text.push_str(&format!("[#{} {}]\n", m.sequence_num, m.role)),
+        }
+        text.push_str(&m.content);
+        text.push_str("\n\n");
+    }
+
+    let contents = vec![ResourceContents {
+        uri: uri.to_string(),
+        mime_type: "text/plain".to_string(),
+        text,
+    }];
+    JsonRpcResponse::success(id, json!({ "contents": contents }))
+}
+
 /// Handle yolog_search_memories tool call
 /// Supports: query-only, type-filter-only, tag-filter-only, and combined modes
 fn handle_search_memories(arguments: Value, db: &McpDb) -> ToolCallResult {
@@ -342,6 +458,81 @@ fn handle_search_memories(arguments: Value, db: &McpDb) -> ToolCallResult {
     ToolCallResult::text(output)
 }
 
+/// Handle yolog_search_sessions tool call
+fn handle_search_sessions(arguments: Value, db: &McpDb) -> ToolCallResult {
+    let params: SearchSessionsParams = match serde_json::from_value(arguments) {
+        Ok(p) => p,
+        Err(e) => return ToolCallResult::error(format!("Invalid parameters: {}", e)),
+    };
+
+    let project = match params.project_path.as_deref() {
+        Some(path) => match db.get_project_by_path_prefix(path) {
+            Ok(Some(p)) => Some(p),
+            Ok(None) => {
+                return ToolCallResult::text(format!("No Yolog project found for path: {}", path));
+            }
+            Err(e) => return ToolCallResult::error(format!("Database error: {}", e)),
+        },
+        None => None,
+    };
+
+    let query_str = params.query.trim();
+    if query_str.is_empty() {
+        return ToolCallResult::error("Query must not be empty".to_string());
+    }
+
+    let matches = match db.search_sessions(
+        query_str,
+        project.as_ref().map(|p| p.id.as_str()),
+        params.role.as_deref(),
+        params.has_code,
+        params.limit,
+    ) {
+        Ok(m) => m,
+        Err(e) => return ToolCallResult::error(format!("Search failed: {}", e)),
+    };
+
+    let scope = match &project {
+        Some(p) => format!("project '{}'", p.name),
+        None => "all projects".to_string(),
+    };
+
+    if matches.is_empty() {
+        return ToolCallResult::text(format!(
+            "No session messages found for query '{}' in {}.",
+            query_str, scope
+        ));
+    }
+
+    let mut output = format!(
+        "Found {} session messages for query '{}' in {}:\n\n",
+        matches.len(),
+        query_str,
+        scope
+    );
+
+    for (i, m) in matches.iter().enumerate() {
+        let role = match &m.tool_name {
+            Some(tool) => format!("{} ({})", m.role, tool),
+            None => m.role.clone(),
+        };
+        output.push_str(&format!(
+            "{}. {} — message #{} [{}]\n",
+            i + 1,
+            m.session_title.as_deref().unwrap_or("Untitled session"),
+            m.sequence_num,
+            role
+        ));
+        output.push_str(&format!(
+            "   Session: {} at {}\n",
+            m.session_id, m.timestamp
+        ));
+        output.push_str(&format!("   {}\n\n", m.preview));
+    }
+
+    ToolCallResult::text(output)
+}
+
 /// Handle yolog_get_project_context tool call
 fn handle_get_project_context(arguments: Value, db: &McpDb) -> ToolCallResult {
     let params: GetProjectContextParams = match serde_json::from_value(arguments) {
@@ -690,3 +881,58 @@ fn handle_save_lifeboat(arguments: Value, db: &McpDb) -> ToolCallResult {
         params.session_id, summary
     ))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Database;
+    use crate::test_support;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_read_session_resource() {
+        let tmp = tempfile::tempdir().unwrap();
+        let db = Database::new(tmp.path().join("test.db")).unwrap();
+        {
+            #[allow(deprecated)]
+            let conn = db.conn();
+            test_support::seed_project(&conn);
+            conn.execute_batch(
+                "INSERT INTO sessions (id, project_id, file_path, title, ai_tool, message_count,
+                     created_at, indexed_at)
+                 VALUES ('s1', 'p1', '/tmp/proj/s1.jsonl', 'Fix flaky login', 'Claude Code', 2,
+                     '2024-01-01T00:00:00Z', '2024-01-01T00:00:00Z');
+                 INSERT INTO session_messages (session_id, sequence_num, role, content_preview,
+                     search_content, tool_name, timestamp)
+                 VALUES
+                     ('s1', 0, 'user', 'Why is login flaky?', 'Why is login flaky?', NULL,
+                      '2024-01-01T00:00:00Z'),
+                     ('s1', 1, 'assistant', 'Running tests', 'cargo test login', 'Bash',
+                      '2024-01-01T00:00:01Z');",
+            )
+            .unwrap();
+        }
+        let db = McpDb::new(Arc::new(db));
+
+        let list = handle_resources_list(json!(1), &db);
+        let resources = &list.result.unwrap()["resources"];
+        assert_eq!(resources[0]["uri"], "yolog://session/s1");
+        assert_eq!(resources[0]["name"], "Fix flaky login");
+
+        let read =
+            handle_resources_read(json!(2), Some(json!({ "uri": "yolog://session/s1" })), &db);
+        let contents = &read.result.unwrap()["contents"][0];
+        assert_eq!(contents["mimeType"], "text/plain");
+        let text = contents["text"].as_str().unwrap();
+        assert!(text.starts_with("# Fix flaky login"));
+        assert!(text.contains("[#0 user]\nWhy is login flaky?"));
+        assert!(text.contains("[#1 assistant · Bash]\ncargo test login"));
+
+        let missing = handle_resources_read(
+            json!(3),
+            Some(json!({ "uri": "yolog://session/nope" })),
+            &db,
+        );
+        assert!(missing.error.is_some());
+    }
+}
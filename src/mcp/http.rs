@@ -0,0 +1,417 @@
+//! MCP over HTTP (streamable HTTP transport)
+//!
+//! `POST /mcp` takes one JSON-RPC message, or a batch array of them, and
+//! answers with the response(s) as JSON; notifications (messages without an
+//! `id`) get `202 Accepted`. `GET /mcp` opens an SSE stream for
+//! server-to-client notifications: `notifications/resources/list_changed` is
+//! sent when the watcher stores or removes a session. Requests go through the
+//! same dispatch as the stdio server.
+//!
+//! Requests whose `Origin` is not a loopback address are rejected, so a web
+//! page can't reach the server through DNS rebinding.
+
+use super::db::McpDb;
+use super::protocol::{JsonRpcError, JsonRpcRequest, JsonRpcResponse};
+use crate::config::{ApiKeyScope, ServerConfig};
+use crate::error::Result;
+use crate::watcher::WatcherEvent;
+use crate::Core;
+use axum::{
+    extract::State,
+    http::{header, HeaderMap, StatusCode, Uri},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Response,
+    },
+    routing::post,
+    Json, Router,
+};
+use serde_json::Value;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio_stream::wrappers::BroadcastStream;
+use tokio_stream::StreamExt;
+use tokio_util::sync::CancellationToken;
+
+/// State shared by the MCP HTTP handlers
+#[derive(Clone)]
+struct McpHttpState {
+    db: Arc<McpDb>,
+    /// Accepted bearer keys; `None` when no server keys are configured
+    api_keys: Option<Vec<String>>,
+    /// Server-to-client notifications, fanned out to every open SSE stream
+    notifications: broadcast::Sender<Value>,
+    /// Cancelled on shutdown; ends open SSE streams
+    shutdown: CancellationToken,
+}
+
+/// Serve MCP over HTTP at `addr` until Ctrl+C / SIGTERM
+pub async fn serve_http(addr: SocketAddr, core: Core) -> Result<()> {
+    let db = core.db.clone().ok_or_else(|| {
+        crate::error::CoreError::Config("MCP server requires storage = \"db\"".into())
+    })?;
+    let state = McpHttpState {
+        db: Arc::new(McpDb::new(db).with_limits(&core.config().mcp)),
+        api_keys: admin_keys(&core.config().server),
+        notifications: broadcast::channel(64).0,
+        shutdown: CancellationToken::new(),
+    };
+    let shutdown = state.shutdown.clone();
+    tokio::spawn(forward_session_events(
+        core.event_sender().subscribe(),
+        state.notifications.clone(),
+        shutdown.clone(),
+    ));
+
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    tracing::info!("MCP server listening on http://{}/mcp", addr);
+
+    axum::serve(listener, router(state))
+        .with_graceful_shutdown(async move {
+            crate::api::shutdown_signal().await;
+            shutdown.cancel();
+        })
+        .await
+        .map_err(|e| crate::error::CoreError::Api(e.to_string()))
+}
+
+/// Turn watcher events that change the session list into
+/// `notifications/resources/list_changed` until `shutdown` is cancelled
+async fn forward_session_events(
+    mut events: broadcast::Receiver<WatcherEvent>,
+    notifications: broadcast::Sender<Value>,
+    shutdown: CancellationToken,
+) {
+    loop {
+        let event = tokio::select! {
+            _ = shutdown.cancelled() => return,
+            event = events.recv() => event,
+        };
+        let changed = match event {
+            Ok(WatcherEvent::NewSession { .. } | WatcherEvent::SessionRemoved { .. }) => true,
+            Ok(_) => false,
+            // Missed events may have changed the list
+            Err(broadcast::error::RecvError::Lagged(_)) => true,
+            Err(broadcast::error::RecvError::Closed) => return,
+        };
+        if changed {
+            // No open SSE streams is not an error
+            let _ = notifications.send(serde_json::json!({
+                "jsonrpc": "2.0",
+                "method": "notifications/resources/list_changed",
+            }));
+        }
+    }
+}
+
+fn router(state: McpHttpState) -> Router {
+    Router::new()
+        .route("/mcp", post(handle_post).get(handle_stream))
+        .with_state(state)
+}
+
+/// Admin-scoped server keys, or `None` if auth is disabled. Read-scoped keys
+/// are not accepted because tools such as `yolog_save_lifeboat` write.
+fn admin_keys(server: &ServerConfig) -> Option<Vec<String>> {
+    let keys = server.effective_api_keys();
+    if keys.is_empty() {
+        return None;
+    }
+    Some(
+        keys.into_iter()
+            .filter(|k| k.scope == ApiKeyScope::Admin)
+            .map(|k| k.key)
+            .collect(),
+    )
+}
+
+/// Whether the request carries one of the accepted keys (if any are configured)
+fn is_authorized(state: &McpHttpState, headers: &HeaderMap) -> bool {
+    let Some(api_keys) = &state.api_keys else {
+        return true;
+    };
+    let provided = headers
+        .get("Authorization")
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.strip_prefix("Bearer "));
+    provided.is_some_and(|key| api_keys.iter().any(|k| k == key))
+}
+
+/// Whether the request may come from its `Origin`. Clients that aren't
+/// browsers send none; browser pages must be served from a loopback host.
+fn is_allowed_origin(headers: &HeaderMap) -> bool {
+    let Some(origin) = headers.get(header::ORIGIN) else {
+        return true;
+    };
+    let host = origin
+        .to_str()
+        .ok()
+        .and_then(|o| o.parse::<Uri>().ok())
+        .and_then(|uri| uri.host().map(str::to_string));
+    matches!(host.as_deref(), Some("localhost" | "127.0.0.1" | "[::1]"))
+}
+
+fn forbidden_origin() -> Response {
+    (
+        StatusCode::FORBIDDEN,
+        Json(serde_json::json!({ "error": "Origin not allowed" })),
+    )
+        .into_response()
+}
+
+fn unauthorized() -> Response {
+    (
+        StatusCode::UNAUTHORIZED,
+        Json(serde_json::json!({
+            "error": "API key required. Set Authorization: Bearer <api_key>"
+        })),
+    )
+        .into_response()
+}
+
+/// A JSON-RPC message as a request, `Ok(None)` for a notification, or the
+/// error response for an invalid request
+fn parse_request(
+    message: Value,
+) -> std::result::Result<Option<JsonRpcRequest>, Box<JsonRpcResponse>> {
+    // Notifications expect no response
+    if message.get("id").is_none() {
+        return Ok(None);
+    }
+    serde_json::from_value(message).map(Some).map_err(|e| {
+        Box::new(JsonRpcResponse::error(
+            Value::Null,
+            JsonRpcError::invalid_request(format!("Invalid request: {}", e)),
+        ))
+    })
+}
+
+/// Dispatch `requests` off the async runtime (tool handlers query SQLite
+/// synchronously)
+async fn dispatch(
+    db: Arc<McpDb>,
+    requests: Vec<JsonRpcRequest>,
+) -> std::result::Result<Vec<JsonRpcResponse>, Response> {
+    tokio::task::spawn_blocking(move || {
+        requests
+            .into_iter()
+            .map(|request| super::handle_request(request, &db, true))
+            .collect()
+    })
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({ "error": e.to_string() })),
+        )
+            .into_response()
+    })
+}
+
+/// POST /mcp - handle a JSON-RPC message or batch
+async fn handle_post(
+    State(state): State<McpHttpState>,
+    headers: HeaderMap,
+    body: String,
+) -> Response {
+    if !is_allowed_origin(&headers) {
+        return forbidden_origin();
+    }
+    if !is_authorized(&state, &headers) {
+        return unauthorized();
+    }
+
+    let message: Value = match serde_json::from_str(&body) {
+        Ok(message) => message,
+        Err(e) => {
+            let error = JsonRpcResponse::error(
+                Value::Null,
+                JsonRpcError::parse_error(format!("Invalid JSON: {}", e)),
+            );
+            return (StatusCode::BAD_REQUEST, Json(error)).into_response();
+        }
+    };
+
+    let messages = match message {
+        Value::Array(messages) => messages,
+        message => {
+            return match parse_request(message) {
+                Ok(None) => StatusCode::ACCEPTED.into_response(),
+                Ok(Some(request)) => match dispatch(state.db.clone(), vec![request]).await {
+                    Ok(mut responses) => Json(responses.remove(0)).into_response(),
+                    Err(resp) => resp,
+                },
+                Err(error) => (StatusCode::BAD_REQUEST, Json(*error)).into_response(),
+            }
+        }
+    };
+
+    if messages.is_empty() {
+        let error = JsonRpcResponse::error(
+            Value::Null,
+            JsonRpcError::invalid_request("Empty batch".to_string()),
+        );
+        return (StatusCode::BAD_REQUEST, Json(error)).into_response();
+    }
+
+    // Invalid members get an error response each; notifications get none
+    let mut requests = Vec::new();
+    let mut errors = Vec::new();
+    for message in messages {
+        match parse_request(message) {
+            Ok(Some(request)) => requests.push(request),
+            Ok(None) => {}
+            Err(error) => errors.push(*error),
+        }
+    }
+    let mut responses = match dispatch(state.db.clone(), requests).await {
+        Ok(responses) => responses,
+        Err(resp) => return resp,
+    };
+    responses.extend(errors);
+
+    if responses.is_empty() {
+        StatusCode::ACCEPTED.into_response()
+    } else {
+        Json(responses).into_response()
+    }
+}
+
+/// GET /mcp - SSE stream of server-to-client notifications
+async fn handle_stream(State(state): State<McpHttpState>, headers: HeaderMap) -> Response {
+    if !is_allowed_origin(&headers) {
+        return forbidden_origin();
+    }
+    if !is_authorized(&state, &headers) {
+        return unauthorized();
+    }
+
+    let notifications =
+        BroadcastStream::new(state.notifications.subscribe()).filter_map(|result| {
+            // Lagged receivers skip missed notifications
+            result.ok().map(|message| {
+                Ok::<_, Infallible>(Event::default().event("message").data(message.to_string()))
+            })
+        });
+    let stream = futures::StreamExt::take_until(notifications, state.shutdown.cancelled_owned());
+
+    Sse::new(stream)
+        .keep_alive(KeepAlive::new().interval(Duration::from_secs(30)))
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Database;
+
+    async fn start(api_key: Option<&str>) -> (tempfile::TempDir, String) {
+        let tmp = tempfile::tempdir().unwrap();
+        let db = Arc::new(Database::new(tmp.path().join("test.db")).unwrap());
+        let state = McpHttpState {
+            db: Arc::new(McpDb::new(db)),
+            api_keys: api_key.map(|k| vec![k.to_string()]),
+            notifications: broadcast::channel(8).0,
+            shutdown: CancellationToken::new(),
+        };
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/mcp", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, router(state)).await });
+        (tmp, url)
+    }
+
+    async fn call(url: &str, message: Value) -> reqwest::Response {
+        reqwest::Client::new()
+            .post(url)
+            .json(&message)
+            .send()
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_batch_gets_responses() {
+        let (_tmp, url) = start(None).await;
+
+        let resp = call(
+            &url,
+            serde_json::json!([
+                {"jsonrpc": "2.0", "id": 1, "method": "ping"},
+                {"jsonrpc": "2.0", "method": "notifications/initialized"},
+                {"jsonrpc": "2.0", "id": 2, "method": "tools/list"},
+            ]),
+        )
+        .await;
+        assert_eq!(resp.status(), 200);
+        let body: Value = resp.json().await.unwrap();
+        let responses = body.as_array().unwrap();
+        assert_eq!(responses.len(), 2);
+        assert_eq!(responses[0]["id"], 1);
+        assert_eq!(responses[1]["id"], 2);
+
+        // Only notifications: nothing to answer
+        let resp = call(
+            &url,
+            serde_json::json!([{"jsonrpc": "2.0", "method": "notifications/initialized"}]),
+        )
+        .await;
+        assert_eq!(resp.status(), 202);
+
+        assert_eq!(call(&url, serde_json::json!([])).await.status(), 400);
+    }
+
+    #[tokio::test]
+    async fn test_non_loopback_origin_rejected() {
+        let (_tmp, url) = start(None).await;
+        let ping = serde_json::json!({"jsonrpc": "2.0", "id": 1, "method": "ping"});
+        let with_origin = |origin: &str| {
+            reqwest::Client::new()
+                .post(&url)
+                .header("Origin", origin)
+                .json(&ping)
+                .send()
+        };
+
+        assert_eq!(
+            with_origin("http://evil.example").await.unwrap().status(),
+            403
+        );
+        assert_eq!(with_origin("null").await.unwrap().status(), 403);
+        assert_eq!(
+            with_origin("http://localhost:5173").await.unwrap().status(),
+            200
+        );
+        assert_eq!(
+            with_origin("http://[::1]:8080").await.unwrap().status(),
+            200
+        );
+
+        let resp = reqwest::Client::new()
+            .get(&url)
+            .header("Origin", "http://evil.example")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), 403);
+    }
+
+    #[tokio::test]
+    async fn test_api_key_required_when_configured() {
+        let (_tmp, url) = start(Some("secret")).await;
+        let ping = serde_json::json!({"jsonrpc": "2.0", "id": 1, "method": "ping"});
+
+        assert_eq!(call(&url, ping.clone()).await.status(), 401);
+
+        let resp = reqwest::Client::new()
+            .post(&url)
+            .bearer_auth("secret")
+            .json(&ping)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), 200);
+    }
+}
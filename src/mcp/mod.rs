@@ -10,10 +10,13 @@
 //! yocore --mcp
 //! ```
 //!
-//! The server communicates over stdio using JSON-RPC 2.0.
+//! The server communicates over stdio using JSON-RPC 2.0. With `--mcp-http`
+//! it is served over HTTP instead, so several assistants can share one
+//! running yocore (see [`serve_http`]).
 
 pub(crate) mod db;
 mod handlers;
+mod http;
 mod protocol;
 pub(crate) mod types;
 
@@ -23,12 +26,14 @@ use db::McpDb;
 use protocol::{JsonRpcError, JsonRpcRequest, JsonRpcResponse};
 use std::io::{BufRead, Write};
 
+pub use http::serve_http;
+
 /// Run the MCP server over stdio
 pub async fn run_mcp_server(core: Core) -> Result<()> {
     let db = core.db.clone().ok_or_else(|| {
         crate::error::CoreError::Config("MCP server requires storage = \"db\"".into())
     })?;
-    let mcp_db = McpDb::new(db);
+    let mcp_db = McpDb::new(db).with_limits(&core.config().mcp);
 
     tracing::info!("Starting MCP server (stdio mode)");
 
@@ -62,7 +67,7 @@ fn run_stdio_loop(db: &McpDb) -> std::result::Result<(), String> {
 
         // Parse JSON-RPC request
         let response = match serde_json::from_str::<JsonRpcRequest>(&line) {
-            Ok(request) => handle_request(request, db),
+            Ok(request) => handle_request(request, db, false),
             Err(e) => JsonRpcResponse::error(
                 serde_json::Value::Null,
                 JsonRpcError::parse_error(format!("Invalid JSON: {}", e)),
@@ -83,15 +88,21 @@ fn run_stdio_loop(db: &McpDb) -> std::result::Result<(), String> {
     Ok(())
 }
 
-/// Handle a single JSON-RPC request
-fn handle_request(request: JsonRpcRequest, db: &McpDb) -> JsonRpcResponse {
+/// Handle a single JSON-RPC request. `push_notifications` is whether the
+/// transport pushes list-changed notifications to the client.
+fn handle_request(
+    request: JsonRpcRequest,
+    db: &McpDb,
+    push_notifications: bool,
+) -> JsonRpcResponse {
     match request.method.as_str() {
         // MCP protocol methods
-        "initialize" => handlers::handle_initialize(request.id),
+        "initialize" => handlers::handle_initialize(request.id, push_notifications),
         "initialized" => JsonRpcResponse::success(request.id, serde_json::json!({})),
         "tools/list" => handlers::handle_tools_list(request.id),
         "tools/call" => handlers::handle_tools_call(request.id, request.params, db),
-        "resources/list" => handlers::handle_resources_list(request.id),
+        "resources/list" => handlers::handle_resources_list(request.id, db),
+        "resources/read" => handlers::handle_resources_read(request.id, request.params, db),
         "ping" => JsonRpcResponse::success(request.id, serde_json::json!({})),
 
         // Unknown method
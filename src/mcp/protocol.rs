@@ -123,6 +123,15 @@ pub struct ResourceDefinition {
     pub mime_type: Option<String>,
 }
 
+/// Contents of a resource returned by `resources/read`
+#[derive(Debug, Clone, Serialize)]
+pub struct ResourceContents {
+    pub uri: String,
+    #[serde(rename = "mimeType")]
+    pub mime_type: String,
+    pub text: String,
+}
+
 /// MCP Server Capabilities
 #[derive(Debug, Clone, Serialize)]
 pub struct ServerCapabilities {
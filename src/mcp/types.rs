@@ -76,6 +76,18 @@ pub struct Memory {
     pub state: String,
 }
 
+/// A reusable workflow pattern discovered in a project's sessions
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Skill {
+    pub id: i64,
+    pub name: String,
+    pub description: String,
+    pub steps: Vec<String>,
+    pub confidence: f32,
+    /// Sessions the skill was seen in (original + linked duplicates)
+    pub session_count: i64,
+}
+
 /// Filters for memory queries
 #[allow(dead_code)]
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
@@ -140,6 +152,55 @@ pub struct GetProjectContextParams {
     pub project_path: String,
 }
 
+/// MCP-specific session transcript search request parameters
+#[derive(Debug, Clone, Deserialize)]
+pub struct SearchSessionsParams {
+    pub query: String,
+    /// Restrict to one project (all projects when omitted)
+    #[serde(default)]
+    pub project_path: Option<String>,
+    /// "all", "user", "assistant", or "tool"
+    #[serde(default)]
+    pub role: Option<String>,
+    #[serde(default)]
+    pub has_code: Option<bool>,
+    #[serde(default = "default_limit")]
+    pub limit: usize,
+}
+
+/// A session message matching a transcript search
+#[derive(Debug, Clone, Deserialize)]
+pub struct SessionMatch {
+    pub session_id: String,
+    pub session_title: Option<String>,
+    #[serde(rename = "line_number")]
+    pub sequence_num: i64,
+    pub preview: String,
+    pub role: String,
+    pub timestamp: String,
+    pub tool_name: Option<String>,
+}
+
+/// A session exposed as an MCP resource
+#[derive(Debug, Clone)]
+pub struct SessionSummary {
+    pub id: String,
+    pub title: Option<String>,
+    pub project_name: String,
+    pub ai_tool: String,
+    pub message_count: i64,
+    pub created_at: String,
+}
+
+/// One message of a session transcript
+#[derive(Debug, Clone)]
+pub struct TranscriptMessage {
+    pub sequence_num: i64,
+    pub role: String,
+    pub tool_name: Option<String>,
+    pub content: String,
+}
+
 /// MCP-specific get recent memories request parameters
 #[derive(Debug, Clone, Deserialize)]
 pub struct GetRecentMemoriesParams {
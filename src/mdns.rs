@@ -1,12 +1,16 @@
 //! mDNS/Bonjour service discovery for local network auto-discovery.
 //!
 //! Announces the yocore HTTP API server on the local network so desktop apps
-//! can find all running instances without manual configuration.
+//! can find all running instances without manual configuration, and browses
+//! for other instances (`GET /api/discover`).
 //!
 //! Service type: `_yocore._tcp.local.`
 //! TXT records: version, uuid, hostname, api_key_required, projects
 
-use mdns_sd::{ServiceDaemon, ServiceInfo};
+use mdns_sd::{ServiceDaemon, ServiceEvent, ServiceInfo};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
 
 const SERVICE_TYPE: &str = "_yocore._tcp.local.";
 
@@ -110,3 +114,173 @@ pub fn generate_instance_name(hostname: &str, uuid: &str, custom_name: Option<&s
     let short_uuid = &uuid[..8.min(uuid.len())];
     format!("Yocore-{}-{}", hostname, short_uuid)
 }
+
+/// Another yocore instance found on the local network.
+#[derive(Debug, Clone, Serialize)]
+pub struct DiscoveredInstance {
+    /// mDNS instance name (e.g. "Yocore-macbook-1a2b3c4d")
+    pub instance_name: String,
+    pub uuid: String,
+    pub version: String,
+    pub hostname: String,
+    pub api_key_required: bool,
+    pub project_count: Option<usize>,
+    pub name: Option<String>,
+    pub port: u16,
+    pub addresses: Vec<String>,
+}
+
+impl DiscoveredInstance {
+    /// Read an instance from a resolved service. `None` when the TXT records
+    /// lack a uuid, i.e. it isn't a yocore announcement.
+    fn from_service(info: &ServiceInfo) -> Option<Self> {
+        let txt = |key: &str| info.get_property_val_str(key).map(str::to_string);
+        let uuid = txt("uuid").filter(|u| !u.is_empty())?;
+
+        let fullname = info.get_fullname();
+        let instance_name = fullname
+            .strip_suffix(SERVICE_TYPE)
+            .map(|n| n.trim_end_matches('.'))
+            .unwrap_or(fullname)
+            .to_string();
+        let mut addresses: Vec<String> =
+            info.get_addresses().iter().map(|a| a.to_string()).collect();
+        addresses.sort();
+
+        Some(DiscoveredInstance {
+            instance_name,
+            uuid,
+            version: txt("version").unwrap_or_default(),
+            hostname: txt("hostname").unwrap_or_default(),
+            api_key_required: txt("api_key_required").as_deref() == Some("true"),
+            project_count: txt("projects").and_then(|p| p.parse().ok()),
+            name: txt("name"),
+            port: info.get_port(),
+            addresses,
+        })
+    }
+}
+
+/// Browse the local network for yocore instances for `timeout`.
+///
+/// Blocks for the whole timeout. An instance announced on several interfaces
+/// is reported once (by uuid) with all its addresses. Sorted by instance name.
+pub fn browse(timeout: Duration) -> Result<Vec<DiscoveredInstance>, String> {
+    let daemon =
+        ServiceDaemon::new().map_err(|e| format!("Failed to create mDNS daemon: {}", e))?;
+    let events = daemon
+        .browse(SERVICE_TYPE)
+        .map_err(|e| format!("Failed to browse for mDNS services: {}", e))?;
+
+    let deadline = Instant::now() + timeout;
+    let mut found: HashMap<String, DiscoveredInstance> = HashMap::new();
+    while let Ok(event) = events.recv_deadline(deadline) {
+        let ServiceEvent::ServiceResolved(info) = event else {
+            continue;
+        };
+        let Some(instance) = DiscoveredInstance::from_service(&info) else {
+            continue;
+        };
+        match found.get_mut(&instance.uuid) {
+            Some(existing) => {
+                for addr in instance.addresses {
+                    if !existing.addresses.contains(&addr) {
+                        existing.addresses.push(addr);
+                    }
+                }
+                existing.addresses.sort();
+            }
+            None => {
+                found.insert(instance.uuid.clone(), instance);
+            }
+        }
+    }
+
+    if let Err(e) = daemon.stop_browse(SERVICE_TYPE) {
+        tracing::debug!("Failed to stop mDNS browse: {}", e);
+    }
+    if let Err(e) = daemon.shutdown() {
+        tracing::debug!("Failed to shut down mDNS daemon: {}", e);
+    }
+
+    let mut instances: Vec<DiscoveredInstance> = found.into_values().collect();
+    instances.sort_by(|a, b| a.instance_name.cmp(&b.instance_name));
+    Ok(instances)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_discovered_instance_from_txt_records() {
+        let info = ServiceInfo::new(
+            SERVICE_TYPE,
+            "Yocore-box-1a2b3c4d",
+            "box.local.",
+            "192.168.1.20,192.168.1.10",
+            19420,
+            &[
+                ("version", "0.9.0"),
+                ("uuid", "1a2b3c4d-0000"),
+                ("hostname", "box"),
+                ("api_key_required", "true"),
+                ("projects", "3"),
+            ][..],
+        )
+        .unwrap();
+        let instance = DiscoveredInstance::from_service(&info).unwrap();
+        assert_eq!(instance.instance_name, "Yocore-box-1a2b3c4d");
+        assert_eq!(instance.uuid, "1a2b3c4d-0000");
+        assert_eq!(instance.version, "0.9.0");
+        assert_eq!(instance.hostname, "box");
+        assert!(instance.api_key_required);
+        assert_eq!(instance.project_count, Some(3));
+        assert_eq!(instance.name, None);
+        assert_eq!(instance.port, 19420);
+        assert_eq!(instance.addresses, vec!["192.168.1.10", "192.168.1.20"]);
+
+        // Not a yocore announcement
+        let other = ServiceInfo::new(
+            SERVICE_TYPE,
+            "printer",
+            "printer.local.",
+            "",
+            631,
+            &[("version", "1")][..],
+        )
+        .unwrap();
+        assert!(DiscoveredInstance::from_service(&other).is_none());
+    }
+
+    /// Registers and browses on this host's interfaces; the multicast
+    /// packets loop back locally (mdns-sd doesn't bind loopback interfaces).
+    /// Run with `cargo test -- --ignored` on a host with multicast.
+    #[test]
+    #[ignore = "needs multicast on a non-loopback interface"]
+    fn test_browse_finds_registered_instance() {
+        let uuid = format!("test-{}", std::process::id());
+        let _service = MdnsService::register(
+            &generate_instance_name("browse-test", &uuid, None),
+            19499,
+            MdnsMetadata {
+                version: "0.0.0-test".to_string(),
+                uuid: uuid.clone(),
+                hostname: "browse-test".to_string(),
+                api_key_required: false,
+                project_count: 2,
+                name: Some("Test box".to_string()),
+            },
+        )
+        .unwrap();
+
+        let instances = browse(Duration::from_secs(3)).unwrap();
+        let found: Vec<_> = instances.iter().filter(|i| i.uuid == uuid).collect();
+        assert_eq!(found.len(), 1, "registered instance not found once");
+        assert_eq!(found[0].port, 19499);
+        assert_eq!(found[0].version, "0.0.0-test");
+        assert_eq!(found[0].name.as_deref(), Some("Test box"));
+        assert_eq!(found[0].project_count, Some(2));
+        assert!(!found[0].api_key_required);
+    }
+}
@@ -2,6 +2,8 @@
 //!
 //! Parses JSONL session files from Claude Code.
 
+use super::common::{content_to_string, detect_code_language};
+use super::git::GitContext;
 use super::types::*;
 use super::SessionParser;
 use regex::Regex;
@@ -40,17 +42,15 @@ impl ClaudeCodeParser {
         format!("{}...", &s[..end])
     }
 
-    /// Parse a single JSONL line into a ParsedEvent
+    /// Convert a single decoded JSONL event into a ParsedEvent
     fn parse_event(
         &self,
-        line: &str,
+        event: &Value,
         sequence: usize,
         byte_offset: i64,
-        events_by_uuid: &HashMap<String, Value>,
+        byte_length: i64,
+        tool_calls_by_uuid: &HashMap<String, Value>,
     ) -> Option<ParsedEvent> {
-        let event: Value = serde_json::from_str(line).ok()?;
-        let byte_length = line.len() as i64;
-
         let timestamp = event
             .get("timestamp")
             .and_then(|v| v.as_str())
@@ -61,18 +61,18 @@ impl ClaudeCodeParser {
 
         match event_type {
             "user" => self.parse_user_event(
-                &event,
+                event,
                 sequence,
                 byte_offset,
                 byte_length,
                 &timestamp,
-                events_by_uuid,
+                tool_calls_by_uuid,
             ),
             "assistant" => {
-                self.parse_assistant_event(&event, sequence, byte_offset, byte_length, &timestamp)
+                self.parse_assistant_event(event, sequence, byte_offset, byte_length, &timestamp)
             }
             "system" => {
-                self.parse_system_event(&event, sequence, byte_offset, byte_length, &timestamp)
+                self.parse_system_event(event, sequence, byte_offset, byte_length, &timestamp)
             }
             "file-history-snapshot" => Some(ParsedEvent {
                 sequence,
@@ -81,15 +81,18 @@ impl ClaudeCodeParser {
                 content_preview: "File history snapshot".to_string(),
                 search_content: "file history snapshot".to_string(),
                 has_code: false,
+                code_language: None,
                 has_error: false,
                 has_file_changes: true,
                 tool_name: Some("file-history-snapshot".to_string()),
                 tool_type: None,
                 tool_summary: None,
+                parent_sequence: None,
                 input_tokens: None,
                 output_tokens: None,
                 cache_read_tokens: None,
                 cache_creation_tokens: None,
+                tokens_estimated: false,
                 model: None,
                 timestamp,
                 byte_offset,
@@ -106,7 +109,7 @@ impl ClaudeCodeParser {
         byte_offset: i64,
         byte_length: i64,
         timestamp: &str,
-        events_by_uuid: &HashMap<String, Value>,
+        tool_calls_by_uuid: &HashMap<String, Value>,
     ) -> Option<ParsedEvent> {
         // Check if this is a meta/system prompt
         if event
@@ -123,15 +126,18 @@ impl ClaudeCodeParser {
                 content_preview: preview,
                 search_content: content,
                 has_code: false,
+                code_language: None,
                 has_error: false,
                 has_file_changes: false,
                 tool_name: Some("skill-prompt".to_string()),
                 tool_type: None,
                 tool_summary: None,
+                parent_sequence: None,
                 input_tokens: None,
                 output_tokens: None,
                 cache_read_tokens: None,
                 cache_creation_tokens: None,
+                tokens_estimated: false,
                 model: None,
                 timestamp: timestamp.to_string(),
                 byte_offset,
@@ -151,15 +157,18 @@ impl ClaudeCodeParser {
                 content_preview: preview,
                 search_content: notification_content,
                 has_code: false,
+                code_language: None,
                 has_error: false,
                 has_file_changes: false,
                 tool_name: Some("task-notification".to_string()),
                 tool_type: None,
                 tool_summary: None,
+                parent_sequence: None,
                 input_tokens: None,
                 output_tokens: None,
                 cache_read_tokens: None,
                 cache_creation_tokens: None,
+                tokens_estimated: false,
                 model: None,
                 timestamp: timestamp.to_string(),
                 byte_offset,
@@ -171,8 +180,7 @@ impl ClaudeCodeParser {
         if let Some(tool_result) = self.extract_tool_result(event) {
             // Find parent tool call
             let parent_uuid = event.get("parentUuid").and_then(|v| v.as_str());
-            let parent = parent_uuid.and_then(|uuid| events_by_uuid.get(uuid));
-            let tool_call = parent.and_then(|p| self.extract_tool_call(p));
+            let tool_call = parent_uuid.and_then(|uuid| tool_calls_by_uuid.get(uuid).cloned());
 
             let content = self.extract_tool_result_content(&tool_result);
             let tool_name = tool_call
@@ -182,6 +190,8 @@ impl ClaudeCodeParser {
                 .or_else(|| self.infer_tool_name_from_result(event));
 
             let has_code = self.detect_code(&content);
+            let code_language =
+                detect_code_language(&content, tool_call.as_ref().and_then(|tc| tc.get("input")));
             let has_error = self.detect_error(&content);
             let has_file_changes = self.detect_file_changes(&tool_call, &content);
 
@@ -200,15 +210,18 @@ impl ClaudeCodeParser {
                 content_preview: preview,
                 search_content: content,
                 has_code,
+                code_language,
                 has_error,
                 has_file_changes,
                 tool_name,
                 tool_type: Some("result".to_string()),
                 tool_summary: Some(tool_summary),
+                parent_sequence: None,
                 input_tokens: None,
                 output_tokens: None,
                 cache_read_tokens: None,
                 cache_creation_tokens: None,
+                tokens_estimated: false,
                 model: None,
                 timestamp: timestamp.to_string(),
                 byte_offset,
@@ -220,6 +233,7 @@ impl ClaudeCodeParser {
         let content = self.extract_user_content(event);
         let preview = self.sanitize_preview(&content, 200);
         let has_code = self.detect_code(&content);
+        let code_language = detect_code_language(&content, None);
 
         Some(ParsedEvent {
             sequence,
@@ -228,15 +242,18 @@ impl ClaudeCodeParser {
             content_preview: preview,
             search_content: content,
             has_code,
+            code_language,
             has_error: false,
             has_file_changes: false,
             tool_name: None,
             tool_type: None,
             tool_summary: None,
+            parent_sequence: None,
             input_tokens: None,
             output_tokens: None,
             cache_read_tokens: None,
             cache_creation_tokens: None,
+            tokens_estimated: false,
             model: None,
             timestamp: timestamp.to_string(),
             byte_offset,
@@ -311,15 +328,18 @@ impl ClaudeCodeParser {
                 content_preview: preview,
                 search_content,
                 has_code: false,
+                code_language: detect_code_language(&text_content, tool_call.get("input")),
                 has_error: false,
                 has_file_changes: false,
                 tool_name,
                 tool_type: Some("use".to_string()),
                 tool_summary: Some(tool_summary),
+                parent_sequence: None,
                 input_tokens,
                 output_tokens,
                 cache_read_tokens,
                 cache_creation_tokens,
+                tokens_estimated: false,
                 model,
                 timestamp: timestamp.to_string(),
                 byte_offset,
@@ -331,6 +351,7 @@ impl ClaudeCodeParser {
         let content = self.extract_assistant_content(event);
         let preview = self.sanitize_preview(&content, 200);
         let has_code = self.detect_code(&content);
+        let code_language = detect_code_language(&content, None);
 
         Some(ParsedEvent {
             sequence,
@@ -339,15 +360,18 @@ impl ClaudeCodeParser {
             content_preview: preview,
             search_content: content,
             has_code,
+            code_language,
             has_error: false,
             has_file_changes: false,
             tool_name: None,
             tool_type: None,
             tool_summary: None,
+            parent_sequence: None,
             input_tokens,
             output_tokens,
             cache_read_tokens,
             cache_creation_tokens,
+            tokens_estimated: false,
             model,
             timestamp: timestamp.to_string(),
             byte_offset,
@@ -378,15 +402,18 @@ impl ClaudeCodeParser {
             content_preview: preview,
             search_content: content,
             has_code: false,
+            code_language: None,
             has_error: false,
             has_file_changes: false,
             tool_name: None,
             tool_type: None,
             tool_summary: None,
+            parent_sequence: None,
             input_tokens: None,
             output_tokens: None,
             cache_read_tokens: None,
             cache_creation_tokens: None,
+            tokens_estimated: false,
             model: None,
             timestamp: timestamp.to_string(),
             byte_offset,
@@ -763,6 +790,144 @@ impl ClaudeCodeParser {
         stats
     }
 
+    /// Detect a sub-agent transcript event and return its (parent session id, agent id).
+    ///
+    /// Sub-agent files mark their events with `isSidechain: true` and carry the
+    /// parent's `sessionId`; `agentId` correlates with the Task tool result in the parent.
+    fn extract_sidechain_link(event: &Value) -> Option<(String, Option<String>)> {
+        if event.get("isSidechain").and_then(|v| v.as_bool()) != Some(true) {
+            return None;
+        }
+        let parent = event.get("sessionId").and_then(|v| v.as_str())?;
+        let agent_id = event
+            .get("agentId")
+            .and_then(|v| v.as_str())
+            .map(String::from);
+        Some((parent.to_string(), agent_id))
+    }
+
+    /// Feed an event's branch hint, Bash commands and their output to `git`.
+    ///
+    /// `git` remembers the ids of Bash `tool_use` blocks so only their results
+    /// are read.
+    fn observe_git(git: &mut GitContext, event: &Value) {
+        if let Some(branch) = event.get("gitBranch").and_then(|v| v.as_str()) {
+            git.observe_branch_hint(branch);
+        }
+        let blocks = event
+            .get("message")
+            .and_then(|m| m.get("content"))
+            .and_then(|c| c.as_array());
+        for block in blocks.into_iter().flatten() {
+            match block.get("type").and_then(|t| t.as_str()) {
+                Some("tool_use") if block.get("name").and_then(|n| n.as_str()) == Some("Bash") => {
+                    let command = block
+                        .get("input")
+                        .and_then(|i| i.get("command"))
+                        .and_then(|c| c.as_str())
+                        .unwrap_or_default();
+                    let id = block.get("id").and_then(|id| id.as_str());
+                    git.observe_command_call(id, command);
+                }
+                Some("tool_result") => {
+                    let is_bash = block
+                        .get("tool_use_id")
+                        .and_then(|id| id.as_str())
+                        .is_some_and(|id| git.take_pending_call(id));
+                    if let (true, Some(content)) = (is_bash, block.get("content")) {
+                        git.observe_output(&content_to_string(content));
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Point a tool result at the `tool_use` event named by its `parentUuid`
+    fn link_tool_result(
+        mut parsed: ParsedEvent,
+        event: &Value,
+        tool_use_sequences: &HashMap<String, usize>,
+    ) -> ParsedEvent {
+        if parsed.event_type.as_deref() == Some("tool_result") {
+            parsed.parent_sequence = event
+                .get("parentUuid")
+                .and_then(|v| v.as_str())
+                .and_then(|uuid| tool_use_sequences.get(uuid).copied());
+        }
+        parsed
+    }
+
+    /// Parse lines one at a time.
+    ///
+    /// Parents always precede their tool results in the transcript, so a single
+    /// pass suffices. Only the `tool_use` block (name + input) of each event is
+    /// kept for the parent lookup, not the whole decoded event.
+    fn parse_lines<I, S>(&self, lines: I, mut git: GitContext) -> ParseResult
+    where
+        I: Iterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut events = Vec::new();
+        let mut tool_calls_by_uuid: HashMap<String, Value> = HashMap::new();
+        let mut tool_use_sequences: HashMap<String, usize> = HashMap::new();
+        let mut sidechain_link = None;
+        let mut byte_offset: i64 = 0;
+        let mut errors = Vec::new();
+        let mut error_lines = Vec::new();
+
+        for (sequence, line) in lines.enumerate() {
+            let line = line.as_ref();
+            let byte_length = line.len() as i64;
+            match serde_json::from_str::<Value>(line) {
+                Ok(event) => {
+                    if let Some(parsed) = self.parse_event(
+                        &event,
+                        sequence,
+                        byte_offset,
+                        byte_length,
+                        &tool_calls_by_uuid,
+                    ) {
+                        events.push(Self::link_tool_result(parsed, &event, &tool_use_sequences));
+                    }
+                    if sidechain_link.is_none() {
+                        sidechain_link = Self::extract_sidechain_link(&event);
+                    }
+                    Self::observe_git(&mut git, &event);
+                    if let Some(uuid) = event.get("uuid").and_then(|u| u.as_str()) {
+                        if let Some(tool_call) = self.extract_tool_call(&event) {
+                            tool_calls_by_uuid.insert(uuid.to_string(), tool_call);
+                            tool_use_sequences.insert(uuid.to_string(), sequence);
+                        }
+                    }
+                }
+                Err(_) => {
+                    errors.push(format!("Failed to parse line {}", sequence));
+                    error_lines.push(sequence + 1);
+                }
+            }
+            byte_offset += byte_length + 1; // +1 for newline
+        }
+
+        let mut metadata = self.extract_metadata(&events);
+        if let Some((parent_session_id, agent_id)) = sidechain_link {
+            metadata.parent_session_id = Some(parent_session_id);
+            metadata.agent_id = agent_id;
+        }
+        metadata.git_branch = git.branch().map(String::from);
+        metadata.git_commit = git.commit().map(String::from);
+        metadata.git_context = Some(git);
+        let stats = self.calculate_stats(&events);
+
+        ParseResult {
+            events,
+            metadata,
+            stats,
+            errors,
+            error_lines,
+        }
+    }
+
     fn extract_metadata(&self, events: &[ParsedEvent]) -> SessionMetadata {
         let mut metadata = SessionMetadata::default();
 
@@ -833,43 +998,19 @@ impl Default for ClaudeCodeParser {
 
 impl SessionParser for ClaudeCodeParser {
     fn parse(&self, lines: &[String]) -> ParseResult {
-        let mut events = Vec::new();
-        let mut events_by_uuid: HashMap<String, Value> = HashMap::new();
-        let mut byte_offset: i64 = 0;
-        let mut errors = Vec::new();
-
-        // First pass: collect events by UUID for parent-child linking
-        for line in lines {
-            if let Ok(event) = serde_json::from_str::<Value>(line) {
-                if let Some(uuid) = event.get("uuid").and_then(|u| u.as_str()) {
-                    events_by_uuid.insert(uuid.to_string(), event);
-                }
-            }
-        }
-
-        // Second pass: parse events
-        for (sequence, line) in lines.iter().enumerate() {
-            match self.parse_event(line, sequence, byte_offset, &events_by_uuid) {
-                Some(event) => events.push(event),
-                None => {
-                    // Try to parse just for error logging
-                    if serde_json::from_str::<Value>(line).is_err() {
-                        errors.push(format!("Failed to parse line {}", sequence));
-                    }
-                }
-            }
-            byte_offset += line.len() as i64 + 1; // +1 for newline
-        }
+        self.parse_lines(lines.iter(), GitContext::default())
+    }
 
-        let metadata = self.extract_metadata(&events);
-        let stats = self.calculate_stats(&events);
+    fn parse_stream(&self, lines: &mut dyn Iterator<Item = String>) -> ParseResult {
+        self.parse_lines(lines, GitContext::default())
+    }
 
-        ParseResult {
-            events,
-            metadata,
-            stats,
-            errors,
-        }
+    fn parse_stream_from(
+        &self,
+        lines: &mut dyn Iterator<Item = String>,
+        git: GitContext,
+    ) -> ParseResult {
+        self.parse_lines(lines, git)
     }
 
     fn name(&self) -> &'static str {
@@ -915,4 +1056,156 @@ mod tests {
         assert!(parser.detect_code("function foo() {}"));
         assert!(!parser.detect_code("just plain text"));
     }
+
+    #[test]
+    fn test_code_language_from_fence_and_tool_input() {
+        let parser = ClaudeCodeParser::new();
+        let lines = vec![
+            r#"{"type":"assistant","timestamp":"2024-01-01T00:00:00Z","message":{"content":[{"type":"text","text":"Run this:\n```python\nprint('hi')\n```"}]}}"#.to_string(),
+            r#"{"type":"assistant","uuid":"a1","timestamp":"2024-01-01T00:00:01Z","message":{"content":[{"type":"tool_use","id":"t1","name":"Read","input":{"file_path":"/repo/src/main.rs"}}]}}"#.to_string(),
+            r#"{"type":"user","uuid":"u1","parentUuid":"a1","timestamp":"2024-01-01T00:00:02Z","message":{"content":[{"type":"tool_result","tool_use_id":"t1","content":"fn main() {}"}]}}"#.to_string(),
+            r#"{"type":"user","timestamp":"2024-01-01T00:00:03Z","message":{"content":"Thanks"}}"#.to_string(),
+        ];
+
+        let result = parser.parse(&lines);
+        let languages: Vec<Option<&str>> = result
+            .events
+            .iter()
+            .map(|e| e.code_language.as_deref())
+            .collect();
+        assert_eq!(
+            languages,
+            vec![Some("python"), Some("rust"), Some("rust"), None]
+        );
+        assert!(result.events[0].has_code);
+    }
+
+    #[test]
+    fn test_malformed_line_is_reported_and_keeps_offsets() {
+        let parser = ClaudeCodeParser::new();
+        let lines = vec![
+            r#"{"type":"user","timestamp":"2024-01-01T00:00:00Z","message":{"content":"First"}}"#
+                .to_string(),
+            r#"{"type":"user","timestamp":"#.to_string(),
+            r#"{"type":"user","timestamp":"2024-01-01T00:00:01Z","message":{"content":"Third"}}"#
+                .to_string(),
+        ];
+        let file = lines.join("\n");
+
+        let result = parser.parse(&lines);
+        assert_eq!(result.events.len(), 2);
+        assert_eq!(result.error_lines, vec![2]);
+
+        // The message after the bad line still points at its own bytes
+        let third = &result.events[1];
+        assert_eq!(third.sequence, 2);
+        let start = third.byte_offset as usize;
+        let end = start + third.byte_length as usize;
+        assert_eq!(&file[start..end], lines[2]);
+    }
+
+    #[test]
+    fn test_sidechain_links_to_parent_session() {
+        let parser = ClaudeCodeParser::new();
+        let lines = vec![
+            r#"{"type":"user","isSidechain":true,"sessionId":"parent-uuid","agentId":"a1b2c3","timestamp":"2024-01-01T00:00:00Z","message":{"content":"Search the codebase"}}"#.to_string(),
+        ];
+
+        let result = parser.parse(&lines);
+        assert_eq!(
+            result.metadata.parent_session_id.as_deref(),
+            Some("parent-uuid")
+        );
+        assert_eq!(result.metadata.agent_id.as_deref(), Some("a1b2c3"));
+    }
+
+    #[test]
+    fn test_main_session_has_no_parent() {
+        let parser = ClaudeCodeParser::new();
+        let lines = vec![
+            r#"{"type":"user","isSidechain":false,"sessionId":"self-uuid","timestamp":"2024-01-01T00:00:00Z","message":{"content":"Hi"}}"#.to_string(),
+        ];
+
+        let result = parser.parse(&lines);
+        assert!(result.metadata.parent_session_id.is_none());
+    }
+
+    #[test]
+    fn test_git_branch_from_checkout_command() {
+        let parser = ClaudeCodeParser::new();
+        let lines = vec![
+            r#"{"type":"user","gitBranch":"main","timestamp":"2024-01-01T00:00:00Z","message":{"content":"Start the login feature on a branch"}}"#.to_string(),
+            r#"{"type":"assistant","uuid":"a1","gitBranch":"main","timestamp":"2024-01-01T00:00:01Z","message":{"content":[{"type":"tool_use","id":"t1","name":"Bash","input":{"command":"git fetch && git checkout feature/x"}}]}}"#.to_string(),
+            r#"{"type":"user","parentUuid":"a1","gitBranch":"main","timestamp":"2024-01-01T00:00:02Z","message":{"content":[{"type":"tool_result","tool_use_id":"t1","content":"Switched to branch 'feature/x'"}]}}"#.to_string(),
+            r#"{"type":"assistant","uuid":"a2","timestamp":"2024-01-01T00:00:03Z","message":{"content":[{"type":"tool_use","id":"t2","name":"Bash","input":{"command":"git commit -am 'Add login form'"}}]}}"#.to_string(),
+            r#"{"type":"user","parentUuid":"a2","timestamp":"2024-01-01T00:00:04Z","message":{"content":[{"type":"tool_result","tool_use_id":"t2","content":"[feature/x 3f9c2ab] Add login form\n 1 file changed"}]}}"#.to_string(),
+        ];
+
+        let result = parser.parse(&lines);
+        assert_eq!(result.metadata.git_branch.as_deref(), Some("feature/x"));
+        assert_eq!(result.metadata.git_commit.as_deref(), Some("3f9c2ab"));
+
+        // Switching back to an unnamed previous branch is ambiguous
+        let mut lines = lines;
+        lines.push(r#"{"type":"assistant","uuid":"a3","timestamp":"2024-01-01T00:00:05Z","message":{"content":[{"type":"tool_use","id":"t3","name":"Bash","input":{"command":"git checkout -"}}]}}"#.to_string());
+        let result = parser.parse(&lines);
+        assert!(result.metadata.git_branch.is_none());
+        assert!(result.metadata.git_commit.is_none());
+
+        // Continuing from the context at any split point matches the full parse
+        for split in 1..lines.len() {
+            let first = parser.parse(&lines[..split]);
+            let rest = parser.parse_stream_from(
+                &mut lines[split..].iter().cloned(),
+                first.metadata.git_context.unwrap(),
+            );
+            assert_eq!(rest.metadata.git_context, result.metadata.git_context);
+            assert!(rest.metadata.git_branch.is_none(), "split at {}", split);
+        }
+        let first = parser.parse(&lines[..4]);
+        let rest = parser.parse_stream_from(
+            &mut lines[4..5].iter().cloned(),
+            first.metadata.git_context.unwrap(),
+        );
+        assert_eq!(rest.metadata.git_branch.as_deref(), Some("feature/x"));
+        assert_eq!(rest.metadata.git_commit.as_deref(), Some("3f9c2ab"));
+    }
+
+    #[test]
+    fn test_tool_result_resolves_parent_tool_call() {
+        let parser = ClaudeCodeParser::new();
+        let lines = vec![
+            r#"{"type":"assistant","uuid":"a1","timestamp":"2024-01-01T00:00:00Z","message":{"content":[{"type":"tool_use","id":"t1","name":"Grep","input":{"pattern":"fn main"}}]}}"#.to_string(),
+            r#"{"type":"user","uuid":"u1","parentUuid":"a1","timestamp":"2024-01-01T00:00:01Z","message":{"content":[{"type":"tool_result","tool_use_id":"t1","content":"src/main.rs"}]}}"#.to_string(),
+        ];
+
+        let result = parser.parse_stream(&mut lines.into_iter());
+        assert_eq!(result.events.len(), 2);
+        assert_eq!(result.events[1].tool_type.as_deref(), Some("result"));
+        assert_eq!(result.events[1].tool_name.as_deref(), Some("Grep"));
+    }
+
+    #[test]
+    fn test_tool_result_links_parent_sequence() {
+        let parser = ClaudeCodeParser::new();
+        // Two parallel tool calls, answered out of order after an unrelated line
+        let lines = vec![
+            r#"{"type":"user","uuid":"q1","timestamp":"2024-01-01T00:00:00Z","message":{"content":"Look around"}}"#.to_string(),
+            r#"{"type":"assistant","uuid":"a1","parentUuid":"q1","timestamp":"2024-01-01T00:00:01Z","message":{"content":[{"type":"tool_use","id":"t1","name":"Grep","input":{"pattern":"fn main"}}]}}"#.to_string(),
+            r#"{"type":"assistant","uuid":"a2","parentUuid":"a1","timestamp":"2024-01-01T00:00:01Z","message":{"content":[{"type":"tool_use","id":"t2","name":"Read","input":{"file_path":"Cargo.toml"}}]}}"#.to_string(),
+            "not json".to_string(),
+            r#"{"type":"user","uuid":"u2","parentUuid":"a2","timestamp":"2024-01-01T00:00:02Z","message":{"content":[{"type":"tool_result","tool_use_id":"t2","content":"[package]"}]}}"#.to_string(),
+            r#"{"type":"user","uuid":"u1","parentUuid":"a1","timestamp":"2024-01-01T00:00:02Z","message":{"content":[{"type":"tool_result","tool_use_id":"t1","content":"src/main.rs"}]}}"#.to_string(),
+        ];
+
+        let result = parser.parse(&lines);
+        let by_seq = |seq: usize| result.events.iter().find(|e| e.sequence == seq).unwrap();
+        assert_eq!(by_seq(4).parent_sequence, Some(2));
+        assert_eq!(by_seq(4).tool_name.as_deref(), Some("Read"));
+        assert_eq!(by_seq(5).parent_sequence, Some(1));
+        assert_eq!(by_seq(5).tool_name.as_deref(), Some("Grep"));
+        // Only tool results are linked
+        assert_eq!(by_seq(1).parent_sequence, None);
+        assert_eq!(by_seq(0).parent_sequence, None);
+    }
 }
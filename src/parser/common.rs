@@ -20,6 +20,7 @@ pub struct ParsedEventBuilder {
     content_preview: String,
     search_content: String,
     has_code: bool,
+    code_language: Option<String>,
     has_error: bool,
     has_file_changes: bool,
     tool_name: Option<String>,
@@ -50,6 +51,7 @@ impl ParsedEventBuilder {
             content_preview: String::new(),
             search_content: String::new(),
             has_code: false,
+            code_language: None,
             has_error: false,
             has_file_changes: false,
             tool_name: None,
@@ -107,6 +109,11 @@ impl ParsedEventBuilder {
         self
     }
 
+    pub fn code_language(mut self, language: Option<String>) -> Self {
+        self.code_language = language;
+        self
+    }
+
     pub fn build(self) -> ParsedEvent {
         ParsedEvent {
             sequence: self.sequence,
@@ -115,15 +122,18 @@ impl ParsedEventBuilder {
             content_preview: self.content_preview,
             search_content: self.search_content,
             has_code: self.has_code,
+            code_language: self.code_language,
             has_error: self.has_error,
             has_file_changes: self.has_file_changes,
             tool_name: self.tool_name,
             tool_type: self.tool_type,
             tool_summary: self.tool_summary,
+            parent_sequence: None,
             input_tokens: self.input_tokens,
             output_tokens: self.output_tokens,
             cache_read_tokens: self.cache_read_tokens,
             cache_creation_tokens: self.cache_creation_tokens,
+            tokens_estimated: false,
             model: self.model,
             timestamp: self.timestamp,
             byte_offset: self.byte_offset,
@@ -167,6 +177,81 @@ impl Default for ContentDetector {
     }
 }
 
+// ─── Code language ───────────────────────────────────────────────────────────
+
+/// Language of the code in a message: the info string of its first tagged
+/// fenced block (```rust), else the extension of the file a tool call touches.
+pub fn detect_code_language(content: &str, tool_input: Option<&Value>) -> Option<String> {
+    fence_language(content).or_else(|| tool_input.and_then(tool_file_language))
+}
+
+fn fence_language(content: &str) -> Option<String> {
+    content.lines().find_map(|line| {
+        let info = line.trim_start().strip_prefix("```")?;
+        let tag = info
+            .split(|c: char| c.is_whitespace() || c == ',' || c == '{')
+            .next()?;
+        normalize_language(tag)
+    })
+}
+
+/// Canonical name for a fence info string, or `None` for plain text
+pub fn normalize_language(tag: &str) -> Option<String> {
+    let tag = tag.to_lowercase();
+    let language = match tag.as_str() {
+        "" | "text" | "txt" | "plain" | "plaintext" => return None,
+        "py" | "python3" => "python",
+        "rs" => "rust",
+        "js" | "jsx" | "mjs" | "cjs" | "node" => "javascript",
+        "ts" | "tsx" => "typescript",
+        "sh" | "bash" | "zsh" | "shell" | "console" | "shellsession" => "shell",
+        "yml" => "yaml",
+        "rb" => "ruby",
+        "golang" => "go",
+        "c++" | "cc" | "cxx" | "hpp" => "cpp",
+        "c#" | "cs" => "csharp",
+        "kt" | "kts" => "kotlin",
+        "md" => "markdown",
+        other => other,
+    };
+    Some(language.to_string())
+}
+
+/// Language of the file named in a tool input (`file_path`, `notebook_path`, or `path`)
+fn tool_file_language(tool_input: &Value) -> Option<String> {
+    let path = ["file_path", "notebook_path", "path"]
+        .iter()
+        .find_map(|key| tool_input.get(*key).and_then(|v| v.as_str()))?;
+    let extension = std::path::Path::new(path).extension()?.to_str()?;
+    let language = match extension.to_lowercase().as_str() {
+        "rs" => "rust",
+        "py" | "pyi" | "ipynb" => "python",
+        "js" | "jsx" | "mjs" | "cjs" => "javascript",
+        "ts" | "tsx" | "mts" | "cts" => "typescript",
+        "go" => "go",
+        "java" => "java",
+        "kt" | "kts" => "kotlin",
+        "swift" => "swift",
+        "rb" => "ruby",
+        "php" => "php",
+        "c" | "h" => "c",
+        "cpp" | "cc" | "cxx" | "hpp" | "hh" => "cpp",
+        "cs" => "csharp",
+        "sh" | "bash" | "zsh" => "shell",
+        "sql" => "sql",
+        "html" | "htm" => "html",
+        "css" => "css",
+        "scss" => "scss",
+        "vue" => "vue",
+        "svelte" => "svelte",
+        "json" => "json",
+        "yaml" | "yml" => "yaml",
+        "toml" => "toml",
+        _ => return None,
+    };
+    Some(language.to_string())
+}
+
 // ─── Text utilities ──────────────────────────────────────────────────────────
 
 /// Truncate a string at a valid UTF-8 character boundary.
@@ -311,6 +396,48 @@ pub fn extract_metadata(events: &[ParsedEvent]) -> SessionMetadata {
     metadata
 }
 
+// ─── Token estimation ────────────────────────────────────────────────────────
+
+/// Approximate the number of tokens `text` takes.
+///
+/// A rough stand-in for a BPE tokenizer: each run of letters and digits costs
+/// one token per four characters (rounded up), and every other non-whitespace
+/// character costs one.
+pub fn estimate_tokens(text: &str) -> i64 {
+    let mut tokens = 0;
+    let mut word_len: usize = 0;
+    for c in text.chars() {
+        if c.is_alphanumeric() {
+            word_len += 1;
+            continue;
+        }
+        tokens += word_len.div_ceil(4);
+        word_len = 0;
+        if !c.is_whitespace() {
+            tokens += 1;
+        }
+    }
+    (tokens + word_len.div_ceil(4)) as i64
+}
+
+/// Estimate `output_tokens` for assistant events that reported no usage,
+/// marking them `tokens_estimated`, and add the estimates to the stats.
+/// Input tokens are left unset: the prompt isn't in the event's text.
+pub fn estimate_missing_tokens(result: &mut ParseResult) {
+    for event in &mut result.events {
+        if event.role != "assistant"
+            || event.output_tokens.is_some()
+            || event.search_content.is_empty()
+        {
+            continue;
+        }
+        let tokens = estimate_tokens(&event.search_content);
+        event.output_tokens = Some(tokens);
+        event.tokens_estimated = true;
+        result.stats.total_output_tokens += tokens;
+    }
+}
+
 // ─── Tool summary ────────────────────────────────────────────────────────────
 
 /// Generate a human-readable summary for a tool invocation.
@@ -549,6 +676,25 @@ mod tests {
         assert!(!detector.has_code("just plain text here"));
     }
 
+    #[test]
+    fn test_detect_code_language() {
+        assert_eq!(
+            detect_code_language("Try this:\n```python\nprint('hello')\n```", None).as_deref(),
+            Some("python")
+        );
+        assert_eq!(
+            detect_code_language("```\nplain\n```\n```rs\nfn main() {}\n```", None).as_deref(),
+            Some("rust")
+        );
+        let input = serde_json::json!({ "file_path": "/repo/src/App.tsx" });
+        assert_eq!(
+            detect_code_language("", Some(&input)).as_deref(),
+            Some("typescript")
+        );
+        let input = serde_json::json!({ "file_path": "/repo/README.txt" });
+        assert_eq!(detect_code_language("```text\nhi\n```", Some(&input)), None);
+    }
+
     #[test]
     fn test_content_detector_error() {
         let detector = ContentDetector::new();
@@ -679,6 +825,43 @@ mod tests {
         assert_eq!(cache_create, None);
     }
 
+    #[test]
+    fn test_estimate_missing_tokens() {
+        let events = vec![
+            ParsedEventBuilder::new(0, "user", "2024-01-01T00:00:00Z", 0, 10)
+                .content("Hi".to_string(), "Hi".to_string())
+                .build(),
+            ParsedEventBuilder::new(1, "assistant", "2024-01-01T00:00:01Z", 10, 10)
+                .content(
+                    "Hello world...".to_string(),
+                    "Hello world, this is fine.".to_string(),
+                )
+                .build(),
+            ParsedEventBuilder::new(2, "assistant", "2024-01-01T00:00:02Z", 20, 10)
+                .content("Done".to_string(), "Done".to_string())
+                .usage(Some(10), Some(5), None, None)
+                .build(),
+        ];
+        let mut result = ParseResult {
+            stats: calculate_stats(&events),
+            events,
+            metadata: SessionMetadata::default(),
+            errors: Vec::new(),
+            error_lines: Vec::new(),
+        };
+        estimate_missing_tokens(&mut result);
+
+        // Usage-less assistant text gets an estimate flagged as such
+        assert_eq!(result.events[1].output_tokens, Some(9));
+        assert!(result.events[1].tokens_estimated);
+        assert_eq!(result.events[1].input_tokens, None);
+        // Reported usage and user messages are untouched
+        assert_eq!(result.events[2].output_tokens, Some(5));
+        assert!(!result.events[2].tokens_estimated);
+        assert_eq!(result.events[0].output_tokens, None);
+        assert_eq!(result.stats.total_output_tokens, 14);
+    }
+
     #[test]
     fn test_generate_tool_summary() {
         let input: Value = serde_json::json!({"command": "ls -la /tmp"});
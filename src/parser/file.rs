@@ -0,0 +1,105 @@
+//! Reading session files from disk
+//!
+//! Session files are plain JSONL or gzip-compressed JSONL (`.jsonl.gz`, as left
+//! by tools that rotate old sessions). Byte offsets recorded for a compressed
+//! file are offsets into its decompressed content, so reads by offset go
+//! through these helpers rather than seeking the file directly.
+
+use flate2::read::GzDecoder;
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// Whether `path` names a gzip-compressed session file
+pub fn is_compressed(path: &Path) -> bool {
+    path.extension().and_then(|e| e.to_str()) == Some("gz")
+}
+
+/// Open a session file for reading, decompressing `.gz` files on the fly
+pub fn open(path: &Path) -> io::Result<Box<dyn Read + Send>> {
+    let file = std::fs::File::open(path)?;
+    if is_compressed(path) {
+        Ok(Box::new(GzDecoder::new(BufReader::new(file))))
+    } else {
+        Ok(Box::new(file))
+    }
+}
+
+/// Open a session file positioned `offset` bytes into its (decompressed)
+/// content. Reads past the end of the content yield nothing.
+pub fn open_at(path: &Path, offset: u64) -> io::Result<Box<dyn Read + Send>> {
+    if is_compressed(path) {
+        // gzip streams can't seek; decompress and discard up to the offset
+        let mut reader = open(path)?;
+        io::copy(&mut (&mut reader).take(offset), &mut io::sink())?;
+        Ok(reader)
+    } else {
+        let mut file = std::fs::File::open(path)?;
+        file.seek(SeekFrom::Start(offset))?;
+        Ok(Box::new(file))
+    }
+}
+
+/// Size in bytes of the (decompressed) content of a session file
+pub fn content_len(path: &Path) -> io::Result<u64> {
+    if is_compressed(path) {
+        io::copy(&mut open(path)?, &mut io::sink())
+    } else {
+        Ok(std::fs::metadata(path)?.len())
+    }
+}
+
+/// The full (decompressed) content of a session file
+pub fn read_to_string(path: &Path) -> io::Result<String> {
+    let mut content = String::new();
+    open(path)?.read_to_string(&mut content)?;
+    Ok(content)
+}
+
+/// `length` bytes at `offset` into the (decompressed) content of a session file
+pub fn read_range(path: &Path, offset: u64, length: usize) -> io::Result<Vec<u8>> {
+    let mut buffer = vec![0u8; length];
+    if is_compressed(path) {
+        // gzip streams can't seek; decompress and discard up to the offset
+        let mut reader = open(path)?;
+        let skipped = io::copy(&mut (&mut reader).take(offset), &mut io::sink())?;
+        if skipped < offset {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        reader.read_exact(&mut buffer)?;
+    } else {
+        let mut file = std::fs::File::open(path)?;
+        file.seek(SeekFrom::Start(offset))?;
+        file.read_exact(&mut buffer)?;
+    }
+    Ok(buffer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::{write::GzEncoder, Compression};
+    use std::io::Write;
+
+    #[test]
+    fn test_read_compressed_session_file() {
+        let tmp = tempfile::tempdir().unwrap();
+        let content = "{\"a\":1}\n{\"b\":2}\n";
+        let path = tmp.path().join("session.jsonl.gz");
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(content.as_bytes()).unwrap();
+        std::fs::write(&path, encoder.finish().unwrap()).unwrap();
+
+        assert!(is_compressed(&path));
+        assert_eq!(read_to_string(&path).unwrap(), content);
+        // Offsets address the decompressed bytes
+        assert_eq!(read_range(&path, 8, 7).unwrap(), b"{\"b\":2}");
+        assert!(read_range(&path, 100, 1).is_err());
+        assert_eq!(content_len(&path).unwrap(), content.len() as u64);
+        let mut rest = String::new();
+        open_at(&path, 8)
+            .unwrap()
+            .read_to_string(&mut rest)
+            .unwrap();
+        assert_eq!(rest, "{\"b\":2}\n");
+    }
+}
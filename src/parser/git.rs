@@ -0,0 +1,291 @@
+//! Git branch and commit detection
+//!
+//! Session files don't say which branch the work happened on, so it is
+//! inferred from the transcript: branch hints from the environment (Claude
+//! Code's per-event `gitBranch`), `git checkout`/`git switch` commands the
+//! agent ran, and the `[branch sha]` line printed by `git commit`. Parsers feed
+//! events in order and the state at the end of the session is what gets
+//! stored. A switch to something that can't be resolved from the command
+//! alone (`git checkout -`, shell variables) makes the branch unknown rather
+//! than guessed.
+//!
+//! The context is serializable so the watcher can store it with the session
+//! and continue from it when lines are appended, ending up where a parse of
+//! the whole file would.
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::sync::LazyLock;
+
+/// `git commit` summary line: `[main 1a2b3c4] message` or `[main (root-commit) 1a2b3c4] message`
+static COMMIT_LINE_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^\[([^\s\]]+)(?: \([^)]*\))? ([0-9a-f]{7,40})\] ").unwrap());
+
+/// `git commit` summary line on a detached HEAD: `[detached HEAD 1a2b3c4] message`
+static DETACHED_COMMIT_LINE_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^\[detached HEAD ([0-9a-f]{7,40})\] ").unwrap());
+
+/// The branch and commit a session was working on, built up event by event
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GitContext {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    branch: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    commit: Option<String>,
+    /// Last branch reported by the environment
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    last_hint: Option<String>,
+    /// Ids of command tool calls whose output hasn't been seen yet
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pending_calls: BTreeSet<String>,
+}
+
+impl GitContext {
+    /// Continue from a branch and commit stored without the rest of the context
+    pub fn resume(branch: Option<String>, commit: Option<String>) -> Self {
+        GitContext {
+            branch,
+            commit,
+            ..GitContext::default()
+        }
+    }
+
+    /// The active branch, if known
+    pub fn branch(&self) -> Option<&str> {
+        self.branch.as_deref()
+    }
+
+    /// The last commit made or checked out on the active branch, if known
+    pub fn commit(&self) -> Option<&str> {
+        self.commit.as_deref()
+    }
+
+    /// A branch reported by the environment (`HEAD` when detached).
+    ///
+    /// Hints are only acted on when they change: one that repeats what was
+    /// reported before a `git checkout` in the transcript is stale.
+    pub fn observe_branch_hint(&mut self, branch: &str) {
+        let branch = branch.trim();
+        if branch.is_empty() || self.last_hint.as_deref() == Some(branch) {
+            return;
+        }
+        self.last_hint = Some(branch.to_string());
+        if branch == "HEAD" {
+            self.detach(None);
+        } else {
+            self.switch(Some(branch.to_string()));
+        }
+    }
+
+    /// A shell command the agent ran; `git checkout`/`git switch` move the context
+    pub fn observe_command(&mut self, command: &str) {
+        for segment in command.split(['\n', ';', '|', '&']) {
+            let args: Vec<&str> = segment
+                .split_whitespace()
+                .map(|arg| arg.trim_matches(|c| c == '"' || c == '\''))
+                .collect();
+            self.observe_git_args(&args);
+        }
+    }
+
+    /// A shell command run as tool call `id`; its output is read by
+    /// [`observe_call_output`](Self::observe_call_output), possibly in a later
+    /// chunk of the transcript
+    pub fn observe_command_call(&mut self, id: Option<&str>, command: &str) {
+        self.observe_command(command);
+        if let Some(id) = id {
+            self.pending_calls.insert(id.to_string());
+        }
+    }
+
+    /// Whether tool call `id` is a command whose output is still expected.
+    /// Returns true once, so the caller reads the output with
+    /// [`observe_output`](Self::observe_output).
+    pub fn take_pending_call(&mut self, id: &str) -> bool {
+        self.pending_calls.remove(id)
+    }
+
+    /// Output of a command; picks up the summary line of `git commit`
+    pub fn observe_output(&mut self, output: &str) {
+        for line in output.lines().map(str::trim_start) {
+            if let Some(caps) = DETACHED_COMMIT_LINE_RE.captures(line) {
+                self.detach(Some(caps[1].to_string()));
+            } else if let Some(caps) = COMMIT_LINE_RE.captures(line) {
+                self.switch(Some(caps[1].to_string()));
+                self.commit = Some(caps[2].to_string());
+            }
+        }
+    }
+
+    fn observe_git_args(&mut self, args: &[&str]) {
+        let Some((&"git", mut rest)) = args.split_first() else {
+            return;
+        };
+        // Global options before the subcommand
+        while let Some((&option, tail)) = rest.split_first() {
+            match option {
+                // Another repository
+                "-C" | "--git-dir" | "--work-tree" => return,
+                "-c" => rest = tail.get(1..).unwrap_or_default(),
+                _ if option.starts_with('-') => rest = tail,
+                _ => break,
+            }
+        }
+        match rest.split_first() {
+            Some((&"checkout", args)) => self.checkout(args, &["-b", "-B", "--orphan"]),
+            Some((&"switch", args)) => self.checkout(
+                args,
+                &["-c", "-C", "--create", "--force-create", "--orphan"],
+            ),
+            _ => {}
+        }
+    }
+
+    /// `git checkout`/`git switch` arguments; `create` lists the options naming a new branch
+    fn checkout(&mut self, args: &[&str], create: &[&str]) {
+        // Restoring files (`git checkout -- path`, `git checkout main src/lib.rs`)
+        if args.contains(&"--") {
+            return;
+        }
+        if let Some(i) = args.iter().position(|arg| create.contains(arg)) {
+            match args.get(i + 1) {
+                Some(name) if is_literal(name) => self.switch(Some(name.to_string())),
+                _ => self.switch(None),
+            }
+            return;
+        }
+
+        let targets: Vec<&str> = args
+            .iter()
+            .copied()
+            .filter(|arg| !arg.starts_with('-') || *arg == "-")
+            .collect();
+        let detach = args.contains(&"--detach");
+        match targets.as_slice() {
+            [target] if *target == "." => {}
+            [target] if is_commit_hash(target) => self.detach(Some(target.to_string())),
+            [_] if detach => self.detach(None),
+            [target] if !is_literal(target) => self.switch(None),
+            [target] => {
+                // `git checkout --track origin/feature` creates `feature`
+                let tracked = (args.contains(&"-t") || args.contains(&"--track"))
+                    .then(|| target.split_once('/').map(|(_, branch)| branch))
+                    .flatten();
+                self.switch(Some(tracked.unwrap_or(target).to_string()));
+            }
+            _ => {}
+        }
+    }
+
+    fn switch(&mut self, branch: Option<String>) {
+        if branch != self.branch {
+            self.commit = None;
+        }
+        self.branch = branch;
+    }
+
+    fn detach(&mut self, commit: Option<String>) {
+        self.branch = None;
+        self.commit = commit;
+    }
+}
+
+/// A branch name written out in full, not `-` (the previous branch) or shell expansion
+fn is_literal(name: &str) -> bool {
+    name != "-" && !name.contains(['$', '`', '*', '(', ')', '<', '>'])
+}
+
+fn is_commit_hash(name: &str) -> bool {
+    (7..=40).contains(&name.len()) && name.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn after(commands: &[&str]) -> GitContext {
+        let mut git = GitContext::default();
+        for command in commands {
+            git.observe_command(command);
+        }
+        git
+    }
+
+    #[test]
+    fn test_branch_from_commands() {
+        assert_eq!(
+            after(&["git checkout feature/x"]).branch(),
+            Some("feature/x")
+        );
+        assert_eq!(
+            after(&["cd repo && git switch -c fix/login origin/main"]).branch(),
+            Some("fix/login")
+        );
+        assert_eq!(
+            after(&["git checkout -t origin/feature/y"]).branch(),
+            Some("feature/y")
+        );
+        // Restoring files doesn't change branch
+        assert_eq!(
+            after(&[
+                "git checkout main",
+                "git checkout -- src/lib.rs",
+                "git checkout ."
+            ])
+            .branch(),
+            Some("main")
+        );
+        // Other repositories are ignored
+        assert_eq!(after(&["git -C ../other checkout dev"]).branch(), None);
+    }
+
+    #[test]
+    fn test_ambiguous_switch_clears_branch() {
+        assert_eq!(
+            after(&["git checkout main", "git checkout -"]).branch(),
+            None
+        );
+        assert_eq!(
+            after(&["git checkout main", "git switch \"$BRANCH\""]).branch(),
+            None
+        );
+
+        let git = after(&["git checkout main", "git checkout 1a2b3c4d"]);
+        assert_eq!(git.branch(), None);
+        assert_eq!(git.commit(), Some("1a2b3c4d"));
+    }
+
+    #[test]
+    fn test_commit_output_and_hints() {
+        let mut git = GitContext::default();
+        git.observe_branch_hint("main");
+        git.observe_command("git checkout -b feature/x");
+        // Stale hint from before the checkout
+        git.observe_branch_hint("main");
+        git.observe_output("[feature/x 9f8e7d6] Add login form\n 2 files changed");
+        assert_eq!(git.branch(), Some("feature/x"));
+        assert_eq!(git.commit(), Some("9f8e7d6"));
+
+        // A new branch forgets the commit
+        git.observe_branch_hint("release");
+        assert_eq!(git.branch(), Some("release"));
+        assert_eq!(git.commit(), None);
+    }
+
+    #[test]
+    fn test_context_survives_serialization() {
+        let mut git = GitContext::default();
+        git.observe_branch_hint("main");
+        git.observe_command_call(Some("toolu_1"), "git checkout -b feature/x");
+
+        let mut resumed: GitContext =
+            serde_json::from_str(&serde_json::to_string(&git).unwrap()).unwrap();
+        assert_eq!(resumed, git);
+        // The stale hint is still recognized, and the call's output still read
+        resumed.observe_branch_hint("main");
+        assert!(resumed.take_pending_call("toolu_1"));
+        assert!(!resumed.take_pending_call("toolu_1"));
+        assert_eq!(resumed.branch(), Some("feature/x"));
+    }
+}
@@ -1,17 +1,23 @@
 //! JSONL session file parsers
 //!
 //! This module contains parsers for different AI coding assistant session formats.
-//! Each parser implements the `SessionParser` trait and is registered in `get_parser()`.
+//! Each parser implements the `SessionParser` trait and is registered by name in a
+//! `ParserRegistry`; the watcher looks up each watch path's `parser` there.
 //!
 //! ## Adding a new parser
 //!
 //! 1. Create `src/parser/<tool>.rs` implementing `SessionParser`
 //! 2. Use utilities from `common` module (`ParsedEventBuilder`, `ContentDetector`, etc.)
-//! 3. Add `pub mod <tool>;` below and register in `get_parser()`
+//! 3. Add `pub mod <tool>;` below and register it in `ParserRegistry::default()`
 //! 4. Add display name in `watcher/storage.rs` and `watcher/store.rs`
+//!
+//! Applications embedding yocore can instead register their own parsers at
+//! runtime through `Core::parsers()`.
 
 pub mod claude_code;
 pub mod common;
+pub mod file;
+pub mod git;
 pub mod openclaw;
 pub mod types;
 
@@ -19,27 +25,107 @@ pub use claude_code::ClaudeCodeParser;
 pub use openclaw::OpenClawParser;
 pub use types::*;
 
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
 /// Parser trait for session file formats
 pub trait SessionParser: Send + Sync {
     /// Parse a JSONL file and return parsed events
     fn parse(&self, lines: &[String]) -> ParseResult;
 
+    /// Parse lines as they are produced, without holding the whole file.
+    ///
+    /// The default collects the lines and delegates to `parse`; parsers that
+    /// can work line by line should override it.
+    fn parse_stream(&self, lines: &mut dyn Iterator<Item = String>) -> ParseResult {
+        let lines: Vec<String> = lines.collect();
+        self.parse(&lines)
+    }
+
+    /// Parse lines appended to a session, continuing from `git`, the
+    /// `metadata.git_context` its earlier lines left.
+    ///
+    /// The default ignores `git` and parses the lines on their own.
+    fn parse_stream_from(
+        &self,
+        lines: &mut dyn Iterator<Item = String>,
+        git: git::GitContext,
+    ) -> ParseResult {
+        let _ = git;
+        self.parse_stream(lines)
+    }
+
     /// Get the parser name
     fn name(&self) -> &'static str;
 }
 
-/// Get a parser for the specified AI tool.
+impl<P: SessionParser + ?Sized> SessionParser for Arc<P> {
+    fn parse(&self, lines: &[String]) -> ParseResult {
+        (**self).parse(lines)
+    }
+
+    fn parse_stream(&self, lines: &mut dyn Iterator<Item = String>) -> ParseResult {
+        (**self).parse_stream(lines)
+    }
+
+    fn parse_stream_from(
+        &self,
+        lines: &mut dyn Iterator<Item = String>,
+        git: git::GitContext,
+    ) -> ParseResult {
+        (**self).parse_stream_from(lines, git)
+    }
+
+    fn name(&self) -> &'static str {
+        (**self).name()
+    }
+}
+
+/// Parsers by name, as referenced by `watch[].parser`.
 ///
-/// Supported parsers:
+/// The default registry holds the built-in parsers:
 /// - `"claude_code"` / `"claude-code"` → Claude Code sessions
 /// - `"openclaw"` → OpenClaw sessions
-pub fn get_parser(tool: &str) -> Option<Box<dyn SessionParser + Send + Sync>> {
-    match tool {
-        "claude_code" | "claude-code" => Some(Box::new(ClaudeCodeParser::new())),
-        "openclaw" => Some(Box::new(OpenClawParser::new())),
-        // Future parsers:
-        // "codex" => Some(Box::new(CodexParser::new())),
-        // "cursor" => Some(Box::new(CursorParser::new())),
-        _ => None,
+pub struct ParserRegistry {
+    parsers: RwLock<HashMap<String, Arc<dyn SessionParser>>>,
+}
+
+impl Default for ParserRegistry {
+    fn default() -> Self {
+        let registry = ParserRegistry::empty();
+        let claude_code: Arc<dyn SessionParser> = Arc::new(ClaudeCodeParser::new());
+        registry.register("claude_code", claude_code.clone());
+        registry.register("claude-code", claude_code);
+        registry.register("openclaw", Arc::new(OpenClawParser::new()));
+        registry
+    }
+}
+
+impl ParserRegistry {
+    /// A registry with no parsers
+    pub fn empty() -> Self {
+        ParserRegistry {
+            parsers: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Register `parser` under `name`, replacing any parser already registered there
+    pub fn register(&self, name: impl Into<String>, parser: Arc<dyn SessionParser>) {
+        self.parsers.write().unwrap().insert(name.into(), parser);
+    }
+
+    /// The parser registered under `name`
+    pub fn get(&self, name: &str) -> Option<Arc<dyn SessionParser>> {
+        self.parsers.read().unwrap().get(name).cloned()
     }
 }
+
+/// Get a built-in parser for the specified AI tool.
+///
+/// Kept for compatibility; parsers registered at runtime are only found
+/// through a `ParserRegistry`.
+pub fn get_parser(tool: &str) -> Option<Box<dyn SessionParser + Send + Sync>> {
+    ParserRegistry::default()
+        .get(tool)
+        .map(|p| Box::new(p) as Box<dyn SessionParser + Send + Sync>)
+}
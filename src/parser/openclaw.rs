@@ -19,6 +19,7 @@ use super::common::{
     calculate_stats, content_to_string, extract_metadata, generate_tool_summary, sanitize_preview,
     truncate_str, ContentDetector, ParsedEventBuilder,
 };
+use super::git::GitContext;
 use super::types::*;
 use super::SessionParser;
 use regex::Regex;
@@ -377,24 +378,48 @@ impl OpenClawParser {
             .and_then(|v| v.as_str())
             .map(|s| s.to_string())
     }
-}
 
-impl Default for OpenClawParser {
-    fn default() -> Self {
-        Self::new()
+    /// Feed an event's `bash` tool calls and their output to `git`
+    fn observe_git(git: &mut GitContext, event: &Value) {
+        let Some(message) = event.get("message") else {
+            return;
+        };
+        if message.get("role").and_then(|r| r.as_str()) == Some("toolResult") {
+            if message.get("toolName").and_then(|n| n.as_str()) == Some("bash") {
+                if let Some(content) = message.get("content") {
+                    git.observe_output(&content_to_string(content));
+                }
+            }
+            return;
+        }
+        let blocks = message.get("content").and_then(|c| c.as_array());
+        for block in blocks.into_iter().flatten() {
+            if block.get("type").and_then(|t| t.as_str()) == Some("toolCall")
+                && block.get("name").and_then(|n| n.as_str()) == Some("bash")
+            {
+                if let Some(command) = block
+                    .get("arguments")
+                    .and_then(|a| a.get("command"))
+                    .and_then(|c| c.as_str())
+                {
+                    git.observe_command(command);
+                }
+            }
+        }
     }
-}
 
-impl SessionParser for OpenClawParser {
-    fn parse(&self, lines: &[String]) -> ParseResult {
+    /// Parse `lines`, continuing git detection from `git`
+    fn parse_from(&self, lines: &[String], mut git: GitContext) -> ParseResult {
         let mut events = Vec::new();
         let mut events_by_id: HashMap<String, Value> = HashMap::new();
         let mut byte_offset: i64 = 0;
         let mut errors = Vec::new();
+        let mut error_lines = Vec::new();
 
-        // First pass: index events by id (for parent-child linking)
+        // First pass: index events by id (for parent-child linking) and follow git commands
         for line in lines {
             if let Ok(event) = serde_json::from_str::<Value>(line) {
+                Self::observe_git(&mut git, &event);
                 if let Some(id) = event.get("id").and_then(|u| u.as_str()) {
                     events_by_id.insert(id.to_string(), event);
                 }
@@ -408,13 +433,17 @@ impl SessionParser for OpenClawParser {
                 None => {
                     if serde_json::from_str::<Value>(line).is_err() {
                         errors.push(format!("Failed to parse line {}", sequence));
+                        error_lines.push(sequence + 1);
                     }
                 }
             }
             byte_offset += line.len() as i64 + 1; // +1 for newline
         }
 
-        let metadata = extract_metadata(&events);
+        let mut metadata = extract_metadata(&events);
+        metadata.git_branch = git.branch().map(String::from);
+        metadata.git_commit = git.commit().map(String::from);
+        metadata.git_context = Some(git);
         let stats = calculate_stats(&events);
 
         ParseResult {
@@ -422,8 +451,30 @@ impl SessionParser for OpenClawParser {
             metadata,
             stats,
             errors,
+            error_lines,
         }
     }
+}
+
+impl Default for OpenClawParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SessionParser for OpenClawParser {
+    fn parse(&self, lines: &[String]) -> ParseResult {
+        self.parse_from(lines, GitContext::default())
+    }
+
+    fn parse_stream_from(
+        &self,
+        lines: &mut dyn Iterator<Item = String>,
+        git: GitContext,
+    ) -> ParseResult {
+        let lines: Vec<String> = lines.collect();
+        self.parse_from(&lines, git)
+    }
 
     fn name(&self) -> &'static str {
         "openclaw"
@@ -589,6 +640,7 @@ mod tests {
         let result = parser.parse(&["not valid json".to_string()]);
         assert_eq!(result.events.len(), 0);
         assert_eq!(result.errors.len(), 1);
+        assert_eq!(result.error_lines, vec![1]);
     }
 
     #[test]
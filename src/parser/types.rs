@@ -16,6 +16,10 @@ pub struct ParseResult {
 
     /// Any parsing errors encountered
     pub errors: Vec<String>,
+
+    /// Line numbers (1-based) of lines that could not be parsed
+    #[serde(default)]
+    pub error_lines: Vec<usize>,
 }
 
 /// A parsed event from a session file
@@ -40,6 +44,10 @@ pub struct ParsedEvent {
     /// Whether this event contains code
     pub has_code: bool,
 
+    /// Language of the code, from a fenced block's info string or a tool's file path
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code_language: Option<String>,
+
     /// Whether this event indicates an error
     pub has_error: bool,
 
@@ -58,6 +66,10 @@ pub struct ParsedEvent {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tool_summary: Option<String>,
 
+    /// Sequence of the `tool_use` event a tool result answers, when it was parsed in the same pass
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parent_sequence: Option<usize>,
+
     /// Token usage
     #[serde(skip_serializing_if = "Option::is_none")]
     pub input_tokens: Option<i64>,
@@ -68,6 +80,11 @@ pub struct ParsedEvent {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub cache_creation_tokens: Option<i64>,
 
+    /// Whether `output_tokens` was estimated from the text because the event
+    /// reported no usage (`parser.estimate_tokens`)
+    #[serde(default)]
+    pub tokens_estimated: bool,
+
     /// Model used for this event
     #[serde(skip_serializing_if = "Option::is_none")]
     pub model: Option<String>,
@@ -75,7 +92,8 @@ pub struct ParsedEvent {
     /// Timestamp of the event
     pub timestamp: String,
 
-    /// Byte offset in the original file
+    /// Byte offset in the original file (into the decompressed content for
+    /// `.jsonl.gz` files, see `parser::file`)
     pub byte_offset: i64,
 
     /// Byte length in the original file
@@ -108,6 +126,30 @@ pub struct SessionMetadata {
     /// Model used (if consistent)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub model: Option<String>,
+
+    /// Parent session ID for sub-agent transcripts (Claude Code `sessionId` on sidechain events)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parent_session_id: Option<String>,
+
+    /// Sub-agent correlation ID (matches the parent's Task tool result `agentId`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub agent_id: Option<String>,
+
+    /// Git branch active at the end of the session, if it could be determined
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub git_branch: Option<String>,
+
+    /// Last commit made or checked out on `git_branch`, if seen in the transcript
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub git_commit: Option<String>,
+
+    /// Git detection state after the last line, for continuing the parse when
+    /// lines are appended (see [`SessionParser::parse_stream_from`]); `None`
+    /// from parsers that don't track it
+    ///
+    /// [`SessionParser::parse_stream_from`]: super::SessionParser::parse_stream_from
+    #[serde(skip)]
+    pub git_context: Option<super::git::GitContext>,
 }
 
 /// Statistics from parsing
@@ -0,0 +1,269 @@
+//! Config hot-reload
+//!
+//! Re-reads the config file on SIGHUP or `POST /api/admin/reload-config` and
+//! applies what can change at runtime: watch paths are added to or removed from
+//! the live watcher, and settings read per use (AI features, scheduler tasks)
+//! take effect on their next use. Settings only read at startup are logged and
+//! ignored until restart.
+
+use crate::config::Config;
+use crate::error::Result;
+use crate::watcher::WatcherHandle;
+use serde::Serialize;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+
+/// Config sections (JSON pointers into the serialized config) that are only
+/// read at startup
+const RESTART_ONLY: &[&str] = &[
+    "/storage",
+    "/server",
+    "/data_dir",
+    "/database",
+    "/search",
+    "/ephemeral",
+    "/embeddings",
+    "/mcp",
+    "/parser",
+    "/webhooks",
+    "/ai/max_concurrent_tasks",
+    "/scheduler/ranking/interval_hours",
+    "/scheduler/duplicate_cleanup/interval_hours",
+    "/scheduler/embedding_refresh/interval_hours",
+    "/scheduler/skill_cleanup/interval_hours",
+    "/scheduler/maintenance/interval_hours",
+    "/scheduler/session_purge/interval_hours",
+    "/scheduler/ranking/cron",
+    "/scheduler/duplicate_cleanup/cron",
+    "/scheduler/embedding_refresh/cron",
+    "/scheduler/skill_cleanup/cron",
+    "/scheduler/maintenance/cron",
+    "/scheduler/session_purge/cron",
+];
+
+/// The running config, shared with the scheduler and AI recovery
+pub type SharedConfig = Arc<RwLock<Config>>;
+
+/// What a reload changed
+#[derive(Debug, Default, Serialize)]
+pub struct ReloadSummary {
+    /// Watch directories that started being watched
+    pub watch_added: Vec<String>,
+    /// Watch directories that stopped being watched
+    pub watch_removed: Vec<String>,
+    /// Changed settings that need a restart to take effect
+    pub ignored: Vec<String>,
+}
+
+/// Reloads the config file into the running service. Clones share state.
+#[derive(Clone)]
+pub struct ConfigReloader {
+    config_path: PathBuf,
+    live: SharedConfig,
+    watcher: Arc<tokio::sync::RwLock<Option<WatcherHandle>>>,
+    /// Serializes reloads so watch path diffs don't interleave
+    reloading: Arc<tokio::sync::Mutex<()>>,
+}
+
+impl ConfigReloader {
+    pub fn new(
+        config: Config,
+        config_path: PathBuf,
+        watcher: Arc<tokio::sync::RwLock<Option<WatcherHandle>>>,
+    ) -> Self {
+        ConfigReloader {
+            config_path,
+            live: Arc::new(RwLock::new(config)),
+            watcher,
+            reloading: Arc::new(tokio::sync::Mutex::new(())),
+        }
+    }
+
+    /// A copy of the running config
+    pub fn current(&self) -> Config {
+        self.live.read().unwrap().clone()
+    }
+
+    /// The running config, for components that re-read it on each use
+    pub fn shared(&self) -> SharedConfig {
+        self.live.clone()
+    }
+
+    /// Re-read the config file and apply it
+    pub async fn reload(&self) -> Result<ReloadSummary> {
+        let _reloading = self.reloading.lock().await;
+        let mut config = Config::from_file(&self.config_path)?;
+
+        let running = self.current();
+        let mut summary = ReloadSummary {
+            ignored: restart_only_changes(&running, &config),
+            ..Default::default()
+        };
+        for key in &summary.ignored {
+            tracing::warn!(
+                "Config reload: {} changed but requires a restart, ignored",
+                key.trim_start_matches('/').replace('/', ".")
+            );
+        }
+        // Keep startup-only settings so the running config describes what's running
+        config.storage = running.storage;
+        config.server = running.server;
+        config.data_dir = running.data_dir;
+        config.database = running.database;
+        config.search = running.search;
+        config.ephemeral = running.ephemeral;
+        config.embeddings = running.embeddings;
+        config.mcp = running.mcp;
+        config.parser = running.parser;
+        config.webhooks = running.webhooks;
+        config.ai.max_concurrent_tasks = running.ai.max_concurrent_tasks;
+        config.scheduler.ranking.interval_hours = running.scheduler.ranking.interval_hours;
+        config.scheduler.duplicate_cleanup.interval_hours =
+            running.scheduler.duplicate_cleanup.interval_hours;
+        config.scheduler.embedding_refresh.interval_hours =
+            running.scheduler.embedding_refresh.interval_hours;
+        config.scheduler.skill_cleanup.interval_hours =
+            running.scheduler.skill_cleanup.interval_hours;
+        config.scheduler.maintenance.interval_hours = running.scheduler.maintenance.interval_hours;
+        config.scheduler.session_purge.interval_hours =
+            running.scheduler.session_purge.interval_hours;
+        config.scheduler.ranking.cron = running.scheduler.ranking.cron;
+        config.scheduler.duplicate_cleanup.cron = running.scheduler.duplicate_cleanup.cron;
+        config.scheduler.embedding_refresh.cron = running.scheduler.embedding_refresh.cron;
+        config.scheduler.skill_cleanup.cron = running.scheduler.skill_cleanup.cron;
+        config.scheduler.maintenance.cron = running.scheduler.maintenance.cron;
+        config.scheduler.session_purge.cron = running.scheduler.session_purge.cron;
+
+        if let Some(watcher) = self.watcher.read().await.as_ref() {
+            let changes = watcher.set_watch_paths(config.watch_paths()).await?;
+            summary.watch_added = changes.added;
+            summary.watch_removed = changes.removed;
+        }
+
+        *self.live.write().unwrap() = config;
+        tracing::info!(
+            "Config reloaded from {} ({} watch path(s) added, {} removed)",
+            self.config_path.display(),
+            summary.watch_added.len(),
+            summary.watch_removed.len()
+        );
+        Ok(summary)
+    }
+
+    /// Reload whenever the process receives SIGHUP
+    #[cfg(unix)]
+    pub fn reload_on_sighup(&self) -> Result<()> {
+        use tokio::signal::unix::{signal, SignalKind};
+        let mut hangup = signal(SignalKind::hangup())?;
+        let reloader = self.clone();
+        tokio::spawn(async move {
+            while hangup.recv().await.is_some() {
+                tracing::info!("SIGHUP received, reloading config");
+                if let Err(e) = reloader.reload().await {
+                    tracing::error!("Config reload failed: {}", e);
+                }
+            }
+        });
+        Ok(())
+    }
+
+    #[cfg(not(unix))]
+    pub fn reload_on_sighup(&self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// The `RESTART_ONLY` keys whose values differ between `old` and `new`
+fn restart_only_changes(old: &Config, new: &Config) -> Vec<String> {
+    let (Ok(old), Ok(new)) = (serde_json::to_value(old), serde_json::to_value(new)) else {
+        return Vec::new();
+    };
+    RESTART_ONLY
+        .iter()
+        .filter(|key| old.pointer(key) != new.pointer(key))
+        .map(|key| key.to_string())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::WatchConfig;
+    use crate::watcher::WatcherEvent;
+    use crate::Core;
+    use std::time::Duration;
+
+    fn watch(path: &std::path::Path) -> WatchConfig {
+        WatchConfig {
+            path: path.to_path_buf(),
+            parser: "claude_code".to_string(),
+            enabled: true,
+            delete_on_remove: false,
+            namespace_session_ids: false,
+            parse_agents: false,
+            dedupe_by_hash: false,
+            include: Vec::new(),
+            exclude: Vec::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reload_watches_added_path() {
+        let tmp = tempfile::tempdir().unwrap();
+        let config_path = tmp.path().join("config.toml");
+        let mut config = Config {
+            data_dir: tmp.path().join("data"),
+            ..Config::default()
+        };
+        config.save_to_file(&config_path).unwrap();
+
+        let core = Core::new(config.clone(), config_path.clone()).unwrap();
+        core.start_watching().await.unwrap();
+        let mut events = core.event_sender().subscribe();
+
+        let sessions = tmp.path().join("sessions");
+        std::fs::create_dir_all(sessions.join("project")).unwrap();
+        config.watch.push(watch(&sessions));
+        config.server.port += 1;
+        config.save_to_file(&config_path).unwrap();
+
+        let summary = core.reload_config().await.unwrap();
+        assert_eq!(
+            summary.watch_added,
+            vec![sessions.to_string_lossy().to_string()]
+        );
+        assert!(summary.watch_removed.is_empty());
+        assert_eq!(summary.ignored, vec!["/server".to_string()]);
+        // The reloaded watch path shows in the running config, the restart-only change doesn't
+        assert_eq!(core.config().watch.len(), 1);
+        assert_eq!(core.config().server.port, config.server.port - 1);
+
+        // A session written under the new path is picked up by the live watcher
+        std::fs::write(
+            sessions.join("project/session-a.jsonl"),
+            r#"{"type":"user","timestamp":"2024-01-01T00:00:00Z","message":{"content":[{"type":"text","text":"Hello"}]}}"#
+                .to_string()
+                + "\n",
+        )
+        .unwrap();
+        let parsed = tokio::time::timeout(Duration::from_secs(10), async {
+            loop {
+                if let Ok(WatcherEvent::SessionParsed { session_id, .. }) = events.recv().await {
+                    return session_id;
+                }
+            }
+        })
+        .await
+        .expect("new watch path was not watched");
+        assert_eq!(parsed, "session-a");
+
+        // Removing it again stops watching
+        config.watch.clear();
+        config.save_to_file(&config_path).unwrap();
+        let summary = core.reload_config().await.unwrap();
+        assert_eq!(
+            summary.watch_removed,
+            vec![sessions.to_string_lossy().to_string()]
+        );
+    }
+}
@@ -5,24 +5,34 @@
 //! - **Duplicate cleanup**: Find and soft-remove near-duplicate memories
 //! - **Embedding refresh**: Backfill embeddings for memories missing them
 //! - **Skill cleanup**: Find and hard-delete near-duplicate skills
+//! - **Maintenance**: incremental_vacuum, ANALYZE and PRAGMA optimize (no AI dependency)
+//! - **Session purge**: Permanently delete sessions soft-deleted longer than the retention window
 //!
 //! Each task declares its feature dependencies (e.g., requires AI + memory_extraction).
 //! The scheduler checks these per-task — future tasks with different dependencies
 //! won't be incorrectly skipped.
 //!
-//! Each task runs in its own tokio::spawn with independent interval timers.
+//! Each task runs in its own tokio::spawn with independent timers, every
+//! `interval_hours` or at the times given by its optional `cron` expression.
 //! Tasks are staggered to avoid simultaneous DB contention.
 
 pub mod tasks;
 
 use crate::config::Config;
 use crate::db::Database;
+use crate::maintenance::MaintenanceMode;
+use crate::reload::SharedConfig;
 use crate::watcher::WatcherEvent;
-use std::sync::Arc;
+use chrono::{DateTime, Local, TimeZone};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 use tokio::sync::broadcast;
 
 /// Result of a single scheduler task execution
+#[derive(Debug, Serialize)]
 pub struct TaskResult {
     pub task_name: String,
     pub items_processed: usize,
@@ -31,8 +41,101 @@ pub struct TaskResult {
     pub detail: String,
 }
 
+/// A recorded task execution from the `scheduler_runs` table
+#[derive(Debug, Serialize)]
+pub struct SchedulerRun {
+    pub id: i64,
+    pub task_name: String,
+    pub started_at: String,
+    pub finished_at: String,
+    pub items_processed: usize,
+    pub items_affected: usize,
+    pub errors: usize,
+    pub detail: String,
+    /// The tick was skipped because the previous run was still active
+    pub skipped: bool,
+}
+
 use crate::config::AiFeature;
 
+/// Names of tasks with a run in progress, shared by the timers and on-demand
+/// runs so a task never runs twice at once. Clones refer to the same set.
+#[derive(Clone, Default)]
+pub struct RunningTasks(Arc<Mutex<HashSet<String>>>);
+
+impl RunningTasks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Mark `name` as running, or `None` if it already is.
+    /// The mark is cleared when the returned guard is dropped.
+    fn try_start(&self, name: &str) -> Option<RunGuard> {
+        let mut running = self.0.lock().unwrap();
+        if !running.insert(name.to_string()) {
+            return None;
+        }
+        Some(RunGuard {
+            tasks: self.clone(),
+            name: name.to_string(),
+        })
+    }
+}
+
+/// Clears a task's running mark when dropped
+struct RunGuard {
+    tasks: RunningTasks,
+    name: String,
+}
+
+impl Drop for RunGuard {
+    fn drop(&mut self) {
+        self.tasks.0.lock().unwrap().remove(&self.name);
+    }
+}
+
+/// Parse a cron expression. Standard five-field expressions (minute, hour,
+/// day of month, month, day of week) are accepted as well as the six- or
+/// seven-field form with leading seconds and trailing years.
+pub fn parse_cron(expr: &str) -> Result<cron::Schedule, cron::error::Error> {
+    let expr = expr.trim();
+    if expr.split_whitespace().count() == 5 {
+        format!("0 {}", expr).parse()
+    } else {
+        expr.parse()
+    }
+}
+
+/// When a task runs
+#[derive(Clone)]
+enum TaskSchedule {
+    /// A fixed period, first run one period after startup
+    Every(Duration),
+    /// Whenever the cron expression matches, in local time
+    Cron(Box<cron::Schedule>),
+}
+
+impl TaskSchedule {
+    /// The first run time after `after`, or `None` if the schedule has no more runs
+    fn next_run<Tz: TimeZone>(&self, after: &DateTime<Tz>) -> Option<DateTime<Tz>> {
+        match self {
+            TaskSchedule::Every(period) => {
+                Some(after.clone() + chrono::Duration::from_std(*period).ok()?)
+            }
+            TaskSchedule::Cron(schedule) => schedule.after(after).next(),
+        }
+    }
+}
+
+impl std::fmt::Display for TaskSchedule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TaskSchedule::Every(period) => write!(f, "every {} hours", period.as_secs() / 3600),
+            TaskSchedule::Cron(schedule) => write!(f, "on cron '{}'", schedule),
+        }
+    }
+}
+
 /// Registered periodic tasks
 #[derive(Clone)]
 enum ScheduledTask {
@@ -40,46 +143,98 @@ enum ScheduledTask {
     DuplicateCleanup,
     EmbeddingRefresh,
     SkillCleanup,
+    Maintenance,
+    SessionPurge,
 }
 
 impl ScheduledTask {
+    const ALL: [ScheduledTask; 6] = [
+        ScheduledTask::Ranking,
+        ScheduledTask::DuplicateCleanup,
+        ScheduledTask::EmbeddingRefresh,
+        ScheduledTask::SkillCleanup,
+        ScheduledTask::Maintenance,
+        ScheduledTask::SessionPurge,
+    ];
+
+    fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|task| task.name() == name)
+    }
+
     fn name(&self) -> &str {
         match self {
             ScheduledTask::Ranking => "ranking",
             ScheduledTask::DuplicateCleanup => "duplicate_cleanup",
             ScheduledTask::EmbeddingRefresh => "embedding_refresh",
             ScheduledTask::SkillCleanup => "skill_cleanup",
+            ScheduledTask::Maintenance => "maintenance",
+            ScheduledTask::SessionPurge => "session_purge",
         }
     }
 
-    /// The parent AI feature that activates this task.
-    fn parent_feature(&self) -> AiFeature {
+    /// The parent AI feature that activates this task, if any.
+    fn parent_feature(&self) -> Option<AiFeature> {
         match self {
-            ScheduledTask::Ranking => AiFeature::MemoryExtraction,
-            ScheduledTask::DuplicateCleanup => AiFeature::MemoryExtraction,
-            ScheduledTask::EmbeddingRefresh => AiFeature::MemoryExtraction,
-            ScheduledTask::SkillCleanup => AiFeature::SkillsDiscovery,
+            ScheduledTask::Ranking => Some(AiFeature::MemoryExtraction),
+            ScheduledTask::DuplicateCleanup => Some(AiFeature::MemoryExtraction),
+            ScheduledTask::EmbeddingRefresh => Some(AiFeature::MemoryExtraction),
+            ScheduledTask::SkillCleanup => Some(AiFeature::SkillsDiscovery),
+            ScheduledTask::Maintenance | ScheduledTask::SessionPurge => None,
         }
     }
 
-    /// Check if this task's parent feature is active
+    /// Check if this task's parent feature (or its own enabled flag) is active
     fn is_active(&self, config: &Config) -> bool {
-        config.is_feature_active(self.parent_feature())
+        match self.parent_feature() {
+            Some(feature) => config.is_feature_active(feature),
+            None => {
+                let enabled = match self {
+                    ScheduledTask::SessionPurge => config.scheduler.session_purge.enabled,
+                    _ => config.scheduler.maintenance.enabled,
+                };
+                config.storage.is_db() && enabled
+            }
+        }
     }
 
-    fn interval_secs(&self, config: &Config) -> u64 {
-        match self {
-            ScheduledTask::Ranking => config.scheduler.ranking.interval_hours as u64 * 3600,
-            ScheduledTask::DuplicateCleanup => {
-                config.scheduler.duplicate_cleanup.interval_hours as u64 * 3600
-            }
-            ScheduledTask::EmbeddingRefresh => {
-                config.scheduler.embedding_refresh.interval_hours as u64 * 3600
-            }
-            ScheduledTask::SkillCleanup => {
-                config.scheduler.skill_cleanup.interval_hours as u64 * 3600
+    /// The task's `cron` expression if set, otherwise its `interval_hours`
+    fn schedule(&self, config: &Config) -> TaskSchedule {
+        let scheduler = &config.scheduler;
+        let (interval_hours, cron) = match self {
+            ScheduledTask::Ranking => (scheduler.ranking.interval_hours, &scheduler.ranking.cron),
+            ScheduledTask::DuplicateCleanup => (
+                scheduler.duplicate_cleanup.interval_hours,
+                &scheduler.duplicate_cleanup.cron,
+            ),
+            ScheduledTask::EmbeddingRefresh => (
+                scheduler.embedding_refresh.interval_hours,
+                &scheduler.embedding_refresh.cron,
+            ),
+            ScheduledTask::SkillCleanup => (
+                scheduler.skill_cleanup.interval_hours,
+                &scheduler.skill_cleanup.cron,
+            ),
+            ScheduledTask::Maintenance => (
+                scheduler.maintenance.interval_hours,
+                &scheduler.maintenance.cron,
+            ),
+            ScheduledTask::SessionPurge => (
+                scheduler.session_purge.interval_hours,
+                &scheduler.session_purge.cron,
+            ),
+        };
+        if let Some(expr) = cron {
+            match parse_cron(expr) {
+                Ok(schedule) => return TaskSchedule::Cron(Box::new(schedule)),
+                Err(e) => tracing::warn!(
+                    "Scheduler: invalid cron '{}' for task '{}', using interval_hours: {}",
+                    expr,
+                    self.name(),
+                    e
+                ),
             }
         }
+        TaskSchedule::Every(Duration::from_secs(interval_hours as u64 * 3600))
     }
 
     async fn execute(
@@ -99,6 +254,188 @@ impl ScheduledTask {
             ScheduledTask::SkillCleanup => {
                 tasks::skill_cleanup::execute(db, config, event_tx).await
             }
+            ScheduledTask::Maintenance => tasks::db_maintenance::execute(db).await,
+            ScheduledTask::SessionPurge => tasks::session_purge::execute(db, config).await,
+        }
+    }
+
+    /// Execute the task and record the run in `scheduler_runs`
+    async fn execute_and_record(
+        &self,
+        db: Arc<Database>,
+        config: &Config,
+        event_tx: broadcast::Sender<WatcherEvent>,
+    ) -> TaskResult {
+        let started_at = chrono::Utc::now().to_rfc3339();
+        let result = self.execute(db.clone(), config, event_tx).await;
+        if let Err(e) = record_run(
+            &db,
+            &result,
+            started_at,
+            false,
+            config.scheduler.history_retention,
+        )
+        .await
+        {
+            tracing::warn!(
+                "Scheduler: failed to record run of '{}': {}",
+                self.name(),
+                e
+            );
+        }
+        result
+    }
+}
+
+/// Insert a run into `scheduler_runs` and trim the task's history to `retention` rows
+async fn record_run(
+    db: &Database,
+    result: &TaskResult,
+    started_at: String,
+    skipped: bool,
+    retention: usize,
+) -> rusqlite::Result<()> {
+    let finished_at = chrono::Utc::now().to_rfc3339();
+    let task_name = result.task_name.clone();
+    let counts = (
+        result.items_processed as i64,
+        result.items_affected as i64,
+        result.errors as i64,
+    );
+    let detail = result.detail.clone();
+    db.with_conn_result(move |conn| {
+        conn.execute(
+            "INSERT INTO scheduler_runs
+                (task_name, started_at, finished_at, items_processed, items_affected, errors, detail, skipped)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            rusqlite::params![
+                task_name,
+                started_at,
+                finished_at,
+                counts.0,
+                counts.1,
+                counts.2,
+                detail,
+                skipped
+            ],
+        )?;
+        conn.execute(
+            "DELETE FROM scheduler_runs WHERE task_name = ?1 AND id NOT IN (
+                SELECT id FROM scheduler_runs WHERE task_name = ?1 ORDER BY id DESC LIMIT ?2
+             )",
+            rusqlite::params![task_name, retention as i64],
+        )?;
+        Ok(())
+    })
+    .await
+}
+
+/// Why a task couldn't be run on demand
+#[derive(Debug)]
+pub enum RunTaskError {
+    /// No task has this name
+    UnknownTask(String),
+    /// The task's dependencies aren't met by the current config
+    Inactive(String),
+    /// A run of the task (scheduled or on demand) hasn't finished yet
+    AlreadyRunning(String),
+}
+
+impl std::fmt::Display for RunTaskError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RunTaskError::UnknownTask(name) => write!(f, "Unknown scheduler task '{}'", name),
+            RunTaskError::Inactive(reason) => write!(f, "{}", reason),
+            RunTaskError::AlreadyRunning(name) => {
+                write!(f, "Task '{}' is already running", name)
+            }
+        }
+    }
+}
+
+/// Run the named task once, outside its timer.
+///
+/// Uses the same dependency check as `start_scheduler`, so a task that would be
+/// skipped there is rejected here, and the same `running` set, so a task whose
+/// previous run hasn't finished is rejected too.
+pub async fn run_task(
+    name: &str,
+    config: &Config,
+    db: Arc<Database>,
+    event_tx: broadcast::Sender<WatcherEvent>,
+    running: &RunningTasks,
+) -> Result<TaskResult, RunTaskError> {
+    let task = ScheduledTask::from_name(name)
+        .ok_or_else(|| RunTaskError::UnknownTask(name.to_string()))?;
+    if !task.is_active(config) {
+        return Err(RunTaskError::Inactive(match task.parent_feature() {
+            Some(feature) => format!("Task '{}' requires {:?} to be active", name, feature),
+            None => format!("Task '{}' is disabled", name),
+        }));
+    }
+
+    let _guard = running
+        .try_start(task.name())
+        .ok_or_else(|| RunTaskError::AlreadyRunning(name.to_string()))?;
+
+    tracing::info!("Scheduler: running task '{}' on demand", name);
+    Ok(task.execute_and_record(db, config, event_tx).await)
+}
+
+/// Call `run` at each run time of `schedule`, never immediately at startup.
+///
+/// Runs are spawned so a slow run doesn't hold up the ticker, and marked in
+/// `running` so runs never overlap: a tick that arrives while a previous run
+/// (scheduled or on demand) is still active calls `on_skip` instead. Ticks
+/// during maintenance mode are dropped. `now` is the wall clock run times are
+/// computed against.
+#[allow(clippy::too_many_arguments)]
+async fn run_on_schedule<N, R, RFut, S, SFut>(
+    name: String,
+    schedule: TaskSchedule,
+    maintenance: MaintenanceMode,
+    running: RunningTasks,
+    now: N,
+    run: R,
+    on_skip: S,
+) where
+    N: Fn() -> DateTime<Local>,
+    R: Fn() -> RFut,
+    RFut: Future<Output = ()> + Send + 'static,
+    S: Fn() -> SFut,
+    SFut: Future<Output = ()>,
+{
+    let mut last = now();
+
+    loop {
+        // Never schedule before the last run time, in case the sleep woke early
+        let Some(next) = schedule.next_run(&last.max(now())) else {
+            tracing::warn!("Scheduler: task '{}' has no upcoming runs, stopping", name);
+            return;
+        };
+        let wait = (next - now()).to_std().unwrap_or_default();
+        tokio::time::sleep(wait).await;
+        last = next;
+
+        if maintenance.is_enabled() {
+            tracing::info!("Scheduler: task '{}' skipped (maintenance mode)", name);
+            continue;
+        }
+        match running.try_start(&name) {
+            Some(guard) => {
+                let run = run();
+                tokio::spawn(async move {
+                    run.await;
+                    drop(guard);
+                });
+            }
+            None => {
+                tracing::warn!(
+                    "Scheduler: task '{}' skipped (previous run still active)",
+                    name
+                );
+                on_skip().await;
+            }
         }
     }
 }
@@ -108,7 +445,7 @@ impl ScheduledTask {
 /// SQLite's `wal_autocheckpoint` can fail to trigger under high write contention
 /// (single Mutex connection). This safety net runs every 5 minutes to force a
 /// checkpoint, preventing the WAL from growing unbounded.
-fn start_wal_checkpoint_task(db: Arc<Database>) {
+fn start_wal_checkpoint_task(db: Arc<Database>, maintenance: MaintenanceMode) {
     tokio::spawn(async move {
         let interval = Duration::from_secs(300); // 5 minutes
         let mut ticker = tokio::time::interval(interval);
@@ -116,6 +453,9 @@ fn start_wal_checkpoint_task(db: Arc<Database>) {
 
         loop {
             ticker.tick().await;
+            if maintenance.is_enabled() {
+                continue;
+            }
             let result = db
                 .with_conn(|conn| conn.execute("PRAGMA wal_checkpoint(PASSIVE)", []))
                 .await;
@@ -127,84 +467,283 @@ fn start_wal_checkpoint_task(db: Arc<Database>) {
     });
 }
 
-/// Start all enabled periodic tasks.
+/// Start all periodic tasks.
 ///
 /// Each task declares its feature dependencies (AI, memory_extraction, etc.).
-/// Tasks whose dependencies aren't met are skipped individually.
+/// They are checked against the live `config` on every tick, so tasks whose
+/// dependencies aren't met are skipped individually and start running once a
+/// config reload enables them.
 ///
-/// Each enabled task runs in its own tokio::spawn with an independent interval timer.
+/// Each task runs in its own tokio::spawn with an independent interval timer.
 /// Tasks are staggered by 10 seconds to avoid simultaneous DB contention.
+/// Runs are tracked in `running`, shared with on-demand [`run_task`] calls.
 pub fn start_scheduler(
-    config: Config,
+    config: SharedConfig,
     db: Arc<Database>,
     event_tx: broadcast::Sender<WatcherEvent>,
+    maintenance: MaintenanceMode,
+    running: RunningTasks,
 ) {
     // Always run WAL checkpoint regardless of AI settings
-    start_wal_checkpoint_task(db.clone());
+    start_wal_checkpoint_task(db.clone(), maintenance.clone());
 
-    let all_tasks = [
-        ScheduledTask::Ranking,
-        ScheduledTask::DuplicateCleanup,
-        ScheduledTask::EmbeddingRefresh,
-        ScheduledTask::SkillCleanup,
-    ];
-
-    for (idx, task) in all_tasks.into_iter().enumerate() {
+    let startup = config.read().unwrap().clone();
+    for (idx, task) in ScheduledTask::ALL.into_iter().enumerate() {
         // Check if parent AI feature is active (provider set + feature on + db storage)
-        if !task.is_active(&config) {
-            tracing::info!(
-                "Scheduler: task '{}' skipped ({:?} not active)",
-                task.name(),
-                task.parent_feature()
-            );
-            continue;
+        if !task.is_active(&startup) {
+            match task.parent_feature() {
+                Some(feature) => tracing::info!(
+                    "Scheduler: task '{}' idle ({:?} not active)",
+                    task.name(),
+                    feature
+                ),
+                None => tracing::info!("Scheduler: task '{}' idle (disabled)", task.name()),
+            }
         }
 
-        let interval_secs = task.interval_secs(&config);
-        tracing::info!(
-            "Scheduler: starting task '{}' (every {} hours)",
-            task.name(),
-            interval_secs / 3600
-        );
+        let schedule = task.schedule(&startup);
+        tracing::info!("Scheduler: starting task '{}' ({})", task.name(), schedule);
 
         let config = config.clone();
         let db = db.clone();
         let event_tx = event_tx.clone();
+        let maintenance = maintenance.clone();
+        let running = running.clone();
         let stagger = Duration::from_secs(idx as u64 * 10);
 
         tokio::spawn(async move {
             // Stagger start to avoid simultaneous execution
             tokio::time::sleep(stagger).await;
 
-            let interval = Duration::from_secs(interval_secs);
-            let mut ticker = tokio::time::interval(interval);
+            let name = task.name().to_string();
+            let skip_db = db.clone();
+            let skip_config = config.clone();
+            run_on_schedule(
+                name.clone(),
+                schedule,
+                maintenance,
+                running,
+                chrono::Local::now,
+                || {
+                    let task = task.clone();
+                    let db = db.clone();
+                    let config = config.read().unwrap().clone();
+                    let event_tx = event_tx.clone();
+                    async move {
+                        if !task.is_active(&config) {
+                            tracing::debug!(
+                                "Scheduler: task '{}' skipped (not active)",
+                                task.name()
+                            );
+                            return;
+                        }
+                        tracing::info!("Scheduler: running task '{}'", task.name());
+                        let result = task.execute_and_record(db, &config, event_tx).await;
+                        log_result(&result);
+                    }
+                },
+                || {
+                    let db = skip_db.clone();
+                    let retention = skip_config.read().unwrap().scheduler.history_retention;
+                    let result = TaskResult {
+                        task_name: name.clone(),
+                        items_processed: 0,
+                        items_affected: 0,
+                        errors: 0,
+                        detail: "previous run still active".to_string(),
+                    };
+                    async move {
+                        let started_at = chrono::Utc::now().to_rfc3339();
+                        if let Err(e) = record_run(&db, &result, started_at, true, retention).await
+                        {
+                            tracing::warn!(
+                                "Scheduler: failed to record skipped run of '{}': {}",
+                                result.task_name,
+                                e
+                            );
+                        }
+                    }
+                },
+            )
+            .await;
+        });
+    }
+}
+
+fn log_result(result: &TaskResult) {
+    if result.errors > 0 {
+        tracing::warn!(
+            "Scheduler: task '{}' completed with {} errors: {}",
+            result.task_name,
+            result.errors,
+            result.detail
+        );
+    } else if result.items_affected > 0 {
+        tracing::info!(
+            "Scheduler: task '{}' completed: {}",
+            result.task_name,
+            result.detail
+        );
+    } else {
+        tracing::debug!(
+            "Scheduler: task '{}' completed (no changes)",
+            result.task_name
+        );
+    }
+}
 
-            // Skip the first immediate tick (tasks run after the interval, not immediately)
-            ticker.tick().await;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::SubsecRound;
+    use std::sync::atomic::{AtomicUsize, Ordering};
 
-            loop {
-                ticker.tick().await;
-                tracing::info!("Scheduler: running task '{}'", task.name());
-
-                let result = task.execute(db.clone(), &config, event_tx.clone()).await;
-
-                if result.errors > 0 {
-                    tracing::warn!(
-                        "Scheduler: task '{}' completed with {} errors: {}",
-                        task.name(),
-                        result.errors,
-                        result.detail
-                    );
-                } else if result.items_affected > 0 {
-                    tracing::info!(
-                        "Scheduler: task '{}' completed: {}",
-                        task.name(),
-                        result.detail
-                    );
-                } else {
-                    tracing::debug!("Scheduler: task '{}' completed (no changes)", task.name());
-                }
-            }
+    /// A wall clock that follows tokio's clock (so it stops while time is paused),
+    /// starting at `start`
+    fn virtual_clock(start: &str) -> impl Fn() -> DateTime<Local> + Copy {
+        let start = DateTime::parse_from_rfc3339(start)
+            .unwrap()
+            .with_timezone(&Local);
+        let origin = tokio::time::Instant::now();
+        move || start + chrono::Duration::from_std(origin.elapsed()).unwrap()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_overlapping_tick_is_skipped() {
+        let started = Arc::new(AtomicUsize::new(0));
+        let skipped = Arc::new(AtomicUsize::new(0));
+        let running = RunningTasks::new();
+
+        // Ticks at 100ms (starts a 300ms run) and 200ms (run still active)
+        let driver = tokio::spawn({
+            let started = started.clone();
+            let skipped = skipped.clone();
+            run_on_schedule(
+                "slow".to_string(),
+                TaskSchedule::Every(Duration::from_millis(100)),
+                MaintenanceMode::new(),
+                running.clone(),
+                virtual_clock("2024-01-01T12:00:00Z"),
+                move || {
+                    let started = started.clone();
+                    async move {
+                        started.fetch_add(1, Ordering::SeqCst);
+                        tokio::time::sleep(Duration::from_millis(300)).await;
+                    }
+                },
+                move || {
+                    skipped.fetch_add(1, Ordering::SeqCst);
+                    async {}
+                },
+            )
+        });
+        tokio::time::sleep(Duration::from_millis(250)).await;
+
+        assert_eq!(started.load(Ordering::SeqCst), 1);
+        assert_eq!(skipped.load(Ordering::SeqCst), 1);
+        assert!(running.try_start("slow").is_none());
+
+        // The run ends at 400ms, so the 500ms tick starts the next one
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        driver.abort();
+        assert_eq!(started.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_run_task_rejected_while_running() {
+        let tmp = tempfile::tempdir().unwrap();
+        let db = Arc::new(crate::test_support::test_db(tmp.path()));
+        let (event_tx, _) = broadcast::channel(16);
+        let config = Config::default();
+        let running = RunningTasks::new();
+
+        // A scheduled run of the task is in flight
+        let guard = running.try_start("maintenance").unwrap();
+        let err = run_task(
+            "maintenance",
+            &config,
+            db.clone(),
+            event_tx.clone(),
+            &running,
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(err, RunTaskError::AlreadyRunning(_)), "{}", err);
+
+        drop(guard);
+        let result = run_task("maintenance", &config, db, event_tx, &running)
+            .await
+            .unwrap();
+        assert_eq!(result.task_name, "maintenance");
+        assert!(running.try_start("maintenance").is_some());
+    }
+
+    #[test]
+    fn test_cron_next_runs() {
+        let at = |s: &str| {
+            DateTime::parse_from_rfc3339(s)
+                .unwrap()
+                .with_timezone(&chrono::Utc)
+        };
+
+        // Five-field expression: 3am daily
+        let daily = TaskSchedule::Cron(Box::new(parse_cron("0 3 * * *").unwrap()));
+        let first = daily.next_run(&at("2024-01-01T12:00:00Z")).unwrap();
+        assert_eq!(first, at("2024-01-02T03:00:00Z"));
+        assert_eq!(daily.next_run(&first).unwrap(), at("2024-01-03T03:00:00Z"));
+
+        // Six-field expression with seconds
+        let quarter = TaskSchedule::Cron(Box::new(parse_cron("*/15 * * * * *").unwrap()));
+        assert_eq!(
+            quarter.next_run(&at("2024-01-01T12:00:07Z")).unwrap(),
+            at("2024-01-01T12:00:15Z")
+        );
+
+        // Interval mode counts from the given time
+        let every = TaskSchedule::Every(Duration::from_secs(6 * 3600));
+        assert_eq!(
+            every.next_run(&at("2024-01-01T12:00:00Z")).unwrap(),
+            at("2024-01-01T18:00:00Z")
+        );
+
+        assert!(parse_cron("at 3am").is_err());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_cron_task_fires_at_computed_time() {
+        let fired = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let clock = virtual_clock("2024-01-01T12:00:00.250Z");
+
+        // Every second, on the second
+        let driver = tokio::spawn({
+            let fired = fired.clone();
+            run_on_schedule(
+                "cron".to_string(),
+                TaskSchedule::Cron(Box::new(parse_cron("* * * * * *").unwrap())),
+                MaintenanceMode::new(),
+                RunningTasks::new(),
+                clock,
+                move || {
+                    fired.lock().unwrap().push(clock());
+                    async {}
+                },
+                || async {},
+            )
         });
+        tokio::time::sleep(Duration::from_millis(2500)).await;
+        driver.abort();
+
+        // Tokio timers have millisecond resolution
+        let fired: Vec<_> = fired
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|t| t.trunc_subsecs(3))
+            .collect();
+        let at = |s: &str| DateTime::parse_from_rfc3339(s).unwrap();
+        assert_eq!(
+            fired,
+            vec![at("2024-01-01T12:00:01Z"), at("2024-01-01T12:00:02Z")]
+        );
     }
 }
@@ -186,6 +186,7 @@ fn cleanup_project_duplicates(
         )
         .map_err(|e| format!("Failed to remove duplicate {}: {}", id, e))?;
     }
+    db.unindex_memories(&duplicate_ids);
 
     Ok((scanned, duplicate_ids.len()))
 }
@@ -180,7 +180,10 @@ fn refresh_project_embeddings(
                     "INSERT OR REPLACE INTO memory_embeddings (memory_id, embedding) VALUES (?, ?)",
                     rusqlite::params![id, bytes],
                 ) {
-                    Ok(_) => success += 1,
+                    Ok(_) => {
+                        db.index_memory_embedding(*id, &embedding);
+                        success += 1;
+                    }
                     Err(e) => {
                         tracing::warn!("Failed to store embedding for memory {}: {}", id, e);
                         failed += 1;
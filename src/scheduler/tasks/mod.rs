@@ -1,4 +1,6 @@
+pub mod db_maintenance;
 pub mod duplicate_cleanup;
 pub mod embedding_refresh;
 pub mod ranking;
+pub mod session_purge;
 pub mod skill_cleanup;
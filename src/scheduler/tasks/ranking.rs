@@ -19,6 +19,7 @@ pub async fn execute(
     event_tx: broadcast::Sender<WatcherEvent>,
 ) -> TaskResult {
     let batch_size = config.scheduler.ranking.batch_size;
+    let thresholds = ai::RankingConfig::from(&config.scheduler.ranking);
 
     // Get all project IDs
     let db_clone = db.clone();
@@ -58,8 +59,9 @@ pub async fn execute(
 
         let db_clone = db.clone();
         let pid = project_id.clone();
+        let thresholds = thresholds.clone();
         let ranking_future = tokio::task::spawn_blocking(move || {
-            ai::ranking::rank_project_memories(&db_clone, &pid, batch_size)
+            ai::ranking::rank_project_memories(&db_clone, &pid, batch_size, &thresholds)
         });
 
         // Timeout after 60 seconds per project
@@ -0,0 +1,101 @@
+//! Periodic purge of soft-deleted sessions
+//!
+//! `DELETE /api/sessions/:id` only sets `sessions.deleted_at`, so a session
+//! deleted by mistake can be restored. Once `retention_days` have passed the
+//! row is deleted for good, taking its messages, memories, skills, markers and
+//! intents with it (foreign key cascades).
+
+use crate::config::Config;
+use crate::db::Database;
+use crate::scheduler::TaskResult;
+use std::sync::Arc;
+
+pub async fn execute(db: Arc<Database>, config: &Config) -> TaskResult {
+    let retention_days = config.scheduler.session_purge.retention_days;
+    let cutoff = chrono::Utc::now() - chrono::Duration::days(retention_days as i64);
+    let cutoff = cutoff.to_rfc3339();
+
+    let result = db
+        .with_conn(move |conn| {
+            let memory_ids = crate::db::memory_ids_where(
+                conn,
+                "session_id IN (SELECT id FROM sessions
+                    WHERE deleted_at IS NOT NULL AND julianday(deleted_at) < julianday(?1))",
+                &cutoff,
+            )?;
+            let purged = conn.execute(
+                "DELETE FROM sessions
+                 WHERE deleted_at IS NOT NULL AND julianday(deleted_at) < julianday(?1)",
+                [&cutoff],
+            )?;
+            Ok::<_, rusqlite::Error>((purged, memory_ids))
+        })
+        .await;
+
+    match result {
+        Ok((purged, memory_ids)) => {
+            db.unindex_memories(&memory_ids);
+            TaskResult {
+                task_name: "session_purge".to_string(),
+                items_processed: purged,
+                items_affected: purged,
+                errors: 0,
+                detail: format!(
+                    "{} session(s) deleted more than {} days ago purged",
+                    purged, retention_days
+                ),
+            }
+        }
+        Err(e) => TaskResult {
+            task_name: "session_purge".to_string(),
+            items_processed: 0,
+            items_affected: 0,
+            errors: 1,
+            detail: format!("Session purge failed: {}", e),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support;
+
+    #[tokio::test]
+    async fn test_purges_only_sessions_past_retention() {
+        let tmp = tempfile::tempdir().unwrap();
+        let db = Arc::new(Database::new(tmp.path().join("test.db")).unwrap());
+        let recent = (chrono::Utc::now() - chrono::Duration::days(2)).to_rfc3339();
+        let old = (chrono::Utc::now() - chrono::Duration::days(40)).to_rfc3339();
+        db.with_conn(move |conn| {
+            test_support::seed_project(conn);
+            conn.execute_batch(
+                "INSERT INTO sessions (id, project_id, file_path, ai_tool, created_at, indexed_at) VALUES
+                    ('live', 'p1', '/tmp/proj/a.jsonl', 'Claude Code', '2024-01-01T00:00:00Z', '2024-01-01T00:00:00Z'),
+                    ('recent', 'p1', '/tmp/proj/b.jsonl', 'Claude Code', '2024-01-01T00:00:00Z', '2024-01-01T00:00:00Z'),
+                    ('old', 'p1', '/tmp/proj/c.jsonl', 'Claude Code', '2024-01-01T00:00:00Z', '2024-01-01T00:00:00Z');",
+            )?;
+            conn.execute(
+                "UPDATE sessions SET deleted_at = ?1 WHERE id = 'recent'",
+                [&recent],
+            )?;
+            conn.execute("UPDATE sessions SET deleted_at = ?1 WHERE id = 'old'", [&old])
+        })
+        .await
+        .unwrap();
+
+        let result = execute(db.clone(), &Config::default()).await;
+        assert_eq!(result.errors, 0, "{}", result.detail);
+        assert_eq!(result.items_affected, 1);
+
+        let remaining: Vec<String> = db
+            .with_conn(|conn| {
+                let mut stmt = conn.prepare("SELECT id FROM sessions ORDER BY id")?;
+                let rows = stmt.query_map([], |row| row.get(0))?;
+                rows.collect::<rusqlite::Result<_>>()
+            })
+            .await
+            .unwrap();
+        assert_eq!(remaining, vec!["live", "recent"]);
+    }
+}
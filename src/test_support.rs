@@ -0,0 +1,56 @@
+//! Fixtures shared by unit tests
+
+use crate::ai::AiTaskQueue;
+use crate::api::AppState;
+use crate::config::Storage;
+use crate::db::Database;
+use rusqlite::Connection;
+use std::path::Path;
+use std::sync::Arc;
+
+/// A fresh database at `dir/test.db`
+pub fn test_db(dir: &Path) -> Database {
+    Database::new(dir.join("test.db")).unwrap()
+}
+
+/// Database-backed API state over `dir/test.db`, reading config from `dir/config.toml`
+pub fn app_state(dir: &Path) -> AppState {
+    let (event_tx, _) = tokio::sync::broadcast::channel(16);
+    let (ai_event_tx, _) = tokio::sync::broadcast::channel(16);
+    AppState {
+        db: Some(Arc::new(test_db(dir))),
+        ephemeral: None,
+        storage: Storage::Db,
+        api_keys: Vec::new(),
+        event_tx,
+        ai_event_tx,
+        ai_task_queue: AiTaskQueue::new(1),
+        config_path: dir.join("config.toml"),
+        maintenance: crate::maintenance::MaintenanceMode::new(),
+        running_tasks: crate::scheduler::RunningTasks::new(),
+        shutdown: tokio_util::sync::CancellationToken::new(),
+        event_log: crate::api::sse::EventLog::new(16),
+        config_reload: None,
+    }
+}
+
+/// Insert project `p1` (folder `/tmp/proj`) unless it exists
+pub fn seed_project(conn: &Connection) {
+    conn.execute(
+        "INSERT OR IGNORE INTO projects (id, name, folder_path, created_at, updated_at)
+         VALUES ('p1', 'proj', '/tmp/proj', '2024-01-01T00:00:00Z', '2024-01-01T00:00:00Z')",
+        [],
+    )
+    .unwrap();
+}
+
+/// Insert session `id` (file `/tmp/proj/<id>.jsonl`, created 2024-01-01) in project `p1`
+pub fn seed_session(conn: &Connection, id: &str) {
+    seed_project(conn);
+    conn.execute(
+        "INSERT INTO sessions (id, project_id, file_path, ai_tool, created_at, indexed_at)
+         VALUES (?1, 'p1', ?2, 'Claude Code', '2024-01-01T00:00:00Z', '2024-01-01T00:00:00Z')",
+        rusqlite::params![id, format!("/tmp/proj/{}.jsonl", id)],
+    )
+    .unwrap();
+}
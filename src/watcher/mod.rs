@@ -9,23 +9,26 @@ pub mod store;
 use crate::ai::auto_trigger::AiAutoTrigger;
 use crate::ai::types::AiEvent;
 use crate::ai::AiTaskQueue;
-use crate::config::Config;
+use crate::config::{Config, WatchConfig};
 use crate::db::Database;
-use crate::error::Result;
-use crate::parser::get_parser;
+use crate::error::{CoreError, Result};
+use crate::maintenance::MaintenanceMode;
+use crate::parser::git::GitContext;
+use crate::parser::ParserRegistry;
+use globset::{Glob, GlobSet, GlobSetBuilder};
 use notify::RecursiveMode;
 use notify_debouncer_mini::{new_debouncer, DebouncedEventKind};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::Duration;
 use store::SessionStore;
-use tokio::sync::{broadcast, mpsc};
+use tokio::sync::{broadcast, mpsc, oneshot};
 
 /// Events emitted by the file watcher and other core services
 #[derive(Debug, Clone)]
 pub enum WatcherEvent {
-    /// New session file detected
+    /// New session file stored, under the project (`projects.id`) it belongs to
     NewSession {
         project_id: String,
         file_path: String,
@@ -38,11 +41,19 @@ pub enum WatcherEvent {
         previous_size: u64,
         new_size: u64,
     },
+    /// Progress through a large session file's full parse
+    ParseProgress {
+        session_id: String,
+        bytes_done: u64,
+        bytes_total: u64,
+    },
     /// Session parsing completed
     SessionParsed {
         session_id: String,
         message_count: usize,
     },
+    /// Tracked session file was deleted or renamed away
+    SessionRemoved { session_id: String },
     /// Error during processing
     Error { file_path: String, error: String },
     /// Memory ranking started
@@ -79,6 +90,15 @@ pub enum WatcherEvent {
 pub struct WatcherHandle {
     /// Shutdown signal sender
     shutdown_tx: mpsc::Sender<()>,
+    /// Replaces the watched paths; replies with what was added and removed
+    paths_tx: mpsc::Sender<(Vec<WatchConfig>, oneshot::Sender<WatchPathChanges>)>,
+}
+
+/// Directories added and removed by [`WatcherHandle::set_watch_paths`]
+#[derive(Debug, Default)]
+pub struct WatchPathChanges {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
 }
 
 impl WatcherHandle {
@@ -87,12 +107,82 @@ impl WatcherHandle {
         let _ = self.shutdown_tx.send(()).await;
         Ok(())
     }
+
+    /// Watch exactly `paths` from now on, starting and stopping directory
+    /// watches as needed. Settings of paths that stay watched are updated in place.
+    pub async fn set_watch_paths(&self, paths: Vec<WatchConfig>) -> Result<WatchPathChanges> {
+        let (reply_tx, reply_rx) = oneshot::channel();
+        self.paths_tx
+            .send((paths, reply_tx))
+            .await
+            .map_err(|_| CoreError::Watcher("File watcher is not running".into()))?;
+        reply_rx
+            .await
+            .map_err(|_| CoreError::Watcher("File watcher is not running".into()))
+    }
+}
+
+/// Compiled include/exclude globs for a watch path.
+///
+/// Patterns match against the file path relative to the watched folder.
+#[derive(Debug, Default)]
+pub struct PathFilter {
+    include: Option<GlobSet>,
+    exclude: Option<GlobSet>,
+}
+
+impl PathFilter {
+    /// Compile include/exclude patterns. Empty lists impose no restriction.
+    pub fn new(include: &[String], exclude: &[String]) -> std::result::Result<Self, String> {
+        Ok(PathFilter {
+            include: compile_globs(include)?,
+            exclude: compile_globs(exclude)?,
+        })
+    }
+
+    /// Whether a path relative to the watched folder should be tracked
+    pub fn matches(&self, relative: &Path) -> bool {
+        if let Some(exclude) = &self.exclude {
+            if exclude.is_match(relative) {
+                return false;
+            }
+        }
+        match &self.include {
+            Some(include) => include.is_match(relative),
+            None => true,
+        }
+    }
+}
+
+fn compile_globs(patterns: &[String]) -> std::result::Result<Option<GlobSet>, String> {
+    if patterns.is_empty() {
+        return Ok(None);
+    }
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let glob = Glob::new(pattern).map_err(|e| format!("Invalid glob '{}': {}", pattern, e))?;
+        builder.add(glob);
+    }
+    builder
+        .build()
+        .map(Some)
+        .map_err(|e| format!("Failed to compile globs: {}", e))
 }
 
 /// A watched directory configuration
 struct WatchedDirectory {
     folder_path: PathBuf,
     parser_type: String,
+    /// Delete the stored session when its file disappears
+    delete_on_remove: bool,
+    /// Store session IDs as `{project_id}:{file_stem}`
+    namespace_session_ids: bool,
+    /// Index sub-agent transcripts and link them to their parent session
+    parse_agents: bool,
+    /// Skip new files identical to an already indexed session
+    dedupe_by_hash: bool,
+    /// Include/exclude globs compiled from the watch entry
+    filter: PathFilter,
 }
 
 /// Internal watcher state
@@ -101,6 +191,10 @@ struct WatcherState {
     watched: HashMap<String, WatchedDirectory>,
     /// Storage backend (DB or ephemeral)
     store: Arc<SessionStore>,
+    /// Parsers by name, for each watch path's `parser`
+    parsers: Arc<ParserRegistry>,
+    /// Estimate output tokens for assistant events without usage (`parser.estimate_tokens`)
+    estimate_tokens: bool,
     /// Broadcast event sender (for SSE)
     event_tx: broadcast::Sender<WatcherEvent>,
     /// AI auto-trigger (None in ephemeral mode — no DB for AI tasks)
@@ -111,44 +205,38 @@ struct WatcherState {
     ai_event_tx: broadcast::Sender<AiEvent>,
     /// AI task queue (for ephemeral title concurrency)
     ai_task_queue: AiTaskQueue,
+    /// Parsing is deferred while enabled
+    maintenance: MaintenanceMode,
+    /// Paths that changed during maintenance, replayed on resume
+    deferred: std::sync::Mutex<HashSet<PathBuf>>,
 }
 
 /// Start watching configured paths for session files
+#[allow(clippy::too_many_arguments)]
 pub async fn start_watcher(
     config: &Config,
     config_path: PathBuf,
     store: Arc<SessionStore>,
+    parsers: Arc<ParserRegistry>,
     db: Option<Arc<Database>>,
     event_tx: broadcast::Sender<WatcherEvent>,
     ai_event_tx: broadcast::Sender<AiEvent>,
     ai_task_queue: AiTaskQueue,
+    maintenance: MaintenanceMode,
 ) -> Result<WatcherHandle> {
     let (shutdown_tx, mut shutdown_rx) = mpsc::channel::<()>(1);
+    let (paths_tx, mut paths_rx) = mpsc::channel(4);
 
     let watch_paths = config.watch_paths();
 
     if watch_paths.is_empty() {
         tracing::info!("No project paths configured, file watcher idle");
-        return Ok(WatcherHandle { shutdown_tx });
     }
 
     // Initialize watched directories (no pre-scan — DB stores file positions)
-    let mut watched = HashMap::new();
-    for (path, parser_type) in watch_paths.iter() {
-        if !path.exists() || !path.is_dir() {
-            tracing::warn!("Watch path does not exist: {}", path.display());
-            continue;
-        }
-
-        tracing::info!("Watching {}: {}", parser_type, path.display());
-
-        watched.insert(
-            path.to_string_lossy().to_string(),
-            WatchedDirectory {
-                folder_path: path.clone(),
-                parser_type: parser_type.clone(),
-            },
-        );
+    let watched = watched_directories(watch_paths);
+    for dir in watched.values() {
+        namespace_existing_sessions(&store, dir).await;
     }
 
     // Clone before potential move into AiAutoTrigger
@@ -156,12 +244,7 @@ pub async fn start_watcher(
     let ai_event_tx_for_state = ai_event_tx.clone();
     let ai_task_queue_for_state = ai_task_queue.clone();
 
-    let provider = config
-        .ai
-        .provider
-        .as_deref()
-        .and_then(crate::ai::cli::CliProvider::from_config_str)
-        .unwrap_or(crate::ai::cli::CliProvider::ClaudeCode);
+    let providers = crate::ai::cli::CliProvider::chain_from_ai_config(&config.ai);
 
     let ai_trigger = db.map(|db| {
         Arc::new(tokio::sync::Mutex::new(AiAutoTrigger::new(
@@ -169,20 +252,34 @@ pub async fn start_watcher(
             db,
             ai_event_tx,
             ai_task_queue,
-            provider,
+            providers,
         )))
     });
 
     let state = Arc::new(tokio::sync::RwLock::new(WatcherState {
         watched,
         store,
+        parsers,
+        estimate_tokens: config.parser.estimate_tokens,
         event_tx,
         ai_trigger: ai_trigger.clone(),
         config_path: config_path_for_state,
         ai_event_tx: ai_event_tx_for_state,
         ai_task_queue: ai_task_queue_for_state,
+        maintenance: maintenance.clone(),
+        deferred: std::sync::Mutex::new(HashSet::new()),
     }));
 
+    // Replay files that changed while maintenance mode was on
+    let state_for_resume = Arc::clone(&state);
+    tokio::spawn(async move {
+        loop {
+            maintenance.wait_for(true).await;
+            maintenance.wait_for(false).await;
+            replay_deferred(&state_for_resume).await;
+        }
+    });
+
     // Create a channel to send events from notify thread to tokio runtime
     let (notify_tx, mut notify_rx) = mpsc::channel::<PathBuf>(100);
 
@@ -221,7 +318,10 @@ pub async fn start_watcher(
         Ok(d) => d,
         Err(e) => {
             tracing::error!("Failed to create file watcher: {}", e);
-            return Ok(WatcherHandle { shutdown_tx });
+            return Ok(WatcherHandle {
+                shutdown_tx,
+                paths_tx,
+            });
         }
     };
 
@@ -244,46 +344,155 @@ pub async fn start_watcher(
 
     tracing::info!("File watcher started");
 
-    // Spawn shutdown handler
+    // Owns the debouncer: applies watch path changes until shutdown
     tokio::spawn(async move {
-        let _ = shutdown_rx.recv().await;
+        loop {
+            tokio::select! {
+                _ = shutdown_rx.recv() => break,
+                Some((paths, reply)) = paths_rx.recv() => {
+                    let mut state_guard = state.write().await;
+                    let wanted = watched_directories(paths);
+                    let mut changes = WatchPathChanges::default();
+
+                    let removed: Vec<String> = state_guard
+                        .watched
+                        .keys()
+                        .filter(|key| !wanted.contains_key(*key))
+                        .cloned()
+                        .collect();
+                    for key in removed {
+                        if let Some(dir) = state_guard.watched.remove(&key) {
+                            if let Err(e) = debouncer.watcher().unwatch(&dir.folder_path) {
+                                tracing::warn!(
+                                    "Failed to unwatch directory {}: {}",
+                                    dir.folder_path.display(),
+                                    e
+                                );
+                            }
+                            tracing::info!("Stopped watching {}", dir.folder_path.display());
+                            changes.removed.push(key);
+                        }
+                    }
+
+                    for (key, dir) in wanted {
+                        if !state_guard.watched.contains_key(&key) {
+                            if let Err(e) = debouncer
+                                .watcher()
+                                .watch(&dir.folder_path, RecursiveMode::Recursive)
+                            {
+                                tracing::error!(
+                                    "Failed to watch directory {}: {}",
+                                    dir.folder_path.display(),
+                                    e
+                                );
+                                continue;
+                            }
+                            namespace_existing_sessions(&state_guard.store, &dir).await;
+                            changes.added.push(key.clone());
+                        }
+                        state_guard.watched.insert(key, dir);
+                    }
+
+                    let _ = reply.send(changes);
+                }
+            }
+        }
         // Debouncer will be dropped when this task ends
         drop(debouncer);
         tracing::info!("File watcher stopped");
     });
 
-    Ok(WatcherHandle { shutdown_tx })
+    Ok(WatcherHandle {
+        shutdown_tx,
+        paths_tx,
+    })
 }
 
-/// Check if a file is a main session file (not an agent file)
-fn is_session_file(path: &Path) -> bool {
-    let extension = path.extension().and_then(|e| e.to_str());
-    let file_name = path.file_name().and_then(|n| n.to_str());
+/// Build the watched directory table from watch entries, skipping missing
+/// directories and entries with invalid globs
+fn watched_directories(watch_paths: Vec<WatchConfig>) -> HashMap<String, WatchedDirectory> {
+    let mut watched = HashMap::new();
+    for watch in watch_paths {
+        let path = watch.path;
+        if !path.exists() || !path.is_dir() {
+            tracing::warn!("Watch path does not exist: {}", path.display());
+            continue;
+        }
 
-    // Must have .jsonl extension
-    if extension != Some("jsonl") {
-        return false;
+        let filter = match PathFilter::new(&watch.include, &watch.exclude) {
+            Ok(f) => f,
+            Err(e) => {
+                tracing::error!("Skipping watch path {}: {}", path.display(), e);
+                continue;
+            }
+        };
+
+        tracing::info!("Watching {}: {}", watch.parser, path.display());
+
+        watched.insert(
+            path.to_string_lossy().to_string(),
+            WatchedDirectory {
+                folder_path: path,
+                parser_type: watch.parser,
+                delete_on_remove: watch.delete_on_remove,
+                namespace_session_ids: watch.namespace_session_ids,
+                parse_agents: watch.parse_agents,
+                dedupe_by_hash: watch.dedupe_by_hash,
+                filter,
+            },
+        );
     }
+    watched
+}
 
-    // Skip agent files
-    if let Some(name) = file_name {
-        if name.starts_with("agent-") || name.contains("-agent-") {
-            return false;
-        }
+/// Migrate existing bare session IDs for a path that opted into namespacing
+async fn namespace_existing_sessions(store: &SessionStore, dir: &WatchedDirectory) {
+    let SessionStore::Db(db) = store else {
+        return;
+    };
+    if !dir.namespace_session_ids {
+        return;
+    }
+    match storage::db_namespace_session_ids(db, &dir.folder_path).await {
+        Ok(0) => {}
+        Ok(n) => tracing::info!(
+            "Namespaced {} session ids under {}",
+            n,
+            dir.folder_path.display()
+        ),
+        Err(e) => tracing::error!("{}", e),
     }
+}
 
-    true
+/// Check if a file is a session file: `.jsonl`, or `.jsonl.gz` for rotated sessions
+fn is_session_file(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|n| n.to_str())
+        .is_some_and(|name| name.ends_with(".jsonl") || name.ends_with(".jsonl.gz"))
+}
+
+/// File name without the `.jsonl` / `.jsonl.gz` extension
+fn session_file_stem(path: &Path) -> Option<&str> {
+    let name = path.file_name()?.to_str()?;
+    name.strip_suffix(".jsonl.gz")
+        .or_else(|| name.strip_suffix(".jsonl"))
+}
+
+/// Check if a file is a sub-agent transcript rather than a main session file
+fn is_agent_file(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|n| n.to_str())
+        .is_some_and(|name| name.starts_with("agent-") || name.contains("-agent-"))
 }
 
 /// Handle a file system event
 async fn handle_file_event(state: &Arc<tokio::sync::RwLock<WatcherState>>, path: &Path) {
-    // Must be a .jsonl file
-    if path.extension().and_then(|e| e.to_str()) != Some("jsonl") {
+    if !is_session_file(path) {
         return;
     }
 
-    // Skip agent files
-    if !is_session_file(path) {
+    if defer_if_maintenance(state, path).await {
+        tracing::debug!("Maintenance mode, deferring: {}", path.display());
         return;
     }
 
@@ -291,7 +500,7 @@ async fn handle_file_event(state: &Arc<tokio::sync::RwLock<WatcherState>>, path:
 
     let path_str = path.to_string_lossy().to_string();
 
-    let file_stem = match path.file_stem().and_then(|s| s.to_str()) {
+    let file_stem = match session_file_stem(path) {
         Some(stem) => stem.to_string(),
         None => return,
     };
@@ -301,7 +510,12 @@ async fn handle_file_event(state: &Arc<tokio::sync::RwLock<WatcherState>>, path:
     let new_size =
         match tokio::task::spawn_blocking(move || std::fs::metadata(&path_for_stat)).await {
             Ok(Ok(m)) => m.len(),
-            _ => return, // File might have been deleted
+            Ok(Err(e)) if e.kind() == std::io::ErrorKind::NotFound => {
+                // Deleted, or the old side of a rename (the new name arrives as its own event)
+                handle_file_removed(state, path).await;
+                return;
+            }
+            _ => return,
         };
 
     // Read lock only — no mutation needed
@@ -317,8 +531,23 @@ async fn handle_file_event(state: &Arc<tokio::sync::RwLock<WatcherState>>, path:
         None => return,
     };
 
+    // Agent files are only indexed when the watch path opts in
+    if is_agent_file(path) && !watched_dir.parse_agents {
+        return;
+    }
+
+    let relative = path.strip_prefix(&watched_dir.folder_path).unwrap_or(path);
+    if !watched_dir.filter.matches(relative) {
+        tracing::debug!("Ignoring filtered file: {}", path.display());
+        return;
+    }
+
     let parser_type = watched_dir.parser_type.clone();
+    let namespace_session_ids = watched_dir.namespace_session_ids;
+    let dedupe_by_hash = watched_dir.dedupe_by_hash;
     let store = Arc::clone(&state_guard.store);
+    let parsers = Arc::clone(&state_guard.parsers);
+    let estimate_tokens = state_guard.estimate_tokens;
     let event_tx = state_guard.event_tx.clone();
     let ai_trigger = state_guard.ai_trigger.clone();
     let config_path = state_guard.config_path.clone();
@@ -328,11 +557,16 @@ async fn handle_file_event(state: &Arc<tokio::sync::RwLock<WatcherState>>, path:
     // Drop read lock before store queries and parsing
     drop(state_guard);
 
+    let session_id = store
+        .resolve_session_id(&path_str, &file_stem, &parser_type, namespace_session_ids)
+        .await;
+
     // Query store for this session's last known state
-    let session_state = store.get_session_state(&file_stem).await;
+    let session_state = store.get_session_state(&session_id).await;
     let db_file_size = session_state.file_size;
     let db_message_count = session_state.message_count;
     let db_max_sequence = session_state.max_sequence;
+    let git_context = session_state.git_context;
 
     if new_size == db_file_size as u64 {
         return; // No change
@@ -341,7 +575,7 @@ async fn handle_file_event(state: &Arc<tokio::sync::RwLock<WatcherState>>, path:
     // Emit SessionChanged for existing sessions that grew
     if db_file_size > 0 && new_size > db_file_size as u64 {
         let _ = event_tx.send(WatcherEvent::SessionChanged {
-            session_id: file_stem.clone(),
+            session_id: session_id.clone(),
             file_path: path_str.clone(),
             previous_size: db_file_size as u64,
             new_size,
@@ -349,33 +583,60 @@ async fn handle_file_event(state: &Arc<tokio::sync::RwLock<WatcherState>>, path:
     }
 
     // Choose parse strategy and execute
+    let compressed = crate::parser::file::is_compressed(path);
     let message_count = if new_size < db_file_size as u64 {
         // File was truncated — full re-parse
-        tracing::info!("File truncated for {}, full re-parse", file_stem);
-        full_parse(&store, &event_tx, &path_str, &file_stem, &parser_type).await
-    } else if db_file_size > 0 && db_message_count > 0 {
+        tracing::info!("File truncated for {}, full re-parse", session_id);
+        full_parse(
+            &store,
+            &parsers,
+            &event_tx,
+            &path_str,
+            &session_id,
+            &parser_type,
+            estimate_tokens,
+            false,
+            false,
+        )
+        .await
+    } else if db_file_size > 0 && db_message_count > 0 && !compressed {
         // Existing session with data — incremental parse (delta only)
         incremental_parse(
             &store,
+            &parsers,
             &event_tx,
             &path_str,
-            &file_stem,
+            &session_id,
             &parser_type,
+            estimate_tokens,
             db_file_size,
             db_message_count,
             db_max_sequence,
+            git_context,
+            dedupe_by_hash,
         )
         .await
     } else {
-        // New session or empty — full parse
-        full_parse(&store, &event_tx, &path_str, &file_stem, &parser_type).await
+        // New session, empty, or compressed (gzip can't resume mid-stream) — full parse
+        full_parse(
+            &store,
+            &parsers,
+            &event_tx,
+            &path_str,
+            &session_id,
+            &parser_type,
+            estimate_tokens,
+            db_file_size == 0,
+            dedupe_by_hash && db_file_size == 0,
+        )
+        .await
     };
 
     if let (Some(count), Some(trigger)) = (message_count, &ai_trigger) {
         trigger
             .lock()
             .await
-            .on_session_parsed(&file_stem, count)
+            .on_session_parsed(&session_id, count)
             .await;
     }
 
@@ -385,7 +646,7 @@ async fn handle_file_event(state: &Arc<tokio::sync::RwLock<WatcherState>>, path:
             if let SessionStore::Ephemeral(idx) = store.as_ref() {
                 maybe_trigger_ephemeral_title(
                     idx,
-                    &file_stem,
+                    &session_id,
                     &config_path,
                     &ai_event_tx,
                     &ai_task_queue,
@@ -396,6 +657,77 @@ async fn handle_file_event(state: &Arc<tokio::sync::RwLock<WatcherState>>, path:
     }
 }
 
+/// Record `path` for later if maintenance mode is on. Returns true if deferred.
+///
+/// The flag is checked under the `deferred` lock so a concurrent resume either
+/// sees this path in its replay or this call sees maintenance already off.
+async fn defer_if_maintenance(state: &Arc<tokio::sync::RwLock<WatcherState>>, path: &Path) -> bool {
+    let state_guard = state.read().await;
+    let mut deferred = state_guard.deferred.lock().unwrap();
+    if !state_guard.maintenance.is_enabled() {
+        return false;
+    }
+    deferred.insert(path.to_path_buf());
+    true
+}
+
+/// Process every file event deferred during maintenance mode
+async fn replay_deferred(state: &Arc<tokio::sync::RwLock<WatcherState>>) {
+    let paths: Vec<PathBuf> = {
+        let state_guard = state.read().await;
+        let mut deferred = state_guard.deferred.lock().unwrap();
+        deferred.drain().collect()
+    };
+    if !paths.is_empty() {
+        tracing::info!(
+            "Maintenance ended, processing {} deferred file(s)",
+            paths.len()
+        );
+    }
+    for path in paths {
+        handle_file_event(state, &path).await;
+    }
+}
+
+/// Handle a tracked session file disappearing from disk.
+///
+/// Emits `SessionRemoved` if the session is known to the store, and deletes it
+/// when the watch path has `delete_on_remove` set.
+async fn handle_file_removed(state: &Arc<tokio::sync::RwLock<WatcherState>>, path: &Path) {
+    let state_guard = state.read().await;
+    let delete_on_remove = match state_guard
+        .watched
+        .values()
+        .find(|d| path.starts_with(&d.folder_path))
+    {
+        Some(d) => d.delete_on_remove,
+        None => return,
+    };
+    let store = Arc::clone(&state_guard.store);
+    let event_tx = state_guard.event_tx.clone();
+    drop(state_guard);
+
+    let session_id = match store.find_session_by_path(&path.to_string_lossy()).await {
+        Some(id) => id,
+        None => return, // Never tracked
+    };
+
+    tracing::info!("Session file removed: {}", path.display());
+
+    if delete_on_remove {
+        if let Err(e) = store.remove_session(&session_id).await {
+            tracing::error!("Failed to delete removed session {}: {}", session_id, e);
+            let _ = event_tx.send(WatcherEvent::Error {
+                file_path: path.to_string_lossy().to_string(),
+                error: format!("Failed to delete session: {}", e),
+            });
+            return;
+        }
+    }
+
+    let _ = event_tx.send(WatcherEvent::SessionRemoved { session_id });
+}
+
 /// Trigger title generation for an ephemeral session if conditions are met.
 async fn maybe_trigger_ephemeral_title(
     idx: &Arc<crate::ephemeral::EphemeralIndex>,
@@ -446,12 +778,8 @@ async fn maybe_trigger_ephemeral_title(
     // Mark as generated before spawning to prevent duplicate triggers
     idx.set_title_generated(session_id);
 
-    let provider = config
-        .ai
-        .provider
-        .as_deref()
-        .and_then(crate::ai::cli::CliProvider::from_config_str)
-        .unwrap_or(crate::ai::cli::CliProvider::ClaudeCode);
+    let providers = crate::ai::cli::CliProvider::chain_from_ai_config(&config.ai);
+    let ai = config.ai.clone();
 
     let idx = idx.clone();
     let sid = session_id.to_string();
@@ -463,8 +791,10 @@ async fn maybe_trigger_ephemeral_title(
             session_id: sid.clone(),
         });
 
-        let result =
-            crate::ai::title::generate_title_from_text(&sid, &first_messages, None, provider).await;
+        let result = crate::ai::cli::with_fallback("Title generation", &providers, |provider| {
+            crate::ai::title::generate_title_from_text(&sid, &first_messages, None, provider, &ai)
+        })
+        .await;
 
         if let Some(ref title) = result.title {
             idx.update_session(&sid, Some(title.clone()), None);
@@ -473,6 +803,7 @@ async fn maybe_trigger_ephemeral_title(
             let _ = tx.send(AiEvent::TitleComplete {
                 session_id: sid,
                 title: title.clone(),
+                provider: crate::ai::cli::provider_name(result.provider),
             });
         } else if let Some(error) = result.error {
             tracing::warn!("Ephemeral title failed for {}: {}", &sid[..8], error);
@@ -484,40 +815,124 @@ async fn maybe_trigger_ephemeral_title(
     });
 }
 
+/// Files smaller than this are parsed without progress events
+const PARSE_PROGRESS_MIN_BYTES: u64 = 1024 * 1024;
+
+/// Most progress events sent for one parse
+const PARSE_PROGRESS_STEPS: u64 = 20;
+
+/// Line iterator that sends `ParseProgress` each time another
+/// 1/`PARSE_PROGRESS_STEPS` of the file has been consumed
+struct ProgressLines<'a, I> {
+    lines: I,
+    event_tx: &'a broadcast::Sender<WatcherEvent>,
+    session_id: &'a str,
+    bytes_done: u64,
+    bytes_total: u64,
+    next_report: u64,
+}
+
+impl<'a, I> ProgressLines<'a, I> {
+    fn new(
+        lines: I,
+        bytes_total: u64,
+        event_tx: &'a broadcast::Sender<WatcherEvent>,
+        session_id: &'a str,
+    ) -> Self {
+        let next_report = if bytes_total < PARSE_PROGRESS_MIN_BYTES {
+            u64::MAX
+        } else {
+            bytes_total / PARSE_PROGRESS_STEPS
+        };
+        ProgressLines {
+            lines,
+            event_tx,
+            session_id,
+            bytes_done: 0,
+            bytes_total,
+            next_report,
+        }
+    }
+}
+
+impl<'l, I: Iterator<Item = &'l str>> Iterator for ProgressLines<'_, I> {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        let line = self.lines.next()?;
+        // Count the newline the line iterator strips
+        self.bytes_done = (self.bytes_done + line.len() as u64 + 1).min(self.bytes_total);
+        // The final `SessionParsed` stands in for 100%
+        if self.bytes_done >= self.next_report && self.bytes_done < self.bytes_total {
+            let _ = self.event_tx.send(WatcherEvent::ParseProgress {
+                session_id: self.session_id.to_string(),
+                bytes_done: self.bytes_done,
+                bytes_total: self.bytes_total,
+            });
+            let step = self.bytes_total / PARSE_PROGRESS_STEPS;
+            self.next_report = (self.bytes_done / step + 1) * step;
+        }
+        Some(line.to_string())
+    }
+}
+
 /// Read and parse a full session file, then store via SessionStore.
-/// Returns Some(message_count) on success, None on failure.
+/// A `new_session` that gets stored is announced with `NewSession`.
+/// With `skip_duplicate`, a file whose content matches another stored session
+/// is logged and not indexed.
+/// Returns Some(message_count) on success, None on failure or when skipped.
+#[allow(clippy::too_many_arguments)]
 async fn full_parse(
     store: &SessionStore,
+    parsers: &ParserRegistry,
     event_tx: &broadcast::Sender<WatcherEvent>,
     file_path: &str,
     session_id: &str,
     parser_type: &str,
+    estimate_tokens: bool,
+    new_session: bool,
+    skip_duplicate: bool,
 ) -> Option<usize> {
     let file_path_owned = file_path.to_string();
 
     // Read file content
     let path_for_read = PathBuf::from(file_path);
-    let content =
-        match tokio::task::spawn_blocking(move || std::fs::read_to_string(&path_for_read)).await {
-            Ok(Ok(c)) => c,
-            Ok(Err(e)) => {
-                let _ = event_tx.send(WatcherEvent::Error {
-                    file_path: file_path_owned,
-                    error: format!("Failed to read file: {}", e),
-                });
-                return None;
-            }
-            Err(_) => {
-                let _ = event_tx.send(WatcherEvent::Error {
-                    file_path: file_path_owned,
-                    error: "spawn_blocking task panicked".to_string(),
-                });
-                return None;
-            }
-        };
+    let content = match tokio::task::spawn_blocking(move || {
+        crate::parser::file::read_to_string(&path_for_read)
+    })
+    .await
+    {
+        Ok(Ok(c)) => c,
+        Ok(Err(e)) => {
+            let _ = event_tx.send(WatcherEvent::Error {
+                file_path: file_path_owned,
+                error: format!("Failed to read file: {}", e),
+            });
+            return None;
+        }
+        Err(_) => {
+            let _ = event_tx.send(WatcherEvent::Error {
+                file_path: file_path_owned,
+                error: "spawn_blocking task panicked".to_string(),
+            });
+            return None;
+        }
+    };
+
+    let content_hash = storage::content_hash(content.as_bytes());
+    if skip_duplicate {
+        if let Some(original) = store.find_session_by_hash(&content_hash, session_id).await {
+            tracing::info!(
+                "Skipping {}: identical to already indexed session {}",
+                file_path,
+                original
+            );
+            return None;
+        }
+    }
 
     // Parse
-    let parser = match get_parser(parser_type) {
+    let parser = match parsers.get(parser_type) {
         Some(p) => p,
         None => {
             tracing::warn!("Unknown parser type: {}", parser_type);
@@ -525,26 +940,43 @@ async fn full_parse(
         }
     };
 
-    let lines: Vec<String> = content.lines().map(|l| l.to_string()).collect();
-    let result = parser.parse(&lines);
+    let mut lines = ProgressLines::new(content.lines(), content.len() as u64, event_tx, session_id);
+    let mut result = parser.parse_stream(&mut lines);
+    if estimate_tokens {
+        crate::parser::common::estimate_missing_tokens(&mut result);
+    }
     let message_count = result.events.len();
 
     tracing::info!("Parsed session {}: {} messages", session_id, message_count);
 
     // Store via SessionStore
     match store
-        .store_full_parse(file_path, session_id, parser_type, &result)
+        .store_full_parse(file_path, session_id, parser_type, &result, &content_hash)
         .await
     {
-        Ok(true) => {
+        Ok(Some(project_id)) => {
+            if new_session {
+                let path = Path::new(file_path);
+                let _ = event_tx.send(WatcherEvent::NewSession {
+                    project_id,
+                    file_path: file_path.to_string(),
+                    file_name: path
+                        .file_name()
+                        .map(|n| n.to_string_lossy().to_string())
+                        .unwrap_or_default(),
+                });
+            }
             let _ = event_tx.send(WatcherEvent::SessionParsed {
                 session_id: session_id.to_string(),
                 message_count,
             });
             Some(message_count)
         }
-        Ok(false) => {
-            tracing::debug!("Skipped session {} - no matching project", session_id);
+        Ok(None) => {
+            tracing::debug!(
+                "Skipped session {} - no matching project or auto_sync off",
+                session_id
+            );
             None
         }
         Err(e) => {
@@ -558,18 +990,24 @@ async fn full_parse(
     }
 }
 
-/// Read and parse only new bytes appended to a session file, then store via SessionStore.
+/// Read and parse only new bytes appended to a session file, continuing git
+/// detection from `git_context`, then store via SessionStore.
+/// The file is re-hashed for `sessions.content_hash` only when `dedupe_by_hash` is set.
 /// Returns Some(total_message_count) on success, None on failure.
 #[allow(clippy::too_many_arguments)]
 async fn incremental_parse(
     store: &SessionStore,
+    parsers: &ParserRegistry,
     event_tx: &broadcast::Sender<WatcherEvent>,
     file_path: &str,
     session_id: &str,
     parser_type: &str,
+    estimate_tokens: bool,
     last_offset: i64,
     last_message_count: i64,
     last_max_sequence: i64,
+    git_context: GitContext,
+    dedupe_by_hash: bool,
 ) -> Option<usize> {
     use std::io::{Read, Seek, SeekFrom};
 
@@ -600,13 +1038,16 @@ async fn incremental_parse(
     };
 
     // Parse new lines
-    let parser = match get_parser(parser_type) {
+    let parser = match parsers.get(parser_type) {
         Some(p) => p,
         None => return None,
     };
 
-    let lines: Vec<String> = new_content.lines().map(|l| l.to_string()).collect();
-    let result = parser.parse(&lines);
+    let mut result =
+        parser.parse_stream_from(&mut new_content.lines().map(String::from), git_context);
+    if estimate_tokens {
+        crate::parser::common::estimate_missing_tokens(&mut result);
+    }
 
     if result.events.is_empty() {
         return None;
@@ -626,19 +1067,23 @@ async fn incremental_parse(
             session_id,
             &result.events,
             &result.stats,
+            &result.metadata,
+            result.error_lines.len(),
             last_offset,
             last_message_count,
             last_max_sequence,
+            dedupe_by_hash,
         )
         .await
     {
-        Ok(total) => {
+        Ok(Some(total)) => {
             let _ = event_tx.send(WatcherEvent::SessionParsed {
                 session_id: session_id.to_string(),
                 message_count: total,
             });
             Some(total)
         }
+        Ok(None) => None,
         Err(e) => {
             tracing::error!(
                 "Failed to store incremental parse for {}: {}",
@@ -649,3 +1094,843 @@ async fn incremental_parse(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::EphemeralConfig;
+    use crate::ephemeral::EphemeralIndex;
+    use crate::parser::{ClaudeCodeParser, ParseResult, SessionParser};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    const USER_LINE: &str = r#"{"type":"user","timestamp":"2024-01-01T00:00:00Z","message":{"content":[{"type":"text","text":"Hello world"}]}}"#;
+
+    fn test_state(
+        dir: &Path,
+        delete_on_remove: bool,
+    ) -> (
+        Arc<tokio::sync::RwLock<WatcherState>>,
+        Arc<EphemeralIndex>,
+        broadcast::Receiver<WatcherEvent>,
+    ) {
+        let idx = Arc::new(EphemeralIndex::new(EphemeralConfig::default()));
+        let (event_tx, event_rx) = broadcast::channel(32);
+        let (ai_event_tx, _) = broadcast::channel(32);
+
+        let mut watched = HashMap::new();
+        watched.insert(
+            dir.to_string_lossy().to_string(),
+            WatchedDirectory {
+                folder_path: dir.to_path_buf(),
+                parser_type: "claude_code".to_string(),
+                delete_on_remove,
+                namespace_session_ids: false,
+                parse_agents: false,
+                dedupe_by_hash: false,
+                filter: PathFilter::default(),
+            },
+        );
+
+        let state = WatcherState {
+            watched,
+            store: Arc::new(SessionStore::Ephemeral(idx.clone())),
+            parsers: Arc::new(ParserRegistry::default()),
+            estimate_tokens: false,
+            event_tx,
+            ai_trigger: None,
+            config_path: dir.join("config.toml"),
+            ai_event_tx,
+            ai_task_queue: AiTaskQueue::new(1),
+            maintenance: MaintenanceMode::new(),
+            deferred: std::sync::Mutex::new(HashSet::new()),
+        };
+        (Arc::new(tokio::sync::RwLock::new(state)), idx, event_rx)
+    }
+
+    fn db_state(
+        dir: &Path,
+        namespace_session_ids: bool,
+    ) -> (Arc<tokio::sync::RwLock<WatcherState>>, Arc<Database>) {
+        let db = Arc::new(Database::new(dir.join("test.db")).unwrap());
+        let (event_tx, _) = broadcast::channel(32);
+        let (ai_event_tx, _) = broadcast::channel(32);
+
+        let mut watched = HashMap::new();
+        watched.insert(
+            dir.to_string_lossy().to_string(),
+            WatchedDirectory {
+                folder_path: dir.to_path_buf(),
+                parser_type: "claude_code".to_string(),
+                delete_on_remove: false,
+                namespace_session_ids,
+                parse_agents: false,
+                dedupe_by_hash: false,
+                filter: PathFilter::default(),
+            },
+        );
+
+        let state = WatcherState {
+            watched,
+            store: Arc::new(SessionStore::Db(db.clone())),
+            parsers: Arc::new(ParserRegistry::default()),
+            estimate_tokens: false,
+            event_tx,
+            ai_trigger: None,
+            config_path: dir.join("config.toml"),
+            ai_event_tx,
+            ai_task_queue: AiTaskQueue::new(1),
+            maintenance: MaintenanceMode::new(),
+            deferred: std::sync::Mutex::new(HashSet::new()),
+        };
+        (Arc::new(tokio::sync::RwLock::new(state)), db)
+    }
+
+    async fn session_rows(db: &Database) -> Vec<(String, String)> {
+        db.with_conn(|conn| {
+            let mut stmt = conn
+                .prepare("SELECT id, file_path FROM sessions ORDER BY file_path")
+                .unwrap();
+            stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+                .unwrap()
+                .map(|r| r.unwrap())
+                .collect()
+        })
+        .await
+    }
+
+    fn removed_sessions(rx: &mut broadcast::Receiver<WatcherEvent>) -> Vec<String> {
+        let mut removed = Vec::new();
+        while let Ok(event) = rx.try_recv() {
+            if let WatcherEvent::SessionRemoved { session_id } = event {
+                removed.push(session_id);
+            }
+        }
+        removed
+    }
+
+    #[tokio::test]
+    async fn test_deleted_file_emits_session_removed() {
+        let tmp = tempfile::tempdir().unwrap();
+        let project = tmp.path().join("project");
+        std::fs::create_dir(&project).unwrap();
+        let (state, idx, mut rx) = test_state(tmp.path(), false);
+
+        let file = project.join("session-a.jsonl");
+        std::fs::write(&file, format!("{}\n", USER_LINE)).unwrap();
+        handle_file_event(&state, &file).await;
+        assert!(idx.get_session("session-a").is_some());
+        assert!(removed_sessions(&mut rx).is_empty());
+
+        std::fs::remove_file(&file).unwrap();
+        handle_file_event(&state, &file).await;
+        assert_eq!(removed_sessions(&mut rx), vec!["session-a".to_string()]);
+        // Without delete_on_remove the session is kept
+        assert!(idx.get_session("session-a").is_some());
+    }
+
+    #[tokio::test]
+    async fn test_delete_on_remove_deletes_session() {
+        let tmp = tempfile::tempdir().unwrap();
+        let project = tmp.path().join("project");
+        std::fs::create_dir(&project).unwrap();
+        let (state, idx, mut rx) = test_state(tmp.path(), true);
+
+        let file = project.join("session-b.jsonl");
+        std::fs::write(&file, format!("{}\n", USER_LINE)).unwrap();
+        handle_file_event(&state, &file).await;
+        std::fs::remove_file(&file).unwrap();
+        handle_file_event(&state, &file).await;
+
+        assert_eq!(removed_sessions(&mut rx), vec!["session-b".to_string()]);
+        assert!(idx.get_session("session-b").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_rename_is_remove_plus_add() {
+        let tmp = tempfile::tempdir().unwrap();
+        let project = tmp.path().join("project");
+        std::fs::create_dir(&project).unwrap();
+        let (state, idx, mut rx) = test_state(tmp.path(), true);
+
+        let old = project.join("old-name.jsonl");
+        let new = project.join("new-name.jsonl");
+        std::fs::write(&old, format!("{}\n", USER_LINE)).unwrap();
+        handle_file_event(&state, &old).await;
+
+        std::fs::rename(&old, &new).unwrap();
+        handle_file_event(&state, &old).await;
+        handle_file_event(&state, &new).await;
+
+        assert_eq!(removed_sessions(&mut rx), vec!["old-name".to_string()]);
+        assert!(idx.get_session("old-name").is_none());
+        assert!(idx.get_session("new-name").is_some());
+    }
+
+    #[tokio::test]
+    async fn test_untracked_missing_file_is_ignored() {
+        let tmp = tempfile::tempdir().unwrap();
+        let (state, _idx, mut rx) = test_state(tmp.path(), true);
+
+        handle_file_event(&state, &tmp.path().join("never-existed.jsonl")).await;
+        assert!(removed_sessions(&mut rx).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_colliding_stems_across_projects_are_kept_apart() {
+        let tmp = tempfile::tempdir().unwrap();
+        let (state, db) = db_state(tmp.path(), false);
+
+        let mut files = Vec::new();
+        for project in ["alpha", "beta"] {
+            let dir = tmp.path().join(project);
+            std::fs::create_dir(&dir).unwrap();
+            let file = dir.join("session.jsonl");
+            std::fs::write(&file, format!("{}\n", USER_LINE)).unwrap();
+            handle_file_event(&state, &file).await;
+            files.push(file.to_string_lossy().to_string());
+        }
+
+        let rows = session_rows(&db).await;
+        assert_eq!(rows.len(), 2);
+        // First file keeps the bare stem, the colliding one is namespaced
+        assert_eq!(rows[0], ("session".to_string(), files[0].clone()));
+        assert!(rows[1].0.ends_with(":session"));
+        assert_eq!(rows[1].1, files[1]);
+
+        // Re-processing a grown file resolves to the same ids (no new rows)
+        std::fs::write(&files[1], format!("{}\n{}\n", USER_LINE, USER_LINE)).unwrap();
+        handle_file_event(&state, Path::new(&files[1])).await;
+        assert_eq!(session_rows(&db).await, rows);
+    }
+
+    #[tokio::test]
+    async fn test_colliding_stems_across_parsers_are_kept_apart() {
+        use flate2::{write::GzEncoder, Compression};
+        use std::io::Write;
+
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path().join("alpha");
+        std::fs::create_dir(&dir).unwrap();
+        let (state, db) = db_state(tmp.path(), true);
+
+        let claude = dir.join("session.jsonl");
+        std::fs::write(&claude, format!("{}\n", USER_LINE)).unwrap();
+        handle_file_event(&state, &claude).await;
+
+        // Another tool's session with the same stem, in the same project folder
+        for dir in state.write().await.watched.values_mut() {
+            dir.parser_type = "openclaw".to_string();
+        }
+        let openclaw = dir.join("session.jsonl.gz");
+        let line = r#"{"type":"message","id":"msg1","timestamp":"2026-02-16T09:00:00Z","message":{"role":"user","content":[{"type":"text","text":"Hello world"}]}}"#;
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(format!("{}\n", line).as_bytes()).unwrap();
+        std::fs::write(&openclaw, encoder.finish().unwrap()).unwrap();
+        handle_file_event(&state, &openclaw).await;
+
+        let rows: Vec<(String, String, String, String)> = db
+            .with_conn(|conn| {
+                let mut stmt = conn
+                    .prepare(
+                        "SELECT id, project_id, file_path, ai_tool FROM sessions
+                         ORDER BY file_path",
+                    )
+                    .unwrap();
+                stmt.query_map([], |row| {
+                    Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+                })
+                .unwrap()
+                .map(|r| r.unwrap())
+                .collect()
+            })
+            .await;
+        assert_eq!(rows.len(), 2, "{:?}", rows);
+        let project_id = &rows[0].1;
+        assert_eq!(rows[0].0, format!("{}:session", project_id));
+        assert_eq!(rows[0].2, claude.to_string_lossy());
+        assert_eq!(rows[1].0, format!("{}:openclaw:session", project_id));
+        assert_eq!(rows[1].2, openclaw.to_string_lossy());
+        assert_ne!(rows[0].3, rows[1].3);
+    }
+
+    #[tokio::test]
+    async fn test_dedupe_by_hash_skips_copied_session() {
+        let tmp = tempfile::tempdir().unwrap();
+        let fixture = format!("{}\n{}\n", USER_LINE, USER_LINE);
+        let mut files = Vec::new();
+        for (project, stem) in [("alpha", "session-a"), ("beta", "session-b")] {
+            let dir = tmp.path().join(project);
+            std::fs::create_dir(&dir).unwrap();
+            let file = dir.join(format!("{}.jsonl", stem));
+            std::fs::write(&file, &fixture).unwrap();
+            files.push(file);
+        }
+
+        let (state, db) = db_state(tmp.path(), false);
+        for dir in state.write().await.watched.values_mut() {
+            dir.dedupe_by_hash = true;
+        }
+        for file in &files {
+            handle_file_event(&state, file).await;
+        }
+        let rows = session_rows(&db).await;
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].0, "session-a");
+
+        // Once the copy diverges it is indexed as its own session
+        std::fs::write(&files[1], format!("{}{}\n", fixture, USER_LINE)).unwrap();
+        handle_file_event(&state, &files[1]).await;
+        let rows = session_rows(&db).await;
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[1].0, "session-b");
+    }
+
+    #[tokio::test]
+    async fn test_append_rehashes_only_with_dedupe() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path().join("alpha");
+        std::fs::create_dir(&dir).unwrap();
+        let file = dir.join("session-a.jsonl");
+        let (state, db) = db_state(tmp.path(), false);
+        let stored_hash = || {
+            let db = db.clone();
+            async move {
+                db.with_conn(|conn| {
+                    conn.query_row("SELECT content_hash FROM sessions", [], |row| {
+                        row.get::<_, Option<String>>(0)
+                    })
+                    .unwrap()
+                })
+                .await
+            }
+        };
+
+        let mut content = format!("{}\n", USER_LINE);
+        std::fs::write(&file, &content).unwrap();
+        handle_file_event(&state, &file).await;
+        assert_eq!(
+            stored_hash().await,
+            Some(storage::content_hash(content.as_bytes()))
+        );
+
+        // Without dedupe an append doesn't re-read the file; the stale hash is dropped
+        content.push_str(&format!("{}\n", USER_LINE));
+        std::fs::write(&file, &content).unwrap();
+        handle_file_event(&state, &file).await;
+        assert_eq!(stored_hash().await, None);
+
+        for dir in state.write().await.watched.values_mut() {
+            dir.dedupe_by_hash = true;
+        }
+        content.push_str(&format!("{}\n", USER_LINE));
+        std::fs::write(&file, &content).unwrap();
+        handle_file_event(&state, &file).await;
+        assert_eq!(
+            stored_hash().await,
+            Some(storage::content_hash(content.as_bytes()))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_appends_continue_git_detection() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path().join("alpha");
+        std::fs::create_dir(&dir).unwrap();
+        let file = dir.join("session-a.jsonl");
+        let (state, db) = db_state(tmp.path(), false);
+        let branch = || {
+            db.with_conn(|conn| {
+                conn.query_row("SELECT git_branch FROM sessions", [], |row| {
+                    row.get::<_, Option<String>>(0)
+                })
+                .unwrap()
+            })
+        };
+        let mut content = String::new();
+        let mut append = |line: &str| {
+            content.push_str(line);
+            content.push('\n');
+            std::fs::write(&file, &content).unwrap();
+        };
+
+        append(
+            r#"{"type":"user","gitBranch":"main","timestamp":"2024-01-01T00:00:00Z","message":{"content":"Start a branch"}}"#,
+        );
+        append(
+            r#"{"type":"assistant","uuid":"a1","timestamp":"2024-01-01T00:00:01Z","message":{"content":[{"type":"tool_use","id":"t1","name":"Bash","input":{"command":"git checkout -b feature/x"}}]}}"#,
+        );
+        handle_file_event(&state, &file).await;
+        assert_eq!(branch().await.as_deref(), Some("feature/x"));
+
+        // The hint from before the checkout is stale in a later chunk too
+        append(
+            r#"{"type":"user","gitBranch":"main","timestamp":"2024-01-01T00:00:02Z","message":{"content":"Keep going"}}"#,
+        );
+        handle_file_event(&state, &file).await;
+        assert_eq!(branch().await.as_deref(), Some("feature/x"));
+
+        // An ambiguous switch clears the stored branch
+        append(
+            r#"{"type":"assistant","uuid":"a2","timestamp":"2024-01-01T00:00:03Z","message":{"content":[{"type":"tool_use","id":"t2","name":"Bash","input":{"command":"git checkout -"}}]}}"#,
+        );
+        handle_file_event(&state, &file).await;
+        assert_eq!(branch().await, None);
+    }
+
+    #[tokio::test]
+    async fn test_gzipped_session_file_is_parsed() {
+        use flate2::{write::GzEncoder, Compression};
+        use std::io::Write;
+
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path().join("alpha");
+        std::fs::create_dir(&dir).unwrap();
+        let (state, db) = db_state(tmp.path(), false);
+
+        let content = format!("{}\n{}\n", USER_LINE, USER_LINE);
+        let file = dir.join("session-a.jsonl.gz");
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(content.as_bytes()).unwrap();
+        std::fs::write(&file, encoder.finish().unwrap()).unwrap();
+        handle_file_event(&state, &file).await;
+
+        let rows = session_rows(&db).await;
+        assert_eq!(
+            rows,
+            vec![("session-a".to_string(), file.to_string_lossy().to_string())]
+        );
+        let messages: Vec<(String, i64, i64)> = db
+            .with_conn(|conn| {
+                let mut stmt = conn
+                    .prepare(
+                        "SELECT search_content, byte_offset, byte_length FROM session_messages
+                         WHERE session_id = 'session-a' ORDER BY sequence_num",
+                    )
+                    .unwrap();
+                stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+                    .unwrap()
+                    .map(|r| r.unwrap())
+                    .collect()
+            })
+            .await;
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].0, "Hello world");
+        // Offsets point into the decompressed content
+        let (_, offset, length) = messages[1];
+        let line = crate::parser::file::read_range(&file, offset as u64, length as usize).unwrap();
+        assert_eq!(line, USER_LINE.as_bytes());
+    }
+
+    #[tokio::test]
+    async fn test_new_session_event_carries_db_project_id() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path().join("alpha");
+        std::fs::create_dir(&dir).unwrap();
+        let (state, db) = db_state(tmp.path(), false);
+        let mut rx = state.read().await.event_tx.subscribe();
+
+        let file = dir.join("session-a.jsonl");
+        std::fs::write(&file, format!("{}\n", USER_LINE)).unwrap();
+        handle_file_event(&state, &file).await;
+        // Growing the file is not a new session
+        std::fs::write(&file, format!("{}\n{}\n", USER_LINE, USER_LINE)).unwrap();
+        handle_file_event(&state, &file).await;
+
+        let mut new_sessions = Vec::new();
+        while let Ok(event) = rx.try_recv() {
+            if let WatcherEvent::NewSession {
+                project_id,
+                file_name,
+                ..
+            } = event
+            {
+                new_sessions.push((project_id, file_name));
+            }
+        }
+        let folder = dir.to_string_lossy().to_string();
+        let project_id: String = db
+            .with_conn(move |conn| {
+                conn.query_row(
+                    "SELECT id FROM projects WHERE folder_path = ?",
+                    [&folder],
+                    |row| row.get(0),
+                )
+                .unwrap()
+            })
+            .await;
+        assert_eq!(
+            new_sessions,
+            vec![(project_id, "session-a.jsonl".to_string())]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_session_in_paused_project_is_not_stored() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path().join("alpha");
+        std::fs::create_dir(&dir).unwrap();
+        let folder = dir.to_string_lossy().to_string();
+        let (state, db) = db_state(tmp.path(), false);
+        db.with_conn(move |conn| {
+            conn.execute(
+                "INSERT INTO projects (id, name, folder_path, auto_sync, created_at, updated_at)
+                 VALUES ('p1', 'alpha', ?, 0, datetime('now'), datetime('now'))",
+                [&folder],
+            )
+            .unwrap()
+        })
+        .await;
+        let session_count = || {
+            db.with_conn(|conn| {
+                conn.query_row("SELECT COUNT(*) FROM sessions", [], |row| {
+                    row.get::<_, i64>(0)
+                })
+                .unwrap()
+            })
+        };
+
+        let file = dir.join("session-a.jsonl");
+        std::fs::write(&file, format!("{}\n", USER_LINE)).unwrap();
+        handle_file_event(&state, &file).await;
+        assert_eq!(session_count().await, 0);
+
+        // Turning auto_sync back on resumes storage
+        db.with_conn(|conn| {
+            conn.execute("UPDATE projects SET auto_sync = 1 WHERE id = 'p1'", [])
+                .unwrap()
+        })
+        .await;
+        std::fs::write(&file, format!("{}\n{}\n", USER_LINE, USER_LINE)).unwrap();
+        handle_file_event(&state, &file).await;
+        assert_eq!(session_count().await, 1);
+
+        // Pausing again stops appends; they're caught up on resume
+        let message_count = || {
+            db.with_conn(|conn| {
+                conn.query_row("SELECT COUNT(*) FROM session_messages", [], |row| {
+                    row.get::<_, i64>(0)
+                })
+                .unwrap()
+            })
+        };
+        let stored = message_count().await;
+        db.with_conn(|conn| {
+            conn.execute("UPDATE projects SET auto_sync = 0 WHERE id = 'p1'", [])
+                .unwrap()
+        })
+        .await;
+        std::fs::write(
+            &file,
+            format!("{}\n{}\n{}\n", USER_LINE, USER_LINE, USER_LINE),
+        )
+        .unwrap();
+        handle_file_event(&state, &file).await;
+        assert_eq!(message_count().await, stored);
+
+        db.with_conn(|conn| {
+            conn.execute("UPDATE projects SET auto_sync = 1 WHERE id = 'p1'", [])
+                .unwrap()
+        })
+        .await;
+        handle_file_event(&state, &file).await;
+        assert!(message_count().await > stored);
+    }
+
+    #[tokio::test]
+    async fn test_parse_errors_are_recorded() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path().join("alpha");
+        std::fs::create_dir(&dir).unwrap();
+        let file = dir.join("broken.jsonl");
+        std::fs::write(
+            &file,
+            format!("{}\n{{\"type\":\n{}\n", USER_LINE, USER_LINE),
+        )
+        .unwrap();
+
+        let (state, db) = db_state(tmp.path(), false);
+        let parse_errors = || {
+            db.with_conn(|conn| {
+                conn.query_row(
+                    "SELECT message_count, parse_errors, parse_error_lines FROM sessions WHERE id = 'broken'",
+                    [],
+                    |row| Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?, row.get::<_, Option<String>>(2)?)),
+                )
+                .unwrap()
+            })
+        };
+        handle_file_event(&state, &file).await;
+        assert_eq!(parse_errors().await, (2, 1, Some("[2]".to_string())));
+
+        // Appended bad lines add to the count
+        let mut content = std::fs::read_to_string(&file).unwrap();
+        content.push_str(&format!("not json\n{}\n", USER_LINE));
+        std::fs::write(&file, content).unwrap();
+        handle_file_event(&state, &file).await;
+        assert_eq!(parse_errors().await, (3, 2, Some("[2]".to_string())));
+    }
+
+    #[tokio::test]
+    async fn test_namespace_session_ids_migrates_existing_rows() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path().join("alpha");
+        std::fs::create_dir(&dir).unwrap();
+        let file = dir.join("abc.jsonl");
+        std::fs::write(&file, format!("{}\n", USER_LINE)).unwrap();
+
+        let (state, db) = db_state(tmp.path(), false);
+        handle_file_event(&state, &file).await;
+        assert_eq!(session_rows(&db).await[0].0, "abc");
+        // A child session outside the folder keeps pointing at its parent
+        db.with_conn(|conn| {
+            conn.execute(
+                "INSERT INTO sessions (id, project_id, file_path, ai_tool, parent_session_id, created_at, indexed_at)
+                 SELECT 'child', project_id, '/elsewhere/child.jsonl', ai_tool, 'abc', created_at, indexed_at
+                 FROM sessions WHERE id = 'abc'",
+                [],
+            )
+            .unwrap();
+        })
+        .await;
+
+        let migrated = storage::db_namespace_session_ids(&db, tmp.path())
+            .await
+            .unwrap();
+        assert_eq!(migrated, 1);
+        // Idempotent
+        assert_eq!(
+            storage::db_namespace_session_ids(&db, tmp.path())
+                .await
+                .unwrap(),
+            0
+        );
+
+        let (id, project_id, messages): (String, String, i64) = db
+            .with_conn(|conn| {
+                conn.query_row(
+                    "SELECT s.id, s.project_id,
+                            (SELECT COUNT(*) FROM session_messages m WHERE m.session_id = s.id)
+                     FROM sessions s WHERE s.id != 'child'",
+                    [],
+                    |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+                )
+                .unwrap()
+            })
+            .await;
+        assert_eq!(id, format!("{}:abc", project_id));
+        assert_eq!(messages, 1);
+
+        let parent: String = db
+            .with_conn(|conn| {
+                conn.query_row(
+                    "SELECT parent_session_id FROM sessions WHERE id = 'child'",
+                    [],
+                    |row| row.get(0),
+                )
+                .unwrap()
+            })
+            .await;
+        assert_eq!(parent, id);
+    }
+
+    #[test]
+    fn test_path_filter_include_exclude() {
+        let filter =
+            PathFilter::new(&["**/*.jsonl".to_string()], &["**/archive/**".to_string()]).unwrap();
+        assert!(filter.matches(Path::new("project/session.jsonl")));
+        assert!(!filter.matches(Path::new("project/archive/old.jsonl")));
+        assert!(!filter.matches(Path::new("project/notes.txt")));
+        assert!(PathFilter::default().matches(Path::new("anything")));
+        assert!(PathFilter::new(&["[".to_string()], &[]).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_exclude_pattern_keeps_archived_files_untracked() {
+        let tmp = tempfile::tempdir().unwrap();
+        let project = tmp.path().join("project");
+        let archive = project.join("archive");
+        std::fs::create_dir_all(&archive).unwrap();
+        let (state, idx, _rx) = test_state(tmp.path(), false);
+        state
+            .write()
+            .await
+            .watched
+            .values_mut()
+            .for_each(|d| d.filter = PathFilter::new(&[], &["**/archive/**".to_string()]).unwrap());
+
+        let live = project.join("live.jsonl");
+        let archived = archive.join("archived.jsonl");
+        std::fs::write(&live, format!("{}\n", USER_LINE)).unwrap();
+        std::fs::write(&archived, format!("{}\n", USER_LINE)).unwrap();
+        handle_file_event(&state, &live).await;
+        handle_file_event(&state, &archived).await;
+
+        assert!(idx.get_session("live").is_some());
+        assert!(idx.get_session("archived").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_agent_file_links_to_parent_session() {
+        let tmp = tempfile::tempdir().unwrap();
+        let project = tmp.path().join("project");
+        let subagents = project.join("parent-uuid").join("subagents");
+        std::fs::create_dir_all(&subagents).unwrap();
+
+        let parent = project.join("parent-uuid.jsonl");
+        std::fs::write(
+            &parent,
+            concat!(
+                r#"{"type":"user","sessionId":"parent-uuid","timestamp":"2024-01-01T00:00:00Z","message":{"content":"Find the bug"}}"#,
+                "\n",
+                r#"{"type":"user","sessionId":"parent-uuid","timestamp":"2024-01-01T00:00:05Z","toolUseResult":{"agentId":"a1b2c3","status":"completed"},"message":{"content":[{"type":"tool_result","tool_use_id":"toolu_1","content":"Found it"}]}}"#,
+                "\n",
+            ),
+        )
+        .unwrap();
+        let agent = subagents.join("agent-a1b2c3.jsonl");
+        std::fs::write(
+            &agent,
+            concat!(
+                r#"{"type":"user","isSidechain":true,"sessionId":"parent-uuid","agentId":"a1b2c3","timestamp":"2024-01-01T00:00:01Z","message":{"content":"Search for the bug"}}"#,
+                "\n",
+            ),
+        )
+        .unwrap();
+
+        let (state, db) = db_state(tmp.path(), false);
+
+        // Disabled by default: agent files are skipped
+        handle_file_event(&state, &parent).await;
+        handle_file_event(&state, &agent).await;
+        assert_eq!(session_rows(&db).await.len(), 1);
+
+        for dir in state.write().await.watched.values_mut() {
+            dir.parse_agents = true;
+        }
+        handle_file_event(&state, &agent).await;
+
+        let rows: Vec<(String, Option<String>, String)> = db
+            .with_conn(|conn| {
+                let mut stmt = conn
+                    .prepare("SELECT id, parent_session_id, project_id FROM sessions ORDER BY id")
+                    .unwrap();
+                stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+                    .unwrap()
+                    .map(|r| r.unwrap())
+                    .collect()
+            })
+            .await;
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].0, "agent-a1b2c3");
+        assert_eq!(rows[0].1.as_deref(), Some("parent-uuid"));
+        assert_eq!(rows[1].0, "parent-uuid");
+        assert!(rows[1].1.is_none());
+        // Agent transcript belongs to the parent's project, not the subagents folder
+        assert_eq!(rows[0].2, rows[1].2);
+    }
+
+    #[tokio::test]
+    async fn test_large_file_emits_parse_progress() {
+        let tmp = tempfile::tempdir().unwrap();
+        let project = tmp.path().join("project");
+        std::fs::create_dir(&project).unwrap();
+        let (state, _idx, mut rx) = test_state(tmp.path(), false);
+
+        // ~2MB of messages
+        let line = format!("{}\n", USER_LINE);
+        let count = (2 * PARSE_PROGRESS_MIN_BYTES as usize) / line.len() + 1;
+        let file = project.join("large.jsonl");
+        std::fs::write(&file, line.repeat(count)).unwrap();
+        handle_file_event(&state, &file).await;
+
+        let mut progress = Vec::new();
+        let mut parsed = false;
+        while let Ok(event) = rx.try_recv() {
+            match event {
+                WatcherEvent::ParseProgress {
+                    session_id,
+                    bytes_done,
+                    bytes_total,
+                } => {
+                    assert!(!parsed, "progress after SessionParsed");
+                    assert_eq!(session_id, "large");
+                    assert!(bytes_done < bytes_total);
+                    progress.push(bytes_done);
+                }
+                WatcherEvent::SessionParsed { session_id, .. } => {
+                    assert_eq!(session_id, "large");
+                    parsed = true;
+                }
+                _ => {}
+            }
+        }
+        assert!(parsed);
+        assert!(!progress.is_empty());
+        assert!(progress.len() < PARSE_PROGRESS_STEPS as usize);
+        assert!(progress.windows(2).all(|w| w[0] < w[1]));
+
+        // Small files parse without progress events
+        let file = project.join("small.jsonl");
+        std::fs::write(&file, &line).unwrap();
+        handle_file_event(&state, &file).await;
+        while let Ok(event) = rx.try_recv() {
+            assert!(!matches!(event, WatcherEvent::ParseProgress { .. }));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_maintenance_mode_defers_parsing() {
+        let tmp = tempfile::tempdir().unwrap();
+        let project = tmp.path().join("project");
+        std::fs::create_dir(&project).unwrap();
+        let (state, idx, _rx) = test_state(tmp.path(), false);
+        let maintenance = state.read().await.maintenance.clone();
+
+        maintenance.set(true);
+        let file = project.join("paused.jsonl");
+        std::fs::write(&file, format!("{}\n", USER_LINE)).unwrap();
+        handle_file_event(&state, &file).await;
+        assert!(idx.get_session("paused").is_none());
+
+        maintenance.set(false);
+        replay_deferred(&state).await;
+        assert!(idx.get_session("paused").is_some());
+    }
+
+    /// Delegates to the Claude Code parser, counting calls
+    struct CountingParser(AtomicUsize);
+
+    impl SessionParser for CountingParser {
+        fn parse(&self, lines: &[String]) -> ParseResult {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            ClaudeCodeParser::new().parse(lines)
+        }
+
+        fn name(&self) -> &'static str {
+            "counting"
+        }
+    }
+
+    #[tokio::test]
+    async fn test_watcher_uses_registered_parser() {
+        let tmp = tempfile::tempdir().unwrap();
+        let project = tmp.path().join("project");
+        std::fs::create_dir(&project).unwrap();
+        let (state, idx, _rx) = test_state(tmp.path(), false);
+
+        let counting = Arc::new(CountingParser(Default::default()));
+        {
+            let mut state = state.write().await;
+            state.parsers.register("counting", counting.clone());
+            for dir in state.watched.values_mut() {
+                dir.parser_type = "counting".to_string();
+            }
+        }
+
+        let file = project.join("session-c.jsonl");
+        std::fs::write(&file, format!("{}\n", USER_LINE)).unwrap();
+        handle_file_event(&state, &file).await;
+        assert_eq!(counting.0.load(Ordering::SeqCst), 1);
+        assert!(idx.get_session("session-c").is_some());
+    }
+}
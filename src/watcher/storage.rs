@@ -4,22 +4,41 @@
 //! They handle project lookup/creation, session upsert, and message insertion in SQLite.
 
 use super::store::SessionState;
+use crate::config::IdType;
 use crate::db::Database;
-use crate::parser::{ParseResult, ParseStats, ParsedEvent};
+use crate::error::CoreError;
+use crate::parser::git::GitContext;
+use crate::parser::{ParseResult, ParseStats, ParsedEvent, SessionMetadata};
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
+/// Unparseable line numbers kept per session in `parse_error_lines`
+const PARSE_ERROR_SAMPLE: usize = 10;
+
 /// Get incremental parse state for a session from the database.
 pub(super) async fn db_get_session_state(db: &Arc<Database>, session_id: &str) -> SessionState {
     let sid = session_id.to_string();
     db.with_conn(move |conn| {
-        let (file_size, message_count) = conn
+        let (file_size, message_count, git_state, git_branch, git_commit) = conn
             .query_row(
-                "SELECT COALESCE(file_size, 0), COALESCE(message_count, 0) FROM sessions WHERE id = ?",
+                "SELECT COALESCE(file_size, 0), COALESCE(message_count, 0), git_state, git_branch, git_commit
+                 FROM sessions WHERE id = ?",
                 [&sid],
-                |row| Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?)),
+                |row| {
+                    Ok((
+                        row.get::<_, i64>(0)?,
+                        row.get::<_, i64>(1)?,
+                        row.get::<_, Option<String>>(2)?,
+                        row.get::<_, Option<String>>(3)?,
+                        row.get::<_, Option<String>>(4)?,
+                    ))
+                },
             )
-            .unwrap_or((0, 0));
+            .unwrap_or((0, 0, None, None, None));
+        // Sessions stored before `git_state` existed continue from their branch and commit
+        let git_context = git_state
+            .and_then(|state| serde_json::from_str(&state).ok())
+            .unwrap_or_else(|| GitContext::resume(git_branch, git_commit));
 
         let max_sequence: i64 = conn
             .query_row(
@@ -33,20 +52,210 @@ pub(super) async fn db_get_session_state(db: &Arc<Database>, session_id: &str) -
             file_size,
             message_count,
             max_sequence,
+            git_context,
+        }
+    })
+    .await
+}
+
+/// Find the session stored for a file path.
+pub(super) async fn db_find_session_by_path(db: &Arc<Database>, file_path: &str) -> Option<String> {
+    let file_path = file_path.to_string();
+    db.with_conn(move |conn| {
+        conn.query_row(
+            "SELECT id FROM sessions WHERE file_path = ?",
+            [&file_path],
+            |row| row.get(0),
+        )
+        .ok()
+    })
+    .await
+}
+
+/// Find a session other than `session_id` whose file content hashed to `content_hash`.
+pub(super) async fn db_find_session_by_hash(
+    db: &Arc<Database>,
+    content_hash: &str,
+    session_id: &str,
+) -> Option<String> {
+    let content_hash = content_hash.to_string();
+    let session_id = session_id.to_string();
+    db.with_conn(move |conn| {
+        conn.query_row(
+            "SELECT id FROM sessions WHERE content_hash = ?1 AND id != ?2 LIMIT 1",
+            [&content_hash, &session_id],
+            |row| row.get(0),
+        )
+        .ok()
+    })
+    .await
+}
+
+/// Choose the session ID for a file that has no stored session yet.
+/// Falls back to the bare stem if the file's project can't be resolved.
+pub(super) async fn db_assign_session_id(
+    db: &Arc<Database>,
+    file_path: &str,
+    stem: &str,
+    parser_type: &str,
+    namespaced: bool,
+) -> String {
+    let path = PathBuf::from(file_path);
+    let stem = stem.to_string();
+    let parser_type = parser_type.to_string();
+    let id_type = db.id_type();
+    db.with_conn(move |conn| {
+        // The file and tool of the session stored under `id`, if any
+        let taken_by = |id: &str| {
+            conn.query_row(
+                "SELECT file_path, ai_tool FROM sessions WHERE id = ?",
+                [id],
+                |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)),
+            )
+            .ok()
+        };
+        let stem_taken_by = taken_by(&stem);
+        if !namespaced && stem_taken_by.is_none() {
+            return stem.clone();
+        }
+        let Some(project_id) = get_or_create_project_for_path_sync(conn, &path, id_type) else {
+            return stem.clone();
+        };
+        if let Some((other_path, other_tool)) = stem_taken_by {
+            tracing::warn!(
+                "Session id '{}' already used by {} ({}), namespacing {}",
+                stem,
+                other_path,
+                other_tool,
+                path.display()
+            );
+        }
+
+        let id = super::store::namespaced_session_id(&project_id, &stem);
+        let Some((other_path, other_tool)) = taken_by(&id) else {
+            return id;
+        };
+        let namespaced_id =
+            super::store::parser_namespaced_session_id(&project_id, &parser_type, &stem, |id| {
+                taken_by(id).is_some()
+            });
+        tracing::warn!(
+            "Session id '{}' already used by {} ({}), storing {} as '{}'",
+            id,
+            other_path,
+            other_tool,
+            path.display(),
+            namespaced_id
+        );
+        namespaced_id
+    })
+    .await
+}
+
+/// Rewrite bare session IDs under `folder_path` to `{project_id}:{id}`.
+///
+/// Child tables and `parent_session_id` links are updated in the same
+/// transaction with foreign key checks deferred to commit. Each old ID gets a
+/// tombstone and renamed rows a fresh `indexed_at`, so delta sync clients drop
+/// the old ID and fetch the new one. Returns the number of sessions migrated.
+pub(crate) async fn db_namespace_session_ids(
+    db: &Arc<Database>,
+    folder_path: &Path,
+) -> Result<usize, String> {
+    let folder = folder_path.to_string_lossy().to_string();
+    db.with_conn(move |conn| {
+        let tx = conn.unchecked_transaction()?;
+        tx.execute("PRAGMA defer_foreign_keys = ON", [])?;
+
+        let renames: Vec<(String, String)> = {
+            let mut stmt = tx.prepare(
+                "SELECT id, project_id FROM sessions
+                 WHERE substr(file_path, 1, length(?1)) = ?1
+                   AND substr(id, 1, length(project_id) + 1) != project_id || ':'",
+            )?;
+            let rows = stmt.query_map([&folder], |row| {
+                let id: String = row.get(0)?;
+                let project_id: String = row.get(1)?;
+                Ok((
+                    id.clone(),
+                    super::store::namespaced_session_id(&project_id, &id),
+                ))
+            })?;
+            rows.collect::<rusqlite::Result<_>>()?
+        };
+
+        // Delta sync sees a rename as the old id deleted and the new one changed
+        let now = chrono::Utc::now().to_rfc3339();
+        for (old_id, new_id) in &renames {
+            tx.execute(
+                "UPDATE sessions SET id = ?1, indexed_at = ?3 WHERE id = ?2",
+                [new_id, old_id, &now],
+            )?;
+            tx.execute(
+                "INSERT INTO tombstones (entity_type, entity_id, project_id, deleted_at)
+                 SELECT 'session', ?2, project_id, strftime('%Y-%m-%dT%H:%M:%fZ', 'now')
+                 FROM sessions WHERE id = ?1",
+                [new_id, old_id],
+            )?;
+            tx.execute(
+                "UPDATE sessions SET parent_session_id = ?1, indexed_at = ?3
+                 WHERE parent_session_id = ?2",
+                [new_id, old_id, &now],
+            )?;
+            for table in [
+                "session_messages",
+                "memories",
+                "skills",
+                "skill_sessions",
+                "session_markers",
+                "session_context",
+                "session_intents",
+                "session_tags",
+            ] {
+                tx.execute(
+                    &format!("UPDATE {} SET session_id = ?1 WHERE session_id = ?2", table),
+                    [new_id, old_id],
+                )?;
+            }
         }
+
+        tx.commit()?;
+        Ok::<usize, rusqlite::Error>(renames.len())
     })
     .await
+    .map_err(|e| format!("Failed to namespace session ids: {}", e))
+}
+
+/// Delete a session (and its messages via cascade) from the database.
+/// Returns Ok(true) if a row was deleted.
+pub(super) async fn db_delete_session(
+    db: &Arc<Database>,
+    session_id: &str,
+) -> Result<bool, String> {
+    let sid = session_id.to_string();
+    let (deleted, memory_ids) = db
+        .with_conn(move |conn| {
+            let memory_ids = crate::db::memory_ids_where(conn, "session_id = ?", &sid)?;
+            let deleted = conn.execute("DELETE FROM sessions WHERE id = ?", [&sid])?;
+            Ok::<_, rusqlite::Error>((deleted, memory_ids))
+        })
+        .await
+        .map_err(|e| format!("Failed to delete session: {}", e))?;
+    db.unindex_memories(&memory_ids);
+    Ok(deleted > 0)
 }
 
 /// Store a fully-parsed session in the database.
-/// Returns Ok(true) if stored, Ok(false) if skipped (no matching project), Err on failure.
+/// Returns the ID of the project it was stored under, or `None` if skipped
+/// (no matching project, or the project has `auto_sync` turned off).
 pub(super) async fn db_store_session(
     db: &Arc<Database>,
     file_path: &str,
     session_id: &str,
     parser_type: &str,
     result: &ParseResult,
-) -> Result<bool, String> {
+    content_hash: &str,
+) -> crate::error::Result<Option<String>> {
     let now = chrono::Utc::now().to_rfc3339();
     let path = PathBuf::from(file_path);
 
@@ -61,7 +270,7 @@ pub(super) async fn db_store_session(
         (size, modified)
     })
     .await
-    .map_err(|e| format!("spawn_blocking failed: {}", e))?;
+    .map_err(|e| CoreError::Watcher(format!("spawn_blocking failed: {}", e)))?;
 
     // Determine AI tool name
     let ai_tool = match parser_type {
@@ -87,24 +296,59 @@ pub(super) async fn db_store_session(
         .clone()
         .unwrap_or_else(|| now.clone());
     let events = result.events.clone();
+    let parent_session_id = result.metadata.parent_session_id.clone();
+    let git_branch = result.metadata.git_branch.clone();
+    let git_commit = result.metadata.git_commit.clone();
+    let git_state = git_state_json(&result.metadata);
+    let parse_errors = result.error_lines.len() as i64;
+    let parse_error_lines = (!result.error_lines.is_empty()).then(|| {
+        let sample = &result.error_lines[..result.error_lines.len().min(PARSE_ERROR_SAMPLE)];
+        serde_json::to_string(sample).unwrap_or_default()
+    });
+    let content_hash = content_hash.to_string();
+    let id_type = db.id_type();
 
     let project_id = db
         .with_conn(move |conn| {
             use rusqlite::params;
 
-            let project_id = match get_or_create_project_for_path_sync(conn, &path) {
+            let project_id = match get_or_create_project_for_path_sync(conn, &path, id_type) {
                 Some(id) => id,
                 None => {
                     return Ok(None);
                 }
             };
+            let auto_sync: bool = conn.query_row(
+                "SELECT auto_sync FROM projects WHERE id = ?",
+                [&project_id],
+                |row| row.get(0),
+            )?;
+            if !auto_sync {
+                tracing::debug!(
+                    "Skipping session {}: project {} has auto_sync off",
+                    session_id,
+                    project_id
+                );
+                return Ok(None);
+            }
+
+            // The parent may have been stored under a namespaced id
+            let parent_session_id = parent_session_id.as_ref().map(|parent| {
+                conn.query_row(
+                    "SELECT id FROM sessions WHERE project_id = ?1 AND (id = ?2 OR id = ?1 || ':' || ?2)",
+                    params![project_id, parent],
+                    |row| row.get::<_, String>(0),
+                )
+                .unwrap_or_else(|_| parent.clone())
+            });
 
             conn.execute(
                 "INSERT INTO sessions (
                     id, project_id, file_path, title, ai_tool, message_count,
                     duration_ms, has_code, has_errors, file_size, file_modified,
-                    created_at, indexed_at
-                ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)
+                    created_at, indexed_at, parent_session_id, parse_errors, parse_error_lines,
+                    content_hash, git_branch, git_commit, git_state
+                ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20)
                 ON CONFLICT(id) DO UPDATE SET
                     ai_tool = ?5,
                     message_count = ?6,
@@ -113,7 +357,14 @@ pub(super) async fn db_store_session(
                     has_errors = ?9,
                     file_size = ?10,
                     file_modified = ?11,
-                    indexed_at = ?13",
+                    indexed_at = ?13,
+                    parent_session_id = ?14,
+                    parse_errors = ?15,
+                    parse_error_lines = ?16,
+                    content_hash = ?17,
+                    git_branch = ?18,
+                    git_commit = ?19,
+                    git_state = ?20",
                 params![
                     session_id,
                     project_id,
@@ -128,15 +379,22 @@ pub(super) async fn db_store_session(
                     file_modified,
                     start_time,
                     now,
+                    parent_session_id,
+                    parse_errors,
+                    parse_error_lines,
+                    content_hash,
+                    git_branch,
+                    git_commit,
+                    git_state,
                 ],
             )
-            .map_err(|e| format!("Failed to insert session: {}", e))?;
+            .map_err(|e| CoreError::Watcher(format!("Failed to insert session: {}", e)))?;
 
             conn.execute(
                 "DELETE FROM session_messages WHERE session_id = ?",
                 params![session_id],
             )
-            .map_err(|e| format!("Failed to delete old messages: {}", e))?;
+            .map_err(|e| CoreError::Watcher(format!("Failed to delete old messages: {}", e)))?;
 
             for event in &events {
                 conn.execute(
@@ -144,8 +402,9 @@ pub(super) async fn db_store_session(
                         session_id, sequence_num, role, content_preview, search_content,
                         has_code, has_error, has_file_changes, tool_name, tool_type, tool_summary,
                         byte_offset, byte_length, input_tokens, output_tokens,
-                        cache_read_tokens, cache_creation_tokens, model, timestamp
-                    ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19)",
+                        cache_read_tokens, cache_creation_tokens, model, timestamp, parent_sequence_num,
+                        code_language, tokens_estimated
+                    ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21, ?22)",
                     params![
                         session_id,
                         event.sequence as i64,
@@ -166,18 +425,30 @@ pub(super) async fn db_store_session(
                         event.cache_creation_tokens,
                         event.model,
                         event.timestamp,
+                        event.parent_sequence.map(|s| s as i64),
+                        event.code_language,
+                        event.tokens_estimated,
                     ],
                 )
-                .map_err(|e| format!("Failed to insert message {}: {}", event.sequence, e))?;
+                .map_err(|e| {
+                    CoreError::Watcher(format!(
+                        "Failed to insert message {}: {}",
+                        event.sequence, e
+                    ))
+                })?;
             }
 
-            Ok::<Option<String>, String>(Some(project_id))
+            if let Err(e) = crate::ai::intent::refresh_heuristic_intents(conn, &session_id) {
+                tracing::warn!("Failed to extract intents for {}: {}", session_id, e);
+            }
+
+            Ok::<Option<String>, CoreError>(Some(project_id))
         })
         .await?;
 
     let project_id = match project_id {
         Some(id) => id,
-        None => return Ok(false),
+        None => return Ok(None),
     };
 
     tracing::info!(
@@ -187,11 +458,20 @@ pub(super) async fn db_store_session(
         project_id
     );
 
-    Ok(true)
+    Ok(Some(project_id))
 }
 
 /// Store incrementally-parsed messages in the database.
-/// Returns the new total message count on success.
+/// `parse_errors` is added to the session's count; line numbers of errors in
+/// appended lines aren't known here, so the sample is left as is. The parser
+/// continued git detection from the stored state, so the branch and commit it
+/// reports replace the stored ones; for parsers that don't track that state, a
+/// branch detected in the appended lines replaces the stored one.
+/// With `rehash` the whole file is hashed again for `content_hash`; otherwise
+/// the hash is cleared, so appends cost only the appended bytes.
+/// Returns the new total message count on success, or `None` if the session's
+/// project has `auto_sync` turned off. Nothing is stored then, so the appended
+/// lines are picked up once it is turned back on.
 #[allow(clippy::too_many_arguments)]
 pub(super) async fn db_store_incremental(
     db: &Arc<Database>,
@@ -199,25 +479,55 @@ pub(super) async fn db_store_incremental(
     session_id: &str,
     events: &[ParsedEvent],
     stats: &ParseStats,
+    metadata: &SessionMetadata,
+    parse_errors: usize,
     last_offset: i64,
     last_message_count: i64,
     last_max_sequence: i64,
-) -> Result<usize, String> {
+    rehash: bool,
+) -> crate::error::Result<Option<usize>> {
     let path = PathBuf::from(file_path);
     let total_message_count = last_message_count as usize + events.len();
 
-    // Get current file metadata
+    let session_for_check = session_id.to_string();
+    let auto_sync = db
+        .with_read_conn(move |conn| {
+            use rusqlite::OptionalExtension;
+            conn.query_row(
+                "SELECT p.auto_sync FROM sessions s JOIN projects p ON p.id = s.project_id
+                 WHERE s.id = ?1",
+                [&session_for_check],
+                |row| row.get::<_, bool>(0),
+            )
+            .optional()
+        })
+        .await
+        .map_err(|e| CoreError::Watcher(format!("Failed to read project auto_sync: {}", e)))?;
+    if auto_sync == Some(false) {
+        tracing::debug!(
+            "Skipping appended messages of {}: project has auto_sync off",
+            session_id
+        );
+        return Ok(None);
+    }
+
+    // Get current file metadata, and re-hash the whole file if asked so the
+    // stored content hash keeps matching it
     let path_for_stat = path;
-    let (file_size, file_modified) = tokio::task::spawn_blocking(move || {
+    let (file_size, file_modified, content_hash) = tokio::task::spawn_blocking(move || {
         let meta = std::fs::metadata(&path_for_stat).ok();
         let size = meta.as_ref().map(|m| m.len() as i64);
         let modified = meta
             .and_then(|m| m.modified().ok())
             .map(|t| chrono::DateTime::<chrono::Utc>::from(t).to_rfc3339());
-        (size, modified)
+        let hash = rehash
+            .then(|| std::fs::read(&path_for_stat).ok())
+            .flatten()
+            .map(|bytes| content_hash(&bytes));
+        (size, modified, hash)
     })
     .await
-    .unwrap_or((None, None));
+    .unwrap_or((None, None, None));
 
     let session_id_owned = session_id.to_string();
     let now = chrono::Utc::now().to_rfc3339();
@@ -226,6 +536,9 @@ pub(super) async fn db_store_incremental(
     let events = events.to_vec();
     let has_code = stats.has_code;
     let has_errors = stats.has_errors;
+    let git_branch = metadata.git_branch.clone();
+    let git_commit = metadata.git_commit.clone();
+    let git_state = git_state_json(metadata);
 
     db.with_conn(move |conn| {
         use rusqlite::params;
@@ -237,8 +550,18 @@ pub(super) async fn db_store_incremental(
                 file_modified = ?3,
                 has_code = has_code OR ?4,
                 has_errors = has_errors OR ?5,
-                indexed_at = ?6
-            WHERE id = ?7",
+                indexed_at = ?6,
+                parse_errors = parse_errors + ?7,
+                content_hash = ?9,
+                git_commit = CASE
+                    WHEN ?12 IS NOT NULL THEN ?11
+                    WHEN ?10 IS NULL THEN git_commit
+                    WHEN ?10 = git_branch THEN COALESCE(?11, git_commit)
+                    ELSE ?11
+                END,
+                git_branch = CASE WHEN ?12 IS NOT NULL THEN ?10 ELSE COALESCE(?10, git_branch) END,
+                git_state = COALESCE(?12, git_state)
+            WHERE id = ?8",
             params![
                 total_message_count as i64,
                 file_size,
@@ -246,10 +569,15 @@ pub(super) async fn db_store_incremental(
                 has_code,
                 has_errors,
                 now,
+                parse_errors as i64,
                 session_id_owned,
+                content_hash,
+                git_branch,
+                git_commit,
+                git_state,
             ],
         )
-        .map_err(|e| format!("Failed to update session: {}", e))?;
+        .map_err(|e| CoreError::Watcher(format!("Failed to update session: {}", e)))?;
 
         for event in &events {
             let adjusted_seq = seq_base + event.sequence as i64;
@@ -260,8 +588,9 @@ pub(super) async fn db_store_incremental(
                     session_id, sequence_num, role, content_preview, search_content,
                     has_code, has_error, has_file_changes, tool_name, tool_type, tool_summary,
                     byte_offset, byte_length, input_tokens, output_tokens,
-                    cache_read_tokens, cache_creation_tokens, model, timestamp
-                ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19)",
+                    cache_read_tokens, cache_creation_tokens, model, timestamp, parent_sequence_num,
+                    code_language, tokens_estimated
+                ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21, ?22)",
                 params![
                     session_id_owned,
                     adjusted_seq,
@@ -282,17 +611,45 @@ pub(super) async fn db_store_incremental(
                     event.cache_creation_tokens,
                     event.model,
                     event.timestamp,
+                    event.parent_sequence.map(|s| seq_base + s as i64),
+                    event.code_language,
+                    event.tokens_estimated,
                 ],
             )
-            .map_err(|e| format!("Failed to insert message: {}", e))?;
+            .map_err(|e| {
+                CoreError::Watcher(format!(
+                    "Failed to insert message {}: {}",
+                    adjusted_seq, e
+                ))
+            })?;
+        }
+
+        if let Err(e) = crate::ai::intent::refresh_heuristic_intents_after_append(
+            conn,
+            &session_id_owned,
+            seq_base,
+        ) {
+            tracing::warn!("Failed to extract intents for {}: {}", session_id_owned, e);
         }
 
-        Ok::<(), String>(())
+        Ok::<(), CoreError>(())
     })
-    .await
-    .map_err(|e| format!("DB error: {}", e))?;
+    .await?;
+
+    Ok(Some(total_message_count))
+}
+
+/// `metadata.git_context` as stored in `sessions.git_state`
+fn git_state_json(metadata: &SessionMetadata) -> Option<String> {
+    metadata
+        .git_context
+        .as_ref()
+        .and_then(|git| serde_json::to_string(git).ok())
+}
 
-    Ok(total_message_count)
+/// Hex blake3 hash of a session file's bytes, stored as `sessions.content_hash`
+pub(super) fn content_hash(bytes: &[u8]) -> String {
+    blake3::hash(bytes).to_hex().to_string()
 }
 
 /// Check if a Claude Code folder name encodes a temp/system directory path.
@@ -313,13 +670,26 @@ fn is_temp_directory(folder_name: &str) -> bool {
 
 /// Get or create a project for the given session file path.
 /// If no project exists for this folder, auto-creates one with a derived name.
+/// Folder that owns a session file.
+///
+/// Sub-agent transcripts live in `<project>/<session-id>/subagents/`, so they
+/// resolve to the same project folder as their parent session.
+fn project_folder_for_session(session_path: &Path) -> Option<&Path> {
+    let folder = session_path.parent()?;
+    if folder.file_name().and_then(|n| n.to_str()) == Some("subagents") {
+        return folder.parent()?.parent();
+    }
+    Some(folder)
+}
+
 fn get_or_create_project_for_path_sync(
     conn: &rusqlite::Connection,
     session_path: &Path,
+    id_type: IdType,
 ) -> Option<String> {
     use rusqlite::params;
 
-    let folder = session_path.parent()?;
+    let folder = project_folder_for_session(session_path)?;
     let folder_path = folder.to_string_lossy().to_string();
 
     let folder_name = folder.file_name().and_then(|n| n.to_str()).unwrap_or("");
@@ -337,13 +707,25 @@ fn get_or_create_project_for_path_sync(
     }
 
     let name = crate::derive_project_name(folder);
-    let id = uuid::Uuid::new_v4().to_string();
-    conn.execute(
-        "INSERT INTO projects (id, name, folder_path, auto_sync, created_at, updated_at)
-         VALUES (?, ?, ?, 1, datetime('now'), datetime('now'))",
-        params![id, name, folder_path],
-    )
-    .ok()?;
+    let id = crate::db::generate_id(id_type);
+    let inserted = conn
+        .execute(
+            "INSERT INTO projects (id, name, folder_path, auto_sync, created_at, updated_at)
+             VALUES (?, ?, ?, 1, datetime('now'), datetime('now'))
+             ON CONFLICT(folder_path) DO NOTHING",
+            params![id, name, folder_path],
+        )
+        .ok()?;
+    if inserted == 0 {
+        // Created concurrently (e.g. via the API) since the lookup above
+        return conn
+            .query_row(
+                "SELECT id FROM projects WHERE folder_path = ?",
+                params![folder_path],
+                |row| row.get::<_, String>(0),
+            )
+            .ok();
+    }
 
     tracing::info!("Auto-created project '{}' for {}", name, folder_path);
     Some(id)
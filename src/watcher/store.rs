@@ -6,7 +6,8 @@
 
 use crate::db::Database;
 use crate::ephemeral::EphemeralIndex;
-use crate::parser::{ParseResult, ParseStats, ParsedEvent};
+use crate::parser::git::GitContext;
+use crate::parser::{ParseResult, ParseStats, ParsedEvent, SessionMetadata};
 use std::sync::Arc;
 
 /// Incremental parse state for a session
@@ -17,6 +18,8 @@ pub struct SessionState {
     pub message_count: i64,
     /// Highest sequence number stored
     pub max_sequence: i64,
+    /// Git detection state at the end of the stored part of the file
+    pub git_context: GitContext,
 }
 
 impl Default for SessionState {
@@ -25,10 +28,13 @@ impl Default for SessionState {
             file_size: 0,
             message_count: 0,
             max_sequence: -1,
+            git_context: GitContext::default(),
         }
     }
 }
 
+impl crate::db::BusyOutcome for SessionState {}
+
 /// Storage backend used by the file watcher.
 ///
 /// Abstracts over DB vs in-memory storage so the watcher logic is identical
@@ -41,6 +47,61 @@ pub enum SessionStore {
 }
 
 impl SessionStore {
+    /// Find the ID of the session already stored for `file_path`, if any.
+    pub async fn find_session_by_path(&self, file_path: &str) -> Option<String> {
+        match self {
+            SessionStore::Db(db) => super::storage::db_find_session_by_path(db, file_path).await,
+            SessionStore::Ephemeral(idx) => idx.find_session_by_file_path(file_path),
+        }
+    }
+
+    /// Resolve the session ID to store `file_path` under.
+    ///
+    /// A session already stored for this file keeps its ID. New files use the
+    /// bare file stem, or `{project_id}:{stem}` when `namespaced` is set or the
+    /// stem is already taken by a different file (e.g. two projects each with a
+    /// `session.jsonl`). If that is taken too, typically by another tool's
+    /// session in the same folder, the ID also carries `parser_type` (see
+    /// [`parser_namespaced_session_id`]). An existing session is never
+    /// overwritten by a different file.
+    pub async fn resolve_session_id(
+        &self,
+        file_path: &str,
+        stem: &str,
+        parser_type: &str,
+        namespaced: bool,
+    ) -> String {
+        if let Some(id) = self.find_session_by_path(file_path).await {
+            return id;
+        }
+        match self {
+            SessionStore::Db(db) => {
+                super::storage::db_assign_session_id(db, file_path, stem, parser_type, namespaced)
+                    .await
+            }
+            SessionStore::Ephemeral(idx) => {
+                if !namespaced && idx.get_session(stem).is_none() {
+                    return stem.to_string();
+                }
+                let project_id = ephemeral_project_for_path(idx, file_path);
+                let id = namespaced_session_id(&project_id, stem);
+                if idx.get_session(&id).is_none() {
+                    return id;
+                }
+                let id = parser_namespaced_session_id(&project_id, parser_type, stem, |id| {
+                    idx.get_session(id).is_some()
+                });
+                tracing::warn!(
+                    "Session id '{}' already used by another file, storing {} as '{}'",
+                    namespaced_session_id(&project_id, stem),
+                    file_path,
+                    id
+                );
+                id
+            }
+        }
+    }
+
     /// Get the incremental parse state for a session.
     /// Returns defaults (0, 0, -1) if the session doesn't exist yet.
     pub async fn get_session_state(&self, session_id: &str) -> SessionState {
@@ -52,39 +113,53 @@ impl SessionStore {
                     file_size,
                     message_count,
                     max_sequence,
+                    ..SessionState::default()
                 }
             }
         }
     }
 
+    /// Find another session whose file has the same content hash.
+    /// Always `None` in ephemeral mode, which doesn't record hashes.
+    pub async fn find_session_by_hash(
+        &self,
+        content_hash: &str,
+        session_id: &str,
+    ) -> Option<String> {
+        match self {
+            SessionStore::Db(db) => {
+                super::storage::db_find_session_by_hash(db, content_hash, session_id).await
+            }
+            SessionStore::Ephemeral(_) => None,
+        }
+    }
+
     /// Store a fully-parsed session (full parse or re-parse after truncation).
-    /// Returns `Ok(true)` if stored, `Ok(false)` if skipped (e.g., temp directory).
+    /// `content_hash` is the hash of the parsed file (see `storage::content_hash`).
+    /// Returns the ID of the project the session was stored under, or `None`
+    /// if skipped (e.g., temp directory).
     pub async fn store_full_parse(
         &self,
         file_path: &str,
         session_id: &str,
         parser_type: &str,
         result: &ParseResult,
-    ) -> Result<bool, String> {
+        content_hash: &str,
+    ) -> crate::error::Result<Option<String>> {
         match self {
             SessionStore::Db(db) => {
-                super::storage::db_store_session(db, file_path, session_id, parser_type, result)
-                    .await
+                super::storage::db_store_session(
+                    db,
+                    file_path,
+                    session_id,
+                    parser_type,
+                    result,
+                    content_hash,
+                )
+                .await
             }
             SessionStore::Ephemeral(idx) => {
                 use crate::ephemeral::MessageMeta;
-                use std::path::PathBuf;
-
-                let path = PathBuf::from(file_path);
-                let folder = path
-                    .parent()
-                    .map(|p| p.to_string_lossy().to_string())
-                    .unwrap_or_default();
-                let folder_name = path
-                    .parent()
-                    .and_then(|p| p.file_name())
-                    .and_then(|n| n.to_str())
-                    .unwrap_or("unknown");
 
                 let ai_tool = match parser_type {
                     "claude_code" | "claude-code" => "Claude Code",
@@ -93,7 +168,7 @@ impl SessionStore {
                     _ => parser_type,
                 };
 
-                let project_id = idx.get_or_create_project(&folder, folder_name);
+                let project_id = ephemeral_project_for_path(idx, file_path);
                 let messages: Vec<MessageMeta> =
                     result.events.iter().map(MessageMeta::from).collect();
 
@@ -113,13 +188,16 @@ impl SessionStore {
                     result.stats.has_errors,
                 );
 
-                Ok(true)
+                Ok(Some(project_id))
             }
         }
     }
 
     /// Append incrementally-parsed messages to an existing session.
-    /// Returns the new total message count on success.
+    /// `rehash` re-hashes the whole file for `sessions.content_hash`; without
+    /// it the stored hash is cleared, as it no longer matches the file.
+    /// Returns the new total message count on success, or `None` if the
+    /// session's project has `auto_sync` turned off.
     #[allow(clippy::too_many_arguments)]
     pub async fn store_incremental_parse(
         &self,
@@ -127,10 +205,13 @@ impl SessionStore {
         session_id: &str,
         events: &[ParsedEvent],
         stats: &ParseStats,
+        metadata: &SessionMetadata,
+        parse_errors: usize,
         last_offset: i64,
         last_message_count: i64,
         last_max_sequence: i64,
-    ) -> Result<usize, String> {
+        rehash: bool,
+    ) -> crate::error::Result<Option<usize>> {
         match self {
             SessionStore::Db(db) => {
                 super::storage::db_store_incremental(
@@ -139,9 +220,12 @@ impl SessionStore {
                     session_id,
                     events,
                     stats,
+                    metadata,
+                    parse_errors,
                     last_offset,
                     last_message_count,
                     last_max_sequence,
+                    rehash,
                 )
                 .await
             }
@@ -159,6 +243,7 @@ impl SessionStore {
                     .map(|e| {
                         let mut m = MessageMeta::from(e);
                         m.sequence_num = seq_base + e.sequence as i64;
+                        m.parent_sequence_num = m.parent_sequence_num.map(|s| seq_base + s);
                         m.byte_offset = byte_base + e.byte_offset;
                         m
                     })
@@ -172,8 +257,53 @@ impl SessionStore {
                     stats.has_errors,
                 );
 
-                Ok(total)
+                Ok(Some(total))
             }
         }
     }
+
+    /// Remove a session whose file no longer exists.
+    /// Returns `Ok(true)` if a session was deleted.
+    pub async fn remove_session(&self, session_id: &str) -> Result<bool, String> {
+        match self {
+            SessionStore::Db(db) => super::storage::db_delete_session(db, session_id).await,
+            SessionStore::Ephemeral(idx) => Ok(idx.delete_session(session_id)),
+        }
+    }
+}
+
+/// Build a project-namespaced session ID
+pub(crate) fn namespaced_session_id(project_id: &str, stem: &str) -> String {
+    format!("{}:{}", project_id, stem)
+}
+
+/// Session ID for a file whose project-namespaced ID is already taken:
+/// `{project_id}:{parser_type}:{stem}`, with a `~2`, `~3`, ... suffix until
+/// `taken` returns false
+pub(crate) fn parser_namespaced_session_id(
+    project_id: &str,
+    parser_type: &str,
+    stem: &str,
+    taken: impl Fn(&str) -> bool,
+) -> String {
+    let base = namespaced_session_id(project_id, &format!("{}:{}", parser_type, stem));
+    std::iter::once(base.clone())
+        .chain((2..).map(|n| format!("{}~{}", base, n)))
+        .find(|id| !taken(id))
+        .expect("unbounded candidates")
+}
+
+/// Get or create the ephemeral project for a session file's parent folder
+fn ephemeral_project_for_path(idx: &EphemeralIndex, file_path: &str) -> String {
+    let path = std::path::Path::new(file_path);
+    let folder = path
+        .parent()
+        .map(|p| p.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let folder_name = path
+        .parent()
+        .and_then(|p| p.file_name())
+        .and_then(|n| n.to_str())
+        .unwrap_or("unknown");
+    idx.get_or_create_project(&folder, folder_name)
 }
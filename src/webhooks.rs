@@ -0,0 +1,286 @@
+//! Outbound webhooks
+//!
+//! POSTs watcher and AI events to the URLs in `[[webhooks]]`, e.g. to notify a
+//! chat channel when a session finishes parsing. Each webhook gets its own
+//! delivery task and queue, so a slow or failing endpoint doesn't hold up the
+//! others. Failed deliveries are retried with backoff; a webhook that keeps
+//! failing is disabled until restart.
+
+use crate::ai::types::AiEvent;
+use crate::config::WebhookConfig;
+use crate::events::{get_event_type, SseEvent};
+use crate::watcher::WatcherEvent;
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc};
+use tokio_stream::wrappers::BroadcastStream;
+use tokio_stream::StreamExt;
+
+/// Header carrying the body signature when a webhook has a `secret`
+pub const SIGNATURE_HEADER: &str = "X-Yocore-Signature";
+
+/// Waits between delivery attempts of one event
+const RETRY_DELAYS: &[Duration] = &[Duration::from_secs(1), Duration::from_secs(5)];
+
+/// Consecutive failed deliveries (after retries) before a webhook is disabled
+const MAX_CONSECUTIVE_FAILURES: usize = 5;
+
+/// Events queued per webhook; further events are dropped while it's full
+const QUEUE_SIZE: usize = 256;
+
+/// Timeout for a single delivery attempt
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Deliver matching watcher and AI events to `webhooks` until both channels close
+pub fn spawn(
+    webhooks: Vec<WebhookConfig>,
+    event_tx: &broadcast::Sender<WatcherEvent>,
+    ai_event_tx: &broadcast::Sender<AiEvent>,
+) -> tokio::task::JoinHandle<()> {
+    spawn_with(webhooks, event_tx, ai_event_tx, RETRY_DELAYS)
+}
+
+fn spawn_with(
+    webhooks: Vec<WebhookConfig>,
+    event_tx: &broadcast::Sender<WatcherEvent>,
+    ai_event_tx: &broadcast::Sender<AiEvent>,
+    retry_delays: &'static [Duration],
+) -> tokio::task::JoinHandle<()> {
+    let client = reqwest::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .build()
+        .unwrap_or_default();
+    let queues: Vec<(WebhookConfig, mpsc::Sender<String>)> = webhooks
+        .into_iter()
+        .map(|webhook| {
+            let (tx, rx) = mpsc::channel(QUEUE_SIZE);
+            tokio::spawn(deliver_all(
+                client.clone(),
+                webhook.clone(),
+                rx,
+                retry_delays,
+            ));
+            (webhook, tx)
+        })
+        .collect();
+
+    // Lagged errors are skipped
+    let watcher = BroadcastStream::new(event_tx.subscribe())
+        .filter_map(|result| result.ok().map(SseEvent::from));
+    let ai = BroadcastStream::new(ai_event_tx.subscribe())
+        .filter_map(|result| result.ok().map(SseEvent::from));
+    let mut events = futures::stream::select(watcher, ai);
+
+    tokio::spawn(async move {
+        while let Some(event) = events.next().await {
+            let name = get_event_type(&event);
+            let body = serde_json::json!({
+                "event": name,
+                "timestamp": chrono::Utc::now().to_rfc3339(),
+                "data": event,
+            })
+            .to_string();
+            for (webhook, queue) in &queues {
+                if !webhook.matches(name) {
+                    continue;
+                }
+                if let Err(mpsc::error::TrySendError::Full(_)) = queue.try_send(body.clone()) {
+                    tracing::warn!("Webhook {} is backed up, dropping {}", webhook.url, name);
+                }
+            }
+        }
+    })
+}
+
+/// Deliver queued bodies to one webhook in order, until it is disabled
+async fn deliver_all(
+    client: reqwest::Client,
+    webhook: WebhookConfig,
+    mut queue: mpsc::Receiver<String>,
+    retry_delays: &[Duration],
+) {
+    let mut failures = 0;
+    while let Some(body) = queue.recv().await {
+        match deliver(&client, &webhook, &body, retry_delays).await {
+            Ok(()) => failures = 0,
+            Err(e) => {
+                failures += 1;
+                tracing::warn!("Webhook {} delivery failed: {}", webhook.url, e);
+                if failures >= MAX_CONSECUTIVE_FAILURES {
+                    tracing::error!(
+                        "Webhook {} disabled after {} consecutive failures",
+                        webhook.url,
+                        failures
+                    );
+                    return;
+                }
+            }
+        }
+    }
+}
+
+/// POST `body`, retrying after each of `retry_delays` on failure
+async fn deliver(
+    client: &reqwest::Client,
+    webhook: &WebhookConfig,
+    body: &str,
+    retry_delays: &[Duration],
+) -> Result<(), String> {
+    let mut attempt = 0;
+    loop {
+        let mut request = client
+            .post(&webhook.url)
+            .header("Content-Type", "application/json")
+            .body(body.to_string());
+        if let Some(secret) = &webhook.secret {
+            request = request.header(SIGNATURE_HEADER, sign(secret, body));
+        }
+        let error = match request.send().await {
+            Ok(resp) if resp.status().is_success() => return Ok(()),
+            Ok(resp) => format!("HTTP {}", resp.status()),
+            Err(e) => e.to_string(),
+        };
+        let Some(delay) = retry_delays.get(attempt) else {
+            return Err(error);
+        };
+        tracing::debug!(
+            "Webhook {} attempt {} failed ({}), retrying in {:?}",
+            webhook.url,
+            attempt + 1,
+            error,
+            delay
+        );
+        tokio::time::sleep(*delay).await;
+        attempt += 1;
+    }
+}
+
+/// `sha256=<hex>`: the HMAC-SHA256 of the raw body keyed with `secret`, as
+/// GitHub-style receivers verify it
+pub fn sign(secret: &str, body: &str) -> String {
+    let key = ring::hmac::Key::new(ring::hmac::HMAC_SHA256, secret.as_bytes());
+    let tag = ring::hmac::sign(&key, body.as_bytes());
+    let hex: String = tag.as_ref().iter().map(|b| format!("{:02x}", b)).collect();
+    format!("sha256={}", hex)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{extract::State, http::HeaderMap, http::StatusCode, routing::post, Router};
+    use std::sync::{Arc, Mutex};
+
+    /// Signature header and raw body of each request
+    type Received = Arc<Mutex<Vec<(Option<String>, String)>>>;
+
+    async fn record(
+        State((received, status)): State<(Received, StatusCode)>,
+        headers: HeaderMap,
+        body: String,
+    ) -> StatusCode {
+        let signature = headers
+            .get(SIGNATURE_HEADER)
+            .map(|v| v.to_str().unwrap().to_string());
+        received.lock().unwrap().push((signature, body));
+        status
+    }
+
+    /// Mock endpoint recording signatures and bodies, answering with `status`
+    async fn mock_server(status: StatusCode) -> (String, Received) {
+        let received: Received = Arc::default();
+        let app = Router::new()
+            .route("/hook", post(record))
+            .with_state((received.clone(), status));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        (url, received)
+    }
+
+    async fn wait_for(received: &Received, count: usize) {
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while received.lock().unwrap().len() < count {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("webhook was not called");
+    }
+
+    #[tokio::test]
+    async fn test_session_parsed_webhook() {
+        let (url, received) = mock_server(StatusCode::OK).await;
+        let (event_tx, _) = broadcast::channel(16);
+        let (ai_event_tx, _) = broadcast::channel(16);
+        let webhook = WebhookConfig {
+            url,
+            events: vec!["session:parsed".to_string()],
+            secret: Some("s3cret".to_string()),
+        };
+        spawn_with(vec![webhook], &event_tx, &ai_event_tx, &[]);
+
+        // Filtered out
+        event_tx
+            .send(WatcherEvent::SessionRemoved {
+                session_id: "s0".to_string(),
+            })
+            .unwrap();
+        event_tx
+            .send(WatcherEvent::SessionParsed {
+                session_id: "s1".to_string(),
+                message_count: 12,
+            })
+            .unwrap();
+        wait_for(&received, 1).await;
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let received = received.lock().unwrap();
+        assert_eq!(received.len(), 1);
+        let (signature, raw_body) = &received[0];
+        let body: serde_json::Value = serde_json::from_str(raw_body).unwrap();
+        assert_eq!(body["event"], "session:parsed");
+        assert_eq!(body["data"]["session_id"], "s1");
+        assert_eq!(body["data"]["message_count"], 12);
+        // The signature covers the exact bytes sent
+        assert_eq!(
+            signature.as_deref(),
+            Some(sign("s3cret", raw_body).as_str())
+        );
+    }
+
+    #[test]
+    fn test_sign_is_hmac_sha256() {
+        // RFC 4231 test case 2
+        assert_eq!(
+            sign("Jefe", "what do ya want for nothing?"),
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_failing_webhook_is_retried_then_disabled() {
+        let (url, received) = mock_server(StatusCode::INTERNAL_SERVER_ERROR).await;
+        let (event_tx, _) = broadcast::channel(64);
+        let (ai_event_tx, _) = broadcast::channel(16);
+        let webhook = WebhookConfig {
+            url,
+            events: Vec::new(),
+            secret: None,
+        };
+        const NO_WAIT: &[Duration] = &[Duration::ZERO];
+        spawn_with(vec![webhook], &event_tx, &ai_event_tx, NO_WAIT);
+
+        for i in 0..MAX_CONSECUTIVE_FAILURES + 3 {
+            event_tx
+                .send(WatcherEvent::SessionRemoved {
+                    session_id: format!("s{}", i),
+                })
+                .unwrap();
+        }
+        // Two attempts per event until the webhook is disabled
+        wait_for(&received, 2 * MAX_CONSECUTIVE_FAILURES).await;
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let received = received.lock().unwrap();
+        assert_eq!(received.len(), 2 * MAX_CONSECUTIVE_FAILURES);
+        assert!(received.iter().all(|(signature, _)| signature.is_none()));
+    }
+}
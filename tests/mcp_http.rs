@@ -0,0 +1,105 @@
+//! `yocore --mcp-http`: MCP over HTTP against a real `Core`
+
+use serde_json::{json, Value};
+use std::net::SocketAddr;
+use std::time::Duration;
+use yocore::watcher::WatcherEvent;
+use yocore::{Config, Core};
+
+/// Serve MCP for a fresh `Core` in `data_dir`; returns the endpoint URL and
+/// the core's watcher event sender
+async fn start(
+    data_dir: &std::path::Path,
+) -> (String, tokio::sync::broadcast::Sender<WatcherEvent>) {
+    let config = Config {
+        data_dir: data_dir.to_path_buf(),
+        ..Config::default()
+    };
+    let core = Core::new(config, data_dir.join("config.toml")).unwrap();
+    let events = core.event_sender();
+
+    let addr: SocketAddr = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+    tokio::spawn(yocore::mcp::serve_http(addr, core));
+
+    let url = format!("http://{}/mcp", addr);
+    for _ in 0..100 {
+        if tokio::net::TcpStream::connect(addr).await.is_ok() {
+            return (url, events);
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    panic!("MCP server did not start on {}", addr);
+}
+
+async fn call(url: &str, message: Value) -> reqwest::Response {
+    reqwest::Client::new()
+        .post(url)
+        .json(&message)
+        .send()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_initialize_and_list_tools() {
+    let tmp = tempfile::tempdir().unwrap();
+    let (url, _events) = start(tmp.path()).await;
+
+    let resp = call(
+        &url,
+        json!({"jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {}}),
+    )
+    .await;
+    assert_eq!(resp.status(), 200);
+    let body: Value = resp.json().await.unwrap();
+    assert_eq!(body["id"], 1);
+    assert_eq!(body["result"]["serverInfo"]["name"], "yocore");
+    assert_eq!(
+        body["result"]["capabilities"]["resources"]["listChanged"],
+        true
+    );
+
+    let resp = call(
+        &url,
+        json!({"jsonrpc": "2.0", "method": "notifications/initialized"}),
+    )
+    .await;
+    assert_eq!(resp.status(), 202);
+
+    let resp = call(
+        &url,
+        json!({"jsonrpc": "2.0", "id": 2, "method": "tools/list"}),
+    )
+    .await;
+    let body: Value = resp.json().await.unwrap();
+    let tools = body["result"]["tools"].as_array().unwrap();
+    assert!(tools.iter().any(|t| t["name"] == "yolog_search_sessions"));
+}
+
+#[tokio::test]
+async fn test_session_changes_are_pushed_over_sse() {
+    let tmp = tempfile::tempdir().unwrap();
+    let (url, events) = start(tmp.path()).await;
+
+    let mut stream = reqwest::Client::new().get(&url).send().await.unwrap();
+    assert_eq!(stream.status(), 200);
+
+    events
+        .send(WatcherEvent::SessionRemoved {
+            session_id: "s1".to_string(),
+        })
+        .unwrap();
+
+    let mut received = String::new();
+    while !received.contains("notifications/resources/list_changed") {
+        let chunk = tokio::time::timeout(Duration::from_secs(5), stream.chunk())
+            .await
+            .expect("no notification within 5s")
+            .unwrap()
+            .expect("stream ended");
+        received.push_str(&String::from_utf8_lossy(&chunk));
+    }
+}
@@ -0,0 +1,94 @@
+//! Peak heap use of streaming parses.
+//!
+//! A separate test binary, since measuring needs its own global allocator.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use yocore::parser::{ClaudeCodeParser, SessionParser};
+
+/// Counts live heap bytes on threads that opt in, so a test can measure
+/// the peak memory of a parse without interference from other tests.
+struct TrackingAlloc;
+
+thread_local! {
+    static TRACKING: Cell<bool> = const { Cell::new(false) };
+    static LIVE: Cell<isize> = const { Cell::new(0) };
+    static PEAK: Cell<isize> = const { Cell::new(0) };
+}
+
+fn track(delta: isize) {
+    let _ = TRACKING.try_with(|tracking| {
+        if tracking.get() {
+            LIVE.with(|live| {
+                live.set(live.get() + delta);
+                PEAK.with(|peak| peak.set(peak.get().max(live.get())));
+            });
+        }
+    });
+}
+
+unsafe impl GlobalAlloc for TrackingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        track(layout.size() as isize);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        track(-(layout.size() as isize));
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOC: TrackingAlloc = TrackingAlloc;
+
+/// Peak live heap bytes allocated by `f` on the current thread
+fn peak_heap<T>(f: impl FnOnce() -> T) -> (T, usize) {
+    LIVE.with(|live| live.set(0));
+    PEAK.with(|peak| peak.set(0));
+    TRACKING.with(|tracking| tracking.set(true));
+    let result = f();
+    TRACKING.with(|tracking| tracking.set(false));
+    (result, PEAK.with(|peak| peak.get()).max(0) as usize)
+}
+
+#[test]
+fn test_streaming_parse_does_not_retain_raw_events() {
+    const PAIRS: usize = 200;
+    const PAYLOAD: usize = 50_000;
+
+    // Bulky toolUseResult payloads are not part of the parsed output, so only a
+    // parser that keeps whole decoded events around would hold on to them
+    let payload = "x".repeat(PAYLOAD);
+    let make_lines = move || {
+        (0..PAIRS).flat_map(move |i| {
+            [
+                format!(
+                    r#"{{"type":"assistant","uuid":"a{i}","timestamp":"2024-01-01T00:00:00Z","message":{{"content":[{{"type":"tool_use","id":"t{i}","name":"Bash","input":{{"command":"ls"}}}}]}}}}"#
+                ),
+                format!(
+                    r#"{{"type":"user","uuid":"u{i}","parentUuid":"a{i}","timestamp":"2024-01-01T00:00:01Z","toolUseResult":{{"stdout":"{payload}"}},"message":{{"content":[{{"type":"tool_result","tool_use_id":"t{i}","content":"ok"}}]}}}}"#
+                ),
+            ]
+        })
+    };
+    let input_bytes: usize = make_lines.clone()().map(|line| line.len()).sum();
+
+    let parser = ClaudeCodeParser::new();
+    // Build the parser's lazily initialized statics outside the measurement
+    parser.parse_stream(&mut make_lines.clone()().take(2));
+    let (result, peak) = peak_heap(|| parser.parse_stream(&mut make_lines()));
+
+    assert_eq!(result.events.len(), PAIRS * 2);
+    assert!(result
+        .events
+        .iter()
+        .filter(|e| e.tool_type.as_deref() == Some("result"))
+        .all(|e| e.tool_name.as_deref() == Some("Bash")));
+    assert!(
+        peak < input_bytes / 10,
+        "peak heap {} bytes for {} bytes of input",
+        peak,
+        input_bytes
+    );
+}